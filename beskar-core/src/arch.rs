@@ -2,5 +2,8 @@
 //! with the implementation of the architecture specific code.
 mod addrs;
 pub use addrs::*;
+mod range;
+pub use range::{AddrRange, Address};
 
+pub mod irq;
 pub mod paging;
@@ -87,6 +87,17 @@ impl Alignment {
         (x + mask) & !mask
     }
 
+    #[must_use]
+    #[inline]
+    /// Aligns `x` up, returning `None` on overflow.
+    pub const fn checked_align_up(self, x: u64) -> Option<u64> {
+        let mask = self.mask();
+        let Some(x) = x.checked_add(mask) else {
+            return None;
+        };
+        Some(x & !mask)
+    }
+
     #[must_use]
     #[inline]
     pub const fn is_aligned(self, x: u64) -> bool {
@@ -213,6 +224,38 @@ impl VirtAddr {
         Self::new_extend(align.align_up(self.0))
     }
 
+    #[must_use]
+    #[inline]
+    /// Aligns the address up, returning `None` if the result is not canonical.
+    pub const fn checked_aligned_up(self, align: Alignment) -> Option<Self> {
+        match align.checked_align_up(self.0) {
+            Some(addr) => Self::try_new(addr),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Adds `rhs` to the address, returning `None` on overflow
+    /// or if the result is not canonical.
+    pub const fn checked_add(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_add(rhs) {
+            Some(addr) => Self::try_new(addr),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Subtracts `rhs` from the address, returning `None` on overflow
+    /// or if the result is not canonical.
+    pub const fn checked_sub(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_sub(rhs) {
+            Some(addr) => Self::try_new(addr),
+            None => None,
+        }
+    }
+
     #[must_use]
     #[inline]
     pub const fn is_aligned(self, align: Alignment) -> bool {
@@ -311,6 +354,37 @@ impl PhysAddr {
         Self(align.align_up(self.0))
     }
 
+    #[must_use]
+    #[inline]
+    /// Aligns the address up, returning `None` if the result is not a valid physical address.
+    pub const fn checked_aligned_up(self, align: Alignment) -> Option<Self> {
+        match align.checked_align_up(self.0) {
+            Some(addr) => Self::try_new(addr),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Adds `rhs` to the address, returning `None` on overflow
+    /// or if the result is not a valid physical address.
+    pub const fn checked_add(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_add(rhs) {
+            Some(addr) => Self::try_new(addr),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Subtracts `rhs` from the address, returning `None` on overflow.
+    pub const fn checked_sub(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_sub(rhs) {
+            Some(addr) => Self::try_new(addr),
+            None => None,
+        }
+    }
+
     #[must_use]
     #[inline]
     pub const fn is_aligned(self, align: Alignment) -> bool {
@@ -429,6 +503,32 @@ mod tests {
         assert!(addr.is_aligned(Alignment::Align32));
     }
 
+    #[test]
+    fn test_p_checked() {
+        let addr = PhysAddr::new_truncate(0x1000);
+        assert_eq!(
+            addr.checked_add(0x1000),
+            Some(PhysAddr::new_truncate(0x2000))
+        );
+        assert_eq!(addr.checked_sub(0x1000), Some(PhysAddr::ZERO));
+        assert_eq!(addr.checked_sub(0x1001), None);
+
+        assert_eq!(PhysAddr::MAX.checked_add(0), Some(PhysAddr::MAX));
+        assert_eq!(PhysAddr::MAX.checked_add(1), None);
+        assert_eq!(PhysAddr::ZERO.checked_add(u64::MAX), None);
+
+        // Already aligned addresses are left as is
+        assert_eq!(addr.checked_aligned_up(Alignment::Align4K), Some(addr));
+        assert_eq!(addr.aligned_down(Alignment::Align4K), addr);
+        assert_eq!(PhysAddr::MAX.checked_aligned_up(Alignment::Align4K), None);
+        assert_eq!(
+            PhysAddr::MAX
+                .aligned_down(Alignment::Align4K)
+                .checked_aligned_up(Alignment::Align4K),
+            Some(PhysAddr::new_truncate(0x000F_FFFF_FFFF_F000))
+        );
+    }
+
     #[test]
     fn test_v() {
         let addr = VirtAddr::new_extend(0x18000031060);
@@ -480,6 +580,64 @@ mod tests {
         assert_eq!(addr.aligned_up(Alignment::Align4K).as_u64(), 0x18000032000);
     }
 
+    #[test]
+    fn test_v_checked() {
+        let addr = VirtAddr::new_extend(0x1000);
+        assert_eq!(addr.checked_add(0x1000), Some(VirtAddr::new_extend(0x2000)));
+        assert_eq!(addr.checked_sub(0x1000), Some(VirtAddr::ZERO));
+        assert_eq!(addr.checked_sub(0x1001), None);
+        assert_eq!(VirtAddr::MAX.checked_add(1), None);
+        assert_eq!(
+            VirtAddr::MAX.checked_sub(1),
+            VirtAddr::try_new(u64::MAX - 1)
+        );
+    }
+
+    #[test]
+    fn test_v_checked_canonical_boundary() {
+        let lower = VirtAddr::MAX_LOWER_HALF;
+        assert_eq!(lower.checked_add(0), Some(lower));
+        assert_eq!(lower.checked_add(1), None);
+        assert_eq!((lower - 1).checked_add(1), Some(lower));
+        // Crossing the hole entirely does not make the address canonical
+        assert_eq!(
+            lower.checked_add(VirtAddr::MIN_UPPER_HALF - lower),
+            Some(VirtAddr::MIN_UPPER_HALF)
+        );
+
+        let upper = VirtAddr::MIN_UPPER_HALF;
+        assert_eq!(upper.checked_sub(0), Some(upper));
+        assert_eq!(upper.checked_sub(1), None);
+        assert_eq!(
+            upper.checked_add(1),
+            VirtAddr::try_new(0xFFFF_8000_0000_0001)
+        );
+    }
+
+    #[test]
+    fn test_v_checked_align() {
+        // Already aligned addresses are left as is
+        let addr = VirtAddr::new_extend(0x4000);
+        assert_eq!(addr.checked_aligned_up(Alignment::Align4K), Some(addr));
+        assert_eq!(addr.aligned_down(Alignment::Align4K), addr);
+        let upper = VirtAddr::MIN_UPPER_HALF;
+        assert_eq!(upper.checked_aligned_up(Alignment::Align1G), Some(upper));
+        assert_eq!(upper.aligned_down(Alignment::Align512G), upper);
+
+        // Aligning up to the hole or past the end of the address space fails
+        let last_page = VirtAddr::MAX_LOWER_HALF.aligned_down(Alignment::Align4K);
+        assert_eq!(
+            last_page.checked_aligned_up(Alignment::Align4K),
+            Some(last_page)
+        );
+        assert_eq!((last_page + 1).checked_aligned_up(Alignment::Align4K), None);
+        assert_eq!(VirtAddr::MAX.checked_aligned_up(Alignment::Align4K), None);
+        assert_eq!(
+            VirtAddr::new_extend(0x1234).checked_aligned_up(Alignment::Align4K),
+            Some(VirtAddr::new_extend(0x2000))
+        );
+    }
+
     #[test]
     fn test_v_page_index() {
         let addr = VirtAddr::new_extend(0x18000031060);
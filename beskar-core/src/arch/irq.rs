@@ -0,0 +1,182 @@
+//! Bookkeeping of interrupts.
+//!
+//! A stray interrupt must not hang the machine: it is acknowledged and ignored.
+//! It is still logged, but only on its 1st, 2nd, 4th, 8th... occurrence on a vector,
+//! so that an interrupt storm does not flood the logs.
+//!
+//! Each core also counts the interrupt handlers it is running, so that code which
+//! must not run in an interrupt handler, such as allocations, can check it.
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// First vector that is not reserved for exceptions.
+pub const FIRST_IRQ_VECTOR: u8 = 32;
+/// Vector of the spurious interrupts of the local APIC.
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What to do about an unhandled interrupt.
+pub struct UnhandledAction {
+    /// Number of times the vector was raised without a handler, including this one.
+    pub count: u32,
+    /// Whether this occurrence should be logged.
+    pub log: bool,
+    /// Whether an end-of-interrupt should be sent.
+    ///
+    /// The local APIC does not wait for an EOI of a spurious interrupt,
+    /// and sending one would acknowledge another in-service interrupt instead.
+    pub eoi: bool,
+}
+
+#[derive(Debug)]
+/// Counters of the interrupts raised on vectors without a handler.
+pub struct UnhandledIrqs {
+    counts: [AtomicU32; 256],
+}
+
+impl Default for UnhandledIrqs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnhandledIrqs {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            counts: [const { AtomicU32::new(0) }; 256],
+        }
+    }
+
+    /// Records an interrupt raised on `vector`, and decides how to handle it.
+    pub fn record(&self, vector: u8) -> UnhandledAction {
+        let count = self.counts[usize::from(vector)]
+            .fetch_add(1, Ordering::Relaxed)
+            .saturating_add(1);
+        UnhandledAction {
+            count,
+            log: count.is_power_of_two(),
+            eoi: vector != SPURIOUS_VECTOR,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of times `vector` was raised without a handler.
+    pub fn count(&self, vector: u8) -> u32 {
+        self.counts[usize::from(vector)].load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+/// Number of nested interrupt handlers running on a core.
+pub struct InterruptNesting {
+    depth: AtomicU32,
+}
+
+impl InterruptNesting {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            depth: AtomicU32::new(0),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Marks the start of an interrupt handler, which ends when the returned scope is dropped.
+    ///
+    /// The scope must be dropped before switching to another thread,
+    /// which would otherwise run in interrupt context.
+    pub fn enter(&self) -> InterruptScope<'_> {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        InterruptScope { nesting: self }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether an interrupt handler is running.
+    pub fn is_active(&self) -> bool {
+        self.depth.load(Ordering::Relaxed) != 0
+    }
+
+    #[inline]
+    #[track_caller]
+    /// Asserts that no interrupt handler is running, in debug builds only.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from an interrupt handler, in debug builds.
+    pub fn debug_assert_outside(&self) {
+        debug_assert!(!self.is_active(), "Must not be called in interrupt context");
+    }
+}
+
+/// An interrupt handler running on a core.
+pub struct InterruptScope<'a> {
+    nesting: &'a InterruptNesting,
+}
+
+impl Drop for InterruptScope<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.nesting.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eoi() {
+        let irqs = UnhandledIrqs::new();
+
+        let action = irqs.record(0x40);
+        assert!(action.eoi);
+        assert!(action.log);
+        assert_eq!(action.count, 1);
+
+        let action = irqs.record(SPURIOUS_VECTOR);
+        assert!(!action.eoi);
+        assert!(action.log);
+
+        assert_eq!(irqs.count(0x40), 1);
+        assert_eq!(irqs.count(SPURIOUS_VECTOR), 1);
+        assert_eq!(irqs.count(0x41), 0);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let irqs = UnhandledIrqs::new();
+
+        let logged = (1..=100).filter(|_| irqs.record(0x50).log);
+        assert!(logged.eq([1, 2, 4, 8, 16, 32, 64]));
+        assert_eq!(irqs.count(0x50), 100);
+    }
+
+    #[test]
+    fn test_nesting() {
+        let nesting = InterruptNesting::new();
+        assert!(!nesting.is_active());
+        nesting.debug_assert_outside();
+
+        let outer = nesting.enter();
+        let inner = nesting.enter();
+        drop(inner);
+        assert!(nesting.is_active());
+        drop(outer);
+        assert!(!nesting.is_active());
+        nesting.debug_assert_outside();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic = "Must not be called in interrupt context"]
+    fn test_assert_in_interrupt() {
+        let nesting = InterruptNesting::new();
+        let _scope = nesting.enter();
+        nesting.debug_assert_outside();
+    }
+}
@@ -0,0 +1,238 @@
+//! Ranges of physical or virtual addresses.
+use super::{
+    Alignment, PhysAddr, VirtAddr,
+    paging::{Frame, FrameRangeInclusive, MemSize, Page, PageRangeInclusive},
+};
+use core::ops::{Add, Sub};
+
+/// An address, physical or virtual.
+pub trait Address: Copy + Ord + Add<u64, Output = Self> + Sub<Self, Output = u64> {}
+
+impl Address for PhysAddr {}
+impl Address for VirtAddr {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A range of addresses, from `start` included to `end` excluded.
+pub struct AddrRange<A: Address> {
+    start: A,
+    end: A,
+}
+
+impl<A: Address> AddrRange<A> {
+    #[must_use]
+    #[inline]
+    /// Creates a range from `start` to `end`, excluded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `end` is before `start`.
+    pub fn new(start: A, end: A) -> Self {
+        assert!(start <= end, "Range ends before it starts");
+        Self { start, end }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Creates a range of `len` bytes, starting at `start`.
+    pub fn from_len(start: A, len: u64) -> Self {
+        Self {
+            start,
+            end: start + len,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn start(&self) -> A {
+        self.start
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the first address after the range.
+    pub const fn end(&self) -> A {
+        self.end
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the size of the range, in bytes.
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, addr: A) -> bool {
+        self.start <= addr && addr < self.end
+    }
+
+    #[must_use]
+    #[inline]
+    /// Checks if the ranges share an address. Empty ranges overlap with nothing.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the addresses that are in both ranges, if any.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then_some(Self { start, end })
+    }
+}
+
+impl AddrRange<VirtAddr> {
+    #[must_use]
+    #[inline]
+    /// Creates a range of `len` bytes, starting at `start`.
+    ///
+    /// Returns `None` if the end of the range overflows or is not canonical.
+    pub const fn checked_from_len(start: VirtAddr, len: u64) -> Option<Self> {
+        match start.checked_add(len) {
+            Some(end) => Some(Self { start, end }),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether both ends of the range are aligned to `align`.
+    pub const fn is_aligned(&self, align: Alignment) -> bool {
+        self.start.is_aligned(align) && self.end.is_aligned(align)
+    }
+
+    #[must_use]
+    /// Returns the pages that contain an address of the range.
+    ///
+    /// The first and last pages are only partly in the range if it is not aligned.
+    pub fn pages<S: MemSize>(&self) -> PageRangeInclusive<S> {
+        let first = Page::containing_address(self.start);
+        if self.is_empty() {
+            return Page::range_inclusive(first + 1, first);
+        }
+        Page::range_inclusive(first, Page::containing_address(self.end - 1))
+    }
+}
+
+impl AddrRange<PhysAddr> {
+    #[must_use]
+    /// Returns the frames that contain an address of the range.
+    ///
+    /// The first and last frames are only partly in the range if it is not aligned.
+    pub fn frames<S: MemSize>(&self) -> FrameRangeInclusive<S> {
+        let first = Frame::containing_address(self.start);
+        if self.is_empty() {
+            return Frame::range_inclusive(first + 1, first);
+        }
+        Frame::range_inclusive(first, Frame::containing_address(self.end - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::paging::{M2MiB, M4KiB};
+
+    fn phys(start: u64, end: u64) -> AddrRange<PhysAddr> {
+        AddrRange::new(PhysAddr::new_truncate(start), PhysAddr::new_truncate(end))
+    }
+
+    #[test]
+    fn test_contains() {
+        let range = phys(0x1000, 0x3000);
+        assert_eq!(range.len(), 0x2000);
+        assert!(range.contains(PhysAddr::new_truncate(0x1000)));
+        assert!(range.contains(PhysAddr::new_truncate(0x2FFF)));
+        assert!(!range.contains(PhysAddr::new_truncate(0x3000)));
+        assert!(!range.contains(PhysAddr::new_truncate(0xFFF)));
+
+        let empty = phys(0x1000, 0x1000);
+        assert!(empty.is_empty());
+        assert!(!empty.contains(PhysAddr::new_truncate(0x1000)));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let range = phys(0x1000, 0x3000);
+        assert!(range.overlaps(&phys(0x2000, 0x4000)));
+        assert!(range.overlaps(&phys(0x0, 0x1001)));
+        assert!(range.overlaps(&phys(0x1800, 0x1900)));
+        assert!(range.overlaps(&range));
+        // Adjacent ranges do not overlap
+        assert!(!range.overlaps(&phys(0x3000, 0x4000)));
+        assert!(!range.overlaps(&phys(0x0, 0x1000)));
+        assert!(!range.overlaps(&phys(0x2000, 0x2000)));
+    }
+
+    #[test]
+    fn test_intersection() {
+        let range = phys(0x1000, 0x3000);
+        assert_eq!(
+            range.intersection(&phys(0x2000, 0x4000)),
+            Some(phys(0x2000, 0x3000))
+        );
+        assert_eq!(
+            range.intersection(&phys(0x1800, 0x1900)),
+            Some(phys(0x1800, 0x1900))
+        );
+        assert_eq!(range.intersection(&phys(0x3000, 0x4000)), None);
+    }
+
+    #[test]
+    fn test_pages() {
+        let aligned = AddrRange::from_len(VirtAddr::new_extend(0x4000), 0x2000);
+        let mut pages = aligned.pages::<M4KiB>().into_iter();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages.next().unwrap().start_address().as_u64(), 0x4000);
+        assert_eq!(pages.next().unwrap().start_address().as_u64(), 0x5000);
+
+        // An unaligned range spans the pages of both its ends
+        let unaligned = AddrRange::from_len(VirtAddr::new_extend(0x4FF0), 0x20);
+        let pages = unaligned.pages::<M4KiB>();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages.start().start_address().as_u64(), 0x4000);
+        assert_eq!(pages.end().start_address().as_u64(), 0x5000);
+        assert_eq!(unaligned.pages::<M2MiB>().len(), 1);
+
+        let empty = AddrRange::from_len(VirtAddr::new_extend(0x4800), 0);
+        assert!(empty.pages::<M4KiB>().is_empty());
+        assert_eq!(empty.pages::<M4KiB>().into_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_checked_from_len() {
+        let start = VirtAddr::new_extend(0x4000);
+        let range = AddrRange::checked_from_len(start, 0x2000).unwrap();
+        assert_eq!(range.end().as_u64(), 0x6000);
+        assert!(range.is_aligned(Alignment::Align4K));
+
+        // Unaligned start or length
+        let range = AddrRange::checked_from_len(VirtAddr::new_extend(0x4010), 0x2000).unwrap();
+        assert!(!range.is_aligned(Alignment::Align4K));
+        let range = AddrRange::checked_from_len(start, 0x1800).unwrap();
+        assert!(!range.is_aligned(Alignment::Align4K));
+
+        // The end would leave the lower half, or wrap around
+        assert!(AddrRange::checked_from_len(start, 0x7FFF_FFFF_F000).is_none());
+        assert!(AddrRange::checked_from_len(start, u64::MAX).is_none());
+    }
+
+    #[test]
+    fn test_frames() {
+        let range = phys(0x1FFF, 0x3001);
+        let frames = range.frames::<M4KiB>();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames.start().start_address().as_u64(), 0x1000);
+        assert_eq!(frames.end().start_address().as_u64(), 0x3000);
+        assert!(phys(0x2000, 0x2000).frames::<M4KiB>().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! Sets of bit flags, see `bitflags!`.
+
+#[macro_export]
+/// Defines a set of bit flags, stored in an integer.
+///
+/// The type is a tuple struct around the integer, with a private field, and an associated
+/// constant for each flag. It gets:
+/// - `EMPTY`, `from_bits` and `bits`,
+/// - `is_empty`, `contains`, `union`, `intersection` and `without`,
+/// - `BitOr`, `BitAnd`, `BitOrAssign` and `BitAndAssign`.
+///
+/// Attributes, such as derives, are forwarded to the type and to the constants.
+///
+/// ```rust
+/// # use beskar_core::bitflags;
+/// #
+/// bitflags! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     pub struct Permissions: u8 {
+///         const READ = 1;
+///         const WRITE = 1 << 1;
+///         /// Flags can be combined.
+///         const READ_WRITE = Self::READ.bits() | Self::WRITE.bits();
+///     }
+/// }
+///
+/// let permissions = Permissions::READ | Permissions::WRITE;
+/// assert_eq!(permissions, Permissions::READ_WRITE);
+/// assert!(permissions.contains(Permissions::WRITE));
+/// assert_eq!(permissions.without(Permissions::WRITE), Permissions::READ);
+/// ```
+macro_rules! bitflags {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident: $ty:ty {
+            $(
+                $(#[$flag_attr:meta])*
+                $flag_vis:vis const $flag:ident = $value:expr;
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name($ty);
+
+        impl $name {
+            $(
+                $(#[$flag_attr])*
+                $flag_vis const $flag: Self = Self($value);
+            )*
+
+            /// No flag set.
+            pub const EMPTY: Self = Self(0);
+
+            #[must_use]
+            #[inline]
+            /// Creates a set from raw bits, keeping bits that do not match any flag.
+            pub const fn from_bits(bits: $ty) -> Self {
+                Self(bits)
+            }
+
+            #[must_use]
+            #[inline]
+            pub const fn bits(self) -> $ty {
+                self.0
+            }
+
+            #[must_use]
+            #[inline]
+            pub const fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            #[must_use]
+            #[inline]
+            /// Returns true if every flag of `other` is set in `self`.
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            #[must_use]
+            #[inline]
+            pub const fn union(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+
+            #[must_use]
+            #[inline]
+            pub const fn intersection(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+
+            #[must_use]
+            #[inline]
+            /// Returns the flags of `self` that are not set in `other`.
+            pub const fn without(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+        }
+
+        impl ::core::ops::BitOr for $name {
+            type Output = Self;
+
+            #[inline]
+            fn bitor(self, rhs: Self) -> Self::Output {
+                self.union(rhs)
+            }
+        }
+        impl ::core::ops::BitAnd for $name {
+            type Output = Self;
+
+            #[inline]
+            fn bitand(self, rhs: Self) -> Self::Output {
+                self.intersection(rhs)
+            }
+        }
+        impl ::core::ops::BitOrAssign for $name {
+            #[inline]
+            fn bitor_assign(&mut self, rhs: Self) {
+                self.0 |= rhs.0;
+            }
+        }
+        impl ::core::ops::BitAndAssign for $name {
+            #[inline]
+            fn bitand_assign(&mut self, rhs: Self) {
+                self.0 &= rhs.0;
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    bitflags! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        struct TestFlags: u16 {
+            const A = 1;
+            const B = 1 << 1;
+            const C = 1 << 15;
+            const AB = Self::A.bits() | Self::B.bits();
+        }
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(TestFlags::A.bits(), 1);
+        assert_eq!(TestFlags::C.bits(), 0x8000);
+        assert_eq!(TestFlags::AB, TestFlags::A | TestFlags::B);
+        assert!(TestFlags::EMPTY.is_empty());
+        assert!(!TestFlags::A.is_empty());
+        assert_eq!(TestFlags::from_bits(0x8003).bits(), 0x8003);
+    }
+
+    #[test]
+    fn test_operations_match_bits() {
+        let values = [0, 1, 2, 3, 0x8000, 0x8001, 0xFFFF, 0x1234];
+        for a in values {
+            for b in values {
+                let (x, y) = (TestFlags::from_bits(a), TestFlags::from_bits(b));
+                assert_eq!(x.contains(y), a & b == b);
+                assert_eq!(x.union(y).bits(), a | b);
+                assert_eq!((x | y).bits(), a | b);
+                assert_eq!(x.intersection(y).bits(), a & b);
+                assert_eq!((x & y).bits(), a & b);
+                assert_eq!(x.without(y).bits(), a & !b);
+
+                let mut z = x;
+                z |= y;
+                assert_eq!(z.bits(), a | b);
+                let mut z = x;
+                z &= y;
+                assert_eq!(z.bits(), a & b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(TestFlags::AB.contains(TestFlags::A));
+        assert!(TestFlags::AB.contains(TestFlags::AB));
+        assert!(TestFlags::A.contains(TestFlags::EMPTY));
+        assert!(!TestFlags::A.contains(TestFlags::AB));
+        assert!(!TestFlags::EMPTY.contains(TestFlags::C));
+    }
+}
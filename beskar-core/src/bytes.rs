@@ -0,0 +1,133 @@
+//! Reading and writing integers in byte slices, at a given offset.
+//!
+//! The `read_*` and `write_*` functions panic if the integer does not fit in the slice,
+//! like indexing does. Their `try_*` variants return `None` instead.
+
+#[must_use]
+#[inline]
+fn array<const N: usize>(bytes: &[u8], offset: usize) -> Option<[u8; N]> {
+    let end = offset.checked_add(N)?;
+    bytes.get(offset..end)?.try_into().ok()
+}
+
+#[must_use]
+#[inline]
+fn array_mut<const N: usize>(bytes: &mut [u8], offset: usize) -> Option<&mut [u8; N]> {
+    let end = offset.checked_add(N)?;
+    bytes.get_mut(offset..end)?.try_into().ok()
+}
+
+macro_rules! impl_endian {
+    ($ty:ty, $from:ident, $to:ident, $read:ident, $try_read:ident, $write:ident, $try_write:ident) => {
+        #[must_use]
+        #[inline]
+        #[doc = concat!("Reads a `", stringify!($ty), "` at `offset`, or returns `None` if it is out of bounds.")]
+        pub fn $try_read(bytes: &[u8], offset: usize) -> Option<$ty> {
+            array(bytes, offset).map(<$ty>::$from)
+        }
+
+        #[must_use]
+        #[inline]
+        #[doc = concat!("Reads a `", stringify!($ty), "` at `offset`.")]
+        ///
+        /// # Panics
+        ///
+        /// Panics if the integer does not fit in `bytes`.
+        pub fn $read(bytes: &[u8], offset: usize) -> $ty {
+            $try_read(bytes, offset).expect("Integer out of bounds")
+        }
+
+        #[inline]
+        #[doc = concat!("Writes a `", stringify!($ty), "` at `offset`, or returns `None` if it is out of bounds.")]
+        ///
+        /// Nothing is written if the integer does not fit.
+        pub fn $try_write(bytes: &mut [u8], offset: usize, value: $ty) -> Option<()> {
+            *array_mut(bytes, offset)? = value.$to();
+            Some(())
+        }
+
+        #[inline]
+        #[doc = concat!("Writes a `", stringify!($ty), "` at `offset`.")]
+        ///
+        /// # Panics
+        ///
+        /// Panics if the integer does not fit in `bytes`.
+        pub fn $write(bytes: &mut [u8], offset: usize, value: $ty) {
+            $try_write(bytes, offset, value).expect("Integer out of bounds");
+        }
+    };
+}
+
+impl_endian! { u16, from_le_bytes, to_le_bytes, read_u16_le, try_read_u16_le, write_u16_le, try_write_u16_le }
+impl_endian! { u32, from_le_bytes, to_le_bytes, read_u32_le, try_read_u32_le, write_u32_le, try_write_u32_le }
+impl_endian! { u64, from_le_bytes, to_le_bytes, read_u64_le, try_read_u64_le, write_u64_le, try_write_u64_le }
+impl_endian! { u16, from_be_bytes, to_be_bytes, read_u16_be, try_read_u16_be, write_u16_be, try_write_u16_be }
+impl_endian! { u32, from_be_bytes, to_be_bytes, read_u32_be, try_read_u32_be, write_u32_be, try_write_u32_be }
+impl_endian! { u64, from_be_bytes, to_be_bytes, read_u64_be, try_read_u64_be, write_u64_be, try_write_u64_be }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYTES: [u8; 10] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0x55, 0xAA];
+
+    #[test]
+    fn test_read_le() {
+        assert_eq!(read_u16_le(&BYTES, 0), 0x2301);
+        assert_eq!(read_u16_le(&BYTES, 8), 0xAA55);
+        assert_eq!(read_u32_le(&BYTES, 1), 0x8967_4523);
+        assert_eq!(read_u64_le(&BYTES, 0), 0xEFCD_AB89_6745_2301);
+        assert_eq!(read_u64_le(&BYTES, 2), 0xAA55_EFCD_AB89_6745);
+    }
+
+    #[test]
+    fn test_read_be() {
+        assert_eq!(read_u16_be(&BYTES, 0), 0x0123);
+        assert_eq!(read_u32_be(&BYTES, 1), 0x2345_6789);
+        assert_eq!(read_u64_be(&BYTES, 0), 0x0123_4567_89AB_CDEF);
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        assert_eq!(try_read_u16_le(&BYTES, 8), Some(0xAA55));
+        assert_eq!(try_read_u16_le(&BYTES, 9), None);
+        assert_eq!(try_read_u32_le(&BYTES, 7), None);
+        assert_eq!(try_read_u64_le(&BYTES, 3), None);
+        assert_eq!(try_read_u64_be(&BYTES, 10), None);
+        assert_eq!(try_read_u16_le(&[], 0), None);
+        // The end of the integer overflows
+        assert_eq!(try_read_u32_le(&BYTES, usize::MAX - 1), None);
+
+        let mut bytes = BYTES;
+        assert_eq!(try_write_u32_le(&mut bytes, 7, 0), None);
+        assert_eq!(try_write_u64_be(&mut bytes, usize::MAX, 0), None);
+        // Nothing was written
+        assert_eq!(bytes, BYTES);
+    }
+
+    #[test]
+    #[should_panic = "Integer out of bounds"]
+    fn test_read_out_of_bounds() {
+        let _ = read_u32_le(&BYTES, 8);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut bytes = [0; 10];
+        write_u16_le(&mut bytes, 0, 0x2301);
+        write_u32_le(&mut bytes, 2, 0x6745_2301);
+        write_u32_be(&mut bytes, 6, 0x0123_4567);
+        assert_eq!(
+            bytes,
+            [0x01, 0x23, 0x01, 0x23, 0x45, 0x67, 0x01, 0x23, 0x45, 0x67]
+        );
+
+        let mut bytes = [0xFF; 10];
+        assert_eq!(
+            try_write_u64_le(&mut bytes, 1, 0x0807_0605_0403_0201),
+            Some(())
+        );
+        assert_eq!(bytes, [0xFF, 1, 2, 3, 4, 5, 6, 7, 8, 0xFF]);
+        assert_eq!(read_u64_le(&bytes, 1), 0x0807_0605_0403_0201);
+    }
+}
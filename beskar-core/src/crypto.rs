@@ -0,0 +1,59 @@
+//! Cryptographic primitives, shared by the kernel and userspace.
+//!
+//! They do not allocate, and run in constant time where it matters.
+pub mod chacha20;
+pub mod sha256;
+
+#[must_use]
+/// Compares two byte slices in constant time.
+///
+/// Digests, MACs and signatures must be compared with this function rather than `==`,
+/// which returns at the first difference and so leaks its position through timing.
+///
+/// The time taken depends on the length of the slices, but not on their contents:
+/// every byte is compared, and differences are accumulated without branching.
+/// Slices of different lengths are unequal, which only reveals that their lengths differ.
+///
+/// # Limits
+///
+/// Rust gives no timing guarantee, so this is best effort: the accumulator goes through
+/// `core::hint::black_box` at each step, which keeps the optimizer from turning the loop
+/// into an early exit in practice. Whether it did can be checked on the generated code,
+/// which must not branch on the contents of the slices, e.g. with `cargo asm`.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a
+        .iter()
+        .zip(b)
+        .fold(0_u8, |diff, (a, b)| core::hint::black_box(diff | (a ^ b)));
+    diff == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"digest", b"digest"));
+        assert!(ct_eq(&[0xA5; 64], &[0xA5; 64]));
+
+        assert!(!ct_eq(b"digest", b"digesT"));
+        assert!(!ct_eq(b"digest", b"Digest"));
+        // Only one bit differs
+        let mut other = [0xA5; 64];
+        other[31] ^= 0x10;
+        assert!(!ct_eq(&[0xA5; 64], &other));
+    }
+
+    #[test]
+    fn test_ct_eq_length_mismatch() {
+        assert!(!ct_eq(b"digest", b"diges"));
+        assert!(!ct_eq(b"", b"\0"));
+        // A prefix is not equal
+        assert!(!ct_eq(&[0; 32], &[0; 33]));
+    }
+}
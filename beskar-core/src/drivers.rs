@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+pub mod ahci;
+pub mod e1000;
 pub mod keyboard;
 
 /// Errors that can occur during block device operations.
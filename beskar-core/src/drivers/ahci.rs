@@ -0,0 +1,129 @@
+//! Advanced Host Controller Interface (AHCI) structures.
+//!
+//! These are the in-memory structures shared by the host and the controller.
+
+use crate::storage::BlockDeviceError;
+
+pub mod command;
+pub mod fis;
+
+/// Status bit of the task file: an error occurred
+const STATUS_ERR: u8 = 1 << 0;
+/// Status bit of the task file: device fault
+const STATUS_DF: u8 = 1 << 5;
+/// Error bit of the task file: command aborted
+const ERROR_ABRT: u8 = 1 << 2;
+/// Error bit of the task file: ID not found
+const ERROR_IDNF: u8 = 1 << 4;
+
+/// Checks the task file data of a port after a command completed.
+///
+/// Aborted commands are unsupported by the device, and sectors that cannot be found are out of bounds.
+/// Other failures are reported as I/O errors.
+pub const fn check_task_file(tfd: u32) -> Result<(), BlockDeviceError> {
+    let status = (tfd & 0xFF) as u8;
+    let error = ((tfd >> 8) & 0xFF) as u8;
+
+    if status & (STATUS_ERR | STATUS_DF) == 0 {
+        Ok(())
+    } else if status & STATUS_DF != 0 {
+        Err(BlockDeviceError::Io)
+    } else if error & ERROR_IDNF != 0 {
+        Err(BlockDeviceError::OutOfBounds)
+    } else if error & ERROR_ABRT != 0 {
+        Err(BlockDeviceError::Unsupported)
+    } else {
+        Err(BlockDeviceError::Io)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+/// Data returned by the IDENTIFY DEVICE command.
+pub struct IdentifyData([u16; 256]);
+
+impl IdentifyData {
+    #[must_use]
+    #[inline]
+    pub const fn new(words: [u16; 256]) -> Self {
+        Self(words)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether the device supports 48-bit LBA addressing.
+    pub const fn supports_lba48(&self) -> bool {
+        self.0[83] & (1 << 10) != 0
+    }
+
+    #[must_use]
+    /// Number of addressable logical sectors.
+    pub const fn sectors(&self) -> u64 {
+        if self.supports_lba48() {
+            (self.0[100] as u64)
+                | ((self.0[101] as u64) << 16)
+                | ((self.0[102] as u64) << 32)
+                | ((self.0[103] as u64) << 48)
+        } else {
+            (self.0[60] as u64) | ((self.0[61] as u64) << 16)
+        }
+    }
+
+    #[must_use]
+    /// Size of a logical sector, in bytes.
+    pub const fn sector_size(&self) -> u32 {
+        let word = self.0[106];
+        // Bits 15-14 must read 01 for the word to be valid
+        let valid = word & 0xC000 == 0x4000;
+        if valid && word & (1 << 12) != 0 {
+            let words = (self.0[117] as u32) | ((self.0[118] as u32) << 16);
+            words * 2
+        } else {
+            512
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_task_file() {
+        // DRDY set, no error
+        assert_eq!(check_task_file(0x50), Ok(()));
+        // Errors are ignored without the ERR bit
+        assert_eq!(check_task_file(0x0450), Ok(()));
+        assert_eq!(check_task_file(0x0451), Err(BlockDeviceError::Unsupported));
+        assert_eq!(check_task_file(0x1051), Err(BlockDeviceError::OutOfBounds));
+        assert_eq!(check_task_file(0x4051), Err(BlockDeviceError::Io));
+        assert_eq!(check_task_file(0x0470), Err(BlockDeviceError::Io));
+    }
+
+    #[test]
+    fn test_identify() {
+        let mut words = [0; 256];
+        words[60] = 0x5678;
+        words[61] = 0x1234;
+        let data = IdentifyData::new(words);
+        assert!(!data.supports_lba48());
+        assert_eq!(data.sectors(), 0x1234_5678);
+        assert_eq!(data.sector_size(), 512);
+
+        words[83] = 1 << 10;
+        words[100] = 0x4444;
+        words[101] = 0x3333;
+        words[102] = 0x2222;
+        words[103] = 0x0001;
+        words[106] = 0x4000 | (1 << 12);
+        words[117] = 2048;
+        let data = IdentifyData::new(words);
+        assert!(data.supports_lba48());
+        assert_eq!(data.sectors(), 0x0001_2222_3333_4444);
+        assert_eq!(data.sector_size(), 4096);
+
+        // Invalid word 106
+        words[106] = 1 << 12;
+        assert_eq!(IdentifyData::new(words).sector_size(), 512);
+    }
+}
@@ -0,0 +1,382 @@
+//! AHCI Command Management
+//!
+//! Command builders and the in-memory structures of the command list.
+
+use super::fis::{AtaCommand, FisH2D};
+
+/// Device register bit selecting LBA addressing
+const DEVICE_LBA_MODE: u8 = 1 << 6;
+
+/// Command structure to be sent to the device
+pub struct AhciCommand {
+    pub fis: FisH2D,
+}
+
+impl Default for AhciCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AhciCommand {
+    #[must_use]
+    #[inline]
+    /// Create a new ATA command
+    pub const fn new() -> Self {
+        Self { fis: FisH2D::new() }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Build an Identify Device command
+    pub const fn identify_device() -> Self {
+        let mut cmd = Self::new();
+        cmd.fis.command = AtaCommand::IdentifyDevice as u8;
+        cmd
+    }
+
+    #[must_use]
+    #[inline]
+    /// Build a Read DMA Extended command
+    pub const fn read_dma_ext(lba: u64, count: u16) -> Self {
+        Self::lba_command(AtaCommand::ReadDmaEx, lba, count)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Build a Write DMA Extended command
+    pub const fn write_dma_ext(lba: u64, count: u16) -> Self {
+        Self::lba_command(AtaCommand::WriteDmaEx, lba, count)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Build a Read Sector Extended command
+    pub const fn read_sector_ext(lba: u64, count: u16) -> Self {
+        Self::lba_command(AtaCommand::ReadSectorEx, lba, count)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Build a Write Sector Extended command
+    pub const fn write_sector_ext(lba: u64, count: u16) -> Self {
+        Self::lba_command(AtaCommand::WriteSectorEx, lba, count)
+    }
+
+    #[must_use]
+    #[inline]
+    const fn lba_command(command: AtaCommand, lba: u64, count: u16) -> Self {
+        let mut cmd = Self::new();
+        cmd.fis.command = command as u8;
+        cmd.fis.device = DEVICE_LBA_MODE;
+        cmd.fis.set_lba(lba);
+        cmd.fis.set_count(count);
+        cmd
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the underlying FIS structure
+    pub const fn fis(&self) -> &FisH2D {
+        &self.fis
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get mutable reference to FIS for advanced configuration
+    pub const fn fis_mut(&mut self) -> &mut FisH2D {
+        &mut self.fis
+    }
+}
+
+/// Port command list entry header
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C, packed)]
+pub struct CommandHeader {
+    /// Bits 0-4: Command FIS length (in DWORDs)
+    /// Bit 6: Write (1=Host to device)
+    /// Bits 12-15: Port multiplier port
+    pub cmd_fis_len_flags: u16,
+    /// Physical region descriptor table length
+    pub prdt_len: u16,
+    /// Physical region descriptor byte count
+    pub prd_byte_count: u32,
+    /// Command table base address (lower 32-bits)
+    pub ctba_low: u32,
+    /// Command table base address (upper 32-bits)
+    pub ctba_high: u32,
+    _reserved: [u32; 4],
+}
+
+impl CommandHeader {
+    #[must_use]
+    /// Create a command header pointing to a command table holding a Host-to-Device FIS
+    /// and `prdt_len` PRDT entries.
+    pub const fn new(ctba: u64, prdt_len: u16, write: bool) -> Self {
+        let mut header = Self {
+            cmd_fis_len_flags: 0,
+            prdt_len,
+            prd_byte_count: 0,
+            ctba_low: 0,
+            ctba_high: 0,
+            _reserved: [0; 4],
+        };
+        #[expect(clippy::cast_possible_truncation, reason = "The FIS is 20 bytes long")]
+        header.set_fis_length((size_of::<FisH2D>() / size_of::<u32>()) as u8);
+        header.set_write(write);
+        header.set_ctba(ctba);
+        header
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get FIS length in DWORDs
+    pub const fn fis_length(&self) -> u8 {
+        (self.cmd_fis_len_flags & 0x1F) as u8
+    }
+
+    #[inline]
+    /// Set FIS length in DWORDs
+    pub const fn set_fis_length(&mut self, len: u8) {
+        self.cmd_fis_len_flags = (self.cmd_fis_len_flags & !0x1F) | (len as u16 & 0x1F);
+    }
+
+    #[must_use]
+    #[inline]
+    /// Check if this is a write (host to device)
+    pub const fn is_write(&self) -> bool {
+        (self.cmd_fis_len_flags & (1 << 6)) != 0
+    }
+
+    #[inline]
+    /// Set write flag
+    pub const fn set_write(&mut self, write: bool) {
+        if write {
+            self.cmd_fis_len_flags |= 1 << 6;
+        } else {
+            self.cmd_fis_len_flags &= !(1 << 6);
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get command table address (48-bit physical address)
+    pub const fn ctba(&self) -> u64 {
+        ((self.ctba_high as u64) << 32) | (self.ctba_low as u64)
+    }
+
+    #[inline]
+    /// Set command table address
+    pub const fn set_ctba(&mut self, addr: u64) {
+        self.ctba_low = (addr & 0xFFFF_FFFF) as u32;
+        self.ctba_high = ((addr >> 32) & 0xFFFF_FFFF) as u32;
+    }
+
+    #[must_use]
+    /// Serialize the header as laid out in the command list
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[0..2].copy_from_slice(&{ self.cmd_fis_len_flags }.to_le_bytes());
+        bytes[2..4].copy_from_slice(&{ self.prdt_len }.to_le_bytes());
+        bytes[4..8].copy_from_slice(&{ self.prd_byte_count }.to_le_bytes());
+        bytes[8..12].copy_from_slice(&{ self.ctba_low }.to_le_bytes());
+        bytes[12..16].copy_from_slice(&{ self.ctba_high }.to_le_bytes());
+        bytes
+    }
+}
+
+/// Received FIS structure (typically 256 bytes per port)
+#[repr(C, packed)]
+pub struct ReceivedFis {
+    pub dma_setup: [u8; 28],
+    _pad1: [u8; 4],
+    pub pio_setup: [u8; 20],
+    _pad2: [u8; 12],
+    pub d2h_register: [u8; 20],
+    _pad3: [u8; 4],
+    pub set_device_bits: [u8; 8],
+    pub unknown_fis: [u8; 64],
+    _reserved: [u8; 96],
+}
+
+/// Physical Region Descriptor Table entry
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C, packed)]
+pub struct PrdTableEntry {
+    /// Data base address (lower 32-bits)
+    pub dba_low: u32,
+    /// Data base address (upper 32-bits)
+    pub dba_high: u32,
+    _reserved: u32,
+    /// Bits 21-0: byte count minus one
+    /// Bit 31: Interrupt on completion
+    pub dbc_ioc: u32,
+}
+
+impl PrdTableEntry {
+    /// Largest byte count a single entry can describe
+    pub const MAX_BYTE_COUNT: u32 = 0x40_0000;
+
+    #[must_use]
+    /// Create an entry describing `byte_count` bytes at `addr`
+    pub fn new(addr: u64, byte_count: u32, ioc: bool) -> Self {
+        let mut entry = Self {
+            dba_low: 0,
+            dba_high: 0,
+            _reserved: 0,
+            dbc_ioc: 0,
+        };
+        entry.set_dba(addr);
+        entry.set_byte_count(byte_count);
+        entry.set_ioc(ioc);
+        entry
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get data buffer address (48-bit physical address)
+    pub const fn dba(&self) -> u64 {
+        ((self.dba_high as u64) << 32) | (self.dba_low as u64)
+    }
+
+    #[inline]
+    /// Set data buffer address
+    pub fn set_dba(&mut self, addr: u64) {
+        self.dba_low = u32::try_from(addr & 0xFFFF_FFFF).unwrap();
+        self.dba_high = u32::try_from((addr >> 32) & 0xFFFF_FFFF).unwrap();
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get byte count
+    pub const fn byte_count(&self) -> u32 {
+        (self.dbc_ioc & 0x3F_FFFF) + 1
+    }
+
+    #[inline]
+    /// Set byte count
+    ///
+    /// The count is clamped to `1..=MAX_BYTE_COUNT`.
+    pub fn set_byte_count(&mut self, count: u32) {
+        let count = count.clamp(1, Self::MAX_BYTE_COUNT);
+        self.dbc_ioc = (self.dbc_ioc & 0xFFC0_0000) | ((count - 1) & 0x3F_FFFF);
+    }
+
+    #[must_use]
+    #[inline]
+    /// Check interrupt on completion flag
+    pub const fn ioc(&self) -> bool {
+        (self.dbc_ioc & (1 << 31)) != 0
+    }
+
+    #[inline]
+    /// Set interrupt on completion flag
+    pub const fn set_ioc(&mut self, ioc: bool) {
+        if ioc {
+            self.dbc_ioc |= 1 << 31;
+        } else {
+            self.dbc_ioc &= !(1 << 31);
+        }
+    }
+
+    #[must_use]
+    /// Serialize the entry as laid out in the command table
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0..4].copy_from_slice(&{ self.dba_low }.to_le_bytes());
+        bytes[4..8].copy_from_slice(&{ self.dba_high }.to_le_bytes());
+        bytes[12..16].copy_from_slice(&{ self.dbc_ioc }.to_le_bytes());
+        bytes
+    }
+}
+
+/// Command table with a single PRDT entry
+///
+/// Command tables must be aligned on 128 bytes.
+#[derive(Debug, Copy, Clone)]
+#[repr(C, packed)]
+pub struct CommandTable {
+    /// Command FIS
+    pub cfis: [u8; 64],
+    /// ATAPI command
+    pub acmd: [u8; 16],
+    _reserved: [u8; 48],
+    pub prdt: [PrdTableEntry; 1],
+}
+
+impl CommandTable {
+    #[must_use]
+    /// Create a command table for the given command, transferring to or from a single region
+    pub fn new(command: &AhciCommand, prd: PrdTableEntry) -> Self {
+        let mut cfis = [0; 64];
+        cfis[..size_of::<FisH2D>()].copy_from_slice(&command.fis().to_bytes());
+        Self {
+            cfis,
+            acmd: [0; 16],
+            _reserved: [0; 48],
+            prdt: [prd],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_sizes() {
+        assert_eq!(size_of::<CommandHeader>(), 32);
+        assert_eq!(size_of::<PrdTableEntry>(), 16);
+        assert_eq!(size_of::<ReceivedFis>(), 256);
+        assert_eq!(size_of::<CommandTable>(), 0x90);
+    }
+
+    #[test]
+    fn test_command_header_bytes() {
+        let header = CommandHeader::new(0x1_2345_6780, 1, true);
+        assert_eq!(header.fis_length(), 5);
+        assert!(header.is_write());
+        assert_eq!(header.ctba(), 0x1_2345_6780);
+
+        let bytes = header.to_bytes();
+        // CFL = 5 DWORDs, W bit (6) set
+        assert_eq!(&bytes[0..2], &[0x45, 0x00]);
+        assert_eq!(&bytes[2..4], &[0x01, 0x00]);
+        assert_eq!(&bytes[4..8], &[0; 4]);
+        assert_eq!(&bytes[8..12], &[0x80, 0x67, 0x45, 0x23]);
+        assert_eq!(&bytes[12..16], &[0x01, 0x00, 0x00, 0x00]);
+        assert!(bytes[16..].iter().all(|&b| b == 0));
+
+        let read = CommandHeader::new(0x1000, 1, false);
+        assert_eq!(&read.to_bytes()[0..2], &[0x05, 0x00]);
+    }
+
+    #[test]
+    fn test_prd_entry_bytes() {
+        let entry = PrdTableEntry::new(0xAB_0000_2000, 512, true);
+        assert_eq!(entry.dba(), 0xAB_0000_2000);
+        assert_eq!(entry.byte_count(), 512);
+        assert!(entry.ioc());
+
+        let bytes = entry.to_bytes();
+        assert_eq!(&bytes[0..4], &[0x00, 0x20, 0x00, 0x00]);
+        assert_eq!(&bytes[4..8], &[0xAB, 0x00, 0x00, 0x00]);
+        assert_eq!(&bytes[8..12], &[0; 4]);
+        // Byte count is stored minus one
+        assert_eq!(&bytes[12..16], &[0xFF, 0x01, 0x00, 0x80]);
+
+        let mut entry = PrdTableEntry::new(0, u32::MAX, false);
+        assert_eq!(entry.byte_count(), PrdTableEntry::MAX_BYTE_COUNT);
+        entry.set_byte_count(0);
+        assert_eq!(entry.byte_count(), 1);
+    }
+
+    #[test]
+    fn test_command_table() {
+        let command = AhciCommand::read_dma_ext(0x10, 8);
+        let table = CommandTable::new(&command, PrdTableEntry::new(0x3000, 8 * 512, true));
+        assert_eq!(table.cfis[..20], command.fis().to_bytes());
+        assert!(table.cfis[20..].iter().all(|&b| b == 0));
+        assert_eq!({ table.prdt[0] }.byte_count(), 4096);
+    }
+}
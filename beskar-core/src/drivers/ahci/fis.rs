@@ -119,6 +119,33 @@ impl FisH2D {
     pub const fn count(&self) -> u16 {
         (self.count_l as u16) | ((self.count_h as u16) << 8)
     }
+
+    #[must_use]
+    /// Serialize the FIS as sent to the device
+    pub const fn to_bytes(&self) -> [u8; 20] {
+        [
+            self.fis_type,
+            self.pmport_c,
+            self.command,
+            self.feature_l,
+            self.lba0,
+            self.lba1,
+            self.lba2,
+            self.device,
+            self.lba3,
+            self.lba4,
+            self.lba5,
+            self.feature_h,
+            self.count_l,
+            self.count_h,
+            self.icc,
+            self.control,
+            0,
+            0,
+            0,
+            0,
+        ]
+    }
 }
 
 /// Device-to-Host Register FIS
@@ -219,3 +246,35 @@ pub struct PioSetup {
     pub transfer_count: u16,
     _reserved3: [u8; 2],
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::ahci::command::AhciCommand;
+
+    #[test]
+    fn test_fis_sizes() {
+        assert_eq!(size_of::<FisH2D>(), 20);
+        assert_eq!(size_of::<FisD2H>(), 20);
+        assert_eq!(size_of::<DmaSetup>(), 28);
+        assert_eq!(size_of::<PioSetup>(), 20);
+    }
+
+    #[test]
+    fn test_h2d_bytes() {
+        let cmd = AhciCommand::write_dma_ext(0x0605_0403_0201, 0x0102);
+        assert_eq!(cmd.fis().lba(), 0x0605_0403_0201);
+        assert_eq!(cmd.fis().count(), 0x0102);
+        assert_eq!(
+            cmd.fis().to_bytes(),
+            [
+                0x27, 0x80, 0x35, 0x00, 0x01, 0x02, 0x03, 0x40, 0x04, 0x05, 0x06, 0x00, 0x02, 0x01,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ]
+        );
+
+        let identify = AhciCommand::identify_device();
+        assert_eq!(&identify.fis().to_bytes()[..4], &[0x27, 0x80, 0xEC, 0x00]);
+        assert_eq!(identify.fis().device, 0);
+    }
+}
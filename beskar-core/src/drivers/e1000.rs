@@ -0,0 +1,114 @@
+//! Intel e1000 network controller family.
+//!
+//! Structures and registers are shared by the e1000 (82540EM, as emulated by QEMU)
+//! and e1000e controllers, which both use legacy descriptors.
+
+pub mod descriptors;
+pub mod registers;
+
+/// Bookkeeping of the software side of a descriptor ring.
+///
+/// The controller owns the descriptors from the head register up to (but excluding) the tail register,
+/// so that equal head and tail registers mean the controller owns nothing.
+/// The software processes the descriptors in order, starting from `current`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingIndex {
+    len: usize,
+    current: usize,
+}
+
+impl RingIndex {
+    #[must_use]
+    #[inline]
+    /// Create the bookkeeping of a ring of `len` descriptors, starting at the first one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero.
+    pub const fn new(len: usize) -> Self {
+        assert!(len > 0, "Ring must not be empty");
+        Self { len, current: 0 }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Index of the next descriptor to process.
+    pub const fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Move on to the next descriptor, wrapping around the end of the ring.
+    ///
+    /// Returns the index of the descriptor that was just processed.
+    pub const fn advance(&mut self) -> usize {
+        let processed = self.current;
+        self.current = (self.current + 1) % self.len;
+        processed
+    }
+
+    #[must_use]
+    #[inline]
+    /// Initial value of the tail register of a receive ring.
+    ///
+    /// One descriptor is kept from the controller so that a full ring is not mistaken for an empty one.
+    pub const fn initial_rx_tail(&self) -> usize {
+        self.len - 1
+    }
+}
+
+#[must_use]
+/// Get the MAC address from the first three words of the EEPROM.
+pub const fn mac_from_eeprom(words: [u16; 3]) -> [u8; 6] {
+    let [w0, w1, w2] = words;
+    let [b0, b1] = w0.to_le_bytes();
+    let [b2, b3] = w1.to_le_bytes();
+    let [b4, b5] = w2.to_le_bytes();
+    [b0, b1, b2, b3, b4, b5]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_advance() {
+        let mut ring = RingIndex::new(4);
+        assert_eq!(ring.current(), 0);
+        assert_eq!(ring.initial_rx_tail(), 3);
+
+        assert_eq!(ring.advance(), 0);
+        assert_eq!(ring.advance(), 1);
+        assert_eq!(ring.advance(), 2);
+        assert_eq!(ring.current(), 3);
+        // Wrap around
+        assert_eq!(ring.advance(), 3);
+        assert_eq!(ring.current(), 0);
+    }
+
+    #[test]
+    fn test_rx_tail_follows() {
+        // The receive tail register is set to the descriptor just processed,
+        // so that it always trails the next descriptor to process.
+        let mut ring = RingIndex::new(8);
+        let mut tail = ring.initial_rx_tail();
+        for _ in 0..20 {
+            assert_eq!((tail + 1) % 8, ring.current());
+            tail = ring.advance();
+        }
+    }
+
+    #[test]
+    fn test_single_descriptor() {
+        let mut ring = RingIndex::new(1);
+        assert_eq!(ring.advance(), 0);
+        assert_eq!(ring.current(), 0);
+    }
+
+    #[test]
+    fn test_mac_from_eeprom() {
+        assert_eq!(
+            mac_from_eeprom([0x5452, 0x3412, 0x5634]),
+            [0x52, 0x54, 0x12, 0x34, 0x34, 0x56]
+        );
+    }
+}
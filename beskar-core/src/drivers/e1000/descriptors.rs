@@ -1,8 +1,8 @@
-//! E1000e descriptors structures and implementation.
+//! Legacy descriptors, shared by the whole e1000 family.
 
-use beskar_core::arch::PhysAddr;
+use crate::arch::PhysAddr;
 
-/// Receive descriptor for the e1000e NIC.
+/// Receive descriptor for the e1000 NICs.
 #[repr(C, packed)]
 pub struct RxDescriptor {
     buffer_addr: PhysAddr,
@@ -61,6 +61,17 @@ impl RxDescriptor {
         self.errors != 0
     }
 
+    /// Get the length of a packet received in full and without errors.
+    ///
+    /// Returns `None` if the descriptor does not hold such a packet (yet).
+    #[must_use]
+    pub const fn received_length(&self) -> Option<u16> {
+        if !self.is_done() || !self.is_end_of_packet() || self.has_errors() || self.length == 0 {
+            return None;
+        }
+        Some(self.length)
+    }
+
     /// Reset the descriptor for reuse.
     #[inline]
     pub const fn reset(&mut self) {
@@ -70,7 +81,7 @@ impl RxDescriptor {
     }
 }
 
-/// Transmit descriptor for the e1000e NIC.
+/// Transmit descriptor for the e1000 NICs.
 #[repr(C, packed)]
 pub struct TxDescriptor {
     buffer_addr: PhysAddr,
@@ -130,3 +141,51 @@ impl TxDescriptor {
         self.css = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptor_sizes() {
+        assert_eq!(size_of::<RxDescriptor>(), 16);
+        assert_eq!(size_of::<TxDescriptor>(), 16);
+    }
+
+    #[test]
+    fn test_received_length() {
+        let mut desc = RxDescriptor::new(PhysAddr::new_truncate(0x1000), 2048);
+        assert_eq!(desc.received_length(), None);
+
+        desc.reset();
+        desc.length = 60;
+        desc.status = RxDescriptor::STATUS_DD;
+        // Not the end of the packet
+        assert_eq!(desc.received_length(), None);
+
+        desc.status |= RxDescriptor::STATUS_EOP;
+        assert_eq!(desc.received_length(), Some(60));
+
+        desc.errors = 1;
+        assert_eq!(desc.received_length(), None);
+
+        desc.reset();
+        assert!(!desc.is_done());
+        assert_eq!(desc.received_length(), None);
+    }
+
+    #[test]
+    fn test_prepare_for_send() {
+        let mut desc = TxDescriptor::new(PhysAddr::new_truncate(0x2000), 2048);
+        assert!(desc.is_done());
+
+        desc.prepare_for_send(42);
+        assert!(!desc.is_done());
+        let length = desc.length;
+        assert_eq!(length, 42);
+        assert_eq!(
+            desc.cmd,
+            TxDescriptor::CMD_EOP | TxDescriptor::CMD_IFCS | TxDescriptor::CMD_RS
+        );
+    }
+}
@@ -1,9 +1,8 @@
-//! E1000e register offsets and bit flags.
+//! Register offsets and bit flags, shared by the whole e1000 family.
 
-/// Register offsets for the e1000e NIC.
+/// Register offsets for the e1000 NICs.
 pub struct Registers;
 
-#[allow(dead_code)]
 impl Registers {
     // Control registers
     pub const CTRL: usize = 0x00000;
@@ -43,7 +42,6 @@ impl Registers {
 /// RCTL (Receive Control) register flags
 pub struct RctlFlags;
 
-#[allow(dead_code)]
 impl RctlFlags {
     /// Receiver Enable
     pub const EN: u32 = 1 << 1;
@@ -102,7 +100,6 @@ impl RctlFlags {
 /// TCTL (Transmit Control) register flags
 pub struct TctlFlags;
 
-#[allow(dead_code)]
 impl TctlFlags {
     /// Transmit Enable
     pub const EN: u32 = 1 << 1;
@@ -133,6 +130,8 @@ impl TctlFlags {
 pub struct CtrlFlags;
 
 impl CtrlFlags {
+    /// Set Link Up
+    pub const SLU: u32 = 1 << 6;
     /// Device Reset
     pub const RST: u32 = 1 << 26;
 }
@@ -140,7 +139,6 @@ impl CtrlFlags {
 /// Interrupt Cause flags
 pub struct IntFlags;
 
-#[allow(dead_code)]
 impl IntFlags {
     /// Transmit Descriptor Written Back
     pub const TXDW: u32 = 1 << 0;
@@ -157,3 +155,27 @@ impl IntFlags {
     /// Receive Timer Interrupt
     pub const RXT0: u32 = 1 << 7;
 }
+
+/// EERD (EEPROM Read) register fields of the 82540EM.
+///
+/// Later controllers (including the e1000e) moved the done bit and the address.
+pub struct EerdFlags;
+
+impl EerdFlags {
+    /// Start Read
+    pub const START: u32 = 1 << 0;
+    /// Read Done
+    pub const DONE: u32 = 1 << 4;
+    /// Read Address shift
+    pub const ADDR_SHIFT: u32 = 8;
+    /// Read Data shift
+    pub const DATA_SHIFT: u32 = 16;
+}
+
+/// RAH (Receive Address High) register flags
+pub struct RahFlags;
+
+impl RahFlags {
+    /// Address Valid
+    pub const AV: u32 = 1 << 31;
+}
@@ -1,5 +1,9 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+pub mod focus;
+pub mod layout;
+pub mod typematic;
+
 #[derive(Debug, Clone, Copy)]
 pub struct KeyEvent {
     key: KeyCode,
@@ -163,6 +167,9 @@ pub enum KeyCode {
     Menu,
     WindowsLeft,
     WindowsRight,
+
+    /// Additional key of ISO keyboards, between left shift and Z.
+    NonUsBackslash,
 }
 
 impl KeyCode {
@@ -191,6 +198,10 @@ impl KeyCode {
     }
 
     #[must_use]
+    /// Translates the key into a character, using the US layout.
+    ///
+    /// Only letters, space and numpad digits are supported.
+    /// Use a [`layout::KeyboardLayout`] for a complete translation.
     pub const fn as_char(&self, modifiers: KeyModifiers) -> char {
         let raw = match self {
             Self::A => 'a',
@@ -259,6 +270,7 @@ impl KeyModifiers {
     const ALT: u8 = 0b0000_0100;
     const CAPS_LOCK: u8 = 0b0000_1000;
     const NUM_LOCK: u8 = 0b0001_0000;
+    const ALT_GR: u8 = 0b0010_0000;
 
     #[must_use]
     #[inline]
@@ -284,6 +296,13 @@ impl KeyModifiers {
         self.flags & Self::ALT != 0
     }
 
+    #[must_use]
+    #[inline]
+    /// Whether the right Alt key (`AltGr`) is pressed.
+    pub const fn is_alt_gr(&self) -> bool {
+        self.flags & Self::ALT_GR != 0
+    }
+
     #[must_use]
     #[inline]
     pub const fn is_caps_locked(&self) -> bool {
@@ -329,6 +348,15 @@ impl KeyModifiers {
         }
     }
 
+    #[inline]
+    pub const fn set_alt_gr(&mut self, alt_gr: bool) {
+        if alt_gr {
+            self.flags |= Self::ALT_GR;
+        } else {
+            self.flags &= !Self::ALT_GR;
+        }
+    }
+
     #[inline]
     pub const fn set_caps_locked(&mut self, caps_locked: bool) {
         if caps_locked {
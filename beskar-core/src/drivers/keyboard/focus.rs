@@ -0,0 +1,358 @@
+//! Keyboard focus.
+//!
+//! Key events are only delivered to the focused process, which is the app in the foreground.
+//! While no process has the focus, any process may read them: this is how apps that do not
+//! hold the display, such as the shell, get their input.
+//!
+//! When the focus changes, buffered events are dropped, so that keys typed for an app
+//! never reach another one.
+//! Alt+Tab is a system hotkey that switches the focus and never reaches apps.
+//!
+//! The keyboard and the display make up the controlling terminal, whose foreground
+//! process group is the group of the focused process. While a process has the focus,
+//! Ctrl-C raises `Signal::Interrupt` and Ctrl-Z `Signal::Suspend` in that group
+//! instead of reaching it as keys. A shell starts apps in groups of their own, so that
+//! it is not interrupted along with them. Without a focused process, these keys are
+//! delivered as any other, for the shell to handle them.
+//! Ctrl-D is always delivered as a key, which apps read as the end of their input.
+//!
+//! A process blocked reading a key while it does not have the focus keeps waiting.
+//! If it had the focus at some point of the wait and loses it, the read fails instead,
+//! so that the app notices it went to the background.
+
+use super::{KeyCode, KeyEvent, KeyState};
+use crate::process::signal::Signal;
+
+/// What to do with an event coming from the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    /// The event is buffered for the focused process.
+    Deliver,
+    /// The event is the focus switch hotkey.
+    SwitchFocus,
+    /// The event is part of the hotkey and must be dropped.
+    Discard,
+    /// The event is a control key, which raises a signal in the process group
+    /// of the focused process.
+    Signal { signal: Signal, pid: u64 },
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+/// A modifier key, found on both sides of the keyboard.
+struct Modifier {
+    left: bool,
+    right: bool,
+}
+
+impl Modifier {
+    #[must_use]
+    #[inline]
+    const fn new() -> Self {
+        Self {
+            left: false,
+            right: false,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    const fn is_held(self) -> bool {
+        self.left || self.right
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FocusRouter {
+    focused: Option<u64>,
+    alt: Modifier,
+    /// Set while Tab is held as part of the hotkey, so that its release is dropped as well.
+    hotkey_held: bool,
+    ctrl: Modifier,
+    /// The control key that raised a signal, while it is held, so that its release is dropped.
+    control_held: Option<KeyCode>,
+}
+
+impl FocusRouter {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            focused: None,
+            alt: Modifier::new(),
+            hotkey_held: false,
+            ctrl: Modifier::new(),
+            control_held: None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the PID of the focused process, if any.
+    pub const fn focused(&self) -> Option<u64> {
+        self.focused
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether the given process may read key events.
+    pub fn may_receive(&self, pid: u64) -> bool {
+        self.focused.is_none_or(|focused| focused == pid)
+    }
+
+    /// Gives the focus to the given process, or to none.
+    ///
+    /// Returns `true` if the focus changed, in which case buffered events must be dropped.
+    pub fn set_focus(&mut self, pid: Option<u64>) -> bool {
+        let changed = self.focused != pid;
+        self.focused = pid;
+        changed
+    }
+
+    /// Feeds an event coming from the keyboard, tracking the modifiers of the hotkeys.
+    pub const fn on_event(&mut self, event: KeyEvent) -> Route {
+        let pressed = matches!(event.pressed(), KeyState::Pressed);
+        match event.key() {
+            KeyCode::AltLeft => self.alt.left = pressed,
+            KeyCode::AltRight => self.alt.right = pressed,
+            KeyCode::CtrlLeft => self.ctrl.left = pressed,
+            KeyCode::CtrlRight => self.ctrl.right = pressed,
+            key @ (KeyCode::C | KeyCode::Z) if pressed && self.ctrl.is_held() => {
+                if let Some(pid) = self.focused {
+                    self.control_held = Some(key);
+                    let signal = if matches!(key, KeyCode::C) {
+                        Signal::Interrupt
+                    } else {
+                        Signal::Suspend
+                    };
+                    return Route::Signal { signal, pid };
+                }
+            }
+            key if !pressed && Self::is_control_held(self.control_held, key) => {
+                self.control_held = None;
+                return Route::Discard;
+            }
+            KeyCode::Tab if pressed && self.alt.is_held() => {
+                self.hotkey_held = true;
+                return Route::SwitchFocus;
+            }
+            KeyCode::Tab if !pressed && self.hotkey_held => {
+                self.hotkey_held = false;
+                return Route::Discard;
+            }
+            _ => {}
+        }
+        Route::Deliver
+    }
+
+    #[must_use]
+    #[inline]
+    const fn is_control_held(held: Option<KeyCode>, key: KeyCode) -> bool {
+        match held {
+            Some(held) => held as u8 == key as u8,
+            None => false,
+        }
+    }
+}
+
+/// Outcome of an attempt of a blocking read.
+#[derive(Debug, Clone, Copy)]
+pub enum ReadStep {
+    /// An event was read.
+    Event(KeyEvent),
+    /// No event is available: the reader must be parked until the next one.
+    Park,
+    /// The reader lost the focus while waiting.
+    FocusLost,
+}
+
+/// A blocking key read, attempted again every time the reader is woken up.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockingRead {
+    pid: u64,
+    had_focus: bool,
+}
+
+impl BlockingRead {
+    #[must_use]
+    #[inline]
+    pub const fn new(pid: u64) -> Self {
+        Self {
+            pid,
+            had_focus: false,
+        }
+    }
+
+    /// Attempts to read an event.
+    ///
+    /// `pop` takes the next buffered event, and is only called if the reader has the focus.
+    pub fn step(
+        &mut self,
+        router: &FocusRouter,
+        pop: impl FnOnce() -> Option<KeyEvent>,
+    ) -> ReadStep {
+        if !router.may_receive(self.pid) {
+            return if self.had_focus {
+                ReadStep::FocusLost
+            } else {
+                ReadStep::Park
+            };
+        }
+        self.had_focus = true;
+        pop().map_or(ReadStep::Park, ReadStep::Event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: KeyCode) -> KeyEvent {
+        KeyEvent::new(key, KeyState::Pressed)
+    }
+
+    fn release(key: KeyCode) -> KeyEvent {
+        KeyEvent::new(key, KeyState::Released)
+    }
+
+    #[test]
+    fn test_routing_to_focused() {
+        let mut router = FocusRouter::new();
+        // Without focus, anyone reads.
+        assert!(router.may_receive(1));
+        assert!(router.may_receive(2));
+
+        assert!(router.set_focus(Some(1)));
+        assert_eq!(router.focused(), Some(1));
+        assert!(router.may_receive(1));
+        assert!(!router.may_receive(2));
+
+        // Giving the focus to the same process does not drop events.
+        assert!(!router.set_focus(Some(1)));
+
+        assert!(router.set_focus(Some(2)));
+        assert!(!router.may_receive(1));
+        assert!(router.may_receive(2));
+
+        assert!(router.set_focus(None));
+        assert!(router.may_receive(1));
+        assert!(!router.set_focus(None));
+    }
+
+    #[test]
+    fn test_hotkey() {
+        let mut router = FocusRouter::new();
+        assert_eq!(router.on_event(press(KeyCode::Tab)), Route::Deliver);
+        assert_eq!(router.on_event(release(KeyCode::Tab)), Route::Deliver);
+
+        // Modifiers still reach apps.
+        assert_eq!(router.on_event(press(KeyCode::AltLeft)), Route::Deliver);
+        assert_eq!(router.on_event(press(KeyCode::Tab)), Route::SwitchFocus);
+        // Holding Alt and pressing Tab again keeps cycling.
+        assert_eq!(router.on_event(press(KeyCode::Tab)), Route::SwitchFocus);
+        assert_eq!(router.on_event(release(KeyCode::AltLeft)), Route::Deliver);
+        // The release of the hotkey is dropped, even after Alt.
+        assert_eq!(router.on_event(release(KeyCode::Tab)), Route::Discard);
+        assert_eq!(router.on_event(press(KeyCode::Tab)), Route::Deliver);
+
+        assert_eq!(router.on_event(press(KeyCode::AltRight)), Route::Deliver);
+        assert_eq!(router.on_event(press(KeyCode::A)), Route::Deliver);
+        assert_eq!(router.on_event(press(KeyCode::Tab)), Route::SwitchFocus);
+    }
+
+    #[test]
+    fn test_ctrl_c_signals_focused_child() {
+        // The shell (PID 1) reads without the focus, while its child (PID 2) has it.
+        let mut router = FocusRouter::new();
+        router.set_focus(Some(2));
+
+        assert_eq!(router.on_event(press(KeyCode::CtrlLeft)), Route::Deliver);
+        assert_eq!(
+            router.on_event(press(KeyCode::C)),
+            Route::Signal {
+                signal: Signal::Interrupt,
+                pid: 2
+            }
+        );
+        // The release is dropped, even after Ctrl, so the shell never sees the key.
+        assert_eq!(router.on_event(release(KeyCode::CtrlLeft)), Route::Deliver);
+        assert_eq!(router.on_event(release(KeyCode::C)), Route::Discard);
+        assert!(!router.may_receive(1));
+
+        // Without Ctrl, C is a key.
+        assert_eq!(router.on_event(press(KeyCode::C)), Route::Deliver);
+        assert_eq!(router.on_event(release(KeyCode::C)), Route::Deliver);
+
+        assert_eq!(router.on_event(press(KeyCode::CtrlRight)), Route::Deliver);
+        assert_eq!(
+            router.on_event(press(KeyCode::Z)),
+            Route::Signal {
+                signal: Signal::Suspend,
+                pid: 2
+            }
+        );
+        assert_eq!(router.on_event(press(KeyCode::D)), Route::Deliver);
+        assert_eq!(router.on_event(release(KeyCode::Z)), Route::Discard);
+
+        // Once the child is done, Ctrl-C reaches the shell as keys.
+        router.set_focus(None);
+        assert_eq!(router.on_event(press(KeyCode::C)), Route::Deliver);
+        assert_eq!(router.on_event(release(KeyCode::C)), Route::Deliver);
+    }
+
+    #[test]
+    fn test_blocking_read_wakes_on_event() {
+        let mut router = FocusRouter::new();
+        router.set_focus(Some(1));
+        let mut queue = None;
+
+        let mut read = BlockingRead::new(1);
+        assert!(matches!(
+            read.step(&router, || queue.take()),
+            ReadStep::Park
+        ));
+        // Spurious wake up.
+        assert!(matches!(
+            read.step(&router, || queue.take()),
+            ReadStep::Park
+        ));
+
+        queue = Some(press(KeyCode::A));
+        let ReadStep::Event(event) = read.step(&router, || queue.take()) else {
+            panic!("the event was not read");
+        };
+        assert_eq!(event.key(), KeyCode::A);
+    }
+
+    #[test]
+    fn test_blocking_read_focus() {
+        let mut router = FocusRouter::new();
+        router.set_focus(Some(1));
+        let mut queue = Some(press(KeyCode::A));
+
+        // A background reader waits without taking the events of the focused process.
+        let mut background = BlockingRead::new(2);
+        assert!(matches!(
+            background.step(&router, || queue.take()),
+            ReadStep::Park
+        ));
+        assert!(queue.is_some());
+
+        let mut foreground = BlockingRead::new(1);
+        queue = None;
+        assert!(matches!(
+            foreground.step(&router, || queue.take()),
+            ReadStep::Park
+        ));
+
+        router.set_focus(Some(2));
+        assert!(matches!(
+            foreground.step(&router, || queue.take()),
+            ReadStep::FocusLost
+        ));
+        queue = Some(press(KeyCode::B));
+        assert!(matches!(
+            background.step(&router, || queue.take()),
+            ReadStep::Event(_)
+        ));
+    }
+}
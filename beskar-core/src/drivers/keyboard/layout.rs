@@ -0,0 +1,506 @@
+//! Keyboard layouts.
+//!
+//! Key codes describe the physical position of a key, named after the US layout.
+//! A layout translates them into characters.
+
+use super::{KeyCode, KeyModifiers};
+use core::sync::atomic::{AtomicU8, Ordering};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+pub trait KeyboardLayout {
+    /// Translates a key into a character.
+    ///
+    /// Returns `None` if the key does not produce a character, including dead keys.
+    fn translate(&self, key: KeyCode, modifiers: KeyModifiers) -> Option<char>;
+
+    /// Returns the dead key produced by `key`, if any.
+    fn dead_key(&self, _key: KeyCode, _modifiers: KeyModifiers) -> Option<DeadKey> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Accent that is combined with the next character.
+pub enum DeadKey {
+    Grave,
+    Acute,
+    Circumflex,
+    Tilde,
+    Diaeresis,
+}
+
+impl DeadKey {
+    #[must_use]
+    /// Returns the spacing form of the accent.
+    pub const fn as_char(self) -> char {
+        match self {
+            Self::Grave => '`',
+            Self::Acute => '´',
+            Self::Circumflex => '^',
+            Self::Tilde => '~',
+            Self::Diaeresis => '¨',
+        }
+    }
+
+    #[must_use]
+    /// Combines the accent with `c`.
+    ///
+    /// Returns `None` if there is no such accented character.
+    pub const fn compose(self, c: char) -> Option<char> {
+        let composed = match (self, c) {
+            (Self::Grave, 'a') => 'à',
+            (Self::Grave, 'e') => 'è',
+            (Self::Grave, 'i') => 'ì',
+            (Self::Grave, 'o') => 'ò',
+            (Self::Grave, 'u') => 'ù',
+            (Self::Grave, 'A') => 'À',
+            (Self::Grave, 'E') => 'È',
+            (Self::Grave, 'I') => 'Ì',
+            (Self::Grave, 'O') => 'Ò',
+            (Self::Grave, 'U') => 'Ù',
+
+            (Self::Acute, 'a') => 'á',
+            (Self::Acute, 'e') => 'é',
+            (Self::Acute, 'i') => 'í',
+            (Self::Acute, 'o') => 'ó',
+            (Self::Acute, 'u') => 'ú',
+            (Self::Acute, 'y') => 'ý',
+            (Self::Acute, 'A') => 'Á',
+            (Self::Acute, 'E') => 'É',
+            (Self::Acute, 'I') => 'Í',
+            (Self::Acute, 'O') => 'Ó',
+            (Self::Acute, 'U') => 'Ú',
+            (Self::Acute, 'Y') => 'Ý',
+
+            (Self::Circumflex, 'a') => 'â',
+            (Self::Circumflex, 'e') => 'ê',
+            (Self::Circumflex, 'i') => 'î',
+            (Self::Circumflex, 'o') => 'ô',
+            (Self::Circumflex, 'u') => 'û',
+            (Self::Circumflex, 'A') => 'Â',
+            (Self::Circumflex, 'E') => 'Ê',
+            (Self::Circumflex, 'I') => 'Î',
+            (Self::Circumflex, 'O') => 'Ô',
+            (Self::Circumflex, 'U') => 'Û',
+
+            (Self::Tilde, 'a') => 'ã',
+            (Self::Tilde, 'n') => 'ñ',
+            (Self::Tilde, 'o') => 'õ',
+            (Self::Tilde, 'A') => 'Ã',
+            (Self::Tilde, 'N') => 'Ñ',
+            (Self::Tilde, 'O') => 'Õ',
+
+            (Self::Diaeresis, 'a') => 'ä',
+            (Self::Diaeresis, 'e') => 'ë',
+            (Self::Diaeresis, 'i') => 'ï',
+            (Self::Diaeresis, 'o') => 'ö',
+            (Self::Diaeresis, 'u') => 'ü',
+            (Self::Diaeresis, 'y') => 'ÿ',
+            (Self::Diaeresis, 'A') => 'Ä',
+            (Self::Diaeresis, 'E') => 'Ë',
+            (Self::Diaeresis, 'I') => 'Ï',
+            (Self::Diaeresis, 'O') => 'Ö',
+            (Self::Diaeresis, 'U') => 'Ü',
+
+            _ => return None,
+        };
+        Some(composed)
+    }
+}
+
+/// Applies the case of the modifiers to a letter.
+const fn letter(c: char, modifiers: KeyModifiers) -> char {
+    if modifiers.is_uppercase() {
+        c.to_ascii_uppercase()
+    } else {
+        c
+    }
+}
+
+/// Translates the keys that are the same on every layout.
+const fn translate_common(key: KeyCode) -> Option<char> {
+    let c = match key {
+        KeyCode::Space => ' ',
+        KeyCode::Numpad0 => '0',
+        KeyCode::Numpad1 => '1',
+        KeyCode::Numpad2 => '2',
+        KeyCode::Numpad3 => '3',
+        KeyCode::Numpad4 => '4',
+        KeyCode::Numpad5 => '5',
+        KeyCode::Numpad6 => '6',
+        KeyCode::Numpad7 => '7',
+        KeyCode::Numpad8 => '8',
+        KeyCode::Numpad9 => '9',
+        KeyCode::NumpadAdd => '+',
+        KeyCode::NumpadSub => '-',
+        KeyCode::NumpadMul => '*',
+        KeyCode::NumpadDiv => '/',
+        KeyCode::NumpadDot => '.',
+        _ => return None,
+    };
+    Some(c)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+/// US QWERTY layout.
+pub struct UsQwerty;
+
+impl KeyboardLayout for UsQwerty {
+    fn translate(&self, key: KeyCode, modifiers: KeyModifiers) -> Option<char> {
+        let shifted = modifiers.is_shifted();
+
+        let (normal, shift) = match key {
+            KeyCode::A => return Some(letter('a', modifiers)),
+            KeyCode::B => return Some(letter('b', modifiers)),
+            KeyCode::C => return Some(letter('c', modifiers)),
+            KeyCode::D => return Some(letter('d', modifiers)),
+            KeyCode::E => return Some(letter('e', modifiers)),
+            KeyCode::F => return Some(letter('f', modifiers)),
+            KeyCode::G => return Some(letter('g', modifiers)),
+            KeyCode::H => return Some(letter('h', modifiers)),
+            KeyCode::I => return Some(letter('i', modifiers)),
+            KeyCode::J => return Some(letter('j', modifiers)),
+            KeyCode::K => return Some(letter('k', modifiers)),
+            KeyCode::L => return Some(letter('l', modifiers)),
+            KeyCode::M => return Some(letter('m', modifiers)),
+            KeyCode::N => return Some(letter('n', modifiers)),
+            KeyCode::O => return Some(letter('o', modifiers)),
+            KeyCode::P => return Some(letter('p', modifiers)),
+            KeyCode::Q => return Some(letter('q', modifiers)),
+            KeyCode::R => return Some(letter('r', modifiers)),
+            KeyCode::S => return Some(letter('s', modifiers)),
+            KeyCode::T => return Some(letter('t', modifiers)),
+            KeyCode::U => return Some(letter('u', modifiers)),
+            KeyCode::V => return Some(letter('v', modifiers)),
+            KeyCode::W => return Some(letter('w', modifiers)),
+            KeyCode::X => return Some(letter('x', modifiers)),
+            KeyCode::Y => return Some(letter('y', modifiers)),
+            KeyCode::Z => return Some(letter('z', modifiers)),
+
+            KeyCode::Num1 => ('1', '!'),
+            KeyCode::Num2 => ('2', '@'),
+            KeyCode::Num3 => ('3', '#'),
+            KeyCode::Num4 => ('4', '$'),
+            KeyCode::Num5 => ('5', '%'),
+            KeyCode::Num6 => ('6', '^'),
+            KeyCode::Num7 => ('7', '&'),
+            KeyCode::Num8 => ('8', '*'),
+            KeyCode::Num9 => ('9', '('),
+            KeyCode::Num0 => ('0', ')'),
+
+            KeyCode::Minus => ('-', '_'),
+            KeyCode::Equal => ('=', '+'),
+            KeyCode::LeftBracket => ('[', '{'),
+            KeyCode::RightBracket => (']', '}'),
+            KeyCode::Backslash | KeyCode::NonUsBackslash => ('\\', '|'),
+            KeyCode::Semicolon => (';', ':'),
+            KeyCode::Apostrophe => ('\'', '"'),
+            KeyCode::Tilde => ('`', '~'),
+            KeyCode::Comma => (',', '<'),
+            KeyCode::Dot => ('.', '>'),
+            KeyCode::Slash => ('/', '?'),
+
+            key => return translate_common(key),
+        };
+
+        Some(if shifted { shift } else { normal })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+/// French AZERTY layout.
+pub struct Azerty;
+
+impl KeyboardLayout for Azerty {
+    fn translate(&self, key: KeyCode, modifiers: KeyModifiers) -> Option<char> {
+        if modifiers.is_alt_gr() {
+            return match key {
+                KeyCode::Num3 => Some('#'),
+                KeyCode::Num4 => Some('{'),
+                KeyCode::Num5 => Some('['),
+                KeyCode::Num6 => Some('|'),
+                KeyCode::Num8 => Some('\\'),
+                KeyCode::Num9 => Some('^'),
+                KeyCode::Num0 => Some('@'),
+                KeyCode::Minus => Some(']'),
+                KeyCode::Equal => Some('}'),
+                KeyCode::E => Some('€'),
+                KeyCode::RightBracket => Some('¤'),
+                _ => None,
+            };
+        }
+
+        let shifted = modifiers.is_shifted();
+
+        let (normal, shift) = match key {
+            KeyCode::Q => return Some(letter('a', modifiers)),
+            KeyCode::B => return Some(letter('b', modifiers)),
+            KeyCode::C => return Some(letter('c', modifiers)),
+            KeyCode::D => return Some(letter('d', modifiers)),
+            KeyCode::E => return Some(letter('e', modifiers)),
+            KeyCode::F => return Some(letter('f', modifiers)),
+            KeyCode::G => return Some(letter('g', modifiers)),
+            KeyCode::H => return Some(letter('h', modifiers)),
+            KeyCode::I => return Some(letter('i', modifiers)),
+            KeyCode::J => return Some(letter('j', modifiers)),
+            KeyCode::K => return Some(letter('k', modifiers)),
+            KeyCode::L => return Some(letter('l', modifiers)),
+            KeyCode::Semicolon => return Some(letter('m', modifiers)),
+            KeyCode::N => return Some(letter('n', modifiers)),
+            KeyCode::O => return Some(letter('o', modifiers)),
+            KeyCode::P => return Some(letter('p', modifiers)),
+            KeyCode::A => return Some(letter('q', modifiers)),
+            KeyCode::R => return Some(letter('r', modifiers)),
+            KeyCode::S => return Some(letter('s', modifiers)),
+            KeyCode::T => return Some(letter('t', modifiers)),
+            KeyCode::U => return Some(letter('u', modifiers)),
+            KeyCode::V => return Some(letter('v', modifiers)),
+            KeyCode::Z => return Some(letter('w', modifiers)),
+            KeyCode::X => return Some(letter('x', modifiers)),
+            KeyCode::Y => return Some(letter('y', modifiers)),
+            KeyCode::W => return Some(letter('z', modifiers)),
+
+            KeyCode::Num1 => ('&', '1'),
+            KeyCode::Num2 => ('é', '2'),
+            KeyCode::Num3 => ('"', '3'),
+            KeyCode::Num4 => ('\'', '4'),
+            KeyCode::Num5 => ('(', '5'),
+            KeyCode::Num6 => ('-', '6'),
+            KeyCode::Num7 => ('è', '7'),
+            KeyCode::Num8 => ('_', '8'),
+            KeyCode::Num9 => ('ç', '9'),
+            KeyCode::Num0 => ('à', '0'),
+
+            KeyCode::Tilde => return (!shifted).then_some('²'),
+            KeyCode::Minus => (')', '°'),
+            KeyCode::Equal => ('=', '+'),
+            KeyCode::RightBracket => ('$', '£'),
+            KeyCode::Apostrophe => ('ù', '%'),
+            KeyCode::Backslash => ('*', 'µ'),
+            KeyCode::M => (',', '?'),
+            KeyCode::Comma => (';', '.'),
+            KeyCode::Dot => (':', '/'),
+            KeyCode::Slash => ('!', '§'),
+            KeyCode::NonUsBackslash => ('<', '>'),
+
+            key => return translate_common(key),
+        };
+
+        Some(if shifted { shift } else { normal })
+    }
+
+    fn dead_key(&self, key: KeyCode, modifiers: KeyModifiers) -> Option<DeadKey> {
+        match key {
+            KeyCode::LeftBracket if !modifiers.is_alt_gr() => Some(if modifiers.is_shifted() {
+                DeadKey::Diaeresis
+            } else {
+                DeadKey::Circumflex
+            }),
+            KeyCode::Num2 if modifiers.is_alt_gr() => Some(DeadKey::Tilde),
+            KeyCode::Num7 if modifiers.is_alt_gr() => Some(DeadKey::Grave),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+/// Available keyboard layouts.
+pub enum Layout {
+    #[default]
+    UsQwerty,
+    Azerty,
+}
+
+impl KeyboardLayout for Layout {
+    fn translate(&self, key: KeyCode, modifiers: KeyModifiers) -> Option<char> {
+        match self {
+            Self::UsQwerty => UsQwerty.translate(key, modifiers),
+            Self::Azerty => Azerty.translate(key, modifiers),
+        }
+    }
+
+    fn dead_key(&self, key: KeyCode, modifiers: KeyModifiers) -> Option<DeadKey> {
+        match self {
+            Self::UsQwerty => UsQwerty.dead_key(key, modifiers),
+            Self::Azerty => Azerty.dead_key(key, modifiers),
+        }
+    }
+}
+
+/// Layout that can be changed at runtime.
+pub struct ActiveLayout(AtomicU8);
+
+impl Default for ActiveLayout {
+    fn default() -> Self {
+        Self::new(Layout::default())
+    }
+}
+
+impl ActiveLayout {
+    #[must_use]
+    #[inline]
+    pub const fn new(layout: Layout) -> Self {
+        Self(AtomicU8::new(layout as u8))
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn get(&self) -> Layout {
+        Layout::try_from(self.0.load(Ordering::Relaxed)).unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn set(&self, layout: Layout) {
+        self.0.store(layout.into(), Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+/// Translates key presses into characters, combining dead keys with the next key.
+pub struct Translator {
+    pending: Option<DeadKey>,
+}
+
+impl Translator {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self { pending: None }
+    }
+
+    /// Translates a key press.
+    ///
+    /// Returns `None` for dead keys, whose accent is buffered until the next key press.
+    /// If the accent cannot be combined with the next character, the accent is dropped,
+    /// except for space which produces the accent itself.
+    pub fn translate(
+        &mut self,
+        layout: &impl KeyboardLayout,
+        key: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> Option<char> {
+        if let Some(dead_key) = layout.dead_key(key, modifiers) {
+            // Pressing a dead key twice produces the accent.
+            if self.pending.take() == Some(dead_key) {
+                return Some(dead_key.as_char());
+            }
+            self.pending = Some(dead_key);
+            return None;
+        }
+
+        // Modifiers do not consume the pending accent.
+        let c = layout.translate(key, modifiers)?;
+
+        Some(match self.pending.take() {
+            Some(dead_key) if c == ' ' => dead_key.as_char(),
+            Some(dead_key) => dead_key.compose(c).unwrap_or(c),
+            None => c,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the buffered dead key, if any.
+    pub const fn pending(&self) -> Option<DeadKey> {
+        self.pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_key_two_layouts() {
+        let modifiers = KeyModifiers::new();
+        assert_eq!(UsQwerty.translate(KeyCode::Q, modifiers), Some('q'));
+        assert_eq!(Azerty.translate(KeyCode::Q, modifiers), Some('a'));
+        assert_eq!(UsQwerty.translate(KeyCode::Num2, modifiers), Some('2'));
+        assert_eq!(Azerty.translate(KeyCode::Num2, modifiers), Some('é'));
+        assert_eq!(UsQwerty.translate(KeyCode::M, modifiers), Some('m'));
+        assert_eq!(Azerty.translate(KeyCode::M, modifiers), Some(','));
+
+        let mut shifted = KeyModifiers::new();
+        shifted.set_shifted(true);
+        assert_eq!(UsQwerty.translate(KeyCode::Num2, shifted), Some('@'));
+        assert_eq!(Azerty.translate(KeyCode::Num2, shifted), Some('2'));
+        assert_eq!(Azerty.translate(KeyCode::W, shifted), Some('Z'));
+
+        let mut alt_gr = KeyModifiers::new();
+        alt_gr.set_alt_gr(true);
+        assert_eq!(Azerty.translate(KeyCode::Num0, alt_gr), Some('@'));
+        assert_eq!(Azerty.translate(KeyCode::A, alt_gr), None);
+
+        assert_eq!(UsQwerty.translate(KeyCode::ShiftLeft, modifiers), None);
+        assert_eq!(Azerty.translate(KeyCode::Space, modifiers), Some(' '));
+    }
+
+    #[test]
+    fn test_dead_keys() {
+        let layout = Layout::Azerty;
+        let modifiers = KeyModifiers::new();
+        let mut shifted = KeyModifiers::new();
+        shifted.set_shifted(true);
+
+        let mut translator = Translator::new();
+        assert_eq!(
+            translator.translate(&layout, KeyCode::LeftBracket, modifiers),
+            None
+        );
+        assert_eq!(translator.pending(), Some(DeadKey::Circumflex));
+        // Pressing shift keeps the accent
+        assert_eq!(
+            translator.translate(&layout, KeyCode::ShiftLeft, shifted),
+            None
+        );
+        assert_eq!(
+            translator.translate(&layout, KeyCode::E, shifted),
+            Some('Ê')
+        );
+        assert_eq!(translator.pending(), None);
+
+        translator.translate(&layout, KeyCode::LeftBracket, shifted);
+        assert_eq!(
+            translator.translate(&layout, KeyCode::Q, modifiers),
+            Some('ä')
+        );
+
+        // No composition: the accent is dropped
+        translator.translate(&layout, KeyCode::LeftBracket, modifiers);
+        assert_eq!(
+            translator.translate(&layout, KeyCode::X, modifiers),
+            Some('x')
+        );
+
+        translator.translate(&layout, KeyCode::LeftBracket, modifiers);
+        assert_eq!(
+            translator.translate(&layout, KeyCode::Space, modifiers),
+            Some('^')
+        );
+
+        translator.translate(&layout, KeyCode::LeftBracket, modifiers);
+        assert_eq!(
+            translator.translate(&layout, KeyCode::LeftBracket, modifiers),
+            Some('^')
+        );
+
+        // US QWERTY has no dead keys
+        assert_eq!(
+            translator.translate(&Layout::UsQwerty, KeyCode::LeftBracket, modifiers),
+            Some('[')
+        );
+    }
+
+    #[test]
+    fn test_active_layout() {
+        let active = ActiveLayout::default();
+        assert_eq!(active.get(), Layout::UsQwerty);
+        active.set(Layout::Azerty);
+        assert_eq!(active.get(), Layout::Azerty);
+        assert_eq!(
+            active.get().translate(KeyCode::A, KeyModifiers::new()),
+            Some('q')
+        );
+    }
+}
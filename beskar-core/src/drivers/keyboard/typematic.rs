@@ -0,0 +1,243 @@
+//! Software key repeat (typematic).
+//!
+//! After a key has been held for `delay`, it is repeated every `interval` until released.
+//! Only the most recently pressed key repeats, and modifier keys never do.
+
+use super::{KeyCode, KeyEvent, KeyState};
+use crate::time::{Duration, Instant};
+
+/// Default delay before the first repeat.
+pub const DEFAULT_DELAY: Duration = Duration::from_millis(500);
+/// Default interval between two repeats (about 30 characters per second).
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(33);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypematicConfig {
+    delay: Duration,
+    interval: Duration,
+}
+
+impl Default for TypematicConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_DELAY, DEFAULT_INTERVAL)
+    }
+}
+
+impl TypematicConfig {
+    #[must_use]
+    #[inline]
+    /// Creates a new configuration.
+    ///
+    /// A null `interval` disables repeat.
+    pub const fn new(delay: Duration, interval: Duration) -> Self {
+        Self { delay, interval }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HeldKey {
+    key: KeyCode,
+    next_repeat: Instant,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Typematic {
+    config: TypematicConfig,
+    held: Option<HeldKey>,
+}
+
+impl Typematic {
+    #[must_use]
+    #[inline]
+    pub const fn new(config: TypematicConfig) -> Self {
+        Self { config, held: None }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn config(&self) -> TypematicConfig {
+        self.config
+    }
+
+    #[inline]
+    /// Changes the configuration, which applies from the next key press.
+    pub const fn set_config(&mut self, config: TypematicConfig) {
+        self.config = config;
+    }
+
+    /// Feeds an event coming from the keyboard.
+    ///
+    /// Returns `false` if the event must be dropped, which is the case
+    /// for repeats generated by the keyboard itself.
+    pub fn on_event(&mut self, event: KeyEvent, now: Instant) -> bool {
+        let key = event.key();
+
+        match event.pressed() {
+            KeyState::Pressed => {
+                if self.held.is_some_and(|held| held.key == key) {
+                    return false;
+                }
+                if Self::repeats(key) && self.config.interval > Duration::ZERO {
+                    self.held = Some(HeldKey {
+                        key,
+                        next_repeat: now + self.config.delay,
+                    });
+                }
+            }
+            KeyState::Released => {
+                if self.held.is_some_and(|held| held.key == key) {
+                    self.held = None;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Returns the repeat event that is due at `now`, if any.
+    ///
+    /// Should be called periodically.
+    /// If calls are further apart than the interval, missed repeats are skipped.
+    pub fn poll(&mut self, now: Instant) -> Option<KeyEvent> {
+        let held = self.held.as_mut()?;
+        if now < held.next_repeat {
+            return None;
+        }
+
+        held.next_repeat += self.config.interval;
+        if held.next_repeat <= now {
+            held.next_repeat = now + self.config.interval;
+        }
+        Some(KeyEvent::new(held.key, KeyState::Pressed))
+    }
+
+    #[inline]
+    /// Stops repeating the held key until the next key press.
+    pub const fn cancel(&mut self) {
+        self.held = None;
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the key that is currently repeating or waiting to.
+    pub fn held_key(&self) -> Option<KeyCode> {
+        self.held.map(|held| held.key)
+    }
+
+    #[must_use]
+    const fn repeats(key: KeyCode) -> bool {
+        !matches!(
+            key,
+            KeyCode::ShiftLeft
+                | KeyCode::ShiftRight
+                | KeyCode::CtrlLeft
+                | KeyCode::CtrlRight
+                | KeyCode::AltLeft
+                | KeyCode::AltRight
+                | KeyCode::CapsLock
+                | KeyCode::NumLock
+                | KeyCode::ScrollLock
+                | KeyCode::WindowsLeft
+                | KeyCode::WindowsRight
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: u64) -> Instant {
+        Instant::from_millis(millis)
+    }
+
+    fn press(key: KeyCode) -> KeyEvent {
+        KeyEvent::new(key, KeyState::Pressed)
+    }
+
+    fn release(key: KeyCode) -> KeyEvent {
+        KeyEvent::new(key, KeyState::Released)
+    }
+
+    fn typematic() -> Typematic {
+        Typematic::new(TypematicConfig::new(
+            Duration::from_millis(500),
+            Duration::from_millis(100),
+        ))
+    }
+
+    #[test]
+    fn test_press_hold_release() {
+        let mut typematic = typematic();
+
+        assert!(typematic.on_event(press(KeyCode::Backspace), at(0)));
+        assert!(typematic.poll(at(0)).is_none());
+        assert!(typematic.poll(at(499)).is_none());
+
+        let repeat = typematic.poll(at(500)).unwrap();
+        assert_eq!(repeat.key(), KeyCode::Backspace);
+        assert_eq!(repeat.pressed(), KeyState::Pressed);
+        assert!(typematic.poll(at(550)).is_none());
+        assert!(typematic.poll(at(600)).is_some());
+        assert!(typematic.poll(at(700)).is_some());
+
+        assert!(typematic.on_event(release(KeyCode::Backspace), at(750)));
+        assert!(typematic.poll(at(800)).is_none());
+        assert!(typematic.held_key().is_none());
+    }
+
+    #[test]
+    fn test_last_key_repeats() {
+        let mut typematic = typematic();
+
+        typematic.on_event(press(KeyCode::A), at(0));
+        typematic.on_event(press(KeyCode::B), at(200));
+        assert_eq!(typematic.held_key(), Some(KeyCode::B));
+
+        // A would be due, but only B repeats, after its own delay
+        assert!(typematic.poll(at(500)).is_none());
+        assert_eq!(typematic.poll(at(700)).unwrap().key(), KeyCode::B);
+
+        // Releasing A does not stop B
+        typematic.on_event(release(KeyCode::A), at(750));
+        assert_eq!(typematic.poll(at(800)).unwrap().key(), KeyCode::B);
+    }
+
+    #[test]
+    fn test_modifiers_and_hardware_repeat() {
+        let mut typematic = typematic();
+
+        typematic.on_event(press(KeyCode::A), at(0));
+        // Holding shift neither repeats nor interrupts A
+        typematic.on_event(press(KeyCode::ShiftLeft), at(100));
+        assert_eq!(typematic.held_key(), Some(KeyCode::A));
+
+        // Repeats sent by the keyboard are dropped
+        assert!(!typematic.on_event(press(KeyCode::A), at(500)));
+        assert_eq!(typematic.poll(at(500)).unwrap().key(), KeyCode::A);
+
+        // Missed repeats are skipped
+        assert!(typematic.poll(at(2000)).is_some());
+        assert!(typematic.poll(at(2050)).is_none());
+        assert!(typematic.poll(at(2100)).is_some());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut typematic = Typematic::new(TypematicConfig::new(Duration::ZERO, Duration::ZERO));
+        typematic.on_event(press(KeyCode::A), at(0));
+        assert!(typematic.poll(at(1000)).is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Formatting helpers for debugging.
+use core::fmt;
+
+/// Number of bytes on each line of a hexdump.
+const BYTES_PER_LINE: usize = 16;
+
+#[must_use]
+#[inline]
+/// Formats `bytes` in the classic offset/hex/ASCII columns, 16 bytes per line.
+///
+/// Offsets start at `base_addr`, so that a structure can be dumped at its address.
+/// Bytes that are not printable ASCII are shown as `.` in the last column.
+/// Lines are separated by newlines, without a trailing one, so the dump can be logged as is:
+///
+/// ```rust
+/// # use beskar_core::fmt::hexdump;
+/// #
+/// assert_eq!(
+///     hexdump(b"Hello\n", 0x1000).to_string(),
+///     "00001000  48 65 6c 6c 6f 0a                                 |Hello.|"
+/// );
+/// ```
+pub const fn hexdump(bytes: &[u8], base_addr: u64) -> HexDump<'_> {
+    HexDump { bytes, base_addr }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A hexdump of a byte slice, see `hexdump`.
+pub struct HexDump<'a> {
+    bytes: &'a [u8],
+    base_addr: u64,
+}
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.bytes.chunks(BYTES_PER_LINE).enumerate() {
+            if i != 0 {
+                f.write_str("\n")?;
+            }
+            let offset = self.base_addr.wrapping_add((i * BYTES_PER_LINE) as u64);
+            write!(f, "{offset:08x} ")?;
+
+            for column in 0..BYTES_PER_LINE {
+                // An extra space separates the two halves of the line
+                if column == BYTES_PER_LINE / 2 {
+                    f.write_str(" ")?;
+                }
+                match line.get(column) {
+                    Some(byte) => write!(f, " {byte:02x}")?,
+                    // Pad partial lines so that the ASCII column stays aligned
+                    None => f.write_str("   ")?,
+                }
+            }
+
+            f.write_str("  |")?;
+            for &byte in line {
+                let c = if byte.is_ascii_graphic() || byte == b' ' {
+                    char::from(byte)
+                } else {
+                    '.'
+                };
+                write!(f, "{c}")?;
+            }
+            f.write_str("|")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    /// A fixed-size buffer to format into, as there is no allocator.
+    struct Buffer {
+        bytes: [u8; 512],
+        len: usize,
+    }
+
+    impl Buffer {
+        fn format(args: impl fmt::Display) -> Self {
+            let mut buffer = Self {
+                bytes: [0; 512],
+                len: 0,
+            };
+            write!(buffer, "{args}").unwrap();
+            buffer
+        }
+
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+        }
+    }
+
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let bytes = b"\x7fELF\x02\x01\x01\x00Hello, world!\r\n\xff";
+        let dump = Buffer::format(hexdump(bytes, 0x1000));
+        assert_eq!(
+            dump.as_str(),
+            "00001000  7f 45 4c 46 02 01 01 00  48 65 6c 6c 6f 2c 20 77  |.ELF....Hello, w|\n\
+             00001010  6f 72 6c 64 21 0d 0a ff                           |orld!...|"
+        );
+    }
+
+    #[test]
+    fn test_hexdump_full_lines() {
+        let bytes: [u8; 32] = core::array::from_fn(|i| b'a' + u8::try_from(i % 26).unwrap());
+        let dump = Buffer::format(hexdump(&bytes, 0));
+        assert_eq!(
+            dump.as_str(),
+            "00000000  61 62 63 64 65 66 67 68  69 6a 6b 6c 6d 6e 6f 70  |abcdefghijklmnop|\n\
+             00000010  71 72 73 74 75 76 77 78  79 7a 61 62 63 64 65 66  |qrstuvwxyzabcdef|"
+        );
+    }
+
+    #[test]
+    fn test_hexdump_empty() {
+        assert_eq!(Buffer::format(hexdump(&[], 0)).as_str(), "");
+    }
+}
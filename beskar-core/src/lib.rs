@@ -10,9 +10,14 @@
 )]
 
 pub mod arch;
+mod bitflags;
+pub mod bytes;
+pub mod crypto;
 pub mod drivers;
+pub mod fmt;
 pub mod mem;
 pub mod process;
+pub mod rand;
 pub mod storage;
 pub mod syscall;
 pub mod time;
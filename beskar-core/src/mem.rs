@@ -1 +1,4 @@
+pub mod brk;
+pub mod fault;
 pub mod ranges;
+pub mod vma;
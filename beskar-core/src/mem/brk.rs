@@ -0,0 +1,218 @@
+//! Program break of a process.
+//!
+//! The heap of a process is a single area that starts at a fixed address and ends at the
+//! program break. The break moves up to grow the heap and down to shrink it,
+//! within the address space reserved for the heap, which bounds its size.
+//!
+//! Only the bookkeeping lives here: the kernel maps the pages between the old and
+//! the new break, rounded to whole pages, see `BreakMove::pages`.
+use super::ranges::MemoryRange;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum BrkError {
+    #[error("The break would move below the start of the heap")]
+    BelowStart,
+    #[error("The heap would exceed its maximum size")]
+    TooLarge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A move of the program break.
+pub struct BreakMove {
+    pub old: u64,
+    pub new: u64,
+}
+
+impl BreakMove {
+    #[must_use]
+    #[inline]
+    pub const fn grows(self) -> bool {
+        self.new > self.old
+    }
+
+    #[must_use]
+    /// Returns the pages of `page_size` bytes the heap gains or gives up with the move.
+    ///
+    /// Returns `None` if both breaks end the heap in the same page.
+    pub const fn pages(self, page_size: u64) -> Option<MemoryRange> {
+        let old_end = self.old.next_multiple_of(page_size);
+        let new_end = self.new.next_multiple_of(page_size);
+        if old_end < new_end {
+            Some(MemoryRange::new(old_end, new_end - 1))
+        } else if new_end < old_end {
+            Some(MemoryRange::new(new_end, old_end - 1))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The heap of a process, from its start to the program break.
+pub struct ProgramBreak {
+    start: u64,
+    current: u64,
+    max_size: u64,
+}
+
+impl ProgramBreak {
+    #[must_use]
+    #[inline]
+    /// Creates an empty heap starting at `start`, that may grow up to `max_size` bytes.
+    pub const fn new(start: u64, max_size: u64) -> Self {
+        Self {
+            start,
+            current: start,
+            max_size,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn start(&self) -> u64 {
+        self.start
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the program break, i.e. the end of the heap.
+    pub const fn current(&self) -> u64 {
+        self.current
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the size of the heap, in bytes.
+    pub const fn size(&self) -> u64 {
+        self.current - self.start
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Moves the program break to `new`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `new` is below the start of the heap, or past its maximum size.
+    /// The break is left unchanged in that case.
+    pub const fn set(&mut self, new: u64) -> Result<BreakMove, BrkError> {
+        if new < self.start {
+            return Err(BrkError::BelowStart);
+        }
+        if new - self.start > self.max_size {
+            return Err(BrkError::TooLarge);
+        }
+        let old = self.current;
+        self.current = new;
+        Ok(BreakMove { old, new })
+    }
+
+    /// Moves the program break by `increment` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Same as `set`.
+    pub const fn adjust(&mut self, increment: i64) -> Result<BreakMove, BrkError> {
+        match self.current.checked_add_signed(increment) {
+            Some(new) => self.set(new),
+            None if increment < 0 => Err(BrkError::BelowStart),
+            None => Err(BrkError::TooLarge),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::vma::{Backing, Vma, VmaFlags, VmaSet};
+
+    const START: u64 = 0x4000_0000;
+    const PAGE: u64 = 0x1000;
+
+    #[test]
+    fn test_grow_and_shrink() {
+        let mut brk = ProgramBreak::new(START, 0x10_0000);
+        assert_eq!(brk.current(), START);
+        assert_eq!(brk.size(), 0);
+
+        let grow = brk.adjust(0x2000).unwrap();
+        assert_eq!(
+            grow,
+            BreakMove {
+                old: START,
+                new: START + 0x2000
+            }
+        );
+        assert!(grow.grows());
+
+        let shrink = brk.set(START + 0x1000).unwrap();
+        assert!(!shrink.grows());
+        assert_eq!(brk.size(), 0x1000);
+
+        assert_eq!(brk.adjust(-0x1000).unwrap().new, START);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut brk = ProgramBreak::new(START, 0x10_0000);
+
+        assert_eq!(brk.set(START + 0x10_0000).unwrap().new, START + 0x10_0000);
+        assert_eq!(brk.adjust(1), Err(BrkError::TooLarge));
+        assert_eq!(brk.set(START - 1), Err(BrkError::BelowStart));
+        assert_eq!(brk.adjust(i64::MIN), Err(BrkError::BelowStart));
+        assert_eq!(brk.adjust(i64::MAX), Err(BrkError::TooLarge));
+        // Failed moves leave the break untouched
+        assert_eq!(brk.current(), START + 0x10_0000);
+    }
+
+    #[test]
+    fn test_pages() {
+        let mut brk = ProgramBreak::new(START, 0x10_0000);
+
+        assert_eq!(
+            brk.adjust(0x1800).unwrap().pages(PAGE),
+            Some(MemoryRange::new(START, START + 0x1FFF))
+        );
+        // Within the last page of the heap
+        assert_eq!(brk.adjust(0x100).unwrap().pages(PAGE), None);
+        assert_eq!(brk.adjust(-0x100).unwrap().pages(PAGE), None);
+        assert_eq!(
+            brk.set(START).unwrap().pages(PAGE),
+            Some(MemoryRange::new(START, START + 0x1FFF))
+        );
+    }
+
+    /// Records the pages gained or given up by `mv` in the areas, as the kernel does.
+    fn apply(vmas: &mut VmaSet<4>, mv: BreakMove) -> impl Iterator<Item = MemoryRange> {
+        let pages = mv.pages(PAGE).unwrap();
+        if mv.grows() {
+            let flags = VmaFlags::READ | VmaFlags::WRITE;
+            vmas.insert(Vma::new(pages, flags, Backing::Anonymous))
+                .unwrap();
+        } else {
+            vmas.remove(pages).unwrap();
+        }
+        vmas.iter().map(Vma::range)
+    }
+
+    #[test]
+    fn test_shrink_then_grow_areas() {
+        let mut brk = ProgramBreak::new(START, 0x10_0000);
+        let mut vmas = VmaSet::<4>::new();
+
+        let heap = apply(&mut vmas, brk.adjust(0x3000).unwrap());
+        assert!(heap.eq([MemoryRange::new(START, START + 0x2FFF)]));
+        let heap = apply(&mut vmas, brk.adjust(-0x1800).unwrap());
+        assert!(heap.eq([MemoryRange::new(START, START + 0x1FFF)]));
+
+        // The pages given up are no longer in the heap area, so they can be gained again
+        let heap = apply(&mut vmas, brk.set(START + 0x4000).unwrap());
+        assert!(heap.eq([MemoryRange::new(START, START + 0x3FFF)]));
+        assert_eq!(apply(&mut vmas, brk.set(START).unwrap()).count(), 0);
+    }
+}
@@ -0,0 +1,312 @@
+//! Page fault classification.
+//!
+//! A page fault is not always an error: pages of anonymous mappings are only backed
+//! by a frame on first access, copy-on-write pages are shared until written,
+//! and stacks grow downward on demand.
+//! The decision of what to do about a fault only depends on the fault itself
+//! and on the memory region it hit, which is what `classify` implements.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Kind of access that caused a fault.
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A decoded page fault.
+pub struct PageFault {
+    /// Faulting virtual address.
+    pub addr: u64,
+    pub access: Access,
+    /// Whether the page was present, i.e. the fault is a protection violation.
+    pub present: bool,
+    /// Whether the fault happened in user mode.
+    pub user: bool,
+    /// Whether a reserved bit was set in a paging structure.
+    pub reserved_bit: bool,
+    /// Stack pointer of the faulting code.
+    pub stack_pointer: u64,
+}
+
+/// How far below the stack pointer a stack may grow on a fault, in bytes.
+///
+/// Code only accesses its stack above the stack pointer, except for a few
+/// instructions (such as `push`) that touch the bytes right below it.
+/// An access further below is a bug rather than a growing stack.
+pub const STACK_GROWTH_GAP: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How the pages of a region are backed.
+pub enum RegionKind {
+    /// Zero-filled pages, backed on first access.
+    Anonymous,
+    /// Pages shared read-only until they are written, then copied.
+    CopyOnWrite,
+    /// A stack, growing down on demand.
+    ///
+    /// The region spans the maximum size of the stack.
+    Stack,
+    /// Frames shared with other address spaces, which are mapped upfront.
+    Shared,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A memory region of a process, as far as faults are concerned.
+pub struct Region {
+    pub kind: RegionKind,
+    pub readable: bool,
+    pub writable: bool,
+    pub executable: bool,
+}
+
+impl Region {
+    #[must_use]
+    #[inline]
+    /// Returns whether the region allows `access`.
+    pub const fn allows(&self, access: Access) -> bool {
+        match access {
+            Access::Read => self.readable,
+            Access::Write => self.writable,
+            Access::Execute => self.executable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Why a faulting process is killed.
+pub enum KillReason {
+    /// The address is not part of any region.
+    Unmapped,
+    /// The region does not allow the access.
+    AccessViolation,
+    /// A paging structure is corrupted.
+    ReservedBit,
+    /// No frame is left to service the fault.
+    OutOfMemory,
+    /// The access is too far below the stack pointer to grow the stack.
+    BelowStack,
+}
+
+impl KillReason {
+    #[must_use]
+    #[inline]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unmapped => "access to unmapped memory",
+            Self::AccessViolation => "access not allowed by the memory region",
+            Self::ReservedBit => "reserved bit set in a page table",
+            Self::OutOfMemory => "out of memory",
+            Self::BelowStack => "access too far below the stack pointer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What to do about a page fault.
+pub enum Resolution {
+    /// Back the page with a zeroed frame.
+    MapZeroed,
+    /// Back the page with a private copy of the frame it shares.
+    CopyOnWrite,
+    /// Grow the stack down to the page.
+    GrowStack,
+    /// The page is already accessible, which happens when another core serviced
+    /// the same fault: return and retry the access.
+    Retry,
+    /// Kill the faulting process.
+    Kill(KillReason),
+    /// The kernel itself faulted: the system cannot go on.
+    Fatal,
+}
+
+#[must_use]
+/// Decides what to do about `fault`, given the region containing the faulting address.
+///
+/// Kernel-mode faults are serviced the same way as user-mode ones when they hit a region
+/// of the process, which happens when the kernel accesses user memory.
+/// Any other kernel-mode fault is fatal.
+///
+/// A stack only grows if the access is at most `STACK_GROWTH_GAP` below the stack pointer,
+/// and never past its region, which bounds its size.
+/// The stack pointer of a kernel-mode fault is not the one of the process,
+/// so the distance is not checked for them.
+pub const fn classify(fault: &PageFault, region: Option<&Region>) -> Resolution {
+    const fn kill(fault: &PageFault, reason: KillReason) -> Resolution {
+        if fault.user {
+            Resolution::Kill(reason)
+        } else {
+            Resolution::Fatal
+        }
+    }
+
+    if fault.reserved_bit {
+        return kill(fault, KillReason::ReservedBit);
+    }
+    let Some(region) = region else {
+        return kill(fault, KillReason::Unmapped);
+    };
+
+    match (fault.access, fault.present, region.kind) {
+        (Access::Write, true, RegionKind::CopyOnWrite) if region.writable => {
+            Resolution::CopyOnWrite
+        }
+        _ if !region.allows(fault.access) => kill(fault, KillReason::AccessViolation),
+        (_, true, _) => Resolution::Retry,
+        (_, false, RegionKind::Stack)
+            if !fault.user
+                || fault.addr.saturating_add(STACK_GROWTH_GAP) >= fault.stack_pointer =>
+        {
+            Resolution::GrowStack
+        }
+        (_, false, RegionKind::Stack) => kill(fault, KillReason::BelowStack),
+        // There is no frame to share
+        (_, false, RegionKind::Shared) => kill(fault, KillReason::Unmapped),
+        (_, false, RegionKind::Anonymous | RegionKind::CopyOnWrite) => Resolution::MapZeroed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANON_RW: Region = Region {
+        kind: RegionKind::Anonymous,
+        readable: true,
+        writable: true,
+        executable: false,
+    };
+    const COW: Region = Region {
+        kind: RegionKind::CopyOnWrite,
+        ..ANON_RW
+    };
+    const STACK: Region = Region {
+        kind: RegionKind::Stack,
+        ..ANON_RW
+    };
+    const SHARED: Region = Region {
+        kind: RegionKind::Shared,
+        ..ANON_RW
+    };
+    const CODE: Region = Region {
+        kind: RegionKind::Anonymous,
+        readable: true,
+        writable: false,
+        executable: true,
+    };
+
+    const fn fault(access: Access, present: bool, user: bool) -> PageFault {
+        PageFault {
+            addr: 0x1000,
+            access,
+            present,
+            user,
+            reserved_bit: false,
+            stack_pointer: 0x1000,
+        }
+    }
+
+    #[test]
+    fn test_decision_table() {
+        use Access::{Execute, Read, Write};
+        use Resolution::{CopyOnWrite, Fatal, GrowStack, Kill, MapZeroed, Retry};
+
+        let table = [
+            // Demand paging
+            (fault(Read, false, true), Some(ANON_RW), MapZeroed),
+            (fault(Write, false, true), Some(ANON_RW), MapZeroed),
+            (fault(Write, false, false), Some(ANON_RW), MapZeroed),
+            // Copy-on-write
+            (fault(Write, true, true), Some(COW), CopyOnWrite),
+            (fault(Write, true, false), Some(COW), CopyOnWrite),
+            (fault(Read, true, true), Some(COW), Retry),
+            // Stack growth
+            (fault(Write, false, true), Some(STACK), GrowStack),
+            // Shared frames are never backed on demand
+            (
+                fault(Read, false, true),
+                Some(SHARED),
+                Kill(KillReason::Unmapped),
+            ),
+            (fault(Read, true, true), Some(SHARED), Retry),
+            // Already serviced
+            (fault(Write, true, true), Some(ANON_RW), Retry),
+            // Access violations
+            (
+                fault(Write, true, true),
+                Some(CODE),
+                Kill(KillReason::AccessViolation),
+            ),
+            (
+                fault(Execute, false, true),
+                Some(ANON_RW),
+                Kill(KillReason::AccessViolation),
+            ),
+            (fault(Write, true, false), Some(CODE), Fatal),
+            (fault(Execute, false, true), Some(CODE), MapZeroed),
+            // Outside of any region
+            (fault(Read, false, true), None, Kill(KillReason::Unmapped)),
+            (fault(Read, false, false), None, Fatal),
+            (fault(Read, true, false), None, Fatal),
+        ];
+
+        for (fault, region, expected) in table {
+            assert_eq!(
+                classify(&fault, region.as_ref()),
+                expected,
+                "{fault:?} in {region:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_stack_growth() {
+        let below_sp = |distance: u64, user| PageFault {
+            addr: 0x10_0000 - distance,
+            stack_pointer: 0x10_0000,
+            ..fault(Access::Write, false, user)
+        };
+
+        // Right below the stack pointer, as by a `push`
+        assert_eq!(
+            classify(&below_sp(8, true), Some(&STACK)),
+            Resolution::GrowStack
+        );
+        assert_eq!(
+            classify(&below_sp(STACK_GROWTH_GAP, true), Some(&STACK)),
+            Resolution::GrowStack
+        );
+        // Far below
+        assert_eq!(
+            classify(&below_sp(STACK_GROWTH_GAP + 1, true), Some(&STACK)),
+            Resolution::Kill(KillReason::BelowStack)
+        );
+        assert_eq!(
+            classify(&below_sp(0x8_0000, true), Some(&STACK)),
+            Resolution::Kill(KillReason::BelowStack)
+        );
+        // The kernel stack pointer says nothing about the user stack
+        assert_eq!(
+            classify(&below_sp(0x8_0000, false), Some(&STACK)),
+            Resolution::GrowStack
+        );
+        // Past the maximum size of the stack, the address is outside of the region
+        assert_eq!(
+            classify(&below_sp(8, true), None),
+            Resolution::Kill(KillReason::Unmapped)
+        );
+    }
+
+    #[test]
+    fn test_reserved_bit() {
+        let mut fault = fault(Access::Read, true, true);
+        fault.reserved_bit = true;
+        assert_eq!(
+            classify(&fault, Some(&ANON_RW)),
+            Resolution::Kill(KillReason::ReservedBit)
+        );
+        fault.user = false;
+        assert_eq!(classify(&fault, Some(&ANON_RW)), Resolution::Fatal);
+    }
+}
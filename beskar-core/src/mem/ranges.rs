@@ -1,4 +1,7 @@
-use crate::arch::Alignment;
+use crate::arch::{
+    Alignment, PhysAddr,
+    paging::{Frame, MemSize},
+};
 use core::ops::{Index, IndexMut};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -315,6 +318,37 @@ impl<const N: usize> MemoryRanges<N> {
         }
     }
 
+    #[must_use]
+    /// Allocates `count` contiguous frames, the first of which is returned.
+    ///
+    /// The start address of the run is aligned on `align`, or on the frame size if it is stricter.
+    /// The run must fit in a single range, so it may fail on fragmented memory
+    /// even if enough memory is free in total.
+    pub fn allocate_frames<S: MemSize>(
+        &mut self,
+        count: u64,
+        align: Alignment,
+    ) -> Option<Frame<S>> {
+        let size = S::SIZE.checked_mul(count)?;
+        let alignment = if align.as_u64() > S::ALIGNMENT.as_u64() {
+            align
+        } else {
+            S::ALIGNMENT
+        };
+
+        let addr = self.allocate(size, alignment)?;
+        Some(PhysAddr::new_truncate(addr).frame())
+    }
+
+    /// Frees `count` contiguous frames previously allocated with `allocate_frames`.
+    pub fn free_frames<S: MemSize>(&mut self, first: Frame<S>, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let start = first.start_address().as_u64();
+        self.insert(MemoryRange::new(start, start + (S::SIZE * count - 1)));
+    }
+
     #[must_use]
     pub fn intersection(&self, other: &Self) -> Self {
         let mut result = Self::new();
@@ -331,10 +365,187 @@ impl<const N: usize> MemoryRanges<N> {
     }
 }
 
+/// Sorts `regions` by start address and merges the contiguous ones,
+/// returning the number of regions left at the beginning of the slice.
+///
+/// Regions that overlap are merged as well, although a memory map should not contain any.
+/// The regions past the returned length are left in an unspecified state.
+///
+/// As memory maps only list usable memory, all regions have the same type.
+pub fn merge_adjacent(regions: &mut [MemoryRange]) -> usize {
+    regions.sort_unstable_by_key(MemoryRange::start);
+
+    let mut len = 0_usize;
+    for i in 0..regions.len() {
+        let region = regions[i];
+        if let Some(last) = len.checked_sub(1).map(|last| &mut regions[last])
+            && region.start <= last.end.saturating_add(1)
+        {
+            last.end = last.end.max(region.end);
+        } else {
+            regions[len] = region;
+            len += 1;
+        }
+    }
+    len
+}
+
+/// Removes `reserved` from the first `len` regions, returning the number of regions left
+/// at the beginning of the slice.
+///
+/// The order of the regions is not kept. A region that strictly contains `reserved`
+/// is split in two, which takes one more slot of the slice.
+///
+/// # Panics
+///
+/// Panics if there is no slot left to split a region.
+pub fn carve_out(regions: &mut [MemoryRange], mut len: usize, reserved: &MemoryRange) -> usize {
+    let mut i = 0;
+    while i < len {
+        let region = regions[i];
+        if region.end < reserved.start || region.start > reserved.end {
+            i += 1;
+            continue;
+        }
+
+        let before = (region.start < reserved.start)
+            .then(|| MemoryRange::new(region.start, reserved.start - 1));
+        let after =
+            (region.end > reserved.end).then(|| MemoryRange::new(reserved.end + 1, region.end));
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                assert!(len < regions.len(), "No slot left to split a memory range");
+                regions[i] = before;
+                regions[len] = after;
+                len += 1;
+                i += 1;
+            }
+            (Some(rest), None) | (None, Some(rest)) => {
+                regions[i] = rest;
+                i += 1;
+            }
+            (None, None) => {
+                // The region is entirely reserved, check the one swapped in its place
+                len -= 1;
+                regions.swap(i, len);
+            }
+        }
+    }
+    len
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_merge_adjacent() {
+        let mut regions = [
+            MemoryRange::new(0x3000, 0x3FFF),
+            MemoryRange::new(0x1000, 0x1FFF),
+            MemoryRange::new(0x8000, 0x8FFF),
+            MemoryRange::new(0x2000, 0x2FFF),
+        ];
+        let len = merge_adjacent(&mut regions);
+        assert_eq!(
+            regions[..len],
+            [
+                MemoryRange::new(0x1000, 0x3FFF),
+                MemoryRange::new(0x8000, 0x8FFF),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_adjacent_keeps_gaps() {
+        // A single byte separates the regions
+        let mut regions = [
+            MemoryRange::new(0x2001, 0x2FFF),
+            MemoryRange::new(0x1000, 0x1FFF),
+        ];
+        let len = merge_adjacent(&mut regions);
+        assert_eq!(
+            regions[..len],
+            [
+                MemoryRange::new(0x1000, 0x1FFF),
+                MemoryRange::new(0x2001, 0x2FFF),
+            ]
+        );
+
+        let mut empty = [];
+        assert_eq!(merge_adjacent(&mut empty), 0);
+    }
+
+    #[test]
+    fn test_merge_adjacent_overlapping() {
+        let mut regions = [
+            MemoryRange::new(0x1000, 0x2FFF),
+            MemoryRange::new(0x1800, 0x1FFF),
+            MemoryRange::new(0x2800, 0x4FFF),
+            MemoryRange::new(0, u64::MAX - 1),
+            MemoryRange::new(u64::MAX - 1, u64::MAX),
+        ];
+        let len = merge_adjacent(&mut regions);
+        assert_eq!(regions[..len], [MemoryRange::new(0, u64::MAX)]);
+    }
+
+    #[test]
+    fn test_carve_out() {
+        let mut regions = [
+            MemoryRange::new(0x1000, 0x4FFF),
+            MemoryRange::new(0x6000, 0x6FFF),
+            MemoryRange::new(0x8000, 0x9FFF),
+            MemoryRange::default(),
+        ];
+        let reserved = [
+            // Strictly inside the first region
+            MemoryRange::new(0x2000, 0x2FFF),
+            // Covers the second region and the start of the third
+            MemoryRange::new(0x5800, 0x8FFF),
+        ];
+
+        let mut len = 3;
+        for reserved in &reserved {
+            len = carve_out(&mut regions, len, reserved);
+        }
+        let len = merge_adjacent(&mut regions[..len]);
+        assert_eq!(
+            regions[..len],
+            [
+                MemoryRange::new(0x1000, 0x1FFF),
+                MemoryRange::new(0x3000, 0x4FFF),
+                MemoryRange::new(0x9000, 0x9FFF),
+            ]
+        );
+        for region in &regions[..len] {
+            for reserved in &reserved {
+                assert!(region.end() < reserved.start() || region.start() > reserved.end());
+            }
+        }
+    }
+
+    #[test]
+    fn test_carve_out_whole_regions() {
+        let mut regions = [
+            MemoryRange::new(0x1000, 0x1FFF),
+            MemoryRange::new(0x2000, 0x2FFF),
+            MemoryRange::new(0x4000, 0x4FFF),
+        ];
+        let len = carve_out(&mut regions, 3, &MemoryRange::new(0, 0x2FFF));
+        assert_eq!(regions[..len], [MemoryRange::new(0x4000, 0x4FFF)]);
+
+        // Reserving nothing usable leaves the regions as they are
+        let len = carve_out(&mut regions, len, &MemoryRange::new(0x5000, 0x5FFF));
+        assert_eq!(regions[..len], [MemoryRange::new(0x4000, 0x4FFF)]);
+    }
+
+    #[test]
+    #[should_panic = "No slot left to split a memory range"]
+    fn test_carve_out_full() {
+        let mut regions = [MemoryRange::new(0x1000, 0x3FFF)];
+        let _ = carve_out(&mut regions, 1, &MemoryRange::new(0x2000, 0x2FFF));
+    }
+
     #[test]
     fn test_memory_range_new() {
         let range = MemoryRange::new(0, 10);
@@ -421,6 +632,111 @@ mod tests {
         assert_eq!(addr % 8, 0);
     }
 
+    #[test]
+    fn test_memory_ranges_allocate_contiguous_frames() {
+        const FRAME: u64 = 4096;
+
+        let mut ranges = MemoryRanges::<10>::new();
+        // 3 free frames at an unaligned address, then 8 free frames starting at 64KiB
+        ranges.insert(MemoryRange::new(FRAME, 4 * FRAME - 1));
+        ranges.insert(MemoryRange::new(16 * FRAME, 24 * FRAME - 1));
+
+        let addr = ranges.allocate(4 * FRAME, Alignment::Align16K).unwrap();
+        assert!(Alignment::Align16K.is_aligned(addr));
+        assert!(addr >= 16 * FRAME && addr + 4 * FRAME <= 24 * FRAME);
+
+        // The run must not be split across free ranges
+        assert_eq!(ranges.sum(), 7 * FRAME);
+        assert!(ranges.allocate(5 * FRAME, Alignment::Align4K).is_none());
+        assert_eq!(ranges.sum(), 7 * FRAME);
+    }
+
+    #[test]
+    fn test_allocate_frames_aligned() {
+        use crate::arch::paging::M4KiB;
+
+        let mut ranges = MemoryRanges::<10>::new();
+        // Frames 1 to 39, so that the first 64KiB-aligned run starts at frame 16
+        ranges.insert(MemoryRange::new(0x1000, 0x2_7FFF));
+
+        let first = ranges
+            .allocate_frames::<M4KiB>(4, Alignment::Align64K)
+            .unwrap();
+        assert_eq!(first.start_address().as_u64(), 0x1_0000);
+        assert_eq!(ranges.sum(), 35 * M4KiB::SIZE);
+
+        // A smaller alignment than the frame size still gives frame-aligned runs
+        let first = ranges
+            .allocate_frames::<M4KiB>(2, Alignment::Align128)
+            .unwrap();
+        assert!(Alignment::Align4K.is_aligned(first.start_address().as_u64()));
+    }
+
+    #[test]
+    fn test_allocate_frames_no_run() {
+        use crate::arch::paging::M4KiB;
+
+        let mut ranges = MemoryRanges::<10>::new();
+        // 6 free frames, but at most 3 of them are contiguous
+        ranges.insert(MemoryRange::new(0x1000, 0x3FFF));
+        ranges.insert(MemoryRange::new(0x5000, 0x7FFF));
+
+        assert!(
+            ranges
+                .allocate_frames::<M4KiB>(4, Alignment::Align4K)
+                .is_none()
+        );
+        assert!(
+            ranges
+                .allocate_frames::<M4KiB>(0, Alignment::Align4K)
+                .is_none()
+        );
+        assert!(
+            ranges
+                .allocate_frames::<M4KiB>(u64::MAX, Alignment::Align4K)
+                .is_none()
+        );
+        // No frame was taken by the failed attempts
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges.sum(), 6 * M4KiB::SIZE);
+
+        assert!(
+            ranges
+                .allocate_frames::<M4KiB>(3, Alignment::Align4K)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_allocate_frames_reuse() {
+        use crate::arch::paging::M4KiB;
+
+        let mut ranges = MemoryRanges::<10>::new();
+        ranges.insert(MemoryRange::new(0x1_0000, 0x1_FFFF));
+
+        let first = ranges
+            .allocate_frames::<M4KiB>(16, Alignment::Align64K)
+            .unwrap();
+        assert!(ranges.is_empty());
+        assert!(
+            ranges
+                .allocate_frames::<M4KiB>(1, Alignment::Align4K)
+                .is_none()
+        );
+
+        ranges.free_frames(first, 16);
+        assert_eq!(ranges.entries(), &[MemoryRange::new(0x1_0000, 0x1_FFFF)]);
+
+        let again = ranges
+            .allocate_frames::<M4KiB>(16, Alignment::Align64K)
+            .unwrap();
+        assert_eq!(again, first);
+
+        // Freeing nothing is a no-op
+        ranges.free_frames(again, 0);
+        assert!(ranges.is_empty());
+    }
+
     #[test]
     fn test_memory_ranges_intersection() {
         let mut ranges1 = MemoryRanges::<10>::new();
@@ -0,0 +1,543 @@
+//! Virtual memory areas of a process.
+//!
+//! A VMA is a range of the address space that the process intends to use, along with
+//! its permissions and what backs it. Page tables only tell what is mapped right now,
+//! while VMAs tell what may be mapped, which is what servicing page faults requires.
+//!
+//! VMAs never overlap. Adjacent compatible VMAs are merged on insertion,
+//! and removing part of a VMA splits it.
+use super::{
+    fault::{Region, RegionKind},
+    ranges::MemoryRange,
+};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum VmaError {
+    #[error("The area overlaps an existing one")]
+    Overlap,
+    #[error("Too many areas")]
+    Full,
+    #[error("The range is not entirely covered by areas")]
+    NotMapped,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Access permissions of a VMA.
+pub struct VmaFlags(u8);
+
+impl VmaFlags {
+    pub const NONE: Self = Self(0);
+    pub const READ: Self = Self(1);
+    pub const WRITE: Self = Self(1 << 1);
+    pub const EXECUTE: Self = Self(1 << 2);
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl core::ops::BitOr for VmaFlags {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(rhs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What backs the pages of a VMA.
+pub enum Backing {
+    /// Zero-filled private memory.
+    Anonymous,
+    /// Private copy of a file, starting at `offset` in the file.
+    File { file: u64, offset: u64 },
+    /// Frames shared with other address spaces.
+    Shared,
+    /// Stack of a thread, mapped on demand as it grows down.
+    ///
+    /// The area spans the maximum size of the stack.
+    Stack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A virtual memory area.
+pub struct Vma {
+    range: MemoryRange,
+    flags: VmaFlags,
+    backing: Backing,
+}
+
+impl Vma {
+    #[must_use]
+    #[inline]
+    pub const fn new(range: MemoryRange, flags: VmaFlags, backing: Backing) -> Self {
+        Self {
+            range,
+            flags,
+            backing,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn range(&self) -> MemoryRange {
+        self.range
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn flags(&self) -> VmaFlags {
+        self.flags
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn backing(&self) -> Backing {
+        self.backing
+    }
+
+    #[must_use]
+    #[inline]
+    /// Describes the VMA for the page fault handler.
+    pub const fn region(&self) -> Region {
+        Region {
+            kind: match self.backing {
+                Backing::Anonymous => RegionKind::Anonymous,
+                // Pages of a file are private: they are copied when written.
+                Backing::File { .. } => RegionKind::CopyOnWrite,
+                Backing::Shared => RegionKind::Shared,
+                Backing::Stack => RegionKind::Stack,
+            },
+            readable: self.flags.contains(VmaFlags::READ),
+            writable: self.flags.contains(VmaFlags::WRITE),
+            executable: self.flags.contains(VmaFlags::EXECUTE),
+        }
+    }
+
+    /// Returns the part of the VMA that lies within `start..=end`, if any.
+    #[must_use]
+    fn slice(&self, start: u64, end: u64) -> Option<Self> {
+        let start = start.max(self.range.start());
+        let end = end.min(self.range.end());
+        if start > end {
+            return None;
+        }
+        let backing = match self.backing {
+            Backing::File { file, offset } => Backing::File {
+                file,
+                offset: offset + (start - self.range.start()),
+            },
+            backing => backing,
+        };
+        Some(Self::new(MemoryRange::new(start, end), self.flags, backing))
+    }
+
+    /// Returns whether `next` directly follows the VMA and can be merged into it.
+    #[must_use]
+    fn merges_with(&self, next: &Self) -> bool {
+        if self.flags != next.flags || self.range.end().checked_add(1) != Some(next.range.start()) {
+            return false;
+        }
+        match (self.backing, next.backing) {
+            (Backing::Anonymous, Backing::Anonymous) | (Backing::Shared, Backing::Shared) => true,
+            (
+                Backing::File { file, offset },
+                Backing::File {
+                    file: next_file,
+                    offset: next_offset,
+                },
+            ) => file == next_file && offset + self.range.size() == next_offset,
+            // Stacks are never merged, as each one is bounded by its own area.
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// An array-backed set of non-overlapping VMAs, sorted by address.
+pub struct VmaSet<const N: usize> {
+    vmas: [Option<Vma>; N],
+    used: usize,
+}
+
+impl<const N: usize> Default for VmaSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> VmaSet<N> {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            vmas: [None; N],
+            used: 0,
+        }
+    }
+
+    /// Iterates over the VMAs, by increasing address.
+    pub fn iter(&self) -> impl Iterator<Item = &Vma> {
+        self.vmas[..self.used].iter().flatten()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.used
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.used == 0
+    }
+
+    #[must_use]
+    #[inline]
+    const fn get(&self, index: usize) -> &Vma {
+        self.vmas[index].as_ref().unwrap()
+    }
+
+    /// Index of the first VMA ending at or after `addr`.
+    #[must_use]
+    fn partition(&self, addr: u64) -> usize {
+        self.vmas[..self.used].partition_point(|vma| vma.is_some_and(|vma| vma.range.end() < addr))
+    }
+
+    #[must_use]
+    /// Returns the VMA containing `addr`.
+    pub fn lookup(&self, addr: u64) -> Option<&Vma> {
+        let index = self.partition(addr);
+        (index < self.used)
+            .then(|| self.get(index))
+            .filter(|vma| vma.range.start() <= addr)
+    }
+
+    #[must_use]
+    /// Returns whether any VMA overlaps `range`.
+    pub fn overlaps(&self, range: MemoryRange) -> bool {
+        let index = self.partition(range.start());
+        index < self.used && self.get(index).range.start() <= range.end()
+    }
+
+    fn insert_at(&mut self, index: usize, vma: Vma) -> Result<(), VmaError> {
+        if self.used == N {
+            return Err(VmaError::Full);
+        }
+        self.vmas[index..=self.used].rotate_right(1);
+        self.vmas[index] = Some(vma);
+        self.used += 1;
+        Ok(())
+    }
+
+    fn remove_at(&mut self, index: usize) {
+        self.vmas[index] = None;
+        self.vmas[index..self.used].rotate_left(1);
+        self.used -= 1;
+    }
+
+    /// Inserts a VMA, merging it with its neighbors if they are compatible.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the VMA overlaps an existing one, or if there is no room left.
+    pub fn insert(&mut self, mut vma: Vma) -> Result<(), VmaError> {
+        if self.overlaps(vma.range) {
+            return Err(VmaError::Overlap);
+        }
+        let mut index = self.partition(vma.range.start());
+
+        if index > 0 && self.get(index - 1).merges_with(&vma) {
+            index -= 1;
+            let prev = *self.get(index);
+            vma = Vma::new(
+                MemoryRange::new(prev.range.start(), vma.range.end()),
+                prev.flags,
+                prev.backing,
+            );
+            self.remove_at(index);
+        }
+        if index < self.used && vma.merges_with(self.get(index)) {
+            let next = *self.get(index);
+            vma.range = MemoryRange::new(vma.range.start(), next.range.end());
+            self.remove_at(index);
+        }
+
+        self.insert_at(index, vma)
+    }
+
+    /// Removes the range `range` from the set.
+    ///
+    /// VMAs partially covered by the range are shrunk, and a VMA strictly containing
+    /// the range is split in two. Parts of the range not covered by any VMA are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Splitting a VMA needs room for one more, in which case nothing is removed
+    /// if there is none.
+    pub fn remove(&mut self, range: MemoryRange) -> Result<(), VmaError> {
+        let index = self.partition(range.start());
+        if index < self.used {
+            let vma = *self.get(index);
+            if vma.range.start() < range.start() && vma.range.end() > range.end() {
+                if self.used == N {
+                    return Err(VmaError::Full);
+                }
+                let head = vma.slice(vma.range.start(), range.start() - 1).unwrap();
+                let tail = vma.slice(range.end() + 1, vma.range.end()).unwrap();
+                self.vmas[index] = Some(head);
+                return self.insert_at(index + 1, tail);
+            }
+        }
+
+        let mut index = index;
+        while index < self.used && self.get(index).range.start() <= range.end() {
+            let vma = *self.get(index);
+            let head = range
+                .start()
+                .checked_sub(1)
+                .and_then(|end| vma.slice(vma.range.start(), end));
+            let tail = range
+                .end()
+                .checked_add(1)
+                .and_then(|start| vma.slice(start, vma.range.end()));
+            // Only one of them can be kept, as the VMA does not strictly contain the range.
+            if let Some(rest) = head.or(tail) {
+                self.vmas[index] = Some(rest);
+                index += 1;
+            } else {
+                self.remove_at(index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the permissions of the range `range`, splitting VMAs as needed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the range is not entirely covered by VMAs, or if there is no room
+    /// for the split VMAs. Nothing is changed on failure.
+    pub fn protect(&mut self, range: MemoryRange, flags: VmaFlags) -> Result<(), VmaError> {
+        let mut copy = *self;
+
+        let mut covered = range.start();
+        let first = self.partition(range.start());
+        for vma in self.vmas[first..self.used].iter().flatten() {
+            if vma.range.start() > range.end() {
+                break;
+            }
+            if vma.range.start() > covered {
+                return Err(VmaError::NotMapped);
+            }
+            covered = vma.range.end().saturating_add(1);
+        }
+        if covered <= range.end() {
+            return Err(VmaError::NotMapped);
+        }
+
+        copy.remove(range)?;
+        for vma in self.vmas[first..self.used].iter().flatten() {
+            let Some(mut part) = vma.slice(range.start(), range.end()) else {
+                break;
+            };
+            part.flags = flags;
+            copy.insert(part)?;
+        }
+
+        *self = copy;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RW: VmaFlags = VmaFlags::READ.union(VmaFlags::WRITE);
+
+    fn anon(start: u64, end: u64) -> Vma {
+        Vma::new(MemoryRange::new(start, end), RW, Backing::Anonymous)
+    }
+
+    fn ranges<const N: usize>(set: &VmaSet<N>) -> impl Iterator<Item = (u64, u64)> {
+        set.iter().map(|vma| (vma.range.start(), vma.range.end()))
+    }
+
+    #[test]
+    fn test_insert_lookup() {
+        let mut set = VmaSet::<8>::new();
+        assert!(set.is_empty());
+
+        set.insert(anon(0x3000, 0x3FFF)).unwrap();
+        set.insert(anon(0x1000, 0x1FFF)).unwrap();
+        assert_eq!(set.len(), 2);
+
+        assert_eq!(set.lookup(0x1000), Some(&anon(0x1000, 0x1FFF)));
+        assert_eq!(set.lookup(0x1FFF), Some(&anon(0x1000, 0x1FFF)));
+        assert_eq!(set.lookup(0x2000), None);
+        assert_eq!(set.lookup(0x3800), Some(&anon(0x3000, 0x3FFF)));
+        assert_eq!(set.lookup(0x4000), None);
+        assert_eq!(set.lookup(0), None);
+
+        // Sorted by address
+        assert!(ranges(&set).eq([(0x1000, 0x1FFF), (0x3000, 0x3FFF)]));
+    }
+
+    #[test]
+    fn test_overlap_rejected() {
+        let mut set = VmaSet::<8>::new();
+        set.insert(anon(0x2000, 0x3FFF)).unwrap();
+
+        assert_eq!(set.insert(anon(0x1000, 0x2000)), Err(VmaError::Overlap));
+        assert_eq!(set.insert(anon(0x3FFF, 0x4FFF)), Err(VmaError::Overlap));
+        assert_eq!(set.insert(anon(0x2800, 0x2FFF)), Err(VmaError::Overlap));
+        assert_eq!(set.insert(anon(0x1000, 0x4FFF)), Err(VmaError::Overlap));
+        assert_eq!(set.len(), 1);
+
+        let mut full = VmaSet::<1>::new();
+        full.insert(anon(0x1000, 0x1FFF)).unwrap();
+        let code = Vma::new(
+            MemoryRange::new(0x5000, 0x5FFF),
+            VmaFlags::READ | VmaFlags::EXECUTE,
+            Backing::Anonymous,
+        );
+        assert_eq!(full.insert(code), Err(VmaError::Full));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut set = VmaSet::<8>::new();
+        set.insert(anon(0x1000, 0x1FFF)).unwrap();
+        set.insert(anon(0x3000, 0x3FFF)).unwrap();
+        // Fills the gap, all three are merged
+        set.insert(anon(0x2000, 0x2FFF)).unwrap();
+        assert!(ranges(&set).eq([(0x1000, 0x3FFF)]));
+
+        // Different permissions are not merged
+        let ro = Vma::new(
+            MemoryRange::new(0x4000, 0x4FFF),
+            VmaFlags::READ,
+            Backing::Anonymous,
+        );
+        set.insert(ro).unwrap();
+        assert_eq!(set.len(), 2);
+
+        // Contiguous parts of a file are merged, others are not
+        let file = |start, offset| {
+            Vma::new(
+                MemoryRange::new(start, start + 0xFFF),
+                VmaFlags::READ,
+                Backing::File { file: 3, offset },
+            )
+        };
+        let mut set = VmaSet::<8>::new();
+        set.insert(file(0x1000, 0)).unwrap();
+        set.insert(file(0x2000, 0x1000)).unwrap();
+        set.insert(file(0x3000, 0x5000)).unwrap();
+        assert!(ranges(&set).eq([(0x1000, 0x2FFF), (0x3000, 0x3FFF)]));
+    }
+
+    #[test]
+    fn test_split_on_remove() {
+        let mut set = VmaSet::<8>::new();
+        let file = Vma::new(
+            MemoryRange::new(0x1000, 0x4FFF),
+            VmaFlags::READ,
+            Backing::File { file: 1, offset: 0 },
+        );
+        set.insert(file).unwrap();
+
+        // Punch a hole in the middle
+        set.remove(MemoryRange::new(0x2000, 0x2FFF)).unwrap();
+        assert!(ranges(&set).eq([(0x1000, 0x1FFF), (0x3000, 0x4FFF)]));
+        // The offset of the second half follows
+        assert_eq!(
+            set.lookup(0x3000).unwrap().backing(),
+            Backing::File {
+                file: 1,
+                offset: 0x2000
+            }
+        );
+
+        // Remove across both halves
+        set.remove(MemoryRange::new(0x1800, 0x3FFF)).unwrap();
+        assert!(ranges(&set).eq([(0x1000, 0x17FF), (0x4000, 0x4FFF)]));
+
+        // Remove everything
+        set.remove(MemoryRange::new(0, 0xFFFF)).unwrap();
+        assert!(set.is_empty());
+
+        // No room to split
+        let mut full = VmaSet::<1>::new();
+        full.insert(anon(0x1000, 0x3FFF)).unwrap();
+        assert_eq!(
+            full.remove(MemoryRange::new(0x2000, 0x2FFF)),
+            Err(VmaError::Full)
+        );
+        assert!(ranges(&full).eq([(0x1000, 0x3FFF)]));
+        // Shrinking needs no room
+        full.remove(MemoryRange::new(0x3000, 0x3FFF)).unwrap();
+        assert!(ranges(&full).eq([(0x1000, 0x2FFF)]));
+    }
+
+    #[test]
+    fn test_protect() {
+        let mut set = VmaSet::<8>::new();
+        set.insert(anon(0x1000, 0x3FFF)).unwrap();
+
+        set.protect(MemoryRange::new(0x2000, 0x2FFF), VmaFlags::READ)
+            .unwrap();
+        assert!(ranges(&set).eq([(0x1000, 0x1FFF), (0x2000, 0x2FFF), (0x3000, 0x3FFF)]));
+        assert_eq!(set.lookup(0x2000).unwrap().flags(), VmaFlags::READ);
+        assert_eq!(set.lookup(0x3000).unwrap().flags(), RW);
+
+        // Restoring the permissions merges the VMAs back
+        set.protect(MemoryRange::new(0x2000, 0x2FFF), RW).unwrap();
+        assert!(ranges(&set).eq([(0x1000, 0x3FFF)]));
+
+        // Not fully covered
+        assert_eq!(
+            set.protect(MemoryRange::new(0x3000, 0x4FFF), VmaFlags::READ),
+            Err(VmaError::NotMapped)
+        );
+        assert!(ranges(&set).eq([(0x1000, 0x3FFF)]));
+    }
+
+    #[test]
+    fn test_region() {
+        let region = anon(0x1000, 0x1FFF).region();
+        assert_eq!(region.kind, RegionKind::Anonymous);
+        assert!(region.readable && region.writable && !region.executable);
+
+        let file = Vma::new(
+            MemoryRange::new(0x1000, 0x1FFF),
+            VmaFlags::READ | VmaFlags::EXECUTE,
+            Backing::File { file: 0, offset: 0 },
+        );
+        assert_eq!(file.region().kind, RegionKind::CopyOnWrite);
+        assert!(!file.region().writable && file.region().executable);
+    }
+
+    #[test]
+    fn test_stacks_not_merged() {
+        let stack =
+            |start: u64| Vma::new(MemoryRange::new(start, start + 0xFFF), RW, Backing::Stack);
+        let mut set = VmaSet::<8>::new();
+        set.insert(stack(0x1000)).unwrap();
+        set.insert(stack(0x2000)).unwrap();
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.lookup(0x1800).unwrap().region().kind, RegionKind::Stack);
+    }
+}
@@ -1,7 +1,15 @@
 use crate::time::{Duration, Instant};
 use core::sync::atomic::{AtomicU64, Ordering};
 
+pub mod accounting;
+pub mod affinity;
 pub mod binary;
+pub mod command;
+pub mod env;
+pub mod inheritance;
+pub mod join;
+pub mod signal;
+pub mod tls;
 
 /// A token that identifies a sleepable event.
 ///
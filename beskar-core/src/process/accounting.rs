@@ -0,0 +1,273 @@
+//! CPU and memory usage accounting.
+//!
+//! CPU time is measured with a free-running counter, read when a thread is
+//! switched in and out. The time of every thread of a process is summed.
+//!
+//! The time slice of a thread is counted in timer ticks, charged to the thread
+//! that is running when the timer fires.
+use core::sync::atomic::{AtomicU64, Ordering};
+
+#[must_use]
+#[inline]
+/// Returns the number of ticks between two reads of a `width`-bit counter.
+///
+/// The counter may have wrapped around (at most once) between the two reads.
+pub const fn ticks_between(start: u64, end: u64, width: u32) -> u64 {
+    let mask = if width >= u64::BITS {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    };
+    end.wrapping_sub(start) & mask
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// CPU time of a single thread.
+pub struct CpuTimer {
+    /// Counter value when the thread was last switched in, if it is running.
+    switched_in: Option<u64>,
+    /// Total ticks spent running.
+    total: u64,
+    /// Width of the counter, in bits.
+    width: u32,
+}
+
+impl Default for CpuTimer {
+    fn default() -> Self {
+        Self::new(u64::BITS)
+    }
+}
+
+impl CpuTimer {
+    #[must_use]
+    #[inline]
+    /// Creates a timer for a `width`-bit counter.
+    pub const fn new(width: u32) -> Self {
+        Self {
+            switched_in: None,
+            total: 0,
+            width,
+        }
+    }
+
+    #[inline]
+    /// Records that the thread starts running.
+    pub const fn switch_in(&mut self, now: u64) {
+        self.switched_in = Some(now);
+    }
+
+    #[inline]
+    /// Records that the thread stops running.
+    ///
+    /// Returns the ticks spent running since the last `switch_in`,
+    /// or 0 if the thread was not running.
+    pub const fn switch_out(&mut self, now: u64) -> u64 {
+        let Some(start) = self.switched_in.take() else {
+            return 0;
+        };
+        let ran = ticks_between(start, now, self.width);
+        self.total = self.total.saturating_add(ran);
+        ran
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_running(&self) -> bool {
+        self.switched_in.is_some()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Total ticks spent running, not including the current run.
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Time slice of a thread, counted in timer ticks.
+///
+/// Once a thread has been running for its whole quantum, it is preempted and gets
+/// a full quantum for its next run. A thread that stops running before then,
+/// for instance to sleep, keeps the rest of its quantum.
+pub struct TimeSlice {
+    /// Ticks charged since the quantum was last refilled.
+    used: u32,
+}
+
+impl TimeSlice {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self { used: 0 }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Charges a timer tick to the running thread.
+    ///
+    /// Returns true if the quantum of `quantum` ticks is used up,
+    /// in which case the slice is refilled for the next run.
+    /// A null quantum expires on every tick.
+    pub const fn tick(&mut self, quantum: u32) -> bool {
+        self.used += 1;
+        let expired = self.used >= quantum;
+        if expired {
+            self.used = 0;
+        }
+        expired
+    }
+
+    #[must_use]
+    #[inline]
+    /// Ticks left before the quantum of `quantum` ticks is used up.
+    pub const fn remaining(&self, quantum: u32) -> u32 {
+        quantum.saturating_sub(self.used)
+    }
+}
+
+#[derive(Debug, Default)]
+/// An atomic usage counter that saturates instead of wrapping around.
+///
+/// Used for the CPU time of a process, summed over all its threads,
+/// and for the memory resident in an address space.
+pub struct UsageCounter(AtomicU64);
+
+impl UsageCounter {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    #[inline]
+    pub fn add(&self, value: u64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                Some(total.saturating_add(value))
+            });
+    }
+
+    #[inline]
+    pub fn sub(&self, value: u64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                Some(total.saturating_sub(value))
+            });
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticks_between() {
+        assert_eq!(ticks_between(10, 25, 64), 15);
+        assert_eq!(ticks_between(u64::MAX - 4, 5, 64), 10);
+        assert_eq!(ticks_between(0xFFFF_FFF0, 0x10, 32), 0x20);
+        assert_eq!(ticks_between(7, 7, 32), 0);
+    }
+
+    #[test]
+    fn test_context_switch() {
+        let process_time = UsageCounter::new();
+        let mut a = CpuTimer::default();
+        let mut b = CpuTimer::default();
+
+        // Switching out a thread that never ran accounts nothing.
+        assert_eq!(a.switch_out(100), 0);
+
+        a.switch_in(100);
+        assert!(a.is_running());
+
+        // Switch from `a` to `b` at 130.
+        process_time.add(a.switch_out(130));
+        b.switch_in(130);
+        // Switch from `b` back to `a` at 150.
+        process_time.add(b.switch_out(150));
+        a.switch_in(150);
+        process_time.add(a.switch_out(155));
+
+        assert!(!a.is_running());
+        assert_eq!(a.total(), 35);
+        assert_eq!(b.total(), 20);
+        // The time of both threads is summed.
+        assert_eq!(process_time.get(), 55);
+    }
+
+    #[test]
+    fn test_context_switch_wraparound() {
+        let mut timer = CpuTimer::new(32);
+        timer.switch_in(0xFFFF_FFFE);
+        assert_eq!(timer.switch_out(0x3), 5);
+
+        let process_time = UsageCounter::new();
+        process_time.add(u64::MAX - 1);
+        process_time.add(10);
+        assert_eq!(process_time.get(), u64::MAX);
+    }
+
+    #[test]
+    fn test_resident_memory() {
+        let resident = UsageCounter::new();
+        resident.add(4 * 4096);
+        resident.add(2 * 4096);
+        resident.sub(3 * 4096);
+        assert_eq!(resident.get(), 3 * 4096);
+        resident.sub(10 * 4096);
+        assert_eq!(resident.get(), 0);
+    }
+
+    #[test]
+    fn test_time_slice() {
+        let mut slice = TimeSlice::new();
+        assert_eq!(slice.remaining(3), 3);
+
+        // The quantum expires on its last tick, and is refilled
+        assert!(!slice.tick(3));
+        assert!(!slice.tick(3));
+        assert_eq!(slice.remaining(3), 1);
+        assert!(slice.tick(3));
+        assert_eq!(slice.remaining(3), 3);
+
+        // A single-tick quantum expires on every tick
+        assert!(slice.tick(1));
+        assert!(slice.tick(1));
+        assert!(slice.tick(0));
+    }
+
+    #[test]
+    fn test_time_slice_credit() {
+        // Two threads share a CPU: `a` sleeps after one tick, `b` never blocks.
+        let mut a = TimeSlice::new();
+        let mut b = TimeSlice::new();
+
+        assert!(!a.tick(4));
+        // `a` sleeps before the next tick, and `b` runs its whole quantum.
+        for _ in 0..3 {
+            assert!(!b.tick(4));
+        }
+        assert!(b.tick(4));
+        assert_eq!(b.remaining(4), 4);
+
+        // `a` resumes with the rest of its quantum, not less
+        assert_eq!(a.remaining(4), 3);
+        assert!(!a.tick(4));
+        assert!(!a.tick(4));
+        assert!(a.tick(4));
+
+        // A quantum shortened while the thread runs expires at the next tick
+        assert!(!a.tick(4));
+        assert!(!a.tick(4));
+        assert!(a.tick(2));
+    }
+}
@@ -0,0 +1,269 @@
+//! CPU affinity of threads.
+//!
+//! A thread that may run on every online core is queued on the run queue shared
+//! by all cores. A thread restricted to some cores is queued on the run queue of
+//! one of them, its home core. Another core may only take it from there, to run it,
+//! if the mask allows that core.
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum AffinityError {
+    #[error("The mask does not allow any online core")]
+    NoOnlineCore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+/// A set of cores, where bit `n` stands for the core of ID `n`.
+pub struct CpuMask(u64);
+
+impl Default for CpuMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl CpuMask {
+    /// Every core, which is the default affinity of a thread.
+    ///
+    /// Unlike other masks, it also allows the cores beyond the 64 first ones.
+    pub const ALL: Self = Self(u64::MAX);
+
+    #[must_use]
+    #[inline]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn raw(self) -> u64 {
+        self.0
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns a mask that only allows `core`.
+    pub const fn single(core: usize) -> Self {
+        if core < u64::BITS as usize {
+            Self(1 << core)
+        } else {
+            Self(0)
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn allows(self, core: usize) -> bool {
+        if core < u64::BITS as usize {
+            self.0 & (1 << core) != 0
+        } else {
+            self.0 == Self::ALL.0
+        }
+    }
+
+    /// Checks that the mask allows at least one of the `online` cores, with IDs `0..online`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AffinityError::NoOnlineCore` if it does not.
+    pub const fn check(self, online: usize) -> Result<(), AffinityError> {
+        if self.first_allowed(online).is_some() {
+            Ok(())
+        } else {
+            Err(AffinityError::NoOnlineCore)
+        }
+    }
+
+    #[must_use]
+    /// Returns the core whose run queue holds a thread with this mask,
+    /// when it is made ready on `current` with `online` cores.
+    ///
+    /// `None` stands for the shared run queue, used if every online core is allowed.
+    /// Otherwise, `current` is preferred to keep caches warm, then the first allowed core.
+    pub const fn home_core(self, current: usize, online: usize) -> Option<usize> {
+        if self.allows_all(online) {
+            None
+        } else if self.allows(current) {
+            Some(current)
+        } else {
+            self.first_allowed(online)
+        }
+    }
+
+    #[must_use]
+    const fn allows_all(self, online: usize) -> bool {
+        if online >= u64::BITS as usize {
+            self.0 == Self::ALL.0
+        } else {
+            let online_mask = (1 << online) - 1;
+            self.0 & online_mask == online_mask
+        }
+    }
+
+    #[must_use]
+    const fn first_allowed(self, online: usize) -> Option<usize> {
+        let core = self.0.trailing_zeros() as usize;
+        if self.0 != 0 && core < online {
+            Some(core)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask() {
+        let mask = CpuMask::from_raw(0b1010);
+        assert!(!mask.allows(0));
+        assert!(mask.allows(1));
+        assert!(mask.allows(3));
+        assert!(!mask.allows(64));
+        assert!(CpuMask::ALL.allows(200));
+        assert_eq!(CpuMask::single(2).raw(), 0b100);
+        assert_eq!(CpuMask::single(64).raw(), 0);
+        assert_eq!(CpuMask::default(), CpuMask::ALL);
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(CpuMask::ALL.check(1), Ok(()));
+        assert_eq!(CpuMask::from_raw(0b100).check(4), Ok(()));
+        // Only cores 0 and 1 are online
+        assert_eq!(
+            CpuMask::from_raw(0b100).check(2),
+            Err(AffinityError::NoOnlineCore)
+        );
+        assert_eq!(
+            CpuMask::from_raw(0).check(4),
+            Err(AffinityError::NoOnlineCore)
+        );
+    }
+
+    #[test]
+    fn test_home_core() {
+        assert_eq!(CpuMask::ALL.home_core(3, 4), None);
+        // A mask allowing every online core does not pin the thread
+        assert_eq!(CpuMask::from_raw(0b11).home_core(1, 2), None);
+
+        let mask = CpuMask::from_raw(0b0110);
+        assert_eq!(mask.home_core(2, 4), Some(2));
+        assert_eq!(mask.home_core(0, 4), Some(1));
+        assert_eq!(mask.home_core(3, 4), Some(1));
+    }
+
+    const CORES: usize = 4;
+
+    /// Run queues of the simulation, holding thread indices in FIFO order.
+    struct RunQueues<const THREADS: usize> {
+        shared: [Option<usize>; THREADS],
+        pinned: [[Option<usize>; THREADS]; CORES],
+    }
+
+    impl<const THREADS: usize> RunQueues<THREADS> {
+        fn make_ready(&mut self, thread: usize, mask: CpuMask, current: usize) {
+            let queue = match mask.home_core(current, CORES) {
+                Some(core) => &mut self.pinned[core],
+                None => &mut self.shared,
+            };
+            *queue.iter_mut().find(|slot| slot.is_none()).unwrap() = Some(thread);
+        }
+
+        /// A core takes from its own run queue first, then from the shared one,
+        /// then steals a thread it is allowed to run from the run queue of another core.
+        fn pop(&mut self, core: usize, masks: &[CpuMask; THREADS]) -> Option<usize> {
+            fn pop<const THREADS: usize>(queue: &mut [Option<usize>; THREADS]) -> Option<usize> {
+                let thread = queue[0].take()?;
+                queue.rotate_left(1);
+                Some(thread)
+            }
+
+            pop(&mut self.pinned[core])
+                .or_else(|| pop(&mut self.shared))
+                .or_else(|| {
+                    (0..CORES)
+                        .filter(|&victim| victim != core)
+                        .find_map(|victim| {
+                            let queue = &mut self.pinned[victim];
+                            let thread = pop(queue)?;
+                            if masks[thread].allows(core) {
+                                Some(thread)
+                            } else {
+                                // Put back at the end of the queue
+                                *queue.iter_mut().find(|slot| slot.is_none()).unwrap() =
+                                    Some(thread);
+                                None
+                            }
+                        })
+                })
+        }
+    }
+
+    /// Runs threads with the given masks on `CORES` cores,
+    /// and returns the mask of the cores each thread ran on.
+    fn simulate<const THREADS: usize>(masks: [CpuMask; THREADS], ticks: usize) -> [u64; THREADS] {
+        let mut queues = RunQueues {
+            shared: [None; THREADS],
+            pinned: [[None; THREADS]; CORES],
+        };
+        let mut ran_on = [0; THREADS];
+
+        for (thread, mask) in masks.iter().enumerate() {
+            queues.make_ready(thread, *mask, 0);
+        }
+        for tick in 0..ticks {
+            let core = tick % CORES;
+            let Some(thread) = queues.pop(core, &masks) else {
+                continue;
+            };
+            ran_on[thread] |= 1 << core;
+            // The quantum expires, and the thread is requeued from the core it ran on.
+            queues.make_ready(thread, masks[thread], core);
+        }
+
+        ran_on
+    }
+
+    #[test]
+    fn test_affinity_simulation() {
+        let masks = [
+            CpuMask::ALL,
+            CpuMask::single(2),
+            CpuMask::from_raw(0b1001),
+            CpuMask::ALL,
+            CpuMask::single(1),
+        ];
+        let ran_on = simulate(masks, 200);
+
+        for (mask, ran_on) in masks.iter().zip(ran_on) {
+            // Every thread ran, only on its allowed cores
+            assert_ne!(ran_on, 0);
+            assert_eq!(ran_on & !mask.raw(), 0);
+        }
+        assert_eq!(ran_on[1], 0b0100);
+        // A thread allowed on several cores stays on its home core
+        assert_eq!(ran_on[2], 0b0001);
+    }
+
+    #[test]
+    fn test_affinity_steal() {
+        // Every thread is made ready on core 0, so cores 1 to 3 start with empty queues
+        let masks = [
+            CpuMask::from_raw(0b0011),
+            CpuMask::from_raw(0b0011),
+            CpuMask::single(0),
+        ];
+        let ran_on = simulate(masks, 200);
+
+        for (mask, ran_on) in masks.iter().zip(ran_on) {
+            assert_eq!(ran_on & !mask.raw(), 0);
+        }
+        // Core 1 takes work from core 0, but only what it is allowed to run
+        assert_ne!((ran_on[0] | ran_on[1]) & 0b0010, 0);
+        assert_eq!(ran_on[2], 0b0001);
+    }
+}
@@ -0,0 +1,115 @@
+//! Resolution of command names to program paths.
+
+/// Separator between the directories of a `PATH` variable.
+pub const PATH_SEPARATOR: char = ':';
+
+/// Resolves a command name to the path of a program, using the directories of `path_var`.
+///
+/// Names containing a `/` are already qualified and are only checked with `is_file`.
+/// Otherwise, the directories are searched in order, skipping empty entries,
+/// and the first candidate for which `is_file` returns `true` is returned.
+///
+/// The path is built in `buf`, which must be large enough to hold
+/// the longest directory followed by `/` and `name`.
+pub fn resolve_command<'b>(
+    name: &str,
+    path_var: &str,
+    buf: &'b mut [u8],
+    mut is_file: impl FnMut(&str) -> bool,
+) -> Option<&'b str> {
+    if name.is_empty() {
+        return None;
+    }
+
+    if name.contains('/') {
+        let path = buf.get_mut(..name.len())?;
+        path.copy_from_slice(name.as_bytes());
+        return is_file(name).then(|| core::str::from_utf8(path).unwrap());
+    }
+
+    let mut found = None;
+    for dir in path_var
+        .split(PATH_SEPARATOR)
+        .map(|dir| dir.trim_end_matches('/'))
+        .filter(|dir| !dir.is_empty())
+    {
+        let len = dir.len() + 1 + name.len();
+        let Some(path) = buf.get_mut(..len) else {
+            continue;
+        };
+        path[..dir.len()].copy_from_slice(dir.as_bytes());
+        path[dir.len()] = b'/';
+        path[dir.len() + 1..].copy_from_slice(name.as_bytes());
+
+        // The buffer only contains `str` parts.
+        if is_file(core::str::from_utf8(path).unwrap()) {
+            found = Some(len);
+            break;
+        }
+    }
+
+    found.map(|len| core::str::from_utf8(&buf[..len]).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A VFS that only contains regular files at the given paths.
+    fn mock_vfs(files: &[&str]) -> impl FnMut(&str) -> bool {
+        move |path| files.contains(&path)
+    }
+
+    const FILES: &[&str] = &["/ramdisk/bashkar", "/usr/bin/ls", "/bin/ls"];
+
+    #[test]
+    fn test_resolve_second_entry() {
+        let mut buf = [0; 64];
+        let path = resolve_command("bashkar", "/bin:/ramdisk/", &mut buf, mock_vfs(FILES));
+        assert_eq!(path, Some("/ramdisk/bashkar"));
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let mut buf = [0; 64];
+        let path = resolve_command("ls", "::/usr/bin:/bin", &mut buf, mock_vfs(FILES));
+        assert_eq!(path, Some("/usr/bin/ls"));
+
+        let path = resolve_command("ls", "/bin:/usr/bin", &mut buf, mock_vfs(FILES));
+        assert_eq!(path, Some("/bin/ls"));
+    }
+
+    #[test]
+    fn test_resolve_missing() {
+        let mut buf = [0; 64];
+        assert_eq!(
+            resolve_command("cat", "/bin:/usr/bin", &mut buf, mock_vfs(FILES)),
+            None
+        );
+        assert_eq!(resolve_command("ls", "", &mut buf, mock_vfs(FILES)), None);
+        assert_eq!(resolve_command("", "/bin", &mut buf, mock_vfs(FILES)), None);
+    }
+
+    #[test]
+    fn test_resolve_qualified() {
+        let mut buf = [0; 64];
+        // Qualified names do not use PATH
+        assert_eq!(
+            resolve_command("/bin/ls", "/ramdisk", &mut buf, mock_vfs(FILES)),
+            Some("/bin/ls")
+        );
+        assert_eq!(
+            resolve_command("bin/ls", "/", &mut buf, mock_vfs(FILES)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resolve_small_buffer() {
+        let mut buf = [0; 8];
+        assert_eq!(
+            resolve_command("ls", "/usr/bin:/bin", &mut buf, mock_vfs(FILES)),
+            Some("/bin/ls")
+        );
+    }
+}
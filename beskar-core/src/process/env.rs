@@ -0,0 +1,222 @@
+//! Process environment variables.
+//!
+//! The environment is stored as a fixed-size block of `KEY=VALUE\0` entries,
+//! which is also the format used to transfer it to userspace.
+
+/// Maximum size of the environment block, in bytes.
+pub const ENV_MAX_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum EnvError {
+    #[error("Invalid variable name")]
+    InvalidName,
+    #[error("Invalid variable value")]
+    InvalidValue,
+    #[error("Environment is full")]
+    Full,
+}
+
+pub type EnvResult<T> = Result<T, EnvError>;
+
+#[derive(Clone)]
+pub struct Environment {
+    block: [u8; ENV_MAX_SIZE],
+    len: usize,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for Environment {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.vars()).finish()
+    }
+}
+
+impl Environment {
+    #[must_use]
+    #[inline]
+    /// Creates an empty environment.
+    pub const fn new() -> Self {
+        Self {
+            block: [0; ENV_MAX_SIZE],
+            len: 0,
+        }
+    }
+
+    #[must_use]
+    /// Returns the value of a variable, or `None` if it is unset.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.vars().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Sets a variable, replacing its previous value.
+    ///
+    /// Names must be non-empty and cannot contain `=` or NUL, values cannot contain NUL.
+    /// If the environment is full, it is left unchanged.
+    pub fn set(&mut self, key: &str, value: &str) -> EnvResult<()> {
+        if key.is_empty() || key.contains(['=', '\0']) {
+            return Err(EnvError::InvalidName);
+        }
+        if value.contains('\0') {
+            return Err(EnvError::InvalidValue);
+        }
+
+        let entry_len = key.len() + 1 + value.len() + 1;
+        let old_len = self.find(key).map_or(0, |range| range.len());
+        if self.len - old_len + entry_len > ENV_MAX_SIZE {
+            return Err(EnvError::Full);
+        }
+
+        self.unset(key);
+
+        let entry = &mut self.block[self.len..self.len + entry_len];
+        let (k, rest) = entry.split_at_mut(key.len());
+        k.copy_from_slice(key.as_bytes());
+        rest[0] = b'=';
+        rest[1..=value.len()].copy_from_slice(value.as_bytes());
+        rest[value.len() + 1] = 0;
+        self.len += entry_len;
+
+        Ok(())
+    }
+
+    /// Removes a variable.
+    ///
+    /// Returns whether the variable was set.
+    pub fn unset(&mut self, key: &str) -> bool {
+        let Some(range) = self.find(key) else {
+            return false;
+        };
+
+        self.block.copy_within(range.end..self.len, range.start);
+        self.len -= range.len();
+        self.block[self.len..].fill(0);
+        true
+    }
+
+    /// Iterates over the variables, as `(name, value)` pairs.
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        parse_block(self.as_bytes())
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the raw environment block.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.block[..self.len]
+    }
+
+    /// Returns the byte range of the entry of `key`, including its terminator.
+    fn find(&self, key: &str) -> Option<core::ops::Range<usize>> {
+        let mut start = 0;
+        for entry in self.as_bytes().split_inclusive(|&b| b == 0) {
+            let end = start + entry.len();
+            if entry
+                .strip_prefix(key.as_bytes())
+                .is_some_and(|rest| rest.first() == Some(&b'='))
+            {
+                return Some(start..end);
+            }
+            start = end;
+        }
+        None
+    }
+}
+
+/// Parses an environment block made of `KEY=VALUE\0` entries.
+///
+/// Malformed entries are skipped.
+pub fn parse_block(block: &[u8]) -> impl Iterator<Item = (&str, &str)> {
+    block
+        .split(|&b| b == 0)
+        .filter_map(|entry| core::str::from_utf8(entry).ok()?.split_once('='))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_get_set() {
+        let mut env = Environment::new();
+        assert_eq!(env.get("PATH"), None);
+
+        env.set("PATH", "/bin").unwrap();
+        env.set("TERM", "beskar").unwrap();
+        assert_eq!(env.get("PATH"), Some("/bin"));
+        assert_eq!(env.get("TERM"), Some("beskar"));
+        assert_eq!(env.get("PAT"), None);
+
+        env.set("PATH", "/ramdisk:/bin").unwrap();
+        assert_eq!(env.get("PATH"), Some("/ramdisk:/bin"));
+        assert_eq!(env.vars().count(), 2);
+
+        env.set("EMPTY", "").unwrap();
+        assert_eq!(env.get("EMPTY"), Some(""));
+    }
+
+    #[test]
+    fn test_env_unset() {
+        let mut env = Environment::new();
+        env.set("A", "1").unwrap();
+        env.set("B", "2").unwrap();
+        env.set("C", "3").unwrap();
+
+        assert!(env.unset("B"));
+        assert!(!env.unset("B"));
+        assert_eq!(env.get("B"), None);
+        assert_eq!(env.get("A"), Some("1"));
+        assert_eq!(env.get("C"), Some("3"));
+        assert_eq!(env.as_bytes(), b"A=1\0C=3\0");
+    }
+
+    #[test]
+    fn test_env_invalid() {
+        let mut env = Environment::new();
+        assert_eq!(env.set("", "value"), Err(EnvError::InvalidName));
+        assert_eq!(env.set("A=B", "value"), Err(EnvError::InvalidName));
+        assert_eq!(env.set("A\0", "value"), Err(EnvError::InvalidName));
+        assert_eq!(env.set("A", "val\0ue"), Err(EnvError::InvalidValue));
+        assert_eq!(env.vars().count(), 0);
+    }
+
+    #[test]
+    fn test_env_full() {
+        let bytes = [b'x'; ENV_MAX_SIZE];
+        let big = core::str::from_utf8(&bytes).unwrap();
+
+        let mut env = Environment::new();
+        env.set("A", &big[..ENV_MAX_SIZE - 4 - 10]).unwrap();
+        assert_eq!(env.set("B", &big[..20]), Err(EnvError::Full));
+        assert_eq!(env.get("B"), None);
+
+        // Replacing a variable may use the space it frees
+        env.set("A", &big[..ENV_MAX_SIZE - 3]).unwrap();
+        assert_eq!(env.as_bytes().len(), ENV_MAX_SIZE);
+        assert_eq!(env.set("A", big), Err(EnvError::Full));
+        assert_eq!(env.get("A"), Some(&big[..ENV_MAX_SIZE - 3]));
+    }
+
+    #[test]
+    fn test_env_inheritance() {
+        let mut parent = Environment::new();
+        parent.set("PATH", "/ramdisk").unwrap();
+
+        let mut child = parent.clone();
+        assert_eq!(child.get("PATH"), Some("/ramdisk"));
+
+        child.set("PATH", "/bin").unwrap();
+        child.set("HOME", "/").unwrap();
+        assert_eq!(parent.get("PATH"), Some("/ramdisk"));
+        assert_eq!(parent.get("HOME"), None);
+
+        let mut copied = parse_block(child.as_bytes());
+        assert_eq!(copied.next(), Some(("PATH", "/bin")));
+        assert_eq!(copied.next(), Some(("HOME", "/")));
+        assert_eq!(copied.next(), None);
+    }
+}
@@ -0,0 +1,476 @@
+//! Priority inheritance for sleeping locks.
+//!
+//! When a thread blocks on a lock held by a thread of lower priority, threads of
+//! intermediate priority could keep the holder, and thus the waiter, from running.
+//! To avoid this priority inversion, the waiter lends its priority to the holder
+//! until the lock is released.
+//!
+//! ## Transitivity
+//!
+//! The holder may itself be blocked on another lock. The lent priority is then passed
+//! along the chain of holders, up to the first one that is not blocked, which is the
+//! thread that has to run for the waiter to make progress.
+//! The walk stops at a holder that already runs at that priority, and after
+//! `MAX_CHAIN` locks, which bounds it when the chain is a deadlock cycle.
+//!
+//! ## Restoring
+//!
+//! When a thread releases a lock, its boost is recomputed from the waiters of the locks
+//! it still holds, so that it goes back to its own priority once none of them is contended.
+//! The lock is handed to its waiter of highest priority, which inherits from the others.
+//!
+//! The bookkeeping of threads and locks is left to the implementor of `LockGraph`.
+
+/// Maximum number of locks followed when lending a priority.
+pub const MAX_CHAIN: usize = 32;
+
+/// The threads and locks of the system, as seen by priority inheritance.
+pub trait LockGraph {
+    type Thread: Copy + Eq;
+    type Lock: Copy + Eq;
+    type Priority: Copy + Ord;
+
+    /// Returns the thread holding `lock`, if any.
+    fn holder(&self, lock: Self::Lock) -> Option<Self::Thread>;
+    fn set_holder(&mut self, lock: Self::Lock, holder: Option<Self::Thread>);
+
+    /// Returns the threads blocked on `lock`, in the order they blocked.
+    fn waiters(&self, lock: Self::Lock) -> impl Iterator<Item = Self::Thread>;
+    /// Records that `thread` is blocked on `lock`.
+    fn add_waiter(&mut self, lock: Self::Lock, thread: Self::Thread);
+    /// Records that `thread` is no longer blocked on `lock`.
+    fn remove_waiter(&mut self, lock: Self::Lock, thread: Self::Thread);
+
+    /// Returns the lock `thread` is blocked on, if any.
+    fn blocked_on(&self, thread: Self::Thread) -> Option<Self::Lock>;
+    /// Returns the locks held by `thread`.
+    fn held(&self, thread: Self::Thread) -> impl Iterator<Item = Self::Lock>;
+
+    /// Returns the priority `thread` was given, regardless of inheritance.
+    fn base_priority(&self, thread: Self::Thread) -> Self::Priority;
+    /// Returns the priority `thread` inherited, if any.
+    fn boost(&self, thread: Self::Thread) -> Option<Self::Priority>;
+    fn set_boost(&mut self, thread: Self::Thread, boost: Option<Self::Priority>);
+}
+
+#[must_use]
+/// Returns the priority `thread` runs at.
+pub fn effective_priority<G: LockGraph>(graph: &G, thread: G::Thread) -> G::Priority {
+    let base = graph.base_priority(thread);
+    graph.boost(thread).map_or(base, |boost| boost.max(base))
+}
+
+/// Takes `lock` for `thread`, or blocks `thread` on it.
+///
+/// Returns `true` if `thread` now holds the lock. Otherwise, the priority of `thread` is lent
+/// to the chain of holders, see `lend`, and the caller should put `thread` to sleep until
+/// `release` hands the lock over, see `granted`.
+///
+/// A thread that already holds `lock` would wait on itself forever, so this panics.
+pub fn acquire<G: LockGraph>(
+    graph: &mut G,
+    lock: G::Lock,
+    thread: G::Thread,
+    raised: impl FnMut(G::Thread, G::Priority),
+) -> bool {
+    assert!(
+        graph.blocked_on(thread).is_none(),
+        "Thread is already blocked on a lock"
+    );
+
+    match graph.holder(lock) {
+        None => {
+            graph.set_holder(lock, Some(thread));
+            restore(graph, thread);
+            true
+        }
+        Some(holder) => {
+            assert!(holder != thread, "Recursive locking");
+            graph.add_waiter(lock, thread);
+            let priority = effective_priority(graph, thread);
+            lend(graph, lock, priority, raised);
+            false
+        }
+    }
+}
+
+#[must_use]
+/// Returns whether `release` handed `lock` over to `thread`, which blocked on it.
+///
+/// The new holder is no longer a waiter, so a thread that is woken up
+/// for another reason is not mistaken for the holder.
+pub fn granted<G: LockGraph>(graph: &G, lock: G::Lock, thread: G::Thread) -> bool {
+    graph.blocked_on(thread) != Some(lock) && graph.holder(lock) == Some(thread)
+}
+
+/// Releases `lock`, held by `thread`, and hands it to the next waiter.
+///
+/// Returns the waiter that now holds the lock, which the caller should wake up.
+pub fn release<G: LockGraph>(graph: &mut G, lock: G::Lock, thread: G::Thread) -> Option<G::Thread> {
+    debug_assert!(graph.holder(lock) == Some(thread));
+
+    let next = next_holder(graph, lock);
+    if let Some(next) = next {
+        graph.remove_waiter(lock, next);
+    }
+    graph.set_holder(lock, next);
+    if let Some(next) = next {
+        restore(graph, next);
+    }
+    restore(graph, thread);
+    next
+}
+
+/// Lends `priority` to the holder of `lock`, and along the chain of holders behind it.
+///
+/// `raised` is called with each thread whose priority was raised and the priority
+/// it ran at before, so that a ready thread can be moved to its new run queue.
+pub fn lend<G: LockGraph>(
+    graph: &mut G,
+    mut lock: G::Lock,
+    priority: G::Priority,
+    mut raised: impl FnMut(G::Thread, G::Priority),
+) {
+    for _ in 0..MAX_CHAIN {
+        let Some(holder) = graph.holder(lock) else {
+            return;
+        };
+        let previous = effective_priority(graph, holder);
+        if previous >= priority {
+            return;
+        }
+        graph.set_boost(holder, Some(priority));
+        raised(holder, previous);
+
+        let Some(next) = graph.blocked_on(holder) else {
+            return;
+        };
+        lock = next;
+    }
+}
+
+/// Recomputes the boost of `thread` from the waiters of the locks it holds.
+///
+/// `thread` must not be blocked, as the chain it holds up is not updated.
+/// Returns the priority it ran at before.
+pub fn restore<G: LockGraph>(graph: &mut G, thread: G::Thread) -> G::Priority {
+    let previous = effective_priority(graph, thread);
+    let base = graph.base_priority(thread);
+    let boost = graph
+        .held(thread)
+        .flat_map(|lock| graph.waiters(lock))
+        .map(|waiter| effective_priority(graph, waiter))
+        .max()
+        .filter(|&inherited| inherited > base);
+    graph.set_boost(thread, boost);
+    previous
+}
+
+#[must_use]
+/// Returns the waiter of `lock` with the highest priority, the first to block among equals.
+fn next_holder<G: LockGraph>(graph: &G, lock: G::Lock) -> Option<G::Thread> {
+    graph.waiters(lock).reduce(|best, waiter| {
+        if effective_priority(graph, waiter) > effective_priority(graph, best) {
+            waiter
+        } else {
+            best
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THREADS: usize = 4;
+    const LOCKS: usize = 2;
+
+    const LOW: u8 = 1;
+    const NORMAL: u8 = 2;
+    const HIGH: u8 = 3;
+
+    /// Threads and locks of the simulation, indexed by ID.
+    struct Graph {
+        base: [u8; THREADS],
+        boost: [Option<u8>; THREADS],
+        blocked_on: [Option<usize>; THREADS],
+        holder: [Option<usize>; LOCKS],
+        /// Arrival order of the waiters of each lock, indexed by thread.
+        waiters: [[Option<u64>; THREADS]; LOCKS],
+        arrivals: u64,
+        /// Whether boosts are recorded, to show the inversion without inheritance.
+        inheritance: bool,
+    }
+
+    impl Graph {
+        fn new(base: [u8; THREADS], inheritance: bool) -> Self {
+            Self {
+                base,
+                boost: [None; THREADS],
+                blocked_on: [None; THREADS],
+                holder: [None; LOCKS],
+                waiters: [[None; THREADS]; LOCKS],
+                arrivals: 0,
+                inheritance,
+            }
+        }
+    }
+
+    impl LockGraph for Graph {
+        type Thread = usize;
+        type Lock = usize;
+        type Priority = u8;
+
+        fn holder(&self, lock: usize) -> Option<usize> {
+            self.holder[lock]
+        }
+
+        fn set_holder(&mut self, lock: usize, holder: Option<usize>) {
+            self.holder[lock] = holder;
+        }
+
+        fn waiters(&self, lock: usize) -> impl Iterator<Item = usize> {
+            let mut waiters = [(u64::MAX, 0); THREADS];
+            for (thread, arrival) in self.waiters[lock].iter().enumerate() {
+                if let Some(arrival) = *arrival {
+                    waiters[thread] = (arrival, thread);
+                }
+            }
+            waiters.sort_unstable();
+            waiters
+                .into_iter()
+                .take_while(|&(arrival, _)| arrival != u64::MAX)
+                .map(|(_, thread)| thread)
+        }
+
+        fn add_waiter(&mut self, lock: usize, thread: usize) {
+            self.waiters[lock][thread] = Some(self.arrivals);
+            self.arrivals += 1;
+            self.blocked_on[thread] = Some(lock);
+        }
+
+        fn remove_waiter(&mut self, lock: usize, thread: usize) {
+            self.waiters[lock][thread] = None;
+            self.blocked_on[thread] = None;
+        }
+
+        fn blocked_on(&self, thread: usize) -> Option<usize> {
+            self.blocked_on[thread]
+        }
+
+        fn held(&self, thread: usize) -> impl Iterator<Item = usize> {
+            (0..LOCKS).filter(move |&lock| self.holder[lock] == Some(thread))
+        }
+
+        fn base_priority(&self, thread: usize) -> u8 {
+            self.base[thread]
+        }
+
+        fn boost(&self, thread: usize) -> Option<u8> {
+            self.boost[thread]
+        }
+
+        fn set_boost(&mut self, thread: usize, boost: Option<u8>) {
+            if self.inheritance {
+                self.boost[thread] = boost;
+            }
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    enum Step {
+        Run(u32),
+        Lock(usize),
+        Unlock(usize),
+    }
+
+    /// A thread of the simulation, which becomes ready at tick `start`.
+    struct Program<'a> {
+        start: u32,
+        steps: &'a [Step],
+    }
+
+    /// Runs the programs on a single core, one step or tick of work at a time,
+    /// always picking the ready thread of highest priority.
+    ///
+    /// Returns the tick at which each thread finished.
+    fn simulate(graph: &mut Graph, programs: &[Program; THREADS]) -> [u32; THREADS] {
+        let mut pc = [0; THREADS];
+        let mut left = [None; THREADS];
+        let mut finished = [u32::MAX; THREADS];
+
+        for tick in 0..1000 {
+            let Some(thread) = (0..THREADS)
+                .filter(|&t| {
+                    programs[t].start <= tick
+                        && pc[t] < programs[t].steps.len()
+                        && graph.blocked_on(t).is_none()
+                })
+                .max_by_key(|&t| (effective_priority(graph, t), core::cmp::Reverse(t)))
+            else {
+                break;
+            };
+
+            match programs[thread].steps[pc[thread]] {
+                Step::Run(ticks) => {
+                    let remaining = left[thread].get_or_insert(ticks);
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        pc[thread] += 1;
+                        left[thread] = None;
+                    }
+                }
+                Step::Lock(lock) => {
+                    // A blocked thread only runs again once the lock is handed over
+                    if granted(graph, lock, thread) || acquire(graph, lock, thread, |_, _| {}) {
+                        pc[thread] += 1;
+                    }
+                }
+                Step::Unlock(lock) => {
+                    release(graph, lock, thread);
+                    pc[thread] += 1;
+                }
+            }
+            if pc[thread] == programs[thread].steps.len() {
+                finished[thread] = tick;
+            }
+        }
+        finished
+    }
+
+    /// A low priority thread takes the lock, then a high priority thread needs it
+    /// while a normal priority thread has a lot of work to do.
+    fn inversion(inheritance: bool) -> [u32; THREADS] {
+        let programs = [
+            Program {
+                start: 0,
+                steps: &[Step::Lock(0), Step::Run(3), Step::Unlock(0), Step::Run(1)],
+            },
+            Program {
+                start: 1,
+                steps: &[Step::Run(20)],
+            },
+            Program {
+                start: 1,
+                steps: &[Step::Lock(0), Step::Run(2), Step::Unlock(0)],
+            },
+            // Unused
+            Program {
+                start: 0,
+                steps: &[],
+            },
+        ];
+        let mut graph = Graph::new([LOW, NORMAL, HIGH, LOW], inheritance);
+        simulate(&mut graph, &programs)
+    }
+
+    #[test]
+    fn test_inversion_resolved() {
+        // Without inheritance, the high priority thread waits for the normal one
+        let [low, normal, high, _] = inversion(false);
+        assert_eq!((low, normal, high), (30, 21, 29));
+
+        // The holder runs ahead of the normal thread until it releases the lock,
+        // then goes back to its own priority
+        let [low, normal, high, _] = inversion(true);
+        assert_eq!((low, normal, high), (30, 29, 9));
+    }
+
+    #[test]
+    fn test_transitive() {
+        let (low, normal, high) = (0, 1, 2);
+        let (a, b) = (0, 1);
+        let mut graph = Graph::new([LOW, NORMAL, HIGH, LOW], true);
+
+        assert!(acquire(&mut graph, b, low, |_, _| {}));
+        assert!(acquire(&mut graph, a, normal, |_, _| {}));
+
+        let mut raised = [None; THREADS];
+        assert!(!acquire(&mut graph, b, normal, |t, p| raised[t] = Some(p)));
+        assert_eq!(effective_priority(&graph, low), NORMAL);
+        assert_eq!(raised, [Some(LOW), None, None, None]);
+
+        // The high priority thread is lent through the normal one down to the low one
+        let mut raised = [None; THREADS];
+        assert!(!acquire(&mut graph, a, high, |t, p| raised[t] = Some(p)));
+        assert_eq!(effective_priority(&graph, normal), HIGH);
+        assert_eq!(effective_priority(&graph, low), HIGH);
+        assert_eq!(raised, [Some(NORMAL), Some(NORMAL), None, None]);
+
+        // Releasing hands the lock over, the new holder keeps what it inherits from `a`
+        assert!(!granted(&graph, b, normal));
+        assert_eq!(release(&mut graph, b, low), Some(normal));
+        assert_eq!(effective_priority(&graph, low), LOW);
+        assert_eq!(effective_priority(&graph, normal), HIGH);
+        assert!(granted(&graph, b, normal));
+
+        assert_eq!(release(&mut graph, b, normal), None);
+        assert_eq!(effective_priority(&graph, normal), HIGH);
+        assert_eq!(release(&mut graph, a, normal), Some(high));
+        assert_eq!(effective_priority(&graph, normal), NORMAL);
+        assert!(granted(&graph, a, high));
+    }
+
+    #[test]
+    fn test_handoff_order() {
+        let mut graph = Graph::new([LOW, NORMAL, HIGH, NORMAL], true);
+
+        assert!(acquire(&mut graph, 0, 0, |_, _| {}));
+        for thread in [1, 3, 2] {
+            assert!(!acquire(&mut graph, 0, thread, |_, _| {}));
+        }
+        assert_eq!(effective_priority(&graph, 0), HIGH);
+
+        // Highest priority first, then in the order they blocked
+        assert_eq!(release(&mut graph, 0, 0), Some(2));
+        assert_eq!(effective_priority(&graph, 2), HIGH);
+        assert_eq!(release(&mut graph, 0, 2), Some(1));
+        assert_eq!(effective_priority(&graph, 1), NORMAL);
+        assert_eq!(release(&mut graph, 0, 1), Some(3));
+        assert_eq!(release(&mut graph, 0, 3), None);
+        assert_eq!(graph.holder(0), None);
+    }
+
+    #[test]
+    fn test_granted() {
+        let mut graph = Graph::new([LOW, NORMAL, HIGH, LOW], true);
+
+        assert!(acquire(&mut graph, 0, 0, |_, _| {}));
+        assert!(!acquire(&mut graph, 0, 1, |_, _| {}));
+        assert!(!acquire(&mut graph, 0, 2, |_, _| {}));
+        assert!(!granted(&graph, 0, 1));
+        assert!(!granted(&graph, 0, 2));
+
+        // Only the waiter the lock is handed to holds it
+        assert_eq!(release(&mut graph, 0, 0), Some(2));
+        assert!(granted(&graph, 0, 2));
+        assert!(!granted(&graph, 0, 1));
+        assert_eq!(graph.blocked_on(1), Some(0));
+    }
+
+    #[test]
+    #[should_panic = "Recursive locking"]
+    fn test_recursive_acquire() {
+        let mut graph = Graph::new([LOW, NORMAL, HIGH, LOW], true);
+
+        assert!(acquire(&mut graph, 0, 0, |_, _| {}));
+        let _ = acquire(&mut graph, 0, 0, |_, _| {});
+    }
+
+    #[test]
+    fn test_deadlock_cycle_bounded() {
+        let mut graph = Graph::new([LOW, LOW, HIGH, LOW], true);
+
+        assert!(acquire(&mut graph, 0, 0, |_, _| {}));
+        assert!(acquire(&mut graph, 1, 1, |_, _| {}));
+        assert!(!acquire(&mut graph, 1, 0, |_, _| {}));
+        assert!(!acquire(&mut graph, 0, 1, |_, _| {}));
+
+        // Lending around the cycle terminates
+        let mut raised = 0;
+        assert!(!acquire(&mut graph, 0, 2, |_, _| raised += 1));
+        assert_eq!(raised, 2);
+        assert_eq!(effective_priority(&graph, 0), HIGH);
+        assert_eq!(effective_priority(&graph, 1), HIGH);
+    }
+}
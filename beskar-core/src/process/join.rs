@@ -0,0 +1,193 @@
+//! Bookkeeping of the threads a process can join.
+//!
+//! A thread created by userspace is registered when it is spawned, and marked
+//! as exited, with its exit code, when it exits. Joining it waits for the mark,
+//! then forgets the thread.
+//!
+//! A thread that nobody will join is detached, which forgets it right away:
+//! it no longer takes a slot, whether it is still running or not.
+use crate::syscall::ExitCode;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum JoinError {
+    #[error("Too many threads are waiting to be joined")]
+    Full,
+    #[error("The thread is not joinable")]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Joinable {
+    tid: u64,
+    /// Exit code of the thread, once it has exited.
+    exit_code: Option<ExitCode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The threads of a process that have not been joined yet.
+pub struct JoinTable<const N: usize> {
+    entries: [Option<Joinable>; N],
+}
+
+impl<const N: usize> Default for JoinTable<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> JoinTable<N> {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self { entries: [None; N] }
+    }
+
+    /// Registers a running thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns `JoinError::Full` if `N` threads are already waiting to be joined.
+    pub fn register(&mut self, tid: u64) -> Result<(), JoinError> {
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.is_none())
+            .ok_or(JoinError::Full)?;
+        *slot = Some(Joinable {
+            tid,
+            exit_code: None,
+        });
+        Ok(())
+    }
+
+    /// Marks a thread as exited with `exit_code`.
+    ///
+    /// Returns whether the thread is joinable.
+    pub fn mark_exited(&mut self, tid: u64, exit_code: ExitCode) -> bool {
+        self.entries
+            .iter_mut()
+            .flatten()
+            .find(|entry| entry.tid == tid)
+            .map(|entry| entry.exit_code = Some(exit_code))
+            .is_some()
+    }
+
+    /// Forgets a thread, which can no longer be joined.
+    ///
+    /// # Errors
+    ///
+    /// Returns `JoinError::Unknown` if the thread is not joinable.
+    pub fn detach(&mut self, tid: u64) -> Result<(), JoinError> {
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.is_some_and(|entry| entry.tid == tid))
+            .ok_or(JoinError::Unknown)?;
+        *slot = None;
+        Ok(())
+    }
+
+    /// Joins a thread if it has exited, which forgets it.
+    ///
+    /// Returns the exit code of the thread if it was joined. If not, it is still running.
+    ///
+    /// # Errors
+    ///
+    /// Returns `JoinError::Unknown` if the thread is not joinable,
+    /// which is the case once it has been joined.
+    pub fn try_join(&mut self, tid: u64) -> Result<Option<ExitCode>, JoinError> {
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.is_some_and(|entry| entry.tid == tid))
+            .ok_or(JoinError::Unknown)?;
+        let exit_code = slot.and_then(|entry| entry.exit_code);
+        if exit_code.is_some() {
+            *slot = None;
+        }
+        Ok(exit_code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join() {
+        let mut table = JoinTable::<4>::new();
+        table.register(7).unwrap();
+        table.register(8).unwrap();
+
+        assert_eq!(table.try_join(7), Ok(None));
+        assert!(table.mark_exited(7, ExitCode::Success));
+        assert_eq!(table.try_join(7), Ok(Some(ExitCode::Success)));
+        // A thread is only joined once
+        assert_eq!(table.try_join(7), Err(JoinError::Unknown));
+
+        assert_eq!(table.try_join(8), Ok(None));
+        assert!(!table.mark_exited(9, ExitCode::Success));
+        assert_eq!(table.try_join(9), Err(JoinError::Unknown));
+    }
+
+    #[test]
+    fn test_full() {
+        let mut table = JoinTable::<2>::new();
+        table.register(1).unwrap();
+        table.register(2).unwrap();
+        assert_eq!(table.register(3), Err(JoinError::Full));
+
+        // Joining frees a slot
+        table.mark_exited(1, ExitCode::Success);
+        assert_eq!(table.try_join(1), Ok(Some(ExitCode::Success)));
+        table.register(3).unwrap();
+    }
+
+    #[test]
+    fn test_join_panicked() {
+        let mut table = JoinTable::<2>::new();
+        table.register(5).unwrap();
+
+        // The thread panicked in its boundary, which exits it with `Panicked`
+        assert!(table.mark_exited(5, ExitCode::Panicked));
+        assert_eq!(table.try_join(5), Ok(Some(ExitCode::Panicked)));
+    }
+
+    #[test]
+    fn test_detach() {
+        let mut table = JoinTable::<2>::new();
+        table.register(1).unwrap();
+        table.register(2).unwrap();
+
+        // Detaching a running thread frees its slot, and its exit is not recorded
+        table.detach(1).unwrap();
+        table.register(3).unwrap();
+        assert!(!table.mark_exited(1, ExitCode::Success));
+        assert_eq!(table.try_join(1), Err(JoinError::Unknown));
+
+        // Same for an exited thread
+        assert!(table.mark_exited(2, ExitCode::Success));
+        table.detach(2).unwrap();
+        assert_eq!(table.try_join(2), Err(JoinError::Unknown));
+        assert_eq!(table.detach(2), Err(JoinError::Unknown));
+    }
+
+    #[test]
+    fn test_many_detached() {
+        let mut table = JoinTable::<64>::new();
+        table.register(0).unwrap();
+
+        // Detached threads never fill the table, even while they run
+        for tid in 1..=200 {
+            table.register(tid).unwrap();
+            table.detach(tid).unwrap();
+        }
+        for tid in 1..=200 {
+            assert!(!table.mark_exited(tid, ExitCode::Success));
+        }
+
+        assert!(table.mark_exited(0, ExitCode::Success));
+        assert_eq!(table.try_join(0), Ok(Some(ExitCode::Success)));
+    }
+}
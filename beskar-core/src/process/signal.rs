@@ -0,0 +1,670 @@
+//! Signals, which notify a process of an event asynchronously.
+//!
+//! A process registers a handler for a signal with the `SigAction` syscall.
+//! When the signal is raised, it stays pending until a thread of the process
+//! leaves a syscall. The kernel then pushes a `SignalFrame` on the user stack,
+//! holding the interrupted state, and returns to the handler instead.
+//! The handler returns to the restorer registered along with it,
+//! which calls `SigReturn` to restore the interrupted state from the frame.
+//!
+//! While a handler runs, its signal is blocked, so that handlers do not nest.
+//! Signals without a handler take their default action, which terminates the process
+//! for most of them.
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u64)]
+pub enum Signal {
+    /// The timer of the process, armed with `Syscall::SetTimer`, expired.
+    Alarm = 1,
+    /// The user asked to interrupt the process, with Ctrl-C.
+    Interrupt = 2,
+    /// The process wrote to a pipe whose read end is closed.
+    BrokenPipe = 3,
+    /// The user asked to suspend the process, with Ctrl-Z.
+    Suspend = 4,
+}
+
+impl Signal {
+    /// Every signal, by increasing number.
+    pub const ALL: [Self; 4] = [
+        Self::Alarm,
+        Self::Interrupt,
+        Self::BrokenPipe,
+        Self::Suspend,
+    ];
+
+    #[must_use]
+    #[inline]
+    const fn index(self) -> usize {
+        self as usize - 1
+    }
+
+    #[must_use]
+    #[inline]
+    /// Action taken when the signal is raised and the process has no handler for it.
+    pub const fn default_action(self) -> DefaultAction {
+        match self {
+            Self::Alarm | Self::Interrupt | Self::BrokenPipe => DefaultAction::Terminate,
+            // Processes cannot be stopped yet
+            Self::Suspend => DefaultAction::Ignore,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultAction {
+    /// Every thread of the process exits.
+    Terminate,
+    /// The signal is discarded.
+    Ignore,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// A set of signals.
+pub struct SignalSet(u64);
+
+impl SignalSet {
+    pub const EMPTY: Self = Self(0);
+
+    #[must_use]
+    #[inline]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn raw(self) -> u64 {
+        self.0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(self, signal: Signal) -> bool {
+        self.0 & (1 << signal as u64) != 0
+    }
+
+    #[inline]
+    pub const fn insert(&mut self, signal: Signal) {
+        self.0 |= 1 << signal as u64;
+    }
+
+    #[inline]
+    pub const fn remove(&mut self, signal: Signal) {
+        self.0 &= !(1 << signal as u64);
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    /// Returns the signal of the lowest number in the set.
+    pub fn first(self) -> Option<Signal> {
+        Signal::ALL
+            .into_iter()
+            .find(|&signal| self.contains(signal))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// What a process does when a signal is raised.
+pub enum Disposition {
+    #[default]
+    /// The default action of the signal.
+    Default,
+    /// The signal is discarded.
+    Ignore,
+    /// The signal is delivered to a handler.
+    Handler(Handler),
+}
+
+impl Disposition {
+    #[must_use]
+    /// Decodes the arguments of the `SigAction` syscall.
+    ///
+    /// `handler` is either `SIG_DEFAULT`, `SIG_IGNORE` or the address of the handler.
+    /// `restorer` is only used with a handler.
+    pub const fn from_raw(handler: u64, restorer: u64) -> Self {
+        use crate::syscall::consts::{SIG_DEFAULT, SIG_IGNORE};
+
+        match handler {
+            SIG_DEFAULT => Self::Default,
+            SIG_IGNORE => Self::Ignore,
+            _ => Self::Handler(Handler {
+                entry: handler,
+                restorer,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A signal handler registered by a process.
+pub struct Handler {
+    /// Address of the handler, an `extern "C" fn(Signal)`.
+    entry: u64,
+    /// Address the handler returns to, which must call `SigReturn`.
+    restorer: u64,
+}
+
+impl Handler {
+    #[must_use]
+    #[inline]
+    pub const fn entry(&self) -> u64 {
+        self.entry
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn restorer(&self) -> u64 {
+        self.restorer
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Outcome of raising a signal.
+pub enum Raised {
+    /// The signal was discarded.
+    Ignored,
+    /// The signal waits for a thread to run its handler.
+    Pending,
+    /// The process must terminate.
+    Terminate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A pending signal, taken to be delivered.
+pub enum Delivery {
+    /// The handler of the signal must run.
+    Handle {
+        signal: Signal,
+        handler: Handler,
+        /// Signals blocked before the handler runs, to be restored by `SigReturn`.
+        blocked: SignalSet,
+    },
+    /// The handler was removed while the signal was pending,
+    /// so its default action applies.
+    Terminate(Signal),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The signal dispositions of a process, and the signals waiting to be delivered.
+pub struct SignalState {
+    dispositions: [Disposition; Signal::ALL.len()],
+    pending: SignalSet,
+    blocked: SignalSet,
+}
+
+impl Default for SignalState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalState {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            dispositions: [Disposition::Default; Signal::ALL.len()],
+            pending: SignalSet::EMPTY,
+            blocked: SignalSet::EMPTY,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn disposition(&self, signal: Signal) -> Disposition {
+        self.dispositions[signal.index()]
+    }
+
+    /// Sets the disposition of a signal.
+    ///
+    /// Ignoring a signal discards it if it is pending.
+    pub const fn set_disposition(&mut self, signal: Signal, disposition: Disposition) {
+        self.dispositions[signal.index()] = disposition;
+        if matches!(disposition, Disposition::Ignore) {
+            self.pending.remove(signal);
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn pending(&self) -> SignalSet {
+        self.pending
+    }
+
+    /// Raises a signal.
+    ///
+    /// A signal raised again while it is pending is only delivered once.
+    pub const fn raise(&mut self, signal: Signal) -> Raised {
+        match self.disposition(signal) {
+            Disposition::Ignore => Raised::Ignored,
+            Disposition::Default => match signal.default_action() {
+                DefaultAction::Terminate => Raised::Terminate,
+                DefaultAction::Ignore => Raised::Ignored,
+            },
+            Disposition::Handler(_) => {
+                self.pending.insert(signal);
+                Raised::Pending
+            }
+        }
+    }
+
+    /// Takes the pending signal of the lowest number that is not blocked.
+    ///
+    /// If it has a handler, the signal is blocked until `restore_blocked` is called
+    /// with the mask returned in the delivery.
+    pub fn take_deliverable(&mut self) -> Option<Delivery> {
+        loop {
+            let deliverable = SignalSet::from_raw(self.pending.raw() & !self.blocked.raw());
+            let signal = deliverable.first()?;
+            self.pending.remove(signal);
+
+            match self.disposition(signal) {
+                Disposition::Handler(handler) => {
+                    let blocked = self.blocked;
+                    self.blocked.insert(signal);
+                    return Some(Delivery::Handle {
+                        signal,
+                        handler,
+                        blocked,
+                    });
+                }
+                Disposition::Default => match signal.default_action() {
+                    DefaultAction::Terminate => return Some(Delivery::Terminate(signal)),
+                    DefaultAction::Ignore => {}
+                },
+                // Ignoring a signal removes it from the pending set
+                Disposition::Ignore => unreachable!(),
+            }
+        }
+    }
+
+    #[inline]
+    /// Restores the blocked signals, once a handler returned.
+    pub const fn restore_blocked(&mut self, blocked: SignalSet) {
+        self.blocked = blocked;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// The registers of a thread that a handler may change, and that `SigReturn` restores.
+///
+/// Registers that are callee-saved in the System V ABI are preserved by the handler itself.
+/// `RCX` and `R11` are not saved: returning to userspace sets them to `RIP` and `RFLAGS`.
+pub struct UserContext {
+    pub rax: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub rip: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+}
+
+impl UserContext {
+    /// RFLAGS bits that userspace may set: the status flags and the direction flag.
+    pub const USER_RFLAGS: u64 = 0b1100_1101_0101;
+    /// RFLAGS bits that are always set when running in userspace:
+    /// the interrupt flag and the reserved bit 1.
+    pub const FORCED_RFLAGS: u64 = (1 << 9) | (1 << 1);
+
+    #[must_use]
+    #[inline]
+    /// Returns the context with the RFLAGS bits that userspace may not set restored.
+    pub const fn sanitized(mut self) -> Self {
+        self.rflags = (self.rflags & Self::USER_RFLAGS) | Self::FORCED_RFLAGS;
+        self
+    }
+}
+
+/// Size of the area saved by `FXSAVE`, holding the x87 and SSE state.
+pub const FX_AREA_SIZE: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(16))]
+/// The x87 and SSE state, in the `FXSAVE` format.
+pub struct FxArea([u8; FX_AREA_SIZE]);
+
+impl Default for FxArea {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FxArea {
+    /// Offset of `MXCSR` in the area.
+    const MXCSR_OFFSET: usize = 24;
+    /// Bits of `MXCSR` that every processor supporting SSE2 accepts.
+    ///
+    /// `FXRSTOR` faults if a reserved bit is set.
+    pub const MXCSR_MASK: u32 = 0xFFBF;
+
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self([0; FX_AREA_SIZE])
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn mxcsr(&self) -> u32 {
+        u32::from_le_bytes([
+            self.0[Self::MXCSR_OFFSET],
+            self.0[Self::MXCSR_OFFSET + 1],
+            self.0[Self::MXCSR_OFFSET + 2],
+            self.0[Self::MXCSR_OFFSET + 3],
+        ])
+    }
+
+    #[inline]
+    /// Clears the reserved bits of `MXCSR`, so that restoring an area written
+    /// by userspace does not fault.
+    pub fn sanitize(&mut self) {
+        let mxcsr = self.mxcsr() & Self::MXCSR_MASK;
+        self.0[Self::MXCSR_OFFSET..Self::MXCSR_OFFSET + 4].copy_from_slice(&mxcsr.to_le_bytes());
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn as_bytes(&self) -> &[u8; FX_AREA_SIZE] {
+        &self.0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn as_bytes_mut(&mut self) -> &mut [u8; FX_AREA_SIZE] {
+        &mut self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// The frame pushed on the user stack to run a signal handler.
+///
+/// The handler is entered with the stack pointer on the frame, so that its
+/// return address is the restorer. Once it returns, the stack pointer is right
+/// past the restorer when `SigReturn` is called.
+///
+/// The x87 and SSE state is saved in an `FxArea` above the frame.
+pub struct SignalFrame {
+    /// Return address of the handler.
+    restorer: u64,
+    signal: u64,
+    /// Signals blocked before the handler ran.
+    blocked: u64,
+    /// Address of the saved x87 and SSE state.
+    fx_area: u64,
+    context: UserContext,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Where a signal frame and its `FxArea` go on the user stack.
+pub struct FramePlacement {
+    frame: u64,
+    fx_area: u64,
+}
+
+impl FramePlacement {
+    #[must_use]
+    #[inline]
+    /// Address of the frame, which is the stack pointer of the handler.
+    pub const fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    #[must_use]
+    #[inline]
+    /// Address of the `FxArea`, aligned on 16 bytes.
+    pub const fn fx_area(&self) -> u64 {
+        self.fx_area
+    }
+
+    #[must_use]
+    #[inline]
+    /// End of the `FxArea`, which is the end of the memory written.
+    pub const fn end(&self) -> u64 {
+        self.fx_area + FX_AREA_SIZE as u64
+    }
+}
+
+impl SignalFrame {
+    #[must_use]
+    #[inline]
+    pub const fn new(
+        signal: Signal,
+        handler: Handler,
+        blocked: SignalSet,
+        fx_area: u64,
+        context: UserContext,
+    ) -> Self {
+        Self {
+            restorer: handler.restorer(),
+            signal: signal as u64,
+            blocked: blocked.raw(),
+            fx_area,
+            context,
+        }
+    }
+
+    #[must_use]
+    /// Places a frame below `stack_pointer`.
+    ///
+    /// As after a `call`, the stack pointer of the handler is 8 bytes off a 16-byte boundary.
+    ///
+    /// Returns `None` if the stack pointer is too low to hold the frame.
+    pub fn place(stack_pointer: u64) -> Option<FramePlacement> {
+        let fx_area = stack_pointer.checked_sub(FX_AREA_SIZE as u64)? & !0xF;
+        let frame = (fx_area.checked_sub(size_of::<Self>() as u64)? & !0xF).checked_sub(8)?;
+        Some(FramePlacement { frame, fx_area })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the address of the frame from the stack pointer at `SigReturn`,
+    /// right past the return address of the handler.
+    pub const fn address_at_sigreturn(stack_pointer: u64) -> u64 {
+        stack_pointer.wrapping_sub(size_of::<u64>() as u64)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn signal(&self) -> Option<Signal> {
+        Signal::try_from(self.signal).ok()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn blocked(&self) -> SignalSet {
+        SignalSet::from_raw(self.blocked)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn fx_area(&self) -> u64 {
+        self.fx_area
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the interrupted context to restore.
+    ///
+    /// The frame lives in user memory, so the context is sanitized.
+    pub const fn context(&self) -> UserContext {
+        self.context.sanitized()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HANDLER: Handler = Handler {
+        entry: 0x40_1000,
+        restorer: 0x40_2000,
+    };
+
+    #[test]
+    fn test_default_action() {
+        let mut state = SignalState::new();
+        assert_eq!(state.raise(Signal::BrokenPipe), Raised::Terminate);
+
+        state.set_disposition(Signal::BrokenPipe, Disposition::Ignore);
+        assert_eq!(state.raise(Signal::BrokenPipe), Raised::Ignored);
+        assert!(state.pending().is_empty());
+        assert_eq!(state.take_deliverable(), None);
+    }
+
+    #[test]
+    fn test_delivery() {
+        let mut state = SignalState::new();
+        state.set_disposition(Signal::Alarm, Disposition::from_raw(0x40_1000, 0x40_2000));
+        state.set_disposition(Signal::Interrupt, Disposition::Handler(HANDLER));
+
+        assert_eq!(state.raise(Signal::Interrupt), Raised::Pending);
+        assert_eq!(state.raise(Signal::Alarm), Raised::Pending);
+        assert_eq!(state.raise(Signal::Alarm), Raised::Pending);
+
+        // Lowest number first, and once
+        assert_eq!(
+            state.take_deliverable(),
+            Some(Delivery::Handle {
+                signal: Signal::Alarm,
+                handler: HANDLER,
+                blocked: SignalSet::EMPTY,
+            })
+        );
+        let Some(Delivery::Handle { blocked, .. }) = state.take_deliverable() else {
+            panic!("Interrupt should be delivered");
+        };
+        let mut expected = SignalSet::EMPTY;
+        expected.insert(Signal::Alarm);
+        assert_eq!(blocked, expected);
+
+        // A signal is blocked while its handler runs
+        assert_eq!(state.raise(Signal::Alarm), Raised::Pending);
+        assert_eq!(state.take_deliverable(), None);
+        state.restore_blocked(blocked);
+        assert_eq!(state.take_deliverable(), None);
+        state.restore_blocked(SignalSet::EMPTY);
+        assert!(matches!(
+            state.take_deliverable(),
+            Some(Delivery::Handle {
+                signal: Signal::Alarm,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_handler_removed_while_pending() {
+        let mut state = SignalState::new();
+        state.set_disposition(Signal::Interrupt, Disposition::Handler(HANDLER));
+        assert_eq!(state.raise(Signal::Interrupt), Raised::Pending);
+
+        state.set_disposition(Signal::Interrupt, Disposition::from_raw(0, 0));
+        assert_eq!(
+            state.take_deliverable(),
+            Some(Delivery::Terminate(Signal::Interrupt))
+        );
+
+        // Signals ignored by default are dropped
+        state.set_disposition(Signal::Suspend, Disposition::Handler(HANDLER));
+        assert_eq!(state.raise(Signal::Suspend), Raised::Pending);
+        state.set_disposition(Signal::Suspend, Disposition::Default);
+        assert_eq!(state.take_deliverable(), None);
+        assert_eq!(state.raise(Signal::Suspend), Raised::Ignored);
+    }
+
+    #[test]
+    fn test_frame_placement() {
+        for sp in [0x7FFF_F000, 0x7FFF_EFF8, 0x7FFF_EFE3] {
+            let placement = SignalFrame::place(sp).unwrap();
+            assert_eq!(placement.fx_area() % 16, 0);
+            assert!(placement.end() <= sp);
+            // The handler is entered as if it was called
+            assert_eq!(placement.frame() % 16, 8);
+            assert!(placement.frame() + size_of::<SignalFrame>() as u64 <= placement.fx_area());
+        }
+        assert_eq!(SignalFrame::place(0x100), None);
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let context = UserContext {
+            rax: 42,
+            rdi: 1,
+            rip: 0x40_3000,
+            rsp: 0x7FFF_F000,
+            rflags: 0x202,
+            ..UserContext::default()
+        };
+        let placement = SignalFrame::place(context.rsp).unwrap();
+        let frame = SignalFrame::new(
+            Signal::Interrupt,
+            HANDLER,
+            SignalSet::EMPTY,
+            placement.fx_area(),
+            context,
+        );
+
+        // Lay the frame out as on the stack
+        let mut stack = [0_u64; 64];
+        let base = placement.frame() - 8 * 8;
+        let index = usize::try_from((placement.frame() - base) / 8).unwrap();
+        // Safety: the frame is made of `u64`s and fits in the stack.
+        unsafe {
+            stack
+                .as_mut_ptr()
+                .add(index)
+                .cast::<SignalFrame>()
+                .write_unaligned(frame);
+        };
+        assert_eq!(stack[index], HANDLER.restorer());
+        assert_eq!(stack[index + 1], u64::from(Signal::Interrupt));
+
+        // The handler returns, popping the restorer
+        let sp = placement.frame() + 8;
+        let address = SignalFrame::address_at_sigreturn(sp);
+        assert_eq!(address, placement.frame());
+        let index = usize::try_from((address - base) / 8).unwrap();
+        // Safety: see above.
+        let restored = unsafe {
+            stack
+                .as_ptr()
+                .add(index)
+                .cast::<SignalFrame>()
+                .read_unaligned()
+        };
+        assert_eq!(restored.signal(), Some(Signal::Interrupt));
+        assert_eq!(restored.blocked(), SignalSet::EMPTY);
+        assert_eq!(restored.fx_area(), placement.fx_area());
+        assert_eq!(restored.context(), context);
+    }
+
+    #[test]
+    fn test_sigreturn_sanitizes() {
+        let context = UserContext {
+            // IOPL 3, trap flag and interrupts disabled
+            rflags: 0x3000 | 0x100 | 0x400 | 0x1,
+            ..UserContext::default()
+        };
+        let frame = SignalFrame::new(Signal::Alarm, HANDLER, SignalSet::EMPTY, 0, context);
+        // Direction and carry flags are kept
+        assert_eq!(frame.context().rflags, 0x400 | 0x200 | 0x2 | 0x1);
+
+        let mut fx = FxArea::new();
+        fx.as_bytes_mut()[24..28].copy_from_slice(&0xFFFF_1F80_u32.to_le_bytes());
+        fx.sanitize();
+        assert_eq!(fx.mxcsr(), 0x1F80);
+    }
+}
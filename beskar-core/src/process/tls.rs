@@ -0,0 +1,104 @@
+//! Layout of the thread-local storage of userspace threads.
+//!
+//! The x86-64 System V ABI uses TLS "variant II": the FS base register holds the
+//! thread pointer, the TLS block of the binary ends right at the thread pointer,
+//! and the thread control block (TCB) starts there.
+//!
+//! Binaries are statically linked, so thread-local variables use the local-exec model:
+//! they are accessed at a constant negative offset from FS, and no TLS descriptor
+//! or `__tls_get_addr` is involved. The first word of the TCB points to itself,
+//! so that the thread pointer can be read with `mov reg, fs:0`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// The thread control block, pointed to by the thread pointer.
+pub struct Tcb {
+    /// Address of the TCB itself.
+    pub self_ptr: u64,
+    /// ID of the thread.
+    pub thread_id: u64,
+}
+
+impl Tcb {
+    /// Offset of the thread ID from the thread pointer.
+    pub const THREAD_ID_OFFSET: usize = core::mem::offset_of!(Self, thread_id);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Where the TLS block and the TCB of a thread lie in its TLS area.
+///
+/// The TLS block starts the area, and is followed by the TCB.
+pub struct TlsLayout {
+    /// Offset of the thread pointer, i.e. of the TCB, from the start of the area.
+    tp_offset: u64,
+}
+
+impl TlsLayout {
+    #[must_use]
+    /// Computes the layout for a TLS block of `mem_size` bytes, aligned to `align`.
+    ///
+    /// The area must be aligned to `align`.
+    /// Returns `None` if `align` is not a power of two, or if the size overflows.
+    pub const fn new(mem_size: u64, align: u64) -> Option<Self> {
+        // The TCB must be aligned as well
+        let align = if align > align_of::<Tcb>() as u64 {
+            align
+        } else {
+            align_of::<Tcb>() as u64
+        };
+        if !align.is_power_of_two() {
+            return None;
+        }
+        // The block ends at the thread pointer, which is aligned
+        match mem_size.checked_next_multiple_of(align) {
+            Some(tp_offset) => Some(Self { tp_offset }),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Offset of the thread pointer from the start of the area.
+    pub const fn tp_offset(&self) -> u64 {
+        self.tp_offset
+    }
+
+    #[must_use]
+    #[inline]
+    /// Size of the whole area, TCB included.
+    pub const fn size(&self) -> u64 {
+        self.tp_offset + size_of::<Tcb>() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let layout = TlsLayout::new(20, 8).unwrap();
+        assert_eq!(layout.tp_offset(), 24);
+        assert_eq!(layout.size(), 24 + 16);
+
+        // The thread pointer is aligned as much as the block
+        let layout = TlsLayout::new(20, 64).unwrap();
+        assert_eq!(layout.tp_offset(), 64);
+
+        // No TLS block: the area only holds the TCB
+        let layout = TlsLayout::new(0, 1).unwrap();
+        assert_eq!(layout.tp_offset(), 0);
+        assert_eq!(layout.size(), 16);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(TlsLayout::new(20, 24), None);
+        assert_eq!(TlsLayout::new(u64::MAX, 16), None);
+    }
+
+    #[test]
+    fn test_tcb() {
+        assert_eq!(Tcb::THREAD_ID_OFFSET, 8);
+    }
+}
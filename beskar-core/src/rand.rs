@@ -0,0 +1,192 @@
+//! Random number generation.
+//!
+//! Hardware generators produce random 64-bit words, while callers usually want
+//! an arbitrary number of bytes, see `fill_bytes`.
+//! `ChaChaRng` stretches a seed from such sources into as many bytes as needed.
+use crate::crypto::chacha20::{self, BLOCK_SIZE, KEY_SIZE, NONCE_SIZE};
+
+/// Fills `bytes` with words from `next`, in native byte order.
+///
+/// Each word is used for at most 8 bytes, so no two chunks of the buffer share a word.
+/// The last chunk takes the first bytes of its word if the length is not a multiple of 8.
+///
+/// # Errors
+///
+/// Returns the first error of `next`, leaving the rest of the buffer as it was.
+pub fn fill_bytes<E>(bytes: &mut [u8], mut next: impl FnMut() -> Result<u64, E>) -> Result<(), E> {
+    for chunk in bytes.chunks_mut(size_of::<u64>()) {
+        let word = next()?.to_ne_bytes();
+        chunk.copy_from_slice(&word[..chunk.len()]);
+    }
+    Ok(())
+}
+
+/// A cryptographically secure generator, built on ChaCha20 with fast key erasure.
+///
+/// Each request is served from the keystream of the current key, whose first 32 bytes
+/// become the next key. Once a request returns, the key that produced its bytes is gone,
+/// so the state of the generator does not reveal the bytes it already produced.
+pub struct ChaChaRng {
+    key: [u8; KEY_SIZE],
+}
+
+impl ChaChaRng {
+    #[must_use]
+    #[inline]
+    pub const fn new(seed: [u8; KEY_SIZE]) -> Self {
+        Self { key: seed }
+    }
+
+    /// Mixes a new seed into the key.
+    ///
+    /// The previous key is kept in the mix, so a weak seed does not weaken the generator.
+    pub fn reseed(&mut self, seed: &[u8; KEY_SIZE]) {
+        for (byte, seed) in self.key.iter_mut().zip(seed) {
+            *byte ^= seed;
+        }
+        // Derive the next key, so that the key is never a plain XOR with a seed.
+        self.fill(&mut []);
+    }
+
+    /// Fills the whole buffer with random bytes.
+    pub fn fill(&mut self, bytes: &mut [u8]) {
+        let key = self.key;
+        let mut blocks = (0_u64..).map(|index| Self::block(&key, index));
+
+        let first = blocks.next().unwrap();
+        self.key.copy_from_slice(&first[..KEY_SIZE]);
+
+        let (head, rest) = bytes.split_at_mut(bytes.len().min(BLOCK_SIZE - KEY_SIZE));
+        head.copy_from_slice(&first[KEY_SIZE..KEY_SIZE + head.len()]);
+        for (chunk, block) in rest.chunks_mut(BLOCK_SIZE).zip(blocks) {
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+
+    #[must_use]
+    /// Computes a keystream block, using the nonce to extend the block counter to 64 bits.
+    fn block(key: &[u8; KEY_SIZE], index: u64) -> [u8; BLOCK_SIZE] {
+        let index = index.to_le_bytes();
+        let (counter, high) = index.split_at(4);
+        let mut nonce = [0; NONCE_SIZE];
+        nonce[..4].copy_from_slice(high);
+        chacha20::block(key, u32::from_le_bytes(counter.try_into().unwrap()), &nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A xorshift generator, which is more than enough for tests.
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn test_fill_covers_buffer() {
+        // The generator never yields a zero byte, so any zero left is a byte that was not filled.
+        let mut buffer = [0; 64 + 8];
+        for start in 0..8 {
+            for len in 0..=64 {
+                buffer.fill(0);
+                fill_bytes(&mut buffer[start..start + len], || Ok::<_, ()>(u64::MAX)).unwrap();
+
+                assert!(buffer[..start].iter().all(|byte| *byte == 0));
+                assert!(buffer[start..start + len].iter().all(|byte| *byte == 0xFF));
+                assert!(buffer[start + len..].iter().all(|byte| *byte == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_uses_fresh_words() {
+        let mut state = 0x2545_F491_4F6C_DD1D;
+        let mut buffer = [0_u8; 4096];
+        fill_bytes(&mut buffer, || Ok::<_, ()>(xorshift(&mut state))).unwrap();
+
+        // No word is repeated
+        let mut words = [0_u64; 512];
+        for (word, chunk) in words.iter_mut().zip(buffer.chunks_exact(8)) {
+            *word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        }
+        words.sort_unstable();
+        assert!(words.windows(2).all(|pair| pair[0] != pair[1]));
+
+        // Roughly half of the bits are set
+        let ones = buffer.iter().map(|byte| byte.count_ones()).sum::<u32>();
+        let bits = u32::try_from(buffer.len()).unwrap() * 8;
+        assert!(ones.abs_diff(bits / 2) < bits / 50);
+    }
+
+    #[test]
+    fn test_fill_error() {
+        let mut buffer = [0_u8; 20];
+        let mut calls = 0;
+        let res = fill_bytes(&mut buffer, || {
+            calls += 1;
+            if calls == 3 { Err(()) } else { Ok(u64::MAX) }
+        });
+        assert_eq!(res, Err(()));
+        assert_eq!(buffer[..16], [0xFF; 16]);
+        assert_eq!(buffer[16..], [0; 4]);
+    }
+
+    #[test]
+    fn test_chacha_rng_keystream() {
+        let seed = [0x42; KEY_SIZE];
+        let mut rng = ChaChaRng::new(seed);
+        let mut bytes = [0; 200];
+        rng.fill(&mut bytes);
+
+        // The bytes follow the key of the next request in the keystream of the seed
+        let mut keystream = [0; 4 * BLOCK_SIZE];
+        for (counter, block) in (0..).zip(keystream.chunks_exact_mut(BLOCK_SIZE)) {
+            block.copy_from_slice(&chacha20::block(&seed, counter, &[0; NONCE_SIZE]));
+        }
+        assert_eq!(bytes, keystream[KEY_SIZE..KEY_SIZE + 200]);
+        assert_eq!(rng.key, keystream[..KEY_SIZE]);
+    }
+
+    #[test]
+    fn test_chacha_rng_key_erasure() {
+        let mut rng = ChaChaRng::new([7; KEY_SIZE]);
+        let mut first = [0; 48];
+        let mut second = [0; 48];
+        rng.fill(&mut first);
+        let key = rng.key;
+        rng.fill(&mut second);
+
+        // Every request uses a new key
+        assert_ne!(first, second);
+        assert_ne!(rng.key, key);
+        assert_ne!(rng.key, [7; KEY_SIZE]);
+
+        // The same seed gives the same bytes
+        let mut replay = ChaChaRng::new([7; KEY_SIZE]);
+        let mut bytes = [0; 48];
+        replay.fill(&mut bytes);
+        assert_eq!(bytes, first);
+    }
+
+    #[test]
+    fn test_chacha_rng_reseed() {
+        let mut rng = ChaChaRng::new([0; KEY_SIZE]);
+        let mut reseeded = ChaChaRng::new([0; KEY_SIZE]);
+        reseeded.reseed(&[1; KEY_SIZE]);
+
+        let mut bytes = [0; 4096];
+        let mut other = [0; 4096];
+        rng.fill(&mut bytes);
+        reseeded.fill(&mut other);
+        assert_ne!(bytes, other);
+
+        // Roughly half of the bits are set
+        let ones = other.iter().map(|byte| byte.count_ones()).sum::<u32>();
+        let bits = u32::try_from(other.len()).unwrap() * 8;
+        assert!(ones.abs_diff(bits / 2) < bits / 50);
+    }
+}
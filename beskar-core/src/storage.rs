@@ -59,6 +59,16 @@ pub trait KernelDevice {
     ///
     /// This function returns an error if the write operation failed
     fn write(&mut self, src: &[u8], offset: usize) -> Result<(), BlockDeviceError>;
+    /// Makes previous writes durable.
+    ///
+    /// Devices that do not buffer writes have nothing to do.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if buffered data could not be written
+    fn flush(&mut self) -> Result<(), BlockDeviceError> {
+        Ok(())
+    }
 
     fn on_open(&mut self) {}
 
@@ -1,11 +1,20 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+pub mod capture;
+pub mod display;
+pub mod framebuffer;
+pub mod iovec;
+pub mod poll;
+pub mod process;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
 #[repr(u64)]
 pub enum Syscall {
     /// Exit syscall.
     ///
-    /// The first argument is the exit code.
+    /// Exits the calling thread. The other threads of the process keep running.
+    ///
+    /// The first argument is the exit code, reported to a thread joining the calling one.
     Exit = 0,
     /// Open syscall.
     ///
@@ -13,6 +22,7 @@ pub enum Syscall {
     ///
     /// The first argument is a pointer to the file path.
     /// The second argument is the length of the path.
+    /// The third argument is a set of flags, such as `OPEN_APPEND`.
     Open = 1,
     /// Close syscall.
     ///
@@ -27,7 +37,10 @@ pub enum Syscall {
     /// The first argument is a handle to the file.
     /// The second argument is a pointer to the buffer to read into.
     /// The third argument is the length of the buffer.
-    /// The fourth argument is the offset to read from.
+    /// The fourth argument is the offset to read from, or `FILE_OFFSET_CURRENT`.
+    ///
+    /// Reading from the read end of a pipe ignores the offset, and waits until the pipe
+    /// holds data. Once the write end is closed and the pipe is empty, it returns 0.
     Read = 3,
     /// Write syscall.
     ///
@@ -36,7 +49,11 @@ pub enum Syscall {
     /// The first argument is a handle to the file.
     /// The second argument is a pointer to the buffer to write from.
     /// The third argument is the length of the buffer.
-    /// The fourth argument is the offset to write to.
+    /// The fourth argument is the offset to write to, or `FILE_OFFSET_CURRENT`.
+    ///
+    /// Writing to the write end of a pipe ignores the offset, and waits until the pipe
+    /// has room. Once the read end is closed, it raises `Signal::BrokenPipe`
+    /// and returns `WRITE_BROKEN_PIPE`.
     Write = 4,
     /// MemoryMap syscall.
     ///
@@ -62,6 +79,548 @@ pub enum Syscall {
     ///
     /// The first argument is the sleep handle to wait on.
     WaitOnEvent = 8,
+    /// Drain buffered keyboard events without blocking.
+    ///
+    /// The first argument is a pointer to a buffer of packed `KeyEvent`s (`u64`).
+    /// The second argument is the capacity of the buffer, in events.
+    ///
+    /// Returns the number of events copied, ORed with `KEYBOARD_BATCH_OVERFLOW`
+    /// if events were dropped since the last batch, or -1 on failure.
+    PollKeyboardBatch = 9,
+    /// Wait until one of several sources is ready.
+    ///
+    /// The first argument is a pointer to an array of `PollItem`s.
+    /// The second argument is the number of items.
+    /// The third argument is the timeout in milliseconds,
+    /// where 0 returns immediately and `u64::MAX` waits forever.
+    ///
+    /// Returns the number of ready items, or -1 on failure.
+    Poll = 10,
+    /// Read an environment variable of the current process.
+    ///
+    /// The first argument is a pointer to the variable name.
+    /// The second argument is the length of the name.
+    /// The third argument is a pointer to the buffer to copy the value into.
+    /// The fourth argument is the length of the buffer.
+    ///
+    /// If the name is empty, the whole environment block (`KEY=VALUE\0` entries) is copied.
+    ///
+    /// Returns the full length of the value, which may exceed the buffer length,
+    /// or -1 if the variable is unset.
+    GetEnv = 11,
+    /// Set an environment variable of the current process.
+    ///
+    /// The first argument is a pointer to the variable name.
+    /// The second argument is the length of the name.
+    /// The third argument is a pointer to the value.
+    /// The fourth argument is the length of the value.
+    ///
+    /// If the value pointer is null, the variable is removed.
+    SetEnv = 12,
+    /// Get information about a file.
+    ///
+    /// The first argument is a pointer to the file path.
+    /// The second argument is the length of the path.
+    /// The third argument is a pointer to a `FileInfo` to fill.
+    Metadata = 13,
+    /// List the entries of a directory.
+    ///
+    /// The first argument is a pointer to the directory path.
+    /// The second argument is the length of the path.
+    /// The third argument is a pointer to the buffer to fill with NUL-terminated entry names.
+    /// The fourth argument is the length of the buffer.
+    ///
+    /// Returns the size needed to hold every entry, or a negative value on failure.
+    ReadDir = 14,
+    /// List the running processes, by increasing PID.
+    ///
+    /// The first argument is a pointer to an array of `ProcessInfo` to fill.
+    /// The second argument is the capacity of the array, in entries.
+    /// The third argument is the PID to start the listing at.
+    ///
+    /// Returns the number of entries written, or a negative value on failure.
+    /// If the array is full, the listing can continue from the last PID plus one.
+    ProcessList = 15,
+    /// Map the contents of an open file into memory, read-only.
+    ///
+    /// The first argument is a handle to the file.
+    /// The second argument is the length of the mapping.
+    /// The third argument is the offset in the file, which must be a multiple of
+    /// `MMAP_FILE_OFFSET_ALIGN`.
+    ///
+    /// The file is read eagerly, and the mapping is rounded up to whole pages.
+    /// Bytes past the end of the file read as zero.
+    /// Pages of files that never change, such as those of the ramdisk,
+    /// are shared between every mapping of them.
+    /// Making such a page writable with `MemoryProtect` gives the process a private copy of it.
+    /// Mapping from an offset at or past the end of the file fails.
+    ///
+    /// Returns a pointer to the mapping, or null on failure.
+    MmapFile = 16,
+    /// Get the layout of the framebuffer.
+    ///
+    /// The first argument is a pointer to a `FbInfo` to fill.
+    FramebufferInfo = 17,
+    /// Bring the calling process to the foreground of the display.
+    ///
+    /// While a process holds the display, the framebuffer writes of other processes
+    /// are discarded. The previous holder is sent a `DisplayEvent::Lost` event.
+    ///
+    /// Fails if too many processes are waiting for the display.
+    AcquireDisplay = 18,
+    /// Give up the display.
+    ///
+    /// If the calling process was in the foreground, the most recent background
+    /// holder gets it back, along with a `DisplayEvent::Gained` event.
+    ReleaseDisplay = 19,
+    /// Take the pending display event of the calling process.
+    ///
+    /// Returns the raw `DisplayEvent`, or 0 if there is none.
+    /// Pending events can be waited for with `PollSource::Display`.
+    DisplayEvent = 20,
+    /// Wait for the next keyboard event.
+    ///
+    /// The thread is parked until an event is routed to the calling process,
+    /// which only happens while it has the keyboard focus.
+    ///
+    /// Returns the packed `KeyEvent`, or `READ_KEY_FOCUS_LOST` if the process
+    /// lost the focus while waiting.
+    ReadKeyBlocking = 21,
+    /// Get the monotonic time, in microseconds.
+    ///
+    /// Returns `u64::MAX` if no timer is available.
+    ClockMonotonic = 22,
+    /// Map the clock shared by the kernel into the calling process, read-only.
+    ///
+    /// The clock is a `SeqLock<TscClock>`, updated by the kernel,
+    /// that converts TSC values to the monotonic time.
+    ///
+    /// Returns a pointer to the clock, or null on failure.
+    MapClock = 23,
+    /// Read from a file into several buffers, one after the other.
+    ///
+    /// The first argument is a handle to the file.
+    /// The second argument is a pointer to an array of `IoVec`s.
+    /// The third argument is the number of `IoVec`s, at most `MAX_IOVECS`.
+    /// The fourth argument is the offset to read from, or `FILE_OFFSET_CURRENT`.
+    ///
+    /// Every buffer is checked before reading, and their total length must not exceed
+    /// `MAX_VECTORED_BYTES`.
+    ///
+    /// Pipes are read as with `Read`.
+    ///
+    /// Returns the total number of bytes read, or -1 on failure.
+    ReadV = 24,
+    /// Write several buffers to a file, one after the other.
+    ///
+    /// The first argument is a handle to the file.
+    /// The second argument is a pointer to an array of `IoVec`s.
+    /// The third argument is the number of `IoVec`s, at most `MAX_IOVECS`.
+    /// The fourth argument is the offset to write to, or `FILE_OFFSET_CURRENT`.
+    ///
+    /// Every buffer is checked before writing, and their total length must not exceed
+    /// `MAX_VECTORED_BYTES`.
+    ///
+    /// Pipes are written as with `Write`.
+    ///
+    /// Returns the total number of bytes written, `WRITE_BROKEN_PIPE`, or -1 on failure.
+    WriteV = 25,
+    /// Flush the data and metadata of a file to its device.
+    ///
+    /// The first argument is a handle to the file.
+    ///
+    /// The entry of the file in its parent directory is flushed too, so that its size persists.
+    ///
+    /// Returns a `SyscallExitCode`, which is a failure if the device write fails.
+    Fsync = 26,
+    /// Take the frames captured on the network interface, for diagnostics.
+    ///
+    /// The first argument is a pointer to a buffer.
+    /// The second argument is the length of the buffer.
+    /// The third argument is the raw `EtherType` to capture from now on, or 0 for every frame.
+    ///
+    /// Capture starts on the first call. Frames are written as `FrameRecord`s, each followed
+    /// by the captured bytes and padded to `RECORD_ALIGN`. Frames that do not fit are kept
+    /// for the next call. When the kernel ring is full, new frames are dropped,
+    /// and counted in the record of the next frame captured.
+    ///
+    /// Returns the number of bytes written, or a negative value on failure.
+    PacketCapture = 27,
+    /// Set the program break, i.e. the end of the heap of the calling process.
+    ///
+    /// The first argument is the new break, or 0 to leave it unchanged.
+    ///
+    /// The heap starts empty, at an address chosen by the kernel, and may grow up to
+    /// `BRK_MAX_HEAP_SIZE` bytes. Its pages are mapped on first access, read-write.
+    /// Moving the break down unmaps the pages past it.
+    ///
+    /// Returns the break after the call, which is unchanged on failure,
+    /// or 0 if the heap cannot be reserved.
+    Brk = 28,
+    /// Move the program break by a signed number of bytes.
+    ///
+    /// The first argument is the increment, as an `i64`.
+    ///
+    /// Returns the break before the call, which is the start of the new memory
+    /// when growing the heap, or null on failure.
+    Sbrk = 29,
+    /// Create a thread in the calling process.
+    ///
+    /// The first argument is the address of the entry point, an `extern "C" fn(u64) -> !`.
+    /// The second argument is passed to the entry point.
+    /// The third argument is the maximum size of the stack, between `THREAD_STACK_MIN_SIZE`
+    /// and `THREAD_STACK_MAX_SIZE`, or 0 for `THREAD_STACK_MAX_SIZE`.
+    ///
+    /// The stack is followed by a guard page and grows on demand.
+    /// The thread gets its own copy of the TLS template of the binary.
+    /// It must end with `Exit`, and can be waited for with `ThreadJoin`,
+    /// unless it is detached with `ThreadDetach`.
+    ///
+    /// Returns the ID of the thread, or a negative value on failure.
+    ThreadCreate = 30,
+    /// Wait for a thread created by `ThreadCreate` to exit.
+    ///
+    /// The first argument is the ID of the thread.
+    ///
+    /// A thread can only be joined once, by a thread of the same process.
+    /// Until then, it counts against the limit of `MAX_JOINABLE_THREADS`
+    /// threads created and neither joined nor detached.
+    ///
+    /// Returns the `ExitCode` the thread exited with, or a negative value if the thread
+    /// is not joinable.
+    ThreadJoin = 31,
+    /// Exit the calling process.
+    ///
+    /// The first argument is the exit code.
+    ///
+    /// The calling thread exits right away. The other threads of the process exit
+    /// the next time they leave a syscall or are preempted in userspace.
+    ProcessExit = 32,
+    /// Take or release an advisory lock on an open file.
+    ///
+    /// The first argument is a handle to the file.
+    /// The second argument is the `FileLockOperation`.
+    /// The third argument is a set of flags, such as `FLOCK_NONBLOCKING`.
+    ///
+    /// Locks only exclude other locks, and do not prevent reads or writes.
+    /// A handle holds at most one lock: locking it again converts the lock.
+    /// Locks are released when their handle is closed, or when their process exits.
+    ///
+    /// Without `FLOCK_NONBLOCKING`, waits until no other handle holds a conflicting lock.
+    ///
+    /// Returns 0 on success, `FLOCK_WOULD_BLOCK` if the lock is held and the call
+    /// was non-blocking, or -1 on failure.
+    Flock = 33,
+    /// Move the position of a file handle.
+    ///
+    /// The first argument is a handle to the file.
+    /// The second argument is the `SeekWhence` the offset is relative to.
+    /// The third argument is the offset, as a signed integer.
+    ///
+    /// The position is where reads and writes at `FILE_OFFSET_CURRENT` take place,
+    /// and is moved past the bytes they transfer. Every handle has its own position,
+    /// which starts at 0.
+    ///
+    /// Seeking past the end of the file is allowed: reads there return 0 bytes, and writes
+    /// extend the file. Seeking before the start of the file fails.
+    ///
+    /// Returns the new position, or -1 on failure.
+    Seek = 34,
+    /// Create a pipe, a bounded byte channel.
+    ///
+    /// The first argument is a pointer to an array of two `i64`, filled with a handle
+    /// to the read end and a handle to the write end of the pipe.
+    ///
+    /// The handles are used with `Read`, `Write`, `ReadV`, `WriteV`, `Poll` and `Close`.
+    Pipe = 35,
+    /// Duplicate a handle.
+    ///
+    /// The first argument is the handle to duplicate.
+    /// The second argument is a set of flags, such as `DUP_CLOSE_ON_EXEC`.
+    ///
+    /// The new handle refers to the same file or pipe end. A duplicated file handle starts
+    /// at the position of the original, but then moves on its own, and holds no lock.
+    ///
+    /// Returns the new handle, or -1 on failure.
+    Dup = 36,
+    /// Duplicate a handle into a given handle number.
+    ///
+    /// The first argument is the handle to duplicate.
+    /// The second argument is the handle number to duplicate it into,
+    /// which is closed first if it is open. Numbers below 3 are the standard handles,
+    /// `STDIN_HANDLE`, `STDOUT_HANDLE` and `STDERR_HANDLE`.
+    /// The third argument is a set of flags, such as `DUP_CLOSE_ON_EXEC`.
+    ///
+    /// If both handles are the same, only the flags of the handle are set.
+    Dup2 = 37,
+    /// Read from a file, waiting at most a given time for data.
+    ///
+    /// The first four arguments are those of `Read`.
+    /// The fifth argument is the timeout, encoded as with `PollTimeout::to_raw`.
+    /// A null timeout makes the read non-blocking.
+    ///
+    /// Only reads that would wait, such as reads from an empty pipe, are affected.
+    ///
+    /// Returns the number of bytes read, `READ_TIMED_OUT` if no data arrived in time,
+    /// or -1 on failure.
+    ReadTimeout = 38,
+    /// Create a poll set.
+    ///
+    /// A poll set is a list of sources kept by the kernel, so that waiting on them
+    /// does not pass the whole list every time. It lives until the process exits.
+    ///
+    /// Returns the ID of the set, or -1 on failure,
+    /// such as when the process already has `MAX_POLL_SETS` sets.
+    PollCreate = 39,
+    /// Add a source to a poll set.
+    ///
+    /// The first argument is the ID of the set.
+    /// The second argument is a pointer to a `PollItem`, holding the source and the events of interest.
+    ///
+    /// Adding a source that is already in the set replaces its events of interest.
+    /// A set holds at most `MAX_POLL_SET_SOURCES` sources.
+    PollAdd = 40,
+    /// Wait until one of the sources of a poll set is ready.
+    ///
+    /// The first argument is the ID of the set.
+    /// The second argument is a pointer to an array of `PollItem`s, filled with the ready sources.
+    /// The third argument is the capacity of the array, in items.
+    /// The fourth argument is the timeout, as with `Poll`.
+    ///
+    /// Readiness is level-triggered: a source is reported by every wait as long as it is ready.
+    /// A source that becomes invalid, such as a closed handle, is reported once with
+    /// `PollEvents::INVALID`, then removed from the set.
+    ///
+    /// Returns the number of ready items, or -1 on failure.
+    PollWait = 41,
+    /// Remove a source from a poll set.
+    ///
+    /// The first argument is the ID of the set.
+    /// The second argument is a pointer to a `PollItem` holding the source.
+    /// Its events of interest are ignored.
+    PollDel = 42,
+    /// Restrict the cores the calling thread may run on.
+    ///
+    /// The first argument is a `CpuMask`, where bit `n` allows the core of ID `n`.
+    /// Threads start with `CpuMask::ALL`, which allows every core.
+    ///
+    /// The mask must allow at least one online core. If the current core is not allowed,
+    /// the thread moves to an allowed one before the syscall returns.
+    SetAffinity = 43,
+    /// Set what the calling process does when a signal is raised.
+    ///
+    /// The first argument is the `Signal`.
+    /// The second argument is `SIG_DEFAULT`, `SIG_IGNORE`, or the address of the handler,
+    /// an `extern "C" fn(Signal)`.
+    /// The third argument is the address the handler returns to, which must call `SigReturn`.
+    /// It is only used with a handler.
+    ///
+    /// Pending signals with a handler are delivered when a thread of the process leaves a syscall.
+    /// The signal is blocked until the handler returns.
+    /// Signals without a handler take their default action, see `Signal::default_action`.
+    SigAction = 44,
+    /// Return from a signal handler.
+    ///
+    /// Restores the state the thread had before the handler ran, from the `SignalFrame`
+    /// right below the stack pointer. This syscall does not return.
+    SigReturn = 45,
+    /// Arm the timer of the calling process, which raises `Signal::Alarm` when it expires.
+    ///
+    /// The first argument is the delay before the first expiration, in milliseconds.
+    /// A null delay disarms the timer.
+    /// The second argument is the period of the following expirations, in milliseconds,
+    /// or 0 for a one-shot timer.
+    ///
+    /// Arming the timer replaces its previous setting. If the timer expires again while
+    /// the signal is still pending, the expirations are coalesced into a single signal.
+    SetTimer = 46,
+    /// Fill a buffer with random bytes from the kernel generator.
+    ///
+    /// The first argument is a pointer to the buffer.
+    /// The second argument is the length of the buffer.
+    /// The third argument is a set of flags, such as `GETRANDOM_NONBLOCKING`.
+    ///
+    /// Early in boot, the generator may not be seeded yet. The syscall then waits until it is,
+    /// unless `GETRANDOM_NONBLOCKING` is set, in which case it returns `GETRANDOM_WOULD_BLOCK`.
+    ///
+    /// Returns the length of the buffer, which is always filled entirely, or -1 on failure.
+    GetRandom = 47,
+    /// Give up joining a thread created by `ThreadCreate`.
+    ///
+    /// The first argument is the ID of the thread.
+    ///
+    /// The thread keeps running, and no longer counts against `MAX_JOINABLE_THREADS`.
+    /// It cannot be joined anymore, and its exit code is dropped when it exits.
+    ///
+    /// Returns a `SyscallExitCode`, which is a failure if the thread is not joinable.
+    ThreadDetach = 48,
+}
+
+impl Syscall {
+    /// Every syscall, by increasing number.
+    pub const ALL: [Self; 49] = [
+        Self::Exit,
+        Self::Open,
+        Self::Close,
+        Self::Read,
+        Self::Write,
+        Self::MemoryMap,
+        Self::MemoryProtect,
+        Self::Sleep,
+        Self::WaitOnEvent,
+        Self::PollKeyboardBatch,
+        Self::Poll,
+        Self::GetEnv,
+        Self::SetEnv,
+        Self::Metadata,
+        Self::ReadDir,
+        Self::ProcessList,
+        Self::MmapFile,
+        Self::FramebufferInfo,
+        Self::AcquireDisplay,
+        Self::ReleaseDisplay,
+        Self::DisplayEvent,
+        Self::ReadKeyBlocking,
+        Self::ClockMonotonic,
+        Self::MapClock,
+        Self::ReadV,
+        Self::WriteV,
+        Self::Fsync,
+        Self::PacketCapture,
+        Self::Brk,
+        Self::Sbrk,
+        Self::ThreadCreate,
+        Self::ThreadJoin,
+        Self::ProcessExit,
+        Self::Flock,
+        Self::Seek,
+        Self::Pipe,
+        Self::Dup,
+        Self::Dup2,
+        Self::ReadTimeout,
+        Self::PollCreate,
+        Self::PollAdd,
+        Self::PollWait,
+        Self::PollDel,
+        Self::SetAffinity,
+        Self::SigAction,
+        Self::SigReturn,
+        Self::SetTimer,
+        Self::GetRandom,
+        Self::ThreadDetach,
+    ];
+
+    #[must_use]
+    /// Number of arguments the syscall reads, from the first one.
+    pub const fn arg_count(self) -> u8 {
+        match self {
+            Self::AcquireDisplay
+            | Self::ReleaseDisplay
+            | Self::DisplayEvent
+            | Self::ReadKeyBlocking
+            | Self::ClockMonotonic
+            | Self::MapClock
+            | Self::PollCreate
+            | Self::SigReturn => 0,
+            Self::Exit
+            | Self::Close
+            | Self::Sleep
+            | Self::WaitOnEvent
+            | Self::FramebufferInfo
+            | Self::Fsync
+            | Self::Brk
+            | Self::Sbrk
+            | Self::ThreadJoin
+            | Self::ThreadDetach
+            | Self::ProcessExit
+            | Self::Pipe
+            | Self::SetAffinity => 1,
+            Self::PollKeyboardBatch
+            | Self::Dup
+            | Self::PollAdd
+            | Self::PollDel
+            | Self::SetTimer => 2,
+            Self::Open
+            | Self::MemoryMap
+            | Self::MemoryProtect
+            | Self::Poll
+            | Self::Metadata
+            | Self::ProcessList
+            | Self::MmapFile
+            | Self::PacketCapture
+            | Self::ThreadCreate
+            | Self::Flock
+            | Self::Seek
+            | Self::Dup2
+            | Self::SigAction
+            | Self::GetRandom => 3,
+            Self::Read
+            | Self::Write
+            | Self::ReadV
+            | Self::WriteV
+            | Self::GetEnv
+            | Self::SetEnv
+            | Self::ReadDir
+            | Self::PollWait => 4,
+            Self::ReadTimeout => 5,
+        }
+    }
+
+    #[must_use]
+    /// A short description of the syscall.
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Exit => "Exit the current thread",
+            Self::Open => "Open a file",
+            Self::Close => "Close a file handle",
+            Self::Read => "Read from a file",
+            Self::Write => "Write to a file",
+            Self::MemoryMap => "Allocate memory",
+            Self::MemoryProtect => "Change the protection of memory",
+            Self::Sleep => "Sleep for a duration",
+            Self::WaitOnEvent => "Sleep until an event is signalled",
+            Self::PollKeyboardBatch => "Drain buffered keyboard events",
+            Self::Poll => "Wait for sources to be ready",
+            Self::GetEnv => "Read an environment variable",
+            Self::SetEnv => "Set an environment variable",
+            Self::Metadata => "Get information about a file",
+            Self::ReadDir => "List the entries of a directory",
+            Self::ProcessList => "List the running processes",
+            Self::MmapFile => "Map a file into memory",
+            Self::FramebufferInfo => "Get the layout of the framebuffer",
+            Self::AcquireDisplay => "Bring the process to the foreground",
+            Self::ReleaseDisplay => "Give up the display",
+            Self::DisplayEvent => "Take the pending display event",
+            Self::ReadKeyBlocking => "Wait for the next keyboard event",
+            Self::ClockMonotonic => "Get the monotonic time",
+            Self::MapClock => "Map the clock shared by the kernel",
+            Self::ReadV => "Read from a file into several buffers",
+            Self::WriteV => "Write several buffers to a file",
+            Self::Fsync => "Flush a file to its device",
+            Self::PacketCapture => "Take the captured network frames",
+            Self::Brk => "Set the end of the heap",
+            Self::Sbrk => "Grow or shrink the heap",
+            Self::ThreadCreate => "Create a thread",
+            Self::ThreadJoin => "Wait for a thread to exit",
+            Self::ProcessExit => "Exit every thread of the process",
+            Self::Flock => "Lock or unlock a file",
+            Self::Seek => "Move the position of a file handle",
+            Self::Pipe => "Create a pipe",
+            Self::Dup => "Duplicate a handle",
+            Self::Dup2 => "Duplicate a handle into a given number",
+            Self::ReadTimeout => "Read from a file, with a timeout",
+            Self::PollCreate => "Create a poll set",
+            Self::PollAdd => "Add a source to a poll set",
+            Self::PollWait => "Wait for the sources of a poll set to be ready",
+            Self::PollDel => "Remove a source from a poll set",
+            Self::SetAffinity => "Restrict the cores the thread may run on",
+            Self::SigAction => "Set the handler of a signal",
+            Self::SigReturn => "Return from a signal handler",
+            Self::SetTimer => "Arm the alarm timer of the process",
+            Self::GetRandom => "Fill a buffer with random bytes",
+            Self::ThreadDetach => "Let a thread exit without being joined",
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
@@ -122,6 +681,80 @@ impl SyscallReturnValue {
 pub enum ExitCode {
     Success = 0,
     Failure = 1,
+    /// The program, or the thread, panicked.
+    Panicked = 2,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u64)]
+pub enum FileKind {
+    File = 0,
+    Directory = 1,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+/// Information about a file, as returned by the `Metadata` syscall.
+pub struct FileInfo {
+    size: u64,
+    kind: FileKind,
+}
+
+impl FileInfo {
+    #[must_use]
+    #[inline]
+    pub const fn new(size: u64, kind: FileKind) -> Self {
+        Self { size, kind }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Size of the file, in bytes.
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_file(&self) -> bool {
+        matches!(self.kind, FileKind::File)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_dir(&self) -> bool {
+        matches!(self.kind, FileKind::Directory)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u64)]
+/// Operation of the `Flock` syscall.
+pub enum FileLockOperation {
+    /// Take a lock that other handles can share.
+    Shared = 0,
+    /// Take a lock that no other handle can hold.
+    Exclusive = 1,
+    /// Release the lock of the handle.
+    Unlock = 2,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u64)]
+/// Reference point of the offset of the `Seek` syscall.
+pub enum SeekWhence {
+    /// The start of the file.
+    Start = 0,
+    /// The current position of the handle.
+    Current = 1,
+    /// The end of the file.
+    End = 2,
 }
 
 /// Syscall-related constants
@@ -132,12 +765,76 @@ pub mod consts {
     pub const MFLAGS_WRITE: u64 = 0x2;
     /// Memory protection flags - execute permission
     pub const MFLAGS_EXECUTE: u64 = 0x4;
+
+    /// Keyboard batch flag - events were dropped because the kernel queue was full
+    pub const KEYBOARD_BATCH_OVERFLOW: u64 = 1 << 62;
+    /// Blocking key read result - the process lost the keyboard focus while waiting
+    pub const READ_KEY_FOCUS_LOST: u64 = u64::MAX;
+
+    /// Required alignment of the file offset of `MmapFile`, which is the page size
+    pub const MMAP_FILE_OFFSET_ALIGN: u64 = 4096;
+
+    /// Maximum size of the heap of a process, grown with `Brk` and `Sbrk`
+    pub const BRK_MAX_HEAP_SIZE: u64 = 256 * 1024 * 1024;
+
+    /// Minimum size of the stack of a thread created with `ThreadCreate`
+    pub const THREAD_STACK_MIN_SIZE: u64 = 16 * 1024;
+    /// Maximum size of the stack of a thread
+    pub const THREAD_STACK_MAX_SIZE: u64 = 8 * 1024 * 1024;
+    /// Maximum number of threads of a process created with `ThreadCreate`, and neither joined nor detached
+    pub const MAX_JOINABLE_THREADS: usize = 64;
+
+    /// File lock flag - fail instead of waiting for a lock held by another handle
+    pub const FLOCK_NONBLOCKING: u64 = 0x1;
+    /// Non-blocking file lock result - another handle holds a conflicting lock
+    pub const FLOCK_WOULD_BLOCK: i64 = 1;
+    /// File offset - read or write at the position of the handle, and move it past the bytes transferred
+    pub const FILE_OFFSET_CURRENT: u64 = u64::MAX;
+    /// Open flag - every write goes to the end of the file, whatever its offset
+    pub const OPEN_APPEND: u64 = 0x1;
+    /// Write result - the read end of the pipe is closed, so the data can never be read
+    pub const WRITE_BROKEN_PIPE: i64 = -2;
+    /// Read result - no data arrived before the timeout of `ReadTimeout` elapsed
+    pub const READ_TIMED_OUT: i64 = -2;
+    /// Handle flag - the handle is not inherited by the children of the process
+    pub const DUP_CLOSE_ON_EXEC: u64 = 0x1;
+    /// Standard handle - input of the process
+    pub const STDIN_HANDLE: i64 = 0;
+    /// Standard handle - output of the process
+    pub const STDOUT_HANDLE: i64 = 1;
+    /// Standard handle - error output of the process
+    pub const STDERR_HANDLE: i64 = 2;
+    /// Maximum number of poll sets of a process created with `PollCreate`
+    pub const MAX_POLL_SETS: usize = 16;
+    /// Maximum number of sources of a poll set
+    pub const MAX_POLL_SET_SOURCES: usize = 64;
+    /// Signal handler - take the default action of the signal
+    pub const SIG_DEFAULT: u64 = 0;
+    /// Signal handler - discard the signal
+    pub const SIG_IGNORE: u64 = 1;
+    /// Random bytes flag - fail instead of waiting for the generator to be seeded
+    pub const GETRANDOM_NONBLOCKING: u64 = 0x1;
+    /// Non-blocking random bytes result - the generator is not seeded yet
+    pub const GETRANDOM_WOULD_BLOCK: i64 = -2;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_syscall_table() {
+        for (number, syscall) in (0_u64..).zip(Syscall::ALL) {
+            // The kernel recognizes syscalls by their number.
+            assert_eq!(u64::from(syscall), number);
+            assert_eq!(Syscall::try_from(number), Ok(syscall));
+            assert!(syscall.arg_count() <= 6);
+            assert!(!syscall.description().is_empty());
+        }
+        // Numbers are contiguous, so every variant is in the table.
+        assert!(Syscall::try_from(Syscall::ALL.len() as u64).is_err());
+    }
+
     #[test]
     fn test_syscall_exit_code_unwrap() {
         SyscallExitCode::Success.unwrap();
@@ -0,0 +1,171 @@
+//! Types shared by the kernel and userspace for the `PacketCapture` syscall.
+//!
+//! Captured frames are written one after the other, each as a `FrameRecord`
+//! followed by the captured bytes, padded to `RECORD_ALIGN`.
+
+/// Alignment of the records in a capture buffer.
+pub const RECORD_ALIGN: usize = 8;
+
+/// Size of the header of a record, in bytes.
+const HEADER_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Header of a captured frame, as written by the `PacketCapture` syscall.
+pub struct FrameRecord {
+    timestamp_us: u64,
+    original_len: u16,
+    captured_len: u16,
+    dropped_before: u32,
+}
+
+impl FrameRecord {
+    #[must_use]
+    #[inline]
+    pub const fn new(
+        timestamp_us: u64,
+        original_len: u16,
+        captured_len: u16,
+        dropped_before: u32,
+    ) -> Self {
+        Self {
+            timestamp_us,
+            original_len,
+            captured_len,
+            dropped_before,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Monotonic time at which the frame was captured, in microseconds.
+    pub const fn timestamp_us(&self) -> u64 {
+        self.timestamp_us
+    }
+
+    #[must_use]
+    #[inline]
+    /// Length of the frame on the wire.
+    pub const fn original_len(&self) -> u16 {
+        self.original_len
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of bytes following the header, which may be less than `original_len`.
+    pub const fn captured_len(&self) -> u16 {
+        self.captured_len
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of frames dropped right before this one, because the capture ring was full.
+    pub const fn dropped_before(&self) -> u32 {
+        self.dropped_before
+    }
+
+    #[must_use]
+    #[inline]
+    /// Total length of the record, header and padding included.
+    pub const fn record_len(&self) -> usize {
+        (HEADER_LEN + self.captured_len as usize).next_multiple_of(RECORD_ALIGN)
+    }
+
+    /// Writes the record and the captured bytes at the start of `buffer`.
+    ///
+    /// Returns the length of the record, or `None` if it does not fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is not `captured_len` bytes long.
+    pub fn write(&self, data: &[u8], buffer: &mut [u8]) -> Option<usize> {
+        assert_eq!(data.len(), usize::from(self.captured_len));
+
+        let len = self.record_len();
+        let record = buffer.get_mut(..len)?;
+        record[0..8].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        record[8..10].copy_from_slice(&self.original_len.to_le_bytes());
+        record[10..12].copy_from_slice(&self.captured_len.to_le_bytes());
+        record[12..16].copy_from_slice(&self.dropped_before.to_le_bytes());
+        record[HEADER_LEN..HEADER_LEN + data.len()].copy_from_slice(data);
+        record[HEADER_LEN + data.len()..].fill(0);
+        Some(len)
+    }
+
+    #[must_use]
+    /// Reads the record at the start of `buffer`, along with the captured bytes.
+    ///
+    /// Returns `None` if the buffer is too short.
+    pub fn read(buffer: &[u8]) -> Option<(Self, &[u8])> {
+        let header = buffer.get(..HEADER_LEN)?;
+        let record = Self {
+            timestamp_us: u64::from_le_bytes(header[0..8].try_into().ok()?),
+            original_len: u16::from_le_bytes(header[8..10].try_into().ok()?),
+            captured_len: u16::from_le_bytes(header[10..12].try_into().ok()?),
+            dropped_before: u32::from_le_bytes(header[12..16].try_into().ok()?),
+        };
+        let data = buffer.get(HEADER_LEN..HEADER_LEN + usize::from(record.captured_len))?;
+        Some((record, data))
+    }
+}
+
+/// Iterator over the records of a capture buffer.
+pub struct Records<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Records<'a> {
+    #[must_use]
+    #[inline]
+    /// Iterates over the records of the bytes written by the `PacketCapture` syscall.
+    pub const fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = (FrameRecord, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (record, data) = FrameRecord::read(self.buffer)?;
+        self.buffer = self.buffer.get(record.record_len()..).unwrap_or_default();
+        Some((record, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_len() {
+        assert_eq!(FrameRecord::new(0, 0, 0, 0).record_len(), 16);
+        assert_eq!(FrameRecord::new(0, 60, 1, 0).record_len(), 24);
+        assert_eq!(FrameRecord::new(0, 60, 8, 0).record_len(), 24);
+        assert_eq!(FrameRecord::new(0, 60, 9, 0).record_len(), 32);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut buffer = [0xAA; 64];
+        let first = FrameRecord::new(1234, 60, 3, 0);
+        let second = FrameRecord::new(5678, 1514, 5, 7);
+
+        let len = first.write(&[1, 2, 3], &mut buffer).unwrap();
+        assert_eq!(len, 24);
+        let len = len + second.write(&[4, 5, 6, 7, 8], &mut buffer[len..]).unwrap();
+        assert_eq!(len, 48);
+
+        let mut records = Records::new(&buffer[..len]);
+        assert_eq!(records.next(), Some((first, &[1, 2, 3][..])));
+        assert_eq!(records.next(), Some((second, &[4, 5, 6, 7, 8][..])));
+        assert_eq!(records.next(), None);
+    }
+
+    #[test]
+    fn test_does_not_fit() {
+        let mut buffer = [0; 20];
+        let record = FrameRecord::new(0, 60, 8, 0);
+        assert_eq!(record.write(&[0; 8], &mut buffer), None);
+        assert_eq!(FrameRecord::read(&buffer[..12]), None);
+    }
+}
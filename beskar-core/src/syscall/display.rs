@@ -0,0 +1,12 @@
+//! Types shared by the kernel and userspace for the display handoff syscalls.
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u64)]
+/// A change of display ownership, as returned by the `DisplayEvent` syscall.
+pub enum DisplayEvent {
+    /// The app holds the display again, and should redraw the whole screen.
+    Gained = 1,
+    /// Another app holds the display: drawing is discarded until it is gained back.
+    Lost = 2,
+}
@@ -0,0 +1,189 @@
+//! Types shared by the kernel and userspace for the `FramebufferInfo` syscall.
+use crate::video::{Info, PixelBitmask, PixelFormat};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// Layout of the framebuffer, as returned by the `FramebufferInfo` syscall.
+///
+/// Unlike `video::Info`, its representation is stable, so that it can cross the syscall boundary.
+pub struct FbInfo {
+    size: u32,
+    width: u16,
+    height: u16,
+    stride: u16,
+    bytes_per_pixel: u8,
+    /// One of the `FORMAT_*` values.
+    pixel_format: u8,
+    /// Only meaningful for `FORMAT_BITMASK`.
+    bitmask: PixelBitmask,
+}
+
+impl FbInfo {
+    const FORMAT_RGB: u8 = 0;
+    const FORMAT_BGR: u8 = 1;
+    const FORMAT_BITMASK: u8 = 2;
+
+    /// An entry to initialize buffers with.
+    pub const EMPTY: Self = Self {
+        size: 0,
+        width: 0,
+        height: 0,
+        stride: 0,
+        bytes_per_pixel: 0,
+        pixel_format: Self::FORMAT_RGB,
+        bitmask: PixelBitmask {
+            red: 0,
+            green: 0,
+            blue: 0,
+        },
+    };
+
+    #[must_use]
+    pub const fn new(info: &Info) -> Self {
+        let (pixel_format, bitmask) = match info.pixel_format() {
+            PixelFormat::Rgb => (Self::FORMAT_RGB, Self::EMPTY.bitmask),
+            PixelFormat::Bgr => (Self::FORMAT_BGR, Self::EMPTY.bitmask),
+            PixelFormat::Bitmask(bitmask) => (Self::FORMAT_BITMASK, bitmask),
+        };
+        Self {
+            size: info.size(),
+            width: info.width(),
+            height: info.height(),
+            stride: info.stride(),
+            bytes_per_pixel: info.bytes_per_pixel(),
+            pixel_format,
+            bitmask,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// The total size in bytes.
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    #[must_use]
+    #[inline]
+    /// The width in pixels.
+    pub const fn width(&self) -> u16 {
+        self.width
+    }
+
+    #[must_use]
+    #[inline]
+    /// The height in pixels.
+    pub const fn height(&self) -> u16 {
+        self.height
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of pixels between the start of a line and the start of the next.
+    pub const fn stride(&self) -> u16 {
+        self.stride
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn bytes_per_pixel(&self) -> u8 {
+        self.bytes_per_pixel
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the pixel format, or `None` if the raw value is invalid.
+    pub const fn pixel_format(&self) -> Option<PixelFormat> {
+        match self.pixel_format {
+            Self::FORMAT_RGB => Some(PixelFormat::Rgb),
+            Self::FORMAT_BGR => Some(PixelFormat::Bgr),
+            Self::FORMAT_BITMASK => Some(PixelFormat::Bitmask(self.bitmask)),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Converts back to `video::Info`, or returns `None` if the pixel format is invalid.
+    pub const fn to_info(&self) -> Option<Info> {
+        let Some(pixel_format) = self.pixel_format() else {
+            return None;
+        };
+        Some(Info::new(
+            self.size,
+            self.width,
+            self.height,
+            pixel_format,
+            self.stride,
+            self.bytes_per_pixel,
+        ))
+    }
+}
+
+impl From<Info> for FbInfo {
+    #[inline]
+    fn from(info: Info) -> Self {
+        Self::new(&info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Copies the info through raw bytes, as it crosses the syscall boundary.
+    fn marshal(info: &FbInfo) -> FbInfo {
+        let mut bytes = [0_u8; size_of::<FbInfo>()];
+        // Safety: `FbInfo` is `repr(C)` and the buffer has the right size.
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                core::ptr::from_ref(info).cast::<u8>(),
+                bytes.as_mut_ptr(),
+                bytes.len(),
+            );
+            bytes.as_ptr().cast::<FbInfo>().read_unaligned()
+        }
+    }
+
+    #[test]
+    fn test_marshaling() {
+        let bitmask = PixelBitmask {
+            red: 0xFF00_0000,
+            green: 0x00FF_0000,
+            blue: 0x0000_FF00,
+        };
+        for pixel_format in [
+            PixelFormat::Rgb,
+            PixelFormat::Bgr,
+            PixelFormat::Bitmask(bitmask),
+        ] {
+            let info = Info::new(1280 * 4 * 720, 1270, 720, pixel_format, 1280, 4);
+            let fb_info = marshal(&FbInfo::from(info));
+
+            assert_eq!(fb_info.width(), 1270);
+            assert_eq!(fb_info.height(), 720);
+            assert_eq!(fb_info.stride(), 1280);
+            assert_eq!(fb_info.bytes_per_pixel(), 4);
+            assert_eq!(fb_info.size(), 1280 * 4 * 720);
+            assert_eq!(fb_info.pixel_format(), Some(pixel_format));
+            assert_eq!(fb_info.to_info(), Some(info));
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(size_of::<FbInfo>(), 24);
+        assert_eq!(align_of::<FbInfo>(), 4);
+        assert_eq!(core::mem::offset_of!(FbInfo, pixel_format), 11);
+        assert_eq!(core::mem::offset_of!(FbInfo, bitmask), 12);
+    }
+
+    #[test]
+    fn test_invalid_pixel_format() {
+        let mut info = FbInfo::EMPTY;
+        info.pixel_format = 3;
+        let info = marshal(&info);
+        assert_eq!(info.pixel_format(), None);
+        assert_eq!(info.to_info(), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Types shared by the kernel and userspace for the vectored I/O syscalls.
+
+/// Maximum number of buffers of a vectored operation.
+pub const MAX_IOVECS: usize = 64;
+/// Maximum total size of a vectored operation, in bytes.
+pub const MAX_VECTORED_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// A buffer of a vectored operation, as passed to the `ReadV` and `WriteV` syscalls.
+pub struct IoVec {
+    ptr: u64,
+    len: u64,
+}
+
+impl IoVec {
+    #[must_use]
+    #[inline]
+    pub const fn new(ptr: u64, len: u64) -> Self {
+        Self { ptr, len }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Address of the buffer.
+    pub const fn ptr(&self) -> u64 {
+        self.ptr
+    }
+
+    #[must_use]
+    #[inline]
+    /// Length of the buffer, in bytes.
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[must_use]
+/// Returns the total length of the buffers,
+/// or `None` if there are more than `MAX_IOVECS` buffers or more than `MAX_VECTORED_BYTES` bytes.
+pub fn total_len(iovecs: &[IoVec]) -> Option<u64> {
+    if iovecs.len() > MAX_IOVECS {
+        return None;
+    }
+    iovecs
+        .iter()
+        .try_fold(0_u64, |total, iovec| total.checked_add(iovec.len()))
+        .filter(|&total| total <= MAX_VECTORED_BYTES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_len() {
+        assert_eq!(total_len(&[]), Some(0));
+        assert_eq!(
+            total_len(&[
+                IoVec::new(0x1000, 3),
+                IoVec::new(0x2000, 0),
+                IoVec::new(0x3000, 5)
+            ]),
+            Some(8)
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let iovecs = [IoVec::new(0x1000, 1); MAX_IOVECS + 1];
+        assert_eq!(total_len(&iovecs[..MAX_IOVECS]), Some(MAX_IOVECS as u64));
+        assert_eq!(total_len(&iovecs), None);
+
+        assert_eq!(
+            total_len(&[IoVec::new(0x1000, MAX_VECTORED_BYTES)]),
+            Some(MAX_VECTORED_BYTES)
+        );
+        assert_eq!(
+            total_len(&[
+                IoVec::new(0x1000, MAX_VECTORED_BYTES),
+                IoVec::new(0x1000, 1)
+            ]),
+            None
+        );
+        // Lengths that overflow when summed.
+        assert_eq!(
+            total_len(&[IoVec::new(0x1000, u64::MAX), IoVec::new(0x1000, 1)]),
+            None
+        );
+    }
+}
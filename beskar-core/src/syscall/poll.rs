@@ -0,0 +1,495 @@
+//! Types shared by the kernel and userspace for the `Poll` syscall.
+use crate::time::Duration;
+use core::ops::{BitAnd, BitOr, BitOrAssign};
+use thiserror::Error;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+/// A set of readiness events.
+pub struct PollEvents(u32);
+
+impl PollEvents {
+    /// No event.
+    pub const NONE: Self = Self(0);
+    /// Data can be read without blocking.
+    pub const READABLE: Self = Self(1 << 0);
+    /// Data can be written without blocking.
+    pub const WRITABLE: Self = Self(1 << 1);
+    /// The source is invalid or not supported.
+    ///
+    /// This event is always reported, regardless of the interest.
+    pub const INVALID: Self = Self(1 << 2);
+
+    #[must_use]
+    #[inline]
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for PollEvents {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for PollEvents {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for PollEvents {
+    type Output = Self;
+
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An event source that can be polled.
+pub enum PollSource {
+    Keyboard,
+    Mouse,
+    IpcInbox,
+    /// A file, identified by its handle.
+    File(i64),
+    /// Display events of the calling process.
+    Display,
+}
+
+impl PollSource {
+    const KIND_KEYBOARD: u32 = 0;
+    const KIND_MOUSE: u32 = 1;
+    const KIND_IPC_INBOX: u32 = 2;
+    const KIND_FILE: u32 = 3;
+    const KIND_DISPLAY: u32 = 4;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// A source to poll, along with the events of interest.
+///
+/// After the syscall, `ready` holds the events that occurred.
+pub struct PollItem {
+    kind: u32,
+    interest: PollEvents,
+    handle: i64,
+    ready: PollEvents,
+    _reserved: u32,
+}
+
+impl PollItem {
+    #[must_use]
+    #[inline]
+    pub const fn new(source: PollSource, interest: PollEvents) -> Self {
+        let (kind, handle) = match source {
+            PollSource::Keyboard => (PollSource::KIND_KEYBOARD, 0),
+            PollSource::Mouse => (PollSource::KIND_MOUSE, 0),
+            PollSource::IpcInbox => (PollSource::KIND_IPC_INBOX, 0),
+            PollSource::File(handle) => (PollSource::KIND_FILE, handle),
+            PollSource::Display => (PollSource::KIND_DISPLAY, 0),
+        };
+        Self {
+            kind,
+            interest,
+            handle,
+            ready: PollEvents::NONE,
+            _reserved: 0,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the polled source, or `None` if the item is malformed.
+    pub const fn source(&self) -> Option<PollSource> {
+        match self.kind {
+            PollSource::KIND_KEYBOARD => Some(PollSource::Keyboard),
+            PollSource::KIND_MOUSE => Some(PollSource::Mouse),
+            PollSource::KIND_IPC_INBOX => Some(PollSource::IpcInbox),
+            PollSource::KIND_FILE => Some(PollSource::File(self.handle)),
+            PollSource::KIND_DISPLAY => Some(PollSource::Display),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn interest(&self) -> PollEvents {
+        self.interest
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the events that occurred during the last poll.
+    pub const fn ready(&self) -> PollEvents {
+        self.ready
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_ready(&self) -> bool {
+        !self.ready.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How long a poll may block.
+pub enum PollTimeout {
+    /// Return immediately, even if no source is ready.
+    Immediate,
+    /// Block until a source is ready.
+    Infinite,
+    /// Block until a source is ready or the duration elapses.
+    After(Duration),
+}
+
+impl PollTimeout {
+    const RAW_INFINITE: u64 = u64::MAX;
+
+    #[must_use]
+    #[inline]
+    /// Encodes the timeout in milliseconds, as expected by the syscall.
+    pub const fn to_raw(self) -> u64 {
+        match self {
+            Self::Immediate => 0,
+            Self::Infinite => Self::RAW_INFINITE,
+            // A null duration is immediate and a huge one is infinite anyway.
+            Self::After(duration) => duration.total_millis(),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn from_raw(raw: u64) -> Self {
+        match raw {
+            0 => Self::Immediate,
+            Self::RAW_INFINITE => Self::Infinite,
+            millis => Self::After(Duration::from_millis(millis)),
+        }
+    }
+}
+
+/// Fills the `ready` field of every item, using `readiness` to query each source.
+///
+/// Only events of interest are reported, except for `PollEvents::INVALID`
+/// which is also reported for malformed items.
+///
+/// Returns the number of items that are ready.
+pub fn update_readiness(
+    items: &mut [PollItem],
+    mut readiness: impl FnMut(PollSource) -> PollEvents,
+) -> usize {
+    let mut count = 0;
+
+    for item in items {
+        let events = item.source().map_or(PollEvents::INVALID, &mut readiness);
+        item.ready = events & (item.interest | PollEvents::INVALID);
+
+        if item.is_ready() {
+            count += 1;
+        }
+    }
+
+    count
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum PollSetError {
+    #[error("The poll set is full")]
+    Full,
+    #[error("The poll item is malformed")]
+    Malformed,
+    #[error("The source is not in the poll set")]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Sources kept across waits, as used by the `PollCreate` family of syscalls.
+pub struct PollSet<const N: usize> {
+    items: [Option<PollItem>; N],
+}
+
+impl<const N: usize> Default for PollSet<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> PollSet<N> {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self { items: [None; N] }
+    }
+
+    /// Adds the source of `item` with its events of interest.
+    ///
+    /// If the source is already in the set, its events of interest are replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PollSetError::Malformed` if the item is malformed,
+    /// and `PollSetError::Full` if the set already holds `N` sources.
+    pub fn add(&mut self, item: PollItem) -> Result<(), PollSetError> {
+        let source = item.source().ok_or(PollSetError::Malformed)?;
+        let item = PollItem::new(source, item.interest);
+
+        if let Some(existing) = self
+            .items
+            .iter_mut()
+            .flatten()
+            .find(|existing| existing.source() == Some(source))
+        {
+            *existing = item;
+            return Ok(());
+        }
+
+        let slot = self
+            .items
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(PollSetError::Full)?;
+        *slot = Some(item);
+        Ok(())
+    }
+
+    /// Removes a source from the set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PollSetError::Unknown` if the source is not in the set.
+    pub fn remove(&mut self, source: PollSource) -> Result<(), PollSetError> {
+        let slot = self
+            .items
+            .iter_mut()
+            .find(|slot| slot.is_some_and(|item| item.source() == Some(source)))
+            .ok_or(PollSetError::Unknown)?;
+        *slot = None;
+        Ok(())
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.iter().flatten().count()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.iter().all(Option::is_none)
+    }
+
+    /// Copies the sources that are ready into `ready`, using `readiness` to query each source.
+    ///
+    /// Readiness is level-triggered: a source is reported as long as it is ready.
+    /// A source reported with `PollEvents::INVALID` is removed from the set,
+    /// unless `ready` is full, in which case it is reported by a later call.
+    ///
+    /// Returns the number of items copied.
+    pub fn collect_ready(
+        &mut self,
+        ready: &mut [PollItem],
+        mut readiness: impl FnMut(PollSource) -> PollEvents,
+    ) -> usize {
+        let mut count = 0;
+
+        for slot in &mut self.items {
+            if count == ready.len() {
+                break;
+            }
+            let Some(item) = slot else {
+                continue;
+            };
+
+            update_readiness(core::slice::from_mut(item), &mut readiness);
+            if !item.is_ready() {
+                continue;
+            }
+
+            ready[count] = *item;
+            count += 1;
+            if item.ready.contains(PollEvents::INVALID) {
+                *slot = None;
+            }
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_item_source() {
+        for source in [
+            PollSource::Keyboard,
+            PollSource::Mouse,
+            PollSource::IpcInbox,
+            PollSource::File(42),
+            PollSource::Display,
+        ] {
+            let item = PollItem::new(source, PollEvents::READABLE);
+            assert_eq!(item.source(), Some(source));
+            assert!(!item.is_ready());
+        }
+    }
+
+    #[test]
+    fn test_update_readiness() {
+        let mut items = [
+            PollItem::new(PollSource::Keyboard, PollEvents::READABLE),
+            PollItem::new(PollSource::Mouse, PollEvents::READABLE),
+            PollItem::new(PollSource::File(3), PollEvents::WRITABLE),
+            PollItem::new(PollSource::File(-1), PollEvents::READABLE),
+        ];
+
+        let count = update_readiness(&mut items, |source| match source {
+            PollSource::Keyboard | PollSource::IpcInbox | PollSource::Display => PollEvents::NONE,
+            PollSource::Mouse => PollEvents::READABLE,
+            PollSource::File(handle) if handle >= 0 => PollEvents::READABLE | PollEvents::WRITABLE,
+            PollSource::File(_) => PollEvents::INVALID,
+        });
+
+        assert_eq!(count, 3);
+        assert!(!items[0].is_ready());
+        assert_eq!(items[1].ready(), PollEvents::READABLE);
+        // Only the events of interest are reported
+        assert_eq!(items[2].ready(), PollEvents::WRITABLE);
+        assert_eq!(items[3].ready(), PollEvents::INVALID);
+
+        // Readiness is not sticky
+        let count = update_readiness(&mut items, |_| PollEvents::NONE);
+        assert_eq!(count, 0);
+        assert!(items.iter().all(|item| !item.is_ready()));
+    }
+
+    #[test]
+    fn test_update_readiness_malformed() {
+        let mut items = [PollItem::new(PollSource::Keyboard, PollEvents::READABLE)];
+        items[0].kind = 42;
+
+        let count = update_readiness(&mut items, |_| unreachable!());
+        assert_eq!(count, 1);
+        assert_eq!(items[0].ready(), PollEvents::INVALID);
+    }
+
+    #[test]
+    fn test_timeout_raw() {
+        assert_eq!(PollTimeout::from_raw(0), PollTimeout::Immediate);
+        assert_eq!(PollTimeout::from_raw(u64::MAX), PollTimeout::Infinite);
+        assert_eq!(
+            PollTimeout::from_raw(PollTimeout::After(Duration::from_millis(10)).to_raw()),
+            PollTimeout::After(Duration::from_millis(10))
+        );
+        assert_eq!(PollTimeout::Immediate.to_raw(), 0);
+        assert_eq!(PollTimeout::Infinite.to_raw(), u64::MAX);
+    }
+
+    #[test]
+    fn test_poll_set() {
+        let mut set = PollSet::<4>::new();
+        assert!(set.is_empty());
+
+        set.add(PollItem::new(PollSource::Keyboard, PollEvents::READABLE))
+            .unwrap();
+        set.add(PollItem::new(PollSource::File(3), PollEvents::READABLE))
+            .unwrap();
+        // Adding a source again replaces its interest
+        set.add(PollItem::new(PollSource::File(3), PollEvents::WRITABLE))
+            .unwrap();
+        assert_eq!(set.len(), 2);
+
+        let readiness = |source| match source {
+            PollSource::File(_) => PollEvents::READABLE | PollEvents::WRITABLE,
+            _ => PollEvents::NONE,
+        };
+        let mut ready = [PollItem::new(PollSource::Keyboard, PollEvents::NONE); 4];
+        assert_eq!(set.collect_ready(&mut ready, readiness), 1);
+        assert_eq!(ready[0].source(), Some(PollSource::File(3)));
+        assert_eq!(ready[0].ready(), PollEvents::WRITABLE);
+
+        // Readiness is level-triggered
+        assert_eq!(set.collect_ready(&mut ready, readiness), 1);
+        assert_eq!(set.collect_ready(&mut ready, |_| PollEvents::NONE), 0);
+
+        set.remove(PollSource::File(3)).unwrap();
+        assert_eq!(set.remove(PollSource::File(3)), Err(PollSetError::Unknown));
+        assert_eq!(set.collect_ready(&mut ready, readiness), 0);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_poll_set_invalid_removed() {
+        let mut set = PollSet::<4>::new();
+        set.add(PollItem::new(PollSource::File(3), PollEvents::READABLE))
+            .unwrap();
+        set.add(PollItem::new(PollSource::File(4), PollEvents::READABLE))
+            .unwrap();
+
+        // The handle 3 is closed
+        let readiness = |source| match source {
+            PollSource::File(3) => PollEvents::INVALID,
+            _ => PollEvents::NONE,
+        };
+
+        // A full output keeps the invalid source for later
+        assert_eq!(set.collect_ready(&mut [], readiness), 0);
+        assert_eq!(set.len(), 2);
+
+        let mut ready = [PollItem::new(PollSource::Keyboard, PollEvents::NONE); 2];
+        assert_eq!(set.collect_ready(&mut ready, readiness), 1);
+        assert_eq!(ready[0].source(), Some(PollSource::File(3)));
+        assert_eq!(ready[0].ready(), PollEvents::INVALID);
+
+        // It is reported only once
+        assert_eq!(set.collect_ready(&mut ready, readiness), 0);
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.remove(PollSource::File(3)), Err(PollSetError::Unknown));
+    }
+
+    #[test]
+    fn test_poll_set_full() {
+        let mut set = PollSet::<1>::new();
+        set.add(PollItem::new(PollSource::Keyboard, PollEvents::READABLE))
+            .unwrap();
+        assert_eq!(
+            set.add(PollItem::new(PollSource::Mouse, PollEvents::READABLE)),
+            Err(PollSetError::Full)
+        );
+        // Replacing the interest of a source does not need room
+        set.add(PollItem::new(PollSource::Keyboard, PollEvents::WRITABLE))
+            .unwrap();
+
+        let mut malformed = PollItem::new(PollSource::Keyboard, PollEvents::READABLE);
+        malformed.kind = 42;
+        assert_eq!(set.add(malformed), Err(PollSetError::Malformed));
+    }
+}
@@ -0,0 +1,233 @@
+//! Types shared by the kernel and userspace for the `ProcessList` syscall.
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+/// Maximum length of a process name, in bytes.
+///
+/// Longer names are truncated.
+pub const PROCESS_NAME_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ProcessKind {
+    Kernel = 0,
+    Driver = 1,
+    User = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ProcessState {
+    /// The process has at least one thread that has not exited.
+    Running = 0,
+    /// Every thread of the process has exited,
+    /// but the process has not been released yet.
+    ///
+    /// Its CPU time is final.
+    Zombie = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// Information about a process, as returned by the `ProcessList` syscall.
+pub struct ProcessInfo {
+    pid: u64,
+    cpu_time_ms: u64,
+    resident_bytes: u64,
+    name: [u8; PROCESS_NAME_LEN],
+    name_len: u8,
+    kind: ProcessKind,
+    state: ProcessState,
+}
+
+impl ProcessInfo {
+    /// An entry to initialize buffers with.
+    pub const EMPTY: Self = Self {
+        pid: 0,
+        cpu_time_ms: 0,
+        resident_bytes: 0,
+        name: [0; PROCESS_NAME_LEN],
+        name_len: 0,
+        kind: ProcessKind::Kernel,
+        state: ProcessState::Running,
+    };
+
+    #[must_use]
+    /// Creates an entry, truncating `name` to `PROCESS_NAME_LEN` bytes on a char boundary.
+    pub fn new(
+        pid: u64,
+        name: &str,
+        kind: ProcessKind,
+        state: ProcessState,
+        cpu_time_ms: u64,
+        resident_bytes: u64,
+    ) -> Self {
+        let mut len = name.len().min(PROCESS_NAME_LEN);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut raw_name = [0; PROCESS_NAME_LEN];
+        raw_name[..len].copy_from_slice(&name.as_bytes()[..len]);
+
+        Self {
+            pid,
+            cpu_time_ms,
+            resident_bytes,
+            name: raw_name,
+            #[expect(
+                clippy::cast_possible_truncation,
+                reason = "Bounded by PROCESS_NAME_LEN"
+            )]
+            name_len: len as u8,
+            kind,
+            state,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn pid(&self) -> u64 {
+        self.pid
+    }
+
+    #[must_use]
+    #[inline]
+    /// CPU time used by every thread of the process, in milliseconds.
+    pub const fn cpu_time_ms(&self) -> u64 {
+        self.cpu_time_ms
+    }
+
+    #[must_use]
+    #[inline]
+    /// Memory backed by physical frames in the address space of the process, in bytes.
+    pub const fn resident_bytes(&self) -> u64 {
+        self.resident_bytes
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn name(&self) -> &str {
+        let len = usize::from(self.name_len).min(PROCESS_NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn kind(&self) -> ProcessKind {
+        self.kind
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn state(&self) -> ProcessState {
+        self.state
+    }
+}
+
+/// Fills `dst` with the processes whose PID is at least `cursor`.
+///
+/// `processes` must be sorted by PID.
+/// Returns the number of entries written.
+///
+/// If `dst` is full, the listing continues with a cursor of the last PID plus one.
+pub fn fill_list(
+    processes: impl IntoIterator<Item = ProcessInfo>,
+    cursor: u64,
+    dst: &mut [ProcessInfo],
+) -> usize {
+    let mut written = 0;
+    for (slot, info) in dst
+        .iter_mut()
+        .zip(processes.into_iter().skip_while(|info| info.pid < cursor))
+    {
+        *slot = info;
+        written += 1;
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processes() -> [ProcessInfo; 3] {
+        [
+            ProcessInfo::new(
+                0,
+                "kernel",
+                ProcessKind::Kernel,
+                ProcessState::Running,
+                120,
+                4096,
+            ),
+            ProcessInfo::new(
+                1,
+                "Drivers",
+                ProcessKind::Driver,
+                ProcessState::Zombie,
+                40,
+                4096,
+            ),
+            ProcessInfo::new(
+                4,
+                "bashkar",
+                ProcessKind::User,
+                ProcessState::Running,
+                7,
+                4096,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_process_info() {
+        let info = ProcessInfo::new(
+            3,
+            "shell",
+            ProcessKind::User,
+            ProcessState::Zombie,
+            42,
+            4096,
+        );
+        assert_eq!(info.pid(), 3);
+        assert_eq!(info.name(), "shell");
+        assert_eq!(info.kind(), ProcessKind::User);
+        assert_eq!(info.state(), ProcessState::Zombie);
+        assert_eq!(info.cpu_time_ms(), 42);
+        assert_eq!(info.resident_bytes(), 4096);
+
+        // Truncated on a char boundary: 'é' takes two bytes.
+        let long = "ééééééééééééééééé";
+        let info = ProcessInfo::new(3, long, ProcessKind::User, ProcessState::Running, 0, 4096);
+        assert_eq!(info.name().len(), PROCESS_NAME_LEN);
+        assert!(long.starts_with(info.name()));
+
+        let odd = "aééééééééééééééééé";
+        let info = ProcessInfo::new(3, odd, ProcessKind::User, ProcessState::Running, 0, 4096);
+        assert_eq!(info.name().len(), PROCESS_NAME_LEN - 1);
+    }
+
+    #[test]
+    fn test_fill_list() {
+        let mut dst = [ProcessInfo::EMPTY; 8];
+        assert_eq!(fill_list(processes(), 0, &mut dst), 3);
+        assert_eq!(dst[..3], processes());
+        assert_eq!(dst[3], ProcessInfo::EMPTY);
+    }
+
+    #[test]
+    fn test_fill_list_cursor() {
+        let mut dst = [ProcessInfo::EMPTY; 2];
+
+        assert_eq!(fill_list(processes(), 0, &mut dst), 2);
+        assert_eq!(dst[0].name(), "kernel");
+        assert_eq!(dst[1].name(), "Drivers");
+
+        let cursor = dst[1].pid() + 1;
+        assert_eq!(fill_list(processes(), cursor, &mut dst), 1);
+        assert_eq!(dst[0].name(), "bashkar");
+
+        assert_eq!(fill_list(processes(), 5, &mut dst), 0);
+        assert_eq!(fill_list(processes(), 0, &mut []), 0);
+    }
+}
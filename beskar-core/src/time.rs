@@ -316,10 +316,246 @@ impl AtomicInstant {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A one-shot or periodic timer.
+///
+/// The timer does not fire on its own: its owner calls `expire` once the deadline is reached.
+/// A periodic timer that expires late is rescheduled after the current time,
+/// so that the periods it missed make up a single expiration instead of piling up.
+pub struct IntervalTimer {
+    deadline: Option<Instant>,
+    /// Time between two expirations, or zero for a one-shot timer.
+    period: Duration,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// An expiration of an `IntervalTimer`.
+pub struct Expiration {
+    /// Number of periods that ended since the previous expiration,
+    /// besides the one that just ended.
+    pub missed: u64,
+    /// Next deadline of the timer, if it is periodic.
+    pub next: Option<Instant>,
+}
+
+impl Default for IntervalTimer {
+    fn default() -> Self {
+        Self::DISARMED
+    }
+}
+
+impl IntervalTimer {
+    /// A timer that never expires.
+    pub const DISARMED: Self = Self {
+        deadline: None,
+        period: Duration::ZERO,
+    };
+
+    /// Arms the timer to expire `delay` after `now`, then every `period` if it is not zero.
+    ///
+    /// A null delay disarms the timer. Returns the deadline of the timer.
+    pub fn arm(&mut self, now: Instant, delay: Duration, period: Duration) -> Option<Instant> {
+        if delay == Duration::ZERO {
+            self.disarm();
+        } else {
+            self.deadline = Some(now + delay);
+            self.period = period;
+        }
+        self.deadline
+    }
+
+    #[inline]
+    pub const fn disarm(&mut self) {
+        *self = Self::DISARMED;
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the period of the timer, or `None` for a one-shot timer.
+    pub fn period(&self) -> Option<Duration> {
+        (self.period != Duration::ZERO).then_some(self.period)
+    }
+
+    /// Expires the timer if its deadline is reached.
+    ///
+    /// A periodic timer is rescheduled to the first deadline after `now`,
+    /// and a one-shot timer is disarmed.
+    pub fn expire(&mut self, now: Instant) -> Option<Expiration> {
+        let deadline = self.deadline.filter(|deadline| *deadline <= now)?;
+
+        let Some(period) = self.period() else {
+            self.disarm();
+            return Some(Expiration {
+                missed: 0,
+                next: None,
+            });
+        };
+
+        let missed = (now - deadline).total_micros() / period.total_micros();
+        let next = deadline + period * missed.saturating_add(1);
+        self.deadline = Some(next);
+        Some(Expiration {
+            missed,
+            next: Some(next),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+/// TSC calibration published by the kernel, so that userspace can read the time
+/// without a syscall.
+pub struct TscClock {
+    /// TSC value at `base`.
+    base_tsc: u64,
+    base: Instant,
+    /// TSC frequency in MHz, or 0 if the TSC cannot be used.
+    tsc_mhz: u64,
+}
+
+impl TscClock {
+    /// A clock that cannot be used.
+    pub const UNAVAILABLE: Self = Self::new(0, Instant::ZERO, 0);
+
+    #[must_use]
+    #[inline]
+    pub const fn new(base_tsc: u64, base: Instant, tsc_mhz: u64) -> Self {
+        Self {
+            base_tsc,
+            base,
+            tsc_mhz,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether the clock can be used.
+    ///
+    /// This is only the case on hardware with an invariant TSC.
+    pub const fn is_available(&self) -> bool {
+        self.tsc_mhz != 0
+    }
+
+    #[must_use]
+    #[inline]
+    /// Converts a TSC value to an instant, or returns `None` if the clock cannot be used.
+    pub const fn instant_at(&self, tsc: u64) -> Option<Instant> {
+        if !self.is_available() {
+            return None;
+        }
+        let elapsed = tsc.saturating_sub(self.base_tsc) / self.tsc_mhz;
+        Some(Instant::from_micros(
+            self.base.total_micros().saturating_add(elapsed),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_tsc_clock() {
+        assert_eq!(TscClock::UNAVAILABLE.instant_at(1_000), None);
+
+        let clock = TscClock::new(2_000, Instant::from_millis(1), 2_000);
+        assert_eq!(clock.instant_at(2_000), Some(Instant::from_millis(1)));
+        assert_eq!(clock.instant_at(4_000), Some(Instant::from_micros(1_001)));
+        assert_eq!(
+            clock.instant_at(2_000 + 2_000_000_000),
+            Some(Instant::from_micros(1_001_000))
+        );
+        // A TSC read before the base does not go back in time.
+        assert_eq!(clock.instant_at(0), Some(Instant::from_millis(1)));
+    }
+
+    #[test]
+    fn test_one_shot_timer() {
+        let mut timer = IntervalTimer::DISARMED;
+        assert_eq!(timer.expire(Instant::MAX), None);
+
+        let now = Instant::from_millis(100);
+        let deadline = timer.arm(now, Duration::from_millis(50), Duration::ZERO);
+        assert_eq!(deadline, Some(Instant::from_millis(150)));
+        assert_eq!(timer.period(), None);
+
+        assert_eq!(timer.expire(Instant::from_millis(149)), None);
+        assert_eq!(
+            timer.expire(Instant::from_millis(170)),
+            Some(Expiration {
+                missed: 0,
+                next: None
+            })
+        );
+        assert_eq!(timer.deadline(), None);
+        assert_eq!(timer.expire(Instant::from_millis(200)), None);
+
+        // A null delay disarms the timer
+        timer.arm(now, Duration::from_millis(50), Duration::ZERO);
+        assert_eq!(timer.arm(now, Duration::ZERO, Duration::ZERO), None);
+        assert_eq!(timer.expire(Instant::MAX), None);
+    }
+
+    #[test]
+    fn test_periodic_timer() {
+        let mut timer = IntervalTimer::default();
+        let period = Duration::from_millis(10);
+        timer.arm(Instant::ZERO, Duration::from_millis(5), period);
+        assert_eq!(timer.period(), Some(period));
+
+        assert_eq!(
+            timer.expire(Instant::from_millis(5)),
+            Some(Expiration {
+                missed: 0,
+                next: Some(Instant::from_millis(15))
+            })
+        );
+        assert_eq!(timer.expire(Instant::from_millis(14)), None);
+        // A late expiration is rescheduled on the original grid
+        assert_eq!(
+            timer.expire(Instant::from_millis(17)),
+            Some(Expiration {
+                missed: 0,
+                next: Some(Instant::from_millis(25))
+            })
+        );
+    }
+
+    #[test]
+    fn test_periodic_timer_coalesces() {
+        let mut timer = IntervalTimer::DISARMED;
+        timer.arm(
+            Instant::ZERO,
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+        );
+
+        // Deadlines at 10, 20, 30 and 40 passed: one expiration, three missed
+        assert_eq!(
+            timer.expire(Instant::from_millis(45)),
+            Some(Expiration {
+                missed: 3,
+                next: Some(Instant::from_millis(50))
+            })
+        );
+        assert_eq!(timer.expire(Instant::from_millis(49)), None);
+
+        // Exactly on a deadline
+        assert_eq!(
+            timer.expire(Instant::from_millis(70)),
+            Some(Expiration {
+                missed: 2,
+                next: Some(Instant::from_millis(80))
+            })
+        );
+    }
+
     #[test]
     fn test_instant() {
         let instant = Instant::from_millis(4242);
@@ -101,6 +101,50 @@ impl PixelComponents {
     pub const fn new(red: u8, green: u8, blue: u8) -> Self {
         Self { red, green, blue }
     }
+
+    #[must_use]
+    /// Linearly interpolates between `self` and `other`.
+    ///
+    /// An `alpha` of 0 gives `self` and an `alpha` of 255 gives `other`.
+    ///
+    /// Interpolation is done on the raw (gamma-encoded) components,
+    /// see `blend_linear` for a gamma-correct version.
+    pub fn blend(self, other: Self, alpha: u8) -> Self {
+        fn lerp(from: u8, to: u8, alpha: u8) -> u8 {
+            let alpha = u32::from(alpha);
+            let value = (u32::from(from) * (255 - alpha) + u32::from(to) * alpha + 127) / 255;
+            u8::try_from(value).unwrap()
+        }
+
+        Self {
+            red: lerp(self.red, other.red, alpha),
+            green: lerp(self.green, other.green, alpha),
+            blue: lerp(self.blue, other.blue, alpha),
+        }
+    }
+
+    #[must_use]
+    /// Interpolates between `self` and `other` in linear light.
+    ///
+    /// This avoids the darkening that appears when blending sRGB components directly,
+    /// for instance on anti-aliased edges.
+    /// The sRGB curve is approximated by a gamma of 2.
+    pub fn blend_linear(self, other: Self, alpha: u8) -> Self {
+        fn lerp(from: u8, to: u8, alpha: u8) -> u8 {
+            let alpha = u32::from(alpha);
+            let from = u32::from(from) * u32::from(from);
+            let to = u32::from(to) * u32::from(to);
+            let value = (from * (255 - alpha) + to * alpha + 127) / 255;
+            // `value` is at most 255², so its square root fits in a `u8`.
+            u8::try_from(value.isqrt()).unwrap()
+        }
+
+        Self {
+            red: lerp(self.red, other.red, alpha),
+            green: lerp(self.green, other.green, alpha),
+            blue: lerp(self.blue, other.blue, alpha),
+        }
+    }
 }
 
 impl core::ops::Add<Self> for PixelComponents {
@@ -374,4 +418,43 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_pixel_components_blend() {
+        let from = PixelComponents::new(0x00, 0x40, 0xFF);
+        let to = PixelComponents::new(0xFF, 0x80, 0x00);
+
+        for blend in [PixelComponents::blend, PixelComponents::blend_linear] {
+            assert_eq!(blend(from, to, 0), from);
+            assert_eq!(blend(from, to, 255), to);
+            assert_eq!(blend(to, from, 0), to);
+            assert_eq!(
+                blend(PixelComponents::WHITE, PixelComponents::WHITE, 77),
+                PixelComponents::WHITE
+            );
+        }
+
+        assert_eq!(
+            PixelComponents::BLACK.blend(PixelComponents::WHITE, 128),
+            PixelComponents::new(128, 128, 128)
+        );
+        assert_eq!(from.blend(to, 128), PixelComponents::new(0x80, 0x60, 0x7F));
+    }
+
+    #[test]
+    fn test_pixel_components_blend_linear() {
+        // Half of the light of white is brighter than the sRGB midpoint.
+        let mid = PixelComponents::BLACK.blend_linear(PixelComponents::WHITE, 128);
+        assert_eq!(mid, PixelComponents::new(180, 180, 180));
+
+        // Blending is monotonic
+        let mut previous = 0;
+        for alpha in 0..=255 {
+            let value = PixelComponents::BLACK
+                .blend_linear(PixelComponents::WHITE, alpha)
+                .red;
+            assert!(value >= previous);
+            previous = value;
+        }
+    }
 }
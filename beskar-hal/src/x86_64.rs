@@ -1,8 +1,14 @@
 //! x86_64 architecture specific code.
+pub mod apic;
+pub mod cpu;
+pub mod gp;
 pub mod instructions;
+pub mod mce;
 pub mod paging;
 pub mod port;
 pub mod process;
 pub mod registers;
 pub mod structures;
+pub mod topology;
 pub mod userspace;
+pub mod xsave;
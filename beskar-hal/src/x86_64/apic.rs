@@ -0,0 +1,114 @@
+//! Local APIC register layout.
+//!
+//! In xAPIC mode, registers are accessed through a memory-mapped page.
+//! In x2APIC mode, the same registers are accessed through MSRs, starting at `0x800`.
+
+/// First MSR of the x2APIC register range.
+pub const X2APIC_MSR_BASE: u32 = 0x800;
+
+/// `IA32_APIC_BASE` MSR.
+pub const APIC_BASE_MSR: u32 = 0x1B;
+/// `IA32_APIC_BASE` bit globally enabling the APIC.
+pub const APIC_BASE_ENABLE: u64 = 1 << 11;
+/// `IA32_APIC_BASE` bit enabling x2APIC mode.
+pub const APIC_BASE_X2APIC: u64 = 1 << 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Local APIC registers.
+pub enum LapicReg {
+    Id,
+    Version,
+    TaskPriority,
+    Eoi,
+    LogicalDestination,
+    SpuriousVector,
+    ErrorStatus,
+    /// Low half of the Interrupt Command Register.
+    ///
+    /// In x2APIC mode, the ICR is a single 64-bit MSR.
+    IcrLow,
+    /// High half of the Interrupt Command Register.
+    ///
+    /// This register does not exist in x2APIC mode.
+    IcrHigh,
+    LvtTimer,
+    LvtLint0,
+    LvtLint1,
+    LvtError,
+    TimerInitialCount,
+    TimerCurrentCount,
+    TimerDivideConfig,
+}
+
+impl LapicReg {
+    #[must_use]
+    #[inline]
+    /// Offset of the register from the xAPIC MMIO base.
+    pub const fn mmio_offset(self) -> usize {
+        match self {
+            Self::Id => 0x20,
+            Self::Version => 0x30,
+            Self::TaskPriority => 0x80,
+            Self::Eoi => 0xB0,
+            Self::LogicalDestination => 0xD0,
+            Self::SpuriousVector => 0xF0,
+            Self::ErrorStatus => 0x280,
+            Self::IcrLow => 0x300,
+            Self::IcrHigh => 0x310,
+            Self::LvtTimer => 0x320,
+            Self::LvtLint0 => 0x350,
+            Self::LvtLint1 => 0x360,
+            Self::LvtError => 0x370,
+            Self::TimerInitialCount => 0x380,
+            Self::TimerCurrentCount => 0x390,
+            Self::TimerDivideConfig => 0x3E0,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Index of the MSR backing the register in x2APIC mode.
+    ///
+    /// Returns `None` for registers that do not exist in x2APIC mode.
+    pub const fn x2apic_msr(self) -> Option<u32> {
+        match self {
+            Self::IcrHigh => None,
+            #[expect(clippy::cast_possible_truncation, reason = "Offsets are below 0x400")]
+            reg => Some(X2APIC_MSR_BASE + (reg.mmio_offset() >> 4) as u32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_x2apic_msr_mapping() {
+        assert_eq!(LapicReg::Id.x2apic_msr(), Some(0x802));
+        assert_eq!(LapicReg::Version.x2apic_msr(), Some(0x803));
+        assert_eq!(LapicReg::TaskPriority.x2apic_msr(), Some(0x808));
+        assert_eq!(LapicReg::Eoi.x2apic_msr(), Some(0x80B));
+        assert_eq!(LapicReg::LogicalDestination.x2apic_msr(), Some(0x80D));
+        assert_eq!(LapicReg::SpuriousVector.x2apic_msr(), Some(0x80F));
+        assert_eq!(LapicReg::ErrorStatus.x2apic_msr(), Some(0x828));
+        assert_eq!(LapicReg::IcrLow.x2apic_msr(), Some(0x830));
+        assert_eq!(LapicReg::IcrHigh.x2apic_msr(), None);
+        assert_eq!(LapicReg::LvtTimer.x2apic_msr(), Some(0x832));
+        assert_eq!(LapicReg::LvtLint0.x2apic_msr(), Some(0x835));
+        assert_eq!(LapicReg::LvtLint1.x2apic_msr(), Some(0x836));
+        assert_eq!(LapicReg::LvtError.x2apic_msr(), Some(0x837));
+        assert_eq!(LapicReg::TimerInitialCount.x2apic_msr(), Some(0x838));
+        assert_eq!(LapicReg::TimerCurrentCount.x2apic_msr(), Some(0x839));
+        assert_eq!(LapicReg::TimerDivideConfig.x2apic_msr(), Some(0x83E));
+    }
+
+    #[test]
+    fn test_mmio_offsets() {
+        assert_eq!(LapicReg::Eoi.mmio_offset(), 0xB0);
+        assert_eq!(LapicReg::SpuriousVector.mmio_offset(), 0xF0);
+        assert_eq!(LapicReg::IcrLow.mmio_offset(), 0x300);
+        assert_eq!(LapicReg::IcrHigh.mmio_offset(), 0x310);
+        assert_eq!(LapicReg::TimerDivideConfig.mmio_offset(), 0x3E0);
+    }
+}
@@ -0,0 +1,180 @@
+//! Hardware random number generators.
+//!
+//! RDRAND returns the output of a generator reseeded by the hardware entropy source,
+//! while RDSEED returns conditioned entropy, suitable to seed a software generator.
+//! Both set the carry flag on success. They fail, clearing it, when the hardware
+//! cannot keep up, so they are retried a bounded number of times before giving up.
+//!
+//! They are only executed if CPUID reports them: otherwise, the functions return `None`.
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Number of attempts to read RDRAND.
+///
+/// Intel recommends 10: failing that many times in a row means the generator is broken.
+pub const RDRAND_RETRIES: u32 = 10;
+/// Number of attempts to read RDSEED.
+///
+/// The entropy source is slower than the generator, so RDSEED fails more often under load.
+/// Attempts are separated by a pause.
+pub const RDSEED_RETRIES: u32 = 100;
+
+/// Support for an instruction, checked once with CPUID.
+struct Support {
+    state: AtomicU8,
+    check: fn() -> bool,
+}
+
+impl Support {
+    const UNKNOWN: u8 = 0;
+    const UNSUPPORTED: u8 = 1;
+    const SUPPORTED: u8 = 2;
+
+    const fn new(check: fn() -> bool) -> Self {
+        Self {
+            state: AtomicU8::new(Self::UNKNOWN),
+            check,
+        }
+    }
+
+    fn get(&self) -> bool {
+        match self.state.load(Ordering::Relaxed) {
+            Self::UNKNOWN => {
+                let supported = (self.check)();
+                let state = if supported {
+                    Self::SUPPORTED
+                } else {
+                    Self::UNSUPPORTED
+                };
+                self.state.store(state, Ordering::Relaxed);
+                supported
+            }
+            state => state == Self::SUPPORTED,
+        }
+    }
+}
+
+static RDRAND: Support = Support::new(|| {
+    let ecx = core::arch::x86_64::__cpuid(1).ecx;
+    ecx & (1 << 30) != 0
+});
+
+static RDSEED: Support = Support::new(|| {
+    let max_leaf = core::arch::x86_64::__cpuid(0).eax;
+    max_leaf >= 7 && core::arch::x86_64::__cpuid_count(7, 0).ebx & (1 << 18) != 0
+});
+
+#[must_use]
+#[inline]
+pub fn rdrand_supported() -> bool {
+    RDRAND.get()
+}
+
+#[must_use]
+#[inline]
+pub fn rdseed_supported() -> bool {
+    RDSEED.get()
+}
+
+#[must_use]
+#[inline]
+/// Executes RDRAND once, returning its output if the carry flag is set.
+///
+/// # Safety
+///
+/// RDRAND must be supported.
+unsafe fn rdrand_once() -> Option<u64> {
+    let value: u64;
+    let carry: u8;
+    unsafe {
+        core::arch::asm!(
+            "rdrand {value}",
+            "setc {carry}",
+            value = out(reg) value,
+            carry = out(reg_byte) carry,
+            options(nomem, nostack)
+        );
+    }
+    (carry != 0).then_some(value)
+}
+
+#[must_use]
+#[inline]
+/// Executes RDSEED once, returning its output if the carry flag is set.
+///
+/// # Safety
+///
+/// RDSEED must be supported.
+unsafe fn rdseed_once() -> Option<u64> {
+    let value: u64;
+    let carry: u8;
+    unsafe {
+        core::arch::asm!(
+            "rdseed {value}",
+            "setc {carry}",
+            value = out(reg) value,
+            carry = out(reg_byte) carry,
+            options(nomem, nostack)
+        );
+    }
+    (carry != 0).then_some(value)
+}
+
+#[must_use]
+/// Reads a random value with RDRAND, retrying up to `RDRAND_RETRIES` times.
+///
+/// Returns `None` if RDRAND is not supported, or if every attempt failed.
+pub fn rdrand_u64() -> Option<u64> {
+    if !rdrand_supported() {
+        return None;
+    }
+    // Safety: RDRAND is supported.
+    (0..RDRAND_RETRIES).find_map(|_| unsafe { rdrand_once() })
+}
+
+#[must_use]
+/// Reads a seed with RDSEED, retrying up to `RDSEED_RETRIES` times.
+///
+/// Returns `None` if RDSEED is not supported, or if every attempt failed.
+pub fn rdseed_u64() -> Option<u64> {
+    if !rdseed_supported() {
+        return None;
+    }
+    (0..RDSEED_RETRIES).find_map(|_| {
+        // Safety: RDSEED is supported.
+        let seed = unsafe { rdseed_once() };
+        if seed.is_none() {
+            core::hint::spin_loop();
+        }
+        seed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rdrand() {
+        if !rdrand_supported() {
+            assert_eq!(rdrand_u64(), None);
+            return;
+        }
+        // A successful read sets the carry flag
+        assert!((0..RDRAND_RETRIES).any(|_| unsafe { rdrand_once() }.is_some()));
+
+        let values = [(); 8].map(|()| rdrand_u64().unwrap());
+        assert!(values.iter().any(|value| *value != values[0]));
+    }
+
+    #[test]
+    fn test_rdseed() {
+        if !rdseed_supported() {
+            assert_eq!(rdseed_u64(), None);
+            return;
+        }
+        assert!((0..RDSEED_RETRIES).any(|_| unsafe { rdseed_once() }.is_some()));
+
+        let seeds = [(); 8].map(|()| rdseed_u64().unwrap());
+        assert!(seeds.iter().any(|seed| *seed != seeds[0]));
+    }
+}
@@ -0,0 +1,267 @@
+//! General protection fault decoding.
+//!
+//! The `#GP` error code is a segment selector when the fault is related to a segment
+//! or an interrupt gate, and 0 otherwise. In the latter case, the faulting instruction
+//! is decoded just enough to tell a privileged instruction from a memory access.
+//!
+//! Decoding is kept allocation-free, as it runs in the `#GP` handler.
+use super::userspace::Ring;
+
+/// Maximum length of an instruction.
+pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Descriptor table referenced by a selector error code.
+pub enum DescriptorTable {
+    Gdt,
+    Idt,
+    Ldt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Cause of a general protection fault.
+pub enum GpCause {
+    /// A segment selector could not be loaded, or an interrupt gate could not be used.
+    Segment {
+        table: DescriptorTable,
+        index: u16,
+        /// The fault happened while delivering an external interrupt.
+        external: bool,
+    },
+    /// An instruction that requires ring 0 or I/O privileges was executed outside of ring 0.
+    PrivilegedInstruction(&'static str),
+    /// A privileged instruction was executed in the kernel with an invalid operand,
+    /// such as an unknown MSR or a reserved bit.
+    InvalidOperand(&'static str),
+    /// Most likely, a memory access to a non-canonical address.
+    ///
+    /// Misaligned SSE operands also end up here.
+    NonCanonicalAccess,
+    /// The faulting instruction could not be read.
+    Unknown,
+}
+
+impl core::fmt::Display for GpCause {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::Segment {
+                table: DescriptorTable::Idt,
+                index,
+                external,
+            } => {
+                write!(f, "interrupt {index:#x} cannot be raised")?;
+                if external {
+                    f.write_str(" by an external event")?;
+                }
+                Ok(())
+            }
+            Self::Segment {
+                table,
+                index,
+                external,
+            } => {
+                write!(f, "bad segment load ({table:?} entry {index})")?;
+                if external {
+                    f.write_str(" during an external event")?;
+                }
+                Ok(())
+            }
+            Self::PrivilegedInstruction(mnemonic) => {
+                write!(f, "privileged instruction `{mnemonic}`")
+            }
+            Self::InvalidOperand(mnemonic) => write!(f, "invalid operand for `{mnemonic}`"),
+            Self::NonCanonicalAccess => f.write_str("non-canonical memory access"),
+            Self::Unknown => f.write_str("unknown cause"),
+        }
+    }
+}
+
+#[must_use]
+/// Decodes the cause of a `#GP` raised in `ring`.
+///
+/// `instruction` holds the bytes at the faulting instruction pointer,
+/// which may be fewer than the instruction length.
+pub fn decode(error_code: u64, instruction: &[u8], ring: Ring) -> GpCause {
+    if error_code != 0 {
+        return decode_selector(error_code);
+    }
+
+    match privileged_mnemonic(instruction) {
+        Some(mnemonic) if ring == Ring::Kernel => GpCause::InvalidOperand(mnemonic),
+        Some(mnemonic) => GpCause::PrivilegedInstruction(mnemonic),
+        None if instruction.is_empty() => GpCause::Unknown,
+        None => GpCause::NonCanonicalAccess,
+    }
+}
+
+#[must_use]
+const fn decode_selector(error_code: u64) -> GpCause {
+    let table = match (error_code >> 1) & 0b11 {
+        0b00 => DescriptorTable::Gdt,
+        0b10 => DescriptorTable::Ldt,
+        _ => DescriptorTable::Idt,
+    };
+    let index = ((error_code >> 3) & 0x1FFF) as u16;
+    GpCause::Segment {
+        table,
+        index,
+        external: error_code & 1 != 0,
+    }
+}
+
+#[must_use]
+/// Returns the mnemonic of the instruction if it is privileged.
+fn privileged_mnemonic(instruction: &[u8]) -> Option<&'static str> {
+    // Legacy prefixes, then REX
+    let start = instruction.iter().position(|byte| {
+        !matches!(
+            byte,
+            0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 | 0x66 | 0x67 | 0xF0 | 0xF2 | 0xF3
+        )
+    })?;
+    let mut bytes = &instruction[start..];
+    if let [0x40..=0x4F, rest @ ..] = bytes {
+        bytes = rest;
+    }
+
+    match bytes {
+        [0xF4, ..] => Some("hlt"),
+        [0xFA, ..] => Some("cli"),
+        [0xFB, ..] => Some("sti"),
+        [0xE4 | 0xE5 | 0xEC | 0xED, ..] => Some("in"),
+        [0xE6 | 0xE7 | 0xEE | 0xEF, ..] => Some("out"),
+        [0x6C | 0x6D, ..] => Some("ins"),
+        [0x6E | 0x6F, ..] => Some("outs"),
+        [0x0F, opcode, rest @ ..] => two_byte_privileged_mnemonic(*opcode, rest.first().copied()),
+        _ => None,
+    }
+}
+
+#[must_use]
+const fn two_byte_privileged_mnemonic(opcode: u8, modrm: Option<u8>) -> Option<&'static str> {
+    match (opcode, modrm) {
+        (0x00, Some(modrm)) => match (modrm >> 3) & 0b111 {
+            2 => Some("lldt"),
+            3 => Some("ltr"),
+            _ => None,
+        },
+        (0x01, Some(0xF8)) => Some("swapgs"),
+        (0x01, Some(modrm)) => match ((modrm >> 6), (modrm >> 3) & 0b111) {
+            (0b00..=0b10, 2) => Some("lgdt"),
+            (0b00..=0b10, 3) => Some("lidt"),
+            (_, 6) => Some("lmsw"),
+            (0b00..=0b10, 7) => Some("invlpg"),
+            _ => None,
+        },
+        (0x06, _) => Some("clts"),
+        (0x07, _) => Some("sysret"),
+        (0x08, _) => Some("invd"),
+        (0x09, _) => Some("wbinvd"),
+        (0x20 | 0x22, _) => Some("mov cr"),
+        (0x21 | 0x23, _) => Some("mov dr"),
+        (0x30, _) => Some("wrmsr"),
+        (0x32, _) => Some("rdmsr"),
+        (0x35, _) => Some("sysexit"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_error_code() {
+        assert_eq!(
+            decode(0x10, &[0x8E, 0xD8], Ring::Kernel),
+            GpCause::Segment {
+                table: DescriptorTable::Gdt,
+                index: 2,
+                external: false
+            }
+        );
+        // `int 0x80` through a kernel-only gate
+        assert_eq!(
+            decode((0x80 << 3) | 0b010, &[0xCD, 0x80], Ring::User),
+            GpCause::Segment {
+                table: DescriptorTable::Idt,
+                index: 0x80,
+                external: false
+            }
+        );
+        assert_eq!(
+            decode((5 << 3) | 0b101, &[], Ring::Kernel),
+            GpCause::Segment {
+                table: DescriptorTable::Ldt,
+                index: 5,
+                external: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_privileged_instruction() {
+        assert_eq!(
+            decode(0, &[0xFA], Ring::User),
+            GpCause::PrivilegedInstruction("cli")
+        );
+        assert_eq!(
+            decode(0, &[0xF4, 0x90], Ring::User),
+            GpCause::PrivilegedInstruction("hlt")
+        );
+        // `out dx, ax`, with an operand size prefix
+        assert_eq!(
+            decode(0, &[0x66, 0xEF], Ring::User),
+            GpCause::PrivilegedInstruction("out")
+        );
+        // `mov cr3, rax`
+        assert_eq!(
+            decode(0, &[0x0F, 0x22, 0xD8], Ring::User),
+            GpCause::PrivilegedInstruction("mov cr")
+        );
+        // `lgdt [rax]`, `invlpg [rax]` and `swapgs`
+        assert_eq!(
+            decode(0, &[0x0F, 0x01, 0x10], Ring::User),
+            GpCause::PrivilegedInstruction("lgdt")
+        );
+        assert_eq!(
+            decode(0, &[0x0F, 0x01, 0x38], Ring::User),
+            GpCause::PrivilegedInstruction("invlpg")
+        );
+        assert_eq!(
+            decode(0, &[0x0F, 0x01, 0xF8], Ring::User),
+            GpCause::PrivilegedInstruction("swapgs")
+        );
+        // `rdtscp` is not privileged
+        assert_eq!(
+            decode(0, &[0x0F, 0x01, 0xF9], Ring::User),
+            GpCause::NonCanonicalAccess
+        );
+
+        // The kernel may run them, but with invalid operands
+        assert_eq!(
+            decode(0, &[0x0F, 0x30], Ring::Kernel),
+            GpCause::InvalidOperand("wrmsr")
+        );
+    }
+
+    #[test]
+    fn test_memory_access() {
+        // `mov rax, [rbx]`
+        assert_eq!(
+            decode(0, &[0x48, 0x8B, 0x03], Ring::User),
+            GpCause::NonCanonicalAccess
+        );
+        // `lock add [rax], ecx`
+        assert_eq!(
+            decode(0, &[0xF0, 0x01, 0x08], Ring::Kernel),
+            GpCause::NonCanonicalAccess
+        );
+        assert_eq!(decode(0, &[], Ring::Kernel), GpCause::Unknown);
+        // Only prefixes could be read
+        assert_eq!(
+            decode(0, &[0x66, 0x67], Ring::Kernel),
+            GpCause::NonCanonicalAccess
+        );
+    }
+}
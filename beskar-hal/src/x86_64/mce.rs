@@ -0,0 +1,306 @@
+//! Machine-check architecture decoding.
+//!
+//! When a `#MC` is raised, the global `IA32_MCG_STATUS` register tells whether
+//! the interrupted program can be restarted, and every error-reporting bank
+//! holds an `IA32_MCi_STATUS` register describing the error it logged.
+//!
+//! Decoding is kept allocation-free, as it runs in the `#MC` handler.
+use super::registers::{DynMsr, Msr};
+
+/// `IA32_MCG_CAP` MSR index.
+pub const MCG_CAP_MSR: u32 = 0x179;
+/// `IA32_MCG_STATUS` MSR index.
+pub const MCG_STATUS_MSR: u32 = 0x17A;
+/// `IA32_MC0_STATUS` MSR index. Banks are 4 MSRs apart.
+const MC0_STATUS_MSR: u32 = 0x401;
+/// `IA32_MC0_ADDR` MSR index.
+const MC0_ADDR_MSR: u32 = 0x402;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Decoded `IA32_MCG_STATUS` register.
+pub struct McgStatus(u64);
+
+impl McgStatus {
+    /// The program can be restarted at the instruction pointed to by the saved RIP.
+    const RIPV: u64 = 1 << 0;
+    /// The saved RIP points to the instruction that caused the error.
+    const EIPV: u64 = 1 << 1;
+    /// A machine check is in progress.
+    const MCIP: u64 = 1 << 2;
+
+    #[must_use]
+    #[inline]
+    pub const fn new(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn read() -> Self {
+        Self(Msr::<MCG_STATUS_MSR>.read())
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn restart_ip_valid(self) -> bool {
+        self.0 & Self::RIPV != 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn error_ip_valid(self) -> bool {
+        self.0 & Self::EIPV != 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn in_progress(self) -> bool {
+        self.0 & Self::MCIP != 0
+    }
+}
+
+#[must_use]
+#[inline]
+/// Returns the number of error-reporting banks, read from `IA32_MCG_CAP`.
+pub fn bank_count() -> u8 {
+    (Msr::<MCG_CAP_MSR>.read() & 0xFF) as u8
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// How bad an error is.
+pub enum Severity {
+    /// The error was corrected by the processor.
+    Corrected,
+    /// The error was not corrected, but the processor state is intact
+    /// and the program can be restarted.
+    Recoverable,
+    /// The processor state is corrupted, or the program cannot be restarted.
+    Fatal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Class of an MCA error code.
+pub enum ErrorKind {
+    NoError,
+    Unclassified,
+    MicrocodeRomParity,
+    External,
+    FunctionalRedundancyCheck,
+    InternalParity,
+    InternalUnclassified,
+    /// TLB error.
+    Tlb,
+    /// Memory controller error.
+    MemoryController,
+    /// Cache hierarchy error.
+    Cache,
+    /// Bus or interconnect error.
+    Bus,
+    Other(u16),
+}
+
+impl ErrorKind {
+    #[must_use]
+    /// Decodes the MCA error code, the low 16 bits of `IA32_MCi_STATUS`.
+    pub const fn from_code(code: u16) -> Self {
+        // Bit 12 of compound codes only tells whether the error report was filtered
+        let compound = code & !0x1000;
+        match code {
+            0x0000 => Self::NoError,
+            0x0001 => Self::Unclassified,
+            0x0002 => Self::MicrocodeRomParity,
+            0x0003 => Self::External,
+            0x0004 => Self::FunctionalRedundancyCheck,
+            0x0005 => Self::InternalParity,
+            _ if code & 0xFC00 == 0x0400 => Self::InternalUnclassified,
+            _ if compound & 0xF800 == 0x0800 => Self::Bus,
+            _ if compound & 0xFF00 == 0x0100 => Self::Cache,
+            _ if compound & 0xFF80 == 0x0080 => Self::MemoryController,
+            _ if compound & 0xFFF0 == 0x0010 => Self::Tlb,
+            _ => Self::Other(code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Decoded `IA32_MCi_STATUS` register of an error-reporting bank.
+pub struct BankStatus(u64);
+
+impl BankStatus {
+    /// The register holds a valid error.
+    const VAL: u64 = 1 << 63;
+    /// Another error happened while this one was still logged.
+    const OVER: u64 = 1 << 62;
+    /// The error was not corrected.
+    const UC: u64 = 1 << 61;
+    /// `IA32_MCi_ADDR` holds the address of the error.
+    const ADDRV: u64 = 1 << 58;
+    /// The processor context may be corrupted.
+    const PCC: u64 = 1 << 57;
+
+    #[must_use]
+    #[inline]
+    pub const fn new(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Reads the status of bank `bank`.
+    ///
+    /// # Safety
+    ///
+    /// `bank` must be lower than the bank count reported by `bank_count`.
+    pub unsafe fn read(bank: u8) -> Self {
+        Self(unsafe { DynMsr::new(MC0_STATUS_MSR + 4 * u32::from(bank)) }.read())
+    }
+
+    #[must_use]
+    #[inline]
+    /// Reads the address of the error logged by bank `bank`, if there is one.
+    ///
+    /// # Safety
+    ///
+    /// `bank` must be lower than the bank count reported by `bank_count`.
+    pub unsafe fn read_addr(self, bank: u8) -> Option<u64> {
+        self.addr_valid()
+            .then(|| unsafe { DynMsr::new(MC0_ADDR_MSR + 4 * u32::from(bank)) }.read())
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn raw(self) -> u64 {
+        self.0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn valid(self) -> bool {
+        self.0 & Self::VAL != 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn overflow(self) -> bool {
+        self.0 & Self::OVER != 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn uncorrected(self) -> bool {
+        self.0 & Self::UC != 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn addr_valid(self) -> bool {
+        self.0 & Self::ADDRV != 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn context_corrupt(self) -> bool {
+        self.0 & Self::PCC != 0
+    }
+
+    #[must_use]
+    #[inline]
+    /// MCA error code, architecturally defined.
+    pub const fn mca_code(self) -> u16 {
+        (self.0 & 0xFFFF) as u16
+    }
+
+    #[must_use]
+    #[inline]
+    /// Model-specific error code.
+    pub const fn model_code(self) -> u16 {
+        ((self.0 >> 16) & 0xFFFF) as u16
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn kind(self) -> ErrorKind {
+        ErrorKind::from_code(self.mca_code())
+    }
+
+    #[must_use]
+    /// Severity of the logged error, given the global machine-check status.
+    ///
+    /// Returns `None` if the bank holds no valid error.
+    pub const fn severity(self, global: McgStatus) -> Option<Severity> {
+        if !self.valid() {
+            None
+        } else if self.context_corrupt() || (self.uncorrected() && !global.restart_ip_valid()) {
+            Some(Severity::Fatal)
+        } else if self.uncorrected() {
+            Some(Severity::Recoverable)
+        } else {
+            Some(Severity::Corrected)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RIPV: McgStatus = McgStatus::new(0b101);
+    const NO_RIPV: McgStatus = McgStatus::new(0b110);
+
+    #[test]
+    fn test_mcg_status() {
+        assert!(RIPV.restart_ip_valid());
+        assert!(!RIPV.error_ip_valid());
+        assert!(RIPV.in_progress());
+        assert!(!NO_RIPV.restart_ip_valid());
+        assert!(NO_RIPV.error_ip_valid());
+    }
+
+    #[test]
+    fn test_bank_status() {
+        // Valid, uncorrected, address valid, model code 0x12, cache error
+        let status = BankStatus::new((1 << 63) | (1 << 61) | (1 << 58) | (0x12 << 16) | 0x0134);
+        assert!(status.valid());
+        assert!(!status.overflow());
+        assert!(status.uncorrected());
+        assert!(status.addr_valid());
+        assert!(!status.context_corrupt());
+        assert_eq!(status.model_code(), 0x12);
+        assert_eq!(status.mca_code(), 0x0134);
+        assert_eq!(status.kind(), ErrorKind::Cache);
+    }
+
+    #[test]
+    fn test_severity() {
+        let invalid = BankStatus::new(1 << 61);
+        assert_eq!(invalid.severity(RIPV), None);
+
+        let corrected = BankStatus::new(1 << 63);
+        assert_eq!(corrected.severity(RIPV), Some(Severity::Corrected));
+        assert_eq!(corrected.severity(NO_RIPV), Some(Severity::Corrected));
+
+        let uncorrected = BankStatus::new((1 << 63) | (1 << 61));
+        assert_eq!(uncorrected.severity(RIPV), Some(Severity::Recoverable));
+        assert_eq!(uncorrected.severity(NO_RIPV), Some(Severity::Fatal));
+
+        let corrupt = BankStatus::new((1 << 63) | (1 << 61) | (1 << 57));
+        assert_eq!(corrupt.severity(RIPV), Some(Severity::Fatal));
+    }
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(ErrorKind::from_code(0), ErrorKind::NoError);
+        assert_eq!(ErrorKind::from_code(0x0005), ErrorKind::InternalParity);
+        assert_eq!(
+            ErrorKind::from_code(0x0402),
+            ErrorKind::InternalUnclassified
+        );
+        assert_eq!(ErrorKind::from_code(0x0014), ErrorKind::Tlb);
+        assert_eq!(ErrorKind::from_code(0x009F), ErrorKind::MemoryController);
+        assert_eq!(ErrorKind::from_code(0x0110), ErrorKind::Cache);
+        // Filtered cache error
+        assert_eq!(ErrorKind::from_code(0x1110), ErrorKind::Cache);
+        assert_eq!(ErrorKind::from_code(0x0E0B), ErrorKind::Bus);
+        assert_eq!(ErrorKind::from_code(0x0006), ErrorKind::Other(6));
+    }
+}
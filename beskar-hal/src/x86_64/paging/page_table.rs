@@ -11,97 +11,39 @@ use beskar_core::arch::{
 };
 use core::ops::{Index, IndexMut};
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub struct Flags(u64);
+beskar_core::bitflags! {
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct Flags: u64 {
+        pub const PRESENT = 1;
+        pub const WRITABLE = 1 << 1;
+        pub const USER_ACCESSIBLE = 1 << 2;
+        pub const WRITE_THROUGH = 1 << 3;
+        pub const CACHE_DISABLED = 1 << 4;
+        pub const ACCESSED = 1 << 5;
+        pub const DIRTY = 1 << 6;
+        pub const HUGE_PAGE = 1 << 7;
+        pub const GLOBAL = 1 << 8;
+        pub const BIT_9 = 1 << 9;
+        pub const NO_EXECUTE = 1 << 63;
+
+        pub const MMIO_SUITABLE = 1 | (1 << 1) | (1 << 4) | (1 << 63);
+
+        const ALL = 0x8000_0000_0000_0FFF;
+        /// A set of flags that are used to mark the parent entries in the page table.
+        /// The flags are present and writable.
+        ///
+        /// # Warning
+        ///
+        /// If any child page is USER ACCESSIBLE, then the parent page must also be USER ACCESSIBLE.
+        const PARENT = 1 | (1 << 1);
+    }
+}
 
 impl Flags {
-    pub const PRESENT: Self = Self(1);
-    pub const WRITABLE: Self = Self(1 << 1);
-    pub const USER_ACCESSIBLE: Self = Self(1 << 2);
-    pub const WRITE_THROUGH: Self = Self(1 << 3);
-    pub const CACHE_DISABLED: Self = Self(1 << 4);
-    pub const ACCESSED: Self = Self(1 << 5);
-    pub const DIRTY: Self = Self(1 << 6);
-    pub const HUGE_PAGE: Self = Self(1 << 7);
-    pub const GLOBAL: Self = Self(1 << 8);
-    pub const BIT_9: Self = Self(1 << 9);
-    pub const NO_EXECUTE: Self = Self(1 << 63);
-
-    pub const MMIO_SUITABLE: Self = Self(1 | (1 << 1) | (1 << 4) | (1 << 63));
-
-    const ALL: Self = Self(0x8000_0000_0000_0FFF);
-    pub const EMPTY: Self = Self(0);
-    /// A set of flags that are used to mark the parent entries in the page table.
-    /// The flags are present and writable.
-    ///
-    /// # Warning
-    ///
-    /// If any child page is USER ACCESSIBLE, then the parent page must also be USER ACCESSIBLE.
-    const PARENT: Self = Self(1 | (1 << 1));
-
     #[must_use]
     #[inline]
     pub const fn as_u64(self) -> u64 {
-        self.0
-    }
-
-    #[must_use]
-    #[inline]
-    pub const fn is_empty(self) -> bool {
-        self.0 == 0
-    }
-
-    #[must_use]
-    #[inline]
-    pub const fn contains(self, other: Self) -> bool {
-        other.without(self).is_empty()
-    }
-
-    #[must_use]
-    #[inline]
-    pub const fn union(self, other: Self) -> Self {
-        Self(self.0 | other.0)
-    }
-
-    #[must_use]
-    #[inline]
-    pub const fn intersection(self, other: Self) -> Self {
-        Self(self.0 & other.0)
-    }
-
-    #[must_use]
-    #[inline]
-    pub const fn without(self, other: Self) -> Self {
-        Self(self.0 & !other.0)
-    }
-}
-
-impl core::ops::BitOr for Flags {
-    type Output = Self;
-
-    #[inline]
-    fn bitor(self, rhs: Self) -> Self::Output {
-        Self(self.0 | rhs.0)
-    }
-}
-impl core::ops::BitAnd for Flags {
-    type Output = Self;
-
-    #[inline]
-    fn bitand(self, rhs: Self) -> Self::Output {
-        Self(self.0 & rhs.0)
-    }
-}
-impl core::ops::BitOrAssign for Flags {
-    #[inline]
-    fn bitor_assign(&mut self, rhs: Self) {
-        self.0 |= rhs.0;
-    }
-}
-impl core::ops::BitAndAssign for Flags {
-    #[inline]
-    fn bitand_assign(&mut self, rhs: Self) {
-        self.0 &= rhs.0;
+        self.bits()
     }
 }
 
@@ -718,7 +660,7 @@ impl<'t> OffsetPageTable<'t> {
         if !entry.is_present() || entry.is_large() {
             return None;
         }
-        let pt_vaddr = offset + entry.addr().as_u64();
+        let pt_vaddr = offset.checked_add(entry.addr().as_u64())?;
         let pt_ptr = pt_vaddr.as_ptr::<Entries>();
         Some(unsafe { &*pt_ptr })
     }
@@ -732,7 +674,7 @@ impl<'t> OffsetPageTable<'t> {
         if !entry.is_present() || entry.is_large() {
             return None;
         }
-        let pt_vaddr = offset + entry.addr().as_u64();
+        let pt_vaddr = offset.checked_add(entry.addr().as_u64())?;
         let pt_ptr = pt_vaddr.as_mut_ptr::<Entries>();
         Some(unsafe { &mut *pt_ptr })
     }
@@ -890,6 +832,33 @@ mod tests {
         assert!(!without_flags.contains(Flags::WRITABLE));
     }
 
+    #[test]
+    fn test_flags_match_bits() {
+        // The operations behave as the bitwise operations on the raw entry bits
+        let values = [
+            Flags::EMPTY,
+            Flags::PRESENT,
+            Flags::PARENT,
+            Flags::MMIO_SUITABLE,
+            Flags::NO_EXECUTE | Flags::USER_ACCESSIBLE,
+            Flags::ALL,
+        ];
+        for a in values {
+            for b in values {
+                let (x, y) = (a.as_u64(), b.as_u64());
+                assert_eq!(a.contains(b), x & y == y);
+                assert_eq!((a | b).as_u64(), x | y);
+                assert_eq!(a.union(b).as_u64(), x | y);
+                assert_eq!((a & b).as_u64(), x & y);
+                assert_eq!(a.intersection(b).as_u64(), x & y);
+                assert_eq!(a.without(b).as_u64(), x & !y);
+                assert_eq!(a.is_empty(), x == 0);
+            }
+        }
+        assert_eq!(Flags::MMIO_SUITABLE.as_u64(), 0x8000_0000_0000_0013);
+        assert_eq!(Flags::default(), Flags::EMPTY);
+    }
+
     #[test]
     fn test_entry_operations() {
         let mut entry = Entry::default();
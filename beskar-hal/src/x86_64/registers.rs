@@ -220,44 +220,64 @@ impl StarSelectors {
     pub const fn ss_sysret(&self) -> u16 {
         self.ss_sysret
     }
-}
 
-impl Star {
-    const MSR: Msr<0xC000_0081> = Msr;
+    #[must_use]
+    /// Encodes the selectors as a STAR value.
+    ///
+    /// `SYSCALL` loads CS from bits 32..48 and SS 8 above it.
+    /// `SYSRETQ` loads CS 16 above bits 48..64 and SS 8 above them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the selectors cannot be encoded: they must follow this layout,
+    /// with syscall selectors in ring 0 and sysret selectors in ring 3.
+    pub fn to_raw(&self) -> u64 {
+        assert_eq!(self.cs_syscall + 8, self.ss_syscall);
+        assert_eq!(self.cs_sysret, self.ss_sysret + 8);
+
+        let syscall_ring = self.ss_syscall & 0b11;
+        let sysret_ring = self.ss_sysret & 0b11;
+
+        assert_eq!(syscall_ring, 0, "Syscall selectors must be ring 0");
+        assert_eq!(sysret_ring, 3, "Sysret selectors must be ring 3");
+
+        let sysret_base = self.ss_sysret.checked_sub(8).unwrap();
+        let syscall_base = self.cs_syscall;
+
+        u64::from(sysret_base) << 48 | u64::from(syscall_base) << 32
+    }
 
     #[must_use]
-    #[inline]
-    pub fn read() -> StarSelectors {
-        let raw = Self::MSR.read();
+    /// Decodes the selectors of a STAR value.
+    pub fn from_raw(raw: u64) -> Self {
         let sysret_base = u16::try_from(raw >> 48).unwrap();
         let syscall_base = u16::try_from((raw >> 32) & 0xFFFF).unwrap();
 
-        StarSelectors {
+        Self {
             cs_syscall: syscall_base,
             ss_syscall: syscall_base + 8,
             cs_sysret: sysret_base + 16,
             ss_sysret: sysret_base + 8,
         }
     }
+}
+
+impl Star {
+    const MSR: Msr<0xC000_0081> = Msr;
+
+    #[must_use]
+    #[inline]
+    pub fn read() -> StarSelectors {
+        StarSelectors::from_raw(Self::MSR.read())
+    }
 
     #[inline]
-    /// # Safety
+    /// # Panics
     ///
-    /// The values written must be valid STAR values.
+    /// Panics if the selectors cannot be encoded, see `StarSelectors::to_raw`.
     pub fn write(selectors: StarSelectors) {
-        assert_eq!(selectors.cs_syscall() + 8, selectors.ss_syscall());
-        assert_eq!(selectors.cs_sysret(), selectors.ss_sysret() + 8);
-
-        let syscall_ring = selectors.ss_syscall() & 0b11;
-        let sysret_ring = selectors.ss_sysret() & 0b11;
-
-        assert_eq!(syscall_ring, 0, "Syscall selectors must be ring 0");
-        assert_eq!(sysret_ring, 3, "Sysret selectors must be ring 3");
-
-        let sysret_base = selectors.ss_sysret().checked_sub(8).unwrap();
-        let syscall_base = selectors.cs_syscall();
-
-        unsafe { Self::MSR.write(u64::from(sysret_base) << 48 | u64::from(syscall_base) << 32) };
+        let raw = selectors.to_raw();
+        unsafe { Self::MSR.write(raw) };
     }
 }
 
@@ -276,7 +296,10 @@ pub struct Rflags;
 
 impl Rflags {
     pub const ID: u64 = 1 << 21;
+    pub const AC: u64 = 1 << 18;
+    pub const DF: u64 = 1 << 10;
     pub const IF: u64 = 1 << 9;
+    pub const TF: u64 = 1 << 8;
     pub const IOPL_LOW: u64 = 1 << 12;
     pub const IOPL_HIGH: u64 = 1 << 13;
 
@@ -344,6 +367,31 @@ impl SFMask {
 pub struct Msr<const P: u32>;
 
 impl<const P: u32> Msr<P> {
+    #[must_use]
+    #[inline]
+    pub fn read(&self) -> u64 {
+        unsafe { DynMsr::new(P) }.read()
+    }
+
+    #[inline]
+    pub unsafe fn write(&self, value: u64) {
+        unsafe { DynMsr::new(P).write(value) };
+    }
+}
+
+/// A model-specific register whose index is only known at runtime.
+pub struct DynMsr(u32);
+
+impl DynMsr {
+    #[must_use]
+    #[inline]
+    /// # Safety
+    ///
+    /// The MSR must exist on the current processor.
+    pub const unsafe fn new(index: u32) -> Self {
+        Self(index)
+    }
+
     #[must_use]
     #[inline]
     pub fn read(&self) -> u64 {
@@ -352,7 +400,7 @@ impl<const P: u32> Msr<P> {
         unsafe {
             core::arch::asm!(
                 "rdmsr",
-                in("ecx") P,
+                in("ecx") self.0,
                 lateout("eax") low,
                 lateout("edx") high,
                 options(nomem, nostack, preserves_flags)
@@ -368,7 +416,7 @@ impl<const P: u32> Msr<P> {
         unsafe {
             core::arch::asm!(
                 "wrmsr",
-                in("ecx") P,
+                in("ecx") self.0,
                 in("eax") low,
                 in("edx") high,
                 options(nostack, preserves_flags)
@@ -653,3 +701,23 @@ impl FS {
         unsafe { Self::MSR.write(base.as_u64()) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_star_encoding() {
+        // Kernel code 0x08, kernel data 0x10, user data 0x1B, user code 0x23.
+        let selectors = StarSelectors::new(0x08, 0x10, 0x23, 0x1B);
+        let raw = selectors.to_raw();
+        assert_eq!(raw, 0x0013_0008_0000_0000);
+        assert_eq!(StarSelectors::from_raw(raw), selectors);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sysret selectors must be ring 3")]
+    fn test_star_sysret_ring() {
+        let _ = StarSelectors::new(0x08, 0x10, 0x20, 0x18).to_raw();
+    }
+}
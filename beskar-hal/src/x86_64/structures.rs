@@ -145,6 +145,15 @@ impl<T: IdtFnPtr> IdtEntry<T> {
         self.options = (self.options & 0xFFF8) | u16::from(real_index);
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the index of the Interrupt Stack Table entry used by this IDT entry,
+    /// or `None` if the handler runs on the interrupted stack.
+    pub const fn stack_index(&self) -> Option<u8> {
+        let real_index = (self.options & 0x7) as u8;
+        real_index.checked_sub(1)
+    }
+
     #[must_use]
     pub fn handler_vaddr(&self) -> VirtAddr {
         let addr = (u64::from(self.ptr_high) << 32)
@@ -517,21 +526,29 @@ impl InterruptDescriptorTable {
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
-#[repr(transparent)]
-/// Page Fault Error Code
-pub struct PageFaultErrorCode(u64);
+beskar_core::bitflags! {
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    #[repr(transparent)]
+    /// Page Fault Error Code
+    pub struct PageFaultErrorCode: u64 {
+        pub const PROTECTION_VIOLATION = 1;
+        pub const WRITE = 1 << 1;
+        pub const USER_MODE = 1 << 2;
+        pub const MALFORMED_TABLE = 1 << 3;
+        pub const INSTRUCTION_FETCH = 1 << 4;
+        pub const PROTECTION_KEY = 1 << 5;
+        pub const SHADOW_STACK = 1 << 6;
+        pub const INTEL_SGX = 1 << 15;
+        pub const AMD_RMP = 1 << 31;
+    }
+}
 
 impl PageFaultErrorCode {
-    pub const PROTECTION_VIOLATION: Self = Self(1);
-    pub const WRITE: Self = Self(1 << 1);
-    pub const USER_MODE: Self = Self(1 << 2);
-    pub const MALFORMED_TABLE: Self = Self(1 << 3);
-    pub const INSTRUCTION_FETCH: Self = Self(1 << 4);
-    pub const PROTECTION_KEY: Self = Self(1 << 5);
-    pub const SHADOW_STACK: Self = Self(1 << 6);
-    pub const INTEL_SGX: Self = Self(1 << 15);
-    pub const AMD_RMP: Self = Self(1 << 31);
+    #[must_use]
+    #[inline]
+    pub const fn as_u64(self) -> u64 {
+        self.bits()
+    }
 }
 
 impl core::fmt::Binary for PageFaultErrorCode {
@@ -542,15 +559,6 @@ impl core::fmt::Binary for PageFaultErrorCode {
     }
 }
 
-impl core::ops::BitOr for PageFaultErrorCode {
-    type Output = Self;
-
-    #[inline]
-    fn bitor(self, rhs: Self) -> Self::Output {
-        Self(self.0 | rhs.0)
-    }
-}
-
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed(2))]
 pub struct DescriptorTable {
@@ -835,6 +843,33 @@ mod tests {
         assert_eq!(entry.options & 0x7, 4); // IST index starts at 1
     }
 
+    #[test]
+    fn test_double_fault_stack_index() {
+        extern "x86-interrupt" fn double_fault_handler(
+            _stack_frame: InterruptStackFrame,
+            _error_code: u64,
+        ) -> ! {
+            unreachable!()
+        }
+
+        let mut idt = InterruptDescriptorTable::new();
+        idt.double_fault.set_handler_fn(double_fault_handler, 0x08);
+        assert_eq!(idt.double_fault.stack_index(), None);
+
+        unsafe { idt.double_fault.set_stack_index(0) };
+        assert_eq!(idt.double_fault.stack_index(), Some(0));
+        assert_eq!(idt.double_fault.options & 0x7, 1);
+
+        // Other options do not change the stack
+        idt.double_fault.set_dpl(Ring::Kernel);
+        idt.double_fault.set_gate_type(GateType::Interrupt);
+        assert_eq!(idt.double_fault.stack_index(), Some(0));
+        assert_eq!(
+            idt.double_fault.handler_vaddr().as_u64(),
+            double_fault_handler as *const () as u64
+        );
+    }
+
     #[test]
     #[should_panic(expected = "Stack index must be less than 8")]
     fn test_idt_entry_set_stack_index_invalid() {
@@ -0,0 +1,438 @@
+//! CPU topology decoding.
+//!
+//! The x2APIC ID of a logical processor is split into bit fields identifying
+//! its package, core and SMT thread. The widths of these fields are reported
+//! by the extended topology CPUID leaves (`0xB` and `0x1F`).
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Maximum number of logical processors tracked by a [`Topology`].
+pub const MAX_CPUS: usize = 256;
+
+/// Sentinel value for unregistered slots.
+const NO_APIC_ID: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Domain type reported by an extended topology CPUID sub-leaf.
+pub enum LevelType {
+    Smt,
+    Core,
+    Module,
+    Tile,
+    Die,
+    Other(u8),
+}
+
+impl LevelType {
+    #[must_use]
+    #[inline]
+    const fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => None,
+            1 => Some(Self::Smt),
+            2 => Some(Self::Core),
+            3 => Some(Self::Module),
+            4 => Some(Self::Tile),
+            5 => Some(Self::Die),
+            x => Some(Self::Other(x)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A decoded sub-leaf of CPUID leaf `0xB` or `0x1F`.
+pub struct TopologyLevel {
+    level_type: LevelType,
+    shift: u8,
+    logical_count: u16,
+    x2apic_id: u32,
+}
+
+impl TopologyLevel {
+    #[must_use]
+    /// Decodes the registers returned by an extended topology sub-leaf.
+    ///
+    /// Returns `None` for the terminating (invalid) sub-leaf.
+    pub const fn from_registers(eax: u32, ebx: u32, ecx: u32, edx: u32) -> Option<Self> {
+        let Some(level_type) = LevelType::from_raw(((ecx >> 8) & 0xFF) as u8) else {
+            return None;
+        };
+        Some(Self {
+            level_type,
+            shift: (eax & 0x1F) as u8,
+            logical_count: (ebx & 0xFFFF) as u16,
+            x2apic_id: edx,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn level_type(&self) -> LevelType {
+        self.level_type
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of bits to shift the x2APIC ID right to get the ID of the next level.
+    pub const fn shift(&self) -> u8 {
+        self.shift
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of logical processors at this level.
+    ///
+    /// This is informational only and should not be used for enumeration.
+    pub const fn logical_count(&self) -> u16 {
+        self.logical_count
+    }
+
+    #[must_use]
+    #[inline]
+    /// x2APIC ID of the processor that executed CPUID.
+    pub const fn x2apic_id(&self) -> u32 {
+        self.x2apic_id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Layout of the package, core and thread fields within an APIC ID.
+pub struct ApicIdLayout {
+    smt_shift: u8,
+    package_shift: u8,
+}
+
+impl ApicIdLayout {
+    /// Layout used when no topology information is available:
+    /// every APIC ID is a distinct core of a single package.
+    pub const FLAT: Self = Self {
+        smt_shift: 0,
+        package_shift: 32,
+    };
+
+    #[must_use]
+    #[inline]
+    pub const fn new(smt_shift: u8, package_shift: u8) -> Self {
+        assert!(smt_shift <= package_shift && package_shift <= 32);
+        Self {
+            smt_shift,
+            package_shift,
+        }
+    }
+
+    #[must_use]
+    /// Builds the layout from the successive sub-leaves of CPUID leaf `0xB` or `0x1F`.
+    ///
+    /// The SMT level gives the thread field width, while the last level gives
+    /// the number of bits shared by every processor of a package.
+    pub fn from_levels<I: IntoIterator<Item = TopologyLevel>>(levels: I) -> Self {
+        let mut smt_shift = None;
+        let mut package_shift = None;
+
+        for level in levels {
+            if level.level_type() == LevelType::Smt {
+                smt_shift = Some(level.shift());
+            }
+            package_shift = Some(level.shift());
+        }
+
+        package_shift.map_or(Self::FLAT, |package_shift| {
+            Self::new(smt_shift.unwrap_or(0).min(package_shift), package_shift)
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn smt_shift(&self) -> u8 {
+        self.smt_shift
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn package_shift(&self) -> u8 {
+        self.package_shift
+    }
+
+    #[must_use]
+    /// Splits an APIC ID into its package, core and thread IDs.
+    pub const fn decompose(&self, apic_id: u32) -> CpuLocation {
+        let thread = apic_id & low_mask(self.smt_shift);
+        let core = (apic_id & low_mask(self.package_shift)) >> self.smt_shift;
+        let package = apic_id.checked_shr(self.package_shift as u32);
+        CpuLocation {
+            package: match package {
+                Some(p) => p,
+                None => 0,
+            },
+            core,
+            thread,
+        }
+    }
+}
+
+#[must_use]
+#[inline]
+const fn low_mask(bits: u8) -> u32 {
+    match 1_u32.checked_shl(bits as u32) {
+        Some(v) => v - 1,
+        None => u32::MAX,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Position of a logical processor in the topology.
+///
+/// `core` is relative to the package and `thread` is relative to the core.
+pub struct CpuLocation {
+    package: u32,
+    core: u32,
+    thread: u32,
+}
+
+impl CpuLocation {
+    #[must_use]
+    #[inline]
+    pub const fn package(&self) -> u32 {
+        self.package
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn core(&self) -> u32 {
+        self.core
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn thread(&self) -> u32 {
+        self.thread
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns true if both locations share the same physical core.
+    pub const fn same_core(&self, other: &Self) -> bool {
+        self.package == other.package && self.core == other.core
+    }
+}
+
+/// Topology of the logical processors of the system.
+///
+/// Processors register their APIC ID under their core ID as they come up,
+/// which can happen concurrently.
+pub struct Topology {
+    layout: ApicIdLayout,
+    apic_ids: [AtomicU32; MAX_CPUS],
+}
+
+impl Topology {
+    #[must_use]
+    #[inline]
+    pub const fn new(layout: ApicIdLayout) -> Self {
+        Self {
+            layout,
+            apic_ids: [const { AtomicU32::new(NO_APIC_ID) }; MAX_CPUS],
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn layout(&self) -> ApicIdLayout {
+        self.layout
+    }
+
+    /// Registers the APIC ID of a logical processor.
+    pub fn register(&self, core_id: usize, apic_id: u32) {
+        assert_ne!(apic_id, NO_APIC_ID, "Invalid APIC ID");
+        self.apic_ids[core_id].store(apic_id, Ordering::Release);
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the APIC ID of a registered logical processor.
+    pub fn apic_id(&self, core_id: usize) -> Option<u32> {
+        let apic_id = self.apic_ids.get(core_id)?.load(Ordering::Acquire);
+        (apic_id != NO_APIC_ID).then_some(apic_id)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the core ID of the logical processor with the given APIC ID.
+    pub fn core_id(&self, apic_id: u32) -> Option<usize> {
+        self.registered()
+            .find(|&(_, id)| id == apic_id)
+            .map(|(c, _)| c)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the location of the logical processor with the given APIC ID.
+    pub const fn location(&self, apic_id: u32) -> CpuLocation {
+        self.layout.decompose(apic_id)
+    }
+
+    /// Iterates over `(core_id, apic_id)` pairs of registered processors.
+    pub fn registered(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        (0..MAX_CPUS).filter_map(|core_id| Some((core_id, self.apic_id(core_id)?)))
+    }
+
+    /// Iterates over the APIC IDs of the SMT siblings of the given processor,
+    /// excluding itself.
+    pub fn siblings(&self, apic_id: u32) -> impl Iterator<Item = u32> + '_ {
+        let location = self.location(apic_id);
+        self.registered().filter_map(move |(_, other)| {
+            (other != apic_id && self.location(other).same_core(&location)).then_some(other)
+        })
+    }
+
+    #[must_use]
+    /// Returns the number of logical processors registered.
+    pub fn threads(&self) -> usize {
+        self.registered().count()
+    }
+
+    #[must_use]
+    /// Returns the number of physical cores registered.
+    pub fn cores(&self) -> usize {
+        self.count_distinct(|a, b| a.same_core(&b))
+    }
+
+    #[must_use]
+    /// Returns the number of packages registered.
+    pub fn packages(&self) -> usize {
+        self.count_distinct(|a, b| a.package() == b.package())
+    }
+
+    fn count_distinct(&self, same: impl Fn(CpuLocation, CpuLocation) -> bool) -> usize {
+        self.registered()
+            .filter(|&(core_id, apic_id)| {
+                let location = self.location(apic_id);
+                // Only count the first registered processor of each group.
+                !self
+                    .registered()
+                    .take_while(|&(other_core, _)| other_core < core_id)
+                    .any(|(_, other)| same(self.location(other), location))
+            })
+            .count()
+    }
+
+    #[must_use]
+    /// Returns the topological distance between two logical processors.
+    ///
+    /// `0` is the same processor, `1` an SMT sibling, `2` another core of
+    /// the same package and `3` a processor of another package.
+    pub const fn distance(&self, apic_a: u32, apic_b: u32) -> u8 {
+        let a = self.location(apic_a);
+        let b = self.location(apic_b);
+        if apic_a == apic_b {
+            0
+        } else if a.same_core(&b) {
+            1
+        } else if a.package() == b.package() {
+            2
+        } else {
+            3
+        }
+    }
+
+    /// Iterates over the core IDs of the other registered processors,
+    /// closest first.
+    ///
+    /// This is the order in which a processor should look for work to steal,
+    /// so that migrated threads stay close to their caches.
+    pub fn steal_order(&self, core_id: usize) -> impl Iterator<Item = usize> + '_ {
+        let own = self.apic_id(core_id);
+        (1..=3).flat_map(move |distance| {
+            self.registered().filter_map(move |(other_core, other)| {
+                let own = own?;
+                (self.distance(own, other) == distance).then_some(other_core)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    /// Leaf `0xB` as reported by a 2-package, 4-core, 2-thread machine,
+    /// queried from the processor with x2APIC ID 13.
+    const SAMPLE_LEAF_B: [[u32; 4]; 3] = [
+        // Sub-leaf 0: SMT, shift 1, 2 logical processors
+        [0x0000_0001, 0x0000_0002, 0x0000_0100, 13],
+        // Sub-leaf 1: Core, shift 4, 8 logical processors
+        [0x0000_0004, 0x0000_0008, 0x0000_0201, 13],
+        // Sub-leaf 2: invalid, terminates the enumeration
+        [0x0000_0000, 0x0000_0000, 0x0000_0002, 13],
+    ];
+
+    fn sample_levels() -> Vec<TopologyLevel> {
+        SAMPLE_LEAF_B
+            .iter()
+            .map_while(|&[eax, ebx, ecx, edx]| TopologyLevel::from_registers(eax, ebx, ecx, edx))
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_leaf() {
+        let levels = sample_levels();
+        assert_eq!(levels.len(), 2);
+
+        assert_eq!(levels[0].level_type(), LevelType::Smt);
+        assert_eq!(levels[0].shift(), 1);
+        assert_eq!(levels[0].logical_count(), 2);
+        assert_eq!(levels[0].x2apic_id(), 13);
+
+        assert_eq!(levels[1].level_type(), LevelType::Core);
+        assert_eq!(levels[1].shift(), 4);
+        assert_eq!(levels[1].logical_count(), 8);
+    }
+
+    #[test]
+    fn test_layout_decompose() {
+        let layout = ApicIdLayout::from_levels(sample_levels());
+        assert_eq!(layout, ApicIdLayout::new(1, 4));
+
+        let location = layout.decompose(13);
+        assert_eq!(location.package(), 0);
+        assert_eq!(location.core(), 6);
+        assert_eq!(location.thread(), 1);
+
+        let location = layout.decompose(0x12);
+        assert_eq!(location.package(), 1);
+        assert_eq!(location.core(), 1);
+        assert_eq!(location.thread(), 0);
+
+        assert_eq!(ApicIdLayout::from_levels([]), ApicIdLayout::FLAT);
+        assert_eq!(ApicIdLayout::FLAT.decompose(7).core(), 7);
+    }
+
+    #[test]
+    fn test_topology_siblings() {
+        let topology = Topology::new(ApicIdLayout::new(1, 4));
+        // Two packages of two cores with two threads each.
+        for (core_id, apic_id) in [0, 1, 2, 3, 16, 17, 18, 19].into_iter().enumerate() {
+            topology.register(core_id, apic_id);
+        }
+
+        assert_eq!(topology.threads(), 8);
+        assert_eq!(topology.cores(), 4);
+        assert_eq!(topology.packages(), 2);
+
+        assert_eq!(topology.siblings(2).collect::<Vec<_>>(), [3]);
+        assert_eq!(topology.siblings(17).collect::<Vec<_>>(), [16]);
+        assert_eq!(topology.core_id(18), Some(6));
+
+        assert_eq!(topology.distance(0, 0), 0);
+        assert_eq!(topology.distance(0, 1), 1);
+        assert_eq!(topology.distance(0, 3), 2);
+        assert_eq!(topology.distance(0, 16), 3);
+
+        let order = topology.steal_order(0).collect::<Vec<_>>();
+        assert_eq!(order, [1, 2, 3, 4, 5, 6, 7]);
+        let order = topology.steal_order(5).collect::<Vec<_>>();
+        assert_eq!(order, [4, 6, 7, 0, 1, 2, 3]);
+    }
+}
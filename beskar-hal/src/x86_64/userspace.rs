@@ -37,6 +37,23 @@ impl Ring {
     }
 }
 
+/// End of the lower canonical half of the address space, with 48-bit virtual addresses.
+///
+/// Canonical addresses are `0..=0x0000_7FFF_FFFF_FFFF` (the lower half, used by userspace)
+/// and `0xFFFF_8000_0000_0000..=0xFFFF_FFFF_FFFF_FFFF` (the upper half).
+pub const LOWER_HALF_END: u64 = 0x0000_8000_0000_0000;
+
+#[must_use]
+#[inline]
+/// Returns whether `SYSRET` can safely return to `rip`.
+///
+/// On Intel CPUs, `SYSRET` to a non-canonical address raises `#GP` in ring 0,
+/// with the user stack pointer already loaded. Only the lower half is accepted,
+/// as userspace never runs in the upper half.
+pub const fn is_sysret_safe(rip: u64) -> bool {
+    rip < LOWER_HALF_END
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,4 +70,19 @@ mod tests {
         assert_eq!(Ring::from_u8(2), Ring::Hypervisor);
         assert_eq!(Ring::from_u8(3), Ring::User);
     }
+
+    #[test]
+    fn test_sysret_safe() {
+        assert!(is_sysret_safe(0));
+        assert!(is_sysret_safe(0x40_0000));
+        assert!(is_sysret_safe(0x0000_7FFF_FFFF_FFFF));
+
+        // First non-canonical address
+        assert!(!is_sysret_safe(0x0000_8000_0000_0000));
+        assert!(!is_sysret_safe(0x0000_FFFF_FFFF_FFFF));
+        assert!(!is_sysret_safe(0xFFFF_7FFF_FFFF_FFFF));
+        // Canonical, but in the upper half
+        assert!(!is_sysret_safe(0xFFFF_8000_0000_0000));
+        assert!(!is_sysret_safe(u64::MAX));
+    }
 }
@@ -0,0 +1,275 @@
+//! XSAVE area layout.
+//!
+//! The XSAVE area holds the legacy FXSAVE region (x87 and SSE state), a header,
+//! then the extended state components such as AVX.
+//! CPUID leaf `0xD` reports the size of the area for the components enabled in `XCR0`,
+//! and the offset of each extended component in the standard (non-compacted) format.
+use thiserror::Error;
+
+/// CPUID leaf reporting the XSAVE layout.
+pub const XSAVE_LEAF: u32 = 0xD;
+/// Size of the legacy region, laid out as the FXSAVE area.
+pub const LEGACY_REGION_SIZE: u32 = 512;
+/// Size of the XSAVE header, which follows the legacy region.
+pub const HEADER_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A state component saved by XSAVE.
+pub enum Component {
+    X87,
+    Sse,
+    Avx,
+}
+
+impl Component {
+    #[must_use]
+    #[inline]
+    /// Index of the component, which is its bit in `XCR0` and its CPUID sub-leaf.
+    pub const fn index(self) -> u32 {
+        match self {
+            Self::X87 => 0,
+            Self::Sse => 1,
+            Self::Avx => 2,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn mask(self) -> u64 {
+        1 << self.index()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Location of a component in the XSAVE area.
+pub struct ComponentLayout {
+    offset: u32,
+    size: u32,
+}
+
+impl ComponentLayout {
+    /// The x87 state (control words, pointers and registers) in the legacy region.
+    pub const X87: Self = Self {
+        offset: 0,
+        size: 160,
+    };
+    /// The XMM registers in the legacy region.
+    ///
+    /// `MXCSR`, which is also part of the SSE state, lives at offset 24.
+    pub const SSE: Self = Self {
+        offset: 160,
+        size: 256,
+    };
+
+    #[must_use]
+    /// Decodes the registers returned by the sub-leaf of an extended component.
+    ///
+    /// Returns `None` if the component is not supported.
+    pub const fn from_registers(eax: u32, ebx: u32) -> Option<Self> {
+        if eax == 0 {
+            None
+        } else {
+            Some(Self {
+                offset: ebx,
+                size: eax,
+            })
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn end(&self) -> u32 {
+        self.offset.saturating_add(self.size)
+    }
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+/// An inconsistent layout reported by CPUID.
+pub enum XsaveError {
+    #[error("XSAVE area is smaller than the legacy region and header")]
+    TooSmall,
+    #[error("XSAVE area is larger than the maximum size")]
+    TooLarge,
+    #[error("x87 and SSE state are not supported")]
+    MissingLegacyState,
+    #[error("Component {0:?} lies outside of the XSAVE area")]
+    OutOfBounds(Component),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Layout of the XSAVE area, for the components currently enabled in `XCR0`.
+pub struct XsaveLayout {
+    size: u32,
+    max_size: u32,
+    supported: u64,
+    avx: Option<ComponentLayout>,
+}
+
+impl XsaveLayout {
+    /// Decodes the layout from CPUID leaf `0xD`.
+    ///
+    /// `main` holds the registers (`EAX`, `EBX`, `ECX`, `EDX`) of sub-leaf 0,
+    /// and `avx` the decoded sub-leaf 2.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sizes are inconsistent with one another.
+    pub const fn new(main: [u32; 4], avx: Option<ComponentLayout>) -> Result<Self, XsaveError> {
+        let [supported_low, size, max_size, supported_high] = main;
+        let supported = ((supported_high as u64) << 32) | supported_low as u64;
+
+        let legacy = Component::X87.mask() | Component::Sse.mask();
+        if supported & legacy != legacy {
+            return Err(XsaveError::MissingLegacyState);
+        }
+        if size < LEGACY_REGION_SIZE + HEADER_SIZE {
+            return Err(XsaveError::TooSmall);
+        }
+        if size > max_size {
+            return Err(XsaveError::TooLarge);
+        }
+
+        let avx = if supported & Component::Avx.mask() == 0 {
+            None
+        } else {
+            avx
+        };
+        if let Some(avx) = avx
+            && (avx.offset() < LEGACY_REGION_SIZE + HEADER_SIZE || avx.end() > max_size)
+        {
+            return Err(XsaveError::OutOfBounds(Component::Avx));
+        }
+
+        Ok(Self {
+            size,
+            max_size,
+            supported,
+            avx,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Size of the XSAVE area for the components enabled in `XCR0`.
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    #[must_use]
+    #[inline]
+    /// Size of the XSAVE area if every supported component was enabled.
+    pub const fn max_size(&self) -> u32 {
+        self.max_size
+    }
+
+    #[must_use]
+    #[inline]
+    /// Components supported by the processor, as a mask of `XCR0` bits.
+    pub const fn supported(&self) -> u64 {
+        self.supported
+    }
+
+    #[must_use]
+    /// Returns the location of a component, if it is saved in an area of `size()` bytes.
+    ///
+    /// In the standard format, the offset of a component does not depend on the enabled ones,
+    /// so a component is saved if and only if it is enabled.
+    pub const fn component(&self, component: Component) -> Option<ComponentLayout> {
+        let layout = match component {
+            Component::X87 => Some(ComponentLayout::X87),
+            Component::Sse => Some(ComponentLayout::SSE),
+            Component::Avx => self.avx,
+        };
+        match layout {
+            Some(layout) if layout.end() <= self.size => Some(layout),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sub-leaf 2 of a processor with AVX.
+    const AVX: Option<ComponentLayout> = ComponentLayout::from_registers(256, 576);
+
+    #[test]
+    fn test_legacy_only() {
+        // x87, SSE and AVX supported, but only x87 and SSE enabled
+        let layout = XsaveLayout::new([0b111, 576, 832, 0], AVX).unwrap();
+        assert_eq!(layout.size(), 576);
+        assert_eq!(layout.max_size(), 832);
+        assert_eq!(layout.supported(), 0b111);
+
+        assert_eq!(layout.component(Component::X87), Some(ComponentLayout::X87));
+        let sse = layout.component(Component::Sse).unwrap();
+        assert_eq!((sse.offset(), sse.size()), (160, 256));
+        assert_eq!(layout.component(Component::Avx), None);
+    }
+
+    #[test]
+    fn test_avx() {
+        let layout = XsaveLayout::new([0b111, 832, 832, 0], AVX).unwrap();
+        assert_eq!(layout.size(), 832);
+
+        let avx = layout.component(Component::Avx).unwrap();
+        assert_eq!(avx.offset(), 576);
+        assert_eq!(avx.size(), 256);
+        assert_eq!(avx.end(), layout.size());
+
+        // With AVX-512 and PKRU supported, the maximum size is larger
+        let layout = XsaveLayout::new([0x2E7, 832, 2696, 0], AVX).unwrap();
+        assert_eq!(layout.size(), 832);
+        assert_eq!(layout.max_size(), 2696);
+        assert!(layout.component(Component::Avx).is_some());
+    }
+
+    #[test]
+    fn test_unsupported_avx() {
+        assert_eq!(ComponentLayout::from_registers(0, 0), None);
+
+        // A sub-leaf for an unsupported component is ignored
+        let layout = XsaveLayout::new([0b11, 576, 576, 0], AVX).unwrap();
+        assert_eq!(layout.component(Component::Avx), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            XsaveLayout::new([0b111, 512, 832, 0], AVX),
+            Err(XsaveError::TooSmall)
+        );
+        assert_eq!(
+            XsaveLayout::new([0b111, 1024, 832, 0], AVX),
+            Err(XsaveError::TooLarge)
+        );
+        assert_eq!(
+            XsaveLayout::new([0b001, 576, 576, 0], None),
+            Err(XsaveError::MissingLegacyState)
+        );
+        assert_eq!(
+            XsaveLayout::new([0b111, 576, 700, 0], AVX),
+            Err(XsaveError::OutOfBounds(Component::Avx))
+        );
+        assert_eq!(
+            XsaveLayout::new(
+                [0b111, 576, 832, 0],
+                ComponentLayout::from_registers(256, 128)
+            ),
+            Err(XsaveError::OutOfBounds(Component::Avx))
+        );
+    }
+}
@@ -1,2 +1,3 @@
 pub mod syscalls;
 pub mod time;
+pub mod tls;
@@ -1,5 +1,18 @@
 use beskar_core::syscall::Syscall;
 
+pub fn syscall_0(syscall: Syscall) -> u64 {
+    let res_code: u64;
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            in("rax") u64::from(syscall),
+            lateout("rax") res_code,
+            options(nostack, preserves_flags)
+        );
+    }
+    res_code
+}
+
 pub fn syscall_1(syscall: Syscall, arg1: u64) -> u64 {
     let res_code: u64;
     unsafe {
@@ -61,3 +74,35 @@ pub fn syscall_4(syscall: Syscall, arg1: u64, arg2: u64, arg3: u64, arg4: u64) -
     }
     res_code
 }
+
+pub fn syscall_5(syscall: Syscall, arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) -> u64 {
+    let res_code: u64;
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            in("rax") u64::from(syscall),
+            lateout("rax") res_code,
+            in("rdi") arg1,
+            in("rsi") arg2,
+            in("rdx") arg3,
+            in("r10") arg4,
+            in("r8") arg5,
+            options(nostack, preserves_flags)
+        );
+    }
+    res_code
+}
+
+#[unsafe(naked)]
+/// Return address of signal handlers, which restores the state they interrupted.
+///
+/// The kernel enters handlers with this function as their return address,
+/// so the stack pointer is right past the signal frame when it runs.
+pub extern "C" fn signal_restorer() -> ! {
+    core::arch::naked_asm!(
+        "mov rax, {}",
+        "syscall",
+        "ud2",
+        const Syscall::SigReturn as u64,
+    );
+}
@@ -0,0 +1,18 @@
+use beskar_core::process::tls::Tcb;
+
+#[must_use]
+#[inline]
+/// Reads the ID of the current thread from its TCB, pointed to by FS.
+pub fn thread_id() -> u64 {
+    let id: u64;
+    // Safety: The kernel sets up a TCB for every user thread.
+    unsafe {
+        core::arch::asm!(
+            "mov {id}, fs:[{offset}]",
+            id = out(reg) id,
+            offset = const Tcb::THREAD_ID_OFFSET,
+            options(nostack, readonly, preserves_flags),
+        );
+    }
+    id
+}
@@ -0,0 +1,281 @@
+//! Command line argument parsing.
+//!
+//! The parser recognizes:
+//!
+//! - long flags (`--all`), with an optional value (`--name=value` or `--name value`),
+//! - short flags (`-a`), which can be grouped (`-al`) and take a value
+//!   (`-nvalue` or `-n value`),
+//! - positional arguments, including a lone `-`.
+//!
+//! A `--` argument ends flag parsing: every argument after it is positional.
+//! Unknown flags are reported as an error rather than ignored.
+//!
+//! Parsed arguments borrow from the original argument list.
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An option accepted by an `ArgParser`.
+pub struct Opt {
+    short: Option<char>,
+    long: &'static str,
+    takes_value: bool,
+}
+
+impl Opt {
+    #[must_use]
+    #[inline]
+    /// Creates a boolean flag.
+    pub const fn flag(short: Option<char>, long: &'static str) -> Self {
+        Self {
+            short,
+            long,
+            takes_value: false,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Creates a flag that requires a value.
+    pub const fn value(short: Option<char>, long: &'static str) -> Self {
+        Self {
+            short,
+            long,
+            takes_value: true,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn short(&self) -> Option<char> {
+        self.short
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn long(&self) -> &'static str {
+        self.long
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn takes_value(&self) -> bool {
+        self.takes_value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An error caused by an invalid command line.
+pub enum UsageError<'a> {
+    /// A long flag that is not part of the accepted options.
+    UnknownFlag(&'a str),
+    /// A short flag that is not part of the accepted options.
+    UnknownShortFlag(char),
+    /// A flag requiring a value was given none.
+    MissingValue(&'static str),
+    /// A boolean flag was given a value (`--flag=value`).
+    UnexpectedValue(&'static str),
+}
+
+impl fmt::Display for UsageError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFlag(flag) => write!(f, "unknown flag: {flag}"),
+            Self::UnknownShortFlag(c) => write!(f, "unknown flag: -{c}"),
+            Self::MissingValue(long) => write!(f, "missing value for --{long}"),
+            Self::UnexpectedValue(long) => write!(f, "--{long} does not take a value"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A command line parser, described by the options it accepts.
+pub struct ArgParser<'o> {
+    options: &'o [Opt],
+}
+
+impl<'o> ArgParser<'o> {
+    #[must_use]
+    #[inline]
+    pub const fn new(options: &'o [Opt]) -> Self {
+        Self { options }
+    }
+
+    /// Parses the given arguments, not including the program name.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `UsageError` if a flag is unknown or misses its value.
+    pub fn parse<'a, S: AsRef<str>>(&self, args: &'a [S]) -> Result<Args<'a>, UsageError<'a>> {
+        let mut parsed = Args {
+            flags: Vec::new(),
+            positionals: Vec::new(),
+        };
+
+        let mut iter = args.iter().map(AsRef::as_ref);
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                parsed.positionals.extend(iter);
+                break;
+            }
+
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline_value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (long, None),
+                };
+                let opt = self
+                    .options
+                    .iter()
+                    .find(|opt| opt.long == name)
+                    .ok_or(UsageError::UnknownFlag(arg))?;
+
+                let value = match (opt.takes_value, inline_value) {
+                    (true, Some(value)) => Some(value),
+                    (true, None) => Some(iter.next().ok_or(UsageError::MissingValue(opt.long))?),
+                    (false, Some(_)) => return Err(UsageError::UnexpectedValue(opt.long)),
+                    (false, None) => None,
+                };
+                parsed.flags.push((opt.long, value));
+            } else if let Some(shorts) = arg.strip_prefix('-')
+                && !shorts.is_empty()
+            {
+                for (idx, c) in shorts.char_indices() {
+                    let opt = self
+                        .options
+                        .iter()
+                        .find(|opt| opt.short == Some(c))
+                        .ok_or(UsageError::UnknownShortFlag(c))?;
+
+                    if opt.takes_value {
+                        // The rest of the group is the value, if any.
+                        let rest = &shorts[idx + c.len_utf8()..];
+                        let value = if rest.is_empty() {
+                            iter.next().ok_or(UsageError::MissingValue(opt.long))?
+                        } else {
+                            rest
+                        };
+                        parsed.flags.push((opt.long, Some(value)));
+                        break;
+                    }
+                    parsed.flags.push((opt.long, None));
+                }
+            } else {
+                parsed.positionals.push(arg);
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of a successful parse.
+pub struct Args<'a> {
+    /// Flags in the order they were given, identified by their long name.
+    flags: Vec<(&'static str, Option<&'a str>)>,
+    positionals: Vec<&'a str>,
+}
+
+impl<'a> Args<'a> {
+    #[must_use]
+    /// Returns whether the flag with the given long name was given.
+    pub fn flag(&self, long: &str) -> bool {
+        self.flags.iter().any(|(name, _)| *name == long)
+    }
+
+    #[must_use]
+    /// Returns the value of the flag with the given long name.
+    ///
+    /// If the flag was given several times, the last value wins.
+    pub fn value(&self, long: &str) -> Option<&'a str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == long)
+            .and_then(|(_, value)| *value)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the positional arguments, in order.
+    pub fn positionals(&self) -> &[&'a str] {
+        &self.positionals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: &[Opt] = &[
+        Opt::flag(Some('l'), "long"),
+        Opt::flag(Some('a'), "all"),
+        Opt::value(Some('n'), "count"),
+        Opt::flag(None, "verbose"),
+    ];
+
+    #[test]
+    fn test_representative() {
+        let input = ["-la", "--count", "3", "first", "--verbose", "second"];
+        let args = ArgParser::new(OPTIONS).parse(&input).unwrap();
+
+        assert!(args.flag("long"));
+        assert!(args.flag("all"));
+        assert!(args.flag("verbose"));
+        assert_eq!(args.value("count"), Some("3"));
+        assert_eq!(args.positionals(), ["first", "second"]);
+    }
+
+    #[test]
+    fn test_values() {
+        let parser = ArgParser::new(OPTIONS);
+
+        let args = parser.parse(&["--count=4", "-n5", "x"]).unwrap();
+        assert_eq!(args.value("count"), Some("5"));
+        assert_eq!(args.positionals(), ["x"]);
+
+        let args = parser.parse(&["-ln", "6"]).unwrap();
+        assert!(args.flag("long"));
+        assert_eq!(args.value("count"), Some("6"));
+
+        assert!(!args.flag("all"));
+        assert_eq!(args.value("all"), None);
+    }
+
+    #[test]
+    fn test_end_of_flags() {
+        let input = ["-a", "--", "--long", "-", "-n"];
+        let args = ArgParser::new(OPTIONS).parse(&input).unwrap();
+
+        assert!(args.flag("all"));
+        assert!(!args.flag("long"));
+        assert_eq!(args.positionals(), ["--long", "-", "-n"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let parser = ArgParser::new(OPTIONS);
+
+        assert_eq!(
+            parser.parse(&["--unknown"]),
+            Err(UsageError::UnknownFlag("--unknown"))
+        );
+        assert_eq!(
+            parser.parse(&["-lx"]),
+            Err(UsageError::UnknownShortFlag('x'))
+        );
+        assert_eq!(
+            parser.parse(&["--count"]),
+            Err(UsageError::MissingValue("count"))
+        );
+        assert_eq!(
+            parser.parse(&["-n"]),
+            Err(UsageError::MissingValue("count"))
+        );
+        assert_eq!(
+            parser.parse(&["--all=yes"]),
+            Err(UsageError::UnexpectedValue("all"))
+        );
+    }
+}
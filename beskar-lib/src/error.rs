@@ -16,6 +16,9 @@ pub enum IoErrorKind {
     PermissionDenied,
     InvalidData,
     UnexpectedEof,
+    BrokenPipe,
+    TimedOut,
+    WouldBlock,
     Other,
 }
 
@@ -1,14 +1,88 @@
-use crate::error::IoResult;
+use crate::error::{IoResult, SyscallError, SyscallResult};
 use alloc::{vec, vec::Vec};
+use beskar_core::syscall::{SyscallExitCode, consts::DUP_CLOSE_ON_EXEC};
 use core::fmt::Write as _;
 
 mod traits;
 pub use traits::{BufRead, Read, Seek, SeekFrom, Write};
 
+pub mod display;
 mod file;
-pub use file::File;
+pub use beskar_core::syscall::consts::MMAP_FILE_OFFSET_ALIGN;
+pub use beskar_core::syscall::{FileInfo, FileKind};
+pub use file::{File, metadata, read_dir};
 pub mod keyboard;
+mod pipe;
+pub use pipe::{PipeReader, PipeWriter, pipe};
+mod poller;
+pub use poller::Poller;
 pub mod screen;
+mod slice;
+pub use screen::{FbInfo, framebuffer_info};
+pub use slice::{IoSlice, IoSliceMut};
+
+pub use beskar_core::syscall::consts::{STDERR_HANDLE, STDIN_HANDLE, STDOUT_HANDLE};
+pub use beskar_core::syscall::poll::{PollEvents, PollItem, PollSource, PollTimeout};
+
+/// Duplicate a raw handle, and return the new handle
+///
+/// The new handle is inherited by child processes.
+///
+/// # Errors
+///
+/// Returns an error if the handle is invalid.
+pub fn dup(handle: i64) -> SyscallResult<i64> {
+    let new = crate::sys::sc_dup(handle, 0);
+    if new >= 0 {
+        Ok(new)
+    } else {
+        Err(SyscallError::new(-1))
+    }
+}
+
+/// Make the raw handle `new` refer to what `old` refers to, closing `new` first if it is open
+///
+/// This is how standard handles are redirected, e.g. `dup2(file.raw_handle(), STDOUT_HANDLE)`.
+/// The new handle is inherited by child processes.
+///
+/// # Errors
+///
+/// Returns an error if `old` is invalid or if `new` is negative.
+pub fn dup2(old: i64, new: i64) -> SyscallResult<()> {
+    dup2_with_flags(old, new, 0)
+}
+
+/// Set whether a raw handle is left out of the handles inherited by child processes
+///
+/// # Errors
+///
+/// Returns an error if the handle is invalid.
+pub fn set_close_on_exec(handle: i64, close_on_exec: bool) -> SyscallResult<()> {
+    let flags = if close_on_exec { DUP_CLOSE_ON_EXEC } else { 0 };
+    // Duplicating a handle into itself only sets its flags
+    dup2_with_flags(handle, handle, flags)
+}
+
+fn dup2_with_flags(old: i64, new: i64, flags: u64) -> SyscallResult<()> {
+    if crate::sys::sc_dup2(old, new, flags) == SyscallExitCode::Success {
+        Ok(())
+    } else {
+        Err(SyscallError::new(-1))
+    }
+}
+
+/// Wait until at least one of the given sources is ready, or the timeout elapses.
+///
+/// The events that occurred are stored in each item, see `PollItem::ready`.
+/// Returns the number of ready items, which is 0 if the timeout elapsed.
+///
+/// # Errors
+///
+/// Returns an error if the kernel rejects the items.
+pub fn poll(items: &mut [PollItem], timeout: PollTimeout) -> IoResult<usize> {
+    let res = crate::sys::sc_poll(items.as_mut_ptr(), items.len() as u64, timeout.to_raw());
+    usize::try_from(res).map_err(|_| crate::error::IoError::new(crate::error::IoErrorKind::Other))
+}
 
 /// A buffered reader that implements `BufRead`
 pub struct BufReader<R> {
@@ -0,0 +1,64 @@
+//! Fullscreen access to the display, arbitrated between apps.
+//!
+//! An app holding the display is in the foreground: only its framebuffer flushes reach
+//! the screen, and only it receives keyboard input. When another app acquires the display,
+//! or when the user presses Alt+Tab, the app goes to the background: its flushes are
+//! discarded and it gets no key events until it gets the display back.
+//!
+//! While no app holds the display, every app may draw and read the keyboard.
+//!
+//! Changes are reported as `DisplayEvent`s, which can be waited for by polling
+//! `PollSource::Display`.
+use super::screen::FrameBuffer;
+use crate::error::{Result, SyscallError};
+use beskar_core::syscall::SyscallExitCode;
+pub use beskar_core::syscall::display::DisplayEvent;
+
+/// The display, held by the current app until dropped.
+pub struct Display {
+    framebuffer: FrameBuffer,
+}
+
+impl Display {
+    /// Bring the app to the foreground and open the framebuffer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if too many apps hold the display,
+    /// or if the framebuffer cannot be opened.
+    pub fn acquire() -> Result<Self> {
+        if crate::sys::sc_acquire_display() != SyscallExitCode::Success {
+            return Err(SyscallError::new(-1).into());
+        }
+        match FrameBuffer::open() {
+            Ok(framebuffer) => Ok(Self { framebuffer }),
+            Err(err) => {
+                crate::sys::sc_release_display();
+                Err(err)
+            }
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// The framebuffer of the app.
+    ///
+    /// While the app is in the background, drawing is only done in the internal buffer.
+    pub const fn framebuffer(&mut self) -> &mut FrameBuffer {
+        &mut self.framebuffer
+    }
+
+    #[must_use]
+    #[inline]
+    /// Take the pending display event, if any.
+    pub fn poll_event(&self) -> Option<DisplayEvent> {
+        DisplayEvent::try_from(crate::sys::sc_display_event()).ok()
+    }
+}
+
+impl Drop for Display {
+    #[inline]
+    fn drop(&mut self) {
+        crate::sys::sc_release_display();
+    }
+}
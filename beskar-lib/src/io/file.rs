@@ -1,7 +1,19 @@
-use super::traits::{Read, Seek, SeekFrom, Write};
-use crate::error::{FileError, FileErrorKind, FileResult, IoError, IoErrorKind, IoResult};
-use alloc::string::String;
-use beskar_core::syscall::SyscallExitCode;
+use super::{
+    IoSlice, IoSliceMut,
+    traits::{Read, Seek, SeekFrom, Write},
+};
+use crate::error::{
+    FileError, FileErrorKind, FileResult, IoError, IoErrorKind, IoResult, SyscallError,
+    SyscallResult,
+};
+use alloc::{string::String, vec, vec::Vec};
+use beskar_core::syscall::{
+    FileInfo, FileKind, FileLockOperation, SeekWhence, SyscallExitCode,
+    consts::{
+        FILE_OFFSET_CURRENT, FLOCK_NONBLOCKING, FLOCK_WOULD_BLOCK, OPEN_APPEND, READ_TIMED_OUT,
+    },
+    poll::PollTimeout,
+};
 use core::convert::TryFrom;
 
 type Handle = i64;
@@ -13,25 +25,45 @@ const fn is_valid_handle(handle: Handle) -> bool {
 }
 
 /// Represents an opened file
+///
+/// Reads and writes take place at the position of the file, which is kept by the kernel
+/// and moved past the bytes transferred. Files opened separately have their own position.
 pub struct File {
     handle: Handle,
-    position: u64,
     path: String,
 }
 
 impl File {
-    #[expect(clippy::missing_panics_doc, reason = "Never panics")]
+    #[inline]
     /// Open a file
     ///
     /// # Errors
     ///
     /// Returns an error if the file cannot be opened
     pub fn open(path: &str) -> FileResult<Self> {
-        let handle = crate::sys::sc_open(path.as_ptr(), path.len().try_into().unwrap());
+        Self::open_with_flags(path, 0)
+    }
+
+    #[inline]
+    /// Open a file in append mode
+    ///
+    /// Every write goes to the end of the file, whatever the position of the file,
+    /// and then moves the position past the bytes written. The end is found atomically,
+    /// so that files appending concurrently never overwrite each other's data.
+    /// Reads and seeks are not affected.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened
+    pub fn open_append(path: &str) -> FileResult<Self> {
+        Self::open_with_flags(path, OPEN_APPEND)
+    }
+
+    fn open_with_flags(path: &str, flags: u64) -> FileResult<Self> {
+        let handle = crate::sys::sc_open(path.as_ptr(), path.len().try_into().unwrap(), flags);
         if is_valid_handle(handle) {
             Ok(Self {
                 handle,
-                position: 0,
                 path: String::from(path),
             })
         } else {
@@ -46,6 +78,13 @@ impl File {
         &self.path
     }
 
+    #[must_use]
+    #[inline]
+    /// The raw handle of the file, as used by `dup2`
+    pub const fn raw_handle(&self) -> i64 {
+        self.handle
+    }
+
     #[inline]
     /// Create a file
     ///
@@ -56,6 +95,169 @@ impl File {
         todo!("Implement when syscalls support file creation")
     }
 
+    /// Map `len` bytes of the file, starting at `offset`, into memory, read-only
+    ///
+    /// `offset` must be a multiple of `MMAP_FILE_OFFSET_ALIGN` (the page size).
+    /// The file is read when mapping, so later writes to the file are not visible.
+    /// Bytes past the end of the file read as zero.
+    ///
+    /// The mapping stays valid until the process exits, even after the file is closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `offset` is misaligned or at or past the end of the file,
+    /// or if the memory cannot be mapped.
+    pub fn mmap(&self, len: u64, offset: u64) -> SyscallResult<*const u8> {
+        let ptr = crate::sys::sc_mmap_file(self.handle, len, offset);
+        if ptr.is_null() {
+            Err(SyscallError::new(-1))
+        } else {
+            Ok(ptr)
+        }
+    }
+
+    /// Read into several buffers with a single syscall, filling them one after the other
+    ///
+    /// Returns the total number of bytes read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are more than `MAX_IOVECS` buffers,
+    /// if they total more than `MAX_VECTORED_BYTES` bytes, or if the read fails.
+    pub fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> IoResult<usize> {
+        let n = crate::sys::sc_readv(
+            self.handle,
+            IoSliceMut::as_iovecs(bufs).as_ptr(),
+            bufs.len() as u64,
+            FILE_OFFSET_CURRENT,
+        );
+        transferred(n)
+    }
+
+    /// Write several buffers with a single syscall, one after the other
+    ///
+    /// Returns the total number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are more than `MAX_IOVECS` buffers,
+    /// if they total more than `MAX_VECTORED_BYTES` bytes, or if the write fails.
+    pub fn write_vectored(&mut self, bufs: &[IoSlice]) -> IoResult<usize> {
+        let n = crate::sys::sc_writev(
+            self.handle,
+            IoSlice::as_iovecs(bufs).as_ptr(),
+            bufs.len() as u64,
+            FILE_OFFSET_CURRENT,
+        );
+        transferred(n)
+    }
+
+    #[expect(clippy::missing_panics_doc, reason = "Never panics")]
+    /// Read from the file, waiting at most `timeout` for data to arrive
+    ///
+    /// Only reads that would wait are affected, so regular files are read as with `read`.
+    /// `PollTimeout::Immediate` makes the read non-blocking.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TimedOut` error if no data arrived in time, or an error if the read fails.
+    pub fn read_timeout(&mut self, buf: &mut [u8], timeout: PollTimeout) -> IoResult<usize> {
+        let res = crate::sys::sc_read_timeout(
+            self.handle,
+            buf.as_mut_ptr(),
+            buf.len().try_into().unwrap(),
+            FILE_OFFSET_CURRENT,
+            timeout.to_raw(),
+        );
+        read_result(res)
+    }
+
+    #[inline]
+    /// Flush the data and metadata of the file to its device
+    ///
+    /// The size of the file is persisted as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the device write fails
+    pub fn sync(&self) -> FileResult<()> {
+        let code = crate::sys::sc_fsync(self.handle);
+        if code == SyscallExitCode::Success {
+            Ok(())
+        } else {
+            Err(FileError::new(FileErrorKind::Other))
+        }
+    }
+
+    #[inline]
+    /// Take an exclusive lock on the file, waiting for other handles to release theirs
+    ///
+    /// Locks are advisory: they exclude other locks, but not reads or writes.
+    /// A file holds at most one lock, which is converted if the file is locked again,
+    /// and released when the file is closed or the process exits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock cannot be taken
+    pub fn lock_exclusive(&self) -> FileResult<()> {
+        self.flock(FileLockOperation::Exclusive, 0).map(drop)
+    }
+
+    #[inline]
+    /// Take a shared lock on the file, waiting for an exclusive lock to be released
+    ///
+    /// See `lock_exclusive`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock cannot be taken
+    pub fn lock_shared(&self) -> FileResult<()> {
+        self.flock(FileLockOperation::Shared, 0).map(drop)
+    }
+
+    #[inline]
+    /// Try to take an exclusive lock on the file, without waiting
+    ///
+    /// Returns `false` if another handle holds a lock on it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock cannot be taken
+    pub fn try_lock_exclusive(&self) -> FileResult<bool> {
+        self.flock(FileLockOperation::Exclusive, FLOCK_NONBLOCKING)
+    }
+
+    #[inline]
+    /// Try to take a shared lock on the file, without waiting
+    ///
+    /// Returns `false` if another handle holds an exclusive lock on it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock cannot be taken
+    pub fn try_lock_shared(&self) -> FileResult<bool> {
+        self.flock(FileLockOperation::Shared, FLOCK_NONBLOCKING)
+    }
+
+    #[inline]
+    /// Release the lock of the file, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file handle is invalid
+    pub fn unlock(&self) -> FileResult<()> {
+        self.flock(FileLockOperation::Unlock, 0).map(drop)
+    }
+
+    /// Returns whether the lock operation succeeded, or would have to wait.
+    fn flock(&self, operation: FileLockOperation, flags: u64) -> FileResult<bool> {
+        match crate::sys::sc_flock(self.handle, operation, flags) {
+            0 => Ok(true),
+            FLOCK_WOULD_BLOCK => Ok(false),
+            _ => Err(FileError::new(FileErrorKind::Other)),
+        }
+    }
+
     #[inline]
     /// Close the file
     ///
@@ -72,20 +274,69 @@ impl File {
     }
 }
 
+/// Get information about the file at `path`
+///
+/// # Errors
+///
+/// Returns an error if the file does not exist
+pub fn metadata(path: &str) -> FileResult<FileInfo> {
+    let mut info = FileInfo::new(0, FileKind::File);
+    if crate::sys::sc_metadata(path, &mut info) == SyscallExitCode::Success {
+        Ok(info)
+    } else {
+        Err(FileError::new(FileErrorKind::NotFound))
+    }
+}
+
+/// List the names of the entries of the directory at `path`
+///
+/// # Errors
+///
+/// Returns an error if the directory does not exist
+pub fn read_dir(path: &str) -> FileResult<Vec<String>> {
+    const INITIAL_CAPACITY: usize = 256;
+
+    let mut buffer = vec![0; INITIAL_CAPACITY];
+    let len = loop {
+        let len = usize::try_from(crate::sys::sc_read_dir(path, &mut buffer))
+            .map_err(|_| FileError::new(FileErrorKind::NotFound))?;
+        if len <= buffer.len() {
+            break len;
+        }
+        // The entries did not fit, retry with the right size.
+        buffer.resize(len, 0);
+    };
+
+    Ok(buffer[..len]
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+/// Converts the result of a read or write syscall to the number of bytes transferred.
+fn transferred(res: i64) -> IoResult<usize> {
+    usize::try_from(res).map_err(|_| IoError::new(IoErrorKind::Other))
+}
+
+/// Converts the result of a `ReadTimeout` syscall to the number of bytes read.
+pub(super) fn read_result(res: i64) -> IoResult<usize> {
+    if res == READ_TIMED_OUT {
+        Err(IoError::new(IoErrorKind::TimedOut))
+    } else {
+        transferred(res)
+    }
+}
+
 impl Read for File {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
         let n = crate::sys::sc_read(
             self.handle,
             buf.as_mut_ptr(),
             buf.len().try_into().unwrap(),
-            self.position,
+            FILE_OFFSET_CURRENT,
         );
-        if let Ok(n) = usize::try_from(n) {
-            self.position += u64::try_from(n).unwrap();
-            Ok(n)
-        } else {
-            Err(IoError::new(IoErrorKind::Other))
-        }
+        transferred(n)
     }
 }
 
@@ -95,14 +346,9 @@ impl Write for File {
             self.handle,
             buf.as_ptr(),
             buf.len().try_into().unwrap(),
-            self.position,
+            FILE_OFFSET_CURRENT,
         );
-        if let Ok(n) = usize::try_from(n) {
-            self.position += u64::try_from(n).unwrap();
-            Ok(n)
-        } else {
-            Err(IoError::new(IoErrorKind::Other))
-        }
+        transferred(n)
     }
 
     fn flush(&mut self) -> IoResult<()> {
@@ -112,20 +358,22 @@ impl Write for File {
 }
 
 impl Seek for File {
+    /// Seeking past the end of the file is allowed: reads there return 0 bytes,
+    /// and writes extend the file.
+    ///
+    /// Seeking before the start of the file fails with `InvalidData`.
     fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
-        let new_pos = match pos {
-            SeekFrom::Start(n) => n,
-            SeekFrom::Current(n) => if n >= 0 {
-                self.position.checked_add(n.cast_unsigned())
-            } else {
-                self.position.checked_sub((-n).cast_unsigned())
-            }
-            .ok_or(IoError::new(IoErrorKind::InvalidData))?,
-            SeekFrom::End(_) => return Err(IoError::new(IoErrorKind::Other)), // TODO: Implement when file size syscall is available
+        let (whence, offset) = match pos {
+            SeekFrom::Start(n) => (
+                SeekWhence::Start,
+                i64::try_from(n).map_err(|_| IoError::new(IoErrorKind::InvalidData))?,
+            ),
+            SeekFrom::Current(n) => (SeekWhence::Current, n),
+            SeekFrom::End(n) => (SeekWhence::End, n),
         };
 
-        self.position = new_pos;
-        Ok(new_pos)
+        u64::try_from(crate::sys::sc_seek(self.handle, whence, offset))
+            .map_err(|_| IoError::new(IoErrorKind::InvalidData))
     }
 }
 
@@ -1,7 +1,30 @@
 use super::{File, Read};
-use crate::error::{FileResult, IoResult};
-pub use beskar_core::drivers::keyboard::{KeyCode, KeyEvent, KeyModifiers, KeyState};
-use core::mem::size_of;
+use crate::error::{FileResult, IoResult, SyscallError, SyscallResult};
+use beskar_core::drivers::keyboard::layout::ActiveLayout;
+pub use beskar_core::drivers::keyboard::{
+    KeyCode, KeyEvent, KeyModifiers, KeyState,
+    layout::{DeadKey, KeyboardLayout, Layout, Translator},
+};
+use core::{
+    mem::size_of,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Layout used to translate key events of the process.
+static LAYOUT: ActiveLayout = ActiveLayout::new(Layout::UsQwerty);
+
+#[must_use]
+#[inline]
+/// Returns the active keyboard layout.
+pub fn layout() -> Layout {
+    LAYOUT.get()
+}
+
+#[inline]
+/// Sets the active keyboard layout.
+pub fn set_layout(layout: Layout) {
+    LAYOUT.set(layout);
+}
 
 #[repr(align(8))]
 struct KeyboardEventBuffer([u8; size_of::<u64>()]);
@@ -52,6 +75,77 @@ pub fn poll_keyboard() -> Option<KeyEvent> {
     reader.next_event().ok()?
 }
 
+/// Set when the kernel reported dropped events.
+static OVERFLOWED: AtomicBool = AtomicBool::new(false);
+
+/// Drain up to `buf.len()` buffered keyboard events without blocking.
+///
+/// Returns the number of events written at the start of `buf`.
+///
+/// If the kernel queue overflowed, the oldest events were dropped.
+/// This is reported by `take_overflow`.
+pub fn poll_batch(buf: &mut [KeyEvent]) -> usize {
+    const CHUNK_SIZE: usize = 32;
+
+    let mut raw = [0_u64; CHUNK_SIZE];
+    let mut total = 0;
+
+    for chunk in buf.chunks_mut(CHUNK_SIZE) {
+        let res = crate::sys::sc_poll_keyboard_batch(raw.as_mut_ptr(), chunk.len() as u64);
+        if res < 0 {
+            break;
+        }
+        let res = res.cast_unsigned();
+
+        if res & beskar_core::syscall::consts::KEYBOARD_BATCH_OVERFLOW != 0 {
+            OVERFLOWED.store(true, Ordering::Relaxed);
+        }
+        let count = usize::try_from(res & !beskar_core::syscall::consts::KEYBOARD_BATCH_OVERFLOW)
+            .unwrap_or(usize::MAX)
+            .min(chunk.len());
+
+        for (dst, &value) in chunk.iter_mut().zip(&raw[..count]) {
+            if let Some(event) = KeyEvent::unpack_option(value) {
+                *dst = event;
+            }
+        }
+        total += count;
+
+        if count < chunk.len() {
+            break;
+        }
+    }
+
+    total
+}
+
+/// Error code of `read_key` when the process lost the keyboard focus while waiting.
+pub const FOCUS_LOST: i32 = -2;
+
+/// Wait for the next keyboard event, parking the thread until one arrives.
+///
+/// Events only reach the process while it has the keyboard focus.
+/// A process without the focus keeps waiting until it gets it.
+///
+/// # Errors
+///
+/// Returns an error with code `FOCUS_LOST` if the process lost the focus while waiting,
+/// or -1 if there is no keyboard.
+pub fn read_key() -> SyscallResult<KeyEvent> {
+    let res = crate::sys::sc_read_key_blocking();
+    if res == beskar_core::syscall::consts::READ_KEY_FOCUS_LOST {
+        return Err(SyscallError::new(FOCUS_LOST));
+    }
+    KeyEvent::unpack_option(res).ok_or_else(|| SyscallError::new(-1))
+}
+
+#[must_use]
+#[inline]
+/// Returns whether keyboard events were dropped since the last call, and clears the flag.
+pub fn take_overflow() -> bool {
+    OVERFLOWED.swap(false, Ordering::Relaxed)
+}
+
 #[inline]
 /// Wait until the next keyboard event occurs.
 ///
@@ -0,0 +1,117 @@
+use super::traits::{Read, Write};
+use crate::error::{IoError, IoErrorKind, IoResult, SyscallError, SyscallResult};
+use beskar_core::syscall::{SyscallExitCode, consts::WRITE_BROKEN_PIPE, poll::PollTimeout};
+
+/// The read end of a pipe
+///
+/// Reads wait until the pipe holds data, and return 0 once every writer is dropped
+/// and the pipe is empty.
+pub struct PipeReader {
+    handle: i64,
+}
+
+/// The write end of a pipe
+///
+/// Writes wait until the pipe has room. Once the reader is dropped, writing raises
+/// `Signal::BrokenPipe`, which terminates the process, unless it is handled or ignored:
+/// writes then fail with `BrokenPipe`.
+pub struct PipeWriter {
+    handle: i64,
+}
+
+/// Create a pipe, a bounded byte channel from a `PipeWriter` to a `PipeReader`
+///
+/// # Errors
+///
+/// Returns an error if the kernel cannot create the pipe.
+pub fn pipe() -> SyscallResult<(PipeReader, PipeWriter)> {
+    let mut handles = [-1; 2];
+    if crate::sys::sc_pipe(&mut handles) == SyscallExitCode::Success {
+        let [read, write] = handles;
+        Ok((PipeReader { handle: read }, PipeWriter { handle: write }))
+    } else {
+        Err(SyscallError::new(-1))
+    }
+}
+
+impl PipeReader {
+    #[must_use]
+    #[inline]
+    /// The raw handle of the read end, as used by `dup2`
+    pub const fn raw_handle(&self) -> i64 {
+        self.handle
+    }
+
+    #[expect(clippy::missing_panics_doc, reason = "Never panics")]
+    /// Read from the pipe, waiting at most `timeout` for data to arrive
+    ///
+    /// `PollTimeout::Immediate` makes the read non-blocking.
+    /// As with `read`, 0 is returned once every writer is dropped and the pipe is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TimedOut` error if no data arrived in time, or an error if the read fails.
+    pub fn read_timeout(&mut self, buf: &mut [u8], timeout: PollTimeout) -> IoResult<usize> {
+        // Pipes ignore the offset
+        let res = crate::sys::sc_read_timeout(
+            self.handle,
+            buf.as_mut_ptr(),
+            buf.len().try_into().unwrap(),
+            0,
+            timeout.to_raw(),
+        );
+        super::file::read_result(res)
+    }
+}
+
+impl PipeWriter {
+    #[must_use]
+    #[inline]
+    /// The raw handle of the write end, as used by `dup2`
+    pub const fn raw_handle(&self) -> i64 {
+        self.handle
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        // Pipes ignore the offset
+        let res = crate::sys::sc_read(
+            self.handle,
+            buf.as_mut_ptr(),
+            buf.len().try_into().unwrap(),
+            0,
+        );
+        usize::try_from(res).map_err(|_| IoError::new(IoErrorKind::Other))
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        // Pipes ignore the offset
+        let res = crate::sys::sc_write(self.handle, buf.as_ptr(), buf.len().try_into().unwrap(), 0);
+        match res {
+            WRITE_BROKEN_PIPE => Err(IoError::new(IoErrorKind::BrokenPipe)),
+            res => usize::try_from(res).map_err(|_| IoError::new(IoErrorKind::Other)),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        // Data is handed to the reader as soon as it is written
+        Ok(())
+    }
+}
+
+impl Drop for PipeReader {
+    #[inline]
+    fn drop(&mut self) {
+        crate::sys::sc_close(self.handle);
+    }
+}
+
+impl Drop for PipeWriter {
+    #[inline]
+    fn drop(&mut self) {
+        crate::sys::sc_close(self.handle);
+    }
+}
@@ -0,0 +1,74 @@
+use super::{PollEvents, PollItem, PollSource, PollTimeout};
+use crate::error::{IoError, IoErrorKind, IoResult, SyscallError, SyscallResult};
+use beskar_core::syscall::SyscallExitCode;
+
+/// A set of sources kept by the kernel, to wait on many sources without passing them every time
+///
+/// Readiness is level-triggered: a source is reported by every wait as long as it is ready.
+/// A source that becomes invalid, such as a closed handle, is reported once with
+/// `PollEvents::INVALID`, then removed from the set.
+///
+/// The kernel keeps the set until the process exits.
+pub struct Poller {
+    id: u64,
+}
+
+impl Poller {
+    /// Create an empty set
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process already has `MAX_POLL_SETS` sets.
+    pub fn new() -> SyscallResult<Self> {
+        let id = crate::sys::sc_poll_create();
+        u64::try_from(id)
+            .map(|id| Self { id })
+            .map_err(|_| SyscallError::new(-1))
+    }
+
+    /// Add a source, or replace its events of interest if it is already in the set
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the set already holds `MAX_POLL_SET_SOURCES` sources.
+    pub fn add(&self, source: PollSource, interest: PollEvents) -> SyscallResult<()> {
+        let item = PollItem::new(source, interest);
+        if crate::sys::sc_poll_add(self.id, &item) == SyscallExitCode::Success {
+            Ok(())
+        } else {
+            Err(SyscallError::new(-1))
+        }
+    }
+
+    /// Remove a source
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source is not in the set.
+    pub fn remove(&self, source: PollSource) -> SyscallResult<()> {
+        let item = PollItem::new(source, PollEvents::NONE);
+        if crate::sys::sc_poll_del(self.id, &item) == SyscallExitCode::Success {
+            Ok(())
+        } else {
+            Err(SyscallError::new(-1))
+        }
+    }
+
+    /// Wait until at least one source is ready, or the timeout elapses
+    ///
+    /// The ready sources are stored at the start of `ready`, along with their events.
+    /// Returns their number, which is 0 if the timeout elapsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kernel rejects the buffer.
+    pub fn wait(&self, ready: &mut [PollItem], timeout: PollTimeout) -> IoResult<usize> {
+        let res = crate::sys::sc_poll_wait(
+            self.id,
+            ready.as_mut_ptr(),
+            ready.len() as u64,
+            timeout.to_raw(),
+        );
+        usize::try_from(res).map_err(|_| IoError::new(IoErrorKind::Other))
+    }
+}
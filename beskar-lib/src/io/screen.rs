@@ -1,17 +1,33 @@
 use crate::{
-    error::IoResult,
-    io::{File, Read, Seek, SeekFrom, Write},
+    error::{IoResult, SyscallError, SyscallResult},
+    io::{File, Seek, SeekFrom, Write},
     mem,
 };
-use beskar_core::video::{Info, Pixel};
-use core::{
-    mem::{MaybeUninit, align_of, size_of},
-    num::NonZeroU64,
-    ops::Range,
+pub use beskar_core::syscall::framebuffer::FbInfo;
+use beskar_core::{
+    syscall::SyscallExitCode,
+    video::{Info, Pixel},
 };
+use core::{mem::align_of, num::NonZeroU64, ops::Range};
+
+/// Get the layout of the framebuffer
+///
+/// # Errors
+///
+/// Returns an error if the syscall fails.
+pub fn framebuffer_info() -> SyscallResult<FbInfo> {
+    let mut info = FbInfo::EMPTY;
+    match crate::sys::sc_framebuffer_info(&mut info) {
+        SyscallExitCode::Success => Ok(info),
+        _ => Err(SyscallError::new(-1)),
+    }
+}
 
 /// A convenient framebuffer wrapper. It maps an internal buffer and provides
 /// simple `flush` semantics to write ranges back to the kernel framebuffer device.
+///
+/// Each app gets its own internal buffer, and several apps can open the framebuffer
+/// at the same time: their flushes are not arbitrated, so the last one wins.
 pub struct FrameBuffer {
     info: Info,
     fb_file: File,
@@ -35,19 +51,9 @@ impl FrameBuffer {
     ///
     /// Returns an error if the framebuffer device cannot be opened or mapped.
     pub fn open() -> crate::error::Result<Self> {
-        let mut fb_file = File::open(Self::FB_FILE)?;
-
-        // Read screen info
-        let info = {
-            let mut uninit = MaybeUninit::<Info>::uninit();
-            let buf = unsafe {
-                core::slice::from_raw_parts_mut(uninit.as_mut_ptr().cast::<u8>(), size_of::<Info>())
-            };
+        let fb_file = File::open(Self::FB_FILE)?;
 
-            fb_file.read_exact(buf)?;
-
-            unsafe { uninit.assume_init() }
-        };
+        let info = framebuffer_info()?.to_info().ok_or(SyscallError::new(-1))?;
 
         debug_assert_eq!(info.bytes_per_pixel(), 4);
 
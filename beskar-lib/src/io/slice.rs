@@ -0,0 +1,98 @@
+//! Buffers of vectored I/O, laid out as the kernel expects them.
+use beskar_core::syscall::iovec::IoVec;
+use core::{marker::PhantomData, ops::Deref, ops::DerefMut};
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+/// A buffer to write from, in `File::write_vectored`.
+pub struct IoSlice<'a> {
+    vec: IoVec,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> IoSlice<'a> {
+    #[must_use]
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            vec: IoVec::new(buf.as_ptr() as u64, buf.len() as u64),
+            _marker: PhantomData,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn as_iovecs(bufs: &[Self]) -> &[IoVec] {
+        // Safety: `IoSlice` is a transparent wrapper around `IoVec`.
+        unsafe { core::slice::from_raw_parts(bufs.as_ptr().cast(), bufs.len()) }
+    }
+}
+
+impl Deref for IoSlice<'_> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        // Safety: The buffer is borrowed for the lifetime of the slice.
+        unsafe {
+            core::slice::from_raw_parts(
+                self.vec.ptr() as *const u8,
+                usize::try_from(self.vec.len()).unwrap(),
+            )
+        }
+    }
+}
+
+#[derive(Debug)]
+#[repr(transparent)]
+/// A buffer to read into, in `File::read_vectored`.
+pub struct IoSliceMut<'a> {
+    vec: IoVec,
+    _marker: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> IoSliceMut<'a> {
+    #[must_use]
+    #[inline]
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            vec: IoVec::new(buf.as_mut_ptr() as u64, buf.len() as u64),
+            _marker: PhantomData,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub(crate) const fn as_iovecs(bufs: &[Self]) -> &[IoVec] {
+        // Safety: `IoSliceMut` is a transparent wrapper around `IoVec`.
+        unsafe { core::slice::from_raw_parts(bufs.as_ptr().cast(), bufs.len()) }
+    }
+}
+
+impl Deref for IoSliceMut<'_> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        // Safety: The buffer is borrowed for the lifetime of the slice.
+        unsafe {
+            core::slice::from_raw_parts(
+                self.vec.ptr() as *const u8,
+                usize::try_from(self.vec.len()).unwrap(),
+            )
+        }
+    }
+}
+
+impl DerefMut for IoSliceMut<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // Safety: The buffer is mutably borrowed for the lifetime of the slice.
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.vec.ptr() as *mut u8,
+                usize::try_from(self.vec.len()).unwrap(),
+            )
+        }
+    }
+}
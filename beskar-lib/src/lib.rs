@@ -1,5 +1,6 @@
 //! Standard library for `BeskarOS`.
 #![no_std]
+#![feature(thread_local)]
 #![forbid(unsafe_op_in_unsafe_fn)]
 #![warn(clippy::pedantic, clippy::nursery)]
 
@@ -10,25 +11,39 @@ use beskar_core::{syscall::SyscallExitCode, time::Duration};
 use hyperdrive::call_once;
 
 mod arch;
+pub mod args;
 pub mod error;
 use error::{SyscallError, SyscallResult};
 pub mod io;
 pub mod mem;
+pub mod net;
+#[cfg(any(test, not(feature = "hosted")))]
+mod panicking;
 pub mod prelude;
+pub mod process;
 pub mod rand;
+pub mod sync;
 mod sys;
+pub mod thread;
 pub mod time;
 
+#[cfg(not(feature = "hosted"))]
 #[panic_handler]
 fn panic(info: &::core::panic::PanicInfo) -> ! {
-    println!("Panic occurred: {}", info);
-    sys::sc_exit(ExitCode::Failure);
+    panicking::report(info);
+    if thread::panic_exits_process() {
+        sys::sc_process_exit(ExitCode::Panicked);
+    } else {
+        sys::sc_exit(ExitCode::Panicked);
+    }
 }
 
 #[cold]
 /// Exit the program with the given exit code.
+///
+/// Every thread of the program exits.
 pub fn exit(code: ExitCode) -> ! {
-    sys::sc_exit(code)
+    sys::sc_process_exit(code)
 }
 
 #[inline]
@@ -69,12 +84,7 @@ macro_rules! entry_point {
 pub fn __init() {
     call_once!({
         // Heap
-        {
-            let heap_size = mem::HEAP_SIZE;
-            let res = mem::mmap(heap_size, None, mem::MemoryProtection::ReadWrite)
-                .expect("Memory mapping failed");
-            unsafe { mem::init_heap(res.as_ptr(), heap_size.try_into().unwrap()) };
-        }
+        mem::init_heap();
 
         // Time
         time::init();
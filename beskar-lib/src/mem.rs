@@ -1,37 +1,167 @@
 use crate::error::{MemoryError, MemoryErrorKind, MemoryResult};
 use beskar_core::arch::paging::{M4KiB, MemSize as _};
+#[cfg(any(test, not(feature = "hosted")))]
+use core::alloc::Layout;
 use core::{num::NonZeroU64, ptr::NonNull};
 use hyperdrive::locks::mcs::MUMcsLock;
 
-static ALLOCATOR: MUMcsLock<heaperion::Heap> = MUMcsLock::uninit();
+static ALLOCATOR: MUMcsLock<GrowableHeap> = MUMcsLock::uninit();
 
+#[cfg(not(feature = "hosted"))]
 struct Heap;
 
+#[cfg(not(feature = "hosted"))]
 #[global_allocator]
 static HEAP: Heap = Heap;
 
+/// Initial size of the heap.
 pub(crate) const HEAP_SIZE: u64 = 20 * 1024 * 1024; // 20 MiB
 beskar_core::static_assert!(HEAP_SIZE.is_multiple_of(M4KiB::SIZE));
 
+/// Maximum number of extents of the heap.
+///
+/// The heap at least doubles every time it grows, so few extents are needed
+/// to reach the maximum heap size.
+const MAX_HEAP_EXTENTS: usize = 8;
+
+#[cfg(not(feature = "hosted"))]
 unsafe impl core::alloc::GlobalAlloc for Heap {
-    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        let Some(Ok(res)) = ALLOCATOR.with_locked_if_init(|heap| heap.allocate(layout)) else {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(Some(res)) = ALLOCATOR.with_locked_if_init(|heap| heap.allocate(layout)) else {
             return core::ptr::null_mut();
         };
         res.as_ptr()
     }
 
-    unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         ALLOCATOR.with_locked_if_init(|heap| unsafe {
-            let _ = heap.deallocate(NonNull::new_unchecked(ptr), layout);
+            heap.deallocate(NonNull::new_unchecked(ptr), layout);
         });
     }
 }
 
+/// A heap made of several extents, each managed by its own allocator.
+///
+/// When no extent can satisfy an allocation, more memory is requested with `grow`,
+/// and becomes a new extent.
+struct GrowableHeap {
+    extents: [Option<heaperion::Heap>; MAX_HEAP_EXTENTS],
+    /// Total size of the extents, in bytes.
+    size: u64,
+    /// Returns a pointer to `bytes` new bytes of page-aligned memory, or `None`.
+    grow: fn(u64) -> Option<NonNull<u8>>,
+}
+
+// Safety: The extents are only accessed through the allocator lock.
+unsafe impl Send for GrowableHeap {}
+
+impl GrowableHeap {
+    #[must_use]
+    #[inline]
+    const fn new(grow: fn(u64) -> Option<NonNull<u8>>) -> Self {
+        Self {
+            extents: [const { None }; MAX_HEAP_EXTENTS],
+            size: 0,
+            grow,
+        }
+    }
+
+    /// Adds a new extent of at least `bytes` bytes.
+    ///
+    /// Returns the index of the new extent.
+    fn grow(&mut self, bytes: u64) -> Option<usize> {
+        let index = self.extents.iter().position(Option::is_none)?;
+        let size = bytes.next_multiple_of(M4KiB::SIZE);
+        let start = (self.grow)(size)?;
+        // Safety: The memory was just handed to the heap.
+        let heap = unsafe { heaperion::Heap::new(start.as_ptr(), size.try_into().ok()?) }.ok()?;
+        self.extents[index] = Some(heap);
+        self.size += size;
+        Some(index)
+    }
+
+    #[cfg(any(test, not(feature = "hosted")))]
+    fn allocate(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        if let Some(ptr) = self
+            .extents
+            .iter_mut()
+            .flatten()
+            .find_map(|heap| heap.allocate(layout).ok())
+        {
+            return Some(ptr);
+        }
+
+        // The buddy allocator gets 3/4 of an extent, and splits it in power-of-two blocks,
+        // so twice the block size is enough. Growing by at least the current size
+        // keeps the number of extents low.
+        let block = layout
+            .size()
+            .max(layout.align())
+            .checked_next_power_of_two()?;
+        let bytes = u64::try_from(block).ok()?.checked_mul(2)?.max(self.size);
+        let index = self.grow(bytes)?;
+        self.extents[index].as_mut()?.allocate(layout).ok()
+    }
+
+    #[cfg(any(test, not(feature = "hosted")))]
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this heap, with the same `layout`.
+    unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        if let Some(heap) = self
+            .extents
+            .iter_mut()
+            .flatten()
+            .find(|heap| heap.contains(ptr))
+        {
+            let _ = unsafe { heap.deallocate(ptr, layout) };
+        }
+    }
+}
+
+/// Requests `bytes` more bytes of heap from the kernel.
+///
+/// This is how the allocator grows the heap when it runs out of memory.
+fn grow_heap(bytes: u64) -> Option<NonNull<u8>> {
+    sbrk(bytes.try_into().ok()?).ok()
+}
+
 #[inline]
 /// Initialize the heap allocator
-pub(crate) unsafe fn init_heap(start: *mut u8, size: usize) {
-    ALLOCATOR.init(unsafe { heaperion::Heap::new(start, size) }.unwrap());
+pub(crate) fn init_heap() {
+    let mut heap = GrowableHeap::new(grow_heap);
+    heap.grow(HEAP_SIZE).expect("Heap initialization failed");
+    ALLOCATOR.init(heap);
+}
+
+/// Set the program break, i.e. the end of the heap, to `addr`.
+///
+/// Note that the global allocator already grows the heap on demand.
+///
+/// # Errors
+///
+/// Returns an error if the heap would exceed `BRK_MAX_HEAP_SIZE`,
+/// or end below its start.
+pub fn brk(addr: NonNull<u8>) -> MemoryResult<()> {
+    let res = crate::sys::sc_brk(addr.as_ptr());
+    if res == addr.as_ptr() {
+        Ok(())
+    } else {
+        Err(MemoryError::new(MemoryErrorKind::OutOfMemory))
+    }
+}
+
+/// Move the program break by `increment` bytes.
+///
+/// Returns the previous break, which is the start of the new memory when growing.
+///
+/// # Errors
+///
+/// Returns an error if the heap would exceed `BRK_MAX_HEAP_SIZE`,
+/// or shrink below its start.
+pub fn sbrk(increment: i64) -> MemoryResult<NonNull<u8>> {
+    let ptr = crate::sys::sc_sbrk(increment);
+    NonNull::new(ptr).ok_or_else(|| MemoryError::new(MemoryErrorKind::OutOfMemory))
 }
 
 /// Map memory into the address space
@@ -76,3 +206,58 @@ pub enum MemoryProtection {
         | beskar_core::syscall::consts::MFLAGS_WRITE
         | beskar_core::syscall::consts::MFLAGS_EXECUTE,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    const INITIAL_SIZE: u64 = 64 * 1024;
+
+    fn leak_pages(bytes: u64) -> Option<NonNull<u8>> {
+        let layout = Layout::from_size_align(bytes.try_into().unwrap(), 4096).unwrap();
+        NonNull::new(unsafe { alloc::alloc::alloc_zeroed(layout) })
+    }
+
+    #[test]
+    fn test_grow_on_exhaustion() {
+        static GROWS: AtomicUsize = AtomicUsize::new(0);
+        fn grow(bytes: u64) -> Option<NonNull<u8>> {
+            GROWS.fetch_add(1, Ordering::Relaxed);
+            leak_pages(bytes)
+        }
+
+        let mut heap = GrowableHeap::new(grow);
+        heap.grow(INITIAL_SIZE).unwrap();
+        assert_eq!(GROWS.load(Ordering::Relaxed), 1);
+
+        let small = Layout::from_size_align(64, 8).unwrap();
+        let first = heap.allocate(small).unwrap();
+        assert_eq!(GROWS.load(Ordering::Relaxed), 1);
+
+        // Larger than the initial heap
+        let large = Layout::from_size_align(usize::try_from(INITIAL_SIZE).unwrap() * 2, 8).unwrap();
+        let ptr = heap.allocate(large).unwrap();
+        assert_eq!(GROWS.load(Ordering::Relaxed), 2);
+        unsafe { ptr.as_ptr().write_bytes(0xAB, large.size()) };
+
+        unsafe {
+            heap.deallocate(ptr, large);
+            heap.deallocate(first, small);
+        }
+        // The freed memory is reused
+        assert!(heap.allocate(large).is_some());
+        assert_eq!(GROWS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_grow_failure() {
+        fn no_grow(_bytes: u64) -> Option<NonNull<u8>> {
+            None
+        }
+
+        let mut heap = GrowableHeap::new(no_grow);
+        let large = Layout::from_size_align(usize::try_from(INITIAL_SIZE).unwrap(), 8).unwrap();
+        assert!(heap.allocate(large).is_none());
+    }
+}
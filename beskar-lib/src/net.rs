@@ -0,0 +1,19 @@
+//! Network diagnostics.
+use crate::error::{SyscallError, SyscallResult};
+pub use beskar_core::syscall::capture::{FrameRecord, RECORD_ALIGN, Records};
+
+/// Takes the frames captured on the network interface into `buffer`.
+///
+/// The capture starts on the first call. Only frames with the given raw `EtherType`
+/// are captured from now on, or every frame if `ethertype` is `None`.
+/// The written bytes can be read with `Records`.
+///
+/// Returns the number of bytes written.
+///
+/// # Errors
+///
+/// Returns an error if the kernel refuses the buffer.
+pub fn capture(buffer: &mut [u8], ethertype: Option<u16>) -> SyscallResult<usize> {
+    let res = crate::sys::sc_packet_capture(buffer, ethertype.unwrap_or(0));
+    usize::try_from(res).map_err(|_| SyscallError::new(-1))
+}
@@ -0,0 +1,176 @@
+//! Panic reporting.
+//!
+//! The report is formatted into a buffer on the stack and written to stdout with
+//! raw syscalls, so that it does not allocate: a panic can occur before the heap
+//! is initialized, or from within the allocator itself.
+#[cfg(not(feature = "hosted"))]
+use core::{cell::Cell, panic::PanicInfo};
+use core::{
+    fmt::{self, Display, Write},
+    panic::Location,
+};
+
+/// Size of the buffer the report is formatted into.
+///
+/// Longer reports are written in several chunks.
+const BUFFER_SIZE: usize = 512;
+
+#[cfg(not(feature = "hosted"))]
+#[thread_local]
+/// Set by the first panic of the thread, so that a panic while reporting does not recurse.
+static PANICKING: Cell<bool> = Cell::new(false);
+
+#[cfg(not(feature = "hosted"))]
+/// Writes the report of the panic to stdout.
+pub fn report(info: &PanicInfo) {
+    const STDOUT_FILE: &str = "/dev/stdout";
+
+    if PANICKING.replace(true) {
+        // The first report panicked, there is nothing left to do
+        return;
+    }
+
+    let handle = crate::sys::sc_open(STDOUT_FILE.as_ptr(), STDOUT_FILE.len() as u64, 0);
+    if handle < 0 {
+        return;
+    }
+
+    // Make sure what the program already printed comes out before the report
+    let _ = crate::sys::sc_fsync(handle);
+
+    let mut offset = 0;
+    let mut writer = ChunkWriter::new(|bytes: &[u8]| {
+        let n = crate::sys::sc_write(handle, bytes.as_ptr(), bytes.len() as u64, offset);
+        if let Ok(n) = u64::try_from(n) {
+            offset += n;
+        }
+    });
+    let _ = write_report(
+        &mut writer,
+        crate::thread::current_id(),
+        info.location(),
+        info.message(),
+    );
+    writer.flush();
+
+    let _ = crate::sys::sc_close(handle);
+}
+
+/// Formats the report of a panic.
+///
+/// The format is:
+///
+/// ```text
+/// thread 1 panicked at src/main.rs:4:5:
+/// message
+/// ```
+fn write_report(
+    w: &mut impl Write,
+    thread_id: u64,
+    location: Option<&Location>,
+    message: impl Display,
+) -> fmt::Result {
+    write!(w, "thread {thread_id} panicked")?;
+    if let Some(location) = location {
+        write!(
+            w,
+            " at {}:{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        )?;
+    }
+    writeln!(w, ":\n{message}")
+}
+
+/// A writer that accumulates bytes in a fixed-size buffer,
+/// and hands them to `sink` when it is full.
+struct ChunkWriter<F: FnMut(&[u8])> {
+    buf: [u8; BUFFER_SIZE],
+    len: usize,
+    sink: F,
+}
+
+impl<F: FnMut(&[u8])> ChunkWriter<F> {
+    #[must_use]
+    #[inline]
+    const fn new(sink: F) -> Self {
+        Self {
+            buf: [0; BUFFER_SIZE],
+            len: 0,
+            sink,
+        }
+    }
+
+    /// Hands the buffered bytes to the sink.
+    fn flush(&mut self) {
+        if self.len > 0 {
+            (self.sink)(&self.buf[..self.len]);
+            self.len = 0;
+        }
+    }
+}
+
+impl<F: FnMut(&[u8])> Write for ChunkWriter<F> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            if self.len == BUFFER_SIZE {
+                self.flush();
+            }
+            let amt = bytes.len().min(BUFFER_SIZE - self.len);
+            self.buf[self.len..self.len + amt].copy_from_slice(&bytes[..amt]);
+            self.len += amt;
+            bytes = &bytes[amt..];
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::String, vec::Vec};
+
+    #[test]
+    fn test_report_format() {
+        let location = Location::caller();
+        let mut out = String::new();
+        write_report(&mut out, 3, Some(location), format_args!("oops {}", 42)).unwrap();
+
+        assert_eq!(
+            out,
+            alloc::format!(
+                "thread 3 panicked at {}:{}:{}:\noops 42\n",
+                location.file(),
+                location.line(),
+                location.column()
+            )
+        );
+        assert!(out.contains("src/panicking.rs"));
+    }
+
+    #[test]
+    fn test_report_without_location() {
+        let mut out = String::new();
+        write_report(&mut out, 1, None, "oops").unwrap();
+        assert_eq!(out, "thread 1 panicked:\noops\n");
+    }
+
+    #[test]
+    fn test_chunk_writer() {
+        let mut chunks = Vec::new();
+        let long = "x".repeat(BUFFER_SIZE + 10);
+        {
+            let mut writer = ChunkWriter::new(|bytes: &[u8]| chunks.push(bytes.to_vec()));
+            writer.write_str(&long).unwrap();
+            writer.write_str("end").unwrap();
+            writer.flush();
+        }
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), BUFFER_SIZE);
+        assert_eq!(chunks[1].len(), 13);
+        assert_eq!(chunks.concat(), [long.as_bytes(), b"end"].concat());
+    }
+}
@@ -0,0 +1,50 @@
+//! Process-related utilities.
+
+pub mod env;
+pub mod signal;
+
+use crate::error::{SyscallError, SyscallResult};
+use alloc::{string::String, vec, vec::Vec};
+pub use beskar_core::syscall::process::{ProcessInfo, ProcessKind, ProcessState};
+
+#[must_use]
+/// Resolves a command name to the path of a program, like `which`.
+///
+/// `path_var` is a list of directories separated by `:`, usually the `PATH` variable.
+/// They are searched in order for a regular file called `name`.
+/// Names containing a `/` are considered to be paths already.
+pub fn resolve_command(name: &str, path_var: &str) -> Option<String> {
+    // Long enough for any directory of `path_var` joined with `name`.
+    let mut buf = vec![0; path_var.len() + 1 + name.len()];
+    beskar_core::process::command::resolve_command(name, path_var, &mut buf, |path| {
+        crate::io::metadata(path).is_ok_and(|info| info.is_file())
+    })
+    .map(String::from)
+}
+
+/// Lists the processes of the system, by increasing PID.
+///
+/// Processes are listed in batches, so the list is not an atomic snapshot:
+/// processes that start or stop while listing may or may not appear.
+///
+/// # Errors
+///
+/// Returns an error if the kernel refuses to list the processes.
+pub fn list() -> SyscallResult<Vec<ProcessInfo>> {
+    const BATCH_SIZE: usize = 16;
+
+    let mut processes = Vec::new();
+    let mut batch = [ProcessInfo::EMPTY; BATCH_SIZE];
+    let mut cursor = 0;
+    loop {
+        let res = crate::sys::sc_process_list(&mut batch, cursor);
+        let written = usize::try_from(res).map_err(|_| SyscallError::new(-1))?;
+        processes.extend_from_slice(&batch[..written]);
+
+        match batch[..written].last() {
+            Some(last) if written == BATCH_SIZE => cursor = last.pid() + 1,
+            _ => break,
+        }
+    }
+    Ok(processes)
+}
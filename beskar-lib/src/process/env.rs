@@ -0,0 +1,76 @@
+//! Environment variables of the current process.
+//!
+//! The environment is inherited from the parent process.
+use crate::error::{SyscallError, SyscallResult};
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+pub use beskar_core::process::env::ENV_MAX_SIZE;
+use beskar_core::syscall::SyscallExitCode;
+
+/// Reads the raw value of a variable, or the whole block if `key` is empty.
+fn get_raw(key: &str) -> Option<Vec<u8>> {
+    const INITIAL_CAPACITY: usize = 64;
+
+    let mut buffer = vec![0; INITIAL_CAPACITY];
+    loop {
+        let len = usize::try_from(crate::sys::sc_get_env(key, &mut buffer)).ok()?;
+        if len <= buffer.len() {
+            buffer.truncate(len);
+            return Some(buffer);
+        }
+        // The value did not fit, retry with the right size.
+        buffer.resize(len, 0);
+    }
+}
+
+#[must_use]
+/// Returns the value of a variable, or `None` if it is unset.
+pub fn get(key: &str) -> Option<String> {
+    if key.is_empty() {
+        return None;
+    }
+    String::from_utf8(get_raw(key)?).ok()
+}
+
+/// Sets a variable for the current process and the processes it spawns.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid (empty, containing `=` or NUL),
+/// or if the environment would exceed `ENV_MAX_SIZE` bytes.
+pub fn set(key: &str, value: &str) -> SyscallResult<()> {
+    if key.is_empty() {
+        return Err(SyscallError::new(-1));
+    }
+    match crate::sys::sc_set_env(key, Some(value)) {
+        SyscallExitCode::Success => Ok(()),
+        _ => Err(SyscallError::new(-1)),
+    }
+}
+
+/// Removes a variable.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid.
+pub fn unset(key: &str) -> SyscallResult<()> {
+    if key.is_empty() {
+        return Err(SyscallError::new(-1));
+    }
+    match crate::sys::sc_set_env(key, None) {
+        SyscallExitCode::Success => Ok(()),
+        _ => Err(SyscallError::new(-1)),
+    }
+}
+
+#[must_use]
+/// Returns all variables, as `(name, value)` pairs.
+pub fn vars() -> Vec<(String, String)> {
+    let block = get_raw("").unwrap_or_default();
+    beskar_core::process::env::parse_block(&block)
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
@@ -0,0 +1,58 @@
+//! Signals, which notify the process of events asynchronously.
+//!
+//! A signal without a handler takes its default action, which terminates the process,
+//! except for `Signal::Suspend` which is discarded.
+//!
+//! Ctrl-C raises `Signal::Interrupt`, and Ctrl-Z `Signal::Suspend`, in the processes
+//! of the focused app, see `beskar_core::drivers::keyboard::focus`.
+//! Handlers run on the stack of a thread of the process, the next time one
+//! of its threads returns from the kernel, e.g. from a read or a sleep.
+//! While a handler runs, its signal is held back until the handler returns.
+//!
+//! Handlers interrupt the thread at an arbitrary point, so they should only
+//! touch atomics, as a Unix signal handler would.
+use crate::error::{SyscallError, SyscallResult};
+use beskar_core::syscall::{
+    SyscallExitCode,
+    consts::{SIG_DEFAULT, SIG_IGNORE},
+};
+
+pub use beskar_core::process::signal::Signal;
+
+/// A signal handler.
+pub type Handler = extern "C" fn(Signal);
+
+fn sigaction(signal: Signal, handler: u64) -> SyscallResult<()> {
+    if crate::sys::sc_sigaction(signal, handler) == SyscallExitCode::Success {
+        Ok(())
+    } else {
+        Err(SyscallError::new(-1))
+    }
+}
+
+/// Runs `handler` when `signal` is raised.
+///
+/// # Errors
+///
+/// Returns an error if the kernel rejects the handler.
+pub fn set_handler(signal: Signal, handler: Handler) -> SyscallResult<()> {
+    sigaction(signal, handler as usize as u64)
+}
+
+/// Discards `signal` when it is raised, and while it is pending.
+///
+/// # Errors
+///
+/// Returns an error if the syscall fails.
+pub fn ignore(signal: Signal) -> SyscallResult<()> {
+    sigaction(signal, SIG_IGNORE)
+}
+
+/// Restores the default action of `signal`.
+///
+/// # Errors
+///
+/// Returns an error if the syscall fails.
+pub fn reset(signal: Signal) -> SyscallResult<()> {
+    sigaction(signal, SIG_DEFAULT)
+}
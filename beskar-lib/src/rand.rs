@@ -1,7 +1,43 @@
 use crate::error::{IoError, IoErrorKind, IoResult};
 use crate::io::{File, Read};
+use beskar_core::syscall::consts::{GETRANDOM_NONBLOCKING, GETRANDOM_WOULD_BLOCK};
 use core::mem::{self, MaybeUninit};
 
+fn get_random(buf: &mut [u8], flags: u64) -> IoResult<()> {
+    match crate::sys::sc_get_random(buf, flags) {
+        GETRANDOM_WOULD_BLOCK => Err(IoError::new(IoErrorKind::WouldBlock)),
+        len if len.try_into() == Ok(buf.len()) => Ok(()),
+        _ => Err(IoError::new(IoErrorKind::Other)),
+    }
+}
+
+/// Fills the buffer with random bytes from the kernel generator, in a single syscall.
+///
+/// Early in boot, this waits until the generator is seeded.
+///
+/// # Errors
+///
+/// Returns an error if the syscall fails.
+pub fn fill(buf: &mut [u8]) -> IoResult<()> {
+    if buf.is_empty() {
+        return Ok(());
+    }
+    get_random(buf, 0)
+}
+
+/// Fills the buffer with random bytes from the kernel generator, without waiting for it to be seeded.
+///
+/// # Errors
+///
+/// Returns a `WouldBlock` error if the generator is not seeded yet,
+/// or an error if the syscall fails.
+pub fn try_fill(buf: &mut [u8]) -> IoResult<()> {
+    if buf.is_empty() {
+        return Ok(());
+    }
+    get_random(buf, GETRANDOM_NONBLOCKING)
+}
+
 /// Fills the buffer with random bytes.
 ///
 /// # Errors
@@ -48,7 +84,7 @@ pub fn rand_seed(buf: &mut [u8]) -> IoResult<()> {
 ///
 /// # Errors
 ///
-/// Returns an error if the random device cannot be read.
+/// Returns an error if the syscall fails, see `fill`.
 ///
 /// # Safety
 ///
@@ -60,9 +96,9 @@ pub unsafe fn rand<T: Sized>() -> IoResult<T> {
         core::slice::from_raw_parts_mut(uninit.as_mut_ptr().cast::<u8>(), mem::size_of::<T>())
     };
 
-    rand_fill(buf)?;
+    fill(buf)?;
 
-    // Safety: buffer just initialized with bytes from the random device.
+    // Safety: buffer just initialized with random bytes.
     // As per the function safety contract, any byte sequence is valid for `T`.
     let val = unsafe { uninit.assume_init() };
     Ok(val)
@@ -0,0 +1,16 @@
+//! Synchronization primitives for the threads of the current process.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use beskar_lib::sync::Once;
+//!
+//! static CONFIG: Once<u64> = Once::uninit();
+//!
+//! let config = CONFIG.get_or_init(|| 42);
+//! ```
+//!
+//! `Once::get_or_init` runs the initializer at most once, even if several threads
+//! call it at the same time: the other threads block until the initializer completes,
+//! and then get a reference to the same value.
+pub use hyperdrive::once::Once;
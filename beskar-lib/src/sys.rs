@@ -1,7 +1,10 @@
 use crate::arch::syscalls;
 use beskar_core::{
-    process::SleepHandle,
-    syscall::{ExitCode, Syscall, SyscallExitCode},
+    process::{SleepHandle, signal::Signal},
+    syscall::{
+        ExitCode, FileInfo, FileLockOperation, SeekWhence, Syscall, SyscallExitCode,
+        framebuffer::FbInfo, iovec::IoVec, poll::PollItem, process::ProcessInfo,
+    },
 };
 
 #[inline]
@@ -11,8 +14,14 @@ pub fn sc_exit(code: ExitCode) -> ! {
 }
 
 #[inline]
-pub fn sc_open(path: *const u8, len: u64) -> i64 {
-    let res = syscalls::syscall_2(Syscall::Open, path as u64, len);
+pub fn sc_process_exit(code: ExitCode) -> ! {
+    syscalls::syscall_1(Syscall::ProcessExit, u64::from(code));
+    unsafe { core::hint::unreachable_unchecked() }
+}
+
+#[inline]
+pub fn sc_open(path: *const u8, len: u64, flags: u64) -> i64 {
+    let res = syscalls::syscall_3(Syscall::Open, path as u64, len, flags);
     res.cast_signed()
 }
 
@@ -34,6 +43,19 @@ pub fn sc_read(handle: i64, buffer: *mut u8, size: u64, offset: u64) -> i64 {
     res.cast_signed()
 }
 
+#[inline]
+pub fn sc_read_timeout(handle: i64, buffer: *mut u8, size: u64, offset: u64, timeout: u64) -> i64 {
+    let res = syscalls::syscall_5(
+        Syscall::ReadTimeout,
+        handle.cast_unsigned(),
+        buffer as u64,
+        size,
+        offset,
+        timeout,
+    );
+    res.cast_signed()
+}
+
 #[inline]
 pub fn sc_write(handle: i64, buffer: *const u8, size: u64, offset: u64) -> i64 {
     let res = syscalls::syscall_4(
@@ -52,6 +74,41 @@ pub fn sc_mmap(size: u64, alignment: u64, flags: u64) -> *mut u8 {
     res as _
 }
 
+#[inline]
+pub fn sc_brk(addr: *mut u8) -> *mut u8 {
+    let res = syscalls::syscall_1(Syscall::Brk, addr as u64);
+    res as _
+}
+
+#[inline]
+pub fn sc_sbrk(increment: i64) -> *mut u8 {
+    let res = syscalls::syscall_1(Syscall::Sbrk, increment.cast_unsigned());
+    res as _
+}
+
+#[inline]
+pub fn sc_thread_create(entry: extern "C" fn(usize) -> !, arg: u64, stack_size: u64) -> i64 {
+    let res = syscalls::syscall_3(
+        Syscall::ThreadCreate,
+        entry as usize as u64,
+        arg,
+        stack_size,
+    );
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_thread_join(tid: u64) -> i64 {
+    let res = syscalls::syscall_1(Syscall::ThreadJoin, tid);
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_thread_detach(tid: u64) -> SyscallExitCode {
+    let res = syscalls::syscall_1(Syscall::ThreadDetach, tid);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
 #[inline]
 pub fn sc_mprotect(ptr: *mut u8, size: u64, flags: u64) -> SyscallExitCode {
     let res = syscalls::syscall_3(Syscall::MemoryProtect, ptr as u64, size, flags);
@@ -69,3 +126,267 @@ pub fn sc_wait_on_event(handle: SleepHandle) -> SyscallExitCode {
     let res = syscalls::syscall_1(Syscall::WaitOnEvent, handle.raw());
     SyscallExitCode::try_from(res).unwrap()
 }
+
+#[inline]
+pub fn sc_poll_keyboard_batch(buffer: *mut u64, capacity: u64) -> i64 {
+    let res = syscalls::syscall_2(Syscall::PollKeyboardBatch, buffer as u64, capacity);
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_poll(items: *mut PollItem, count: u64, timeout: u64) -> i64 {
+    let res = syscalls::syscall_3(Syscall::Poll, items as u64, count, timeout);
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_get_env(key: &str, buffer: &mut [u8]) -> i64 {
+    let res = syscalls::syscall_4(
+        Syscall::GetEnv,
+        key.as_ptr() as u64,
+        key.len() as u64,
+        buffer.as_mut_ptr() as u64,
+        buffer.len() as u64,
+    );
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_set_env(key: &str, value: Option<&str>) -> SyscallExitCode {
+    let (value_ptr, value_len) = value.map_or((0, 0), |v| (v.as_ptr() as u64, v.len() as u64));
+    let res = syscalls::syscall_4(
+        Syscall::SetEnv,
+        key.as_ptr() as u64,
+        key.len() as u64,
+        value_ptr,
+        value_len,
+    );
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_metadata(path: &str, info: &mut FileInfo) -> SyscallExitCode {
+    let res = syscalls::syscall_3(
+        Syscall::Metadata,
+        path.as_ptr() as u64,
+        path.len() as u64,
+        core::ptr::from_mut(info) as u64,
+    );
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[must_use]
+#[inline]
+pub fn sc_read_dir(path: &str, buffer: &mut [u8]) -> i64 {
+    let res = syscalls::syscall_4(
+        Syscall::ReadDir,
+        path.as_ptr() as u64,
+        path.len() as u64,
+        buffer.as_mut_ptr() as u64,
+        buffer.len() as u64,
+    );
+    res.cast_signed()
+}
+
+#[must_use]
+#[inline]
+pub fn sc_process_list(buffer: &mut [ProcessInfo], cursor: u64) -> i64 {
+    let res = syscalls::syscall_3(
+        Syscall::ProcessList,
+        buffer.as_mut_ptr() as u64,
+        buffer.len() as u64,
+        cursor,
+    );
+    res.cast_signed()
+}
+
+#[must_use]
+#[inline]
+pub fn sc_packet_capture(buffer: &mut [u8], ethertype: u16) -> i64 {
+    let res = syscalls::syscall_3(
+        Syscall::PacketCapture,
+        buffer.as_mut_ptr() as u64,
+        buffer.len() as u64,
+        u64::from(ethertype),
+    );
+    res.cast_signed()
+}
+
+#[must_use]
+#[inline]
+pub fn sc_mmap_file(handle: i64, len: u64, offset: u64) -> *const u8 {
+    let res = syscalls::syscall_3(Syscall::MmapFile, handle.cast_unsigned(), len, offset);
+    res as _
+}
+
+#[inline]
+pub fn sc_framebuffer_info(info: &mut FbInfo) -> SyscallExitCode {
+    let res = syscalls::syscall_1(Syscall::FramebufferInfo, core::ptr::from_mut(info) as u64);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_acquire_display() -> SyscallExitCode {
+    let res = syscalls::syscall_0(Syscall::AcquireDisplay);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_release_display() -> SyscallExitCode {
+    let res = syscalls::syscall_0(Syscall::ReleaseDisplay);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[must_use]
+#[inline]
+pub fn sc_display_event() -> u64 {
+    syscalls::syscall_0(Syscall::DisplayEvent)
+}
+
+#[must_use]
+#[inline]
+pub fn sc_read_key_blocking() -> u64 {
+    syscalls::syscall_0(Syscall::ReadKeyBlocking)
+}
+
+#[must_use]
+#[inline]
+pub fn sc_clock_monotonic() -> u64 {
+    syscalls::syscall_0(Syscall::ClockMonotonic)
+}
+
+#[must_use]
+#[inline]
+pub fn sc_map_clock() -> u64 {
+    syscalls::syscall_0(Syscall::MapClock)
+}
+
+#[inline]
+pub fn sc_readv(handle: i64, iovecs: *const IoVec, count: u64, offset: u64) -> i64 {
+    let res = syscalls::syscall_4(
+        Syscall::ReadV,
+        handle.cast_unsigned(),
+        iovecs as u64,
+        count,
+        offset,
+    );
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_writev(handle: i64, iovecs: *const IoVec, count: u64, offset: u64) -> i64 {
+    let res = syscalls::syscall_4(
+        Syscall::WriteV,
+        handle.cast_unsigned(),
+        iovecs as u64,
+        count,
+        offset,
+    );
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_fsync(handle: i64) -> SyscallExitCode {
+    let res = syscalls::syscall_1(Syscall::Fsync, handle.cast_unsigned());
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_flock(handle: i64, operation: FileLockOperation, flags: u64) -> i64 {
+    let res = syscalls::syscall_3(
+        Syscall::Flock,
+        handle.cast_unsigned(),
+        operation.into(),
+        flags,
+    );
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_seek(handle: i64, whence: SeekWhence, offset: i64) -> i64 {
+    let res = syscalls::syscall_3(
+        Syscall::Seek,
+        handle.cast_unsigned(),
+        whence.into(),
+        offset.cast_unsigned(),
+    );
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_pipe(handles: &mut [i64; 2]) -> SyscallExitCode {
+    let res = syscalls::syscall_1(Syscall::Pipe, handles.as_mut_ptr() as u64);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_dup(handle: i64, flags: u64) -> i64 {
+    let res = syscalls::syscall_2(Syscall::Dup, handle.cast_unsigned(), flags);
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_dup2(old: i64, new: i64, flags: u64) -> SyscallExitCode {
+    let res = syscalls::syscall_3(
+        Syscall::Dup2,
+        old.cast_unsigned(),
+        new.cast_unsigned(),
+        flags,
+    );
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[must_use]
+#[inline]
+pub fn sc_poll_create() -> i64 {
+    syscalls::syscall_0(Syscall::PollCreate).cast_signed()
+}
+
+#[inline]
+pub fn sc_poll_add(set: u64, item: &PollItem) -> SyscallExitCode {
+    let res = syscalls::syscall_2(Syscall::PollAdd, set, core::ptr::from_ref(item) as u64);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_poll_wait(set: u64, ready: *mut PollItem, capacity: u64, timeout: u64) -> i64 {
+    let res = syscalls::syscall_4(Syscall::PollWait, set, ready as u64, capacity, timeout);
+    res.cast_signed()
+}
+
+#[inline]
+pub fn sc_poll_del(set: u64, item: &PollItem) -> SyscallExitCode {
+    let res = syscalls::syscall_2(Syscall::PollDel, set, core::ptr::from_ref(item) as u64);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_set_affinity(mask: u64) -> SyscallExitCode {
+    let res = syscalls::syscall_1(Syscall::SetAffinity, mask);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_sigaction(signal: Signal, handler: u64) -> SyscallExitCode {
+    let restorer = syscalls::signal_restorer as *const () as u64;
+    let res = syscalls::syscall_3(Syscall::SigAction, u64::from(signal), handler, restorer);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[inline]
+pub fn sc_set_timer(delay_ms: u64, period_ms: u64) -> SyscallExitCode {
+    let res = syscalls::syscall_2(Syscall::SetTimer, delay_ms, period_ms);
+    SyscallExitCode::try_from(res).unwrap()
+}
+
+#[must_use]
+#[inline]
+pub fn sc_get_random(buffer: &mut [u8], flags: u64) -> i64 {
+    let res = syscalls::syscall_3(
+        Syscall::GetRandom,
+        buffer.as_mut_ptr() as u64,
+        buffer.len() as u64,
+        flags,
+    );
+    res.cast_signed()
+}
@@ -0,0 +1,189 @@
+//! Threads of the current process.
+//!
+//! A thread runs a function on its own stack, with its own copy of the thread-local
+//! variables. When the function returns, the thread exits.
+//!
+//! A panic in a spawned thread only exits that thread, with `ExitCode::Panicked`,
+//! which its joiner observes. A thread whose handle was dropped is never joined, so its
+//! panic is only reported on stdout. To make such panics fatal, use `set_panic_policy`.
+//! A panic in the main thread always exits the process.
+//!
+//! Thread-local variables are `#[thread_local]` statics, which require the
+//! `thread_local` feature. They follow the x86-64 TLS variant II with the local-exec
+//! model, described in `beskar_core::process::tls`: FS points to the thread control
+//! block of the current thread, right after its copy of the TLS block of the binary.
+use crate::error::{SyscallError, SyscallResult};
+use beskar_core::syscall::{ExitCode, SyscallExitCode};
+use core::{
+    cell::Cell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Default maximum size of the stack of a thread.
+///
+/// Stacks grow on demand, so only the pages actually used are backed by memory.
+pub const DEFAULT_STACK_SIZE: u64 = 2 * 1024 * 1024; // 2 MiB
+
+#[derive(Debug, Clone, Copy)]
+/// Configuration of a new thread.
+pub struct Builder {
+    stack_size: u64,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            stack_size: DEFAULT_STACK_SIZE,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Sets the maximum size of the stack of the thread.
+    ///
+    /// It must be between `THREAD_STACK_MIN_SIZE` and `THREAD_STACK_MAX_SIZE`.
+    pub const fn stack_size(mut self, size: u64) -> Self {
+        self.stack_size = size;
+        self
+    }
+
+    /// Spawns a thread running `f`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stack size is invalid, or if the process has too many
+    /// threads that have not been joined.
+    pub fn spawn(self, f: fn()) -> SyscallResult<JoinHandle> {
+        let res = crate::sys::sc_thread_create(thread_entry, f as usize as u64, self.stack_size);
+        u64::try_from(res)
+            .map(|tid| JoinHandle { tid })
+            .map_err(|_| SyscallError::new(-1))
+    }
+}
+
+#[must_use]
+#[inline]
+/// Returns the ID of the current thread.
+pub fn current_id() -> u64 {
+    crate::arch::tls::thread_id()
+}
+
+pub use beskar_core::process::affinity::CpuMask;
+
+/// Restricts the cores the current thread may run on.
+///
+/// Threads start with `CpuMask::ALL`. A thread restricted to some cores stays on one of them,
+/// which keeps its caches warm, e.g. `set_affinity(CpuMask::single(1))`.
+///
+/// # Errors
+///
+/// Returns an error if the mask does not allow any online core.
+pub fn set_affinity(mask: CpuMask) -> SyscallResult<()> {
+    if crate::sys::sc_set_affinity(mask.raw()) == SyscallExitCode::Success {
+        Ok(())
+    } else {
+        Err(SyscallError::new(-1))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// What a panic in a spawned thread does.
+pub enum PanicPolicy {
+    #[default]
+    /// Only the thread exits, with `ExitCode::Panicked`.
+    ExitThread,
+    /// The whole process exits, as when the main thread panics.
+    ExitProcess,
+}
+
+/// Whether a panic in a spawned thread exits the process.
+static PANIC_EXITS_PROCESS: AtomicBool = AtomicBool::new(false);
+
+#[thread_local]
+/// Set by the entry point of spawned threads, so that it is unset in the main thread.
+static SPAWNED: Cell<bool> = Cell::new(false);
+
+#[inline]
+/// Sets what a panic in a spawned thread does, for every thread of the process.
+pub fn set_panic_policy(policy: PanicPolicy) {
+    PANIC_EXITS_PROCESS.store(policy == PanicPolicy::ExitProcess, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "hosted"))]
+#[must_use]
+#[inline]
+/// Returns whether a panic in the current thread exits the process.
+pub(crate) fn panic_exits_process() -> bool {
+    !SPAWNED.get() || PANIC_EXITS_PROCESS.load(Ordering::Relaxed)
+}
+
+/// Spawns a thread running `f`, with the default configuration.
+///
+/// # Errors
+///
+/// Returns an error if the process has too many threads that have not been joined.
+pub fn spawn(f: fn()) -> SyscallResult<JoinHandle> {
+    Builder::new().spawn(f)
+}
+
+#[derive(Debug)]
+/// A spawned thread.
+///
+/// Until it is joined, it counts against the limit of threads of the process,
+/// even after it exits. Dropping the handle detaches the thread instead, which
+/// then keeps running without counting against the limit.
+pub struct JoinHandle {
+    tid: u64,
+}
+
+impl JoinHandle {
+    #[must_use]
+    #[inline]
+    /// Returns the ID of the thread.
+    pub const fn id(&self) -> u64 {
+        self.tid
+    }
+
+    /// Waits for the thread to exit.
+    ///
+    /// Returns the exit code of the thread, which is `ExitCode::Panicked` if it panicked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread cannot be joined.
+    pub fn join(self) -> SyscallResult<ExitCode> {
+        // Joining forgets the thread, which must not be detached afterwards.
+        let handle = core::mem::ManuallyDrop::new(self);
+        let res = crate::sys::sc_thread_join(handle.tid);
+        u64::try_from(res)
+            .ok()
+            .and_then(|code| ExitCode::try_from(code).ok())
+            .ok_or(SyscallError::new(-1))
+    }
+}
+
+impl Drop for JoinHandle {
+    fn drop(&mut self) {
+        let _ = crate::sys::sc_thread_detach(self.tid);
+    }
+}
+
+/// Entry point of the spawned threads, given the function to run.
+///
+/// It is the panic boundary of the thread: from there, a panic exits the thread
+/// instead of the process, see `PanicPolicy`.
+extern "C" fn thread_entry(f: usize) -> ! {
+    SPAWNED.set(true);
+    // Safety: The kernel passes the argument given to `ThreadCreate`, which is a `fn()`.
+    let f = unsafe { core::mem::transmute::<usize, fn()>(f) };
+    f();
+    crate::sys::sc_exit(ExitCode::Success)
+}
@@ -1,36 +1,95 @@
-use beskar_core::time::MILLIS_PER_SEC;
+//! Monotonic time.
+//!
+//! The kernel shares its TSC calibration with every process through a read-only page,
+//! so that the time is read without a syscall on hardware with an invariant TSC.
+//! Otherwise, the time is asked to the kernel.
+//!
+//! Each process also has a timer, which raises `Signal::Alarm` when it expires.
+//! Without a handler, the signal terminates the process, see `crate::process::signal`.
+use crate::error::{SyscallError, SyscallResult};
 pub use beskar_core::time::{Duration, Instant};
-use hyperdrive::once::Once;
+use beskar_core::{
+    syscall::SyscallExitCode,
+    time::{MICROS_PER_MILLI, TscClock},
+};
+use hyperdrive::{locks::seq::SeqLock, once::Once};
 
-static STARTUP_TIME: Once<Instant> = Once::uninit();
+/// Address of the clock shared by the kernel, or 0 if it is not available.
+static CLOCK: Once<u64> = Once::uninit();
 
 #[must_use]
-/// Reads the time in milliseconds since an arbitrary point in the past.
-fn read_time_raw() -> u64 {
-    #[cfg(target_arch = "x86_64")]
-    {
-        static FREQ: Once<u64> = Once::uninit();
-        FREQ.call_once(|| crate::arch::time::get_tsc_frequency().unwrap());
-
-        let freq = *FREQ.get().unwrap();
-        let tsc = crate::arch::time::read_tsc_fenced();
-        tsc * MILLIS_PER_SEC / freq
-    }
-
-    #[cfg(not(any(target_arch = "x86_64")))]
-    {
-        unimplemented!("Time reading not implemented for this architecture");
-    }
+fn shared_clock() -> Option<&'static SeqLock<TscClock>> {
+    let addr = *CLOCK.get_or_init(crate::sys::sc_map_clock);
+    // Safety: The kernel maps the clock read-only for the lifetime of the process.
+    (addr != 0).then(|| unsafe { &*(addr as *const SeqLock<TscClock>) })
 }
 
 /// Initializes the time module.
 pub(crate) fn init() {
-    STARTUP_TIME.call_once(|| Instant::from_millis(read_time_raw()));
+    let _ = shared_clock();
 }
 
 #[must_use]
 #[inline]
 /// Returns the current instant.
 pub fn now() -> Instant {
-    Instant::from_millis(read_time_raw())
+    #[cfg(target_arch = "x86_64")]
+    if let Some(instant) = shared_clock().and_then(|clock| {
+        clock
+            .read()
+            .instant_at(crate::arch::time::read_tsc_fenced())
+    }) {
+        return instant;
+    }
+
+    Instant::from_micros(crate::sys::sc_clock_monotonic())
+}
+
+/// Converts a duration to the milliseconds expected by the kernel, rounding up.
+const fn timer_millis(duration: Duration) -> u64 {
+    duration.total_micros().div_ceil(MICROS_PER_MILLI)
+}
+
+fn set_timer(delay: Duration, period: Duration) -> SyscallResult<()> {
+    let code = crate::sys::sc_set_timer(timer_millis(delay), timer_millis(period));
+    match code {
+        SyscallExitCode::Success => Ok(()),
+        _ => Err(SyscallError::new(-1)),
+    }
+}
+
+#[inline]
+/// Raises `Signal::Alarm` every `period`, starting one period from now.
+///
+/// This replaces any previous setting of the timer of the process.
+/// If the signal is still pending when the timer expires again, the expirations are
+/// coalesced, so a slow handler sees fewer signals rather than a growing backlog.
+///
+/// # Errors
+///
+/// Returns an error if the syscall fails.
+pub fn set_interval(period: Duration) -> SyscallResult<()> {
+    set_timer(period, period)
+}
+
+#[inline]
+/// Raises `Signal::Alarm` once, after `delay`.
+///
+/// This replaces any previous setting of the timer of the process.
+///
+/// # Errors
+///
+/// Returns an error if the syscall fails.
+pub fn set_alarm(delay: Duration) -> SyscallResult<()> {
+    set_timer(delay, Duration::ZERO)
+}
+
+#[inline]
+/// Disarms the timer of the process.
+///
+/// # Errors
+///
+/// Returns an error if the syscall fails.
+pub fn cancel_timer() -> SyscallResult<()> {
+    set_timer(Duration::ZERO, Duration::ZERO)
 }
@@ -1,6 +1,9 @@
 #![no_std]
 #![forbid(unsafe_op_in_unsafe_fn)]
 #![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::missing_panics_doc)]
+
+pub mod multiboot2;
 
 use beskar_core::{
     arch::{PhysAddr, VirtAddr},
@@ -11,7 +14,8 @@ use beskar_core::{
 #[macro_export]
 /// This macro defines the entry point of the kernel.
 ///
-/// This will be called by the bootloader.
+/// This will be called by the bootloader, or by the Multiboot2 entry code
+/// (see `multiboot2::entry`) which also needs the kernel to be linked with `kernel/multiboot2.ld`.
 ///
 /// You can pass additional arguments that will be forwarded to your entry point function.
 macro_rules! entry_point {
@@ -20,6 +24,11 @@ macro_rules! entry_point {
         extern "C" fn __kernel_entry(boot_info: &'static mut $crate::BootInfo) -> ! {
             ($path)(boot_info $(, $arg)*)
         }
+
+        #[cfg(all(target_arch = "x86_64", target_os = "none"))]
+        #[used]
+        static __MULTIBOOT2_ENTRY: unsafe extern "C" fn() -> ! =
+            $crate::multiboot2::entry::__multiboot2_start;
     };
 }
 
@@ -0,0 +1,796 @@
+//! Multiboot2 boot information.
+//!
+//! This module parses the information structure handed over by a Multiboot2 loader
+//! (e.g. GRUB) and translates it into `BootInfo`.
+//!
+//! The UEFI bootloader remains the primary way of booting the kernel.
+//! The `entry_point!` macro also makes the kernel bootable by a Multiboot2 loader,
+//! through the entry code of the `entry` module, with the following differences:
+//!
+//! - `cpu_count` is always 1, as there is no equivalent tag: the APs are not started.
+//! - `rsdp_paddr` is only reported if the loader copied the RSDP into an ACPI tag.
+//!   Otherwise, the kernel has to discover it separately.
+//! - `framebuffer` is only available in 32-bit RGB modes, the kernel panics otherwise.
+//! - `ramdisk_info` is the first module, if any.
+//! - `kernel_info` and `recursive_index` describe the paging set up by the entry code,
+//!   which maps the kernel image at `KERNEL_IMAGE_BASE` without KASLR, and without
+//!   write protection of its read-only segments.
+
+use beskar_core::{
+    arch::{
+        Alignment, PhysAddr,
+        paging::{M2MiB, MemSize},
+    },
+    mem::ranges::{self, MemoryRange},
+    video::{Info as FrameBufferInfo, PixelBitmask, PixelFormat},
+};
+
+#[cfg(all(target_arch = "x86_64", target_os = "none"))]
+pub mod entry;
+
+/// Value of `eax` when the kernel is entered by a Multiboot2 loader.
+pub const BOOTLOADER_MAGIC: u32 = 0x36D7_6289;
+
+/// Tag types used by the kernel.
+pub mod tag {
+    pub const END: u32 = 0;
+    pub const CMDLINE: u32 = 1;
+    pub const MODULE: u32 = 3;
+    pub const MEMORY_MAP: u32 = 6;
+    pub const FRAMEBUFFER: u32 = 8;
+    pub const ACPI_OLD: u32 = 14;
+    pub const ACPI_NEW: u32 = 15;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The structure is smaller than its header or than its reported size.
+    Truncated,
+    /// A tag has an invalid size.
+    InvalidTag,
+    /// The structure has no end tag.
+    MissingEnd,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Multiboot2 boot information structure.
+pub struct Info<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Info<'a> {
+    /// Parses the boot information structure from its raw bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the structure or one of its tags is malformed.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        let total_size = read_u32(bytes, 0).ok_or(Error::Truncated)?;
+        let bytes = bytes
+            .get(..usize::try_from(total_size).unwrap())
+            .ok_or(Error::Truncated)?;
+        if bytes.len() < 8 {
+            return Err(Error::Truncated);
+        }
+
+        let info = Self { bytes };
+
+        let mut offset = 8;
+        loop {
+            let (tag, next) = info.tag_at(offset)?;
+            if tag.typ == tag::END {
+                break;
+            }
+            offset = next;
+        }
+
+        Ok(info)
+    }
+
+    /// Parses the boot information structure at the given address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the structure or one of its tags is malformed.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a readable Multiboot2 information structure
+    /// that lives for `'a`.
+    pub unsafe fn from_ptr(ptr: *const u8) -> Result<Self, Error> {
+        // Safety: The structure starts with its total size.
+        let total_size = unsafe { ptr.cast::<u32>().read_unaligned() };
+        // Safety: Function safety guards.
+        let bytes =
+            unsafe { core::slice::from_raw_parts(ptr, usize::try_from(total_size).unwrap()) };
+        Self::new(bytes)
+    }
+
+    /// Reads the tag at `offset`, returning it along with the offset of the next tag.
+    fn tag_at(&self, offset: usize) -> Result<(Tag<'a>, usize), Error> {
+        let typ = read_u32(self.bytes, offset).ok_or(Error::MissingEnd)?;
+        let size =
+            usize::try_from(read_u32(self.bytes, offset + 4).ok_or(Error::MissingEnd)?).unwrap();
+        if size < 8 {
+            return Err(Error::InvalidTag);
+        }
+        let data = self
+            .bytes
+            .get(offset + 8..offset + size)
+            .ok_or(Error::InvalidTag)?;
+
+        // Tags are 8-byte aligned
+        let next = (offset + size).next_multiple_of(8);
+
+        Ok((Tag { typ, data }, next))
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the size of the structure in bytes.
+    pub const fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    #[must_use]
+    /// Returns an iterator over the tags, excluding the end tag.
+    pub const fn tags(&self) -> Tags<'a> {
+        Tags {
+            info: *self,
+            offset: 8,
+        }
+    }
+
+    #[must_use]
+    /// Returns the first tag of the given type.
+    pub fn find(&self, typ: u32) -> Option<Tag<'a>> {
+        self.tags().find(|tag| tag.typ == typ)
+    }
+
+    #[must_use]
+    /// Returns the command line passed to the kernel.
+    pub fn cmdline(&self) -> Option<&'a str> {
+        self.find(tag::CMDLINE).and_then(|tag| c_str(tag.data))
+    }
+
+    #[must_use]
+    /// Returns the memory map.
+    pub fn memory_map(&self) -> Option<MemoryMap<'a>> {
+        let data = self.find(tag::MEMORY_MAP)?.data;
+        let entry_size = usize::try_from(read_u32(data, 0)?).unwrap();
+        if entry_size < MemoryMapEntry::SIZE {
+            return None;
+        }
+        Some(MemoryMap {
+            entries: data.get(8..)?,
+            entry_size,
+        })
+    }
+
+    /// Writes the usable memory regions into `regions`, shrunk to page boundaries.
+    ///
+    /// Returns the filled part of `regions`.
+    /// Regions that do not fit are dropped.
+    pub fn usable_regions<'r>(&self, regions: &'r mut [MemoryRange]) -> &'r mut [MemoryRange] {
+        let mut count = 0;
+
+        let usable = self
+            .memory_map()
+            .into_iter()
+            .flat_map(MemoryMap::entries)
+            .filter(|entry| entry.kind() == MemoryKind::Available)
+            .filter_map(|entry| {
+                let start = Alignment::Align4K.align_up(entry.base());
+                let end = Alignment::Align4K.align_down(entry.base().checked_add(entry.length())?);
+                (start < end).then(|| MemoryRange::new(start, end - 1))
+            });
+
+        for (slot, region) in regions.iter_mut().zip(usable) {
+            *slot = region;
+            count += 1;
+        }
+
+        &mut regions[..count]
+    }
+
+    /// Writes the memory regions the kernel may allocate from into `regions`.
+    ///
+    /// These are the usable regions without the `reserved` ones, such as the kernel image,
+    /// the information structure and the modules, merged when adjacent.
+    /// Returns the filled part of `regions`.
+    /// Regions that do not fit are dropped.
+    pub fn memory_regions<'r>(
+        &self,
+        regions: &'r mut [MemoryRange],
+        reserved: &[MemoryRange],
+    ) -> &'r mut [MemoryRange] {
+        // Every reserved range may split a region in two, which takes one more slot
+        let available = regions.len().saturating_sub(reserved.len());
+        let mut len = self.usable_regions(&mut regions[..available]).len();
+
+        for range in reserved {
+            let range = MemoryRange::new(
+                Alignment::Align4K.align_down(range.start()),
+                Alignment::Align4K.align_up(range.end() + 1) - 1,
+            );
+            len = ranges::carve_out(regions, len, &range);
+        }
+        let len = ranges::merge_adjacent(&mut regions[..len]);
+
+        &mut regions[..len]
+    }
+
+    #[must_use]
+    /// Returns the framebuffer reported by the loader.
+    pub fn framebuffer(&self) -> Option<Framebuffer> {
+        Framebuffer::parse(self.find(tag::FRAMEBUFFER)?.data)
+    }
+
+    #[must_use]
+    /// Returns the physical address of the copy of the RSDP, if any.
+    ///
+    /// `info_paddr` is the physical address of the information structure.
+    /// The ACPI 2.0 RSDP is preferred over the ACPI 1.0 one.
+    pub fn rsdp_paddr(&self, info_paddr: PhysAddr) -> Option<PhysAddr> {
+        let base = self.bytes.as_ptr() as usize;
+        let tag = self
+            .find(tag::ACPI_NEW)
+            .or_else(|| self.find(tag::ACPI_OLD))?;
+        // The RSDP is at least 20 bytes long
+        if tag.data.len() < 20 {
+            return None;
+        }
+        let offset = tag.data.as_ptr() as usize - base;
+        PhysAddr::try_new(info_paddr.as_u64() + u64::try_from(offset).unwrap())
+    }
+
+    /// Returns an iterator over the loaded modules.
+    pub fn modules(&self) -> impl Iterator<Item = Module<'a>> + 'a {
+        self.tags()
+            .filter(|tag| tag.typ == tag::MODULE)
+            .filter_map(|tag| Module::parse(tag.data))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tags<'a> {
+    info: Info<'a>,
+    offset: usize,
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Tag<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The structure has been validated, so errors cannot happen here.
+        let (tag, next) = self.info.tag_at(self.offset).ok()?;
+        if tag.typ == tag::END {
+            return None;
+        }
+        self.offset = next;
+        Some(tag)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tag<'a> {
+    typ: u32,
+    data: &'a [u8],
+}
+
+impl<'a> Tag<'a> {
+    #[must_use]
+    #[inline]
+    pub const fn typ(&self) -> u32 {
+        self.typ
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the content of the tag, without its header.
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryKind {
+    Available,
+    AcpiReclaimable,
+    AcpiNvs,
+    Defective,
+    Reserved,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryMap<'a> {
+    entries: &'a [u8],
+    entry_size: usize,
+}
+
+impl<'a> MemoryMap<'a> {
+    /// Returns an iterator over the entries of the memory map.
+    pub fn entries(self) -> impl Iterator<Item = MemoryMapEntry> + 'a {
+        self.entries
+            .chunks_exact(self.entry_size)
+            .filter_map(MemoryMapEntry::parse)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryMapEntry {
+    base: u64,
+    length: u64,
+    kind: MemoryKind,
+}
+
+impl MemoryMapEntry {
+    const SIZE: usize = 24;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let kind = match read_u32(data, 16)? {
+            1 => MemoryKind::Available,
+            3 => MemoryKind::AcpiReclaimable,
+            4 => MemoryKind::AcpiNvs,
+            5 => MemoryKind::Defective,
+            _ => MemoryKind::Reserved,
+        };
+        Some(Self {
+            base: read_u64(data, 0)?,
+            length: read_u64(data, 8)?,
+            kind,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn base(&self) -> u64 {
+        self.base
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn length(&self) -> u64 {
+        self.length
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn kind(&self) -> MemoryKind {
+        self.kind
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Framebuffer {
+    paddr: u64,
+    pitch: u32,
+    width: u32,
+    height: u32,
+    bpp: u8,
+    /// Positions of the red, green and blue channels, if the framebuffer uses direct RGB colors.
+    rgb: Option<[(u8, u8); 3]>,
+}
+
+impl Framebuffer {
+    /// Framebuffer type for direct RGB colors.
+    const TYPE_RGB: u8 = 1;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let typ = *data.get(21)?;
+        let rgb = if typ == Self::TYPE_RGB {
+            let fields = data.get(24..30)?;
+            Some([
+                (fields[0], fields[1]),
+                (fields[2], fields[3]),
+                (fields[4], fields[5]),
+            ])
+        } else {
+            None
+        };
+        Some(Self {
+            paddr: read_u64(data, 0)?,
+            pitch: read_u32(data, 8)?,
+            width: read_u32(data, 12)?,
+            height: read_u32(data, 16)?,
+            bpp: *data.get(20)?,
+            rgb,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the physical address of the framebuffer.
+    pub const fn paddr(&self) -> Option<PhysAddr> {
+        PhysAddr::try_new(self.paddr)
+    }
+
+    #[must_use]
+    /// Translates the framebuffer description into the kernel's format.
+    ///
+    /// Returns `None` for text mode and indexed color framebuffers,
+    /// as well as for pixel sizes other than 32 bits.
+    pub fn info(&self) -> Option<FrameBufferInfo> {
+        let [red, green, blue] = self.rgb?;
+        if self.bpp != 32 {
+            return None;
+        }
+        let bytes_per_pixel = self.bpp / 8;
+
+        let pixel_format = match (red.0, green.0, blue.0) {
+            (0, 8, 16) => PixelFormat::Rgb,
+            (16, 8, 0) => PixelFormat::Bgr,
+            _ => {
+                let mask = |(position, size): (u8, u8)| {
+                    1_u32
+                        .checked_shl(u32::from(size))
+                        .map_or(u32::MAX, |m| m - 1)
+                        .checked_shl(u32::from(position))
+                        .unwrap_or(0)
+                };
+                PixelFormat::Bitmask(PixelBitmask {
+                    red: mask(red),
+                    green: mask(green),
+                    blue: mask(blue),
+                })
+            }
+        };
+
+        Some(FrameBufferInfo::new(
+            self.pitch.checked_mul(self.height)?,
+            u16::try_from(self.width).ok()?,
+            u16::try_from(self.height).ok()?,
+            pixel_format,
+            u16::try_from(self.pitch / u32::from(bytes_per_pixel)).ok()?,
+            bytes_per_pixel,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Module<'a> {
+    start: u32,
+    end: u32,
+    cmdline: Option<&'a str>,
+}
+
+impl<'a> Module<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let start = read_u32(data, 0)?;
+        let end = read_u32(data, 4)?;
+        let cmdline = c_str(data.get(8..)?);
+        (start <= end).then_some(Self {
+            start,
+            end,
+            cmdline,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the physical memory range of the module.
+    pub fn range(&self) -> Option<MemoryRange> {
+        (self.end > self.start)
+            .then(|| MemoryRange::new(u64::from(self.start), u64::from(self.end) - 1))
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn cmdline(&self) -> Option<&'a str> {
+        self.cmdline
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Physical memory that the entry code maps with 2 MiB pages.
+pub struct HugeMapping {
+    /// Physical address of the first page.
+    start: u64,
+    /// Number of pages.
+    pages: u64,
+    /// Offset of the memory in the first page.
+    offset: u64,
+}
+
+impl HugeMapping {
+    #[must_use]
+    pub const fn new(range: MemoryRange) -> Self {
+        let start = M2MiB::ALIGNMENT.align_down(range.start());
+        let end = M2MiB::ALIGNMENT.align_up(range.end() + 1);
+        Self {
+            start,
+            pages: (end - start) / M2MiB::SIZE,
+            offset: range.start() - start,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the physical address of the first page.
+    pub const fn start(&self) -> u64 {
+        self.start
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the number of pages.
+    pub const fn pages(&self) -> u64 {
+        self.pages
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the offset of the memory in the first page.
+    pub const fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().unwrap(),
+    ))
+}
+
+/// Reads a NUL-terminated UTF-8 string.
+fn c_str(bytes: &[u8]) -> Option<&str> {
+    let len = bytes.iter().position(|&b| b == 0)?;
+    core::str::from_utf8(&bytes[..len]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    /// Builds a Multiboot2 information structure from `(type, data)` pairs.
+    fn build(tags: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = alloc::vec![0; 8];
+        for &(typ, data) in tags.iter().chain(&[(tag::END, &[][..])]) {
+            bytes.extend_from_slice(&typ.to_le_bytes());
+            bytes.extend_from_slice(&u32::try_from(data.len() + 8).unwrap().to_le_bytes());
+            bytes.extend_from_slice(data);
+            bytes.resize(bytes.len().next_multiple_of(8), 0);
+        }
+        let total_size = u32::try_from(bytes.len()).unwrap();
+        bytes[..4].copy_from_slice(&total_size.to_le_bytes());
+        bytes
+    }
+
+    fn mmap_entry(base: u64, length: u64, kind: u32) -> Vec<u8> {
+        let mut entry = Vec::new();
+        entry.extend_from_slice(&base.to_le_bytes());
+        entry.extend_from_slice(&length.to_le_bytes());
+        entry.extend_from_slice(&kind.to_le_bytes());
+        entry.extend_from_slice(&0_u32.to_le_bytes());
+        entry
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert_eq!(Info::new(&[]).unwrap_err(), Error::Truncated);
+        assert_eq!(
+            Info::new(&64_u32.to_le_bytes()).unwrap_err(),
+            Error::Truncated
+        );
+
+        // No end tag
+        let mut bytes = build(&[]);
+        bytes.truncate(8);
+        bytes[..4].copy_from_slice(&8_u32.to_le_bytes());
+        assert_eq!(Info::new(&bytes).unwrap_err(), Error::MissingEnd);
+
+        // Tag overflowing the structure
+        let mut bytes = build(&[(tag::CMDLINE, b"abc\0")]);
+        bytes[12..16].copy_from_slice(&64_u32.to_le_bytes());
+        assert_eq!(Info::new(&bytes).unwrap_err(), Error::InvalidTag);
+    }
+
+    #[test]
+    fn test_tags() {
+        let module = [
+            0x00, 0x00, 0x10, 0x00, 0x00, 0x20, 0x10, 0x00, b'r', b'd', 0,
+        ];
+        let bytes = build(&[
+            (tag::CMDLINE, b"console=serial\0"),
+            (tag::MODULE, &module),
+            (42, &[1, 2, 3]),
+        ]);
+        let info = Info::new(&bytes).unwrap();
+
+        assert_eq!(info.size(), bytes.len());
+        assert_eq!(info.tags().count(), 3);
+        assert_eq!(info.find(42).unwrap().data(), &[1, 2, 3]);
+        assert_eq!(info.cmdline(), Some("console=serial"));
+
+        let module = info.modules().next().unwrap();
+        assert_eq!(module.range(), Some(MemoryRange::new(0x10_0000, 0x10_1FFF)));
+        assert_eq!(module.cmdline(), Some("rd"));
+    }
+
+    #[test]
+    fn test_memory_map() {
+        let mut mmap = Vec::new();
+        mmap.extend_from_slice(&24_u32.to_le_bytes());
+        mmap.extend_from_slice(&0_u32.to_le_bytes());
+        mmap.extend(mmap_entry(0, 0x9_FC00, 1));
+        mmap.extend(mmap_entry(0x9_FC00, 0x400, 2));
+        mmap.extend(mmap_entry(0x10_0800, 0x7EF_0000, 1));
+        mmap.extend(mmap_entry(0x7FF_0800, 0x1_0000, 3));
+        mmap.extend(mmap_entry(0x8000_0000, 0x800, 1));
+        let bytes = build(&[(tag::MEMORY_MAP, &mmap)]);
+        let info = Info::new(&bytes).unwrap();
+
+        let kinds = info
+            .memory_map()
+            .unwrap()
+            .entries()
+            .map(|entry| entry.kind())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                MemoryKind::Available,
+                MemoryKind::Reserved,
+                MemoryKind::Available,
+                MemoryKind::AcpiReclaimable,
+                MemoryKind::Available,
+            ]
+        );
+
+        let mut regions = [MemoryRange::default(); 8];
+        let usable = info.usable_regions(&mut regions);
+        assert_eq!(
+            usable,
+            [
+                MemoryRange::new(0, 0x9_EFFF),
+                MemoryRange::new(0x10_1000, 0x7FE_FFFF),
+            ]
+        );
+
+        let mut regions = [MemoryRange::default(); 1];
+        assert_eq!(info.usable_regions(&mut regions).len(), 1);
+    }
+
+    #[test]
+    fn test_memory_regions() {
+        let mut mmap = Vec::new();
+        mmap.extend_from_slice(&24_u32.to_le_bytes());
+        mmap.extend_from_slice(&0_u32.to_le_bytes());
+        mmap.extend(mmap_entry(0, 0x9_FC00, 1));
+        mmap.extend(mmap_entry(0x10_0000, 0x3F0_0000, 1));
+        mmap.extend(mmap_entry(0x400_0000, 0x400_0000, 1));
+        let bytes = build(&[(tag::MEMORY_MAP, &mmap)]);
+        let info = Info::new(&bytes).unwrap();
+
+        let reserved = [
+            // Kernel image
+            MemoryRange::new(0x20_0000, 0x3F_FFFF),
+            // Information structure, not page aligned
+            MemoryRange::new(0x1_0010, 0x1_0FFF),
+            // Module across two regions
+            MemoryRange::new(0x3FF_F000, 0x400_0FFF),
+        ];
+        let mut regions = [MemoryRange::default(); 8];
+        let usable = info.memory_regions(&mut regions, &reserved);
+        assert_eq!(
+            usable,
+            [
+                MemoryRange::new(0, 0xFFFF),
+                MemoryRange::new(0x1_1000, 0x9_EFFF),
+                MemoryRange::new(0x10_0000, 0x1F_FFFF),
+                MemoryRange::new(0x40_0000, 0x3FF_EFFF),
+                MemoryRange::new(0x400_1000, 0x7FF_FFFF),
+            ]
+        );
+
+        // Regions are dropped to keep room for the split ones
+        let mut regions = [MemoryRange::default(); 4];
+        let usable = info.memory_regions(&mut regions, &reserved);
+        assert_eq!(
+            usable,
+            [
+                MemoryRange::new(0, 0xFFFF),
+                MemoryRange::new(0x1_1000, 0x9_EFFF),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_huge_mapping() {
+        let mapping = HugeMapping::new(MemoryRange::new(0xFD00_0000, 0xFD2F_FFFF));
+        assert_eq!(mapping.start(), 0xFD00_0000);
+        assert_eq!(mapping.pages(), 2);
+        assert_eq!(mapping.offset(), 0);
+
+        let mapping = HugeMapping::new(MemoryRange::new(0x12_3000, 0x20_0FFF));
+        assert_eq!(mapping.start(), 0);
+        assert_eq!(mapping.pages(), 2);
+        assert_eq!(mapping.offset(), 0x12_3000);
+
+        let mapping = HugeMapping::new(MemoryRange::new(0x40_0000, 0x40_0000));
+        assert_eq!(mapping.start(), 0x40_0000);
+        assert_eq!(mapping.pages(), 1);
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        let framebuffer = |red: (u8, u8), green: (u8, u8), blue: (u8, u8), typ: u8| {
+            let mut data = Vec::new();
+            data.extend_from_slice(&0xFD00_0000_u64.to_le_bytes());
+            data.extend_from_slice(&(1024_u32 * 4 + 64).to_le_bytes());
+            data.extend_from_slice(&1024_u32.to_le_bytes());
+            data.extend_from_slice(&768_u32.to_le_bytes());
+            data.extend_from_slice(&[32, typ, 0, 0]);
+            data.extend_from_slice(&[red.0, red.1, green.0, green.1, blue.0, blue.1]);
+            data
+        };
+
+        let data = framebuffer((16, 8), (8, 8), (0, 8), 1);
+        let bytes = build(&[(tag::FRAMEBUFFER, &data)]);
+        let fb = Info::new(&bytes).unwrap().framebuffer().unwrap();
+        assert_eq!(fb.paddr(), PhysAddr::try_new(0xFD00_0000));
+        let fb_info = fb.info().unwrap();
+        assert_eq!(fb_info.pixel_format(), PixelFormat::Bgr);
+        assert_eq!(fb_info.width(), 1024);
+        assert_eq!(fb_info.height(), 768);
+        assert_eq!(fb_info.stride(), 1024 + 16);
+        assert_eq!(fb_info.bytes_per_pixel(), 4);
+        assert_eq!(fb_info.size(), (1024 * 4 + 64) * 768);
+
+        let data = framebuffer((0, 8), (8, 8), (16, 8), 1);
+        let bytes = build(&[(tag::FRAMEBUFFER, &data)]);
+        let fb_info = Info::new(&bytes).unwrap().framebuffer().unwrap().info();
+        assert_eq!(fb_info.unwrap().pixel_format(), PixelFormat::Rgb);
+
+        let data = framebuffer((11, 5), (5, 6), (0, 5), 1);
+        let bytes = build(&[(tag::FRAMEBUFFER, &data)]);
+        let fb_info = Info::new(&bytes).unwrap().framebuffer().unwrap().info();
+        assert_eq!(
+            fb_info.unwrap().pixel_format(),
+            PixelFormat::Bitmask(PixelBitmask {
+                red: 0xF800,
+                green: 0x07E0,
+                blue: 0x001F,
+            })
+        );
+
+        // EGA text mode
+        let data = framebuffer((0, 0), (0, 0), (0, 0), 2);
+        let bytes = build(&[(tag::FRAMEBUFFER, &data)]);
+        assert!(
+            Info::new(&bytes)
+                .unwrap()
+                .framebuffer()
+                .unwrap()
+                .info()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_rsdp() {
+        let rsdp_v1 = *b"RSD PTR \0\0\0\0\0\0\0\0\0\0\0\0";
+        let rsdp_v2 = [0_u8; 36];
+
+        let bytes = build(&[(tag::ACPI_OLD, &rsdp_v1)]);
+        let info = Info::new(&bytes).unwrap();
+        let info_paddr = PhysAddr::new_truncate(0x1_0000);
+        assert_eq!(info.rsdp_paddr(info_paddr), PhysAddr::try_new(0x1_0010));
+
+        let bytes = build(&[(tag::ACPI_OLD, &rsdp_v1), (tag::ACPI_NEW, &rsdp_v2)]);
+        let info = Info::new(&bytes).unwrap();
+        assert_eq!(info.rsdp_paddr(info_paddr), PhysAddr::try_new(0x1_0030));
+
+        let bytes = build(&[]);
+        assert!(Info::new(&bytes).unwrap().rsdp_paddr(info_paddr).is_none());
+    }
+}
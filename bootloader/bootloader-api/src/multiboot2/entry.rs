@@ -0,0 +1,455 @@
+//! Entry code for Multiboot2 loaders.
+//!
+//! The loader jumps to `__multiboot2_start` in 32-bit protected mode, with the image
+//! loaded at a 2 MiB aligned physical address and its relocations not applied.
+//! The entry code identity-maps the first 4 GiB, maps the image at `KERNEL_IMAGE_BASE`
+//! and switches to long mode. From the higher half, it relocates the image and builds
+//! the `BootInfo`, then calls the kernel's `_start` like the UEFI bootloader does.
+//!
+//! The link address of `__multiboot2_start` is only known to the linker, so the kernel
+//! must be linked with a script defining `__multiboot2_entry_addr` (see `kernel/multiboot2.ld`).
+use super::{BOOTLOADER_MAGIC, HugeMapping, Info, tag};
+use crate::{
+    BootInfo, FRAMEBUFFER_BASE, KERNEL_IMAGE_BASE, KERNEL_PT_RECURSIVE_INDEX, KernelInfo,
+    RAMDISK_BASE, RamdiskInfo,
+};
+use beskar_core::{
+    arch::{
+        PhysAddr, VirtAddr,
+        paging::{M2MiB, MemSize},
+    },
+    mem::ranges::MemoryRange,
+    video::FrameBuffer,
+};
+use core::mem::{MaybeUninit, offset_of};
+
+/// Value of the magic field of the Multiboot2 header.
+const HEADER_MAGIC: u32 = 0xE852_50D6;
+
+/// The image is loaded at a 2 MiB aligned address between these bounds.
+const LOAD_MIN_ADDR: u32 = 0x20_0000;
+const LOAD_MAX_ADDR: u32 = 0xFFFF_FFFF;
+
+/// Size of the stack the kernel starts on, as given by the UEFI bootloader.
+const STACK_SIZE: usize = 64 * 4096;
+
+/// Maximum number of memory regions reported to the kernel.
+const MAX_REGIONS: usize = 256;
+
+const PRESENT: u64 = 1 << 0;
+const WRITABLE: u64 = 1 << 1;
+const HUGE_PAGE: u64 = 1 << 7;
+const NO_EXECUTE: u64 = 1 << 63;
+
+#[repr(C, align(4096))]
+/// Page tables of the kernel until it sets up its own mappings.
+///
+/// The kernel image, the ramdisk and the framebuffer are mapped with 2 MiB pages.
+struct PageTables {
+    pml4: [u64; 512],
+    identity_pdpt: [u64; 512],
+    identity_pd: [[u64; 512]; 4],
+    image_pdpt: [u64; 512],
+    image_pd: [u64; 512],
+    ramdisk_pdpt: [u64; 512],
+    ramdisk_pd: [u64; 512],
+    boot_pdpt: [u64; 512],
+    boot_pd: [u64; 512],
+}
+
+#[repr(C, align(16))]
+struct Stack([u8; STACK_SIZE]);
+
+static mut TABLES: PageTables = unsafe { core::mem::zeroed() };
+static mut STACK: Stack = Stack([0; STACK_SIZE]);
+static mut REGIONS: [MemoryRange; MAX_REGIONS] = [MemoryRange::new(0, 0); MAX_REGIONS];
+static mut BOOT_INFO: MaybeUninit<BootInfo> = MaybeUninit::uninit();
+
+unsafe extern "C" {
+    /// Entry point for Multiboot2 loaders.
+    ///
+    /// `entry_point!` refers to it, so that the entry code is linked into the kernel.
+    pub fn __multiboot2_start() -> !;
+    /// Start of the image, defined by the linker.
+    static __ehdr_start: u8;
+    /// End of the image, defined by the linker.
+    static _end: u8;
+}
+
+core::arch::global_asm!(
+    r#"
+    .pushsection .note.multiboot2, "a", @note
+    // Note sections come first in the image, and the header must be in its first 32 KiB
+    .balign 8
+.Lmb2_header:
+    .long {header_magic}
+    // i386 protected mode
+    .long 0
+    .long .Lmb2_header_end - .Lmb2_header
+    .long 0x100000000 - ({header_magic} + (.Lmb2_header_end - .Lmb2_header))
+
+    // Information request
+    .balign 8
+    .short 1, 0
+    .long 8 + 4 * 6
+    .long {tag_cmdline}, {tag_module}, {tag_memory_map}, {tag_framebuffer}, {tag_acpi_old}, {tag_acpi_new}
+
+    // Entry address
+    .balign 8
+    .short 3, 0
+    .long 12
+    .long __multiboot2_entry_addr
+
+    // Framebuffer, with a 32-bit depth and no preferred resolution
+    .balign 8
+    .short 5, 0
+    .long 20
+    .long 0, 0, 32
+
+    // Relocatable image, loaded as low as possible
+    .balign 8
+    .short 10, 0
+    .long 24
+    .long {load_min_addr}, {load_max_addr}, {huge_page_size}, 1
+
+    .balign 8
+    .short 0, 0
+    .long 8
+.Lmb2_header_end:
+    .popsection
+
+    .pushsection .text.multiboot2, "ax", @progbits
+    .code32
+    // Offsets from `.Lmb2_base`, as memory operands only take one symbol
+    .set .Lmb2_tables, {tables} - .Lmb2_base
+    .set .Lmb2_image_start, __ehdr_start - .Lmb2_base
+    .set .Lmb2_image_end, _end - .Lmb2_base
+    .set .Lmb2_stack_top, {stack} + {stack_size} - .Lmb2_base
+    .set .Lmb2_gdtr_base, .Lmb2_gdt - .Lmb2_base
+    .set .Lmb2_gdt_limit, .Lmb2_gdt_end - .Lmb2_gdt - 1
+    .set .Lmb2_long_mode_offset, .Lmb2_long_mode - .Lmb2_base
+
+    .global __multiboot2_start
+    .hidden __multiboot2_start
+__multiboot2_start:
+    cli
+    cld
+    // There is no stack yet, so the return address of `call` is pushed onto
+    // the reserved field of the information structure.
+    lea esp, [ebx + 8]
+    call .Lmb2_base
+.Lmb2_base:
+    pop ebp
+    cmp eax, {bootloader_magic}
+    jne .Lmb2_halt
+    mov esi, ebx
+
+    // Paging is disabled, so this is the physical address of the page tables
+    lea edx, [ebp + .Lmb2_tables]
+
+    // Identity-map the first 4 GiB, for the code to keep running once paging is enabled
+    lea eax, [edx + {identity_pd} + {table_flags}]
+    xor ecx, ecx
+.Lmb2_identity_pdpt:
+    mov [edx + {identity_pdpt} + ecx * 8], eax
+    add eax, 4096
+    inc ecx
+    cmp ecx, 4
+    jne .Lmb2_identity_pdpt
+
+    mov eax, {huge_flags}
+    xor ecx, ecx
+.Lmb2_identity_pd:
+    mov [edx + {identity_pd} + ecx * 8], eax
+    add eax, {huge_page_size}
+    inc ecx
+    cmp ecx, 4 * 512
+    jne .Lmb2_identity_pd
+
+    // Map the image at `KERNEL_IMAGE_BASE`
+    lea eax, [ebp + .Lmb2_image_start]
+    lea ecx, [ebp + .Lmb2_image_end]
+    sub ecx, eax
+    add ecx, {huge_page_size} - 1
+    shr ecx, 21
+    or eax, {huge_flags}
+    lea edi, [edx + {image_pd}]
+.Lmb2_image_pd:
+    mov [edi], eax
+    add eax, {huge_page_size}
+    add edi, 8
+    dec ecx
+    jnz .Lmb2_image_pd
+
+    lea eax, [edx + {identity_pdpt} + {table_flags}]
+    mov [edx + {pml4}], eax
+    lea eax, [edx + {pml4} + {table_flags}]
+    mov [edx + {pml4} + {recursive_index} * 8], eax
+    lea eax, [edx + {image_pdpt} + {table_flags}]
+    mov [edx + {pml4} + {image_index} * 8], eax
+    lea eax, [edx + {image_pd} + {table_flags}]
+    mov [edx + {image_pdpt}], eax
+
+    // Enable PAE, then long mode and no-execute pages, then paging and write protection
+    mov eax, cr4
+    or eax, 1 << 5
+    mov cr4, eax
+    lea eax, [edx + {pml4}]
+    mov cr3, eax
+    mov ecx, 0xC0000080
+    rdmsr
+    or eax, (1 << 8) | (1 << 11)
+    wrmsr
+    mov eax, cr0
+    or eax, 0x80010000
+    mov cr0, eax
+
+    lea esp, [ebp + .Lmb2_stack_top]
+    lea eax, [ebp + .Lmb2_gdtr_base]
+    sub esp, 8
+    mov word ptr [esp], offset .Lmb2_gdt_limit
+    mov [esp + 2], eax
+    lgdt [esp]
+    push 0x08
+    lea eax, [ebp + .Lmb2_long_mode_offset]
+    push eax
+    retf
+
+.Lmb2_halt:
+    hlt
+    jmp .Lmb2_halt
+
+    .code64
+.Lmb2_long_mode:
+    xor eax, eax
+    mov ds, ax
+    mov es, ax
+    mov ss, ax
+    mov fs, ax
+    mov gs, ax
+
+    // Jump to the higher half
+    lea rdi, [rip + __ehdr_start]
+    lea rax, [rip + .Lmb2_higher_half]
+    sub rax, rdi
+    movabs rcx, {image_base}
+    add rax, rcx
+    jmp rax
+
+.Lmb2_higher_half:
+    mov r12, rdi
+    mov r13d, esi
+    lea rsp, [rip + {stack} + {stack_size}]
+    xor ebp, ebp
+
+    lea rdi, [rip + __ehdr_start]
+    lea rsi, [rip + _DYNAMIC]
+    call {relocate}
+
+    mov rdi, r13
+    mov rsi, r12
+    call {boot_info}
+
+    mov rdi, rax
+    call _start
+    ud2
+
+    .balign 8
+.Lmb2_gdt:
+    .quad 0
+    // 64-bit code segment
+    .quad 0x00AF9A000000FFFF
+    // Data segment
+    .quad 0x00CF92000000FFFF
+.Lmb2_gdt_end:
+    .popsection
+    "#,
+    header_magic = const HEADER_MAGIC,
+    bootloader_magic = const BOOTLOADER_MAGIC,
+    tag_cmdline = const tag::CMDLINE,
+    tag_module = const tag::MODULE,
+    tag_memory_map = const tag::MEMORY_MAP,
+    tag_framebuffer = const tag::FRAMEBUFFER,
+    tag_acpi_old = const tag::ACPI_OLD,
+    tag_acpi_new = const tag::ACPI_NEW,
+    load_min_addr = const LOAD_MIN_ADDR,
+    load_max_addr = const LOAD_MAX_ADDR,
+    huge_page_size = const M2MiB::SIZE,
+    table_flags = const PRESENT | WRITABLE,
+    huge_flags = const PRESENT | WRITABLE | HUGE_PAGE,
+    tables = sym TABLES,
+    pml4 = const offset_of!(PageTables, pml4),
+    identity_pdpt = const offset_of!(PageTables, identity_pdpt),
+    identity_pd = const offset_of!(PageTables, identity_pd),
+    image_pdpt = const offset_of!(PageTables, image_pdpt),
+    image_pd = const offset_of!(PageTables, image_pd),
+    recursive_index = const KERNEL_PT_RECURSIVE_INDEX,
+    image_index = const (KERNEL_IMAGE_BASE.as_u64() >> 39) & 0x1FF,
+    image_base = const KERNEL_IMAGE_BASE.as_u64(),
+    stack = sym STACK,
+    stack_size = const STACK_SIZE,
+    relocate = sym relocate,
+    boot_info = sym boot_info,
+);
+
+/// Applies the relative relocations of the image, mapped at `base`.
+///
+/// # Safety
+///
+/// The relocations are not applied yet, so this function must not use any pointer
+/// stored in the image, which rules out panics.
+const unsafe extern "C" fn relocate(base: u64, dynamic: *const [u64; 2]) {
+    const DT_NULL: u64 = 0;
+    const DT_RELA: u64 = 7;
+    const DT_RELASZ: u64 = 8;
+    const DT_RELAENT: u64 = 9;
+    const R_X86_64_RELATIVE: u64 = 8;
+
+    let (mut rela, mut rela_size, mut rela_entry) = (0_u64, 0_u64, 24_u64);
+
+    let mut entry = dynamic;
+    loop {
+        // Safety: The dynamic section ends with a `DT_NULL` entry.
+        let [tag, value] = unsafe { entry.read() };
+        match tag {
+            DT_NULL => break,
+            DT_RELA => rela = value,
+            DT_RELASZ => rela_size = value,
+            DT_RELAENT => rela_entry = value,
+            _ => {}
+        }
+        entry = entry.wrapping_add(1);
+    }
+
+    let mut offset = 0;
+    while offset < rela_size {
+        let rela_addr = base.wrapping_add(rela).wrapping_add(offset);
+        // Safety: The entry is in the relocation table of the image.
+        let [r_offset, r_info, r_addend] = unsafe { (rela_addr as *const [u64; 3]).read() };
+        if r_info & 0xFFFF_FFFF == R_X86_64_RELATIVE {
+            let target = base.wrapping_add(r_offset) as *mut u64;
+            // Safety: Relocations target the image, which is mapped writable.
+            unsafe { target.write(base.wrapping_add(r_addend)) };
+        }
+        offset = offset.wrapping_add(rela_entry);
+    }
+}
+
+/// Builds the `BootInfo` from the information structure at `info_paddr`,
+/// for the image loaded at `load_paddr`.
+///
+/// It maps the framebuffer and the ramdisk, then removes the identity mapping.
+extern "C" fn boot_info(info_paddr: u64, load_paddr: u64) -> &'static mut BootInfo {
+    // Safety: The loader hands over a valid structure, which is identity-mapped.
+    let info = unsafe { Info::from_ptr(info_paddr as *const u8) }
+        .expect("Invalid Multiboot2 information structure");
+
+    let image_vaddr = (&raw const __ehdr_start) as u64;
+    let image_size = (&raw const _end) as u64 - image_vaddr;
+    // The entry code only maps the image, whose physical address follows from its virtual one
+    let paddr = |table: *const [u64; 512]| load_paddr + (table as u64 - image_vaddr);
+
+    // Safety: This function runs once, before the kernel.
+    let tables = unsafe { &mut *core::ptr::addr_of_mut!(TABLES) };
+
+    let ramdisk_pdpt = paddr(&raw const tables.ramdisk_pdpt);
+    let ramdisk_pd = paddr(&raw const tables.ramdisk_pd);
+    tables.pml4[usize::from(RAMDISK_BASE.p4_index())] = ramdisk_pdpt | PRESENT | WRITABLE;
+    tables.ramdisk_pdpt[0] = ramdisk_pd | PRESENT | WRITABLE;
+
+    let boot_pdpt = paddr(&raw const tables.boot_pdpt);
+    let boot_pd = paddr(&raw const tables.boot_pd);
+    tables.pml4[usize::from(FRAMEBUFFER_BASE.p4_index())] = boot_pdpt | PRESENT | WRITABLE;
+    tables.boot_pdpt[0] = boot_pd | PRESENT | WRITABLE;
+
+    let framebuffer = {
+        let framebuffer = info.framebuffer().expect("No framebuffer");
+        let fb_info = framebuffer
+            .info()
+            .expect("Only 32-bit RGB framebuffers are supported");
+        let fb_paddr = framebuffer
+            .paddr()
+            .expect("Invalid framebuffer address")
+            .as_u64();
+        let range = MemoryRange::new(fb_paddr, fb_paddr + u64::from(fb_info.size()) - 1);
+        let vaddr = map(
+            &mut tables.boot_pd,
+            FRAMEBUFFER_BASE,
+            HugeMapping::new(range),
+        );
+        // Safety: The framebuffer is mapped at `vaddr`.
+        unsafe { FrameBuffer::new(vaddr, fb_info) }
+    };
+
+    let module = info.modules().next().and_then(|module| module.range());
+    let ramdisk_info = module.map(|range| {
+        let vaddr = map(
+            &mut tables.ramdisk_pd,
+            RAMDISK_BASE,
+            HugeMapping::new(range),
+        );
+        RamdiskInfo::new(vaddr, range.size())
+    });
+
+    let memory_regions = {
+        let kernel = MemoryRange::new(load_paddr, load_paddr + image_size - 1);
+        // The ACPI tags hold the RSDP, so the structure is kept
+        let structure = MemoryRange::new(
+            info_paddr,
+            info_paddr + u64::try_from(info.size()).unwrap() - 1,
+        );
+        let reserved = [kernel, structure, module.unwrap_or(kernel)];
+
+        // Safety: This function runs once, before the kernel.
+        let regions = unsafe { &mut *core::ptr::addr_of_mut!(REGIONS) };
+        info.memory_regions(regions, &reserved)
+    };
+
+    let rsdp_paddr = info.rsdp_paddr(PhysAddr::new_truncate(info_paddr));
+
+    // The kernel only maps the lower half on demand
+    tables.pml4[0] = 0;
+    // Safety: The code runs in the higher half, which is still mapped.
+    unsafe {
+        core::arch::asm!(
+            "mov {0}, cr3",
+            "mov cr3, {0}",
+            out(reg) _,
+            options(nostack, preserves_flags)
+        );
+    }
+
+    let boot_info = BootInfo {
+        memory_regions,
+        framebuffer,
+        recursive_index: KERNEL_PT_RECURSIVE_INDEX,
+        rsdp_paddr,
+        kernel_info: KernelInfo::new(
+            PhysAddr::new_truncate(load_paddr),
+            VirtAddr::new_extend(image_vaddr),
+            image_size,
+        ),
+        ramdisk_info,
+        cpu_count: 1,
+    };
+
+    // Safety: This function runs once, before the kernel.
+    unsafe { (*core::ptr::addr_of_mut!(BOOT_INFO)).write(boot_info) }
+}
+
+/// Maps `mapping` at `vaddr`, in the page directory `pd` of its 1 GiB region.
+///
+/// Returns the virtual address of the mapped memory.
+fn map(pd: &mut [u64; 512], vaddr: VirtAddr, mapping: HugeMapping) -> VirtAddr {
+    let first = usize::from(vaddr.p2_index());
+    let pages = usize::try_from(mapping.pages()).unwrap();
+    assert!(first + pages <= pd.len(), "Mapping does not fit in 1 GiB");
+
+    let mut paddr = mapping.start();
+    for entry in &mut pd[first..first + pages] {
+        *entry = paddr | PRESENT | WRITABLE | HUGE_PAGE | NO_EXECUTE;
+        paddr += M2MiB::SIZE;
+    }
+
+    vaddr + mapping.offset()
+}
@@ -1,6 +1,7 @@
 use beskar_core::arch::{VirtAddr, paging::Frame};
 
 pub mod acpi;
+pub mod rand;
 
 pub fn init() {
     // Find the hopefully available XSDP/RSDP
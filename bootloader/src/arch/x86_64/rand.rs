@@ -0,0 +1,36 @@
+//! Boot-time entropy.
+
+/// Maximum number of RDRAND attempts, as recommended by Intel.
+const RETRY_LIMIT: u8 = 10;
+
+#[must_use]
+/// Returns 64 bits of boot-time entropy.
+///
+/// RDRAND is used when the CPU supports it.
+/// Otherwise, the TSC is used, which only provides a few bits of entropy
+/// (the boot duration varies slightly between boots) but is always available.
+pub fn entropy() -> u64 {
+    rdrand().unwrap_or_else(|| {
+        // Safety: RDTSC is available on every x86_64 CPU.
+        let tsc = unsafe { core::arch::x86_64::_rdtsc() };
+        // Spread the low, most variable bits over the whole word.
+        tsc.wrapping_mul(0x9E37_79B9_7F4A_7C15).rotate_left(32)
+    })
+}
+
+#[must_use]
+fn rdrand() -> Option<u64> {
+    let cpuid = core::arch::x86_64::__cpuid(1);
+    if (cpuid.ecx >> 30) & 1 == 0 {
+        return None;
+    }
+
+    let mut value = 0;
+    for _ in 0..RETRY_LIMIT {
+        // Safety: RDRAND support has been checked.
+        if unsafe { core::arch::x86_64::_rdrand64_step(&mut value) } == 1 {
+            return Some(value);
+        }
+    }
+    None
+}
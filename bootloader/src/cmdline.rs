@@ -0,0 +1,153 @@
+//! Bootloader command line.
+//!
+//! Options are read from the UEFI load options of the bootloader image,
+//! as a whitespace-separated list of flags.
+//! They can be set from the UEFI shell (`bootx64.efi memtest`) or a boot entry.
+
+use crate::warn;
+use hyperdrive::once::Once;
+use uefi::proto::loaded_image::LoadedImage;
+
+static OPTIONS: Once<Options> = Once::uninit();
+
+/// Maximum length of a single flag.
+const MAX_FLAG_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    memtest: bool,
+    kaslr: bool,
+    insecure: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            memtest: false,
+            kaslr: true,
+            insecure: false,
+        }
+    }
+}
+
+impl Options {
+    #[must_use]
+    /// Parses a command line.
+    ///
+    /// Unknown flags are ignored.
+    pub fn parse(cmdline: impl IntoIterator<Item = char>) -> Self {
+        let mut options = Self::default();
+
+        let mut flag = [0_u8; MAX_FLAG_LEN];
+        let mut len = 0;
+        for c in cmdline.into_iter().chain(core::iter::once(' ')) {
+            if c.is_whitespace() || c == '\0' {
+                if len > 0 {
+                    options.apply(flag.get(..len));
+                }
+                len = 0;
+            } else {
+                // Flags that are too long or not ASCII are invalid.
+                if let Some(slot) = flag.get_mut(len)
+                    && c.is_ascii()
+                {
+                    *slot = u8::try_from(c).unwrap();
+                } else {
+                    len = MAX_FLAG_LEN;
+                }
+                len = len.saturating_add(1);
+            }
+        }
+
+        options
+    }
+
+    fn apply(&mut self, flag: Option<&[u8]>) {
+        match flag {
+            Some(b"memtest") => self.memtest = true,
+            Some(b"nokaslr") => self.kaslr = false,
+            Some(b"insecure") => self.insecure = true,
+            Some(flag) => {
+                warn!(
+                    "Unknown command line option: {}",
+                    core::str::from_utf8(flag).unwrap_or("?")
+                );
+            }
+            None => {
+                warn!("Invalid command line option");
+            }
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether to run a memory test before handing memory to the kernel.
+    pub const fn memtest(&self) -> bool {
+        self.memtest
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether to load the kernel at a randomized virtual address.
+    ///
+    /// Enabled unless the `nokaslr` option is set.
+    pub const fn kaslr(&self) -> bool {
+        self.kaslr
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether to boot the kernel even if its signature cannot be verified.
+    pub const fn insecure(&self) -> bool {
+        self.insecure
+    }
+}
+
+/// Reads the command line from the UEFI load options.
+///
+/// Must be called before exiting boot services.
+pub fn init() {
+    OPTIONS.call_once(|| {
+        let Ok(image) =
+            uefi::boot::open_protocol_exclusive::<LoadedImage>(uefi::boot::image_handle())
+        else {
+            return Options::default();
+        };
+        image.load_options_as_cstr16().map_or_else(
+            |_| Options::default(),
+            |cmdline| Options::parse(cmdline.iter().map(|&c| char::from(c))),
+        )
+    });
+}
+
+#[must_use]
+/// Returns the parsed command line options.
+///
+/// If `init` was not called, default options are returned.
+pub fn options() -> Options {
+    OPTIONS.get().copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate alloc;
+
+    #[test]
+    fn test_parse() {
+        assert!(!Options::parse("".chars()).memtest());
+        assert!(Options::parse("memtest".chars()).memtest());
+        assert!(Options::parse("  foo\tmemtest\0".chars()).memtest());
+        assert!(!Options::parse("memtest2 nomemtest".chars()).memtest());
+
+        let long = "memtest".repeat(MAX_FLAG_LEN);
+        assert!(!Options::parse(long.chars()).memtest());
+
+        assert!(Options::parse("memtest".chars()).kaslr());
+        assert!(!Options::parse("nokaslr".chars()).kaslr());
+
+        assert!(!Options::parse("memtest".chars()).insecure());
+        assert!(Options::parse("insecure".chars()).insecure());
+    }
+}
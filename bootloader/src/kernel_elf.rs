@@ -4,7 +4,7 @@ use beskar_core::arch::{
     paging::{CacheFlush, Frame, FrameAllocator, M4KiB, Mapper as _, MemSize, Page},
 };
 use beskar_hal::paging::page_table::{Flags, OffsetPageTable};
-use bootloader_api::KERNEL_IMAGE_BASE;
+use bootloader_api::{KERNEL_IMAGE_BASE, RAMDISK_BASE};
 use xmas_elf::{
     ElfFile,
     dynamic::Tag,
@@ -13,6 +13,12 @@ use xmas_elf::{
     sections::Rela,
 };
 
+/// Size of the virtual region the kernel image can be loaded in.
+///
+/// The region starts at `KERNEL_IMAGE_BASE` and ends at `RAMDISK_BASE`,
+/// so that a randomized kernel never collides with the other fixed regions.
+const KERNEL_REGION_SIZE: u64 = RAMDISK_BASE.as_u64() - KERNEL_IMAGE_BASE.as_u64();
+
 pub struct KernelLoadingUtils<'a> {
     kernel: &'a ElfFile<'a>,
     page_table: &'a mut OffsetPageTable<'static>,
@@ -82,7 +88,22 @@ pub fn load_kernel_elf(mut klu: KernelLoadingUtils) -> LoadedKernelInfo {
 
         assert!(min_addr <= max_addr, "No loadable segments");
 
-        KERNEL_IMAGE_BASE - min_addr
+        let slide = if crate::cmdline::options().kaslr() {
+            let align = klu
+                .kernel
+                .program_iter()
+                .filter(|header| header.get_type() == Ok(Type::Load))
+                .map(|header| header.align())
+                .fold(M4KiB::SIZE, u64::max);
+            let slide = kaslr_slide(crate::arch::rand::entropy(), max_addr - min_addr, align)
+                .expect("Kernel image is too large to fit in its region");
+            crate::debug!("KASLR slide: {:#x}", slide);
+            slide
+        } else {
+            0
+        };
+
+        KERNEL_IMAGE_BASE + slide - min_addr
     }
     .as_u64();
 
@@ -539,9 +560,67 @@ fn handle_segment_gnurelro(
     }
 }
 
+#[must_use]
+/// Computes a random offset from `KERNEL_IMAGE_BASE` to load the kernel image at.
+///
+/// The offset is a multiple of `align`, which must be a power of two at least
+/// as large as the page size and the alignment of every loadable segment,
+/// so that the in-page offsets of segments are preserved.
+/// The image, of size `image_size`, is guaranteed to end before `RAMDISK_BASE`.
+///
+/// Returns `None` if the image does not fit in the region.
+fn kaslr_slide(entropy: u64, image_size: u64, align: u64) -> Option<u64> {
+    assert!(align.is_power_of_two());
+
+    let aligned_size = image_size.checked_next_multiple_of(align)?;
+    let slots = KERNEL_REGION_SIZE.checked_sub(aligned_size)? / align + 1;
+
+    Some((entropy % slots) * align)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct LoadedKernelInfo {
     pub entry_point: VirtAddr,
     pub image_offset: VirtAddr,
     pub kernel_size: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kaslr_slide_bounds() {
+        let image_size = 0x30_0000;
+        let align = M4KiB::SIZE;
+
+        assert_eq!(kaslr_slide(0, image_size, align), Some(0));
+
+        // The largest slide still fits in the region.
+        let max_slide = kaslr_slide(u64::MAX, image_size, align).unwrap();
+        assert!(max_slide + image_size <= KERNEL_REGION_SIZE);
+
+        let slots = (KERNEL_REGION_SIZE - image_size) / align + 1;
+        let last = kaslr_slide(slots - 1, image_size, align).unwrap();
+        assert_eq!(last, KERNEL_REGION_SIZE - image_size);
+        assert!((KERNEL_IMAGE_BASE + last + image_size).as_u64() <= RAMDISK_BASE.as_u64());
+        // Entropy wraps around the number of slots.
+        assert_eq!(kaslr_slide(slots, image_size, align), Some(0));
+    }
+
+    #[test]
+    fn test_kaslr_slide_alignment() {
+        for entropy in [1, 0x1234_5678_9ABC_DEF0, u64::MAX / 3] {
+            let slide = kaslr_slide(entropy, 0x12_3456, 0x20_0000).unwrap();
+            assert_eq!(slide % 0x20_0000, 0);
+            assert!(slide + 0x20_0000 <= KERNEL_REGION_SIZE);
+        }
+        assert_eq!(kaslr_slide(3, 0x1000, 0x1000), Some(0x3000));
+    }
+
+    #[test]
+    fn test_kaslr_slide_too_large() {
+        assert_eq!(kaslr_slide(0, KERNEL_REGION_SIZE, M4KiB::SIZE), Some(0));
+        assert_eq!(kaslr_slide(0, KERNEL_REGION_SIZE + 1, M4KiB::SIZE), None);
+    }
+}
@@ -5,8 +5,8 @@
 
 use beskar_core::{
     arch::{
-        VirtAddr,
-        paging::{CacheFlush as _, FrameAllocator as _, Mapper, Page},
+        AddrRange, VirtAddr,
+        paging::{CacheFlush as _, FrameAllocator as _, M4KiB, Mapper, MemSize as _},
     },
     mem::ranges::MemoryRange,
 };
@@ -16,9 +16,11 @@ use core::alloc::Layout;
 use mem::{EarlyFrameAllocator, Mappings, PageTables};
 
 pub mod arch;
+pub mod cmdline;
 pub mod fs;
 pub mod mem;
 pub mod system;
+pub mod verify;
 pub mod video;
 
 mod kernel_elf;
@@ -40,17 +42,16 @@ pub fn create_boot_info(
     let boot_info_addr = BOOT_INFO_BASE;
 
     let memory_map_regions_addr = boot_info_addr + u64::try_from(memory_regions_offset).unwrap();
-    let memory_map_regions_end = boot_info_addr + u64::try_from(layout.size()).unwrap();
+    let boot_info_range =
+        AddrRange::from_len(boot_info_addr, u64::try_from(layout.size()).unwrap());
 
-    let start_page = Page::containing_address(boot_info_addr);
-    let end_page = Page::containing_address(memory_map_regions_end - 1);
-
-    for page in Page::range_inclusive(start_page, end_page) {
+    for page in boot_info_range.pages::<M4KiB>() {
         let flags = Flags::PRESENT | Flags::WRITABLE | Flags::NO_EXECUTE;
 
         let frame = frame_allocator
             .allocate_frame()
             .expect("Failed to allocate a frame");
+        frame_allocator.reserve(frame.start_address(), M4KiB::SIZE);
 
         for table in [&mut page_tables.kernel, &mut page_tables.bootloader] {
             table
@@ -79,6 +79,8 @@ fn efi_entry() -> Status {
 
     bootloader::system::init();
 
+    bootloader::cmdline::init();
+
     bootloader::arch::init();
 
     // Load Kernel file in RAM
@@ -86,6 +88,7 @@ fn efi_entry() -> Status {
     let kernel = {
         let file_content = bootloader::fs::load_file_from_efi_dir(cstr16!("kernelx64.elf"))
             .expect("Failed to load kernel");
+        bootloader::verify::verify_kernel(file_content);
         xmas_elf::ElfFile::new(file_content).expect("Failed to parse kernel")
     };
     info!("Kernel file loaded");
@@ -1,5 +1,5 @@
 use beskar_core::arch::{
-    VirtAddr,
+    PhysAddr, VirtAddr,
     paging::{Frame, FrameAllocator as _, M4KiB, MemSize as _},
 };
 use beskar_hal::{
@@ -11,6 +11,8 @@ use uefi::{
     mem::memory_map::{MemoryMap, MemoryMapOwned},
 };
 
+pub mod memtest;
+
 mod phys;
 pub use phys::EarlyFrameAllocator;
 
@@ -31,6 +33,18 @@ pub fn init(
 
     let mut frame_allocator = EarlyFrameAllocator::new(memory_map);
 
+    // The kernel image and the ramdisk are mapped where the firmware loaded them
+    for file in core::iter::once(kernel_elf.input).chain(ramdisk) {
+        frame_allocator.reserve(
+            PhysAddr::new_truncate(file.as_ptr() as u64),
+            u64::try_from(file.len()).unwrap(),
+        );
+    }
+
+    if crate::cmdline::options().memtest() {
+        memtest::run(&frame_allocator);
+    }
+
     let mut page_tables = create_page_tables(&mut frame_allocator);
 
     let mappings = virt::make_mappings(kernel_elf, ramdisk, &mut frame_allocator, &mut page_tables);
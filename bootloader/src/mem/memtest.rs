@@ -0,0 +1,180 @@
+//! Boot-time memory test.
+//!
+//! Writes and verifies patterns across usable memory, in order to catch
+//! faulty RAM before the kernel starts relying on it.
+//! Tested memory is then left poisoned, so that reads of uninitialized memory stand out.
+//!
+//! This pass is slow on large machines, so it is only run if the `memtest` option is set.
+
+use super::EarlyFrameAllocator;
+use crate::{error, info, warn};
+
+/// Value left in tested memory.
+pub const POISON: u64 = 0xDEAD_BEEF_DEAD_BEEF;
+
+/// Maximum number of faulty addresses to report.
+const MAX_REPORTED: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    Fixed(u64),
+    /// Each word holds its own address, which catches address line faults.
+    Address,
+}
+
+impl Pattern {
+    #[must_use]
+    #[inline]
+    const fn value(self, paddr: u64) -> u64 {
+        match self {
+            Self::Fixed(value) => value,
+            Self::Address => paddr,
+        }
+    }
+}
+
+const PATTERNS: [Pattern; 4] = [
+    Pattern::Fixed(0x5555_5555_5555_5555),
+    Pattern::Fixed(0xAAAA_AAAA_AAAA_AAAA),
+    Pattern::Address,
+    Pattern::Fixed(POISON),
+];
+
+/// Writes and verifies every pattern over `words`, which start at physical address `base`.
+///
+/// `on_error` is called with the physical address, the expected and the read value
+/// of every mismatching word.
+/// Returns the number of mismatches.
+pub fn test_words(words: &mut [u64], base: u64, on_error: impl FnMut(u64, u64, u64)) -> usize {
+    let ptr = words.as_mut_ptr();
+    test_with(
+        words.len(),
+        base,
+        // Safety: `i` is in bounds of `words`.
+        |i, value| unsafe { ptr.add(i).write_volatile(value) },
+        |i| unsafe { ptr.add(i).read_volatile() },
+        on_error,
+    )
+}
+
+/// Pattern test over `len` words accessed through `write` and `read`.
+fn test_with(
+    len: usize,
+    base: u64,
+    mut write: impl FnMut(usize, u64),
+    mut read: impl FnMut(usize) -> u64,
+    mut on_error: impl FnMut(u64, u64, u64),
+) -> usize {
+    let paddr = |i: usize| base + u64::try_from(i * size_of::<u64>()).unwrap();
+    let mut errors = 0;
+
+    for pattern in PATTERNS {
+        for i in 0..len {
+            write(i, pattern.value(paddr(i)));
+        }
+
+        for i in 0..len {
+            let expected = pattern.value(paddr(i));
+            let found = read(i);
+            if found != expected {
+                errors += 1;
+                on_error(paddr(i), expected, found);
+            }
+        }
+    }
+
+    errors
+}
+
+/// Tests all memory regions that are still free in the frame allocator.
+///
+/// Memory is expected to be identity mapped.
+pub fn run(frame_allocator: &EarlyFrameAllocator) {
+    info!("Running memory test...");
+
+    let mut reported = 0;
+    let mut total_errors = 0;
+    let mut tested_bytes = 0;
+
+    for region in frame_allocator.free_regions() {
+        let len = usize::try_from(region.size()).unwrap() / size_of::<u64>();
+        // Safety: The region is free conventional memory, identity mapped by the firmware.
+        let words = unsafe {
+            core::slice::from_raw_parts_mut(
+                core::ptr::with_exposed_provenance_mut(usize::try_from(region.start()).unwrap()),
+                len,
+            )
+        };
+
+        total_errors += test_words(words, region.start(), |paddr, expected, found| {
+            if reported < MAX_REPORTED {
+                error!(
+                    "Memory error at {:#x}: expected {:#x}, found {:#x}",
+                    paddr, expected, found
+                );
+                reported += 1;
+            }
+        });
+        tested_bytes += region.size();
+    }
+
+    if total_errors == 0 {
+        info!("Memory test passed ({} MiB)", tested_bytes / (1024 * 1024));
+    } else {
+        warn!(
+            "Memory test found {} faulty words over {} MiB",
+            total_errors,
+            tested_bytes / (1024 * 1024)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_pass() {
+        let mut buffer = [0_u64; 512];
+        let mut errors = 0;
+        let count = test_words(&mut buffer, 0x10_0000, |_, _, _| errors += 1);
+
+        assert_eq!(count, 0);
+        assert_eq!(errors, 0);
+        assert!(buffer.iter().all(|&w| w == POISON));
+    }
+
+    #[test]
+    fn test_address_pattern() {
+        assert_eq!(Pattern::Address.value(0x1234_5678), 0x1234_5678);
+        assert_eq!(Pattern::Fixed(POISON).value(0x1234_5678), POISON);
+    }
+
+    #[test]
+    fn test_words_reports_mismatch() {
+        extern crate alloc;
+        use alloc::vec::Vec;
+        use core::cell::Cell;
+
+        // Word 3 has bit 0 stuck at 1.
+        let memory = [const { Cell::new(0_u64) }; 8];
+        let mut errors = Vec::new();
+        let count = test_with(
+            memory.len(),
+            0x1000,
+            |i, value| memory[i].set(value | u64::from(i == 3)),
+            |i| memory[i].get(),
+            |paddr, expected, found| errors.push((paddr, expected, found)),
+        );
+
+        // The stuck bit is only visible for patterns with bit 0 cleared.
+        assert_eq!(count, 2);
+        assert_eq!(
+            errors,
+            [
+                (0x1018, 0xAAAA_AAAA_AAAA_AAAA, 0xAAAA_AAAA_AAAA_AAAB),
+                (0x1018, 0x1018, 0x1019),
+            ]
+        );
+    }
+}
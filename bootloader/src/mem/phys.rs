@@ -3,13 +3,16 @@ use beskar_core::{
         PhysAddr, VirtAddr,
         paging::{Frame, FrameAllocator, M4KiB, MemSize as _},
     },
-    mem::ranges::MemoryRange,
+    mem::ranges::{self, MemoryRange, MemoryRanges},
 };
 use uefi::{
     boot::{MemoryDescriptor, MemoryType},
     mem::memory_map::{MemoryMap, MemoryMapOwned},
 };
 
+/// Maximum number of regions that can be reserved with `EarlyFrameAllocator::reserve`.
+const MAX_RESERVED_REGIONS: usize = 8;
+
 /// A physical frame allocator based on a UEFI provided memory map.
 ///
 /// Its purpose is to keep track of used regions, to avoid overwriting them in the kernel.
@@ -24,6 +27,8 @@ pub struct EarlyFrameAllocator {
     min_frame: Frame,
     /// The largest detected physical memory address.
     max_physical_address: PhysAddr,
+    /// Regions that must never be handed to the kernel, such as the ramdisk.
+    reserved: MemoryRanges<MAX_RESERVED_REGIONS>,
 }
 
 impl EarlyFrameAllocator {
@@ -53,6 +58,7 @@ impl EarlyFrameAllocator {
             next_frame: frame,
             min_frame: frame,
             max_physical_address,
+            reserved: MemoryRanges::new(),
         }
     }
 
@@ -85,12 +91,48 @@ impl EarlyFrameAllocator {
         self.max_physical_address
     }
 
+    /// Iterates over the conventional memory regions that have not been allocated yet.
+    pub fn free_regions(&self) -> impl Iterator<Item = MemoryRange> + '_ {
+        let first_free = self.next_frame.start_address().as_u64();
+        self.memory_map
+            .entries()
+            .filter(|descriptor| descriptor.ty == MemoryType::CONVENTIONAL)
+            .filter_map(move |descriptor| {
+                let end = descriptor.phys_start + descriptor.page_count * M4KiB::SIZE;
+                let start = descriptor.phys_start.max(first_free);
+                (start < end).then(|| MemoryRange::new(start, end - 1))
+            })
+    }
+
+    /// Reserves the frames containing `size` bytes from `start`,
+    /// so that they are excluded from the memory map given to the kernel.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if too many regions are already reserved.
+    pub fn reserve(&mut self, start: PhysAddr, size: u64) {
+        if size == 0 {
+            return;
+        }
+        assert!(
+            self.reserved.len() < MAX_RESERVED_REGIONS,
+            "Too many reserved memory regions"
+        );
+        let start_frame = Frame::<M4KiB>::containing_address(start);
+        let end_frame = Frame::<M4KiB>::containing_address(start + (size - 1));
+        self.reserved.insert(MemoryRange::new(
+            start_frame.start_address().as_u64(),
+            end_frame.end_address().as_u64(),
+        ));
+    }
+
     #[must_use]
     #[inline]
     pub fn mem_map_max_region_count(&self) -> usize {
         // Bootloader memory allocator can end up creating 2
-        // more memory regions when splitting the memory map.
-        self.memory_map.len() + 2
+        // more memory regions when splitting the memory map,
+        // and each reserved region can split one more.
+        self.memory_map.len() + 2 + MAX_RESERVED_REGIONS
     }
 
     #[must_use]
@@ -126,7 +168,22 @@ impl EarlyFrameAllocator {
             }
         }
 
-        &mut regions[..next_index]
+        for reserved in self.reserved.entries() {
+            next_index = ranges::carve_out(regions, next_index, reserved);
+        }
+        let len = ranges::merge_adjacent(&mut regions[..next_index]);
+        let regions = &mut regions[..len];
+
+        for region in regions.iter() {
+            assert!(
+                self.reserved.entries().iter().all(|reserved| {
+                    region.end() < reserved.start() || region.start() > reserved.end()
+                }),
+                "Usable memory overlaps a reserved region"
+            );
+        }
+
+        regions
     }
 
     fn split_region(
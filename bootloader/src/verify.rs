@@ -0,0 +1,162 @@
+//! Verified boot.
+//!
+//! If the bootloader is built with the `BESKAR_KERNEL_PUBKEY` environment variable set
+//! to a hex-encoded Ed25519 public key, it refuses to boot a kernel that does not come
+//! with a valid detached signature, `kernelx64.sig`, in the EFI directory.
+//!
+//! The signature is computed over the SHA-256 digest of `kernelx64.elf`.
+//! Verification failures can be ignored by passing the `insecure` option to the bootloader.
+
+use crate::{cmdline, debug, info, warn};
+use uefi::cstr16;
+
+mod ed25519;
+mod sha2;
+
+pub use beskar_core::crypto::sha256::{Sha256, sha256};
+
+/// Public key embedded at build time, if any.
+const PUBLIC_KEY: Option<[u8; 32]> = match option_env!("BESKAR_KERNEL_PUBKEY") {
+    Some(hex) => Some(parse_key(hex)),
+    None => None,
+};
+
+/// Parses a hex-encoded public key, failing the build if it is malformed.
+const fn parse_key(hex: &str) -> [u8; 32] {
+    const fn nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => panic!("BESKAR_KERNEL_PUBKEY must be hex-encoded"),
+        }
+    }
+
+    let hex = hex.as_bytes();
+    assert!(
+        hex.len() == 64,
+        "BESKAR_KERNEL_PUBKEY must be a 32-byte Ed25519 public key"
+    );
+
+    let mut key = [0; 32];
+    let mut i = 0;
+    while i < key.len() {
+        key[i] = (nibble(hex[2 * i]) << 4) | nibble(hex[2 * i + 1]);
+        i += 1;
+    }
+    key
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationError {
+    /// `kernelx64.sig` was not found.
+    MissingSignature,
+    /// The signature file is not 64 bytes long.
+    MalformedSignature,
+    /// The signature does not match the kernel image.
+    BadSignature,
+}
+
+impl core::fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::MissingSignature => "signature not found",
+            Self::MalformedSignature => "malformed signature",
+            Self::BadSignature => "signature mismatch",
+        })
+    }
+}
+
+/// Checks a detached `signature` of the `kernel` image against `public_key`.
+///
+/// # Errors
+///
+/// Returns an error if the signature is malformed or does not match.
+pub fn check(
+    kernel: &[u8],
+    signature: &[u8],
+    public_key: &[u8; 32],
+) -> Result<(), VerificationError> {
+    let signature: &[u8; 64] = signature
+        .try_into()
+        .map_err(|_| VerificationError::MalformedSignature)?;
+
+    let digest = sha256(kernel);
+
+    if ed25519::verify(public_key, &digest, signature) {
+        Ok(())
+    } else {
+        Err(VerificationError::BadSignature)
+    }
+}
+
+/// Verifies the kernel image against its signature.
+///
+/// Does nothing if no public key was embedded at build time.
+/// Must be called before exiting boot services.
+///
+/// ## Panics
+///
+/// Panics if verification fails, unless the `insecure` option is set.
+pub fn verify_kernel(kernel: &[u8]) {
+    let Some(public_key) = PUBLIC_KEY else {
+        debug!("No public key embedded, skipping kernel verification");
+        return;
+    };
+
+    let result = crate::fs::load_file_from_efi_dir(cstr16!("kernelx64.sig"))
+        .map_or(Err(VerificationError::MissingSignature), |signature| {
+            check(kernel, signature, &public_key)
+        });
+
+    match result {
+        Ok(()) => {
+            info!("Kernel signature verified");
+        }
+        Err(err) if cmdline::options().insecure() => {
+            warn!("Kernel verification failed ({}), booting anyway", err);
+        }
+        Err(err) => panic!("Kernel verification failed: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8032 test key 1
+    const PUBLIC_KEY: [u8; 32] =
+        parse_key("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(PUBLIC_KEY[0], 0xd7);
+        assert_eq!(PUBLIC_KEY[31], 0x1a);
+        assert_eq!(
+            parse_key("D75A980182B10AB7D54BFED3C964073A0EE172F3DAA62325AF021A68F707511A"),
+            PUBLIC_KEY
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let kernel = b"\x7fELF not really a kernel";
+        let signature = [
+            0x40, 0x90, 0x1e, 0x5e, 0x10, 0xe2, 0x7f, 0xd8, 0x8a, 0xc8, 0x5c, 0xc1, 0x43, 0x03,
+            0xfe, 0xe4, 0xd2, 0x42, 0x9f, 0x82, 0xec, 0xd4, 0x80, 0x0b, 0x64, 0x6f, 0xed, 0xb8,
+            0x7d, 0x53, 0x86, 0x48, 0xd0, 0x21, 0xde, 0x4f, 0xc9, 0xb2, 0x88, 0xa2, 0x2b, 0xbe,
+            0x3b, 0xd1, 0x6b, 0xe9, 0x65, 0xe8, 0x4d, 0xad, 0x57, 0x69, 0xaa, 0x13, 0xc3, 0x47,
+            0x72, 0x31, 0x03, 0xe5, 0x1c, 0x0e, 0xe2, 0x0a,
+        ];
+
+        assert_eq!(check(kernel, &signature, &PUBLIC_KEY), Ok(()));
+        assert_eq!(
+            check(b"\x7fELF tampered kernel", &signature, &PUBLIC_KEY),
+            Err(VerificationError::BadSignature)
+        );
+        assert_eq!(
+            check(kernel, &signature[..63], &PUBLIC_KEY),
+            Err(VerificationError::MalformedSignature)
+        );
+    }
+}
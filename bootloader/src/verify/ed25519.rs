@@ -0,0 +1,480 @@
+//! Ed25519 signature verification (RFC 8032).
+//!
+//! Only verification is implemented, as signing is done offline.
+//! The code is not constant-time, which is fine as all inputs are public.
+//! Encodings are still compared with `ct_eq`, so that the final check does not reveal
+//! how many bytes of a forged signature were right.
+#![allow(
+    clippy::many_single_char_names,
+    clippy::cast_possible_truncation,
+    reason = "Names and truncations follow the specification"
+)]
+
+use super::sha2::Sha512;
+use beskar_core::crypto::ct_eq;
+
+/// Mask of the 51 lower bits of a limb.
+const MASK: u64 = (1 << 51) - 1;
+
+/// Order of the base point, little-endian.
+const L: [u64; 4] = [
+    0x5812_631a_5cf5_d3ed,
+    0x14de_f9de_a2f7_9cd6,
+    0,
+    0x1000_0000_0000_0000,
+];
+
+/// Edwards curve constant `d = -121665 / 121666`.
+const D_BYTES: [u8; 32] = [
+    0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75, 0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+    0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c, 0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52,
+];
+
+/// A square root of -1.
+const SQRT_M1_BYTES: [u8; 32] = [
+    0xb0, 0xa0, 0x0e, 0x4a, 0x27, 0x1b, 0xee, 0xc4, 0x78, 0xe4, 0x2f, 0xad, 0x06, 0x18, 0x43, 0x2f,
+    0xa7, 0xd7, 0xfb, 0x3d, 0x99, 0x00, 0x4d, 0x2b, 0x0b, 0xdf, 0xc1, 0x4f, 0x80, 0x24, 0x83, 0x2b,
+];
+
+/// Encoding of the base point.
+const BASE_BYTES: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+
+#[must_use]
+/// Verifies an Ed25519 `signature` of `message` under `public_key`.
+pub fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    let (r_bytes, s_bytes) = signature.split_at(32);
+    let r_bytes: &[u8; 32] = r_bytes.try_into().unwrap();
+    let s_bytes: &[u8; 32] = s_bytes.try_into().unwrap();
+
+    let Some(s) = Scalar::from_canonical_bytes(s_bytes) else {
+        return false;
+    };
+    let (Some(a), Some(r)) = (Point::decompress(public_key), Point::decompress(r_bytes)) else {
+        return false;
+    };
+
+    let mut hasher = Sha512::new();
+    hasher.update(r_bytes);
+    hasher.update(public_key);
+    hasher.update(message);
+    let k = Scalar::from_wide_bytes(&hasher.finalize());
+
+    let base = Point::decompress(&BASE_BYTES).unwrap();
+
+    // [S]B = R + [k]A
+    base.mul(&s).eq(&r.add(&a.mul(&k)))
+}
+
+/// Element of the field of integers modulo `2^255 - 19`.
+///
+/// It is stored as five 51-bit limbs, which are allowed to slightly exceed 51 bits
+/// between operations.
+#[derive(Debug, Clone, Copy)]
+struct FieldElement([u64; 5]);
+
+impl FieldElement {
+    const ZERO: Self = Self([0; 5]);
+    const ONE: Self = Self([1, 0, 0, 0, 0]);
+
+    /// Decodes a little-endian field element, ignoring the top bit.
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let load = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Self([
+            load(0) & MASK,
+            (load(6) >> 3) & MASK,
+            (load(12) >> 6) & MASK,
+            (load(19) >> 1) & MASK,
+            (load(24) >> 12) & MASK,
+        ])
+    }
+
+    /// Encodes the canonical representative of the element.
+    fn to_bytes(self) -> [u8; 32] {
+        let mut l = self.carry().0;
+
+        // Compute `q = 1` if the value is at least `p`, and subtract `q * p`.
+        let mut q = (l[0] + 19) >> 51;
+        for limb in &l[1..] {
+            q = (limb + q) >> 51;
+        }
+        l[0] += 19 * q;
+        for i in 0..4 {
+            l[i + 1] += l[i] >> 51;
+            l[i] &= MASK;
+        }
+        l[4] &= MASK;
+
+        let mut bytes = [0; 32];
+        let mut acc = 0_u128;
+        let mut bits = 0;
+        let mut out = bytes.iter_mut();
+        for limb in l {
+            acc |= u128::from(limb) << bits;
+            bits += 51;
+            while bits >= 8 {
+                *out.next().unwrap() = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+            }
+        }
+        *out.next().unwrap() = acc as u8;
+        bytes
+    }
+
+    /// Propagates the carries so that each limb fits in 51 bits (plus a small excess).
+    fn carry(self) -> Self {
+        let mut l = self.0;
+        for i in 0..4 {
+            l[i + 1] += l[i] >> 51;
+            l[i] &= MASK;
+        }
+        l[0] += 19 * (l[4] >> 51);
+        l[4] &= MASK;
+        Self(l)
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        let mut l = self.0;
+        for (a, b) in l.iter_mut().zip(rhs.0) {
+            *a += b;
+        }
+        Self(l).carry()
+    }
+
+    fn sub(self, rhs: Self) -> Self {
+        // Add `4p` to avoid underflowing.
+        const FOUR_P: [u64; 5] = [4 * (MASK - 18), 4 * MASK, 4 * MASK, 4 * MASK, 4 * MASK];
+        let mut l = self.0;
+        for ((a, b), p) in l.iter_mut().zip(rhs.0).zip(FOUR_P) {
+            *a = *a + p - b;
+        }
+        Self(l).carry()
+    }
+
+    fn neg(self) -> Self {
+        Self::ZERO.sub(self)
+    }
+
+    fn mul(self, rhs: Self) -> Self {
+        let m = |x: u64, y: u64| u128::from(x) * u128::from(y);
+        let [a0, a1, a2, a3, a4] = self.0;
+        let [b0, b1, b2, b3, b4] = rhs.0;
+        let (b1_19, b2_19, b3_19, b4_19) = (b1 * 19, b2 * 19, b3 * 19, b4 * 19);
+
+        let c0 = m(a0, b0) + m(a4, b1_19) + m(a3, b2_19) + m(a2, b3_19) + m(a1, b4_19);
+        let mut c1 = m(a1, b0) + m(a0, b1) + m(a4, b2_19) + m(a3, b3_19) + m(a2, b4_19);
+        let mut c2 = m(a2, b0) + m(a1, b1) + m(a0, b2) + m(a4, b3_19) + m(a3, b4_19);
+        let mut c3 = m(a3, b0) + m(a2, b1) + m(a1, b2) + m(a0, b3) + m(a4, b4_19);
+        let mut c4 = m(a4, b0) + m(a3, b1) + m(a2, b2) + m(a1, b3) + m(a0, b4);
+
+        let mask = u128::from(MASK);
+        c1 += c0 >> 51;
+        c2 += c1 >> 51;
+        c3 += c2 >> 51;
+        c4 += c3 >> 51;
+        let c0 = (c0 & mask) + (c4 >> 51) * 19;
+
+        Self([
+            (c0 & mask) as u64,
+            (c1 & mask) as u64 + (c0 >> 51) as u64,
+            (c2 & mask) as u64,
+            (c3 & mask) as u64,
+            (c4 & mask) as u64,
+        ])
+    }
+
+    fn square(self) -> Self {
+        self.mul(self)
+    }
+
+    fn pow2k(mut self, k: u32) -> Self {
+        for _ in 0..k {
+            self = self.square();
+        }
+        self
+    }
+
+    /// Computes `self^(2^250 - 1)`.
+    fn pow22501(self) -> Self {
+        let t0 = self.square();
+        let t1 = t0.pow2k(2);
+        let t2 = self.mul(t1);
+        let t3 = t0.mul(t2);
+        let t4 = t3.square();
+        let t5 = t2.mul(t4);
+        let t7 = t5.pow2k(5).mul(t5);
+        let t9 = t7.pow2k(10).mul(t7);
+        let t11 = t9.pow2k(20).mul(t9);
+        let t13 = t11.pow2k(10).mul(t7);
+        let t15 = t13.pow2k(50).mul(t13);
+        let t17 = t15.pow2k(100).mul(t15);
+        t17.pow2k(50).mul(t13)
+    }
+
+    /// Computes `self^((p - 5) / 8)`.
+    fn pow_p58(self) -> Self {
+        self.pow22501().pow2k(2).mul(self)
+    }
+
+    fn is_zero(self) -> bool {
+        ct_eq(&self.to_bytes(), &[0; 32])
+    }
+
+    fn is_negative(self) -> bool {
+        self.to_bytes()[0] & 1 == 1
+    }
+
+    fn eq(self, rhs: Self) -> bool {
+        ct_eq(&self.to_bytes(), &rhs.to_bytes())
+    }
+}
+
+/// Point on the curve, in extended twisted Edwards coordinates.
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+    t: FieldElement,
+}
+
+impl Point {
+    const IDENTITY: Self = Self {
+        x: FieldElement::ZERO,
+        y: FieldElement::ONE,
+        z: FieldElement::ONE,
+        t: FieldElement::ZERO,
+    };
+
+    /// Decodes a point, as specified in RFC 8032 section 5.1.3.
+    fn decompress(bytes: &[u8; 32]) -> Option<Self> {
+        let sign = bytes[31] >> 7 == 1;
+        let y = FieldElement::from_bytes(bytes);
+
+        // Reject non-canonical encodings of `y`.
+        let mut canonical = y.to_bytes();
+        canonical[31] |= bytes[31] & 0x80;
+        if !ct_eq(&canonical, bytes) {
+            return None;
+        }
+
+        let d = FieldElement::from_bytes(&D_BYTES);
+        let y2 = y.square();
+        let u = y2.sub(FieldElement::ONE);
+        let v = d.mul(y2).add(FieldElement::ONE);
+
+        // x = u * v^3 * (u * v^7)^((p - 5) / 8)
+        let v3 = v.square().mul(v);
+        let v7 = v3.square().mul(v);
+        let mut x = u.mul(v3).mul(u.mul(v7).pow_p58());
+
+        let vx2 = v.mul(x.square());
+        if !vx2.eq(u) {
+            if vx2.eq(u.neg()) {
+                x = x.mul(FieldElement::from_bytes(&SQRT_M1_BYTES));
+            } else {
+                return None;
+            }
+        }
+
+        if x.is_zero() && sign {
+            return None;
+        }
+        if x.is_negative() != sign {
+            x = x.neg();
+        }
+
+        Some(Self {
+            x,
+            y,
+            z: FieldElement::ONE,
+            t: x.mul(y),
+        })
+    }
+
+    /// Adds two points.
+    ///
+    /// The formula is complete, so it can also be used for doubling.
+    fn add(&self, rhs: &Self) -> Self {
+        let d2 = {
+            let d = FieldElement::from_bytes(&D_BYTES);
+            d.add(d)
+        };
+
+        let a = self.y.sub(self.x).mul(rhs.y.sub(rhs.x));
+        let b = self.y.add(self.x).mul(rhs.y.add(rhs.x));
+        let c = self.t.mul(d2).mul(rhs.t);
+        let d = self.z.add(self.z).mul(rhs.z);
+        let (e, f, g, h) = (b.sub(a), d.sub(c), d.add(c), b.add(a));
+
+        Self {
+            x: e.mul(f),
+            y: g.mul(h),
+            z: f.mul(g),
+            t: e.mul(h),
+        }
+    }
+
+    /// Multiplies the point by a scalar, using double-and-add.
+    fn mul(&self, scalar: &Scalar) -> Self {
+        let mut result = Self::IDENTITY;
+        for bit in (0..256).rev() {
+            result = result.add(&result);
+            if (scalar.0[bit / 64] >> (bit % 64)) & 1 == 1 {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+
+    fn eq(&self, rhs: &Self) -> bool {
+        // Both coordinates are compared, whatever the first comparison gave
+        self.x.mul(rhs.z).eq(rhs.x.mul(self.z)) & self.y.mul(rhs.z).eq(rhs.y.mul(self.z))
+    }
+}
+
+/// Integer modulo the order of the base point, as little-endian 64-bit words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Scalar([u64; 4]);
+
+impl Scalar {
+    /// Decodes a scalar, rejecting values that are not reduced modulo `L`.
+    fn from_canonical_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let mut words = [0; 4];
+        for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        (!ge_l(&words)).then_some(Self(words))
+    }
+
+    /// Reduces a 512-bit little-endian integer modulo `L`.
+    fn from_wide_bytes(bytes: &[u8; 64]) -> Self {
+        let mut r = [0_u64; 4];
+        for bit in (0..512).rev() {
+            // r = 2r + bit, which fits as r < L < 2^253
+            let mut carry = u64::from((bytes[bit / 8] >> (bit % 8)) & 1);
+            for word in &mut r {
+                let next = *word >> 63;
+                *word = (*word << 1) | carry;
+                carry = next;
+            }
+            if ge_l(&r) {
+                let mut borrow = false;
+                for (word, l) in r.iter_mut().zip(L) {
+                    let (v, b1) = word.overflowing_sub(l);
+                    let (v, b2) = v.overflowing_sub(u64::from(borrow));
+                    *word = v;
+                    borrow = b1 || b2;
+                }
+            }
+        }
+        Self(r)
+    }
+}
+
+/// Returns whether `words >= L`.
+fn ge_l(words: &[u64; 4]) -> bool {
+    for (w, l) in words.iter().zip(L).rev() {
+        if *w != l {
+            return *w > l;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex<const N: usize>(s: &str) -> [u8; N] {
+        let mut out = [0; N];
+        for (byte, chunk) in out.iter_mut().zip(s.as_bytes().chunks_exact(2)) {
+            *byte = u8::from_str_radix(core::str::from_utf8(chunk).unwrap(), 16).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn test_constants() {
+        let d = FieldElement::from_bytes(&D_BYTES);
+        let num = FieldElement([121_665, 0, 0, 0, 0]);
+        let den = FieldElement([121_666, 0, 0, 0, 0]);
+        assert!(d.mul(den).eq(num.neg()));
+
+        let i = FieldElement::from_bytes(&SQRT_M1_BYTES);
+        assert!(i.square().eq(FieldElement::ONE.neg()));
+
+        let base = Point::decompress(&BASE_BYTES).unwrap();
+        assert!(base.mul(&Scalar(L)).eq(&Point::IDENTITY));
+    }
+
+    #[test]
+    fn test_rfc8032_vectors() {
+        // Test vectors 1 to 3 of RFC 8032 section 7.1
+        let vectors: [(&str, &[u8], &str); 3] = [
+            (
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                b"",
+                concat!(
+                    "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+                    "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+                ),
+            ),
+            (
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                &[0x72],
+                concat!(
+                    "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da",
+                    "085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+                ),
+            ),
+            (
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+                &[0xaf, 0x82],
+                concat!(
+                    "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac",
+                    "18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a"
+                ),
+            ),
+        ];
+
+        for (public_key, message, signature) in vectors {
+            let public_key = hex(public_key);
+            let signature = hex(signature);
+            assert!(verify(&public_key, message, &signature));
+
+            // Any modification must be rejected
+            assert!(!verify(&public_key, b"tampered", &signature));
+            let mut bad_signature = signature;
+            bad_signature[0] ^= 1;
+            assert!(!verify(&public_key, message, &bad_signature));
+            let mut bad_signature = signature;
+            bad_signature[40] ^= 1;
+            assert!(!verify(&public_key, message, &bad_signature));
+        }
+    }
+
+    #[test]
+    fn test_non_canonical_scalar() {
+        let public_key = hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let mut signature: [u8; 64] = hex(concat!(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+            "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+        ));
+
+        // Adding `L` to `S` gives an equivalent but non-canonical signature
+        let mut carry = false;
+        for (chunk, l) in signature[32..].chunks_exact_mut(8).zip(L) {
+            let s = u64::from_le_bytes((&*chunk).try_into().unwrap());
+            let (v, c1) = s.overflowing_add(l);
+            let (v, c2) = v.overflowing_add(u64::from(carry));
+            chunk.copy_from_slice(&v.to_le_bytes());
+            carry = c1 || c2;
+        }
+        assert!(!carry);
+        assert!(!verify(&public_key, b"", &signature));
+    }
+}
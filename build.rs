@@ -15,6 +15,7 @@ use std::{env::var, fs};
 
 /// List of package names for userspace applications.
 const USERSPACE_APPS: [&str; 1] = ["bashkar"];
+// const USERSPACE_APPS: [&str; 1] = ["top"];
 // const USERSPACE_APPS: [&str; 1] = ["doom"];
 
 /// A macro to print cargo instructions.
@@ -0,0 +1,309 @@
+//! Throughput benchmarks of the heaperion allocators.
+//!
+//! Run with `cargo bench --package heaperion`. Under `cargo test`, every benchmark
+//! runs once, as a smoke test.
+//!
+//! Each benchmark times batches of operations and reports the median time per
+//! operation over the samples, and the matching throughput.
+//!
+//! Baseline, release build on a single vCPU of a shared x86-64 cloud machine:
+//!
+//! ```text
+//! slab/alloc+free/8                   5.0 ns/op     200.00 Mops/s
+//! slab/alloc+free/64                  8.0 ns/op     125.00 Mops/s
+//! slab/alloc+free/512                12.0 ns/op      83.33 Mops/s
+//! slab/fill+drain/64                  8.0 ns/op     125.00 Mops/s
+//! buddy/alloc+free/order 4          142.0 ns/op       7.04 Mops/s
+//! buddy/alloc+free/order 6          127.0 ns/op       7.87 Mops/s
+//! buddy/alloc+free/order 8          108.0 ns/op       9.26 Mops/s
+//! buddy/alloc+free/order 10          94.0 ns/op      10.64 Mops/s
+//! buddy/alloc+free/order 12          76.0 ns/op      13.16 Mops/s
+//! buddy/alloc+free/order 14          61.0 ns/op      16.39 Mops/s
+//! buddy/alloc+free/order 16          47.0 ns/op      21.28 Mops/s
+//! buddy/fill+drain/order 6            6.0 ns/op     166.67 Mops/s
+//! hybrid/mixed                       37.0 ns/op      27.03 Mops/s
+//! hybrid/random sizes                37.0 ns/op      27.03 Mops/s
+//! fragmentation/random sizes        37.7% of the heap live at the first failure
+//! ```
+//!
+//! Slab operations take a few nanoseconds in every size class, as expected
+//! from O(1) free lists. On an empty buddy heap, an allocation splits the top block down
+//! to its order and the free coalesces it back up, so the cost grows linearly with the
+//! number of orders in between: this is the O(log(n)) bound. When blocks of the order
+//! are already split, as in `fill+drain`, both operations are close to O(1).
+//!
+//! The fragmentation run is dominated by the sizing of the heap rather than by the
+//! allocation pattern: the buddy allocator only manages the largest power of two that
+//! fits in its 75% of the heap, and rounds every request up to a power of two.
+use core::alloc::Layout;
+use core::hint::black_box;
+use core::ptr::NonNull;
+use heaperion::{BuddyAllocator, HybridAllocator, SlabAllocator};
+use std::time::{Duration, Instant};
+
+/// Size of the heap given to the allocators.
+const HEAP_SIZE: usize = 16 * 1024 * 1024;
+/// Number of timed batches of each benchmark.
+const SAMPLES: usize = 20;
+/// Operations per batch.
+const BATCH: usize = 10_000;
+
+/// Memory region backing an allocator under test.
+struct Region {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl Region {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, 4096).unwrap();
+        // Safety: The layout has a non-zero size.
+        let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+        assert!(!ptr.is_null());
+        Self { ptr, layout }
+    }
+
+    fn slab(&self) -> SlabAllocator {
+        unsafe { SlabAllocator::new(self.ptr, self.layout.size()) }.unwrap()
+    }
+
+    fn buddy(&self) -> BuddyAllocator {
+        unsafe { BuddyAllocator::new(self.ptr, self.layout.size()) }.unwrap()
+    }
+
+    fn hybrid(&self) -> HybridAllocator {
+        unsafe { HybridAllocator::new(self.ptr, self.layout.size()) }.unwrap()
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        unsafe { std::alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// A xorshift64* generator, so that random workloads are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    const fn new() -> Self {
+        Self(0x2545_F491_4F6C_DD1D)
+    }
+
+    const fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        usize::try_from(self.next_u64() % n as u64).unwrap()
+    }
+}
+
+/// Runs the benchmarks, or each of them once in smoke test mode.
+struct Bencher {
+    quick: bool,
+}
+
+impl Bencher {
+    /// Times `batch`, which performs `ops` operations, and prints the median per operation.
+    fn bench(&self, name: &str, ops: usize, mut batch: impl FnMut()) {
+        if self.quick {
+            batch();
+            return;
+        }
+
+        // Warm-up
+        batch();
+
+        let mut samples = (0..SAMPLES)
+            .map(|_| {
+                let start = Instant::now();
+                batch();
+                start.elapsed()
+            })
+            .collect::<Vec<_>>();
+        samples.sort_unstable();
+
+        let per_op = samples[SAMPLES / 2] / u32::try_from(ops).unwrap();
+        println!(
+            "{name:<30} {:>8.1} ns/op {:>10.2} Mops/s",
+            per_op.as_secs_f64() * 1e9,
+            throughput(per_op)
+        );
+    }
+}
+
+fn throughput(per_op: Duration) -> f64 {
+    1e-6 / per_op.as_secs_f64().max(f64::MIN_POSITIVE)
+}
+
+fn layout(size: usize) -> Layout {
+    Layout::from_size_align(size, 8).unwrap()
+}
+
+fn bench_slab(b: &Bencher) {
+    let region = Region::new(HEAP_SIZE);
+
+    for size in [8, 64, 512] {
+        let mut slab = region.slab();
+        let layout = layout(size);
+        b.bench(&format!("slab/alloc+free/{size}"), BATCH, || {
+            for _ in 0..BATCH {
+                let ptr = slab.allocate(black_box(layout)).unwrap();
+                unsafe { slab.deallocate(black_box(ptr)) }.unwrap();
+            }
+        });
+    }
+
+    // Many live blocks, so that the free list is walked in a scattered order
+    let mut slab = region.slab();
+    let layout = layout(64);
+    let mut live = Vec::with_capacity(BATCH);
+    b.bench("slab/fill+drain/64", 2 * BATCH, || {
+        for _ in 0..BATCH {
+            live.push(slab.allocate(layout).unwrap());
+        }
+        for ptr in live.drain(..).rev() {
+            unsafe { slab.deallocate(ptr) }.unwrap();
+        }
+    });
+}
+
+fn bench_buddy(b: &Bencher) {
+    let region = Region::new(HEAP_SIZE);
+
+    for order in [4, 6, 8, 10, 12, 14, 16] {
+        let mut buddy = region.buddy();
+        let layout = layout(1 << order);
+        b.bench(&format!("buddy/alloc+free/order {order}"), BATCH, || {
+            for _ in 0..BATCH {
+                let ptr = buddy.allocate(black_box(layout)).unwrap();
+                unsafe { buddy.deallocate(black_box(ptr), layout) }.unwrap();
+            }
+        });
+    }
+
+    // Live blocks keep the buddies apart, so frees coalesce only at the end
+    let mut buddy = region.buddy();
+    let layout = layout(1 << 6);
+    let mut live = Vec::with_capacity(BATCH);
+    b.bench("buddy/fill+drain/order 6", 2 * BATCH, || {
+        for _ in 0..BATCH {
+            live.push(buddy.allocate(layout).unwrap());
+        }
+        for ptr in live.drain(..) {
+            unsafe { buddy.deallocate(ptr, layout) }.unwrap();
+        }
+    });
+}
+
+/// Allocates or frees at random, keeping up to `max_live` blocks with sizes from `size`.
+fn churn(
+    allocator: &mut HybridAllocator,
+    rng: &mut Rng,
+    live: &mut Vec<(NonNull<u8>, Layout)>,
+    max_live: usize,
+    size: impl Fn(&mut Rng) -> usize,
+) {
+    if live.len() < max_live && (live.is_empty() || rng.below(2) == 0) {
+        let layout = layout(size(rng));
+        if let Ok(ptr) = allocator.allocate(layout) {
+            live.push((ptr, layout));
+        }
+    } else {
+        let (ptr, layout) = live.swap_remove(rng.below(live.len()));
+        unsafe { allocator.deallocate(ptr, layout) }.unwrap();
+    }
+}
+
+fn free_all(allocator: &mut HybridAllocator, live: &mut Vec<(NonNull<u8>, Layout)>) {
+    for (ptr, layout) in live.drain(..) {
+        unsafe { allocator.deallocate(ptr, layout) }.unwrap();
+    }
+}
+
+fn bench_hybrid(b: &Bencher) {
+    let region = Region::new(HEAP_SIZE);
+
+    // Mostly small objects, with the occasional buffer, as in the kernel
+    let mut hybrid = region.hybrid();
+    let mut rng = Rng::new();
+    let mut live = Vec::new();
+    b.bench("hybrid/mixed", BATCH, || {
+        for _ in 0..BATCH {
+            churn(&mut hybrid, &mut rng, &mut live, 1024, |rng| {
+                if rng.below(10) == 0 {
+                    1024 << rng.below(4)
+                } else {
+                    8 << rng.below(7)
+                }
+            });
+        }
+    });
+    free_all(&mut hybrid, &mut live);
+
+    let mut hybrid = region.hybrid();
+    b.bench("hybrid/random sizes", BATCH, || {
+        for _ in 0..BATCH {
+            churn(&mut hybrid, &mut rng, &mut live, 1024, |rng| {
+                1 + rng.below(8192)
+            });
+        }
+    });
+    free_all(&mut hybrid, &mut live);
+}
+
+/// Measures how much of the heap is live when an allocation first fails,
+/// under random sizes with random frees.
+fn bench_fragmentation(b: &Bencher) {
+    const SIZE: usize = 1024 * 1024;
+
+    let region = Region::new(SIZE);
+    let mut hybrid = region.hybrid();
+    let mut rng = Rng::new();
+    let mut live: Vec<(NonNull<u8>, Layout)> = Vec::new();
+    let mut live_bytes = 0;
+
+    loop {
+        // Grow twice as often as shrinking, so that the heap fills up
+        if live.is_empty() || rng.below(3) != 0 {
+            let layout = layout(1 + rng.below(4096));
+            let Ok(ptr) = hybrid.allocate(layout) else {
+                break;
+            };
+            live.push((ptr, layout));
+            live_bytes += layout.size();
+        } else {
+            let (ptr, layout) = live.swap_remove(rng.below(live.len()));
+            unsafe { hybrid.deallocate(ptr, layout) }.unwrap();
+            live_bytes -= layout.size();
+        }
+    }
+
+    if !b.quick {
+        #[expect(clippy::cast_precision_loss, reason = "Sizes are small")]
+        let ratio = live_bytes as f64 / SIZE as f64;
+        println!(
+            "{:<30} {:>7.1}% of the heap live at the first failure",
+            "fragmentation/random sizes",
+            ratio * 100.0
+        );
+    }
+    free_all(&mut hybrid, &mut live);
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`, `cargo test` does not
+    let b = Bencher {
+        quick: !std::env::args().any(|arg| arg == "--bench"),
+    };
+
+    bench_slab(&b);
+    bench_buddy(&b);
+    bench_hybrid(&b);
+    bench_fragmentation(&b);
+}
@@ -148,6 +148,13 @@ impl BuddyAllocator {
         Ok(())
     }
 
+    /// Check if a pointer lies within the heap region
+    #[must_use]
+    pub fn contains(&self, ptr: NonNull<u8>) -> bool {
+        let ptr = ptr.as_ptr();
+        ptr >= self.heap_start && ptr < self.heap_start.wrapping_add(self.heap_size)
+    }
+
     /// Find and allocate a block of the given order
     fn find_block(&mut self, order: usize) -> Result<*mut u8> {
         // Try to find a block in the current order
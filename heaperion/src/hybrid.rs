@@ -15,6 +15,88 @@ use crate::slab::SlabAllocator;
 /// Allocations smaller than this use slab, larger use buddy
 const SLAB_THRESHOLD: usize = 512;
 
+#[cfg(feature = "debug")]
+/// Byte pattern written over freed blocks when poisoning is enabled
+pub const POISON_BYTE: u8 = 0xDE;
+
+#[cfg(feature = "debug")]
+/// Maximum number of freed slab blocks that can be held in quarantine
+pub const MAX_QUARANTINE: usize = 32;
+
+#[cfg(feature = "debug")]
+/// FIFO of freed slab blocks that are not yet handed back to the slab
+///
+/// Delaying reuse makes stale pointers more likely to observe the poison pattern
+/// instead of a freshly allocated object.
+struct Quarantine {
+    /// Ring buffer of quarantined blocks
+    slots: [Option<NonNull<u8>>; MAX_QUARANTINE],
+    /// Index of the oldest block
+    head: usize,
+    /// Number of quarantined blocks
+    len: usize,
+    /// Maximum number of blocks held before the oldest is released
+    capacity: usize,
+}
+
+#[cfg(feature = "debug")]
+impl Quarantine {
+    #[must_use]
+    #[inline]
+    const fn new(capacity: usize) -> Self {
+        Self {
+            slots: [None; MAX_QUARANTINE],
+            head: 0,
+            len: 0,
+            capacity: if capacity < MAX_QUARANTINE {
+                capacity
+            } else {
+                MAX_QUARANTINE
+            },
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    const fn is_enabled(&self) -> bool {
+        self.capacity != 0
+    }
+
+    #[must_use]
+    fn contains(&self, ptr: NonNull<u8>) -> bool {
+        (0..self.len).any(|i| self.slots[(self.head + i) % MAX_QUARANTINE] == Some(ptr))
+    }
+
+    /// Push a block into the quarantine, returning the evicted block if it was full
+    const fn push(&mut self, ptr: NonNull<u8>) -> Option<NonNull<u8>> {
+        let evicted = if self.len == self.capacity {
+            self.pop()
+        } else {
+            None
+        };
+        self.slots[(self.head + self.len) % MAX_QUARANTINE] = Some(ptr);
+        self.len += 1;
+        evicted
+    }
+
+    /// Remove the oldest block from the quarantine
+    const fn pop(&mut self) -> Option<NonNull<u8>> {
+        if self.len == 0 {
+            return None;
+        }
+        let ptr = self.slots[self.head].take();
+        self.head = (self.head + 1) % MAX_QUARANTINE;
+        self.len -= 1;
+        ptr
+    }
+}
+
+// SAFETY: Quarantined blocks are owned by the allocator and never shared.
+#[cfg(feature = "debug")]
+unsafe impl Send for Quarantine {}
+#[cfg(feature = "debug")]
+unsafe impl Sync for Quarantine {}
+
 /// A hybrid allocator that combines slab and buddy allocators
 ///
 /// This allocator provides the best of both worlds:
@@ -26,6 +108,12 @@ pub struct HybridAllocator {
     slab: SlabAllocator,
     /// Buddy allocator for large allocations
     buddy: BuddyAllocator,
+    #[cfg(feature = "debug")]
+    /// Whether freed blocks are filled with `POISON_BYTE`
+    poison: bool,
+    #[cfg(feature = "debug")]
+    /// Freed slab blocks awaiting reuse
+    quarantine: Quarantine,
 }
 
 impl HybridAllocator {
@@ -52,9 +140,30 @@ impl HybridAllocator {
         Ok(Self {
             slab: unsafe { SlabAllocator::new(heap_start, slab_size) }?,
             buddy: unsafe { BuddyAllocator::new(buddy_start, buddy_size) }?,
+            #[cfg(feature = "debug")]
+            poison: false,
+            #[cfg(feature = "debug")]
+            quarantine: Quarantine::new(0),
         })
     }
 
+    /// Enable the use-after-free debugging mode
+    ///
+    /// When `poison` is set, freed blocks are filled with `POISON_BYTE`.
+    /// Up to `quarantine` freed slab blocks (capped at `MAX_QUARANTINE`) are held back
+    /// before being reused, so that stale pointers keep observing the poison pattern.
+    ///
+    /// Only available with the `debug` feature, so that the default allocator
+    /// carries neither the state nor the checks.
+    #[cfg(feature = "debug")]
+    #[must_use]
+    #[inline]
+    pub const fn with_debug(mut self, poison: bool, quarantine: usize) -> Self {
+        self.poison = poison;
+        self.quarantine = Quarantine::new(quarantine);
+        self
+    }
+
     /// Allocate memory with the given layout
     ///
     /// Small allocations (< 512 bytes) are handled by the slab allocator
@@ -75,6 +184,14 @@ impl HybridAllocator {
             if let Ok(ptr) = self.slab.allocate(layout) {
                 return Ok(ptr);
             }
+            // Release quarantined blocks before falling back to buddy
+            #[cfg(feature = "debug")]
+            if self.quarantine.is_enabled()
+                && self.flush_quarantine()
+                && let Ok(ptr) = self.slab.allocate(layout)
+            {
+                return Ok(ptr);
+            }
             // Fall back to buddy if slab is full
         }
 
@@ -95,7 +212,14 @@ impl HybridAllocator {
     /// # Errors
     ///
     /// - `HeapError::InvalidPointer` if the pointer was not allocated by this allocator
+    /// - `HeapError::DoubleFree` if the pointer is already in quarantine, with the `debug` feature
     pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) -> Result<()> {
+        #[cfg(feature = "debug")]
+        if self.poison || self.quarantine.is_enabled() {
+            // SAFETY: Forwarded from the caller
+            return unsafe { self.deallocate_debug(ptr, layout) };
+        }
+
         // Try to deallocate from slab first for small sizes
         if layout.size() <= SLAB_THRESHOLD && layout.size() <= self.slab.max_size() {
             // SAFETY: Caller guarantees ptr was allocated by us
@@ -108,6 +232,65 @@ impl HybridAllocator {
         // SAFETY: Caller guarantees ptr was allocated by us
         unsafe { self.buddy.deallocate(ptr, layout) }
     }
+
+    #[cfg(feature = "debug")]
+    /// Deallocate memory in debug mode, poisoning and quarantining the block
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as `deallocate`.
+    unsafe fn deallocate_debug(&mut self, ptr: NonNull<u8>, layout: Layout) -> Result<()> {
+        let in_slab = layout.size() <= SLAB_THRESHOLD
+            && layout.size() <= self.slab.max_size()
+            && self.slab.contains(ptr);
+
+        if !in_slab && !self.buddy.contains(ptr) {
+            return Err(HeapError::InvalidPointer);
+        }
+        if self.quarantine.contains(ptr) {
+            return Err(HeapError::DoubleFree);
+        }
+
+        if self.poison {
+            // SAFETY: The block belongs to the heap and is at least `layout.size()` bytes long
+            unsafe { ptr.as_ptr().write_bytes(POISON_BYTE, layout.size()) };
+        }
+
+        if in_slab && self.quarantine.is_enabled() {
+            if let Some(evicted) = self.quarantine.push(ptr) {
+                // SAFETY: Quarantined blocks were allocated by the slab and not yet freed
+                unsafe { self.slab.deallocate(evicted) }?;
+            }
+            return Ok(());
+        }
+
+        if in_slab {
+            // SAFETY: Caller guarantees ptr was allocated by us
+            unsafe { self.slab.deallocate(ptr) }
+        } else {
+            // SAFETY: Caller guarantees ptr was allocated by us
+            unsafe { self.buddy.deallocate(ptr, layout) }
+        }
+    }
+
+    /// Check if a pointer lies within the heap region
+    #[must_use]
+    pub fn contains(&self, ptr: NonNull<u8>) -> bool {
+        self.slab.contains(ptr) || self.buddy.contains(ptr)
+    }
+
+    #[cfg(feature = "debug")]
+    /// Release every quarantined block back to the slab allocator
+    ///
+    /// Returns `true` if at least one block was released.
+    fn flush_quarantine(&mut self) -> bool {
+        let mut released = false;
+        while let Some(ptr) = self.quarantine.pop() {
+            // SAFETY: Quarantined blocks were allocated by the slab and not yet freed
+            released |= unsafe { self.slab.deallocate(ptr) }.is_ok();
+        }
+        released
+    }
 }
 
 #[cfg(test)]
@@ -235,6 +418,66 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "debug")]
+    #[test]
+    fn test_hybrid_debug_poison() {
+        let mut buffer = alloc::vec![0u8; 32_768];
+        let mut allocator = unsafe { HybridAllocator::new(buffer.as_mut_ptr(), buffer.len()) }
+            .unwrap()
+            .with_debug(true, 4);
+
+        for size in [64, 2048] {
+            let layout = Layout::from_size_align(size, 8).unwrap();
+            let ptr = allocator.allocate(layout).unwrap();
+            unsafe { ptr.as_ptr().write_bytes(0xAA, size) };
+
+            unsafe { allocator.deallocate(ptr, layout).unwrap() };
+
+            // Buddy blocks reuse their first bytes for the free list link
+            let skip = if size <= SLAB_THRESHOLD { 0 } else { 16 };
+            let block = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), size) };
+            assert!(block[skip..].iter().all(|&b| b == POISON_BYTE));
+        }
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn test_hybrid_debug_quarantine() {
+        let mut buffer = alloc::vec![0u8; 32_768];
+        let mut allocator = unsafe { HybridAllocator::new(buffer.as_mut_ptr(), buffer.len()) }
+            .unwrap()
+            .with_debug(false, 2);
+
+        let layout = Layout::from_size_align(32, 8).unwrap();
+        let first = allocator.allocate(layout).unwrap();
+        unsafe { allocator.deallocate(first, layout).unwrap() };
+
+        // The quarantined block must not be handed out again right away
+        let second = allocator.allocate(layout).unwrap();
+        assert_ne!(first, second);
+
+        let res = unsafe { allocator.deallocate(first, layout) };
+        assert_eq!(res, Err(HeapError::DoubleFree));
+
+        unsafe { allocator.deallocate(second, layout).unwrap() };
+    }
+
+    #[test]
+    fn test_hybrid_contains() {
+        let mut buffer = alloc::vec![0u8; 16_384];
+        let mut other = alloc::vec![0u8; 16];
+        let mut allocator =
+            unsafe { HybridAllocator::new(buffer.as_mut_ptr(), buffer.len()) }.unwrap();
+
+        let small = allocator.allocate(Layout::new::<u64>()).unwrap();
+        let large = allocator
+            .allocate(Layout::from_size_align(2048, 8).unwrap())
+            .unwrap();
+        assert!(allocator.contains(small));
+        assert!(allocator.contains(large));
+        assert!(!allocator.contains(NonNull::new(other.as_mut_ptr()).unwrap()));
+    }
+
     #[test]
     fn test_hybrid_zero_size() {
         let mut buffer = alloc::vec![0u8; 16_384];
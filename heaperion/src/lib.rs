@@ -42,6 +42,12 @@
 //!     allocator.deallocate(ptr, layout).unwrap();
 //! }
 //! ```
+//!
+//! ### Debugging
+//!
+//! To track down use-after-free bugs, the `debug` feature provides `HybridAllocator::with_debug`,
+//! which fills freed blocks with `POISON_BYTE` and delays the reuse of small blocks
+//! through a quarantine.
 #![warn(clippy::pedantic, clippy::nursery)]
 #![forbid(unsafe_op_in_unsafe_fn)]
 #![no_std]
@@ -56,6 +62,8 @@ mod utils;
 pub use buddy::BuddyAllocator;
 pub use error::{HeapError, Result};
 pub use hybrid::HybridAllocator;
+#[cfg(feature = "debug")]
+pub use hybrid::{MAX_QUARANTINE, POISON_BYTE};
 pub use slab::SlabAllocator;
 
 /// The main heap allocator type
@@ -311,6 +311,15 @@ impl SlabAllocator {
         false
     }
 
+    /// Check if a pointer lies within one of the slabs
+    #[must_use]
+    pub fn contains(&self, ptr: NonNull<u8>) -> bool {
+        self.slabs
+            .iter()
+            .flatten()
+            .any(|slab| slab.contains(ptr.as_ptr()))
+    }
+
     /// Get the maximum size that can be allocated by the slab allocator
     #[must_use]
     pub const fn max_size(&self) -> usize {
@@ -6,7 +6,9 @@
 //! ## Modules
 //!
 //! - `mcs` : Provides an implementation of the MCS lock.
+//! - `order` : Tracks the order of lock acquisitions to find potential deadlocks in debug builds.
 //! - `rw` : Provides an implementation of the read-write lock.
+//! - `seq` : Provides an implementation of the sequence lock.
 //! - `ticket` : Provides an implementation of the ticket lock.
 //!
 //! ## Relax Strategy
@@ -19,9 +21,14 @@
 //! is unable to acquire a lock.
 
 pub mod mcs;
+pub mod order;
 pub mod rw;
+pub mod seq;
 pub mod ticket;
 
+#[cfg(test)]
+mod stress;
+
 /// A trait that defines a relax strategy for locks.
 ///
 /// This trait is used to define how a thread should behave when it
@@ -177,11 +177,15 @@ impl<T, R: RelaxStrategy> McsLock<T, R> {
 
 impl<T: ?Sized, R: RelaxStrategy> McsLock<T, R> {
     #[must_use]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Locks the MCS lock and returns a guard.
     ///
     /// For single operations, prefer `with_locked`.
     /// This function allows for a more fine-grained control over the duration of the lock.
     pub fn lock<'s, 'node>(&'s self, node: &'node mut McsNode) -> McsGuard<'node, 's, T, R> {
+        #[cfg(debug_assertions)]
+        super::order::on_acquire(self, core::panic::Location::caller());
+
         // Assert the node is ready to be used
         node.locked.store(true, Ordering::Relaxed);
         node.set_next(ptr::null_mut());
@@ -206,6 +210,7 @@ impl<T: ?Sized, R: RelaxStrategy> McsLock<T, R> {
     }
 
     #[must_use]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Tries to lock the MCS lock and returns a guard.
     /// If it is already in use, does nothing.
     ///
@@ -224,6 +229,9 @@ impl<T: ?Sized, R: RelaxStrategy> McsLock<T, R> {
             .compare_exchange(ptr::null_mut(), node, Ordering::Acquire, Ordering::Relaxed)
             .ok()?;
 
+        #[cfg(debug_assertions)]
+        super::order::on_try_acquire(self, core::panic::Location::caller());
+
         Some(McsGuard {
             lock: self,
             node: ptr::from_ref(node),
@@ -232,6 +240,7 @@ impl<T: ?Sized, R: RelaxStrategy> McsLock<T, R> {
     }
 
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Locks the lock and calls the closure with the guard.
     pub fn with_locked<F, U>(&self, f: F) -> U
     where
@@ -243,6 +252,7 @@ impl<T: ?Sized, R: RelaxStrategy> McsLock<T, R> {
     }
 
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Locks the lock and calls the closure with the guard.
     pub fn try_with_locked<F, U>(&self, f: F) -> Option<U>
     where
@@ -319,6 +329,9 @@ impl<T: ?Sized, R: RelaxStrategy> DerefMut for McsGuard<'_, '_, T, R> {
 
 impl<T: ?Sized, R: RelaxStrategy> Drop for McsGuard<'_, '_, T, R> {
     fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        super::order::on_release(self.lock);
+
         // Safety: node pointer is always valid for the duration of the guard
         let node = unsafe { &*self.node };
 
@@ -414,6 +427,7 @@ impl<T, R: RelaxStrategy> MUMcsLock<T, R> {
 
     #[must_use]
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Locks the lock and returns a guard.
     ///
     /// # Panics
@@ -429,6 +443,7 @@ impl<T, R: RelaxStrategy> MUMcsLock<T, R> {
     }
 
     #[must_use]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Tries to lock the lock and returns a guard.
     /// If it is already in use or isn't initialized, does nothing.
     ///
@@ -448,6 +463,7 @@ impl<T, R: RelaxStrategy> MUMcsLock<T, R> {
 
     #[must_use]
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Try to lock the lock if it has been initialized.
     /// Returns `None` if the lock has not been initialized.
     ///
@@ -456,10 +472,16 @@ impl<T, R: RelaxStrategy> MUMcsLock<T, R> {
         &'s self,
         node: &'node mut McsNode,
     ) -> Option<MUMcsGuard<'node, 's, T, R>> {
-        self.is_initialized().then(move || self.lock(node))
+        // Not a closure, so that the caller is tracked
+        if self.is_initialized() {
+            Some(self.lock(node))
+        } else {
+            None
+        }
     }
 
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Locks the lock and calls the closure with the guard.
     ///
     /// Panics if the lock is not initialized.
@@ -473,6 +495,7 @@ impl<T, R: RelaxStrategy> MUMcsLock<T, R> {
     }
 
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Try to lock the lock and call the closure with the guard if the lock
     /// is initialized.
     pub fn with_locked_if_init<F, U>(&self, f: F) -> Option<U>
@@ -484,6 +507,7 @@ impl<T, R: RelaxStrategy> MUMcsLock<T, R> {
     }
 
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     /// Try to lock the lock and call the closure with the guard if the lock
     /// is initialized.
     pub fn try_with_locked<F, U>(&self, f: F) -> Option<U>
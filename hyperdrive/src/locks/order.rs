@@ -0,0 +1,416 @@
+//! Lock-order tracking, to find potential deadlocks in debug builds.
+//!
+//! Every blocking acquisition of an `McsLock` or a `RwLock` is checked against the locks
+//! already held by the same context (usually a core). Acquiring `B` while holding `A`
+//! records the order "`A` before `B`". If `A` is later acquired while holding `B`,
+//! two contexts doing so at the same time would deadlock, so an `Inversion` is reported,
+//! once per pair of locks.
+//!
+//! The order is transitive only through direct pairs: `A` before `B` and `B` before `C`
+//! followed by `C` before `A` is not reported.
+//!
+//! Locks are identified by their address, so locks living on the stack may share an identity.
+//! Acquisitions with `try_lock` cannot block, so they are not checked, but the lock counts
+//! as held until it is released.
+//!
+//! Tracking is disabled until `install` is called, and compiled out of release builds.
+//!
+//! ```rust
+//! # use hyperdrive::locks::order::{Inversion, LockOrder};
+//! # use core::panic::Location;
+//! #
+//! let tracker = LockOrder::<1, 4, 16>::new();
+//! let (a, b) = (1, 2);
+//!
+//! tracker.acquire(0, a, Location::caller());
+//! assert!(tracker.acquire(0, b, Location::caller()).is_none());
+//! tracker.release(0, b);
+//! tracker.release(0, a);
+//!
+//! tracker.acquire(0, b, Location::caller());
+//! let inversion = tracker.acquire(0, a, Location::caller()).unwrap();
+//! assert_eq!(inversion.acquired.lock, a);
+//! assert_eq!(inversion.held.lock, b);
+//! ```
+use core::{
+    panic::Location,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// Identity of a lock, which is its address.
+///
+/// 0 is never a valid identity.
+pub type LockId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A lock acquisition.
+pub struct Site {
+    pub lock: LockId,
+    /// Where the lock was acquired.
+    pub location: &'static Location<'static>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Two locks acquired in both orders.
+pub struct Inversion {
+    /// The lock being acquired.
+    pub acquired: Site,
+    /// A lock held by the same context.
+    pub held: Site,
+    /// Where `acquired` was previously acquired before `held`, in this order.
+    pub previous: (Site, Site),
+}
+
+/// A slot holding a lock identity and the location of its acquisition.
+///
+/// Slots are claimed by swapping their lock from 0, which keeps them usable from
+/// interrupt handlers: a lock cannot be used to protect the tracker of locks.
+struct Slot {
+    lock: AtomicUsize,
+    location: AtomicPtr<Location<'static>>,
+}
+
+impl Slot {
+    const fn empty() -> Self {
+        Self {
+            lock: AtomicUsize::new(0),
+            location: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Claims the slot if it is empty.
+    fn claim(&self, site: Site) -> bool {
+        if self
+            .lock
+            .compare_exchange(0, site.lock, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        self.location
+            .store(ptr::from_ref(site.location).cast_mut(), Ordering::Release);
+        true
+    }
+
+    fn site(&self) -> Option<Site> {
+        let lock = self.lock.load(Ordering::Acquire);
+        // The location may not be written yet.
+        let location = unsafe { self.location.load(Ordering::Acquire).as_ref() }?;
+        (lock != 0).then_some(Site { lock, location })
+    }
+}
+
+/// "`before` was held while acquiring `after`".
+struct Edge {
+    before: Slot,
+    after: Slot,
+    reported: AtomicBool,
+}
+
+impl Edge {
+    const fn empty() -> Self {
+        Self {
+            before: Slot::empty(),
+            after: Slot::empty(),
+            reported: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Lock-order tracker for `CONTEXTS` contexts, each holding at most `HELD` locks,
+/// remembering at most `EDGES` ordered pairs of locks.
+///
+/// Locks acquired beyond these limits are not tracked.
+pub struct LockOrder<const CONTEXTS: usize, const HELD: usize, const EDGES: usize> {
+    held: [[Slot; HELD]; CONTEXTS],
+    edges: [Edge; EDGES],
+}
+
+impl<const CONTEXTS: usize, const HELD: usize, const EDGES: usize> Default
+    for LockOrder<CONTEXTS, HELD, EDGES>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CONTEXTS: usize, const HELD: usize, const EDGES: usize>
+    LockOrder<CONTEXTS, HELD, EDGES>
+{
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            held: [const { [const { Slot::empty() }; HELD] }; CONTEXTS],
+            edges: [const { Edge::empty() }; EDGES],
+        }
+    }
+
+    #[must_use]
+    /// Records a blocking acquisition of `lock` by `context`, before it waits for the lock.
+    ///
+    /// Returns the first inversion with a held lock that was not reported yet.
+    pub fn acquire(
+        &self,
+        context: usize,
+        lock: LockId,
+        location: &'static Location<'static>,
+    ) -> Option<Inversion> {
+        let held = self.held.get(context)?;
+        let acquired = Site { lock, location };
+
+        let mut inversion = None;
+        for held in held.iter().filter_map(Slot::site) {
+            // Reads of a lock may be nested.
+            if held.lock == lock {
+                continue;
+            }
+            if let Some(edge) = self.find_edge(lock, held.lock) {
+                if inversion.is_none()
+                    && !edge.reported.swap(true, Ordering::Relaxed)
+                    && let (Some(before), Some(after)) = (edge.before.site(), edge.after.site())
+                {
+                    inversion = Some(Inversion {
+                        acquired,
+                        held,
+                        previous: (before, after),
+                    });
+                }
+            } else if self.find_edge(held.lock, lock).is_none() {
+                self.add_edge(held, acquired);
+            }
+        }
+
+        self.hold(context, acquired);
+        inversion
+    }
+
+    /// Records an acquisition of `lock` by `context` that did not wait, such as `try_lock`.
+    pub fn try_acquire(&self, context: usize, lock: LockId, location: &'static Location<'static>) {
+        self.hold(context, Site { lock, location });
+    }
+
+    /// Records that `lock` was released by `context`.
+    ///
+    /// If `context` does not hold it, the lock is released from the context holding it,
+    /// as a thread may release a lock on another core than the one it acquired it on.
+    pub fn release(&self, context: usize, lock: LockId) {
+        let release = |slots: &[Slot]| {
+            slots.iter().any(|slot| {
+                slot.lock
+                    .compare_exchange(lock, 0, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+            })
+        };
+
+        if self.held.get(context).is_some_and(|slots| release(slots)) {
+            return;
+        }
+        for slots in &self.held {
+            if release(slots) {
+                return;
+            }
+        }
+    }
+
+    fn hold(&self, context: usize, site: Site) {
+        if let Some(slots) = self.held.get(context) {
+            let _ = slots.iter().any(|slot| slot.claim(site));
+        }
+    }
+
+    fn find_edge(&self, before: LockId, after: LockId) -> Option<&Edge> {
+        self.edges.iter().find(|edge| {
+            edge.before.lock.load(Ordering::Acquire) == before
+                && edge.after.lock.load(Ordering::Acquire) == after
+        })
+    }
+
+    fn add_edge(&self, before: Site, after: Site) {
+        // The edge only matches once both ends are written.
+        if let Some(edge) = self.edges.iter().find(|edge| edge.after.claim(after)) {
+            edge.before.claim(before);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Hooks connecting the tracker to its environment.
+pub struct Hooks {
+    /// Returns the current context, such as the ID of the current core.
+    ///
+    /// It must not acquire tracked locks.
+    pub context: fn() -> usize,
+    /// Reports a potential deadlock, for instance by logging it.
+    pub report: fn(&Inversion),
+}
+
+#[cfg(debug_assertions)]
+/// Maximum number of contexts, such as cores, tracked by the global tracker.
+pub const MAX_CONTEXTS: usize = 64;
+
+#[cfg(debug_assertions)]
+static HOOKS: crate::once::Once<Hooks> = crate::once::Once::uninit();
+
+#[cfg(debug_assertions)]
+static TRACKER: LockOrder<MAX_CONTEXTS, 32, 512> = LockOrder::new();
+
+/// Enables the tracking of the order of lock acquisitions.
+///
+/// Only the first call has an effect, and it does nothing in release builds.
+#[cfg_attr(
+    not(debug_assertions),
+    expect(clippy::missing_const_for_fn, reason = "Not const in debug builds")
+)]
+pub fn install(hooks: Hooks) {
+    #[cfg(debug_assertions)]
+    HOOKS.call_once(|| hooks);
+    #[cfg(not(debug_assertions))]
+    let _ = hooks;
+}
+
+#[cfg(debug_assertions)]
+#[inline]
+/// Tracks a blocking acquisition, before the lock is waited for.
+pub(crate) fn on_acquire<T: ?Sized>(lock: &T, location: &'static Location<'static>) {
+    let Some(hooks) = HOOKS.get() else {
+        return;
+    };
+    let lock = ptr::from_ref(lock).cast::<()>().addr();
+    if let Some(inversion) = TRACKER.acquire((hooks.context)(), lock, location) {
+        (hooks.report)(&inversion);
+    }
+}
+
+#[cfg(debug_assertions)]
+#[inline]
+/// Tracks an acquisition that did not wait.
+pub(crate) fn on_try_acquire<T: ?Sized>(lock: &T, location: &'static Location<'static>) {
+    if let Some(hooks) = HOOKS.get() {
+        let lock = ptr::from_ref(lock).cast::<()>().addr();
+        TRACKER.try_acquire((hooks.context)(), lock, location);
+    }
+}
+
+#[cfg(debug_assertions)]
+#[inline]
+pub(crate) fn on_release<T: ?Sized>(lock: &T) {
+    if let Some(hooks) = HOOKS.get() {
+        let lock = ptr::from_ref(lock).cast::<()>().addr();
+        TRACKER.release((hooks.context)(), lock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Tracker = LockOrder<2, 4, 8>;
+
+    #[test]
+    fn test_consistent_order() {
+        let tracker = Tracker::new();
+        let here = Location::caller();
+
+        for context in [0, 1] {
+            assert!(tracker.acquire(context, 1, here).is_none());
+            assert!(tracker.acquire(context, 2, here).is_none());
+            assert!(tracker.acquire(context, 3, here).is_none());
+            tracker.release(context, 3);
+            tracker.release(context, 2);
+            tracker.release(context, 1);
+        }
+
+        // Nested reads of a lock are allowed
+        assert!(tracker.acquire(0, 1, here).is_none());
+        assert!(tracker.acquire(0, 1, here).is_none());
+    }
+
+    #[test]
+    fn test_inversion_reported() {
+        let tracker = Tracker::new();
+        let first = Location::caller();
+        let second = Location::caller();
+
+        assert!(tracker.acquire(0, 1, first).is_none());
+        assert!(tracker.acquire(0, 2, second).is_none());
+        tracker.release(0, 2);
+        tracker.release(0, 1);
+
+        // Another context takes the locks in the other order
+        let inversion_site = Location::caller();
+        assert!(tracker.acquire(1, 2, inversion_site).is_none());
+        let inversion = tracker.acquire(1, 1, inversion_site).unwrap();
+        assert_eq!(
+            inversion.acquired,
+            Site {
+                lock: 1,
+                location: inversion_site
+            }
+        );
+        assert_eq!(inversion.held.lock, 2);
+        assert_eq!(
+            inversion.previous,
+            (
+                Site {
+                    lock: 1,
+                    location: first
+                },
+                Site {
+                    lock: 2,
+                    location: second
+                }
+            )
+        );
+        tracker.release(1, 1);
+        tracker.release(1, 2);
+
+        // An inversion is only reported once
+        assert!(tracker.acquire(0, 2, first).is_none());
+        assert!(tracker.acquire(0, 1, first).is_none());
+    }
+
+    #[test]
+    fn test_try_lock_and_migration() {
+        let tracker = Tracker::new();
+        let here = Location::caller();
+
+        // A lock taken without waiting still counts as held
+        tracker.try_acquire(0, 1, here);
+        assert!(tracker.acquire(0, 2, here).is_none());
+        tracker.release(0, 2);
+
+        // The holder of lock 1 moved to the other context before releasing it
+        tracker.release(1, 1);
+        assert!(tracker.acquire(0, 3, here).is_none());
+        assert!(tracker.acquire(0, 1, here).is_none());
+        tracker.release(0, 1);
+        tracker.release(0, 3);
+
+        // Lock 1 was held while taking lock 2
+        assert!(tracker.acquire(1, 2, here).is_none());
+        assert!(tracker.acquire(1, 1, here).is_some());
+    }
+
+    #[test]
+    fn test_limits() {
+        let tracker = LockOrder::<1, 2, 1>::new();
+        let here = Location::caller();
+
+        // Unknown contexts are not tracked
+        assert!(tracker.acquire(5, 1, here).is_none());
+        tracker.release(5, 1);
+
+        assert!(tracker.acquire(0, 1, here).is_none());
+        assert!(tracker.acquire(0, 2, here).is_none());
+        // Neither are locks beyond the limits
+        assert!(tracker.acquire(0, 3, here).is_none());
+        tracker.release(0, 3);
+        tracker.release(0, 2);
+        tracker.release(0, 1);
+
+        assert!(tracker.acquire(0, 3, here).is_none());
+        assert!(tracker.acquire(0, 1, here).is_none());
+    }
+}
@@ -88,13 +88,19 @@ impl<T, R: RelaxStrategy> RwLock<T, R> {
 
 impl<T: ?Sized, R: RelaxStrategy> RwLock<T, R> {
     #[must_use]
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn read(&self) -> ReadGuard<'_, T, R> {
+        #[cfg(debug_assertions)]
+        super::order::on_acquire(self, core::panic::Location::caller());
         self.state.read_lock();
         ReadGuard { lock: self }
     }
 
     #[must_use]
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn write(&self) -> WriteGuard<'_, T, R> {
+        #[cfg(debug_assertions)]
+        super::order::on_acquire(self, core::panic::Location::caller());
         self.state.write_lock();
         WriteGuard { lock: self }
     }
@@ -116,6 +122,8 @@ impl<T: ?Sized, R: RelaxStrategy> core::ops::Deref for ReadGuard<'_, T, R> {
 impl<T: ?Sized, R: RelaxStrategy> Drop for ReadGuard<'_, T, R> {
     fn drop(&mut self) {
         self.lock.state.read_unlock();
+        #[cfg(debug_assertions)]
+        super::order::on_release(self.lock);
     }
 }
 
@@ -144,6 +152,8 @@ impl<T: ?Sized, R: RelaxStrategy> core::ops::DerefMut for WriteGuard<'_, T, R> {
 impl<T: ?Sized, R: RelaxStrategy> Drop for WriteGuard<'_, T, R> {
     fn drop(&mut self) {
         self.lock.state.write_unlock();
+        #[cfg(debug_assertions)]
+        super::order::on_release(self.lock);
     }
 }
 
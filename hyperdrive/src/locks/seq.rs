@@ -0,0 +1,166 @@
+//! Sequence lock.
+//!
+//! A sequence lock protects small `Copy` data that is read much more often than written.
+//! Readers never block the writer: they retry if a write happened while they were reading.
+//!
+//! The lock has a stable layout, so that it can be read from another address space,
+//! for instance from a page mapped read-only into userspace.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use hyperdrive::locks::seq::SeqLock;
+//! #
+//! let lock = SeqLock::new((0_u64, 0_u64));
+//!
+//! lock.write((1, 2));
+//! assert_eq!(lock.read(), (1, 2));
+//! ```
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU64, Ordering, fence},
+};
+
+#[repr(C)]
+/// A sequence lock.
+pub struct SeqLock<T: Copy> {
+    /// Incremented before and after every write, so that it is odd while a write is in progress.
+    seq: AtomicU64,
+    data: UnsafeCell<T>,
+}
+
+// Safety: Data is only written under the sequence number, and reads are checked against it.
+unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+impl<T: Copy> SeqLock<T> {
+    #[must_use]
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            seq: AtomicU64::new(0),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    #[must_use]
+    /// Reads the value, retrying until no write happens during the read.
+    pub fn read(&self) -> T {
+        loop {
+            if let Some(value) = self.try_read() {
+                return value;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    #[must_use]
+    /// Reads the value, or returns `None` if a write happened during the read.
+    pub fn try_read(&self) -> Option<T> {
+        let start = self.seq.load(Ordering::Acquire);
+        if start % 2 == 1 {
+            return None;
+        }
+
+        // Safety: The pointer is valid. The copy may be torn by a concurrent write,
+        // which is why it is kept uninitialized until the sequence number is checked.
+        let value = unsafe { self.data.get().cast::<MaybeUninit<T>>().read_volatile() };
+
+        fence(Ordering::Acquire);
+        if self.seq.load(Ordering::Relaxed) != start {
+            return None;
+        }
+
+        // Safety: No write happened during the copy, so it is a valid value.
+        Some(unsafe { value.assume_init() })
+    }
+
+    /// Writes the value.
+    ///
+    /// Concurrent writers are serialized.
+    pub fn write(&self, value: T) {
+        let mut seq = self.seq.load(Ordering::Relaxed);
+        loop {
+            if seq % 2 == 1 {
+                core::hint::spin_loop();
+                seq = self.seq.load(Ordering::Relaxed);
+                continue;
+            }
+            // Making the sequence number odd excludes other writers.
+            match self
+                .seq
+                .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+        fence(Ordering::Release);
+
+        // Safety: Other writers are excluded, and readers discard what they read meanwhile.
+        unsafe { self.data.get().write_volatile(value) };
+
+        self.seq.store(seq + 2, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread::spawn;
+
+    #[test]
+    fn test_read_write() {
+        let lock = SeqLock::new(1_u32);
+        assert_eq!(lock.read(), 1);
+        assert_eq!(lock.try_read(), Some(1));
+
+        lock.write(2);
+        assert_eq!(lock.read(), 2);
+    }
+
+    #[test]
+    fn test_pending_write() {
+        let lock = SeqLock::new(1_u32);
+        lock.seq.store(1, Ordering::Relaxed);
+        assert_eq!(lock.try_read(), None);
+    }
+
+    #[test]
+    fn test_no_torn_reads() {
+        const WRITES: u64 = 10_000;
+
+        let lock = Arc::new(SeqLock::new([0_u64; 8]));
+
+        let writer = {
+            let lock = lock.clone();
+            spawn(move || {
+                for i in 1..=WRITES {
+                    lock.write([i; 8]);
+                }
+            })
+        };
+
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let lock = lock.clone();
+                spawn(move || {
+                    let mut last = 0;
+                    while last < WRITES {
+                        let value = lock.read();
+                        assert!(value.iter().all(|&v| v == value[0]));
+                        assert!(value[0] >= last);
+                        last = value[0];
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
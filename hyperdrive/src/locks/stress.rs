@@ -0,0 +1,301 @@
+//! Randomized stress tests of the locks, run on the host with `std` threads.
+//!
+//! Every thread runs a random schedule of operations, drawn from a generator seeded
+//! with the seed of the run and the index of the thread, so that a failing schedule
+//! can be replayed. The seed defaults to `SEED`, and can be set with the
+//! `HYPERDRIVE_STRESS_SEED` environment variable.
+//!
+//! Critical sections are entered through a `Sentinel`, which checks that no other thread
+//! is in a section it excludes.
+//!
+//! Waiting threads yield instead of spinning, so that a host with fewer cores than
+//! threads still runs the holder of the lock.
+use super::{
+    RelaxStrategy,
+    mcs::{MUMcsLock, McsLock, McsNode},
+    rw::RwLock,
+    seq::SeqLock,
+    ticket::TicketLock,
+};
+use std::sync::{
+    Barrier,
+    atomic::{AtomicU32, Ordering},
+};
+
+/// Default seed of the runs.
+const SEED: u64 = 0x5EED_BE5C_A205;
+
+#[cfg(not(miri))]
+const THREADS: usize = 8;
+#[cfg(miri)]
+const THREADS: usize = 3;
+
+/// Number of operations of every thread.
+#[cfg(not(miri))]
+const ITERATIONS: usize = 2_000;
+#[cfg(miri)]
+const ITERATIONS: usize = 20;
+
+/// A relax strategy that lets the holder of the lock run.
+struct Yield;
+
+impl RelaxStrategy for Yield {
+    fn relax() {
+        std::thread::yield_now();
+    }
+}
+
+/// A xorshift64* generator.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // SplitMix64 step, so that close seeds give unrelated sequences.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        // The state of xorshift must not be zero.
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Returns true with a probability of `percent`%.
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+/// Spins for a random short time, to vary the interleavings.
+fn jitter(rng: &mut Rng) {
+    for _ in 0..rng.below(64) {
+        core::hint::spin_loop();
+    }
+    if rng.chance(5) {
+        std::thread::yield_now();
+    }
+}
+
+/// Counts the threads in critical sections.
+///
+/// Shared sections count for one, exclusive sections for `EXCLUSIVE`.
+struct Sentinel(AtomicU32);
+
+impl Sentinel {
+    const EXCLUSIVE: u32 = 1 << 16;
+
+    const fn new() -> Self {
+        Self(AtomicU32::new(0))
+    }
+
+    fn exclusive<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = self.0.fetch_add(Self::EXCLUSIVE, Ordering::SeqCst);
+        assert_eq!(previous, 0, "Exclusive section entered by several threads");
+        let res = f();
+        self.0.fetch_sub(Self::EXCLUSIVE, Ordering::SeqCst);
+        res
+    }
+
+    fn shared<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = self.0.fetch_add(1, Ordering::SeqCst);
+        assert!(
+            previous < Self::EXCLUSIVE,
+            "Shared section entered during an exclusive one"
+        );
+        let res = f();
+        self.0.fetch_sub(1, Ordering::SeqCst);
+        res
+    }
+}
+
+fn seed() -> u64 {
+    std::env::var("HYPERDRIVE_STRESS_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(SEED)
+}
+
+/// Runs `op` `ITERATIONS` times on each of `THREADS` threads, started together.
+///
+/// # Panics
+///
+/// Panics with the seed of the run if any thread panics.
+fn stress<S: Sync>(shared: &S, op: impl Fn(&S, &mut Rng) + Sync) {
+    let seed = seed();
+    let barrier = Barrier::new(THREADS);
+
+    std::thread::scope(|scope| {
+        let handles = (0..THREADS)
+            .map(|i| {
+                let (barrier, op) = (&barrier, &op);
+                scope.spawn(move || {
+                    let mut rng = Rng::new(seed ^ i as u64);
+                    barrier.wait();
+                    for _ in 0..ITERATIONS {
+                        op(shared, &mut rng);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert!(
+                handle.join().is_ok(),
+                "Stress test failed on thread {i} with HYPERDRIVE_STRESS_SEED={seed}"
+            );
+        }
+    });
+}
+
+/// Total number of operations of a run.
+const fn total() -> u64 {
+    (THREADS * ITERATIONS) as u64
+}
+
+#[test]
+fn test_ticket_exclusion() {
+    let lock = TicketLock::<u64, Yield>::new(0);
+    let sentinel = Sentinel::new();
+
+    stress(&lock, |lock, rng| {
+        let mut guard = lock.lock();
+        sentinel.exclusive(|| {
+            *guard += 1;
+            jitter(rng);
+        });
+    });
+
+    assert_eq!(lock.into_inner(), total());
+}
+
+#[test]
+fn test_mcs_exclusion() {
+    let lock = McsLock::<u64, Yield>::new(0);
+    let sentinel = Sentinel::new();
+
+    stress(&lock, |lock, rng| {
+        let increment = |value: &mut u64, rng: &mut Rng| {
+            sentinel.exclusive(|| {
+                *value += 1;
+                jitter(rng);
+            });
+        };
+
+        match rng.below(3) {
+            0 => {
+                let mut node = McsNode::new();
+                let mut guard = lock.lock(&mut node);
+                increment(&mut guard, rng);
+            }
+            1 => lock.with_locked(|value| increment(value, rng)),
+            _ => {
+                // Retry until it succeeds, so that the total is known
+                while lock
+                    .try_with_locked(|value| increment(value, rng))
+                    .is_none()
+                {
+                    jitter(rng);
+                }
+            }
+        }
+    });
+
+    assert_eq!(lock.into_inner(), total());
+}
+
+#[test]
+fn test_mumcs_exclusion() {
+    let lock = MUMcsLock::<u64, Yield>::uninit();
+    lock.init(0);
+    let sentinel = Sentinel::new();
+
+    stress(&lock, |lock, rng| {
+        let increment = |value: &mut u64, rng: &mut Rng| {
+            sentinel.exclusive(|| {
+                *value += 1;
+                jitter(rng);
+            });
+        };
+
+        if rng.chance(50) {
+            lock.with_locked(|value| increment(value, rng));
+        } else {
+            while lock
+                .try_with_locked(|value| increment(value, rng))
+                .is_none()
+            {
+                jitter(rng);
+            }
+        }
+    });
+
+    assert_eq!(lock.into_inner(), Some(total()));
+}
+
+#[test]
+fn test_rw_exclusion() {
+    // Both halves are always equal outside of a write.
+    let lock = RwLock::<(u64, u64), Yield>::new((0, 0));
+    let sentinel = Sentinel::new();
+    let writes = AtomicU32::new(0);
+
+    stress(&lock, |lock, rng| {
+        if rng.chance(20) {
+            let mut guard = lock.write();
+            sentinel.exclusive(|| {
+                guard.0 += 1;
+                jitter(rng);
+                guard.1 += 1;
+            });
+            writes.fetch_add(1, Ordering::Relaxed);
+        } else {
+            let guard = lock.read();
+            sentinel.shared(|| {
+                let first = guard.0;
+                jitter(rng);
+                assert_eq!(first, guard.1, "Read a value being written");
+            });
+        }
+    });
+
+    let (first, second) = lock.into_inner();
+    assert_eq!(first, second);
+    assert_eq!(first, u64::from(writes.load(Ordering::Relaxed)));
+}
+
+#[test]
+fn test_seq_consistency() {
+    // Every element is always equal to the others outside of a write.
+    let lock = SeqLock::<[u64; 4]>::new([0; 4]);
+
+    stress(&lock, |lock, rng| {
+        if rng.chance(20) {
+            let value = rng.next_u64();
+            lock.write([value; 4]);
+        } else {
+            let value = if rng.chance(50) {
+                lock.read()
+            } else {
+                let Some(value) = lock.try_read() else {
+                    return;
+                };
+                value
+            };
+            assert!(
+                value.iter().all(|&v| v == value[0]),
+                "Read a torn value: {value:?}"
+            );
+        }
+        jitter(rng);
+    });
+}
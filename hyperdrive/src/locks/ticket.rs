@@ -167,4 +167,31 @@ mod tests {
         let guard = lock.lock();
         assert_eq!(*guard, nb_threads);
     }
+
+    #[test]
+    fn test_ticket_lock_fifo() {
+        let nb_threads = 8;
+        let lock = Arc::new(TestTicketLock::new(Vec::new()));
+
+        // Hold the lock while the threads queue up, one after the other
+        let guard = lock.lock();
+        let mut handles = Vec::new();
+        for i in 0..nb_threads {
+            let thread_lock = lock.clone();
+            handles.push(spawn(move || thread_lock.lock().push(i)));
+
+            // Wait for the thread to take its ticket
+            while lock.next_ticket.load(Ordering::Relaxed) != i + 2 {
+                std::thread::yield_now();
+            }
+        }
+        drop(guard);
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // The lock was acquired in the order the tickets were taken
+        assert_eq!(*lock.lock(), (0..nb_threads).collect::<Vec<_>>());
+    }
 }
@@ -295,6 +295,25 @@ impl<T> Once<T> {
             State::Poisoned => poisoned(),
         }
     }
+
+    #[track_caller]
+    /// Returns a reference to the value, initializing it with `initializer` if needed.
+    ///
+    /// The initializer runs at most once. Concurrent callers block until it completes,
+    /// and then get the same reference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if initialization failed, which poisons the value (see `get`).
+    pub fn get_or_init<F>(&self, initializer: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        self.call_once(initializer);
+        // Safety: `call_once` returns once the value is initialized, or being initialized
+        // by another thread, in which case `get` waits for it.
+        unsafe { self.get().unwrap_unchecked() }
+    }
 }
 
 impl<T> Drop for Once<T> {
@@ -467,6 +486,38 @@ mod test {
         let _ = once.get();
     }
 
+    #[test]
+    fn test_get_or_init_concurrent() {
+        static CALLS: AtomicU8 = AtomicU8::new(0);
+
+        let once = Arc::new(Once::uninit());
+        let num_threads = 8;
+        let barrier = Arc::new(Barrier::new(num_threads));
+
+        let handles = (0..num_threads)
+            .map(|_| {
+                let once = once.clone();
+                let barrier = barrier.clone();
+                spawn(move || {
+                    barrier.wait();
+                    let value = once.get_or_init(|| {
+                        CALLS.fetch_add(1, Ordering::Relaxed);
+                        // Leave time for the other threads to wait on the initialization
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                        42
+                    });
+                    assert_eq!(*value, 42);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        assert_eq!(once.get_or_init(|| 0), &42);
+    }
+
     #[test]
     fn test_call_once() {
         static COUNTER: AtomicU8 = AtomicU8::new(0);
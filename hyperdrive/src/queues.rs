@@ -4,10 +4,12 @@
 //!
 //! ## Modules
 //!
+//! - `deferred` : Queue of deferred work items.
 //! - `mpmc` : Multiple-producer multiple-consumer queue.
 //! - `mpsc` : Multiple-producer single-consumer queue.
 //! - `ring` : Ring queue backed by a fixed-size array.
 
+pub mod deferred;
 pub mod mpmc;
 pub mod mpsc;
 pub mod ring;
@@ -0,0 +1,252 @@
+//! A queue of deferred work items.
+//!
+//! Work is scheduled from contexts that must stay short, such as interrupt handlers,
+//! and run later from a context where it is safe to take longer.
+//! Scheduling does not allocate nor lock, it only pushes into a bounded ring.
+//!
+//! ## Ordering
+//!
+//! Work items of a queue run in the order they were scheduled (FIFO).
+//! Only one caller runs the queue at a time, so that two items of the same queue never
+//! run concurrently nor out of order.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! # use hyperdrive::queues::deferred::{DeferredQueue, Work};
+//! # use core::sync::atomic::{AtomicUsize, Ordering};
+//! #
+//! static SUM: AtomicUsize = AtomicUsize::new(0);
+//!
+//! fn add(value: usize) {
+//!     SUM.fetch_add(value, Ordering::Relaxed);
+//! }
+//!
+//! let queue = DeferredQueue::<8>::new();
+//! queue.schedule(Work::new(add, 1)).unwrap();
+//! queue.schedule(Work::new(add, 2)).unwrap();
+//!
+//! assert_eq!(queue.run(), 2);
+//! assert_eq!(SUM.load(Ordering::Relaxed), 3);
+//! ```
+use super::mpmc::{MpmcQueue, MpmcQueueFullError};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Copy)]
+/// A deferred work item: a function and the data it is called with.
+pub struct Work {
+    func: fn(usize),
+    data: usize,
+}
+
+impl Work {
+    #[must_use]
+    #[inline]
+    pub const fn new(func: fn(usize), data: usize) -> Self {
+        Self { func, data }
+    }
+
+    #[inline]
+    /// Runs the work item.
+    pub fn run(self) {
+        (self.func)(self.data);
+    }
+}
+
+#[derive(Debug)]
+/// A bounded FIFO queue of deferred work items.
+pub struct DeferredQueue<const SIZE: usize> {
+    queue: MpmcQueue<SIZE, Work>,
+    running: AtomicBool,
+}
+
+impl<const SIZE: usize> Default for DeferredQueue<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const SIZE: usize> DeferredQueue<SIZE> {
+    #[must_use]
+    #[inline]
+    /// Creates a new, empty deferred work queue.
+    ///
+    /// # Panics
+    ///
+    /// If `SIZE` is not greater than 0, this function will panic.
+    pub fn new() -> Self {
+        Self {
+            queue: MpmcQueue::new(),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    #[inline]
+    /// Schedules a work item, to run after all the ones already scheduled.
+    ///
+    /// # Errors
+    ///
+    /// If the queue is full, the work item is given back.
+    pub fn schedule(&self, work: Work) -> Result<(), Work> {
+        self.queue
+            .try_push(work)
+            .map_err(MpmcQueueFullError::into_inner)
+    }
+
+    /// Runs the scheduled work items, in order.
+    ///
+    /// Items scheduled while running are run as well, so that the queue is empty
+    /// when this function returns, unless another caller was already running it,
+    /// in which case nothing is done.
+    ///
+    /// Returns the number of items run.
+    pub fn run(&self) -> usize {
+        self.run_at_most(usize::MAX)
+    }
+
+    /// Runs at most `limit` of the scheduled work items, in order.
+    ///
+    /// The other items are left for the next run, so that a caller that cannot be
+    /// delayed for long, such as the return path of an interrupt, bounds its work.
+    /// If another caller was already running the queue, nothing is done.
+    ///
+    /// Returns the number of items run.
+    pub fn run_at_most(&self, limit: usize) -> usize {
+        if self
+            .running
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return 0;
+        }
+
+        let mut count = 0;
+        while count < limit
+            && let Some(work) = self.queue.pop()
+        {
+            work.run();
+            count += 1;
+        }
+
+        self.running.store(false, Ordering::Release);
+        count
+    }
+
+    #[must_use]
+    #[inline]
+    /// Checks if there is no work waiting to run.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_fifo() {
+        static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+        fn record(value: usize) {
+            ORDER.lock().unwrap().push(value);
+        }
+
+        let queue = DeferredQueue::<4>::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.run(), 0);
+
+        for i in 0..4 {
+            queue.schedule(Work::new(record, i)).unwrap();
+        }
+        // The queue is full
+        assert!(queue.schedule(Work::new(record, 4)).is_err());
+
+        assert_eq!(queue.run(), 4);
+        assert!(queue.is_empty());
+
+        // Wrap around the ring
+        queue.schedule(Work::new(record, 5)).unwrap();
+        queue.schedule(Work::new(record, 6)).unwrap();
+        assert_eq!(queue.run(), 2);
+
+        assert_eq!(*ORDER.lock().unwrap(), [0, 1, 2, 3, 5, 6]);
+    }
+
+    #[test]
+    fn test_schedule_while_running() {
+        static QUEUE: Mutex<Option<&'static DeferredQueue<4>>> = Mutex::new(None);
+        static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+        fn record(value: usize) {
+            ORDER.lock().unwrap().push(value);
+        }
+        fn chain(value: usize) {
+            record(value);
+            let queue = QUEUE.lock().unwrap().unwrap();
+            queue.schedule(Work::new(record, value + 10)).unwrap();
+            // Running from within a work item is a no-op
+            assert_eq!(queue.run(), 0);
+        }
+
+        let queue = Box::leak(Box::new(DeferredQueue::<4>::new()));
+        *QUEUE.lock().unwrap() = Some(queue);
+
+        queue.schedule(Work::new(chain, 1)).unwrap();
+        queue.schedule(Work::new(record, 2)).unwrap();
+
+        // Items scheduled by a running item come after the ones already queued
+        assert_eq!(queue.run(), 3);
+        assert!(queue.is_empty());
+        assert_eq!(*ORDER.lock().unwrap(), [1, 2, 11]);
+    }
+
+    #[test]
+    fn test_run_at_most() {
+        static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+        fn record(value: usize) {
+            ORDER.lock().unwrap().push(value);
+        }
+
+        let queue = DeferredQueue::<8>::new();
+        for i in 0..5 {
+            queue.schedule(Work::new(record, i)).unwrap();
+        }
+
+        // The rest is left for the next run, in order
+        assert_eq!(queue.run_at_most(2), 2);
+        assert_eq!(*ORDER.lock().unwrap(), [0, 1]);
+        assert!(!queue.is_empty());
+        assert_eq!(queue.run_at_most(0), 0);
+        assert_eq!(queue.run_at_most(8), 3);
+        assert!(queue.is_empty());
+        assert_eq!(*ORDER.lock().unwrap(), [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_run_while_busy() {
+        use std::sync::atomic::AtomicBool;
+
+        static DONE: AtomicBool = AtomicBool::new(false);
+        fn finish(_: usize) {
+            DONE.store(true, Ordering::Release);
+        }
+
+        let queue = DeferredQueue::<4>::new();
+        queue.schedule(Work::new(finish, 0)).unwrap();
+
+        // A thread that never gives the queue a chance to run,
+        // and only stops once the work item ran
+        std::thread::scope(|scope| {
+            let busy = scope.spawn(|| {
+                while !DONE.load(Ordering::Acquire) {
+                    core::hint::spin_loop();
+                }
+            });
+
+            // Another context, like the return path of the timer interrupt, runs it
+            assert_eq!(queue.run_at_most(1), 1);
+            busy.join().unwrap();
+        });
+        assert!(queue.is_empty());
+    }
+}
@@ -30,6 +30,15 @@ use core::{
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MpmcQueueFullError<T>(T);
 
+impl<T> MpmcQueueFullError<T> {
+    #[must_use]
+    #[inline]
+    /// Returns the value that could not be pushed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
 impl<T> core::fmt::Display for MpmcQueueFullError<T> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_str("MPMC queue buffer is full")
@@ -180,6 +189,28 @@ impl<const SIZE: usize, T> MpmcQueue<SIZE, T> {
         }
     }
 
+    /// Pushes a new value into the queue, evicting the oldest value if the queue is full.
+    ///
+    /// Returns the evicted value, if any.
+    pub fn force_push(&self, mut value: T) -> Option<T> {
+        let mut evicted = None;
+
+        loop {
+            match self.try_push(value) {
+                Ok(()) => break evicted,
+                Err(MpmcQueueFullError(v)) => {
+                    value = v;
+                    // Another consumer may have freed a slot in the meantime,
+                    // in which case there is nothing to evict.
+                    if let Some(old) = self.pop() {
+                        // Only the first eviction is reported, others are dropped.
+                        evicted.get_or_insert(old);
+                    }
+                }
+            }
+        }
+    }
+
     #[must_use]
     /// Pops a value from the queue.
     pub fn pop(&self) -> Option<T> {
@@ -305,6 +336,22 @@ mod tests {
         assert_eq!(res, Err(MpmcQueueFullError(4)));
     }
 
+    #[test]
+    fn test_mpmc_force_push() {
+        let mpmc = MpmcQueue::<3, usize>::new();
+
+        assert_eq!(mpmc.force_push(1), None);
+        assert_eq!(mpmc.force_push(2), None);
+        assert_eq!(mpmc.force_push(3), None);
+        assert_eq!(mpmc.force_push(4), Some(1));
+        assert_eq!(mpmc.force_push(5), Some(2));
+
+        assert_eq!(mpmc.pop(), Some(3));
+        assert_eq!(mpmc.pop(), Some(4));
+        assert_eq!(mpmc.pop(), Some(5));
+        assert!(mpmc.pop().is_none());
+    }
+
     #[test]
     #[cfg(miri)]
     /// Assert that we are not double dropping any elements.
@@ -0,0 +1,10 @@
+//! Links the kernel with `multiboot2.ld`, for it to be bootable by Multiboot2 loaders.
+use std::env::var;
+
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+    println!("cargo::rerun-if-changed=multiboot2.ld");
+
+    let manifest_dir = var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo::rustc-link-arg-bins=-T{manifest_dir}/multiboot2.ld");
+}
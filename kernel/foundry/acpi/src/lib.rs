@@ -18,8 +18,11 @@ use sdt::{
     hpet_table::ParsedHpetTable,
     madt::ParsedMadt,
     mcfg::{self, ParsedMcfg},
+    srat::ParsedSrat,
 };
 
+pub use rsdp::scan as scan_rsdp;
+
 static ACPI_REVISION: AcpiRevisionStorage = AcpiRevisionStorage::uninit();
 
 /// Advanced Configuration and Power Interface (ACPI) support.
@@ -28,6 +31,7 @@ pub struct Acpi<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>>
     fadt: ParsedFadt,
     hpet: Option<ParsedHpetTable>,
     mcfg: Option<ParsedMcfg>,
+    srat: Option<ParsedSrat>,
     dsdt: ParsedDsdt,
     _phantom: core::marker::PhantomData<M>,
 }
@@ -47,6 +51,7 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Acpi<M> {
         // TODO: Support multiple HPET blocks?
         let hpet_paddr = rsdt.locate_table(sdt::Signature::Hpet);
         let mcfg_paddr = rsdt.locate_table(sdt::Signature::Mcfg);
+        let srat_paddr = rsdt.locate_table(sdt::Signature::Srat);
 
         drop(rsdt);
 
@@ -54,6 +59,7 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Acpi<M> {
         let fadt = sdt::fadt::Fadt::<M>::load(fadt_paddr).parse();
         let hpet = hpet_paddr.map(|paddr| sdt::hpet_table::HpetTable::<M>::load(paddr).parse());
         let mcfg = mcfg_paddr.map(|paddr| mcfg::Mcfg::<M>::load(paddr).parse());
+        let srat = srat_paddr.map(|paddr| sdt::srat::Srat::<M>::load(paddr).parse());
 
         let dsdt = sdt::dsdt::Dsdt::<M>::load(fadt.dsdt()).parse();
 
@@ -62,6 +68,7 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Acpi<M> {
             fadt,
             hpet,
             mcfg,
+            srat,
             dsdt,
             _phantom: core::marker::PhantomData,
         }
@@ -91,6 +98,13 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Acpi<M> {
         self.mcfg.as_ref()
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the NUMA affinity information, if the firmware provides it.
+    pub const fn srat(&self) -> Option<&ParsedSrat> {
+        self.srat.as_ref()
+    }
+
     #[must_use]
     #[inline]
     pub const fn dsdt(&self) -> &ParsedDsdt {
@@ -33,6 +33,80 @@ struct Xsdp2 {
     reserved: [u8; 3],
 }
 
+/// RSDP signature.
+const SIGNATURE: &[u8; 8] = b"RSD PTR ";
+
+/// Physical address of the BDA word holding the EBDA segment.
+const EBDA_SEGMENT_PADDR: u64 = 0x40E;
+/// Number of bytes of the EBDA that are searched.
+const EBDA_SEARCH_LEN: usize = 1024;
+/// BIOS read-only memory area that is searched after the EBDA.
+const BIOS_AREA: core::ops::Range<u64> = 0xE_0000..0x10_0000;
+
+/// Scans legacy BIOS memory for the RSDP.
+///
+/// The first KiB of the EBDA is searched first, then the `0xE0000..0x100000` area,
+/// as specified by ACPI section 5.2.5.1.
+/// This is only useful on firmwares that do not report the RSDP, such as legacy BIOSes.
+#[must_use]
+pub fn scan<M: PhysicalMapper<M4KiB>>() -> Option<PhysAddr> {
+    let flags = Flags::PRESENT | Flags::NO_EXECUTE;
+
+    let ebda_paddr = {
+        let paddr = PhysAddr::new_truncate(EBDA_SEGMENT_PADDR);
+        let mapping = M::new(paddr, size_of::<u16>(), flags);
+        let vaddr = mapping.translate(paddr).unwrap();
+        // Safety: The BDA is mapped and the read is unaligned-safe.
+        let segment = unsafe { vaddr.as_ptr::<u16>().read_unaligned() };
+        u64::from(segment) << 4
+    };
+
+    let search = |start: u64, len: usize| {
+        let paddr = PhysAddr::new_truncate(start);
+        let mapping = M::new(paddr, len, flags);
+        let vaddr = mapping.translate(paddr).unwrap();
+        // Safety: The region has just been mapped.
+        let region = unsafe { core::slice::from_raw_parts(vaddr.as_ptr::<u8>(), len) };
+        find_in(region).map(|offset| paddr + u64::try_from(offset).unwrap())
+    };
+
+    // The EBDA must be below the BIOS area, otherwise the pointer is garbage.
+    (ebda_paddr != 0 && ebda_paddr < BIOS_AREA.start)
+        .then(|| search(ebda_paddr, EBDA_SEARCH_LEN))
+        .flatten()
+        .or_else(|| {
+            search(
+                BIOS_AREA.start,
+                usize::try_from(BIOS_AREA.end - BIOS_AREA.start).unwrap(),
+            )
+        })
+}
+
+/// Returns the offset of the first valid RSDP in `region`.
+///
+/// The RSDP is always on a 16-byte boundary.
+/// Candidates with a bad checksum are skipped.
+fn find_in(region: &[u8]) -> Option<usize> {
+    let checksum_ok = |bytes: &[u8]| bytes.iter().fold(0_u8, |sum, &b| sum.wrapping_add(b)) == 0;
+
+    (0..region.len()).step_by(16).find(|&offset| {
+        let candidate = &region[offset..];
+        if !candidate.starts_with(SIGNATURE) {
+            return false;
+        }
+        let Some(v1) = candidate.get(..size_of::<Rsdp1>()) else {
+            return false;
+        };
+        if !checksum_ok(v1) {
+            return false;
+        }
+        if v1[offset_of!(Rsdp1, revision)] < 2 {
+            return true;
+        }
+        candidate.get(..size_of::<Xsdp2>()).is_some_and(checksum_ok)
+    })
+}
+
 #[derive(Debug)]
 pub struct Rsdp<M: PhysicalMapper<M4KiB>> {
     start_vaddr: VirtAddr,
@@ -106,3 +180,74 @@ impl<M: PhysicalMapper<M4KiB>> Rsdp<M> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate alloc;
+    use alloc::vec;
+
+    /// Builds a valid ACPI 2.0 RSDP.
+    fn xsdp() -> [u8; 36] {
+        let mut rsdp = [0_u8; 36];
+        rsdp[..8].copy_from_slice(SIGNATURE);
+        rsdp[9..15].copy_from_slice(b"BESKAR");
+        rsdp[15] = 2;
+        rsdp[16..20].copy_from_slice(&0x7FE_0000_u32.to_le_bytes());
+        rsdp[20..24].copy_from_slice(&36_u32.to_le_bytes());
+        rsdp[24..32].copy_from_slice(&0x7FE_1000_u64.to_le_bytes());
+        let sum = |bytes: &[u8]| bytes.iter().fold(0_u8, |sum, &b| sum.wrapping_add(b));
+        rsdp[8] = 0_u8.wrapping_sub(sum(&rsdp[..20]));
+        rsdp[32] = 0_u8.wrapping_sub(sum(&rsdp));
+        rsdp
+    }
+
+    #[test]
+    fn test_find_valid() {
+        let mut region = vec![0_u8; 0x2000];
+        region[0x1230..0x1230 + 36].copy_from_slice(&xsdp());
+        assert_eq!(find_in(&region), Some(0x1230));
+    }
+
+    #[test]
+    fn test_skip_invalid() {
+        let mut region = vec![0_u8; 0x2000];
+
+        // Not on a 16-byte boundary
+        region[0x108..0x108 + 36].copy_from_slice(&xsdp());
+        assert_eq!(find_in(&region), None);
+
+        // Bad checksum
+        let mut bad = xsdp();
+        bad[10] ^= 0xFF;
+        region[0x200..0x200 + 36].copy_from_slice(&bad);
+        assert_eq!(find_in(&region), None);
+
+        // Bad extended checksum
+        let mut bad = xsdp();
+        bad[24] ^= 0xFF;
+        region[0x300..0x300 + 36].copy_from_slice(&bad);
+        assert_eq!(find_in(&region), None);
+
+        // Truncated at the end of the region
+        region[0x1FF0..].copy_from_slice(&xsdp()[..16]);
+        assert_eq!(find_in(&region), None);
+
+        region[0x400..0x400 + 36].copy_from_slice(&xsdp());
+        assert_eq!(find_in(&region), Some(0x400));
+    }
+
+    #[test]
+    fn test_find_v1() {
+        let mut rsdp = [0_u8; 20];
+        rsdp[..8].copy_from_slice(SIGNATURE);
+        rsdp[16..20].copy_from_slice(&0xF_0000_u32.to_le_bytes());
+        rsdp[8] = 0_u8.wrapping_sub(rsdp.iter().fold(0_u8, |sum, &b| sum.wrapping_add(b)));
+
+        // A revision 0 RSDP is only 20 bytes long
+        let mut region = vec![0_u8; 0x40];
+        region[0x20..0x34].copy_from_slice(&rsdp);
+        assert_eq!(find_in(&region), Some(0x20));
+    }
+}
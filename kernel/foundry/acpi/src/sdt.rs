@@ -10,6 +10,7 @@ pub mod fadt;
 pub mod hpet_table;
 pub mod madt;
 pub mod mcfg;
+pub mod srat;
 
 #[derive(Clone, Copy, Debug)]
 #[repr(C, packed)]
@@ -222,6 +223,7 @@ pub enum Signature {
     Hpet,
     Mcfg,
     Dsdt,
+    Srat,
 }
 
 impl From<Signature> for &'static [u8; 4] {
@@ -241,6 +243,7 @@ impl Signature {
             Self::Hpet => b"HPET",
             Self::Mcfg => b"MCFG",
             Self::Dsdt => b"DSDT",
+            Self::Srat => b"SRAT",
         }
     }
 }
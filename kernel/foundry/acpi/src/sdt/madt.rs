@@ -17,9 +17,11 @@ pub struct ParsedMadt {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A processor's local APIC, described either by a Local APIC
+/// or by a Processor Local x2APIC entry.
 pub struct ParsedLapic {
-    id: u8,
-    acpi_id: u8,
+    id: u32,
+    acpi_id: u32,
     flags: u32,
 }
 
@@ -48,8 +50,8 @@ pub struct ParsedLocalNmi {
     flags: InterruptFlags,
     /// The ACPI ID of the CPU
     ///
-    /// 0xFF means all CPUs
-    acpi_id: u8,
+    /// `ParsedLocalNmi::ALL_CPUS` means all CPUs
+    acpi_id: u32,
     /// Local APIC interrupt input `LINTn` to which NMI is connected.
     lint: Lint,
 }
@@ -225,7 +227,7 @@ struct X2ApicNmi {
     acpi_uid: u32,
     /// Local x2APIC interrupt input `LINTn` to which NMI is connected.
     lx2apic_lint: u8,
-    reserved: [u8; 3],
+    _reserved: [u8; 3],
 }
 
 // See <https://uefi.org/htmlspecs/ACPI_Spec_6_4_html/05_ACPI_Software_Programming_Model/ACPI_Software_Programming_Model.html#multiple-apic-description-table-madt>
@@ -265,18 +267,11 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Madt<M> {
                     let lapic = unsafe { entry_start.cast::<Lapic>().read_unaligned() };
 
                     // Unpack packed fields
-                    let id = lapic.id;
-                    let acpi_id = lapic.acpi_id;
+                    let id = u32::from(lapic.id);
+                    let acpi_id = u32::from(lapic.acpi_id);
                     let flags = lapic.flags;
 
-                    let parsed_lapic = ParsedLapic { id, acpi_id, flags };
-                    if parsed_lapic.flags & 0b1 == 0b1 {
-                        lapics.push(parsed_lapic);
-                    } else if parsed_lapic.flags & 0b10 == 0b10 {
-                        unreachable!("Bootloader should have enabled this LAPIC.");
-                    } else {
-                        // LAPIC is disabled
-                    }
+                    ParsedLapic { id, acpi_id, flags }.push_if_enabled(&mut lapics);
                 }
                 1 => {
                     assert_eq!(usize::from(entry_header.length), size_of::<IoApic>());
@@ -332,7 +327,11 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Madt<M> {
 
                     // Unpack packed fields
                     let flags = local_nmi.flags;
-                    let acpi_id = local_nmi.acpi_id;
+                    let acpi_id = if local_nmi.acpi_id == 0xFF {
+                        ParsedLocalNmi::ALL_CPUS
+                    } else {
+                        u32::from(local_nmi.acpi_id)
+                    };
                     let lint = local_nmi.lint;
 
                     let local_nmi = ParsedLocalNmi {
@@ -364,9 +363,14 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Madt<M> {
                         size_of::<X2Apic>(),
                         "Invalid MADT entry length for Processor Local x2APIC."
                     );
-                    // Local x2APIC
-                    // Same as Local APIC
-                    let _local_x2apic = unsafe { entry_start.cast::<X2Apic>().read_unaligned() };
+                    let local_x2apic = unsafe { entry_start.cast::<X2Apic>().read_unaligned() };
+
+                    // Unpack packed fields
+                    let id = local_x2apic.id;
+                    let acpi_id = local_x2apic.acpi_id;
+                    let flags = local_x2apic.flags;
+
+                    ParsedLapic { id, acpi_id, flags }.push_if_enabled(&mut lapics);
                 }
                 10 => {
                     assert_eq!(
@@ -375,8 +379,19 @@ impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Madt<M> {
                         "Invalid MADT entry length for Local x2APIC NMI Structure."
                     );
 
-                    let _x2apic_nmi = unsafe { entry_start.cast::<X2ApicNmi>().read_unaligned() };
-                    // TODO: Handle Local x2APIC NMI Structure.
+                    let x2apic_nmi = unsafe { entry_start.cast::<X2ApicNmi>().read_unaligned() };
+
+                    // Unpack packed fields
+                    let flags = x2apic_nmi.flags;
+                    let acpi_id = x2apic_nmi.acpi_uid;
+                    let lint = x2apic_nmi.lx2apic_lint;
+
+                    local_nmis.push(ParsedLocalNmi {
+                        flags,
+                        acpi_id,
+                        lint: Lint::try_from(lint)
+                            .expect("Invalid LINT value in Local x2APIC NMI entry."),
+                    });
                 }
                 // GIC related entries
                 x if (11..=15).contains(&x) => {
@@ -498,15 +513,33 @@ impl ParsedIoNmiSource {
 }
 
 impl ParsedLapic {
+    /// Adds the LAPIC to the list if it is enabled and not already present.
+    ///
+    /// Firmware may describe the same processor with both a Local APIC
+    /// and a Processor Local x2APIC entry.
+    fn push_if_enabled(self, lapics: &mut Vec<Self>) {
+        if self.flags & 0b1 == 0b1 {
+            if !lapics.iter().any(|lapic| lapic.id == self.id) {
+                lapics.push(self);
+            }
+        } else if self.flags & 0b10 == 0b10 {
+            unreachable!("Bootloader should have enabled this LAPIC.");
+        } else {
+            // LAPIC is disabled
+        }
+    }
+
     #[must_use]
     #[inline]
-    pub const fn id(&self) -> u8 {
+    /// The (x2)APIC ID of the processor
+    pub const fn id(&self) -> u32 {
         self.id
     }
 
     #[must_use]
     #[inline]
-    pub const fn acpi_id(&self) -> u8 {
+    /// The ACPI processor UID
+    pub const fn acpi_id(&self) -> u32 {
         self.acpi_id
     }
 
@@ -518,6 +551,9 @@ impl ParsedLapic {
 }
 
 impl ParsedLocalNmi {
+    /// ACPI ID value targeting every processor.
+    pub const ALL_CPUS: u32 = u32::MAX;
+
     #[must_use]
     #[inline]
     pub const fn flags(&self) -> InterruptFlags {
@@ -528,11 +564,18 @@ impl ParsedLocalNmi {
     #[inline]
     /// The ACPI ID of the CPU
     ///
-    /// 0xFF means all CPUs
-    pub const fn acpi_id(&self) -> u8 {
+    /// `Self::ALL_CPUS` means all CPUs
+    pub const fn acpi_id(&self) -> u32 {
         self.acpi_id
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns true if the NMI is connected to the processor with the given ACPI ID.
+    pub const fn targets(&self, acpi_id: u32) -> bool {
+        self.acpi_id == Self::ALL_CPUS || self.acpi_id == acpi_id
+    }
+
     #[must_use]
     #[inline]
     /// Local APIC interrupt input `LINTn` to which NMI is connected.
@@ -0,0 +1,392 @@
+//! System Resource Affinity Table (SRAT).
+//!
+//! The SRAT associates processors and memory ranges with proximity domains,
+//! which is how the firmware describes NUMA topology.
+use super::{Sdt, SdtHeader};
+use alloc::vec::Vec;
+use beskar_core::arch::PhysAddr;
+
+super::impl_sdt!(Srat);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedSrat {
+    processors: Vec<ParsedProcessorAffinity>,
+    memory: Vec<ParsedMemoryAffinity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Association between a processor and a proximity domain.
+pub struct ParsedProcessorAffinity {
+    /// (x2)APIC ID of the processor
+    apic_id: u32,
+    proximity_domain: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Association between a physical memory range and a proximity domain.
+pub struct ParsedMemoryAffinity {
+    base: PhysAddr,
+    length: u64,
+    proximity_domain: u32,
+    hot_pluggable: bool,
+    non_volatile: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct SratHeader {
+    sdt_header: SdtHeader,
+    /// Must be 1 for backward compatibility
+    _reserved1: u32,
+    _reserved2: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, packed)]
+struct EntryHeader {
+    entry_type: u8,
+    length: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, packed)]
+/// SRAT Entry type 0: Processor Local APIC/SAPIC Affinity
+struct LapicAffinity {
+    header: EntryHeader,
+    /// Bits 0-7 of the proximity domain
+    proximity_domain_low: u8,
+    apic_id: u8,
+    flags: u32,
+    local_sapic_eid: u8,
+    /// Bits 8-31 of the proximity domain
+    proximity_domain_high: [u8; 3],
+    clock_domain: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, packed)]
+/// SRAT Entry type 1: Memory Affinity
+struct MemoryAffinity {
+    header: EntryHeader,
+    proximity_domain: u32,
+    _reserved1: u16,
+    base_low: u32,
+    base_high: u32,
+    length_low: u32,
+    length_high: u32,
+    _reserved2: u32,
+    flags: u32,
+    _reserved3: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, packed)]
+/// SRAT Entry type 2: Processor Local x2APIC Affinity
+struct X2ApicAffinity {
+    header: EntryHeader,
+    _reserved1: u16,
+    proximity_domain: u32,
+    x2apic_id: u32,
+    flags: u32,
+    clock_domain: u32,
+    _reserved2: u32,
+}
+
+/// Common "enabled" flag of all SRAT entries
+const FLAG_ENABLED: u32 = 1 << 0;
+/// Memory Affinity flag: the range is hot-pluggable
+const FLAG_HOT_PLUGGABLE: u32 = 1 << 1;
+/// Memory Affinity flag: the range is non-volatile
+const FLAG_NON_VOLATILE: u32 = 1 << 2;
+
+// See <https://uefi.org/htmlspecs/ACPI_Spec_6_4_html/05_ACPI_Software_Programming_Model/ACPI_Software_Programming_Model.html#system-resource-affinity-table-srat>
+impl<M: driver_api::PhysicalMapper<beskar_core::arch::paging::M4KiB>> Srat<M> {
+    #[must_use]
+    pub fn parse(&self) -> ParsedSrat {
+        let entries_len = usize::try_from(self.length())
+            .unwrap()
+            .saturating_sub(size_of::<SratHeader>());
+
+        let entries = unsafe {
+            core::slice::from_raw_parts(
+                self.start_vaddr
+                    .as_ptr::<u8>()
+                    .byte_add(size_of::<SratHeader>()),
+                entries_len,
+            )
+        };
+
+        ParsedSrat::from_entries(entries)
+    }
+}
+
+impl ParsedSrat {
+    #[must_use]
+    /// Parse the entries following the SRAT header.
+    ///
+    /// Disabled and unknown entries are skipped.
+    fn from_entries(entries: &[u8]) -> Self {
+        let mut processors = Vec::new();
+        let mut memory = Vec::new();
+
+        let mut offset = 0;
+        while offset + size_of::<EntryHeader>() <= entries.len() {
+            let entry = &entries[offset..];
+            let entry_type = entry[0];
+            let length = usize::from(entry[1]);
+
+            if length < size_of::<EntryHeader>() || length > entry.len() {
+                // Malformed entry, stop parsing
+                break;
+            }
+
+            match entry_type {
+                0 if length == size_of::<LapicAffinity>() => {
+                    let lapic = unsafe { entry.as_ptr().cast::<LapicAffinity>().read_unaligned() };
+
+                    // Unpack packed fields
+                    let flags = lapic.flags;
+                    let high = lapic.proximity_domain_high;
+                    let proximity_domain =
+                        u32::from_le_bytes([lapic.proximity_domain_low, high[0], high[1], high[2]]);
+
+                    if flags & FLAG_ENABLED != 0 {
+                        processors.push(ParsedProcessorAffinity {
+                            apic_id: u32::from(lapic.apic_id),
+                            proximity_domain,
+                        });
+                    }
+                }
+                1 if length == size_of::<MemoryAffinity>() => {
+                    let mem = unsafe { entry.as_ptr().cast::<MemoryAffinity>().read_unaligned() };
+
+                    // Unpack packed fields
+                    let flags = mem.flags;
+                    let base = u64::from(mem.base_low) | (u64::from(mem.base_high) << 32);
+                    let length = u64::from(mem.length_low) | (u64::from(mem.length_high) << 32);
+
+                    if flags & FLAG_ENABLED != 0 && length != 0 {
+                        memory.push(ParsedMemoryAffinity {
+                            base: PhysAddr::new_truncate(base),
+                            length,
+                            proximity_domain: mem.proximity_domain,
+                            hot_pluggable: flags & FLAG_HOT_PLUGGABLE != 0,
+                            non_volatile: flags & FLAG_NON_VOLATILE != 0,
+                        });
+                    }
+                }
+                2 if length == size_of::<X2ApicAffinity>() => {
+                    let x2apic =
+                        unsafe { entry.as_ptr().cast::<X2ApicAffinity>().read_unaligned() };
+
+                    // Unpack packed fields
+                    let flags = x2apic.flags;
+
+                    if flags & FLAG_ENABLED != 0 {
+                        processors.push(ParsedProcessorAffinity {
+                            apic_id: x2apic.x2apic_id,
+                            proximity_domain: x2apic.proximity_domain,
+                        });
+                    }
+                }
+                _ => {
+                    // GICC, GIC ITS, Generic Initiator and unknown entries are ignored
+                }
+            }
+
+            offset += length;
+        }
+
+        Self { processors, memory }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn processor_affinities(&self) -> &[ParsedProcessorAffinity] {
+        &self.processors
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn memory_affinities(&self) -> &[ParsedMemoryAffinity] {
+        &self.memory
+    }
+
+    #[must_use]
+    /// Returns the proximity domain of the processor with the given APIC ID.
+    pub fn domain_of_apic(&self, apic_id: u32) -> Option<u32> {
+        self.processors
+            .iter()
+            .find(|p| p.apic_id == apic_id)
+            .map(ParsedProcessorAffinity::proximity_domain)
+    }
+
+    #[must_use]
+    /// Returns the proximity domain of the memory range containing the given address.
+    pub fn domain_of_paddr(&self, paddr: PhysAddr) -> Option<u32> {
+        self.memory
+            .iter()
+            .find(|m| m.contains(paddr))
+            .map(ParsedMemoryAffinity::proximity_domain)
+    }
+
+    #[must_use]
+    /// Returns the number of distinct proximity domains.
+    pub fn domain_count(&self) -> usize {
+        let mut domains = self
+            .processors
+            .iter()
+            .map(ParsedProcessorAffinity::proximity_domain)
+            .chain(
+                self.memory
+                    .iter()
+                    .map(ParsedMemoryAffinity::proximity_domain),
+            )
+            .collect::<Vec<_>>();
+        domains.sort_unstable();
+        domains.dedup();
+        domains.len()
+    }
+}
+
+impl ParsedProcessorAffinity {
+    #[must_use]
+    #[inline]
+    pub const fn apic_id(&self) -> u32 {
+        self.apic_id
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn proximity_domain(&self) -> u32 {
+        self.proximity_domain
+    }
+}
+
+impl ParsedMemoryAffinity {
+    #[must_use]
+    #[inline]
+    pub const fn base(&self) -> PhysAddr {
+        self.base
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn length(&self) -> u64 {
+        self.length
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn proximity_domain(&self) -> u32 {
+        self.proximity_domain
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_hot_pluggable(&self) -> bool {
+        self.hot_pluggable
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_non_volatile(&self) -> bool {
+        self.non_volatile
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn contains(&self, paddr: PhysAddr) -> bool {
+        let addr = paddr.as_u64();
+        addr >= self.base.as_u64() && addr - self.base.as_u64() < self.length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lapic_entry(apic_id: u8, domain: u32, enabled: bool) -> [u8; 16] {
+        let d = domain.to_le_bytes();
+        let mut entry = [0; 16];
+        entry[0] = 0;
+        entry[1] = 16;
+        entry[2] = d[0];
+        entry[3] = apic_id;
+        entry[4..8].copy_from_slice(&u32::from(enabled).to_le_bytes());
+        entry[9..12].copy_from_slice(&d[1..]);
+        entry
+    }
+
+    fn memory_entry(base: u64, length: u64, domain: u32) -> [u8; 40] {
+        let mut entry = [0; 40];
+        entry[0] = 1;
+        entry[1] = 40;
+        entry[2..6].copy_from_slice(&domain.to_le_bytes());
+        entry[8..16].copy_from_slice(&base.to_le_bytes());
+        entry[16..24].copy_from_slice(&length.to_le_bytes());
+        entry[28..32].copy_from_slice(&FLAG_ENABLED.to_le_bytes());
+        entry
+    }
+
+    fn x2apic_entry(x2apic_id: u32, domain: u32) -> [u8; 24] {
+        let mut entry = [0; 24];
+        entry[0] = 2;
+        entry[1] = 24;
+        entry[4..8].copy_from_slice(&domain.to_le_bytes());
+        entry[8..12].copy_from_slice(&x2apic_id.to_le_bytes());
+        entry[12..16].copy_from_slice(&FLAG_ENABLED.to_le_bytes());
+        entry
+    }
+
+    #[test]
+    fn test_entry_layouts() {
+        assert_eq!(size_of::<SratHeader>(), 48);
+        assert_eq!(size_of::<LapicAffinity>(), 16);
+        assert_eq!(size_of::<MemoryAffinity>(), 40);
+        assert_eq!(size_of::<X2ApicAffinity>(), 24);
+    }
+
+    #[test]
+    fn test_parse_two_domains() {
+        let mut entries = Vec::new();
+        entries.extend_from_slice(&lapic_entry(0, 0, true));
+        entries.extend_from_slice(&lapic_entry(1, 0, true));
+        entries.extend_from_slice(&lapic_entry(2, 1, true));
+        entries.extend_from_slice(&lapic_entry(3, 1, false));
+        entries.extend_from_slice(&x2apic_entry(0x1_0000, 1));
+        entries.extend_from_slice(&memory_entry(0, 0x8000_0000, 0));
+        entries.extend_from_slice(&memory_entry(0x1_0000_0000, 0x8000_0000, 1));
+
+        let srat = ParsedSrat::from_entries(&entries);
+
+        assert_eq!(srat.processor_affinities().len(), 4);
+        assert_eq!(srat.memory_affinities().len(), 2);
+        assert_eq!(srat.domain_count(), 2);
+
+        assert_eq!(srat.domain_of_apic(1), Some(0));
+        assert_eq!(srat.domain_of_apic(2), Some(1));
+        assert_eq!(srat.domain_of_apic(3), None);
+        assert_eq!(srat.domain_of_apic(0x1_0000), Some(1));
+
+        assert_eq!(
+            srat.domain_of_paddr(PhysAddr::new_truncate(0x1000)),
+            Some(0)
+        );
+        assert_eq!(
+            srat.domain_of_paddr(PhysAddr::new_truncate(0x1_2345_0000)),
+            Some(1)
+        );
+        assert_eq!(
+            srat.domain_of_paddr(PhysAddr::new_truncate(0x9000_0000)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let entry = memory_entry(0, 0x1000, 0);
+        let srat = ParsedSrat::from_entries(&entry[..20]);
+        assert!(srat.memory_affinities().is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+//! Packet capture, for diagnostics.
+//!
+//! Received frames are copied into a bounded ring, optionally filtered by `EtherType`.
+//! When the ring is full, new frames are dropped and counted: frames already captured
+//! are never overwritten, so that a slow reader sees a contiguous sequence of frames
+//! and knows how many are missing after it.
+
+use crate::utils::u16_from_inet_bytes;
+use alloc::{collections::VecDeque, vec::Vec};
+use core::time::Duration;
+
+/// Offset of the `EtherType` field in an Ethernet frame.
+const ETHERTYPE_OFFSET: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A frame copied by a `PacketCapture`.
+pub struct CapturedFrame {
+    timestamp: Duration,
+    original_len: usize,
+    dropped_before: u64,
+    data: Vec<u8>,
+}
+
+impl CapturedFrame {
+    #[must_use]
+    #[inline]
+    /// Time at which the frame was captured.
+    pub const fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    #[must_use]
+    #[inline]
+    /// Length of the frame on the wire, which may exceed the length of the copy.
+    pub const fn original_len(&self) -> usize {
+        self.original_len
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of frames dropped because the ring was full, right before this one.
+    pub const fn dropped_before(&self) -> u64 {
+        self.dropped_before
+    }
+
+    #[must_use]
+    #[inline]
+    /// The captured bytes, truncated to the snapshot length of the capture.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A bounded ring of captured frames.
+pub struct PacketCapture {
+    frames: VecDeque<CapturedFrame>,
+    capacity: usize,
+    snap_len: usize,
+    filter: Option<u16>,
+    dropped: u64,
+    /// Frames dropped since the last frame was captured
+    pending_drops: u64,
+}
+
+impl PacketCapture {
+    #[must_use]
+    /// Create a capture holding at most `capacity` frames, each truncated to `snap_len` bytes.
+    pub fn new(capacity: usize, snap_len: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            snap_len,
+            filter: None,
+            dropped: 0,
+            pending_drops: 0,
+        }
+    }
+
+    #[inline]
+    /// Only capture frames with the given raw `EtherType`, or every frame if `None`.
+    ///
+    /// IEEE 802.1Q tags are not looked through.
+    pub const fn set_filter(&mut self, ethertype: Option<u16>) {
+        self.filter = ethertype;
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn filter(&self) -> Option<u16> {
+        self.filter
+    }
+
+    #[must_use]
+    #[expect(clippy::missing_panics_doc, reason = "Never panics")]
+    /// Whether a frame passes the filter.
+    ///
+    /// Frames too short to hold an Ethernet header only pass if there is no filter.
+    pub fn matches(&self, frame: &[u8]) -> bool {
+        self.filter.is_none_or(|ethertype| {
+            frame
+                .get(ETHERTYPE_OFFSET..ETHERTYPE_OFFSET + 2)
+                .is_some_and(|raw| u16_from_inet_bytes(raw.try_into().unwrap()) == ethertype)
+        })
+    }
+
+    /// Copy a received frame into the ring, if it passes the filter.
+    ///
+    /// Returns whether the frame was captured.
+    /// Frames that pass the filter but do not fit in the ring are counted as dropped.
+    pub fn record(&mut self, frame: &[u8], timestamp: Duration) -> bool {
+        if !self.matches(frame) {
+            return false;
+        }
+        if self.frames.len() >= self.capacity {
+            self.dropped += 1;
+            self.pending_drops += 1;
+            return false;
+        }
+
+        let len = frame.len().min(self.snap_len);
+        self.frames.push_back(CapturedFrame {
+            timestamp,
+            original_len: frame.len(),
+            dropped_before: core::mem::take(&mut self.pending_drops),
+            data: frame[..len].to_vec(),
+        });
+        true
+    }
+
+    #[must_use]
+    #[inline]
+    /// Take the oldest captured frame.
+    pub fn pop(&mut self) -> Option<CapturedFrame> {
+        self.frames.pop_front()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the oldest captured frame, without taking it.
+    pub fn peek(&self) -> Option<&CapturedFrame> {
+        self.frames.front()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of frames waiting in the ring.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Total number of frames dropped because the ring was full.
+    pub const fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn snap_len(&self) -> usize {
+        self.snap_len
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::l2::ethernet::EtherType;
+
+    fn frame(ethertype: EtherType, len: usize) -> Vec<u8> {
+        let mut frame = alloc::vec![0; len];
+        frame[ETHERTYPE_OFFSET..ETHERTYPE_OFFSET + 2]
+            .copy_from_slice(&u16::from(ethertype).to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_ethertype_filter() {
+        let mut capture = PacketCapture::new(8, 64);
+        let arp = frame(EtherType::Arp, 42);
+        let ipv4 = frame(EtherType::IpV4, 60);
+
+        assert!(capture.record(&arp, Duration::ZERO));
+        assert!(capture.record(&ipv4, Duration::ZERO));
+        // Runt frames are kept without a filter
+        assert!(capture.record(&[0; 4], Duration::ZERO));
+
+        capture.set_filter(Some(u16::from(EtherType::Arp)));
+        assert!(capture.record(&arp, Duration::ZERO));
+        assert!(!capture.record(&ipv4, Duration::ZERO));
+        assert!(!capture.record(&[0; 4], Duration::ZERO));
+
+        assert_eq!(capture.len(), 4);
+        // Filtered frames are not drops
+        assert_eq!(capture.dropped(), 0);
+    }
+
+    #[test]
+    fn test_snap_len() {
+        let mut capture = PacketCapture::new(1, 16);
+        assert!(capture.record(&frame(EtherType::IpV4, 100), Duration::from_micros(5)));
+
+        let captured = capture.pop().unwrap();
+        assert_eq!(captured.data().len(), 16);
+        assert_eq!(captured.original_len(), 100);
+        assert_eq!(captured.timestamp(), Duration::from_micros(5));
+        assert!(capture.is_empty());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut capture = PacketCapture::new(2, 64);
+        for i in 0..5 {
+            capture.record(&frame(EtherType::IpV4, 20 + i), Duration::ZERO);
+        }
+
+        assert_eq!(capture.len(), 2);
+        assert_eq!(capture.dropped(), 3);
+
+        // The oldest frames are kept
+        let first = capture.pop().unwrap();
+        assert_eq!(first.original_len(), 20);
+        assert_eq!(first.dropped_before(), 0);
+
+        // The next frame that fits reports the drops before it
+        assert!(capture.record(&frame(EtherType::IpV4, 30), Duration::ZERO));
+        assert_eq!(capture.pop().unwrap().original_len(), 21);
+        let after_drops = capture.pop().unwrap();
+        assert_eq!(after_drops.original_len(), 30);
+        assert_eq!(after_drops.dropped_before(), 3);
+
+        assert!(capture.record(&frame(EtherType::IpV4, 31), Duration::ZERO));
+        assert_eq!(capture.pop().unwrap().dropped_before(), 0);
+        assert_eq!(capture.dropped(), 3);
+    }
+}
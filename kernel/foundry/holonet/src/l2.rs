@@ -1 +1,2 @@
 pub mod ethernet;
+pub mod loopback;
@@ -2,11 +2,21 @@ use crate::{
     NetworkError, NetworkResult,
     utils::{u16_from_inet_bytes, u16_to_inet_bytes},
 };
+use alloc::vec::Vec;
 
 const DESTINATION: core::ops::Range<usize> = 0..6;
 const SOURCE: core::ops::Range<usize> = 6..12;
 const ETHERTYPE: core::ops::Range<usize> = 12..14;
 const PAYLOAD: core::ops::RangeFrom<usize> = 14..;
+/// Length of an IEEE 802.1Q tag.
+const VLAN_TAG_LEN: usize = 4;
+
+/// `EtherType` announcing an IEEE 802.1Q tag.
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+/// Smallest length of a frame, without the frame check sequence.
+///
+/// Shorter frames are padded with zeros.
+pub const MIN_FRAME_LEN: usize = 60;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u16)]
@@ -282,6 +292,185 @@ impl Repr {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An IEEE 802.1Q tag.
+pub struct VlanTag {
+    /// Priority code point, on 3 bits.
+    pub priority: u8,
+    /// Drop eligible indicator.
+    pub drop_eligible: bool,
+    /// VLAN identifier, on 12 bits.
+    pub id: u16,
+}
+
+impl VlanTag {
+    #[must_use]
+    #[inline]
+    /// Create a tag for a VLAN, with default priority.
+    pub const fn new(id: u16) -> Self {
+        Self {
+            priority: 0,
+            drop_eligible: false,
+            id: id & 0x0FFF,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Parse the tag control information field.
+    pub const fn from_tci(tci: u16) -> Self {
+        Self {
+            priority: (tci >> 13) as u8,
+            drop_eligible: tci & (1 << 12) != 0,
+            id: tci & 0x0FFF,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the tag control information field.
+    pub const fn to_tci(self) -> u16 {
+        ((self.priority as u16 & 0x7) << 13)
+            | ((self.drop_eligible as u16) << 12)
+            | (self.id & 0x0FFF)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A builder of Ethernet II frames, optionally tagged with IEEE 802.1Q.
+pub struct EthernetFrame<'a> {
+    dst_addr: MacAddress,
+    src_addr: MacAddress,
+    ethertype: EtherType,
+    vlan: Option<VlanTag>,
+    payload: &'a [u8],
+}
+
+impl<'a> EthernetFrame<'a> {
+    #[must_use]
+    #[inline]
+    /// Start building an untagged frame with an empty payload.
+    pub const fn new(dst_addr: MacAddress, src_addr: MacAddress, ethertype: EtherType) -> Self {
+        Self {
+            dst_addr,
+            src_addr,
+            ethertype,
+            vlan: None,
+            payload: &[],
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Tag the frame with an IEEE 802.1Q tag.
+    pub const fn vlan(mut self, tag: VlanTag) -> Self {
+        self.vlan = Some(tag);
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn payload(mut self, payload: &'a [u8]) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the length of the frame, padding included.
+    pub const fn len(&self) -> usize {
+        let tag_len = if self.vlan.is_some() { VLAN_TAG_LEN } else { 0 };
+        let len = HEADER_LEN + tag_len + self.payload.len();
+        if len < MIN_FRAME_LEN {
+            MIN_FRAME_LEN
+        } else {
+            len
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Always false, as frames are padded to `MIN_FRAME_LEN`.
+    pub const fn is_empty(&self) -> bool {
+        false
+    }
+
+    #[must_use]
+    /// Build the frame, padded with zeros to `MIN_FRAME_LEN`.
+    pub fn build(&self) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(self.len());
+        frame.extend_from_slice(&self.dst_addr.as_bytes());
+        frame.extend_from_slice(&self.src_addr.as_bytes());
+        if let Some(tag) = self.vlan {
+            frame.extend_from_slice(&u16_to_inet_bytes(ETHERTYPE_VLAN));
+            frame.extend_from_slice(&u16_to_inet_bytes(tag.to_tci()));
+        }
+        frame.extend_from_slice(&u16_to_inet_bytes(self.ethertype.into()));
+        frame.extend_from_slice(self.payload);
+        frame.resize(self.len(), 0);
+        frame
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The fields of a received Ethernet II frame, optionally tagged with IEEE 802.1Q.
+pub struct ParsedFrame<'a> {
+    pub dst_addr: MacAddress,
+    pub src_addr: MacAddress,
+    pub vlan: Option<VlanTag>,
+    /// The raw `EtherType` of the payload, after the tag if any.
+    pub ethertype: u16,
+    /// The payload, which may end with padding.
+    pub payload: &'a [u8],
+}
+
+impl<'a> ParsedFrame<'a> {
+    /// Parse a frame, looking through a single IEEE 802.1Q tag.
+    ///
+    /// Frames shorter than `MIN_FRAME_LEN` are accepted, as some interfaces strip
+    /// the padding of received frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` if the frame is too short for its header.
+    pub fn parse(frame: &'a [u8]) -> NetworkResult<Self> {
+        let raw_u16 = |offset: usize| {
+            frame
+                .get(offset..offset + 2)
+                .map(|bytes| u16_from_inet_bytes([bytes[0], bytes[1]]))
+                .ok_or(NetworkError::Invalid)
+        };
+
+        let outer = raw_u16(ETHERTYPE.start)?;
+        let (vlan, ethertype, payload_start) = if outer == ETHERTYPE_VLAN {
+            let tci = raw_u16(ETHERTYPE.end)?;
+            let inner = raw_u16(ETHERTYPE.start + VLAN_TAG_LEN)?;
+            (
+                Some(VlanTag::from_tci(tci)),
+                inner,
+                HEADER_LEN + VLAN_TAG_LEN,
+            )
+        } else {
+            (None, outer, HEADER_LEN)
+        };
+
+        Ok(Self {
+            dst_addr: MacAddress::from_bytes(&frame[DESTINATION]),
+            src_addr: MacAddress::from_bytes(&frame[SOURCE]),
+            vlan,
+            ethertype,
+            payload: &frame[payload_start..],
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the `EtherType` of the payload, if it is supported.
+    pub fn protocol(&self) -> Option<EtherType> {
+        EtherType::try_from(self.ethertype).ok()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -375,4 +564,68 @@ mod test {
         frame.payload_mut().copy_from_slice(&PAYLOAD_BYTES_V6[..]);
         assert_eq!(&frame.into_inner()[..], &FRAME_BYTES_V6[..]);
     }
+
+    #[test]
+    fn test_build_and_parse() {
+        let dst = MacAddress([0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        let src = MacAddress([0x11, 0x12, 0x13, 0x14, 0x15, 0x16]);
+
+        // Short frames are padded
+        let frame = EthernetFrame::new(dst, src, EtherType::IpV4)
+            .payload(&PAYLOAD_BYTES_V4[..20])
+            .build();
+        assert_eq!(frame.len(), MIN_FRAME_LEN);
+        assert_eq!(&frame[..], &FRAME_BYTES_V4[..MIN_FRAME_LEN]);
+
+        let long = EthernetFrame::new(dst, src, EtherType::IpV4)
+            .payload(&[0xAB; 100])
+            .build();
+        assert_eq!(long.len(), HEADER_LEN + 100);
+
+        let parsed = ParsedFrame::parse(&frame).unwrap();
+        assert_eq!(parsed.dst_addr, dst);
+        assert_eq!(parsed.src_addr, src);
+        assert_eq!(parsed.vlan, None);
+        assert_eq!(parsed.protocol(), Some(EtherType::IpV4));
+        assert_eq!(parsed.payload, &PAYLOAD_BYTES_V4[..46]);
+
+        assert_eq!(ParsedFrame::parse(&frame[..13]), Err(NetworkError::Invalid));
+    }
+
+    #[test]
+    fn test_vlan() {
+        let dst = MacAddress::BROADCAST;
+        let src = MacAddress([0x52, 0x54, 0, 0, 0, 1]);
+        let tag = VlanTag {
+            priority: 5,
+            drop_eligible: true,
+            id: 42,
+        };
+        assert_eq!(VlanTag::from_tci(tag.to_tci()), tag);
+        assert_eq!(VlanTag::new(0x1234).id, 0x234);
+
+        let frame = EthernetFrame::new(dst, src, EtherType::Arp)
+            .vlan(tag)
+            .payload(&[0xAA; 50])
+            .build();
+        assert_eq!(frame.len(), HEADER_LEN + VLAN_TAG_LEN + 50);
+        assert_eq!(&frame[12..18], &[0x81, 0x00, 0xB0, 0x2A, 0x08, 0x06]);
+
+        let parsed = ParsedFrame::parse(&frame).unwrap();
+        assert_eq!(parsed.dst_addr, dst);
+        assert_eq!(parsed.src_addr, src);
+        assert_eq!(parsed.vlan, Some(tag));
+        assert_eq!(parsed.ethertype, 0x0806);
+        assert_eq!(parsed.payload, &[0xAA; 50]);
+
+        // The tag must be complete
+        assert_eq!(ParsedFrame::parse(&frame[..16]), Err(NetworkError::Invalid));
+
+        // Unknown protocols are kept raw
+        let mut unknown = frame;
+        unknown[16..18].copy_from_slice(&[0x88, 0xCC]);
+        let parsed = ParsedFrame::parse(&unknown).unwrap();
+        assert_eq!(parsed.ethertype, 0x88CC);
+        assert_eq!(parsed.protocol(), None);
+    }
 }
@@ -1,2 +1,3 @@
 pub mod arp;
 pub mod ip;
+pub mod route;
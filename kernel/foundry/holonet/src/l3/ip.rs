@@ -394,6 +394,17 @@ impl Repr {
     ///
     /// Panics if the packet buffer is too short.
     pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
+        self.emit_offloaded(packet);
+        packet.fill_checksum();
+    }
+
+    /// Emit a high-level representation into an IPv4 packet,
+    /// leaving the header checksum to zero for the hardware to fill.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the packet buffer is too short.
+    pub fn emit_offloaded<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
         assert!(packet.buffer.as_ref().len() >= self.buffer_len());
         packet.set_version_and_header_len(4, 5); // Version 4, IHL 5 (20 bytes)
         packet.set_dscp_ecn(0, 0);
@@ -404,7 +415,7 @@ impl Repr {
         packet.set_protocol(self.protocol);
         packet.set_src_addr(self.src_addr);
         packet.set_dst_addr(self.dst_addr);
-        packet.fill_checksum();
+        packet.set_checksum(0);
     }
 }
 
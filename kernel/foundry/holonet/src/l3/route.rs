@@ -0,0 +1,231 @@
+use crate::l3::ip::Ipv4Addr;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Identifier of a network interface, as given by the stack it is registered in.
+pub struct InterfaceId(pub usize);
+
+#[must_use]
+#[inline]
+/// Return the network mask of a prefix length.
+///
+/// Lengths above 32 are clamped.
+pub const fn prefix_mask(prefix_len: u8) -> u32 {
+    match prefix_len {
+        0 => 0,
+        1..=32 => u32::MAX << (32 - prefix_len),
+        _ => u32::MAX,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A route to a network, through an interface.
+pub struct Route {
+    network: Ipv4Addr,
+    prefix_len: u8,
+    /// Router to send packets to, or `None` if the network is directly connected.
+    gateway: Option<Ipv4Addr>,
+    interface: InterfaceId,
+}
+
+impl Route {
+    #[must_use]
+    #[inline]
+    /// Create a route to `network/prefix_len`.
+    ///
+    /// Host bits of `network` are ignored.
+    pub const fn new(
+        network: Ipv4Addr,
+        prefix_len: u8,
+        gateway: Option<Ipv4Addr>,
+        interface: InterfaceId,
+    ) -> Self {
+        let prefix_len = if prefix_len > 32 { 32 } else { prefix_len };
+        Self {
+            network: Ipv4Addr::from_bits(network.to_bits() & prefix_mask(prefix_len)),
+            prefix_len,
+            gateway,
+            interface,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Create a default route (`0.0.0.0/0`) through a gateway.
+    pub const fn default_via(gateway: Ipv4Addr, interface: InterfaceId) -> Self {
+        Self::new(Ipv4Addr::UNSPECIFIED, 0, Some(gateway), interface)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn network(&self) -> Ipv4Addr {
+        self.network
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn gateway(&self) -> Option<Ipv4Addr> {
+        self.gateway
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn interface(&self) -> InterfaceId {
+        self.interface
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether the destination belongs to the network of the route.
+    pub const fn matches(&self, dst: Ipv4Addr) -> bool {
+        dst.to_bits() & prefix_mask(self.prefix_len) == self.network.to_bits()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Return the address a packet to `dst` must be sent to on the link.
+    pub const fn next_hop(&self, dst: Ipv4Addr) -> Ipv4Addr {
+        match self.gateway {
+            Some(gateway) => gateway,
+            None => dst,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+/// A routing table, selecting routes by longest-prefix match.
+///
+/// When several routes have the same prefix length, the first one added wins.
+/// Destinations matched by no route (not even a default route) are unreachable.
+pub struct RoutingTable {
+    routes: Vec<Route>,
+}
+
+impl RoutingTable {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    #[inline]
+    pub fn add(&mut self, route: Route) {
+        self.routes.push(route);
+    }
+
+    /// Remove all the routes through an interface.
+    pub fn remove_interface(&mut self, interface: InterfaceId) {
+        self.routes.retain(|route| route.interface != interface);
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    #[must_use]
+    /// Return the most specific route to a destination.
+    pub fn lookup(&self, dst: Ipv4Addr) -> Option<&Route> {
+        self.routes
+            .iter()
+            .filter(|route| route.matches(dst))
+            // `max_by_key` returns the last maximum, so ties are broken by reversing
+            .rev()
+            .max_by_key(|route| route.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ETH0: InterfaceId = InterfaceId(0);
+    const ETH1: InterfaceId = InterfaceId(1);
+
+    #[test]
+    fn test_prefix_mask() {
+        assert_eq!(prefix_mask(0), 0);
+        assert_eq!(prefix_mask(8), 0xFF00_0000);
+        assert_eq!(prefix_mask(24), 0xFFFF_FF00);
+        assert_eq!(prefix_mask(32), u32::MAX);
+        assert_eq!(prefix_mask(40), u32::MAX);
+    }
+
+    #[test]
+    fn test_route_matches() {
+        let route = Route::new(Ipv4Addr::new(10, 0, 2, 15), 24, None, ETH0);
+        assert_eq!(route.network(), Ipv4Addr::new(10, 0, 2, 0));
+        assert!(route.matches(Ipv4Addr::new(10, 0, 2, 2)));
+        assert!(!route.matches(Ipv4Addr::new(10, 0, 3, 2)));
+
+        let default = Route::default_via(Ipv4Addr::new(10, 0, 2, 2), ETH0);
+        assert!(default.matches(Ipv4Addr::new(1, 1, 1, 1)));
+        assert_eq!(
+            default.next_hop(Ipv4Addr::new(1, 1, 1, 1)),
+            Ipv4Addr::new(10, 0, 2, 2)
+        );
+        assert_eq!(
+            route.next_hop(Ipv4Addr::new(10, 0, 2, 3)),
+            Ipv4Addr::new(10, 0, 2, 3)
+        );
+    }
+
+    #[test]
+    fn test_longest_prefix() {
+        let mut table = RoutingTable::new();
+        table.add(Route::default_via(Ipv4Addr::new(10, 0, 2, 2), ETH0));
+        table.add(Route::new(Ipv4Addr::new(10, 0, 0, 0), 8, None, ETH0));
+        table.add(Route::new(
+            Ipv4Addr::new(10, 1, 0, 0),
+            16,
+            Some(Ipv4Addr::new(10, 0, 0, 1)),
+            ETH1,
+        ));
+        table.add(Route::new(Ipv4Addr::new(10, 1, 2, 0), 24, None, ETH1));
+        table.add(Route::new(Ipv4Addr::new(10, 1, 2, 42), 32, None, ETH0));
+
+        let lookup = |dst: [u8; 4]| {
+            let route = table.lookup(Ipv4Addr::from(dst)).unwrap();
+            (route.prefix_len(), route.interface())
+        };
+
+        assert_eq!(lookup([192, 168, 0, 1]), (0, ETH0));
+        assert_eq!(lookup([10, 200, 0, 1]), (8, ETH0));
+        assert_eq!(lookup([10, 1, 200, 1]), (16, ETH1));
+        assert_eq!(lookup([10, 1, 2, 1]), (24, ETH1));
+        assert_eq!(lookup([10, 1, 2, 42]), (32, ETH0));
+    }
+
+    #[test]
+    fn test_ties_and_unreachable() {
+        let mut table = RoutingTable::new();
+        table.add(Route::new(Ipv4Addr::new(192, 168, 1, 0), 24, None, ETH0));
+        assert!(table.lookup(Ipv4Addr::new(8, 8, 8, 8)).is_none());
+
+        // The first route added wins
+        table.add(Route::new(Ipv4Addr::new(192, 168, 1, 0), 24, None, ETH1));
+        assert_eq!(
+            table
+                .lookup(Ipv4Addr::new(192, 168, 1, 7))
+                .unwrap()
+                .interface(),
+            ETH0
+        );
+
+        table.remove_interface(ETH0);
+        assert_eq!(
+            table
+                .lookup(Ipv4Addr::new(192, 168, 1, 7))
+                .unwrap()
+                .interface(),
+            ETH1
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! DNS client, resolving host names to IPv4 addresses.
+//!
+//! Queries are sent over UDP to a single resolver, and only A records are looked up.
+//! Responses are bounded to the classic UDP message size, so truncated responses
+//! are not retried over TCP.
+
+use crate::{
+    NetworkError, NetworkResult,
+    l3::ip::Ipv4Addr,
+    l4::udp::SocketAddrV4,
+    utils::{u16_from_inet_bytes, u16_to_inet_bytes, u32_from_inet_bytes},
+};
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::time::Duration;
+
+/// Port of DNS servers.
+pub const PORT: u16 = 53;
+/// Largest response accepted, which is the largest message over UDP without EDNS.
+pub const MAX_RESPONSE_LEN: usize = 512;
+
+/// Length of the header of a message.
+const HEADER_LEN: usize = 12;
+/// Largest length of a label.
+const MAX_LABEL_LEN: usize = 63;
+/// Largest length of a name, in its dotted form.
+const MAX_NAME_LEN: usize = 253;
+
+/// Flags: the message is a response.
+const FLAG_QR: u16 = 1 << 15;
+/// Flags: the message is truncated.
+const FLAG_TC: u16 = 1 << 9;
+/// Flags: recursion is desired.
+const FLAG_RD: u16 = 1 << 8;
+/// Mask of the response code in the flags.
+const RCODE_MASK: u16 = 0xF;
+/// Response code: the name does not exist.
+const RCODE_NXDOMAIN: u16 = 3;
+
+/// Type of A records.
+const TYPE_A: u16 = 1;
+/// Internet class.
+const CLASS_IN: u16 = 1;
+
+/// Environment of a DNS client.
+pub trait DnsTransport {
+    /// Send a query datagram to `server` and wait for a response datagram.
+    ///
+    /// Returns the length of the response, which is truncated to the length of `response`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query cannot be sent or no response is received.
+    fn exchange(
+        &mut self,
+        server: SocketAddrV4,
+        query: &[u8],
+        response: &mut [u8],
+    ) -> NetworkResult<usize>;
+
+    /// Return a monotonic time, used to expire cached records.
+    fn now(&self) -> Duration;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CacheEntry {
+    addr: Ipv4Addr,
+    expires: Duration,
+}
+
+/// A DNS client, caching the records it resolved for their time to live.
+pub struct Dns<T: DnsTransport> {
+    transport: T,
+    resolver: Option<Ipv4Addr>,
+    cache: BTreeMap<String, CacheEntry>,
+    next_id: u16,
+}
+
+impl<T: DnsTransport> Dns<T> {
+    #[must_use]
+    #[inline]
+    pub const fn new(transport: T) -> Self {
+        Self {
+            transport,
+            resolver: None,
+            cache: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    #[inline]
+    /// Set the resolver queries are sent to, as given by DHCP.
+    ///
+    /// Cached records are kept.
+    pub const fn set_resolver(&mut self, resolver: Ipv4Addr) {
+        self.resolver = Some(resolver);
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn resolver(&self) -> Option<Ipv4Addr> {
+        self.resolver
+    }
+
+    /// Resolve a host name to its first IPv4 address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotFound` if the name does not exist or has no A record,
+    /// `Uninitialized` if no resolver is configured, and `Invalid` if the name
+    /// or the response is malformed. Transport errors are forwarded.
+    pub fn resolve(&mut self, hostname: &str) -> NetworkResult<Ipv4Addr> {
+        let name = normalize(hostname)?;
+        let now = self.transport.now();
+
+        if let Some(entry) = self.cache.get(&name) {
+            if entry.expires > now {
+                return Ok(entry.addr);
+            }
+            self.cache.remove(&name);
+        }
+
+        let resolver = self.resolver.ok_or(NetworkError::Uninitialized)?;
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let query = build_query(id, &name)?;
+
+        let mut response = [0; MAX_RESPONSE_LEN];
+        let len =
+            self.transport
+                .exchange(SocketAddrV4::new(resolver, PORT), &query, &mut response)?;
+        let answer = parse_response(&response[..len.min(MAX_RESPONSE_LEN)], id)?;
+
+        if answer.ttl != 0 {
+            self.cache.insert(
+                name,
+                CacheEntry {
+                    addr: answer.addr,
+                    expires: now + Duration::from_secs(u64::from(answer.ttl)),
+                },
+            );
+        }
+
+        Ok(answer.addr)
+    }
+
+    /// Forget all cached records.
+    pub fn flush_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+/// Check a host name, and return it in lowercase without its trailing dot.
+fn normalize(hostname: &str) -> NetworkResult<String> {
+    let name = hostname.strip_suffix('.').unwrap_or(hostname);
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .split('.')
+            .all(|label| !label.is_empty() && label.len() <= MAX_LABEL_LEN);
+    if !valid {
+        return Err(NetworkError::Invalid);
+    }
+    Ok(name.to_ascii_lowercase())
+}
+
+/// Build a recursive query for the A records of a (normalized) name.
+///
+/// # Errors
+///
+/// Returns `Invalid` if the name is malformed.
+#[expect(clippy::missing_panics_doc, reason = "Never panics")]
+pub fn build_query(id: u16, name: &str) -> NetworkResult<Vec<u8>> {
+    let name = normalize(name)?;
+
+    let mut query = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    query.extend_from_slice(&u16_to_inet_bytes(id));
+    query.extend_from_slice(&u16_to_inet_bytes(FLAG_RD));
+    // One question, no records
+    query.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    for label in name.split('.') {
+        // Labels were checked to be at most 63 bytes long
+        query.push(u8::try_from(label.len()).unwrap());
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&u16_to_inet_bytes(TYPE_A));
+    query.extend_from_slice(&u16_to_inet_bytes(CLASS_IN));
+
+    Ok(query)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The first A record of a response.
+pub struct Answer {
+    pub addr: Ipv4Addr,
+    /// Time to live, in seconds.
+    pub ttl: u32,
+}
+
+/// Parse the response to the query `id`, and return its first A record.
+///
+/// Other records (e.g. CNAME records preceding the A record) are skipped.
+///
+/// # Errors
+///
+/// Returns `NotFound` if the name does not exist or has no A record,
+/// `Unsupported` if the response is truncated, and `Invalid` if it is malformed,
+/// is not a response to the query, or reports another failure.
+#[expect(clippy::missing_panics_doc, reason = "Never panics")]
+pub fn parse_response(message: &[u8], id: u16) -> NetworkResult<Answer> {
+    if message.len() < HEADER_LEN || message.len() > MAX_RESPONSE_LEN {
+        return Err(NetworkError::Invalid);
+    }
+    let read_u16 = |offset: usize| -> NetworkResult<u16> {
+        message
+            .get(offset..offset + 2)
+            .map(|bytes| u16_from_inet_bytes(bytes.try_into().unwrap()))
+            .ok_or(NetworkError::Invalid)
+    };
+
+    let flags = read_u16(2)?;
+    if read_u16(0)? != id || flags & FLAG_QR == 0 {
+        return Err(NetworkError::Invalid);
+    }
+    if flags & FLAG_TC != 0 {
+        return Err(NetworkError::Unsupported);
+    }
+    match flags & RCODE_MASK {
+        0 => {}
+        RCODE_NXDOMAIN => return Err(NetworkError::NotFound),
+        _ => return Err(NetworkError::Invalid),
+    }
+
+    let questions = read_u16(4)?;
+    let answers = read_u16(6)?;
+
+    let mut offset = HEADER_LEN;
+    for _ in 0..questions {
+        // Name, type and class
+        offset = parse_name(message, offset)?.1 + 4;
+    }
+
+    for _ in 0..answers {
+        offset = parse_name(message, offset)?.1;
+        let rtype = read_u16(offset)?;
+        let class = read_u16(offset + 2)?;
+        let ttl = message
+            .get(offset + 4..offset + 8)
+            .map(|bytes| u32_from_inet_bytes(bytes.try_into().unwrap()))
+            .ok_or(NetworkError::Invalid)?;
+        let rdlength = usize::from(read_u16(offset + 8)?);
+        let rdata = message
+            .get(offset + 10..offset + 10 + rdlength)
+            .ok_or(NetworkError::Invalid)?;
+
+        if rtype == TYPE_A && class == CLASS_IN {
+            let octets = <[u8; 4]>::try_from(rdata).map_err(|_| NetworkError::Invalid)?;
+            return Ok(Answer {
+                addr: Ipv4Addr::from(octets),
+                ttl,
+            });
+        }
+
+        offset += 10 + rdlength;
+    }
+
+    Err(NetworkError::NotFound)
+}
+
+/// Parse the (possibly compressed) name at `offset` of a message.
+///
+/// Returns the name in its dotted form, and the offset right after the name.
+/// Compression pointers must point backwards, which rules out loops.
+///
+/// # Errors
+///
+/// Returns `Invalid` if the name is malformed.
+pub fn parse_name(message: &[u8], offset: usize) -> NetworkResult<(String, usize)> {
+    let mut name = String::new();
+    let mut position = offset;
+    // Offset right after the name, known once the first pointer is followed
+    let mut end = None;
+
+    loop {
+        let &len = message.get(position).ok_or(NetworkError::Invalid)?;
+        match len >> 6 {
+            0b00 if len == 0 => break,
+            0b00 => {
+                let label = message
+                    .get(position + 1..position + 1 + usize::from(len))
+                    .ok_or(NetworkError::Invalid)?;
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                name.extend(label.iter().map(|&c| char::from(c)));
+                if name.len() > MAX_NAME_LEN {
+                    return Err(NetworkError::Invalid);
+                }
+                position += 1 + usize::from(len);
+            }
+            0b11 => {
+                let &low = message.get(position + 1).ok_or(NetworkError::Invalid)?;
+                let target = usize::from(u16::from_be_bytes([len & 0x3F, low]));
+                if target >= position {
+                    return Err(NetworkError::Invalid);
+                }
+                end.get_or_insert(position + 2);
+                position = target;
+            }
+            _ => return Err(NetworkError::Invalid),
+        }
+    }
+
+    Ok((name, end.unwrap_or(position + 1)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::cell::Cell;
+
+    /// Response to an A query for `www.example.com`, with a CNAME record to `example.com`
+    /// followed by its A record, whose names are compressed.
+    const RESPONSE: [u8; 63] = [
+        // Header: ID 0x1234, standard response, 1 question, 2 answers
+        0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        // Question (offset 12): www.example.com, A, IN
+        0x03, b'w', b'w', b'w', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o',
+        b'm', 0x00, 0x00, 0x01, 0x00, 0x01,
+        // Answer (offset 33): pointer to www.example.com, CNAME, IN, TTL 3600
+        0xC0, 0x0C, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x02,
+        // RDATA (offset 45): pointer to example.com
+        0xC0, 0x10, // Answer (offset 47): pointer to the CNAME target, A, IN, TTL 300
+        0xC0, 0x2D, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x04,
+        // RDATA: 93.184.216.34
+        0x5D, 0xB8, 0xD8, 0x22,
+    ];
+
+    #[test]
+    fn test_parse_name() {
+        assert_eq!(
+            parse_name(&RESPONSE, 12).unwrap(),
+            (String::from("www.example.com"), 29)
+        );
+        // Compressed names end after their first pointer
+        assert_eq!(
+            parse_name(&RESPONSE, 33).unwrap(),
+            (String::from("www.example.com"), 35)
+        );
+        // Pointer to a pointer
+        assert_eq!(
+            parse_name(&RESPONSE, 47).unwrap(),
+            (String::from("example.com"), 49)
+        );
+
+        // Pointers must point backwards
+        let looping = [0xC0, 0x00];
+        assert_eq!(parse_name(&looping, 0), Err(NetworkError::Invalid));
+        // Truncated label
+        assert_eq!(parse_name(&[0x05, b'a'], 0), Err(NetworkError::Invalid));
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            parse_response(&RESPONSE, 0x1234),
+            Ok(Answer {
+                addr: Ipv4Addr::new(93, 184, 216, 34),
+                ttl: 300,
+            })
+        );
+        // Not a response to this query
+        assert_eq!(
+            parse_response(&RESPONSE, 0x1235),
+            Err(NetworkError::Invalid)
+        );
+        // Truncated record
+        assert_eq!(
+            parse_response(&RESPONSE[..60], 0x1234),
+            Err(NetworkError::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut nxdomain = RESPONSE[..33].to_vec();
+        nxdomain[3] = 0x83;
+        nxdomain[7] = 0;
+        assert_eq!(
+            parse_response(&nxdomain, 0x1234),
+            Err(NetworkError::NotFound)
+        );
+
+        let mut servfail = nxdomain.clone();
+        servfail[3] = 0x82;
+        assert_eq!(
+            parse_response(&servfail, 0x1234),
+            Err(NetworkError::Invalid)
+        );
+
+        let mut truncated = RESPONSE.to_vec();
+        truncated[2] |= 0x02;
+        assert_eq!(
+            parse_response(&truncated, 0x1234),
+            Err(NetworkError::Unsupported)
+        );
+
+        // No answer
+        let mut nodata = RESPONSE[..33].to_vec();
+        nodata[7] = 0;
+        assert_eq!(parse_response(&nodata, 0x1234), Err(NetworkError::NotFound));
+    }
+
+    #[test]
+    fn test_build_query() {
+        let query = build_query(0x1234, "WWW.Example.com.").unwrap();
+        assert_eq!(
+            &query[..12],
+            &[0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(&query[12..], &RESPONSE[12..33]);
+
+        assert_eq!(build_query(0, ""), Err(NetworkError::Invalid));
+        assert_eq!(build_query(0, "a..b"), Err(NetworkError::Invalid));
+        assert_eq!(build_query(0, &"a".repeat(64)), Err(NetworkError::Invalid));
+    }
+
+    /// A resolver answering with `RESPONSE` (with the ID of the query), at a settable time.
+    struct MockTransport<'a> {
+        now: &'a Cell<u64>,
+        queries: &'a Cell<usize>,
+    }
+
+    impl DnsTransport for MockTransport<'_> {
+        fn exchange(
+            &mut self,
+            server: SocketAddrV4,
+            query: &[u8],
+            response: &mut [u8],
+        ) -> NetworkResult<usize> {
+            assert_eq!(server, SocketAddrV4::new(Ipv4Addr::new(10, 0, 2, 3), PORT));
+            self.queries.set(self.queries.get() + 1);
+            response[..RESPONSE.len()].copy_from_slice(&RESPONSE);
+            response[..2].copy_from_slice(&query[..2]);
+            Ok(RESPONSE.len())
+        }
+
+        fn now(&self) -> Duration {
+            Duration::from_secs(self.now.get())
+        }
+    }
+
+    #[test]
+    fn test_resolve_cache() {
+        let now = Cell::new(1000);
+        let queries = Cell::new(0);
+        let mut dns = Dns::new(MockTransport {
+            now: &now,
+            queries: &queries,
+        });
+
+        assert_eq!(
+            dns.resolve("www.example.com"),
+            Err(NetworkError::Uninitialized)
+        );
+        dns.set_resolver(Ipv4Addr::new(10, 0, 2, 3));
+
+        let expected = Ipv4Addr::new(93, 184, 216, 34);
+        assert_eq!(dns.resolve("www.example.com"), Ok(expected));
+        assert_eq!(queries.get(), 1);
+
+        // Cached, including under another spelling
+        now.set(1299);
+        assert_eq!(dns.resolve("WWW.example.com."), Ok(expected));
+        assert_eq!(queries.get(), 1);
+
+        // Expired
+        now.set(1300);
+        assert_eq!(dns.resolve("www.example.com"), Ok(expected));
+        assert_eq!(queries.get(), 2);
+
+        dns.flush_cache();
+        assert_eq!(dns.resolve("www.example.com"), Ok(expected));
+        assert_eq!(queries.get(), 3);
+    }
+}
@@ -6,9 +6,13 @@
 extern crate alloc;
 use thiserror::Error;
 
+pub mod capture;
 pub mod l2;
 pub mod l3;
 pub mod l4;
+pub mod l7;
+pub mod socket;
+pub mod stack;
 pub mod utils;
 
 pub trait Nic {
@@ -33,6 +37,61 @@ pub trait Nic {
 
     /// Send a frame on the network.
     fn send_frame(&mut self, frame: &[u8]);
+
+    /// Checksums computed by the hardware when sending frames.
+    ///
+    /// Checksums that are offloaded are left to zero by the stack, and the driver must set
+    /// the descriptor bits that make the hardware fill them. By default, nothing is offloaded
+    /// and every checksum is computed in software.
+    fn supports_tx_checksum_offload(&self) -> ChecksumOffload {
+        ChecksumOffload::NONE
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[expect(clippy::struct_excessive_bools, reason = "One capability per protocol")]
+/// Checksums a network interface can compute in hardware, by protocol.
+///
+/// Offload is granted per protocol, as hardware commonly computes the IPv4 header checksum
+/// and the TCP/UDP ones independently. TCP and UDP checksums cover an IPv4 pseudo-header.
+pub struct ChecksumOffload {
+    /// IPv4 header checksum
+    pub ipv4: bool,
+    /// TCP checksum
+    pub tcp: bool,
+    /// UDP checksum
+    pub udp: bool,
+    /// ICMP checksum
+    pub icmp: bool,
+}
+
+impl ChecksumOffload {
+    /// Every checksum is computed in software.
+    pub const NONE: Self = Self {
+        ipv4: false,
+        tcp: false,
+        udp: false,
+        icmp: false,
+    };
+    /// Every checksum is computed in hardware.
+    pub const ALL: Self = Self {
+        ipv4: true,
+        tcp: true,
+        udp: true,
+        icmp: true,
+    };
+
+    #[must_use]
+    #[inline]
+    /// Whether the checksum of a transport protocol is computed in hardware.
+    pub const fn handles(&self, protocol: l3::ip::Protocol) -> bool {
+        match protocol {
+            l3::ip::Protocol::Tcp => self.tcp,
+            l3::ip::Protocol::Udp => self.udp,
+            l3::ip::Protocol::Icmp => self.icmp,
+            l3::ip::Protocol::Igmp => false,
+        }
+    }
 }
 
 #[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
@@ -47,6 +106,12 @@ pub enum NetworkError {
     #[error("Network controller is not initialized")]
     /// The network controller is not initialized
     Uninitialized,
+    #[error("Name does not exist")]
+    /// The name does not exist, or has no record of the requested type
+    NotFound,
+    #[error("No route to host")]
+    /// No route matches the destination
+    Unreachable,
     #[error("Unsupported operation")]
     /// The operation is not supported
     Unsupported,
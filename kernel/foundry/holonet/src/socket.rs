@@ -0,0 +1,140 @@
+//! Sockets and their receive buffers.
+//!
+//! Every socket buffers received data up to its receive buffer size, which is its
+//! high-water mark. Past it, UDP sockets drop incoming datagrams and count them,
+//! while TCP receivers advertise a window that shrinks down to zero as they fill,
+//! so that the peer stops sending until the application reads.
+
+pub mod tcp;
+pub mod udp;
+
+/// Receive buffer size of new sockets, in bytes.
+pub const DEFAULT_RECV_BUFFER_SIZE: usize = 16 * 1024;
+/// Smallest receive buffer size that can be set, in bytes.
+pub const MIN_RECV_BUFFER_SIZE: usize = 256;
+/// Largest receive buffer size that can be set, in bytes.
+pub const MAX_RECV_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An adjustable socket option, like `setsockopt` ones.
+pub enum SocketOption {
+    /// Size of the receive buffer, in bytes.
+    ///
+    /// The size is clamped between `MIN_RECV_BUFFER_SIZE` and `MAX_RECV_BUFFER_SIZE`.
+    /// Shrinking the buffer below the data it holds drops nothing:
+    /// no data is accepted until enough of it is read.
+    RecvBufferSize(usize),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Statistics of the receive buffer of a socket.
+pub struct SocketStats {
+    /// Bytes waiting to be read.
+    pub buffered: usize,
+    /// Size of the receive buffer.
+    pub capacity: usize,
+    /// Largest number of bytes ever buffered.
+    pub high_water: usize,
+    /// Number of datagrams dropped because the buffer was full.
+    ///
+    /// Always zero for TCP, where the window holds the peer back instead.
+    pub dropped: u64,
+}
+
+impl SocketStats {
+    #[must_use]
+    #[inline]
+    /// Fill level of the buffer, in percent.
+    ///
+    /// Exceeds 100 if the buffer was shrunk below the data it holds.
+    pub const fn fill_percent(&self) -> usize {
+        match (self.buffered * 100).checked_div(self.capacity) {
+            Some(percent) => percent,
+            None => 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Byte accounting of a bounded receive buffer.
+struct RecvLimit {
+    buffered: usize,
+    capacity: usize,
+    high_water: usize,
+}
+
+impl RecvLimit {
+    #[must_use]
+    const fn new() -> Self {
+        Self {
+            buffered: 0,
+            capacity: DEFAULT_RECV_BUFFER_SIZE,
+            high_water: 0,
+        }
+    }
+
+    #[must_use]
+    const fn free(&self) -> usize {
+        self.capacity.saturating_sub(self.buffered)
+    }
+
+    fn add(&mut self, len: usize) {
+        self.buffered += len;
+        self.high_water = self.high_water.max(self.buffered);
+    }
+
+    const fn remove(&mut self, len: usize) {
+        self.buffered -= len;
+    }
+
+    const fn set_option(&mut self, option: SocketOption) {
+        match option {
+            SocketOption::RecvBufferSize(size) => {
+                self.capacity = if size < MIN_RECV_BUFFER_SIZE {
+                    MIN_RECV_BUFFER_SIZE
+                } else if size > MAX_RECV_BUFFER_SIZE {
+                    MAX_RECV_BUFFER_SIZE
+                } else {
+                    size
+                };
+            }
+        }
+    }
+
+    #[must_use]
+    const fn stats(&self, dropped: u64) -> SocketStats {
+        SocketStats {
+            buffered: self.buffered,
+            capacity: self.capacity,
+            high_water: self.high_water,
+            dropped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recv_buffer_size() {
+        let mut limit = RecvLimit::new();
+        assert_eq!(limit.free(), DEFAULT_RECV_BUFFER_SIZE);
+
+        limit.set_option(SocketOption::RecvBufferSize(1));
+        assert_eq!(limit.capacity, MIN_RECV_BUFFER_SIZE);
+        limit.set_option(SocketOption::RecvBufferSize(usize::MAX));
+        assert_eq!(limit.capacity, MAX_RECV_BUFFER_SIZE);
+
+        limit.set_option(SocketOption::RecvBufferSize(1000));
+        limit.add(600);
+        limit.remove(200);
+        limit.set_option(SocketOption::RecvBufferSize(300));
+        assert_eq!(limit.free(), 0);
+
+        let stats = limit.stats(0);
+        assert_eq!(stats.buffered, 400);
+        assert_eq!(stats.high_water, 600);
+        assert_eq!(stats.fill_percent(), 133);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{RecvLimit, SocketOption, SocketStats};
+use alloc::collections::VecDeque;
+
+/// The receive side of a TCP connection, holding in-order data until it is read.
+///
+/// The window to advertise is the free space of the buffer, so that the peer never
+/// sends more than what fits. It is zero when the buffer is full.
+pub struct TcpReceiver {
+    data: VecDeque<u8>,
+    limit: RecvLimit,
+}
+
+impl Default for TcpReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpReceiver {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            data: VecDeque::new(),
+            limit: RecvLimit::new(),
+        }
+    }
+
+    /// Buffer in-order data of a segment.
+    ///
+    /// Returns the number of bytes accepted, which are to be acknowledged.
+    /// Bytes past the window are not accepted and will be retransmitted by the peer.
+    pub fn push(&mut self, data: &[u8]) -> usize {
+        let len = data.len().min(self.limit.free());
+        self.data.extend(&data[..len]);
+        self.limit.add(len);
+        len
+    }
+
+    /// Read buffered data into `buffer`.
+    ///
+    /// Returns the number of bytes read.
+    pub fn read(&mut self, buffer: &mut [u8]) -> usize {
+        let len = buffer.len().min(self.data.len());
+        for (dst, src) in buffer.iter_mut().zip(self.data.drain(..len)) {
+            *dst = src;
+        }
+        self.limit.remove(len);
+        len
+    }
+
+    #[must_use]
+    #[inline]
+    /// Window to advertise in the segments sent to the peer.
+    ///
+    /// Window scaling is not supported, so the window is capped to `u16::MAX`.
+    pub fn window(&self) -> u16 {
+        u16::try_from(self.limit.free()).unwrap_or(u16::MAX)
+    }
+
+    #[inline]
+    pub const fn set_option(&mut self, option: SocketOption) {
+        self.limit.set_option(option);
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn stats(&self) -> SocketStats {
+        self.limit.stats(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::socket::DEFAULT_RECV_BUFFER_SIZE;
+
+    #[test]
+    fn test_window_shrinks() {
+        let mut receiver = TcpReceiver::new();
+        assert_eq!(
+            usize::from(receiver.window()),
+            DEFAULT_RECV_BUFFER_SIZE.min(usize::from(u16::MAX))
+        );
+
+        receiver.set_option(SocketOption::RecvBufferSize(1000));
+        assert_eq!(receiver.window(), 1000);
+        assert_eq!(receiver.push(&[1; 300]), 300);
+        assert_eq!(receiver.window(), 700);
+
+        // The segment is cut at the window, which is then closed
+        assert_eq!(receiver.push(&[2; 800]), 700);
+        assert_eq!(receiver.window(), 0);
+        assert_eq!(receiver.push(&[3; 10]), 0);
+
+        let stats = receiver.stats();
+        assert_eq!(stats.buffered, 1000);
+        assert_eq!(stats.dropped, 0);
+
+        // Reading opens the window again
+        let mut buffer = [0; 400];
+        assert_eq!(receiver.read(&mut buffer), 400);
+        assert_eq!(&buffer[..300], &[1; 300]);
+        assert_eq!(&buffer[300..], &[2; 100]);
+        assert_eq!(receiver.window(), 400);
+    }
+
+    #[test]
+    fn test_window_cap() {
+        let mut receiver = TcpReceiver::new();
+        receiver.set_option(SocketOption::RecvBufferSize(100_000));
+        assert_eq!(receiver.window(), u16::MAX);
+        assert_eq!(receiver.push(&alloc::vec![0; 50_000]), 50_000);
+        assert_eq!(receiver.window(), 50_000);
+    }
+}
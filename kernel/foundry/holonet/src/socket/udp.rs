@@ -0,0 +1,134 @@
+use super::{RecvLimit, SocketOption, SocketStats};
+use crate::l4::udp::SocketAddrV4;
+use alloc::{collections::VecDeque, vec::Vec};
+
+/// A datagram waiting to be read.
+struct Datagram {
+    src: SocketAddrV4,
+    payload: Vec<u8>,
+}
+
+/// A UDP socket, bound to a local address.
+///
+/// Datagrams that do not fit in the receive buffer are dropped whole.
+pub struct UdpSocket {
+    local_addr: SocketAddrV4,
+    queue: VecDeque<Datagram>,
+    limit: RecvLimit,
+    dropped: u64,
+}
+
+impl UdpSocket {
+    #[must_use]
+    #[inline]
+    pub const fn new(local_addr: SocketAddrV4) -> Self {
+        Self {
+            local_addr,
+            queue: VecDeque::new(),
+            limit: RecvLimit::new(),
+            dropped: 0,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn local_addr(&self) -> SocketAddrV4 {
+        self.local_addr
+    }
+
+    /// Queue a datagram received from `src`.
+    ///
+    /// Returns whether the datagram was queued.
+    /// It is dropped if it does not fit in the free space of the receive buffer.
+    pub fn deliver(&mut self, src: SocketAddrV4, payload: &[u8]) -> bool {
+        if payload.len() > self.limit.free() {
+            self.dropped += 1;
+            return false;
+        }
+
+        self.limit.add(payload.len());
+        self.queue.push_back(Datagram {
+            src,
+            payload: payload.to_vec(),
+        });
+        true
+    }
+
+    /// Read the oldest datagram into `buffer`.
+    ///
+    /// Returns the length of the datagram and its source, or `None` if there is none.
+    /// As with `recv_from`, the part of the datagram that does not fit in `buffer` is lost.
+    pub fn recv_from(&mut self, buffer: &mut [u8]) -> Option<(usize, SocketAddrV4)> {
+        let datagram = self.queue.pop_front()?;
+        self.limit.remove(datagram.payload.len());
+
+        let len = datagram.payload.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&datagram.payload[..len]);
+        Some((datagram.payload.len(), datagram.src))
+    }
+
+    #[inline]
+    pub const fn set_option(&mut self, option: SocketOption) {
+        self.limit.set_option(option);
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn stats(&self) -> SocketStats {
+        self.limit.stats(self.dropped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::l3::ip::Ipv4Addr;
+
+    const LOCAL: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 7);
+    const PEER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 40000);
+
+    #[test]
+    fn test_recv_from() {
+        let mut socket = UdpSocket::new(LOCAL);
+        assert_eq!(socket.local_addr(), LOCAL);
+        assert!(socket.recv_from(&mut [0; 8]).is_none());
+
+        assert!(socket.deliver(PEER, b"hello"));
+        assert!(socket.deliver(PEER, b"world!"));
+        assert_eq!(socket.stats().buffered, 11);
+
+        let mut buffer = [0; 8];
+        assert_eq!(socket.recv_from(&mut buffer), Some((5, PEER)));
+        assert_eq!(&buffer[..5], b"hello");
+        // Truncated to the buffer
+        assert_eq!(socket.recv_from(&mut buffer[..3]), Some((6, PEER)));
+        assert_eq!(&buffer[..3], b"wor");
+        assert_eq!(socket.stats().buffered, 0);
+    }
+
+    #[test]
+    fn test_drop_when_full() {
+        let mut socket = UdpSocket::new(LOCAL);
+        socket.set_option(SocketOption::RecvBufferSize(1000));
+
+        let datagram = [0xAB; 400];
+        assert!(socket.deliver(PEER, &datagram));
+        assert!(socket.deliver(PEER, &datagram));
+        // 200 bytes are left
+        assert!(!socket.deliver(PEER, &datagram));
+        assert!(!socket.deliver(PEER, &datagram));
+        assert!(socket.deliver(PEER, &datagram[..200]));
+        assert!(!socket.deliver(PEER, &[0]));
+
+        let stats = socket.stats();
+        assert_eq!(stats.dropped, 3);
+        assert_eq!(stats.buffered, 1000);
+        assert_eq!(stats.fill_percent(), 100);
+
+        // Reading makes room again
+        assert_eq!(socket.recv_from(&mut [0; 400]), Some((400, PEER)));
+        assert!(socket.deliver(PEER, &datagram));
+        assert_eq!(socket.stats().high_water, 1000);
+        assert_eq!(socket.stats().dropped, 3);
+    }
+}
@@ -0,0 +1,711 @@
+//! Network interfaces and IPv4 traffic.
+//!
+//! The stack owns its interfaces, routes outbound packets through them and
+//! resolves next hops with ARP. Packets to a next hop that is not resolved yet
+//! are held until the ARP reply is processed.
+//! The loopback interface needs no resolution: its frames never leave the host.
+//!
+//! Checksums are computed in software, unless the egress interface offloads them
+//! (see `Nic::supports_tx_checksum_offload`).
+
+use crate::{
+    ChecksumOffload, NetworkError, NetworkResult, Nic,
+    l2::{
+        ethernet::{EtherType, EthernetFrame, MacAddress, ParsedFrame},
+        loopback::{LOOPBACK_ADDR, LOOPBACK_PREFIX_LEN, LoopbackNic},
+    },
+    l3::{
+        arp,
+        ip::{self, Ipv4Addr, Protocol},
+        route::{InterfaceId, Route, RoutingTable, prefix_mask},
+    },
+    l4::{icmp, tcp, udp},
+};
+use alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+
+/// Time to live of outbound packets.
+const DEFAULT_TTL: u8 = 64;
+/// Number of packets held while their next hop is resolved.
+/// When full, the oldest packet is dropped.
+const MAX_PENDING: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// IPv4 configuration of an interface.
+pub struct InterfaceConfig {
+    pub addr: Ipv4Addr,
+    pub prefix_len: u8,
+    /// Router of the network, used as the default route.
+    pub gateway: Option<Ipv4Addr>,
+}
+
+impl InterfaceConfig {
+    #[must_use]
+    #[inline]
+    /// Return the network mask of the interface.
+    pub const fn mask(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(prefix_mask(self.prefix_len))
+    }
+}
+
+struct Interface {
+    nic: Box<dyn Nic + Send>,
+    config: InterfaceConfig,
+    loopback: bool,
+}
+
+impl Interface {
+    /// Whether an IPv4 packet to `dst` received on the interface is for the host.
+    fn accepts(&self, dst: Ipv4Addr) -> bool {
+        dst == self.config.addr
+            || dst == Ipv4Addr::BROADCAST
+            || (self.loopback && {
+                let mask = prefix_mask(self.config.prefix_len);
+                dst.to_bits() & mask == self.config.addr.to_bits() & mask
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An IPv4 packet received by the stack.
+pub struct Received {
+    pub src_addr: Ipv4Addr,
+    pub dst_addr: Ipv4Addr,
+    pub protocol: Protocol,
+    pub payload: Vec<u8>,
+}
+
+/// A packet waiting for its next hop to be resolved.
+struct Pending {
+    interface: InterfaceId,
+    next_hop: Ipv4Addr,
+    packet: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct NetStack {
+    interfaces: Vec<Interface>,
+    routes: RoutingTable,
+    arp_cache: BTreeMap<Ipv4Addr, MacAddress>,
+    pending: Vec<Pending>,
+}
+
+impl NetStack {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            interfaces: Vec::new(),
+            routes: RoutingTable::new(),
+            arp_cache: BTreeMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Register an interface.
+    ///
+    /// A route to the network of the interface is added, as well as a default route
+    /// if the interface has a gateway. Default routes of interfaces registered
+    /// earlier take precedence.
+    pub fn add_interface(
+        &mut self,
+        nic: Box<dyn Nic + Send>,
+        config: InterfaceConfig,
+    ) -> InterfaceId {
+        self.register(nic, config, false)
+    }
+
+    /// Register a loopback interface, bound to `127.0.0.1/8`.
+    pub fn add_loopback(&mut self) -> InterfaceId {
+        self.register(
+            Box::new(LoopbackNic::new()),
+            InterfaceConfig {
+                addr: LOOPBACK_ADDR,
+                prefix_len: LOOPBACK_PREFIX_LEN,
+                gateway: None,
+            },
+            true,
+        )
+    }
+
+    fn register(
+        &mut self,
+        nic: Box<dyn Nic + Send>,
+        config: InterfaceConfig,
+        loopback: bool,
+    ) -> InterfaceId {
+        let id = InterfaceId(self.interfaces.len());
+        self.interfaces.push(Interface {
+            nic,
+            config,
+            loopback,
+        });
+
+        self.routes
+            .add(Route::new(config.addr, config.prefix_len, None, id));
+        if let Some(gateway) = config.gateway {
+            self.routes.add(Route::default_via(gateway, id));
+        }
+
+        id
+    }
+
+    #[inline]
+    /// Add a static route.
+    pub fn add_route(&mut self, route: Route) {
+        self.routes.add(route);
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn routes(&self) -> &RoutingTable {
+        &self.routes
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn interface_config(&self, id: InterfaceId) -> Option<&InterfaceConfig> {
+        self.interfaces.get(id.0).map(|iface| &iface.config)
+    }
+
+    /// Select the egress interface and the next hop of a destination.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unreachable` if no route matches the destination.
+    pub fn route(&self, dst: Ipv4Addr) -> NetworkResult<(InterfaceId, Ipv4Addr)> {
+        self.routes
+            .lookup(dst)
+            .map(|route| (route.interface(), route.next_hop(dst)))
+            .ok_or(NetworkError::Unreachable)
+    }
+
+    /// Send an IPv4 packet carrying `payload`.
+    ///
+    /// The IPv4 header checksum and the checksum of the TCP, UDP or ICMP `payload`
+    /// are filled, unless the egress interface computes them in hardware.
+    ///
+    /// If the next hop is not resolved yet, an ARP request is sent
+    /// and the packet is sent once the reply is processed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Unreachable` if no route matches the destination
+    /// and `Invalid` if the payload does not fit in a packet.
+    pub fn send_ip(
+        &mut self,
+        dst: Ipv4Addr,
+        protocol: Protocol,
+        payload: &[u8],
+    ) -> NetworkResult<()> {
+        let (interface, next_hop) = self.route(dst)?;
+        let config = self.interfaces[interface.0].config;
+
+        let repr = ip::Repr {
+            src_addr: config.addr,
+            dst_addr: dst,
+            protocol,
+            payload_len: payload.len(),
+            ttl: DEFAULT_TTL,
+            flags: ip::Flags {
+                reserved: false,
+                dont_fragment: true,
+                more_fragments: false,
+            },
+        };
+        if u16::try_from(repr.buffer_len()).is_err() {
+            return Err(NetworkError::Invalid);
+        }
+        let offload = self.interfaces[interface.0]
+            .nic
+            .supports_tx_checksum_offload();
+        let mut packet = ip::Packet::new_unchecked(vec![0; repr.buffer_len()]);
+        if offload.ipv4 {
+            repr.emit_offloaded(&mut packet);
+        } else {
+            repr.emit(&mut packet);
+        }
+        packet.payload_mut().copy_from_slice(payload);
+        fill_transport_checksum(&repr, offload, packet.payload_mut());
+        let packet = packet.into_inner();
+
+        if self.interfaces[interface.0].loopback {
+            self.transmit(interface, MacAddress::default(), EtherType::IpV4, &packet);
+        } else if let Some(&mac) = self.arp_cache.get(&next_hop) {
+            self.transmit(interface, mac, EtherType::IpV4, &packet);
+        } else {
+            if self.pending.len() == MAX_PENDING {
+                self.pending.remove(0);
+            }
+            let already_requested = self.pending.iter().any(|p| p.next_hop == next_hop);
+            self.pending.push(Pending {
+                interface,
+                next_hop,
+                packet,
+            });
+            if !already_requested {
+                self.request_arp(interface, next_hop);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Process an ARP packet received on an interface.
+    ///
+    /// Requests for the address of the interface are answered, and the addresses
+    /// of senders are learnt, sending the packets that were waiting for them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Invalid` or `Unsupported` if the packet is not an Ethernet/IPv4 ARP packet,
+    /// and `Absent` if the interface does not exist.
+    pub fn process_arp(&mut self, interface: InterfaceId, packet: &[u8]) -> NetworkResult<()> {
+        let addr = self
+            .interface_config(interface)
+            .ok_or(NetworkError::Absent)?
+            .addr;
+        let arp::Repr::EthernetIpv4 {
+            operation,
+            source_hardware_addr,
+            source_protocol_addr,
+            target_protocol_addr,
+            ..
+        } = arp::Repr::parse(&arp::Packet::new(packet)?)?;
+
+        // Mappings are only learnt from packets aimed at us,
+        // or refreshed if they are already known.
+        let for_us = target_protocol_addr == addr;
+        if for_us || self.arp_cache.contains_key(&source_protocol_addr) {
+            self.arp_cache
+                .insert(source_protocol_addr, source_hardware_addr);
+            self.flush_pending(source_protocol_addr, source_hardware_addr);
+        }
+
+        if for_us && operation == arp::Operation::Request {
+            let reply = arp::Repr::EthernetIpv4 {
+                operation: arp::Operation::Reply,
+                source_hardware_addr: self.interfaces[interface.0].nic.mac_address(),
+                source_protocol_addr: addr,
+                target_hardware_addr: source_hardware_addr,
+                target_protocol_addr: source_protocol_addr,
+            };
+            self.transmit_arp(interface, source_hardware_addr, &reply);
+        }
+
+        Ok(())
+    }
+
+    /// Process the frames received on an interface, until an IPv4 packet for the host is found.
+    ///
+    /// ARP packets are processed along the way.
+    /// Invalid frames, and packets for other hosts, are dropped.
+    pub fn receive(&mut self, interface: InterfaceId) -> Option<Received> {
+        loop {
+            let nic = &mut self.interfaces.get_mut(interface.0)?.nic;
+            let frame = nic.poll_frame()?.to_vec();
+            nic.consume_frame();
+
+            let Ok(frame) = ParsedFrame::parse(&frame) else {
+                continue;
+            };
+            // Interfaces are not VLAN-aware: tagged frames belong to other networks
+            if frame.vlan.is_some() {
+                continue;
+            }
+            match frame.protocol() {
+                Some(EtherType::Arp) => {
+                    let _ = self.process_arp(interface, frame.payload);
+                }
+                Some(EtherType::IpV4) => {
+                    if let Some(received) = self.parse_ip(interface, frame.payload) {
+                        return Some(received);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_ip(&self, interface: InterfaceId, packet: &[u8]) -> Option<Received> {
+        let packet = ip::Packet::new(packet).ok()?;
+        let header_len = packet.header_len();
+        let total_len = usize::from(packet.total_len());
+        // Frames may be padded past the end of the packet
+        if packet.version() != 4 || header_len < 20 || header_len > total_len {
+            return None;
+        }
+        let data = packet.as_ref().get(..total_len)?;
+        // `Packet::protocol` panics on unknown protocols
+        let protocol = Protocol::try_from(data[9]).ok()?;
+        let dst_addr = packet.dst_addr();
+        if !self.interfaces[interface.0].accepts(dst_addr) {
+            return None;
+        }
+
+        Some(Received {
+            src_addr: packet.src_addr(),
+            dst_addr,
+            protocol,
+            payload: data[header_len..].to_vec(),
+        })
+    }
+
+    fn flush_pending(&mut self, next_hop: Ipv4Addr, mac: MacAddress) {
+        let (ready, waiting) = core::mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|pending| pending.next_hop == next_hop);
+        self.pending = waiting;
+        for pending in ready {
+            self.transmit(pending.interface, mac, EtherType::IpV4, &pending.packet);
+        }
+    }
+
+    fn request_arp(&mut self, interface: InterfaceId, target: Ipv4Addr) {
+        let request = arp::Repr::EthernetIpv4 {
+            operation: arp::Operation::Request,
+            source_hardware_addr: self.interfaces[interface.0].nic.mac_address(),
+            source_protocol_addr: self.interfaces[interface.0].config.addr,
+            target_hardware_addr: MacAddress::default(),
+            target_protocol_addr: target,
+        };
+        self.transmit_arp(interface, MacAddress::BROADCAST, &request);
+    }
+
+    fn transmit_arp(&mut self, interface: InterfaceId, dst: MacAddress, repr: &arp::Repr) {
+        let mut packet = arp::Packet::new_unchecked(vec![0; repr.buffer_len()]);
+        repr.emit(&mut packet);
+        self.transmit(interface, dst, EtherType::Arp, &packet.into_inner());
+    }
+
+    fn transmit(
+        &mut self,
+        interface: InterfaceId,
+        dst: MacAddress,
+        ethertype: EtherType,
+        payload: &[u8],
+    ) {
+        let nic = &mut self.interfaces[interface.0].nic;
+        let frame = EthernetFrame::new(dst, nic.mac_address(), ethertype)
+            .payload(payload)
+            .build();
+        nic.send_frame(&frame);
+    }
+}
+
+/// Fill the checksum of a transport packet, or zero it if the hardware computes it.
+///
+/// Packets too short for their header are left untouched.
+fn fill_transport_checksum(repr: &ip::Repr, offload: ChecksumOffload, payload: &mut [u8]) {
+    let offloaded = offload.handles(repr.protocol);
+    match repr.protocol {
+        Protocol::Tcp => {
+            let len = payload.len();
+            if let Ok(mut packet) = tcp::Packet::new(payload)
+                && packet.header_len() <= len
+            {
+                if offloaded {
+                    packet.set_checksum(0);
+                } else {
+                    packet.fill_checksum(repr.src_addr, repr.dst_addr);
+                }
+            }
+        }
+        Protocol::Udp => {
+            if let Ok(mut packet) = udp::Packet::new(payload) {
+                if offloaded {
+                    packet.set_checksum(0);
+                } else {
+                    packet.fill_checksum(repr.src_addr, repr.dst_addr);
+                }
+            }
+        }
+        Protocol::Icmp => {
+            if let Ok(mut packet) = icmp::Packet::new(payload) {
+                if offloaded {
+                    packet.set_checksum(0);
+                } else {
+                    packet.fill_checksum();
+                }
+            }
+        }
+        Protocol::Igmp => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::l2::ethernet;
+    use alloc::sync::Arc;
+    use std::sync::Mutex;
+
+    extern crate std;
+
+    type Sent = Arc<Mutex<Vec<Vec<u8>>>>;
+
+    /// A NIC recording the frames it sends.
+    struct MockNic {
+        mac: MacAddress,
+        sent: Sent,
+        offload: ChecksumOffload,
+    }
+
+    impl Nic for MockNic {
+        fn mac_address(&self) -> MacAddress {
+            self.mac
+        }
+
+        fn poll_frame(&self) -> Option<&[u8]> {
+            None
+        }
+
+        fn consume_frame(&mut self) {}
+
+        fn send_frame(&mut self, frame: &[u8]) {
+            self.sent.lock().unwrap().push(frame.to_vec());
+        }
+
+        fn supports_tx_checksum_offload(&self) -> ChecksumOffload {
+            self.offload
+        }
+    }
+
+    fn add_nic(stack: &mut NetStack, mac: u8, config: InterfaceConfig) -> (InterfaceId, Sent) {
+        add_offloading_nic(stack, mac, config, ChecksumOffload::NONE)
+    }
+
+    fn add_offloading_nic(
+        stack: &mut NetStack,
+        mac: u8,
+        config: InterfaceConfig,
+        offload: ChecksumOffload,
+    ) -> (InterfaceId, Sent) {
+        let sent = Sent::default();
+        let nic = MockNic {
+            mac: MacAddress::new([0x52, 0x54, 0, 0, 0, mac]),
+            sent: sent.clone(),
+            offload,
+        };
+        (stack.add_interface(Box::new(nic), config), sent)
+    }
+
+    fn arp_packet(repr: &arp::Repr) -> Vec<u8> {
+        let mut packet = arp::Packet::new_unchecked(vec![0; repr.buffer_len()]);
+        repr.emit(&mut packet);
+        packet.into_inner()
+    }
+
+    #[test]
+    fn test_send_ip_resolves_next_hop() {
+        let mut stack = NetStack::new();
+        let (eth0, sent0) = add_nic(
+            &mut stack,
+            1,
+            InterfaceConfig {
+                addr: Ipv4Addr::new(10, 0, 2, 15),
+                prefix_len: 24,
+                gateway: Some(Ipv4Addr::new(10, 0, 2, 2)),
+            },
+        );
+        let (_eth1, sent1) = add_nic(
+            &mut stack,
+            2,
+            InterfaceConfig {
+                addr: Ipv4Addr::new(192, 168, 1, 10),
+                prefix_len: 24,
+                gateway: None,
+            },
+        );
+
+        assert_eq!(stack.route(Ipv4Addr::new(1, 1, 1, 1)).unwrap().0, eth0);
+
+        // Off-link destinations go through the gateway
+        stack
+            .send_ip(Ipv4Addr::new(1, 1, 1, 1), Protocol::Udp, &[0xAA; 8])
+            .unwrap();
+        stack
+            .send_ip(Ipv4Addr::new(8, 8, 8, 8), Protocol::Udp, &[0xBB; 8])
+            .unwrap();
+        let sent = sent0.lock().unwrap().clone();
+        // A single ARP request for the gateway
+        assert_eq!(sent.len(), 1);
+        let frame = ethernet::Frame::new_unchecked(&sent[0][..]);
+        assert_eq!(frame.dst_addr(), MacAddress::BROADCAST);
+        assert_eq!(frame.ethertype(), EtherType::Arp);
+        let arp::Repr::EthernetIpv4 {
+            target_protocol_addr,
+            ..
+        } = arp::Repr::parse(&arp::Packet::new(frame.payload()).unwrap()).unwrap();
+        assert_eq!(target_protocol_addr, Ipv4Addr::new(10, 0, 2, 2));
+        assert!(sent1.lock().unwrap().is_empty());
+
+        let gateway_mac = MacAddress::new([0x52, 0x55, 0x0A, 0, 2, 2]);
+        let reply = arp_packet(&arp::Repr::EthernetIpv4 {
+            operation: arp::Operation::Reply,
+            source_hardware_addr: gateway_mac,
+            source_protocol_addr: Ipv4Addr::new(10, 0, 2, 2),
+            target_hardware_addr: MacAddress::new([0x52, 0x54, 0, 0, 0, 1]),
+            target_protocol_addr: Ipv4Addr::new(10, 0, 2, 15),
+        });
+        stack.process_arp(eth0, &reply).unwrap();
+
+        let sent = sent0.lock().unwrap().clone();
+        assert_eq!(sent.len(), 3);
+        for (frame, dst) in sent[1..].iter().zip([[1, 1, 1, 1], [8, 8, 8, 8]]) {
+            let frame = ethernet::Frame::new_unchecked(&frame[..]);
+            assert_eq!(frame.dst_addr(), gateway_mac);
+            assert_eq!(frame.ethertype(), EtherType::IpV4);
+            let packet = ip::Packet::new_unchecked(frame.payload());
+            assert_eq!(packet.src_addr(), Ipv4Addr::new(10, 0, 2, 15));
+            assert_eq!(packet.dst_addr(), Ipv4Addr::from(dst));
+            // Frames are padded to the minimum length
+            assert_eq!(frame.as_ref().len(), ethernet::MIN_FRAME_LEN);
+            assert_eq!(packet.total_len(), 20 + 8);
+        }
+    }
+
+    #[test]
+    fn test_answer_arp_request() {
+        let mut stack = NetStack::new();
+        let (eth0, sent) = add_nic(
+            &mut stack,
+            1,
+            InterfaceConfig {
+                addr: Ipv4Addr::new(192, 168, 1, 10),
+                prefix_len: 24,
+                gateway: None,
+            },
+        );
+        assert_eq!(
+            stack.interface_config(eth0).unwrap().mask(),
+            Ipv4Addr::new(255, 255, 255, 0)
+        );
+        assert_eq!(
+            stack.send_ip(Ipv4Addr::new(8, 8, 8, 8), Protocol::Udp, &[]),
+            Err(NetworkError::Unreachable)
+        );
+
+        let peer_mac = MacAddress::new([0x52, 0x54, 0, 0, 0, 9]);
+        let request = arp_packet(&arp::Repr::EthernetIpv4 {
+            operation: arp::Operation::Request,
+            source_hardware_addr: peer_mac,
+            source_protocol_addr: Ipv4Addr::new(192, 168, 1, 9),
+            target_hardware_addr: MacAddress::default(),
+            target_protocol_addr: Ipv4Addr::new(192, 168, 1, 10),
+        });
+        stack.process_arp(eth0, &request).unwrap();
+        assert_eq!(sent.lock().unwrap().len(), 1);
+
+        // The sender was learnt: no request is needed
+        stack
+            .send_ip(Ipv4Addr::new(192, 168, 1, 9), Protocol::Icmp, &[0; 4])
+            .unwrap();
+        let sent = sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 2);
+        let frame = ethernet::Frame::new_unchecked(&sent[1][..]);
+        assert_eq!(frame.dst_addr(), peer_mac);
+        assert_eq!(frame.ethertype(), EtherType::IpV4);
+    }
+
+    #[test]
+    fn test_checksum_offload() {
+        use crate::utils::checksum;
+
+        let ip_only = ChecksumOffload {
+            ipv4: true,
+            ..ChecksumOffload::NONE
+        };
+        for offload in [ChecksumOffload::NONE, ChecksumOffload::ALL, ip_only] {
+            let mut stack = NetStack::new();
+            let (eth0, sent) = add_offloading_nic(
+                &mut stack,
+                1,
+                InterfaceConfig {
+                    addr: Ipv4Addr::new(10, 0, 2, 15),
+                    prefix_len: 24,
+                    gateway: None,
+                },
+                offload,
+            );
+            let peer = Ipv4Addr::new(10, 0, 2, 3);
+            let request = arp_packet(&arp::Repr::EthernetIpv4 {
+                operation: arp::Operation::Request,
+                source_hardware_addr: MacAddress::new([0x52, 0x54, 0, 0, 0, 3]),
+                source_protocol_addr: peer,
+                target_hardware_addr: MacAddress::default(),
+                target_protocol_addr: Ipv4Addr::new(10, 0, 2, 15),
+            });
+            stack.process_arp(eth0, &request).unwrap();
+
+            // Source port, destination port, length, checksum and 4 bytes of data
+            let datagram = [0xC0, 0x00, 0, 53, 0, 12, 0, 0, 1, 2, 3, 4];
+            stack.send_ip(peer, Protocol::Udp, &datagram).unwrap();
+
+            let sent = sent.lock().unwrap().clone();
+            let frame = ethernet::Frame::new_unchecked(&sent[1][..]);
+            let packet = ip::Packet::new_unchecked(frame.payload());
+            let datagram = udp::Packet::new_unchecked(packet.payload());
+
+            if offload.ipv4 {
+                assert_eq!(packet.checksum(), 0);
+            } else {
+                // A valid header sums to zero
+                assert_eq!(checksum(&frame.payload()[..20]), 0);
+            }
+
+            if offload.udp {
+                assert_eq!(datagram.checksum(), 0);
+            } else {
+                let mut copy = udp::Packet::new_unchecked(packet.payload().to_vec());
+                copy.fill_checksum(Ipv4Addr::new(10, 0, 2, 15), peer);
+                assert_ne!(datagram.checksum(), 0);
+                assert_eq!(datagram.checksum(), copy.checksum());
+            }
+        }
+    }
+
+    #[test]
+    fn test_loopback_udp() {
+        let mut stack = NetStack::new();
+        let (eth0, sent) = add_nic(
+            &mut stack,
+            1,
+            InterfaceConfig {
+                addr: Ipv4Addr::new(10, 0, 2, 15),
+                prefix_len: 24,
+                gateway: Some(Ipv4Addr::new(10, 0, 2, 2)),
+            },
+        );
+        let lo = stack.add_loopback();
+
+        // The loopback network wins over the default route
+        assert_eq!(stack.route(Ipv4Addr::LOCALHOST).unwrap().0, lo);
+        assert_eq!(stack.route(Ipv4Addr::new(127, 1, 2, 3)).unwrap().0, lo);
+        assert_eq!(stack.route(Ipv4Addr::new(1, 1, 1, 1)).unwrap().0, eth0);
+        assert!(stack.receive(lo).is_none());
+
+        let mut datagram = udp::Packet::new_unchecked(vec![0; 8 + 5]);
+        datagram.set_src_port(40000);
+        datagram.set_dst_port(7);
+        datagram.set_len(13);
+        datagram.payload_mut().copy_from_slice(b"hello");
+        stack
+            .send_ip(Ipv4Addr::LOCALHOST, Protocol::Udp, datagram.as_ref())
+            .unwrap();
+
+        // No ARP, and nothing leaves through the other interfaces
+        assert!(sent.lock().unwrap().is_empty());
+
+        let received = stack.receive(lo).unwrap();
+        assert_eq!(received.src_addr, Ipv4Addr::LOCALHOST);
+        assert_eq!(received.dst_addr, Ipv4Addr::LOCALHOST);
+        assert_eq!(received.protocol, Protocol::Udp);
+        let echoed = udp::Packet::new(&received.payload[..]).unwrap();
+        assert_eq!(echoed.src_port(), 40000);
+        assert_eq!(echoed.dst_port(), 7);
+        assert_eq!(echoed.payload(), b"hello");
+
+        assert!(stack.receive(lo).is_none());
+        assert!(stack.receive(eth0).is_none());
+    }
+}
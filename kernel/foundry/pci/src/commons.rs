@@ -198,6 +198,19 @@ pub struct Device {
 }
 
 impl Device {
+    #[must_use]
+    #[inline]
+    /// Returns the address of a register in the configuration space of the device.
+    pub(crate) const fn register(&self, register_offset: u8) -> PciAddress {
+        PciAddress::new(
+            self.sbdf.segment(),
+            self.sbdf.bus(),
+            self.sbdf.device(),
+            self.sbdf.function(),
+            register_offset,
+        )
+    }
+
     #[must_use]
     #[inline]
     pub const fn id(&self) -> u16 {
@@ -235,6 +248,49 @@ impl Device {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The registers of the standard header of a device that a reset clears.
+pub struct ConfigSnapshot {
+    pub(super) command: u16,
+    /// Raw values of the six BAR registers, including the upper halves of 64-bit BARs.
+    pub(super) bars: [u32; 6],
+    pub(super) cache_line_size: u8,
+    pub(super) latency_timer: u8,
+    pub(super) interrupt_line: u8,
+}
+
+impl ConfigSnapshot {
+    #[must_use]
+    #[inline]
+    pub const fn command(&self) -> u16 {
+        self.command
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn bars(&self) -> [u32; 6] {
+        self.bars
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn cache_line_size(&self) -> u8 {
+        self.cache_line_size
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn latency_timer(&self) -> u8 {
+        self.latency_timer
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn interrupt_line(&self) -> u8 {
+        self.interrupt_line
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PciAddress {
     pub(super) enable: bool,
@@ -413,6 +469,13 @@ impl CapabilityHeader {
         self.id
     }
 
+    #[must_use]
+    #[inline]
+    /// Offset of the capability in the configuration space of the device.
+    pub const fn offset(&self) -> u8 {
+        self.pci_addr.register_offset
+    }
+
     #[must_use]
     #[inline]
     pub const fn next(&self) -> u8 {
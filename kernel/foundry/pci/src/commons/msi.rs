@@ -3,6 +3,62 @@ use core::marker::PhantomData;
 
 use super::super::{PciHandler, commons::CapabilityHeader, iter_capabilities};
 use super::{MsiHelper, PciAddress};
+use beskar_core::arch::PhysAddr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The address and data a device writes to raise an MSI or MSI-X interrupt.
+pub struct MsiMessage {
+    address: u64,
+    data: u32,
+}
+
+impl MsiMessage {
+    #[must_use]
+    #[inline]
+    /// Builds the message delivering `vector` to the LAPIC at `lapic_paddr` with ID `lapic_id`.
+    ///
+    /// The interrupt is edge-triggered, in fixed delivery mode and physical destination mode.
+    pub const fn new(lapic_paddr: PhysAddr, lapic_id: u8, vector: u8) -> Self {
+        // Address format:
+        // Bits 2: Destination mode (0 = physical)
+        // Bits 3: Redirection hint
+        // Bits 12-19: Destination ID
+        // Bits 20-31: 0xFEE
+        let address = lapic_paddr.as_u64() | ((lapic_id as u64) << 12);
+        // Data format:
+        // Bits 0-7: Vector
+        // Bits 8-10: Delivery mode (0 = fixed)
+        // Bit 14: Level (ignored when edge-triggered)
+        // Bit 15: Trigger mode (0 = edge)
+        let data = vector as u32;
+        Self { address, data }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn address(&self) -> u64 {
+        self.address
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the lower 32 bits of the address, which is dword-aligned.
+    pub const fn address_low(&self) -> u32 {
+        (self.address & 0xFFFF_FFFC) as u32
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn address_high(&self) -> u32 {
+        (self.address >> 32) as u32
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn data(&self) -> u32 {
+        self.data
+    }
+}
 
 pub struct Msi<H: MsiHelper> {
     capability: MsiCapability,
@@ -24,9 +80,9 @@ impl<H: MsiHelper> Msi<H> {
     pub fn setup_int(&self, vector: u8, handler: &mut dyn PciHandler, core_id: usize) {
         let (lapic_paddr, lapic_id) = H::get_lapic_info(core_id).unwrap();
 
-        let msg_addr = lapic_paddr.as_u64() | (u64::from(lapic_id) << 12);
-        let low_dword = u32::try_from(msg_addr & 0xFFFF_FFFC).unwrap();
-        let high_dword = u32::try_from(msg_addr >> 32).unwrap();
+        let message = MsiMessage::new(lapic_paddr, lapic_id, vector);
+        let low_dword = message.address_low();
+        let high_dword = message.address_high();
         assert!(high_dword == 0 || self.capability.qword_addressing);
 
         let message_addr_base = PciAddress::new(
@@ -61,7 +117,7 @@ impl<H: MsiHelper> Msi<H> {
             },
         );
 
-        let message_data = u32::from(vector);
+        let message_data = message.data();
         // FIXME: If not Extended Message Capable, writing to the upper DWORD is not allowed
         handler.write_raw(message_data_base, message_data);
     }
@@ -74,6 +130,12 @@ impl<H: MsiHelper> Msi<H> {
 
         handler.write_raw(self.capability.base, first_dword);
     }
+
+    /// Disables MSI, so that the device stops sending interrupts.
+    pub fn disable(&self, handler: &mut dyn PciHandler) {
+        let first_dword = handler.read_raw(self.capability.base);
+        handler.write_raw(self.capability.base, first_dword & !(1 << 16));
+    }
 }
 
 pub struct MsiCapability {
@@ -144,3 +206,24 @@ impl MessageControlValue {
         self.raw & (1 << 9) != 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msi_message() {
+        let lapic_paddr = PhysAddr::new_truncate(0xFEE0_0000);
+
+        let message = MsiMessage::new(lapic_paddr, 3, 0x41);
+        assert_eq!(message.address(), 0xFEE0_3000);
+        assert_eq!(message.address_low(), 0xFEE0_3000);
+        assert_eq!(message.address_high(), 0);
+        // Fixed delivery, edge-triggered.
+        assert_eq!(message.data(), 0x41);
+
+        let message = MsiMessage::new(lapic_paddr, 0xFF, 0xFE);
+        assert_eq!(message.address(), 0xFEEF_F000);
+        assert_eq!(message.data(), 0xFE);
+    }
+}
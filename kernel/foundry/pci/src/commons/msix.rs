@@ -102,26 +102,33 @@ impl<M: PhysicalMapper<M4KiB> + Send, H: MsiHelper> MsiX<M, H> {
 
         let (lapic_paddr, lapic_id) = H::get_lapic_info(core_id).unwrap();
 
-        let msg_addr = lapic_paddr.as_u64() | (u64::from(lapic_id) << 12);
-
-        // Data format:
-        // Bits 0-7: Vector
-        // Bits 8-10: Delivery mode
-        // Bit 11: Edge/Level
-        // Bits 12-15: Reserved
-        // Bits 16-31: Destination ID (x2APIC ID)
-        let msg_data = u32::from(vector);
+        let message = super::msi::MsiMessage::new(lapic_paddr, lapic_id, vector);
 
         let table = TableEntry {
-            msg_addr_low: u32::try_from(msg_addr & 0xFFFF_FFFC).unwrap(),
-            msg_addr_high: u32::try_from(msg_addr >> 32).unwrap(),
-            msg_data,
+            msg_addr_low: message.address_low(),
+            msg_addr_high: message.address_high(),
+            msg_data: message.data(),
             vector_ctrl: 0,
         };
 
         unsafe { endry_ptr.write(table) };
     }
 
+    /// Masks the interrupt of the given table entry, so that the device stops sending it.
+    pub fn mask(&self, table_idx: u16) {
+        /// Bit of the vector control dword that masks the entry.
+        const MASK_BIT: u32 = 1;
+
+        assert!(table_idx < self.capability.table_size);
+        let entry_ptr = unsafe { self.table.byte_add(usize::from(table_idx) * 16) };
+        let vector_ctrl_ptr = unsafe {
+            entry_ptr
+                .cast::<u32>()
+                .byte_add(core::mem::offset_of!(TableEntry, vector_ctrl))
+        };
+        unsafe { vector_ctrl_ptr.update(|vector_ctrl| vector_ctrl | MASK_BIT) };
+    }
+
     pub fn enable(&self, handler: &mut dyn PciHandler) {
         let offset_0x068_addr = self.capability.base;
         let mut offset_0x068 =
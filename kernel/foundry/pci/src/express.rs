@@ -38,35 +38,6 @@ impl<M: PhysicalMapper<M2MiB>> PciExpressHandler<M> {
         }
     }
 
-    pub fn update_devices(&mut self) {
-        self.devices.clear();
-
-        // Brute-force scan
-        for (cs, pmap) in self
-            .configuration_spaces
-            .iter()
-            .zip(&self.physical_mappings)
-        {
-            for bus in cs.start_pci_bus_number()..=cs.end_pci_bus_number() {
-                for dev in 0..=31 {
-                    if let Some(device) = Self::scan_device(
-                        pmap,
-                        cs,
-                        PciAddress::new(
-                            cs.segment_group_number(),
-                            bus,
-                            dev,
-                            0,
-                            RegisterOffset::VendorId as u8,
-                        ),
-                    ) {
-                        self.devices.push(device);
-                    }
-                }
-            }
-        }
-    }
-
     fn scan_device(pmap: &M, cs: &ParsedConfigurationSpace, address: PciAddress) -> Option<Device> {
         let (device, vendor) = {
             let reg = PciAddress {
@@ -170,6 +141,35 @@ impl<M: PhysicalMapper<M2MiB>> PciExpressHandler<M> {
 }
 
 impl<M: PhysicalMapper<M2MiB>> super::PciHandler for PciExpressHandler<M> {
+    fn update_devices(&mut self) {
+        self.devices.clear();
+
+        // Brute-force scan
+        for (cs, pmap) in self
+            .configuration_spaces
+            .iter()
+            .zip(&self.physical_mappings)
+        {
+            for bus in cs.start_pci_bus_number()..=cs.end_pci_bus_number() {
+                for dev in 0..=31 {
+                    if let Some(device) = Self::scan_device(
+                        pmap,
+                        cs,
+                        PciAddress::new(
+                            cs.segment_group_number(),
+                            bus,
+                            dev,
+                            0,
+                            RegisterOffset::VendorId as u8,
+                        ),
+                    ) {
+                        self.devices.push(device);
+                    }
+                }
+            }
+        }
+    }
+
     fn devices(&self) -> &[super::commons::Device] {
         &self.devices
     }
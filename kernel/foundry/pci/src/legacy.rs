@@ -25,25 +25,6 @@ impl LegacyPciHandler {
         }
     }
 
-    pub fn update_devices(&mut self) {
-        self.devices.clear();
-
-        // Brute-force scan
-        for bus in 0..=255 {
-            for device in 0..32 {
-                if let Some(device) = self.scan_device(PciAddress::new(
-                    0,
-                    bus,
-                    device,
-                    0,
-                    RegisterOffset::VendorId as u8,
-                )) {
-                    self.devices.push(device);
-                }
-            }
-        }
-    }
-
     #[must_use]
     fn scan_device(&mut self, address: PciAddress) -> Option<Device> {
         let (device, vendor) = {
@@ -184,6 +165,25 @@ impl_read_u!(read_u16, u16);
 impl_read_u!(read_u32, u32);
 
 impl super::PciHandler for LegacyPciHandler {
+    fn update_devices(&mut self) {
+        self.devices.clear();
+
+        // Brute-force scan
+        for bus in 0..=255 {
+            for device in 0..32 {
+                if let Some(device) = self.scan_device(PciAddress::new(
+                    0,
+                    bus,
+                    device,
+                    0,
+                    RegisterOffset::VendorId as u8,
+                )) {
+                    self.devices.push(device);
+                }
+            }
+        }
+    }
+
     fn devices(&self) -> &[super::commons::Device] {
         &self.devices
     }
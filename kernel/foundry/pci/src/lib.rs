@@ -6,29 +6,175 @@
 
 extern crate alloc;
 
+use alloc::vec::Vec;
+
 mod commons;
-pub use commons::{Bar, Class, Device, MsiHelper, msi, msix};
-use commons::{CapabilityHeader, MemoryBarType, PciAddress, RegisterOffset};
+pub use commons::{Bar, CapabilityHeader, Class, ConfigSnapshot, Device, MsiHelper, msi, msix};
+use commons::{MemoryBarType, PciAddress, RegisterOffset};
 mod express;
 pub use express::PciExpressHandler;
 mod legacy;
 pub use legacy::LegacyPciHandler;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A change of the devices found on the bus between two scans.
+pub enum DeviceEvent {
+    Added(Device),
+    Removed(Device),
+}
+
 pub trait PciHandler {
+    /// Enumerates the devices on the bus, replacing the list of devices.
+    fn update_devices(&mut self);
+
     #[must_use]
     /// Returns the list of devices found by the PCI handler.
     fn devices(&self) -> &[commons::Device];
 
+    /// Enumerates the devices on the bus again, and returns what changed since the last scan.
+    ///
+    /// A device replaced by another one in the same slot is reported as removed, then added.
+    /// Removed devices cannot be accessed anymore: `read_bar` returns `None` and
+    /// they have no capabilities. Drivers holding one must stop using it.
+    fn rescan(&mut self) -> Vec<DeviceEvent> {
+        let previous = self.devices().to_vec();
+        self.update_devices();
+        diff_devices(&previous, self.devices())
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether the device was found by the last scan.
+    fn is_present(&self, device: &commons::Device) -> bool {
+        self.devices().contains(device)
+    }
+
     #[must_use]
     fn read_raw(&mut self, address: PciAddress) -> u32;
 
     fn write_raw(&mut self, address: PciAddress, value: u32);
 
+    #[must_use]
+    /// Saves the registers of the standard header of a device that a reset clears.
+    ///
+    /// These are the command register, the six BAR registers (read raw, so that the
+    /// upper halves of 64-bit BARs are kept), the cache line size, the latency timer
+    /// and the interrupt line.
+    fn save_config(&mut self, device: &Device) -> ConfigSnapshot {
+        let command = self.read_raw(device.register(RegisterOffset::Command as u8));
+        let bars = core::array::from_fn(|i| {
+            let offset = RegisterOffset::Bar0 as u8 + 4 * u8::try_from(i).unwrap();
+            self.read_raw(device.register(offset))
+        });
+        let line_size_latency = self.read_raw(device.register(RegisterOffset::CacheLineSize as u8));
+        let interrupt = self.read_raw(device.register(RegisterOffset::InterruptLine as u8));
+
+        ConfigSnapshot {
+            command: u16::try_from(command & 0xFFFF).unwrap(),
+            bars,
+            cache_line_size: u8::try_from(line_size_latency & 0xFF).unwrap(),
+            latency_timer: u8::try_from((line_size_latency >> 8) & 0xFF).unwrap(),
+            interrupt_line: u8::try_from(interrupt & 0xFF).unwrap(),
+        }
+    }
+
+    /// Restores the registers saved by `save_config`.
+    ///
+    /// The BARs are restored first, then the cache line size and latency timer,
+    /// then the interrupt line. The command register comes last, so that the device
+    /// only decodes addresses and masters the bus once its BARs are set.
+    /// Status bits are left untouched, as they are cleared by writing ones.
+    fn restore_config(&mut self, device: &Device, snapshot: &ConfigSnapshot) {
+        for (i, &bar) in (0..).zip(&snapshot.bars) {
+            self.write_raw(device.register(RegisterOffset::Bar0 as u8 + 4 * i), bar);
+        }
+
+        // Header type is read-only, and writing zero to BIST does not start a self-test.
+        self.write_raw(
+            device.register(RegisterOffset::CacheLineSize as u8),
+            u32::from(snapshot.cache_line_size) | (u32::from(snapshot.latency_timer) << 8),
+        );
+
+        let interrupt_reg = device.register(RegisterOffset::InterruptLine as u8);
+        let interrupt = self.read_raw(interrupt_reg);
+        self.write_raw(
+            interrupt_reg,
+            (interrupt & !0xFF) | u32::from(snapshot.interrupt_line),
+        );
+
+        self.write_raw(
+            device.register(RegisterOffset::Command as u8),
+            u32::from(snapshot.command),
+        );
+    }
+
+    /// Triggers a Function Level Reset of a device through its PCI Express capability.
+    ///
+    /// Returns `false` if the device does not support it.
+    /// The reset takes up to 100 ms, during which the device must not be accessed.
+    /// Its configuration must then be restored with `restore_config`.
+    fn function_level_reset(&mut self, device: &Device) -> bool {
+        /// Offset of the Device Capabilities register in the capability.
+        const DEVICE_CAPABILITIES: u8 = 0x04;
+        /// Offset of the Device Control register in the capability.
+        const DEVICE_CONTROL: u8 = 0x08;
+        const FLR_CAPABLE: u32 = 1 << 28;
+        const INITIATE_FLR: u32 = 1 << 15;
+
+        let Some(capability) =
+            iter_capabilities(self, device).find(|c| c.id() == CapabilityHeader::ID_PCIEXPRESS)
+        else {
+            return false;
+        };
+        let offset = capability.pci_addr().register_offset;
+
+        if self.read_raw(device.register(offset + DEVICE_CAPABILITIES)) & FLR_CAPABLE == 0 {
+            return false;
+        }
+
+        // The upper half is the Device Status register, whose bits are cleared by writing ones.
+        let control_reg = device.register(offset + DEVICE_CONTROL);
+        let control = self.read_raw(control_reg) & 0xFFFF;
+        self.write_raw(control_reg, control | INITIATE_FLR);
+        true
+    }
+
+    #[must_use]
+    /// Reads a DWORD of the configuration space of a device,
+    /// e.g. a field of a vendor-specific capability.
+    ///
+    /// `offset` must be DWORD-aligned.
+    /// Returns `None` if the device is not present anymore.
+    fn read_config(&mut self, device: &Device, offset: u8) -> Option<u32> {
+        self.is_present(device)
+            .then(|| self.read_raw(device.register(offset)))
+    }
+
+    /// Enables memory space and I/O space decoding, as well as bus mastering,
+    /// so that the device can be accessed and can perform DMA.
+    fn enable_bus_master(&mut self, device: &Device) {
+        /// I/O Space, Memory Space and Bus Master bits of the command register.
+        const ENABLE: u32 = 0b111;
+
+        if !self.is_present(device) {
+            return;
+        }
+        // The upper half is the Status register, whose bits are cleared by writing ones.
+        let command_reg = device.register(RegisterOffset::Command as u8);
+        let command = self.read_raw(command_reg) & 0xFFFF;
+        self.write_raw(command_reg, command | ENABLE);
+    }
+
     #[must_use]
     /// Read the raw value from the PCI configuration space
     ///
     /// Bar number must be 0 to 5 (inclusive).
+    ///
+    /// Returns `None` if the device is not present anymore.
     fn read_bar(&mut self, device: &commons::Device, bar: u8) -> Option<commons::Bar> {
+        if !self.is_present(device) {
+            return None;
+        }
         let bar_reg_offset = match bar {
             0 => RegisterOffset::Bar0,
             1 => RegisterOffset::Bar1,
@@ -79,8 +225,22 @@ pub trait PciHandler {
     }
 }
 
-pub fn iter_capabilities(
-    handler: &mut dyn PciHandler,
+#[must_use]
+/// Returns the changes between two lists of devices.
+fn diff_devices(previous: &[Device], current: &[Device]) -> Vec<DeviceEvent> {
+    let removed = previous
+        .iter()
+        .filter(|device| !current.contains(device))
+        .map(|&device| DeviceEvent::Removed(device));
+    let added = current
+        .iter()
+        .filter(|device| !previous.contains(device))
+        .map(|&device| DeviceEvent::Added(device));
+    removed.chain(added).collect()
+}
+
+pub fn iter_capabilities<H: PciHandler + ?Sized>(
+    handler: &mut H,
     device: &commons::Device,
 ) -> impl Iterator<Item = CapabilityHeader> {
     let cap_ptr_reg = PciAddress::new(
@@ -90,7 +250,11 @@ pub fn iter_capabilities(
         device.sbdf().function(),
         RegisterOffset::CapabilitiesPointer as u8,
     );
-    let mut offset = u8::try_from(handler.read_raw(cap_ptr_reg) & 0xFF).unwrap();
+    let mut offset = if handler.is_present(device) {
+        u8::try_from(handler.read_raw(cap_ptr_reg) & 0xFF).unwrap()
+    } else {
+        0
+    };
     core::iter::from_fn(move || {
         if offset != 0 {
             let cap_reg = PciAddress::new(
@@ -110,3 +274,175 @@ pub fn iter_capabilities(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commons::{Csp, SbdfAddress};
+
+    /// A bus whose devices are replaced by the next set on every scan.
+    ///
+    /// Every device shares the same configuration space.
+    struct MockHandler {
+        devices: Vec<Device>,
+        next_scan: Vec<Device>,
+        config: [u32; 64],
+    }
+
+    impl MockHandler {
+        fn new(next_scan: Vec<Device>) -> Self {
+            let mut config = [0; 64];
+            // A 32-bit memory BAR, and no capabilities.
+            config[usize::from(RegisterOffset::Bar0 as u8 / 4)] = 0xFEB0_0000;
+            Self {
+                devices: Vec::new(),
+                next_scan,
+                config,
+            }
+        }
+    }
+
+    impl PciHandler for MockHandler {
+        fn update_devices(&mut self) {
+            self.devices.clone_from(&self.next_scan);
+        }
+
+        fn devices(&self) -> &[Device] {
+            &self.devices
+        }
+
+        fn read_raw(&mut self, address: PciAddress) -> u32 {
+            self.config[usize::from(address.register_offset / 4)]
+        }
+
+        fn write_raw(&mut self, address: PciAddress, value: u32) {
+            self.config[usize::from(address.register_offset / 4)] = value;
+        }
+    }
+
+    fn device(slot: u8, id: u16) -> Device {
+        Device {
+            id,
+            vendor_id: 0x8086,
+            sbdf: SbdfAddress::new(0, 0, slot, 0),
+            functions: 1,
+            csp: Csp::new(Class::Network, 0, 0),
+            revision: 0,
+            segment_group_number: 0,
+        }
+    }
+
+    #[test]
+    fn test_rescan() {
+        let (nic, disk, usb) = (device(1, 0x10D3), device(2, 0x2922), device(3, 0x000D));
+        let mut handler = MockHandler::new(alloc::vec![nic, disk]);
+
+        assert_eq!(
+            handler.rescan(),
+            [DeviceEvent::Added(nic), DeviceEvent::Added(disk)]
+        );
+        assert!(handler.rescan().is_empty());
+
+        handler.next_scan = alloc::vec![nic, usb];
+        assert_eq!(
+            handler.rescan(),
+            [DeviceEvent::Removed(disk), DeviceEvent::Added(usb)]
+        );
+        assert_eq!(handler.devices(), [nic, usb]);
+
+        // Another device in the same slot.
+        let other_nic = device(1, 0x100E);
+        handler.next_scan = alloc::vec![other_nic, usb];
+        assert_eq!(
+            handler.rescan(),
+            [DeviceEvent::Removed(nic), DeviceEvent::Added(other_nic)]
+        );
+    }
+
+    #[test]
+    fn test_removed_device_access() {
+        let nic = device(1, 0x10D3);
+        let mut handler = MockHandler::new(alloc::vec![nic]);
+        handler.update_devices();
+        assert!(handler.is_present(&nic));
+        assert!(matches!(handler.read_bar(&nic, 0), Some(Bar::Memory(_))));
+
+        handler.next_scan.clear();
+        let _ = handler.rescan();
+        assert!(!handler.is_present(&nic));
+        assert_eq!(handler.read_bar(&nic, 0), None);
+        assert_eq!(iter_capabilities(&mut handler, &nic).count(), 0);
+    }
+
+    #[test]
+    fn test_config_save_restore() {
+        let nic = device(1, 0x10D3);
+        let mut handler = MockHandler::new(alloc::vec![nic]);
+        handler.update_devices();
+
+        // Memory space, bus master, and a pending status bit.
+        handler.config[1] = 0x0010_0006;
+        // A 64-bit BAR in BAR0/BAR1, and an IO BAR.
+        handler.config[4] = 0xFEB0_000C;
+        handler.config[5] = 0x0000_0001;
+        handler.config[6] = 0xC001;
+        // Cache line size, latency timer, and a multi-function header.
+        handler.config[3] = 0x0080_4010;
+        // Interrupt line 11 on pin INTA.
+        handler.config[15] = 0x0000_010B;
+        let before = handler.config;
+
+        let snapshot = handler.save_config(&nic);
+        assert_eq!(snapshot.command(), 0x0006);
+        assert_eq!(snapshot.cache_line_size(), 0x10);
+        assert_eq!(snapshot.latency_timer(), 0x40);
+        assert_eq!(snapshot.interrupt_line(), 11);
+
+        // The reset clears writable registers.
+        for i in [1, 3, 4, 5, 6] {
+            handler.config[i] = 0;
+        }
+        handler.config[15] = 0x0000_0100;
+        handler.config[3] = 0x0080_0000;
+
+        handler.restore_config(&nic, &snapshot);
+        assert_eq!(handler.config[1], 0x0006);
+        assert_eq!(handler.config[3] & 0xFFFF, before[3] & 0xFFFF);
+        assert_eq!(handler.config[4..10], before[4..10]);
+        assert_eq!(handler.config[15], before[15]);
+        assert_eq!(handler.save_config(&nic), snapshot);
+    }
+
+    #[test]
+    fn test_config_access() {
+        let nic = device(1, 0x10D3);
+        let mut handler = MockHandler::new(alloc::vec![nic]);
+        assert_eq!(handler.read_config(&nic, 0x10), None);
+        handler.update_devices();
+        assert_eq!(handler.read_config(&nic, 0x10), Some(0xFEB0_0000));
+
+        // A pending status bit.
+        handler.config[1] = 0x0010_0000;
+        handler.enable_bus_master(&nic);
+        assert_eq!(handler.config[1], 0x0000_0007);
+    }
+
+    #[test]
+    fn test_function_level_reset() {
+        let nic = device(1, 0x10D3);
+        let mut handler = MockHandler::new(alloc::vec![nic]);
+        handler.update_devices();
+        assert!(!handler.function_level_reset(&nic));
+
+        // A PCI Express capability at 0x40, without FLR.
+        handler.config[13] = 0x40;
+        handler.config[16] = u32::from(CapabilityHeader::ID_PCIEXPRESS);
+        assert!(!handler.function_level_reset(&nic));
+
+        handler.config[17] = 1 << 28;
+        handler.config[18] = 0x0004_2810;
+        assert!(handler.function_level_reset(&nic));
+        // The status bit is not written back.
+        assert_eq!(handler.config[18], 0x0000_A810);
+    }
+}
@@ -28,6 +28,12 @@ pub enum FileError {
     CorruptedFS,
     #[error("Unsupported operation")]
     UnsupportedOperation,
+    #[error("Invalid offset")]
+    InvalidOffset,
+    #[error("Operation would block")]
+    WouldBlock,
+    #[error("Broken pipe")]
+    BrokenPipe,
 }
 
 impl From<super::BlockDeviceError> for FileError {
@@ -58,6 +64,11 @@ pub trait FileSystem {
     fn create(&mut self, path: Path) -> FileResult<()>;
     /// Deletes the file at the given path.
     fn delete(&mut self, path: Path) -> FileResult<()>;
+    /// Renames the file at `from` to `to`, replacing the file at `to` if there is one.
+    ///
+    /// The replacement must be atomic: after a crash, `to` holds either the old file
+    /// or the renamed one.
+    fn rename(&mut self, from: Path, to: Path) -> FileResult<()>;
     /// Checks if a file exists at the given path.
     fn exists(&mut self, path: Path) -> FileResult<bool>;
     /// Opens the file at the given path and returns a handle to it.
@@ -78,10 +89,21 @@ pub trait FileSystem {
     ///
     /// This returns how many bytes were written.
     fn write(&mut self, path: Path, buffer: &[u8], offset: usize) -> FileResult<usize>;
+    /// Flushes the data and metadata of the file at the given path to the underlying device.
+    ///
+    /// The entry of the file in its parent directory is flushed as well,
+    /// so that its size and first cluster are persisted.
+    fn sync(&mut self, path: Path) -> FileResult<()>;
     /// Returns information about the file at the given path.
     fn metadata(&mut self, path: Path) -> FileResult<FileMetadata>;
     /// Returns every entry in the directory at the given path.
     fn read_dir(&mut self, path: Path) -> FileResult<Vec<PathBuf>>;
+    /// Returns whether the contents of files never change.
+    ///
+    /// Pages of such files can be shared between mappings through the page cache.
+    fn is_read_only(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -68,6 +68,12 @@ impl FileSystem for DeviceFS {
         Err(super::FileError::UnsupportedOperation)
     }
 
+    #[inline]
+    fn rename(&mut self, _from: super::Path, _to: super::Path) -> super::FileResult<()> {
+        // DeviceFS does not support renaming files
+        Err(super::FileError::UnsupportedOperation)
+    }
+
     fn exists(&mut self, path: super::Path) -> super::FileResult<bool> {
         Ok(self
             .devices
@@ -116,6 +122,16 @@ impl FileSystem for DeviceFS {
         Err(super::FileError::NotFound)
     }
 
+    fn sync(&mut self, path: super::Path) -> super::FileResult<()> {
+        for device in &mut self.devices {
+            if device.path.as_path() == path {
+                device.device.flush()?;
+                return Ok(());
+            }
+        }
+        Err(super::FileError::NotFound)
+    }
+
     fn metadata(&mut self, path: super::Path) -> super::FileResult<super::FileMetadata> {
         for device in &mut self.devices {
             if device.path.as_path() == path {
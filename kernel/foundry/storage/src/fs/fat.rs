@@ -1,6 +1,7 @@
 //! File Allocation Table (FAT) file system implementation.
 use super::FileSystem;
-use beskar_core::storage::BlockDevice;
+use beskar_core::storage::{BlockDevice, BlockDeviceError};
+use bs::{BootSector, ExtendedBootSector};
 use thiserror::Error;
 
 pub mod bs;
@@ -10,6 +11,11 @@ pub mod dirent;
 #[expect(clippy::module_inception, reason = "FS is named after this table")]
 pub mod fat;
 pub mod file;
+pub mod format;
+pub mod fsck;
+
+/// Size of the sectors written by the formatter, and of the boot sector.
+const SECTOR_SIZE: usize = 512;
 
 /// Fat types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,6 +31,21 @@ impl FatType {
     pub const fn cluster_size_bits(self) -> u32 {
         Cluster::size_bits(self)
     }
+
+    #[must_use]
+    #[inline]
+    /// Returns the type of a file system with `clusters` data clusters.
+    ///
+    /// The number of clusters is the only thing that determines the FAT type.
+    pub const fn from_cluster_count(clusters: u32) -> Self {
+        if clusters < 4085 {
+            Self::Fat12
+        } else if clusters < 65525 {
+            Self::Fat16
+        } else {
+            Self::Fat32
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -148,10 +169,113 @@ pub enum FatError {
     NotSupported,
     #[error("Unexpected end of file")]
     UnexpectedEOF,
+    #[error("Volume too large")]
+    VolumeTooLarge,
 }
 
 pub type FatResult<T> = Result<T, FatError>;
 
+impl From<BlockDeviceError> for FatError {
+    fn from(error: BlockDeviceError) -> Self {
+        match error {
+            BlockDeviceError::Io => Self::Io,
+            BlockDeviceError::OutOfBounds => Self::OutOfBounds,
+            BlockDeviceError::Unsupported => Self::NotSupported,
+            BlockDeviceError::UnalignedAccess => Self::InvalidParameter,
+        }
+    }
+}
+
+/// Returns the offset of a 512-byte sector, in blocks of `D`.
+fn block_offset<D: BlockDevice>(sector: u32) -> FatResult<usize> {
+    if !SECTOR_SIZE.is_multiple_of(D::BLOCK_SIZE) {
+        return Err(FatError::NotSupported);
+    }
+    let sector = usize::try_from(sector).map_err(|_| FatError::OutOfBounds)?;
+    Ok(sector * (SECTOR_SIZE / D::BLOCK_SIZE))
+}
+
+fn read_sector<D: BlockDevice>(
+    device: &mut D,
+    sector: u32,
+    buffer: &mut [u8; SECTOR_SIZE],
+) -> FatResult<()> {
+    device.read(buffer, block_offset::<D>(sector)?)?;
+    Ok(())
+}
+
+fn write_sector<D: BlockDevice>(
+    device: &mut D,
+    sector: u32,
+    buffer: &[u8; SECTOR_SIZE],
+) -> FatResult<()> {
+    device.write(buffer, block_offset::<D>(sector)?)?;
+    Ok(())
+}
+
+/// Location of the regions of a FAT file system, in sectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    fat_type: FatType,
+    /// First sector of the first FAT.
+    fat_start: u32,
+    fat_size: u32,
+    fat_count: u8,
+    sectors_per_cluster: u32,
+    /// First cluster of the root directory on FAT32, 0 otherwise.
+    ///
+    /// The root directory of FAT12/16 lies between the FATs and the data region.
+    root_cluster: u32,
+    data_start: u32,
+    data_end: u32,
+}
+
+impl Layout {
+    /// Reads and validates the boot sector of `device`.
+    fn read<D: BlockDevice>(device: &mut D) -> FatResult<Self> {
+        let mut sector = [0; SECTOR_SIZE];
+        read_sector(device, 0, &mut sector)?;
+
+        // FAT12/16 boot sectors have a non-zero 16-bit FAT size, FAT32 ones do not
+        if let Ok(boot_sector) = BootSector::from_bytes(&sector) {
+            let bpb = boot_sector.bpb();
+            let clusters = bpb.cluster_count()?;
+            let fat_type = match FatType::from_cluster_count(clusters) {
+                FatType::Fat32 => return Err(FatError::InvalidBootSector),
+                fat_type => fat_type,
+            };
+            let data_start = bpb.total_sectors() - bpb.data_sectors()?;
+            let sectors_per_cluster = u32::from(bpb.sectors_per_cluster());
+            Ok(Self {
+                fat_type,
+                fat_start: u32::from(bpb.reserved_sectors()),
+                fat_size: u32::from(bpb.sectors_per_fat()),
+                fat_count: bpb.fat_count(),
+                sectors_per_cluster,
+                root_cluster: 0,
+                data_start,
+                data_end: data_start + clusters * sectors_per_cluster,
+            })
+        } else {
+            let boot_sector = ExtendedBootSector::from_bytes(&sector)?;
+            let bpb = boot_sector.bpb();
+            let clusters = bpb.cluster_count()?;
+            let data_start = bpb.total_sectors() - bpb.data_sectors()?;
+            let sectors_per_cluster = u32::from(bpb.sectors_per_cluster());
+            Ok(Self {
+                fat_type: FatType::Fat32,
+                fat_start: u32::from(bpb.reserved_sectors()),
+                fat_size: bpb.sectors_per_fat(),
+                fat_count: bpb.fat_count(),
+                sectors_per_cluster,
+                root_cluster: bpb.root_cluster(),
+                data_start,
+                data_end: data_start + clusters * sectors_per_cluster,
+            })
+        }
+    }
+}
+
 type BoxedDataReader<'a> =
     alloc::boxed::Box<dyn FnMut(Cluster, u32, &mut [u8]) -> FatResult<()> + 'a>;
 type RefDataReader<'a> = &'a mut dyn FnMut(Cluster, u32, &mut [u8]) -> FatResult<()>;
@@ -161,12 +285,39 @@ type RefDataWriter<'a> = &'a mut dyn FnMut(Cluster, u32, &[u8]) -> FatResult<()>
 pub struct FatFs<D: BlockDevice> {
     device: D,
     fat_type: FatType,
+    /// Number of sectors of each FAT.
     fat_size: u32,
+    /// Number of sectors of the data region.
     data_size: u32,
+    /// First sector of the data region.
     data_start: u32,
+    /// Sector following the last cluster.
     data_end: u32,
 }
 
+impl<D: BlockDevice> FatFs<D> {
+    /// Mounts the FAT file system of `device`.
+    ///
+    /// The boot sector is validated before any of its fields is used.
+    pub fn mount(mut device: D) -> FatResult<Self> {
+        let layout = Layout::read(&mut device)?;
+        Ok(Self {
+            device,
+            fat_type: layout.fat_type,
+            fat_size: layout.fat_size,
+            data_size: layout.data_end - layout.data_start,
+            data_start: layout.data_start,
+            data_end: layout.data_end,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn fat_type(&self) -> FatType {
+        self.fat_type
+    }
+}
+
 impl<D: BlockDevice> FileSystem for FatFs<D> {
     fn close(&mut self, _path: super::Path) -> super::FileResult<()> {
         // No-op for FAT
@@ -186,6 +337,11 @@ impl<D: BlockDevice> FileSystem for FatFs<D> {
         todo!("Delete file in FAT filesystem");
     }
 
+    fn rename(&mut self, _from: super::Path, _to: super::Path) -> super::FileResult<()> {
+        // Directory entries cannot be rewritten yet, so callers get an error instead of a panic.
+        Err(super::FileError::UnsupportedOperation)
+    }
+
     fn exists(&mut self, _path: super::Path) -> super::FileResult<bool> {
         todo!("Check if file exists in FAT filesystem");
     }
@@ -208,6 +364,11 @@ impl<D: BlockDevice> FileSystem for FatFs<D> {
         todo!("Write file to FAT filesystem");
     }
 
+    fn sync(&mut self, _path: super::Path) -> super::FileResult<()> {
+        // Files cannot be resolved yet, so there is no entry whose clusters could be flushed.
+        Err(super::FileError::UnsupportedOperation)
+    }
+
     fn metadata(&mut self, _path: super::Path) -> super::FileResult<super::FileMetadata> {
         todo!("Get file metadata from FAT filesystem");
     }
@@ -223,6 +384,73 @@ impl<D: BlockDevice> FileSystem for FatFs<D> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fs::{FileError, Path};
+    use alloc::{vec, vec::Vec};
+
+    /// A RAM disk.
+    pub(super) struct MemBlockDevice {
+        data: Vec<u8>,
+    }
+
+    impl MemBlockDevice {
+        pub(super) fn new(size: u64) -> Self {
+            Self {
+                data: vec![0xAA; usize::try_from(size).unwrap()],
+            }
+        }
+    }
+
+    impl BlockDevice for MemBlockDevice {
+        const BLOCK_SIZE: usize = 512;
+
+        fn read(&mut self, dst: &mut [u8], offset: usize) -> Result<(), BlockDeviceError> {
+            let start = offset * Self::BLOCK_SIZE;
+            let src = self
+                .data
+                .get(start..start + dst.len())
+                .ok_or(BlockDeviceError::OutOfBounds)?;
+            dst.copy_from_slice(src);
+            Ok(())
+        }
+
+        fn write(&mut self, src: &[u8], offset: usize) -> Result<(), BlockDeviceError> {
+            let start = offset * Self::BLOCK_SIZE;
+            self.data
+                .get_mut(start..start + src.len())
+                .ok_or(BlockDeviceError::OutOfBounds)?
+                .copy_from_slice(src);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rename_unsupported() {
+        const SIZE: u64 = 1024 * 1024;
+
+        let mut device = MemBlockDevice::new(SIZE);
+        format::format_fat(&mut device, SIZE, FatType::Fat12, "beskar").unwrap();
+        let mut fs = FatFs::mount(device).unwrap();
+
+        assert_eq!(
+            fs.rename(Path::from("/a.tmp"), Path::from("/a")),
+            Err(FileError::UnsupportedOperation)
+        );
+    }
+
+    #[test]
+    fn test_sync_unsupported() {
+        const SIZE: u64 = 1024 * 1024;
+
+        let mut device = MemBlockDevice::new(SIZE);
+        format::format_fat(&mut device, SIZE, FatType::Fat12, "beskar").unwrap();
+        let mut fs = FatFs::mount(device).unwrap();
+
+        // An fsync on a FAT volume must not take the kernel down
+        assert_eq!(
+            fs.sync(Path::from("/a")),
+            Err(FileError::UnsupportedOperation)
+        );
+    }
 
     #[test]
     fn test_fat_union() {
@@ -1,5 +1,11 @@
+use super::{FatError, FatResult};
 use beskar_core::static_assert;
 
+/// Maximum number of clusters of a FAT12/16 file system, addressable by FAT16 entries.
+const MAX_CLUSTERS_FAT16: u32 = 0xFFF5;
+/// Maximum number of clusters of a FAT32 file system, addressable by its 28-bit entries.
+const MAX_CLUSTERS_FAT32: u32 = 0x0FFF_FFF5;
+
 /// BIOS Parameter Block (BPB) start.
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
@@ -360,6 +366,20 @@ impl BootParamBlock {
         self.bpb_start.hidden_sectors
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the volume label.
+    pub const fn volume_label(&self) -> [u8; 11] {
+        self.bpb_end.volume_label
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the file system type.
+    pub const fn fs_type(&self) -> &[u8] {
+        &self.bpb_end.fs_type
+    }
+
     #[must_use]
     #[inline]
     /// Create a new `BootParamBlock` with default values for FAT12/16
@@ -384,57 +404,70 @@ impl BootParamBlock {
         Self::new().with_fs_type(*b"FAT16   ")
     }
 
-    #[must_use]
-    /// Calculate appropriate sectors per FAT for a FAT12 volume
-    pub fn calculate_sectors_per_fat_fat12(&self) -> u16 {
+    /// Returns the number of clusters of a FAT12/16 volume, before the FATs are allocated.
+    fn estimate_clusters(&self) -> FatResult<u32> {
         let bytes_per_sector = u32::from(self.bytes_per_sector());
-        let total_sectors = self.total_sectors();
-        let data_sectors = total_sectors
-            - u32::from(self.reserved_sectors())
-            - u32::from(self.root_entries()) * 32 / bytes_per_sector;
+        if bytes_per_sector == 0 || self.sectors_per_cluster() == 0 {
+            return Err(FatError::InvalidParameter);
+        }
+
+        let data_sectors = self
+            .total_sectors()
+            .checked_sub(u32::from(self.reserved_sectors()))
+            .and_then(|sectors| {
+                sectors.checked_sub(u32::from(self.root_entries()) * 32 / bytes_per_sector)
+            })
+            .ok_or(FatError::InvalidParameter)?;
+
         let clusters = data_sectors / u32::from(self.sectors_per_cluster());
+        if clusters > MAX_CLUSTERS_FAT16 {
+            return Err(FatError::VolumeTooLarge);
+        }
+
+        Ok(clusters)
+    }
+
+    /// Calculate appropriate sectors per FAT for a FAT12 volume
+    pub fn calculate_sectors_per_fat_fat12(&self) -> FatResult<u16> {
+        let clusters = self.estimate_clusters()?;
 
         // Each FAT entry in FAT12 is 12 bits (1.5 bytes)
         // Add some padding to ensure we have enough space
         let fat_size_bytes = (clusters * 3).div_ceil(2);
-        let sectors_per_fat = fat_size_bytes.div_ceil(bytes_per_sector);
+        let sectors_per_fat = fat_size_bytes.div_ceil(u32::from(self.bytes_per_sector()));
 
-        u16::try_from(sectors_per_fat).unwrap()
+        u16::try_from(sectors_per_fat).map_err(|_| FatError::VolumeTooLarge)
     }
 
-    #[must_use]
     /// Calculate appropriate sectors per FAT for a FAT16 volume
-    pub fn calculate_sectors_per_fat_fat16(&self) -> u16 {
-        let bytes_per_sector = u32::from(self.bytes_per_sector());
-        let total_sectors = self.total_sectors();
-        let data_sectors = total_sectors
-            - u32::from(self.reserved_sectors())
-            - u32::from(self.root_entries()) * 32 / bytes_per_sector;
-        let clusters = data_sectors / u32::from(self.sectors_per_cluster());
+    pub fn calculate_sectors_per_fat_fat16(&self) -> FatResult<u16> {
+        let clusters = self.estimate_clusters()?;
 
         // Each FAT entry in FAT16 is 2 bytes
         // Add some padding for safety
         let fat_size_bytes = (clusters + 2) * 2;
-        let sectors_per_fat = fat_size_bytes.div_ceil(bytes_per_sector);
+        let sectors_per_fat = fat_size_bytes.div_ceil(u32::from(self.bytes_per_sector()));
 
-        u16::try_from(sectors_per_fat).unwrap()
+        u16::try_from(sectors_per_fat).map_err(|_| FatError::VolumeTooLarge)
     }
 
-    #[must_use]
     /// Set boot sector fields based on the volume size for automatic configuration
-    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> Self {
+    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> FatResult<Self> {
         let bytes_per_sector = u64::from(self.bytes_per_sector());
-        let total_sectors = volume_size_bytes / bytes_per_sector;
+        let total_sectors = volume_size_bytes
+            .checked_div(bytes_per_sector)
+            .ok_or(FatError::InvalidParameter)?;
 
         // Configure appropriate parameters based on the volume size
-        if total_sectors <= 0xFFFF {
+        if let Ok(total_sectors) = u16::try_from(total_sectors) {
             // Use small sector count field
-            self.bpb_start.total_sectors = u16::try_from(total_sectors).unwrap();
+            self.bpb_start.total_sectors = total_sectors;
             self.bpb_start.total_sectors_large = 0;
         } else {
             // Use large sector count field
             self.bpb_start.total_sectors = 0;
-            self.bpb_start.total_sectors_large = u32::try_from(total_sectors).unwrap();
+            self.bpb_start.total_sectors_large =
+                u32::try_from(total_sectors).map_err(|_| FatError::VolumeTooLarge)?;
         }
 
         // Choose appropriate sectors per cluster based on volume size
@@ -476,14 +509,14 @@ impl BootParamBlock {
         if total_sectors < 4_085 {
             self = self.with_fs_type(*b"FAT12   ");
             // Calculate appropriate sectors per FAT for FAT12
-            self.bpb_start.sectors_per_fat = self.calculate_sectors_per_fat_fat12();
+            self.bpb_start.sectors_per_fat = self.calculate_sectors_per_fat_fat12()?;
         } else {
             self = self.with_fs_type(*b"FAT16   ");
             // Calculate appropriate sectors per FAT for FAT16
-            self.bpb_start.sectors_per_fat = self.calculate_sectors_per_fat_fat16();
+            self.bpb_start.sectors_per_fat = self.calculate_sectors_per_fat_fat16()?;
         }
 
-        self
+        Ok(self)
     }
 
     // Additional builder methods
@@ -856,37 +889,47 @@ impl ExtendedBootParamBlock {
         }
     }
 
-    #[must_use]
     /// Calculate appropriate sectors per FAT for a FAT32 volume
-    pub fn calculate_sectors_per_fat(&self) -> u32 {
+    pub fn calculate_sectors_per_fat(&self) -> FatResult<u32> {
         let bytes_per_sector = u32::from(self.bytes_per_sector());
-        let total_sectors = self.total_sectors();
+        let sectors_per_cluster = u32::from(self.sectors_per_cluster());
+        if bytes_per_sector == 0 || sectors_per_cluster == 0 {
+            return Err(FatError::InvalidParameter);
+        }
 
         // Data area starts after reserved sectors and FAT areas
         let root_dir_sectors = 0; // FAT32 stores root directory as a cluster chain
 
         // Conservatively estimate total clusters (this is iterative in practice)
-        let data_sectors = total_sectors - u32::from(self.reserved_sectors()) - root_dir_sectors;
-        let sectors_per_cluster = u32::from(self.sectors_per_cluster());
-        let estimated_clusters = data_sectors / sectors_per_cluster + 100; // Add padding
+        let data_sectors = self
+            .total_sectors()
+            .checked_sub(u32::from(self.reserved_sectors()) + root_dir_sectors)
+            .ok_or(FatError::InvalidParameter)?;
+        let clusters = data_sectors / sectors_per_cluster;
+        if clusters > MAX_CLUSTERS_FAT32 {
+            return Err(FatError::VolumeTooLarge);
+        }
+        let estimated_clusters = clusters + 100; // Add padding
 
         // Each FAT32 entry is 4 bytes
         let fat_size_bytes = estimated_clusters * 4;
         let sectors_per_fat = fat_size_bytes.div_ceil(bytes_per_sector);
 
         // Add a 5% margin for safety
-        sectors_per_fat + (sectors_per_fat / 20)
+        Ok(sectors_per_fat + (sectors_per_fat / 20))
     }
 
-    #[must_use]
     /// Configure the boot sector fields based on the volume size
-    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> Self {
+    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> FatResult<Self> {
         let bytes_per_sector = u64::from(self.bytes_per_sector());
-        let total_sectors = volume_size_bytes / bytes_per_sector;
+        let total_sectors = volume_size_bytes
+            .checked_div(bytes_per_sector)
+            .ok_or(FatError::InvalidParameter)?;
 
         // FAT32 requires total_sectors_large, even for small volumes
         self.bpb_start.total_sectors = 0;
-        self.bpb_start.total_sectors_large = u32::try_from(total_sectors).unwrap_or(u32::MAX);
+        self.bpb_start.total_sectors_large =
+            u32::try_from(total_sectors).map_err(|_| FatError::VolumeTooLarge)?;
 
         // Choose appropriate sectors per cluster based on volume size
         let sectors_per_cluster = if total_sectors < 532_480 {
@@ -909,9 +952,9 @@ impl ExtendedBootParamBlock {
         self.bpb_start.sectors_per_cluster = sectors_per_cluster;
 
         // Calculate appropriate sectors_per_fat
-        self.sectors_per_fat_large = self.calculate_sectors_per_fat();
+        self.sectors_per_fat_large = self.calculate_sectors_per_fat()?;
 
-        self
+        Ok(self)
     }
 
     #[must_use]
@@ -1147,9 +1190,32 @@ impl BootParamBlock {
             return false;
         }
 
-        // TODO: Check clusters
+        // Check clusters
+        matches!(self.cluster_count(), Ok(1..=MAX_CLUSTERS_FAT16))
+    }
 
-        true
+    /// Returns the number of sectors in the data region.
+    ///
+    /// Fails if the reserved sectors, the FATs and the root directory do not fit in the volume.
+    pub fn data_sectors(&self) -> FatResult<u32> {
+        let root_dir_bytes = u32::from(self.root_entries()) * 32;
+        let root_dir_sectors = root_dir_bytes
+            .checked_div(u32::from(self.bytes_per_sector()))
+            .ok_or(FatError::InvalidBootSector)?;
+        let fat_sectors = u32::from(self.fat_count()) * u32::from(self.sectors_per_fat());
+
+        self.total_sectors()
+            .checked_sub(u32::from(self.reserved_sectors()))
+            .and_then(|sectors| sectors.checked_sub(fat_sectors))
+            .and_then(|sectors| sectors.checked_sub(root_dir_sectors))
+            .ok_or(FatError::InvalidBootSector)
+    }
+
+    /// Returns the number of clusters in the data region.
+    pub fn cluster_count(&self) -> FatResult<u32> {
+        self.data_sectors()?
+            .checked_div(u32::from(self.sectors_per_cluster()))
+            .ok_or(FatError::InvalidBootSector)
     }
 }
 
@@ -1223,9 +1289,30 @@ impl ExtendedBootParamBlock {
             return false;
         }
 
-        // TODO: Check clusters
+        // Check clusters
+        matches!(self.cluster_count(), Ok(1..=MAX_CLUSTERS_FAT32))
+    }
 
-        true
+    /// Returns the number of sectors in the data region.
+    ///
+    /// Fails if the reserved sectors and the FATs do not fit in the volume.
+    pub fn data_sectors(&self) -> FatResult<u32> {
+        // FAT32 stores the root directory as a cluster chain, in the data region
+        let fat_sectors = u32::from(self.fat_count())
+            .checked_mul(self.sectors_per_fat())
+            .ok_or(FatError::InvalidBootSector)?;
+
+        self.total_sectors()
+            .checked_sub(u32::from(self.reserved_sectors()))
+            .and_then(|sectors| sectors.checked_sub(fat_sectors))
+            .ok_or(FatError::InvalidBootSector)
+    }
+
+    /// Returns the number of clusters in the data region.
+    pub fn cluster_count(&self) -> FatResult<u32> {
+        self.data_sectors()?
+            .checked_div(u32::from(self.sectors_per_cluster()))
+            .ok_or(FatError::InvalidBootSector)
     }
 }
 
@@ -1293,12 +1380,11 @@ impl BootSector {
         }
     }
 
-    #[must_use]
     #[inline]
     /// Configure the boot sector for a specific volume size
-    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> Self {
-        self.bpb = self.bpb.configure_for_volume_size(volume_size_bytes);
-        self
+    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> FatResult<Self> {
+        self.bpb = self.bpb.configure_for_volume_size(volume_size_bytes)?;
+        Ok(self)
     }
 
     #[must_use]
@@ -1485,6 +1571,33 @@ impl BootSector {
     pub const fn bpb(&self) -> &BootParamBlock {
         &self.bpb
     }
+
+    /// Parses a boot sector from the start of `bytes`.
+    ///
+    /// The boot sector is validated, so that its fields can be used to locate
+    /// the rest of the file system.
+    pub fn from_bytes(bytes: &[u8]) -> FatResult<Self> {
+        let bytes = bytes
+            .get(..size_of::<Self>())
+            .ok_or(FatError::UnexpectedEOF)?;
+
+        // Safety: `BootSector` is packed and only made of integers, so any bytes are a valid value.
+        let boot_sector = unsafe { bytes.as_ptr().cast::<Self>().read_unaligned() };
+
+        if boot_sector.validate() {
+            Ok(boot_sector)
+        } else {
+            Err(FatError::InvalidBootSector)
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the on-disk representation of the boot sector.
+    pub const fn as_bytes(&self) -> &[u8; 512] {
+        // Safety: `BootSector` is packed, 512 bytes long and only made of integers.
+        unsafe { &*(&raw const *self).cast::<[u8; 512]>() }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -1538,12 +1651,11 @@ impl ExtendedBootSector {
         }
     }
 
-    #[must_use]
     #[inline]
     /// Configure the boot sector for a specific volume size
-    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> Self {
-        self.bpb = self.bpb.configure_for_volume_size(volume_size_bytes);
-        self
+    pub fn configure_for_volume_size(mut self, volume_size_bytes: u64) -> FatResult<Self> {
+        self.bpb = self.bpb.configure_for_volume_size(volume_size_bytes)?;
+        Ok(self)
     }
 
     #[must_use]
@@ -1754,6 +1866,33 @@ impl ExtendedBootSector {
     pub const fn bpb(&self) -> &ExtendedBootParamBlock {
         &self.bpb
     }
+
+    /// Parses a boot sector from the start of `bytes`.
+    ///
+    /// The boot sector is validated, so that its fields can be used to locate
+    /// the rest of the file system.
+    pub fn from_bytes(bytes: &[u8]) -> FatResult<Self> {
+        let bytes = bytes
+            .get(..size_of::<Self>())
+            .ok_or(FatError::UnexpectedEOF)?;
+
+        // Safety: `ExtendedBootSector` is packed and only made of integers, so any bytes are a valid value.
+        let boot_sector = unsafe { bytes.as_ptr().cast::<Self>().read_unaligned() };
+
+        if boot_sector.validate() {
+            Ok(boot_sector)
+        } else {
+            Err(FatError::InvalidBootSector)
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the on-disk representation of the boot sector.
+    pub const fn as_bytes(&self) -> &[u8; 512] {
+        // Safety: `ExtendedBootSector` is packed, 512 bytes long and only made of integers.
+        unsafe { &*(&raw const *self).cast::<[u8; 512]>() }
+    }
 }
 
 pub type BootSectorUnion = super::FatUnion<BootSector, BootSector, ExtendedBootSector>;
@@ -1938,4 +2077,95 @@ mod tests {
         ebpb.sectors_per_fat_large = 0; // Must be non-zero
         assert!(!ebpb.validate());
     }
+
+    #[test]
+    fn test_calculations_out_of_range() {
+        // More reserved sectors than sectors in the volume
+        let bpb = BootParamBlock::new_fat12()
+            .with_total_sectors(1)
+            .with_reserved_sectors(2);
+        assert_eq!(
+            bpb.calculate_sectors_per_fat_fat12(),
+            Err(FatError::InvalidParameter)
+        );
+
+        // More clusters than FAT16 entries can address
+        let bpb = BootParamBlock::new_fat12().with_total_sectors(u32::MAX);
+        assert_eq!(
+            bpb.calculate_sectors_per_fat_fat12(),
+            Err(FatError::VolumeTooLarge)
+        );
+        assert_eq!(
+            bpb.calculate_sectors_per_fat_fat16(),
+            Err(FatError::VolumeTooLarge)
+        );
+
+        assert_eq!(
+            BootSector::new_fat16()
+                .configure_for_volume_size(u64::MAX)
+                .unwrap_err(),
+            FatError::VolumeTooLarge
+        );
+    }
+
+    #[test]
+    fn test_volume_size_limits() {
+        const SECTOR_SIZE: u64 = 512;
+
+        // Large FAT16 volumes have 1 reserved sector, 32 sectors of root directory,
+        // and clusters of 16 sectors. Trailing sectors that do not fill a cluster are unused.
+        let max_fat16_sectors = 1 + 32 + (u64::from(MAX_CLUSTERS_FAT16) + 1) * 16 - 1;
+
+        let bs = BootSector::new_fat16()
+            .configure_for_volume_size(max_fat16_sectors * SECTOR_SIZE)
+            .unwrap();
+        assert!(bs.validate());
+        assert_eq!(bs.bpb().sectors_per_cluster(), 16);
+
+        assert_eq!(
+            BootSector::new_fat16()
+                .configure_for_volume_size((max_fat16_sectors + 1) * SECTOR_SIZE)
+                .unwrap_err(),
+            FatError::VolumeTooLarge
+        );
+        // Such a volume has to be formatted as FAT32
+        let ebs = ExtendedBootSector::new_fat32()
+            .configure_for_volume_size((max_fat16_sectors + 1) * SECTOR_SIZE)
+            .unwrap();
+        assert!(ebs.validate());
+
+        // FAT32 volumes are limited by the 32-bit sector count
+        let max_fat32_bytes = u64::from(u32::MAX) * SECTOR_SIZE;
+        let ebs = ExtendedBootSector::new_fat32()
+            .configure_for_volume_size(max_fat32_bytes)
+            .unwrap();
+        assert!(ebs.validate());
+        assert_eq!(ebs.bpb().total_sectors(), u32::MAX);
+
+        assert_eq!(
+            ExtendedBootSector::new_fat32()
+                .configure_for_volume_size(max_fat32_bytes + SECTOR_SIZE)
+                .unwrap_err(),
+            FatError::VolumeTooLarge
+        );
+
+        // ... and by the 28-bit cluster numbers
+        let ebpb = ExtendedBootParamBlock::new_fat32().with_sectors_per_cluster(1);
+        let max_sectors = u32::from(ebpb.reserved_sectors()) + MAX_CLUSTERS_FAT32;
+        assert!(
+            ebpb.with_total_sectors(max_sectors)
+                .calculate_sectors_per_fat()
+                .is_ok()
+        );
+        assert_eq!(
+            ebpb.with_total_sectors(max_sectors + 1)
+                .calculate_sectors_per_fat(),
+            Err(FatError::VolumeTooLarge)
+        );
+        assert_eq!(
+            ebpb.with_total_sectors(u32::MAX)
+                .calculate_sectors_per_fat(),
+            Err(FatError::VolumeTooLarge)
+        );
+    }
 }
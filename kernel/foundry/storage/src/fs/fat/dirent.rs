@@ -150,6 +150,22 @@ impl DirEntry {
         }
     }
 
+    #[must_use]
+    #[inline]
+    /// Reads a directory entry from its on-disk representation
+    pub const fn from_bytes(bytes: &[u8; DIR_ENTRY_SIZE]) -> Self {
+        // Safety: The entry is a packed structure of integers, of the same size as `bytes`
+        unsafe { bytes.as_ptr().cast::<Self>().read_unaligned() }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the on-disk representation of the entry
+    pub const fn as_bytes(&self) -> &[u8; DIR_ENTRY_SIZE] {
+        // Safety: The entry is a packed structure of integers, of the same size as the array
+        unsafe { &*(&raw const *self).cast::<[u8; DIR_ENTRY_SIZE]>() }
+    }
+
     #[must_use]
     #[inline]
     /// Returns true if the entry is free (unused)
@@ -84,17 +84,13 @@ impl<T: FatEntries> Iterator for FatChainIter<'_, T> {
 /// FAT12 entry handling
 pub(crate) mod fat12 {
     use super::{Cluster, FatEntry, FatError, FatResult};
+    use beskar_core::bytes;
 
     pub fn read_fat_entry(fat: &[u8], cluster: Cluster) -> FatResult<FatEntry> {
         let cluster_val = usize::try_from(cluster.value()).unwrap();
         let offset = cluster_val + (cluster_val / 2); // 3 bytes per 2 entries
 
-        if offset + 1 >= fat.len() {
-            return Err(FatError::OutOfBounds);
-        }
-
-        let mut value = u16::from(fat[offset]);
-        value |= u16::from(fat[offset + 1]) << 8;
+        let mut value = bytes::try_read_u16_le(fat, offset).ok_or(FatError::OutOfBounds)?;
 
         // For odd cluster numbers, take the high 12 bits
         if cluster_val & 1 != 0 {
@@ -117,9 +113,8 @@ pub(crate) mod fat12 {
         let cluster_val = usize::try_from(cluster.value()).unwrap();
         let offset = cluster_val + (cluster_val / 2); // 3 bytes per 2 entries
 
-        if offset + 1 >= fat.len() {
-            return Err(FatError::OutOfBounds);
-        }
+        // Read the original bytes
+        let word = bytes::try_read_u16_le(fat, offset).ok_or(FatError::OutOfBounds)?;
 
         // Convert the entry to a raw value
         let value = match entry {
@@ -130,10 +125,6 @@ pub(crate) mod fat12 {
             FatEntry::Reserved => 0x0FF6,
         };
 
-        // Read the original bytes
-        let bytes = [fat[offset], fat[offset + 1]];
-        let word = u16::from_le_bytes(bytes);
-
         let new_word = if cluster_val & 1 != 0 {
             // Odd cluster: modify the high 12 bits
             (word & 0x000F) | (value << 4)
@@ -143,9 +134,7 @@ pub(crate) mod fat12 {
         };
 
         // Write the modified bytes back
-        let new_bytes = new_word.to_le_bytes();
-        fat[offset] = new_bytes[0];
-        fat[offset + 1] = new_bytes[1];
+        bytes::write_u16_le(fat, offset, new_word);
 
         Ok(())
     }
@@ -154,15 +143,12 @@ pub(crate) mod fat12 {
 /// FAT16 entry handling
 pub(crate) mod fat16 {
     use super::{Cluster, FatEntry, FatError, FatResult};
+    use beskar_core::bytes;
 
     pub fn read_fat_entry(fat: &[u8], cluster: Cluster) -> FatResult<FatEntry> {
         let offset = cluster.value() as usize * 2;
 
-        if offset + 1 >= fat.len() {
-            return Err(FatError::OutOfBounds);
-        }
-
-        let value = u16::from_le_bytes([fat[offset], fat[offset + 1]]);
+        let value = bytes::try_read_u16_le(fat, offset).ok_or(FatError::OutOfBounds)?;
 
         match value {
             0 => Ok(FatEntry::Free),
@@ -176,10 +162,6 @@ pub(crate) mod fat16 {
     pub fn write_fat_entry(fat: &mut [u8], cluster: Cluster, entry: FatEntry) -> FatResult<()> {
         let offset = cluster.value() as usize * 2;
 
-        if offset + 1 >= fat.len() {
-            return Err(FatError::OutOfBounds);
-        }
-
         // Convert the entry to a raw value
         let value = match entry {
             FatEntry::Free => 0,
@@ -189,33 +171,20 @@ pub(crate) mod fat16 {
             FatEntry::Reserved => 0xFFF6,
         };
 
-        // Write the bytes
-        let bytes = value.to_le_bytes();
-        fat[offset] = bytes[0];
-        fat[offset + 1] = bytes[1];
-
-        Ok(())
+        bytes::try_write_u16_le(fat, offset, value).ok_or(FatError::OutOfBounds)
     }
 }
 
 /// FAT32 entry handling
 pub(crate) mod fat32 {
     use super::{Cluster, FatEntry, FatError, FatResult};
+    use beskar_core::bytes;
 
     pub fn read_fat_entry(fat: &[u8], cluster: Cluster) -> FatResult<FatEntry> {
         let offset = cluster.value() as usize * 4;
 
-        if offset + 3 >= fat.len() {
-            return Err(FatError::OutOfBounds);
-        }
-
         // Read 4 bytes but only use the lower 28 bits
-        let value = u32::from_le_bytes([
-            fat[offset],
-            fat[offset + 1],
-            fat[offset + 2],
-            fat[offset + 3] & 0x0F,
-        ]);
+        let value = bytes::try_read_u32_le(fat, offset).ok_or(FatError::OutOfBounds)? & 0x0FFF_FFFF;
 
         match value {
             0 => Ok(FatEntry::Free),
@@ -229,9 +198,7 @@ pub(crate) mod fat32 {
     pub fn write_fat_entry(fat: &mut [u8], cluster: Cluster, entry: FatEntry) -> FatResult<()> {
         let offset = cluster.value() as usize * 4;
 
-        if offset + 3 >= fat.len() {
-            return Err(FatError::OutOfBounds);
-        }
+        let old = bytes::try_read_u32_le(fat, offset).ok_or(FatError::OutOfBounds)?;
 
         // Convert the entry to a raw value (only the lower 28 bits are used)
         let value = match entry {
@@ -242,14 +209,8 @@ pub(crate) mod fat32 {
             FatEntry::Reserved => 0x0FFF_FFF6,
         };
 
-        // Preserve the high 4 bits of the last byte
-        let last_byte = fat[offset + 3] & 0xF0 | ((value >> 24) & 0x0F) as u8;
-
-        // Write the bytes
-        fat[offset] = (value & 0xFF) as u8;
-        fat[offset + 1] = ((value >> 8) & 0xFF) as u8;
-        fat[offset + 2] = ((value >> 16) & 0xFF) as u8;
-        fat[offset + 3] = last_byte;
+        // Preserve the high 4 bits of the entry
+        bytes::write_u32_le(fat, offset, (old & 0xF000_0000) | value);
 
         Ok(())
     }
@@ -0,0 +1,352 @@
+//! Creation of FAT file systems on block devices.
+use super::{
+    Cluster, FatError, FatResult, FatType, Layout, SECTOR_SIZE,
+    bs::{BootSector, ExtendedBootSector},
+    fat::{FatEntry, fat32},
+    write_sector,
+};
+use beskar_core::storage::BlockDevice;
+
+/// Formats the first `volume_size` bytes of `device` as a FAT file system.
+///
+/// The cluster size is chosen from the size of the volume, which must have a number
+/// of clusters in the range of `fat_type`. `label` is at most 11 ASCII characters long.
+///
+/// The boot sector is read back and validated once the file system is written.
+pub fn format_fat<D: BlockDevice>(
+    device: &mut D,
+    volume_size: u64,
+    fat_type: FatType,
+    label: &str,
+) -> FatResult<()> {
+    let label = volume_label(label)?;
+
+    let expected = match fat_type {
+        FatType::Fat12 | FatType::Fat16 => format_fat16(device, volume_size, fat_type, label)?,
+        FatType::Fat32 => format_fat32(device, volume_size, label)?,
+    };
+
+    if Layout::read(device)? == expected {
+        Ok(())
+    } else {
+        Err(FatError::InvalidFilesystem)
+    }
+}
+
+/// Writes a FAT12/16 file system, and returns its layout.
+fn format_fat16<D: BlockDevice>(
+    device: &mut D,
+    volume_size: u64,
+    fat_type: FatType,
+    label: [u8; 11],
+) -> FatResult<Layout> {
+    let fs_type = match fat_type {
+        FatType::Fat12 => *b"FAT12   ",
+        _ => *b"FAT16   ",
+    };
+    let boot_sector = BootSector::new()
+        .configure_for_volume_size(volume_size)?
+        .with_fs_type(fs_type)
+        .with_volume_label(label);
+    let bpb = boot_sector.bpb();
+
+    if !boot_sector.validate() {
+        return Err(FatError::InvalidParameter);
+    }
+    let clusters = bpb.cluster_count()?;
+    if FatType::from_cluster_count(clusters) != fat_type {
+        return Err(FatError::InvalidParameter);
+    }
+
+    let fat_start = u32::from(bpb.reserved_sectors());
+    let fat_size = u32::from(bpb.sectors_per_fat());
+    let root_dir_start = fat_start + u32::from(bpb.fat_count()) * fat_size;
+    let data_start = bpb.total_sectors() - bpb.data_sectors()?;
+
+    // Fail before writing anything if the device is too small
+    zero_sectors(device, bpb.total_sectors() - 1, 1)?;
+
+    write_sector(device, 0, boot_sector.as_bytes())?;
+    write_fats(
+        device,
+        fat_type,
+        bpb.media_descriptor(),
+        fat_start,
+        fat_size,
+        bpb.fat_count(),
+    )?;
+    // The root directory has a fixed size, right after the FATs
+    zero_sectors(device, root_dir_start, data_start - root_dir_start)?;
+
+    Ok(Layout {
+        fat_type,
+        fat_start,
+        fat_size,
+        fat_count: bpb.fat_count(),
+        sectors_per_cluster: u32::from(bpb.sectors_per_cluster()),
+        root_cluster: 0,
+        data_start,
+        data_end: data_start + clusters * u32::from(bpb.sectors_per_cluster()),
+    })
+}
+
+/// Writes a FAT32 file system, and returns its layout.
+fn format_fat32<D: BlockDevice>(
+    device: &mut D,
+    volume_size: u64,
+    label: [u8; 11],
+) -> FatResult<Layout> {
+    let boot_sector = ExtendedBootSector::new_fat32()
+        .configure_for_volume_size(volume_size)?
+        .with_volume_label(label);
+    let bpb = boot_sector.bpb();
+
+    if !boot_sector.validate() {
+        return Err(FatError::InvalidParameter);
+    }
+    let clusters = bpb.cluster_count()?;
+    if FatType::from_cluster_count(clusters) != FatType::Fat32 {
+        return Err(FatError::InvalidParameter);
+    }
+
+    let fat_start = u32::from(bpb.reserved_sectors());
+    let fat_size = bpb.sectors_per_fat();
+    let data_start = bpb.total_sectors() - bpb.data_sectors()?;
+    let sectors_per_cluster = u32::from(bpb.sectors_per_cluster());
+
+    // Fail before writing anything if the device is too small
+    zero_sectors(device, bpb.total_sectors() - 1, 1)?;
+
+    // The root directory is the only cluster in use
+    let fs_info = fs_info_sector(clusters - 1, bpb.root_cluster() + 1);
+    let backup = u32::from(bpb.backup_boot_sector());
+    write_sector(device, 0, boot_sector.as_bytes())?;
+    write_sector(device, u32::from(bpb.fs_info_sector()), &fs_info)?;
+    write_sector(device, backup, boot_sector.as_bytes())?;
+    write_sector(device, backup + 1, &fs_info)?;
+
+    write_fats(
+        device,
+        FatType::Fat32,
+        bpb.media_descriptor(),
+        fat_start,
+        fat_size,
+        bpb.fat_count(),
+    )?;
+    let root_dir_start = data_start + (bpb.root_cluster() - 2) * sectors_per_cluster;
+    zero_sectors(device, root_dir_start, sectors_per_cluster)?;
+
+    Ok(Layout {
+        fat_type: FatType::Fat32,
+        fat_start,
+        fat_size,
+        fat_count: bpb.fat_count(),
+        sectors_per_cluster,
+        root_cluster: bpb.root_cluster(),
+        data_start,
+        data_end: data_start + clusters * sectors_per_cluster,
+    })
+}
+
+/// Writes `fat_count` copies of an empty FAT.
+///
+/// Only the reserved clusters 0 and 1 are in use, as well as the root directory on FAT32.
+fn write_fats<D: BlockDevice>(
+    device: &mut D,
+    fat_type: FatType,
+    media_descriptor: u8,
+    fat_start: u32,
+    fat_size: u32,
+    fat_count: u8,
+) -> FatResult<()> {
+    let mut first_sector = [0; SECTOR_SIZE];
+    // Cluster 0 holds the media descriptor, cluster 1 the end of chain marker
+    match fat_type {
+        FatType::Fat12 => first_sector[..3].copy_from_slice(&[media_descriptor, 0xFF, 0xFF]),
+        FatType::Fat16 => first_sector[..4].copy_from_slice(&[media_descriptor, 0xFF, 0xFF, 0xFF]),
+        FatType::Fat32 => {
+            first_sector[..8].copy_from_slice(&[
+                media_descriptor,
+                0xFF,
+                0xFF,
+                0x0F,
+                0xFF,
+                0xFF,
+                0xFF,
+                0x0F,
+            ]);
+            // The root directory fits in one cluster
+            fat32::write_fat_entry(&mut first_sector, Cluster::new(2), FatEntry::EndOfChain)?;
+        }
+    }
+
+    for i in 0..u32::from(fat_count) {
+        let start = fat_start + i * fat_size;
+        write_sector(device, start, &first_sector)?;
+        zero_sectors(device, start + 1, fat_size - 1)?;
+    }
+
+    Ok(())
+}
+
+fn zero_sectors<D: BlockDevice>(device: &mut D, start: u32, count: u32) -> FatResult<()> {
+    let zero = [0; SECTOR_SIZE];
+    for sector in start..start + count {
+        write_sector(device, sector, &zero)?;
+    }
+    Ok(())
+}
+
+/// Builds the `FSInfo` sector of a FAT32 file system.
+fn fs_info_sector(free_clusters: u32, next_free: u32) -> [u8; SECTOR_SIZE] {
+    const LEAD_SIGNATURE: u32 = 0x4161_5252;
+    const STRUCT_SIGNATURE: u32 = 0x6141_7272;
+    const TRAIL_SIGNATURE: u32 = 0xAA55_0000;
+
+    let mut sector = [0; SECTOR_SIZE];
+    sector[..4].copy_from_slice(&LEAD_SIGNATURE.to_le_bytes());
+    sector[484..488].copy_from_slice(&STRUCT_SIGNATURE.to_le_bytes());
+    sector[488..492].copy_from_slice(&free_clusters.to_le_bytes());
+    sector[492..496].copy_from_slice(&next_free.to_le_bytes());
+    sector[508..].copy_from_slice(&TRAIL_SIGNATURE.to_le_bytes());
+    sector
+}
+
+/// Converts `label` to the upper case, space padded form of the boot sector.
+fn volume_label(label: &str) -> FatResult<[u8; 11]> {
+    let bytes = label.as_bytes();
+    if bytes.len() > 11 || !bytes.iter().all(|&b| b.is_ascii_graphic() || b == b' ') {
+        return Err(FatError::InvalidParameter);
+    }
+
+    let mut padded = [b' '; 11];
+    for (dst, src) in padded.iter_mut().zip(bytes) {
+        *dst = src.to_ascii_uppercase();
+    }
+    Ok(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::fat::{FatFs, fat::fat12, read_sector, tests::MemBlockDevice};
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_format_fat12() {
+        let mut device = MemBlockDevice::new(MIB);
+        format_fat(&mut device, MIB, FatType::Fat12, "beskar").unwrap();
+
+        let mut sector = [0; SECTOR_SIZE];
+        read_sector(&mut device, 0, &mut sector).unwrap();
+        let boot_sector = BootSector::from_bytes(&sector).unwrap();
+        assert_eq!(&boot_sector.bpb().volume_label(), b"BESKAR     ");
+        assert_eq!(boot_sector.bpb().fs_type(), b"FAT12   ");
+
+        // The reserved entries are set, the data clusters are free
+        read_sector(&mut device, 1, &mut sector).unwrap();
+        assert_eq!(sector[..3], [0xF8, 0xFF, 0xFF]);
+        assert_eq!(
+            fat12::read_fat_entry(&sector, Cluster::new(1)).unwrap(),
+            FatEntry::EndOfChain
+        );
+        assert_eq!(
+            fat12::read_fat_entry(&sector, Cluster::new(2)).unwrap(),
+            FatEntry::Free
+        );
+
+        let fs = FatFs::mount(device).unwrap();
+        assert_eq!(fs.fat_type(), FatType::Fat12);
+    }
+
+    #[test]
+    fn test_format_fat16() {
+        let mut device = MemBlockDevice::new(16 * MIB);
+        format_fat(&mut device, 16 * MIB, FatType::Fat16, "").unwrap();
+
+        let layout = Layout::read(&mut device).unwrap();
+        // The root directory is empty
+        let mut sector = [0; SECTOR_SIZE];
+        read_sector(&mut device, layout.data_start - 1, &mut sector).unwrap();
+        assert!(sector.iter().all(|&b| b == 0));
+
+        let fs = FatFs::mount(device).unwrap();
+        assert_eq!(fs.fat_type(), FatType::Fat16);
+        assert_eq!(fs.data_start, layout.data_start);
+        assert!(fs.data_end <= u32::try_from(16 * MIB).unwrap() / 512);
+    }
+
+    #[test]
+    fn test_format_fat32() {
+        let mut device = MemBlockDevice::new(64 * MIB);
+        format_fat(&mut device, 64 * MIB, FatType::Fat32, "BESKAR OS").unwrap();
+
+        let mut sector = [0; SECTOR_SIZE];
+        read_sector(&mut device, 0, &mut sector).unwrap();
+        let boot_sector = ExtendedBootSector::from_bytes(&sector).unwrap();
+        let bpb = boot_sector.bpb();
+
+        let mut backup = [0; SECTOR_SIZE];
+        read_sector(
+            &mut device,
+            u32::from(bpb.backup_boot_sector()),
+            &mut backup,
+        )
+        .unwrap();
+        assert_eq!(sector, backup);
+
+        read_sector(&mut device, u32::from(bpb.fs_info_sector()), &mut sector).unwrap();
+        assert_eq!(sector[..4], *b"RRaA");
+        assert_eq!(sector[484..488], *b"rrAa");
+        assert_eq!(sector[510..], [0x55, 0xAA]);
+        let free = u32::from_le_bytes(sector[488..492].try_into().unwrap());
+        assert_eq!(free, bpb.cluster_count().unwrap() - 1);
+
+        // The root directory is allocated
+        read_sector(&mut device, u32::from(bpb.reserved_sectors()), &mut sector).unwrap();
+        assert_eq!(
+            fat32::read_fat_entry(&sector, Cluster::new(bpb.root_cluster())).unwrap(),
+            FatEntry::EndOfChain
+        );
+        assert_eq!(
+            fat32::read_fat_entry(&sector, Cluster::new(3)).unwrap(),
+            FatEntry::Free
+        );
+
+        let fs = FatFs::mount(device).unwrap();
+        assert_eq!(fs.fat_type(), FatType::Fat32);
+    }
+
+    #[test]
+    fn test_format_errors() {
+        let mut device = MemBlockDevice::new(MIB);
+
+        // There is nothing to mount before formatting
+        assert_eq!(
+            FatFs::mount(MemBlockDevice::new(MIB)).err(),
+            Some(FatError::InvalidBootSector)
+        );
+
+        // Too few clusters for FAT16 and FAT32
+        assert_eq!(
+            format_fat(&mut device, MIB, FatType::Fat16, ""),
+            Err(FatError::InvalidParameter)
+        );
+        assert_eq!(
+            format_fat(&mut device, MIB, FatType::Fat32, ""),
+            Err(FatError::InvalidParameter)
+        );
+
+        assert_eq!(
+            format_fat(&mut device, MIB, FatType::Fat12, "A LONG LABEL"),
+            Err(FatError::InvalidParameter)
+        );
+
+        // The volume does not fit in the device
+        assert_eq!(
+            format_fat(&mut device, 2 * MIB, FatType::Fat12, ""),
+            Err(FatError::OutOfBounds)
+        );
+    }
+}
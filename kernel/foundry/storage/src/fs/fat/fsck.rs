@@ -0,0 +1,481 @@
+//! Consistency checking of FAT file systems.
+use super::{
+    Cluster, FatResult, FatType, Layout, SECTOR_SIZE,
+    bs::ExtendedBootSector,
+    dirent::{DIR_ENTRY_SIZE, DirEntry},
+    fat::{FatEntries, FatEntry, FatTable},
+    read_sector, write_sector,
+};
+use alloc::{vec, vec::Vec};
+use beskar_core::storage::BlockDevice;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Inconsistencies found in a FAT file system
+pub struct FsckReport {
+    lost_chains: u32,
+    lost_clusters: u32,
+    cross_links: u32,
+}
+
+impl FsckReport {
+    #[must_use]
+    #[inline]
+    /// Returns the number of chains in use that no directory entry refers to
+    pub const fn lost_chains(&self) -> u32 {
+        self.lost_chains
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the number of clusters of the lost chains
+    pub const fn lost_clusters(&self) -> u32 {
+        self.lost_clusters
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the number of times a chain runs into a cluster of another chain
+    pub const fn cross_links(&self) -> u32 {
+        self.cross_links
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns true if no inconsistency was found
+    pub const fn is_clean(&self) -> bool {
+        self.lost_chains == 0 && self.cross_links == 0
+    }
+}
+
+/// Checks the FAT file system of `device` for lost chains and cross-linked clusters.
+///
+/// Chains are followed from the root directory, through every subdirectory.
+/// The clusters in use that are not reached are lost.
+///
+/// The device is only written to if `repair` is set, in which case the lost chains
+/// are freed in every copy of the FAT. Cross-linked clusters are reported but left
+/// as they are, as there is no telling which chain they belong to.
+pub fn fsck_fat<D: BlockDevice>(device: &mut D, repair: bool) -> FatResult<FsckReport> {
+    let layout = Layout::read(device)?;
+    let mut fat_data = read_fat(device, &layout)?;
+
+    let (report, lost) = {
+        let mut checker = Checker::new(device, &layout, &mut fat_data);
+        checker.scan()?;
+        checker.collect_lost()?
+    };
+
+    if repair && !lost.is_empty() {
+        let mut fat = FatTable::new(layout.fat_type, &mut fat_data);
+        for &cluster in &lost {
+            fat.set(Cluster::new(cluster), FatEntry::Free)?;
+        }
+        write_fats(device, &layout, &fat_data)?;
+        if layout.fat_type == FatType::Fat32 {
+            invalidate_free_count(device)?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Walks the directory tree of a file system, recording the chain each cluster belongs to.
+struct Checker<'a, D: BlockDevice> {
+    device: &'a mut D,
+    layout: &'a Layout,
+    fat: FatTable<'a>,
+    /// Chain of every cluster, numbered from 1, or 0 if no chain reached it.
+    owners: Vec<u32>,
+    chains: u32,
+    cross_links: u32,
+}
+
+impl<'a, D: BlockDevice> Checker<'a, D> {
+    fn new(device: &'a mut D, layout: &'a Layout, fat_data: &'a mut [u8]) -> Self {
+        let clusters = (layout.data_end - layout.data_start) / layout.sectors_per_cluster;
+        Self {
+            device,
+            layout,
+            fat: FatTable::new(layout.fat_type, fat_data),
+            owners: vec![0; usize::try_from(clusters).unwrap() + 2],
+            chains: 0,
+            cross_links: 0,
+        }
+    }
+
+    /// Follows every chain reachable from the root directory.
+    fn scan(&mut self) -> FatResult<()> {
+        let mut pending = Vec::new();
+
+        if self.layout.fat_type == FatType::Fat32 {
+            let root = self.walk_chain(self.layout.root_cluster)?;
+            self.scan_directory(&root, &mut pending)?;
+        } else {
+            let root_start =
+                self.layout.fat_start + u32::from(self.layout.fat_count) * self.layout.fat_size;
+            self.scan_sectors(root_start..self.layout.data_start, &mut pending)?;
+        }
+
+        while let Some(directory) = pending.pop() {
+            self.scan_directory(&directory, &mut pending)?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks the clusters of the chain starting at `start`, and returns them.
+    ///
+    /// The walk stops at a cluster that another chain already went through.
+    fn walk_chain(&mut self, start: u32) -> FatResult<Vec<u32>> {
+        self.chains += 1;
+        let chain_id = self.chains;
+
+        let mut chain = Vec::new();
+        let mut cluster = start;
+        loop {
+            let index = usize::try_from(cluster).unwrap();
+            if cluster < 2 || index >= self.owners.len() {
+                break;
+            }
+            match self.owners[index] {
+                0 => self.owners[index] = chain_id,
+                // The chain loops on itself
+                owner if owner == chain_id => break,
+                _ => {
+                    self.cross_links += 1;
+                    break;
+                }
+            }
+            chain.push(cluster);
+
+            match self.fat.get(Cluster::new(cluster))? {
+                FatEntry::Next(next) => cluster = next.value(),
+                _ => break,
+            }
+        }
+
+        Ok(chain)
+    }
+
+    fn scan_directory(&mut self, clusters: &[u32], pending: &mut Vec<Vec<u32>>) -> FatResult<()> {
+        let sectors_per_cluster = self.layout.sectors_per_cluster;
+        for &cluster in clusters {
+            let start = self.layout.data_start + (cluster - 2) * sectors_per_cluster;
+            if !self.scan_sectors(start..start + sectors_per_cluster, pending)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks the chains of the entries in `sectors`, and queues the subdirectories.
+    ///
+    /// Returns false if the end of the directory was reached.
+    fn scan_sectors(
+        &mut self,
+        sectors: core::ops::Range<u32>,
+        pending: &mut Vec<Vec<u32>>,
+    ) -> FatResult<bool> {
+        let mut buffer = [0; SECTOR_SIZE];
+        for sector in sectors {
+            read_sector(self.device, sector, &mut buffer)?;
+
+            for bytes in buffer.as_chunks::<DIR_ENTRY_SIZE>().0 {
+                let entry = DirEntry::from_bytes(bytes);
+                if entry.is_free() {
+                    return Ok(false);
+                }
+                if entry.is_deleted() || entry.is_long_name() || entry.is_volume_id() {
+                    continue;
+                }
+                // Dot entries point back to directories that are already walked
+                let name = entry.filename_raw();
+                if &name == DirEntry::DOT_ENTRY || &name == DirEntry::DOTDOT_ENTRY {
+                    continue;
+                }
+
+                let first_cluster = entry.first_cluster(self.layout.fat_type).value();
+                if first_cluster == 0 {
+                    continue;
+                }
+                let chain = self.walk_chain(first_cluster)?;
+                if entry.is_directory() {
+                    pending.push(chain);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the report and the clusters in use that no chain reached.
+    fn collect_lost(&mut self) -> FatResult<(FsckReport, Vec<u32>)> {
+        let mut lost = Vec::new();
+        // Lost clusters that another lost cluster points to
+        let mut pointed = vec![false; self.owners.len()];
+
+        for cluster in 2..u32::try_from(self.owners.len()).unwrap() {
+            if self.owners[usize::try_from(cluster).unwrap()] != 0 {
+                continue;
+            }
+            match self.fat.get(Cluster::new(cluster))? {
+                FatEntry::Next(next) => {
+                    if let Some(pointed) = pointed.get_mut(usize::try_from(next.value()).unwrap()) {
+                        *pointed = true;
+                    }
+                    lost.push(cluster);
+                }
+                FatEntry::EndOfChain => lost.push(cluster),
+                FatEntry::Free | FatEntry::Bad | FatEntry::Reserved => {}
+            }
+        }
+
+        // Walk from the heads of the chains first, so that a chain is only counted once.
+        // The remaining clusters form loops.
+        let heads = lost
+            .iter()
+            .copied()
+            .filter(|&cluster| !pointed[usize::try_from(cluster).unwrap()]);
+        let loops = lost
+            .iter()
+            .copied()
+            .filter(|&cluster| pointed[usize::try_from(cluster).unwrap()]);
+        let mut lost_chains = 0;
+        for start in heads.chain(loops) {
+            if self.owners[usize::try_from(start).unwrap()] == 0 {
+                self.walk_chain(start)?;
+                lost_chains += 1;
+            }
+        }
+
+        let report = FsckReport {
+            lost_chains,
+            lost_clusters: u32::try_from(lost.len()).unwrap(),
+            cross_links: self.cross_links,
+        };
+        Ok((report, lost))
+    }
+}
+
+/// Reads the first copy of the FAT.
+fn read_fat<D: BlockDevice>(device: &mut D, layout: &Layout) -> FatResult<Vec<u8>> {
+    let mut fat = vec![0; usize::try_from(layout.fat_size).unwrap() * SECTOR_SIZE];
+    for (sector, buffer) in (layout.fat_start..).zip(fat.as_chunks_mut::<SECTOR_SIZE>().0) {
+        read_sector(device, sector, buffer)?;
+    }
+    Ok(fat)
+}
+
+/// Writes `fat` to every copy of the FAT.
+fn write_fats<D: BlockDevice>(device: &mut D, layout: &Layout, fat: &[u8]) -> FatResult<()> {
+    for i in 0..u32::from(layout.fat_count) {
+        let start = layout.fat_start + i * layout.fat_size;
+        for (sector, buffer) in (start..).zip(fat.as_chunks::<SECTOR_SIZE>().0) {
+            write_sector(device, sector, buffer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Marks the free cluster count of the `FSInfo` sector as unknown.
+fn invalidate_free_count<D: BlockDevice>(device: &mut D) -> FatResult<()> {
+    let mut sector = [0; SECTOR_SIZE];
+    read_sector(device, 0, &mut sector)?;
+    let fs_info = u32::from(
+        ExtendedBootSector::from_bytes(&sector)?
+            .bpb()
+            .fs_info_sector(),
+    );
+
+    read_sector(device, fs_info, &mut sector)?;
+    sector[488..492].copy_from_slice(&u32::MAX.to_le_bytes());
+    write_sector(device, fs_info, &sector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::fat::{dirent::Attributes, format::format_fat, tests::MemBlockDevice};
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn set_entries(device: &mut MemBlockDevice, entries: &[(u32, FatEntry)]) {
+        let layout = Layout::read(device).unwrap();
+        let mut fat_data = read_fat(device, &layout).unwrap();
+        let mut fat = FatTable::new(layout.fat_type, &mut fat_data);
+        for &(cluster, entry) in entries {
+            fat.set(Cluster::new(cluster), entry).unwrap();
+        }
+        write_fats(device, &layout, &fat_data).unwrap();
+    }
+
+    fn get_entry(device: &mut MemBlockDevice, copy: u32, cluster: u32) -> FatEntry {
+        let mut layout = Layout::read(device).unwrap();
+        layout.fat_start += copy * layout.fat_size;
+        let mut fat_data = read_fat(device, &layout).unwrap();
+        FatTable::new(layout.fat_type, &mut fat_data)
+            .get(Cluster::new(cluster))
+            .unwrap()
+    }
+
+    /// Writes the `index`th entry of the directory starting at `sector`.
+    fn add_dir_entry(
+        device: &mut MemBlockDevice,
+        sector: u32,
+        index: usize,
+        name: &[u8; 8],
+        attributes: u8,
+        first_cluster: u32,
+    ) {
+        let fat_type = Layout::read(device).unwrap().fat_type;
+        let mut entry = DirEntry::new();
+        entry.set_name(*name);
+        entry.set_extension(*b"   ");
+        entry.set_attributes(Attributes::new(attributes));
+        entry.set_first_cluster(Cluster::new(first_cluster), fat_type);
+
+        let mut buffer = [0; SECTOR_SIZE];
+        read_sector(device, sector, &mut buffer).unwrap();
+        buffer[index * DIR_ENTRY_SIZE..][..DIR_ENTRY_SIZE].copy_from_slice(entry.as_bytes());
+        write_sector(device, sector, &buffer).unwrap();
+    }
+
+    #[test]
+    fn test_fsck_clean() {
+        let mut device = MemBlockDevice::new(MIB);
+        format_fat(&mut device, MIB, FatType::Fat12, "").unwrap();
+        let layout = Layout::read(&mut device).unwrap();
+        let root = layout.fat_start + u32::from(layout.fat_count) * layout.fat_size;
+
+        add_dir_entry(&mut device, root, 0, b"FILE    ", Attributes::ARCHIVE, 2);
+        set_entries(
+            &mut device,
+            &[
+                (2, FatEntry::Next(Cluster::new(3))),
+                (3, FatEntry::EndOfChain),
+            ],
+        );
+
+        let report = fsck_fat(&mut device, true).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report, FsckReport::default());
+        assert_eq!(get_entry(&mut device, 0, 3), FatEntry::EndOfChain);
+    }
+
+    #[test]
+    fn test_fsck_lost_chain_and_cross_link() {
+        let mut device = MemBlockDevice::new(MIB);
+        format_fat(&mut device, MIB, FatType::Fat12, "").unwrap();
+        let layout = Layout::read(&mut device).unwrap();
+        let root = layout.fat_start + u32::from(layout.fat_count) * layout.fat_size;
+
+        // FILE_A is 2 -> 3, FILE_B is 20 -> 3, and 10 -> 11 -> 12 is referenced by no entry
+        add_dir_entry(&mut device, root, 0, b"FILE_A  ", Attributes::ARCHIVE, 2);
+        add_dir_entry(&mut device, root, 1, b"FILE_B  ", Attributes::ARCHIVE, 20);
+        set_entries(
+            &mut device,
+            &[
+                (2, FatEntry::Next(Cluster::new(3))),
+                (3, FatEntry::EndOfChain),
+                (20, FatEntry::Next(Cluster::new(3))),
+                (10, FatEntry::Next(Cluster::new(11))),
+                (11, FatEntry::Next(Cluster::new(12))),
+                (12, FatEntry::EndOfChain),
+            ],
+        );
+
+        let report = fsck_fat(&mut device, false).unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.lost_chains(), 1);
+        assert_eq!(report.lost_clusters(), 3);
+        assert_eq!(report.cross_links(), 1);
+        // Nothing is repaired without the flag
+        assert_eq!(
+            get_entry(&mut device, 0, 10),
+            FatEntry::Next(Cluster::new(11))
+        );
+
+        assert_eq!(fsck_fat(&mut device, true).unwrap(), report);
+        for copy in 0..u32::from(layout.fat_count) {
+            for cluster in 10..=12 {
+                assert_eq!(get_entry(&mut device, copy, cluster), FatEntry::Free);
+            }
+            assert_eq!(
+                get_entry(&mut device, copy, 20),
+                FatEntry::Next(Cluster::new(3))
+            );
+        }
+
+        // Cross-links are left to the user
+        let report = fsck_fat(&mut device, true).unwrap();
+        assert_eq!(report.lost_clusters(), 0);
+        assert_eq!(report.cross_links(), 1);
+    }
+
+    #[test]
+    fn test_fsck_fat32_subdirectory() {
+        let mut device = MemBlockDevice::new(64 * MIB);
+        format_fat(&mut device, 64 * MIB, FatType::Fat32, "").unwrap();
+        let layout = Layout::read(&mut device).unwrap();
+        let cluster_sector =
+            |cluster: u32| layout.data_start + (cluster - 2) * layout.sectors_per_cluster;
+
+        // DIR (3) holds FILE (4 -> 5), and 6 -> 7 -> 6 is a lost loop
+        add_dir_entry(
+            &mut device,
+            cluster_sector(layout.root_cluster),
+            0,
+            b"DIR     ",
+            Attributes::DIRECTORY,
+            3,
+        );
+        write_sector(&mut device, cluster_sector(3), &[0; SECTOR_SIZE]).unwrap();
+        add_dir_entry(
+            &mut device,
+            cluster_sector(3),
+            0,
+            b".       ",
+            Attributes::DIRECTORY,
+            3,
+        );
+        add_dir_entry(
+            &mut device,
+            cluster_sector(3),
+            1,
+            b"..      ",
+            Attributes::DIRECTORY,
+            0,
+        );
+        add_dir_entry(
+            &mut device,
+            cluster_sector(3),
+            2,
+            b"FILE    ",
+            Attributes::ARCHIVE,
+            4,
+        );
+        set_entries(
+            &mut device,
+            &[
+                (3, FatEntry::EndOfChain),
+                (4, FatEntry::Next(Cluster::new(5))),
+                (5, FatEntry::EndOfChain),
+                (6, FatEntry::Next(Cluster::new(7))),
+                (7, FatEntry::Next(Cluster::new(6))),
+            ],
+        );
+
+        let report = fsck_fat(&mut device, true).unwrap();
+        assert_eq!(report.lost_chains(), 1);
+        assert_eq!(report.lost_clusters(), 2);
+        assert_eq!(report.cross_links(), 0);
+        assert_eq!(get_entry(&mut device, 0, 5), FatEntry::EndOfChain);
+        assert_eq!(get_entry(&mut device, 1, 7), FatEntry::Free);
+
+        // The free cluster count is no longer known
+        let mut sector = [0; SECTOR_SIZE];
+        read_sector(&mut device, 1, &mut sector).unwrap();
+        assert_eq!(sector[488..492], [0xFF; 4]);
+
+        assert!(fsck_fat(&mut device, false).unwrap().is_clean());
+    }
+}
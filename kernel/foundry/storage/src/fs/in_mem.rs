@@ -139,6 +139,12 @@ impl FileSystem for InMemoryFS<'_> {
         Err(super::FileError::UnsupportedOperation)
     }
 
+    #[inline]
+    fn rename(&mut self, _from: super::Path, _to: super::Path) -> super::FileResult<()> {
+        // InMemoryFS does not support renaming files
+        Err(super::FileError::UnsupportedOperation)
+    }
+
     fn exists(&mut self, path: super::Path) -> super::FileResult<bool> {
         Ok(self.infos.iter().any(|file| file.name() == path.as_str()))
     }
@@ -181,6 +187,17 @@ impl FileSystem for InMemoryFS<'_> {
         Err(super::FileError::UnsupportedOperation)
     }
 
+    #[inline]
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn sync(&mut self, _path: super::Path) -> super::FileResult<()> {
+        // Files are read-only, there is nothing to flush
+        Ok(())
+    }
+
     fn metadata(&mut self, path: super::Path) -> super::FileResult<super::FileMetadata> {
         let Some(file) = self.infos.iter().find(|file| file.name() == path.as_str()) else {
             return Err(super::FileError::NotFound);
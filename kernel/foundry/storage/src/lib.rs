@@ -7,5 +7,7 @@ extern crate alloc;
 pub use beskar_core::storage::{BlockDevice, BlockDeviceError, KernelDevice};
 
 pub mod fs;
+pub mod page_cache;
 pub mod partition;
+pub mod pipe;
 pub mod vfs;
@@ -0,0 +1,178 @@
+//! Page cache.
+//!
+//! Pages of files that never change can be shared between every read-only mapping of them,
+//! instead of being read into new frames for each mapping.
+//!
+//! A cached page is identified by a `PageKey`: the file and the offset of the page in it.
+//! The VFS identifies files by their absolute path, whose mount point stands for the device
+//! and whose path relative to the mount point stands for the inode (or first cluster).
+//!
+//! Pages are reference-counted by mapping, and their frame is handed back
+//! when the last mapping is released.
+//!
+//! Mappings are private: a process that wants to write to a cached page
+//! must first copy it into a frame of its own, and release its mapping of the cached page.
+
+use crate::fs::PathBuf;
+use alloc::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Identifies a page of a file.
+pub struct PageKey {
+    /// Absolute path of the file.
+    file: PathBuf,
+    /// Offset of the page in the file, in bytes.
+    offset: usize,
+}
+
+impl PageKey {
+    #[must_use]
+    #[inline]
+    pub const fn new(file: PathBuf, offset: usize) -> Self {
+        Self { file, offset }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the key of the page at the given offset of the same file.
+    pub fn with_offset(&self, offset: usize) -> Self {
+        Self {
+            file: self.file.clone(),
+            offset,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn file(&self) -> &PathBuf {
+        &self.file
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+#[derive(Debug)]
+struct CachedPage<F> {
+    frame: F,
+    mappings: usize,
+}
+
+#[derive(Debug)]
+/// A reference-counted cache of file pages, holding the frames of type `F`.
+pub struct PageCache<F> {
+    pages: BTreeMap<PageKey, CachedPage<F>>,
+    /// Reverse index, to find whether a mapped frame belongs to the cache.
+    keys: BTreeMap<F, PageKey>,
+}
+
+impl<F: Copy + Ord> Default for PageCache<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Copy + Ord> PageCache<F> {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            pages: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the frame of a cached page, counting a new mapping of it.
+    pub fn acquire(&mut self, key: &PageKey) -> Option<F> {
+        self.pages.get_mut(key).map(|page| {
+            page.mappings += 1;
+            page.frame
+        })
+    }
+
+    /// Caches a page that was just read into `frame`, counting a new mapping of it.
+    ///
+    /// If the page was cached meanwhile, the cached frame is returned instead,
+    /// and `frame` is left to the caller.
+    pub fn insert(&mut self, key: PageKey, frame: F) -> F {
+        let page = self.pages.entry(key).or_insert_with_key(|key| {
+            self.keys.insert(frame, key.clone());
+            CachedPage { frame, mappings: 0 }
+        });
+        page.mappings += 1;
+        page.frame
+    }
+
+    /// Releases a mapping of a cached page.
+    ///
+    /// Returns the frame of the page once it is not mapped anymore,
+    /// in which case it is removed from the cache.
+    pub fn release(&mut self, key: &PageKey) -> Option<F> {
+        let page = self.pages.get_mut(key)?;
+        page.mappings -= 1;
+        if page.mappings == 0 {
+            let frame = self.pages.remove(key)?.frame;
+            self.keys.remove(&frame);
+            Some(frame)
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the key of the page held by the given frame, if it is cached.
+    pub fn key_of(&self, frame: F) -> Option<&PageKey> {
+        self.keys.get(&frame)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the number of mappings of a page.
+    pub fn mappings(&self, key: &PageKey) -> usize {
+        self.pages.get(key).map_or(0, |page| page.mappings)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the number of cached pages.
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release() {
+        let mut cache = PageCache::new();
+        let key = PageKey::new(PathBuf::new("/ramdisk/file"), 0);
+
+        assert_eq!(cache.acquire(&key), None);
+        assert_eq!(cache.insert(key.clone(), 1_u64), 1);
+        // Another reader cached the page while this one was reading it.
+        assert_eq!(cache.insert(key.clone(), 2), 1);
+        assert_eq!(cache.acquire(&key), Some(1));
+        assert_eq!(cache.mappings(&key), 3);
+        assert_eq!(cache.key_of(1), Some(&key));
+        assert_eq!(cache.key_of(2), None);
+
+        assert_eq!(cache.release(&key), None);
+        assert_eq!(cache.release(&key), None);
+        assert_eq!(cache.release(&key), Some(1));
+        assert!(cache.is_empty());
+        assert_eq!(cache.key_of(1), None);
+        assert_eq!(cache.release(&key), None);
+    }
+}
@@ -0,0 +1,230 @@
+//! Anonymous pipes.
+//!
+//! A pipe is a bounded byte channel with a read end and a write end,
+//! each of which can be held by several handles.
+//! Its operations never block: they fail with `WouldBlock` instead,
+//! and it is up to the caller to wait and retry.
+use crate::fs::{FileError, FileResult};
+use hyperdrive::{locks::ticket::TicketLock, queues::ring::Ring};
+
+/// Number of bytes a pipe can hold before writes block.
+pub const PIPE_CAPACITY: usize = 4096;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// An end of a pipe.
+pub enum PipeEnd {
+    Read,
+    Write,
+}
+
+pub struct Pipe {
+    state: TicketLock<PipeState>,
+}
+
+struct PipeState {
+    // The ring needs one extra slot to tell a full buffer from an empty one.
+    buffer: Ring<{ PIPE_CAPACITY + 1 }, u8>,
+    /// Number of handles to the read end.
+    readers: usize,
+    /// Number of handles to the write end.
+    writers: usize,
+}
+
+impl Default for Pipe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pipe {
+    #[must_use]
+    #[inline]
+    /// Creates an empty pipe, with one handle to each end.
+    pub const fn new() -> Self {
+        Self {
+            state: TicketLock::new(PipeState {
+                buffer: Ring::new(),
+                readers: 1,
+                writers: 1,
+            }),
+        }
+    }
+
+    /// Accounts for a new handle to an end of the pipe.
+    pub fn open(&self, end: PipeEnd) {
+        let mut state = self.state.lock();
+        match end {
+            PipeEnd::Read => state.readers += 1,
+            PipeEnd::Write => state.writers += 1,
+        }
+    }
+
+    /// Accounts for a closed handle to an end of the pipe.
+    ///
+    /// Once every handle to the write end is closed, reads return the remaining data and then 0.
+    /// Once every handle to the read end is closed, writes fail with `BrokenPipe`.
+    pub fn close(&self, end: PipeEnd) {
+        let mut state = self.state.lock();
+        match end {
+            PipeEnd::Read => state.readers -= 1,
+            PipeEnd::Write => state.writers -= 1,
+        }
+    }
+
+    /// Reads from the pipe into the given buffers, one after the other.
+    ///
+    /// Returns 0 if the pipe is empty and every handle to its write end is closed.
+    /// Fails with `WouldBlock` if the pipe is empty and its write end is still open.
+    pub fn read(&self, buffers: &mut [&mut [u8]]) -> FileResult<usize> {
+        let mut state = self.state.lock();
+        let wanted = buffers.iter().map(|buffer| buffer.len()).sum::<usize>();
+        if wanted > 0 && state.buffer.is_empty() && state.writers > 0 {
+            return Err(FileError::WouldBlock);
+        }
+
+        let mut total = 0;
+        for byte in buffers.iter_mut().flat_map(|buffer| buffer.iter_mut()) {
+            let Some(value) = state.buffer.pop() else {
+                break;
+            };
+            *byte = value;
+            total += 1;
+        }
+        Ok(total)
+    }
+
+    /// Writes the given buffers to the pipe, one after the other.
+    ///
+    /// Writes as many bytes as the pipe can hold, and returns their number.
+    /// Fails with `BrokenPipe` if every handle to the read end is closed,
+    /// and with `WouldBlock` if the pipe is full.
+    pub fn write(&self, buffers: &[&[u8]]) -> FileResult<usize> {
+        let mut state = self.state.lock();
+        if state.readers == 0 {
+            return Err(FileError::BrokenPipe);
+        }
+        let wanted = buffers.iter().map(|buffer| buffer.len()).sum::<usize>();
+        if wanted > 0 && state.buffer.is_full() {
+            return Err(FileError::WouldBlock);
+        }
+
+        let mut total = 0;
+        for &byte in buffers.iter().flat_map(|buffer| buffer.iter()) {
+            if state.buffer.try_push(byte).is_err() {
+                break;
+            }
+            total += 1;
+        }
+        Ok(total)
+    }
+
+    #[must_use]
+    /// Returns whether an operation on the given end would not block.
+    pub fn is_ready(&self, end: PipeEnd) -> bool {
+        let state = self.state.lock();
+        match end {
+            PipeEnd::Read => !state.buffer.is_empty() || state.writers == 0,
+            PipeEnd::Write => !state.buffer.is_full() || state.readers == 0,
+        }
+    }
+}
+
+/// Retries an operation while it fails with `WouldBlock`.
+///
+/// `wait` is called between attempts, and returns false to give up,
+/// for instance once a timeout elapses. The last `WouldBlock` is then returned.
+pub fn retry_while_blocked(
+    mut operation: impl FnMut() -> FileResult<usize>,
+    mut wait: impl FnMut() -> bool,
+) -> FileResult<usize> {
+    loop {
+        match operation() {
+            Err(FileError::WouldBlock) if wait() => {}
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eof_on_writer_close() {
+        let pipe = Pipe::new();
+        let mut buffer = [0; 8];
+
+        assert_eq!(pipe.read(&mut [&mut buffer]), Err(FileError::WouldBlock));
+        assert!(!pipe.is_ready(PipeEnd::Read));
+
+        assert_eq!(pipe.write(&[b"abc", b"de"]), Ok(5));
+        assert!(pipe.is_ready(PipeEnd::Read));
+        let (first, second) = buffer.split_at_mut(2);
+        assert_eq!(pipe.read(&mut [first, second]), Ok(5));
+        assert_eq!(&buffer[..5], b"abcde");
+
+        // The end of file comes once every writer is closed
+        pipe.open(PipeEnd::Write);
+        pipe.close(PipeEnd::Write);
+        assert_eq!(pipe.read(&mut [&mut buffer]), Err(FileError::WouldBlock));
+
+        // The remaining data is read before the end of file
+        assert_eq!(pipe.write(&[b"fg"]), Ok(2));
+        pipe.close(PipeEnd::Write);
+        assert_eq!(pipe.read(&mut [&mut buffer]), Ok(2));
+        assert_eq!(&buffer[..2], b"fg");
+        assert_eq!(pipe.read(&mut [&mut buffer]), Ok(0));
+        assert!(pipe.is_ready(PipeEnd::Read));
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let pipe = Pipe::new();
+        let mut buffer = [0; 4];
+
+        // No data arrives before the deadline
+        let mut waits = 0;
+        let res = retry_while_blocked(
+            || pipe.read(&mut [&mut buffer]),
+            || {
+                waits += 1;
+                waits < 3
+            },
+        );
+        assert_eq!(res, Err(FileError::WouldBlock));
+        assert_eq!(waits, 3);
+
+        // A null timeout gives up at once
+        let res = retry_while_blocked(|| pipe.read(&mut [&mut buffer]), || false);
+        assert_eq!(res, Err(FileError::WouldBlock));
+
+        // Data written while waiting is read
+        let res = retry_while_blocked(
+            || pipe.read(&mut [&mut buffer]),
+            || pipe.write(&[b"late"]).is_ok(),
+        );
+        assert_eq!(res, Ok(4));
+        assert_eq!(&buffer, b"late");
+    }
+
+    #[test]
+    fn test_broken_pipe_on_reader_close() {
+        let pipe = Pipe::new();
+
+        // Writes are bounded by the capacity
+        let data = [0x42; PIPE_CAPACITY + 10];
+        assert_eq!(pipe.write(&[&data]), Ok(PIPE_CAPACITY));
+        assert!(!pipe.is_ready(PipeEnd::Write));
+        assert_eq!(pipe.write(&[b"x"]), Err(FileError::WouldBlock));
+        assert_eq!(pipe.write(&[]), Ok(0));
+
+        let mut buffer = [0; 10];
+        assert_eq!(pipe.read(&mut [&mut buffer]), Ok(10));
+        assert_eq!(pipe.write(&[&data]), Ok(10));
+
+        pipe.close(PipeEnd::Read);
+        assert!(pipe.is_ready(PipeEnd::Write));
+        assert_eq!(pipe.write(&[b"x"]), Err(FileError::BrokenPipe));
+        assert_eq!(pipe.write(&[]), Err(FileError::BrokenPipe));
+    }
+}
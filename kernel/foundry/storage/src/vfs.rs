@@ -1,6 +1,10 @@
 use super::fs::{FileError, FileResult, FileSystem, PathBuf};
-use crate::fs::Path;
-use alloc::{boxed::Box, collections::BTreeMap};
+use crate::{
+    fs::Path,
+    page_cache::PageKey,
+    pipe::{Pipe, PipeEnd},
+};
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec::Vec};
 use core::{
     marker::PhantomData,
     sync::atomic::{AtomicI64, Ordering},
@@ -24,10 +28,19 @@ impl Default for Handle {
     }
 }
 
-static HANDLE_COUNTER: AtomicI64 = AtomicI64::new(0);
+/// Number of standard handles, which are never allocated and only set with `Vfs::dup2`.
+const STANDARD_HANDLES: i64 = 3;
+
+static HANDLE_COUNTER: AtomicI64 = AtomicI64::new(STANDARD_HANDLES);
 
 impl Handle {
     pub const INVALID: Self = Self { id: -1 };
+    /// Standard input of a process.
+    pub const STDIN: Self = Self { id: 0 };
+    /// Standard output of a process.
+    pub const STDOUT: Self = Self { id: 1 };
+    /// Standard error of a process.
+    pub const STDERR: Self = Self { id: 2 };
 
     #[must_use]
     #[inline]
@@ -64,19 +77,81 @@ impl Handle {
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Kind of an advisory lock on a file.
+pub enum LockKind {
+    /// Can be held by several handles at once.
+    Shared,
+    /// Excludes every other lock on the file.
+    Exclusive,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Position to seek a handle to.
+pub enum SeekFrom {
+    /// Offset from the start of the file.
+    Start(usize),
+    /// Offset from the current position of the handle.
+    Current(isize),
+    /// Offset from the end of the file.
+    End(isize),
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+/// How a file is opened.
+pub enum OpenMode {
+    #[default]
+    /// Reads and writes take place at the given offset.
+    ReadWrite,
+    /// Writes take place at the end of the file, whatever the given offset.
+    ///
+    /// The end of the file is found under the filesystem lock, so that concurrent
+    /// appends never overwrite each other. Reads are not affected.
+    Append,
+}
+
 type Mounts = BTreeMap<PathBuf, RwLock<Box<dyn FileSystem + Send + Sync>>>;
-type OpenFiles = BTreeMap<Handle, OpenFileInfo>;
+/// A handle, with the ID of the process it belongs to.
+///
+/// Every process has its own handle table, so the same handle can refer
+/// to different files in different processes.
+type HandleKey = (u64, Handle);
+type OpenFiles = BTreeMap<HandleKey, OpenFileInfo>;
+/// Locks of every locked file, with the handle holding them.
+type Locks = BTreeMap<PathBuf, Vec<(HandleKey, LockKind)>>;
+type Pipes = BTreeMap<HandleKey, PipeHandle>;
 
 #[derive(Default)]
 pub struct Vfs<H: VfsHelper> {
     mounts: RwLock<Mounts>,
     open_handles: RwLock<OpenFiles>,
+    locks: RwLock<Locks>,
+    pipes: RwLock<Pipes>,
     _helper: PhantomData<H>,
 }
 
+#[derive(Clone)]
 struct OpenFileInfo {
-    process_id: u64,
     path: PathBuf,
+    /// Offset of the next read or write at the current position.
+    position: usize,
+    mode: OpenMode,
+    /// Whether the handle is left out of the handle table of child processes.
+    close_on_exec: bool,
+}
+
+#[derive(Clone)]
+struct PipeHandle {
+    pipe: Arc<Pipe>,
+    end: PipeEnd,
+    /// Whether the handle is left out of the handle table of child processes.
+    close_on_exec: bool,
+}
+
+/// What a handle refers to.
+enum HandleTarget {
+    File(OpenFileInfo),
+    Pipe(PipeHandle),
 }
 
 impl<H: VfsHelper> Vfs<H> {
@@ -87,6 +162,8 @@ impl<H: VfsHelper> Vfs<H> {
         Self {
             mounts: RwLock::new(BTreeMap::new()),
             open_handles: RwLock::new(BTreeMap::new()),
+            locks: RwLock::new(BTreeMap::new()),
+            pipes: RwLock::new(BTreeMap::new()),
             _helper: PhantomData,
         }
     }
@@ -105,45 +182,86 @@ impl<H: VfsHelper> Vfs<H> {
             .ok_or(FileError::NotFound)
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the key of a handle of the current process.
+    fn key(handle: Handle) -> HandleKey {
+        (H::get_current_process_id(), handle)
+    }
+
     /// Checks if a file is opened.
     fn check_file_opened(&self, path: Path) -> bool {
         let current_pid = H::get_current_process_id();
-        self.open_handles.read().values().any(|open_file| {
-            open_file.path.as_path() == path && open_file.process_id == current_pid
-        })
+        self.open_handles
+            .read()
+            .iter()
+            .any(|(&(pid, _handle), open_file)| {
+                open_file.path.as_path() == path && pid == current_pid
+            })
     }
 
     /// Creates a new handle.
     ///
     /// This function performs checks and adds the handle to the open handles list.
-    fn new_handle(&self, path: Path) -> FileResult<Handle> {
+    fn new_handle(&self, path: Path, mode: OpenMode) -> FileResult<Handle> {
         if self.check_file_opened(path) {
             return Err(FileError::PermissionDenied);
         }
         let handle = Handle::new();
         let open_file_info = OpenFileInfo {
             path: path.to_owned(),
-            process_id: H::get_current_process_id(),
+            position: 0,
+            mode,
+            close_on_exec: false,
         };
-        self.open_handles.write().insert(handle, open_file_info);
+        self.open_handles
+            .write()
+            .insert(Self::key(handle), open_file_info);
         Ok(handle)
     }
 
     fn delete_handle(&self, handle: Handle) -> FileResult<()> {
-        self.open_handles
+        let key = Self::key(handle);
+        let open_file = self
+            .open_handles
             .write()
-            .remove(&handle)
+            .remove(&key)
             .ok_or(FileError::InvalidHandle)?;
+        self.release_lock(key, &open_file.path);
         Ok(())
     }
 
+    /// Releases the lock held by the given handle on the file at the given path, if any.
+    fn release_lock(&self, handle: HandleKey, path: &PathBuf) {
+        let mut locks = self.locks.write();
+        if let Some(holders) = locks.get_mut(path) {
+            holders.retain(|(holder, _kind)| *holder != handle);
+            if holders.is_empty() {
+                locks.remove(path);
+            }
+        }
+    }
+
     /// Converts a handle to a path, checking the handle validity.
     fn handle_to_path(&self, handle: Handle) -> FileResult<PathBuf> {
-        let open_files = self.open_handles.read();
-        let open_file = open_files.get(&handle).ok_or(FileError::InvalidHandle)?;
-        (open_file.process_id == H::get_current_process_id())
-            .then(|| open_file.path.clone())
-            .ok_or(FileError::PermissionDenied)
+        self.open_handles
+            .read()
+            .get(&Self::key(handle))
+            .map(|open_file| open_file.path.clone())
+            .ok_or(FileError::InvalidHandle)
+    }
+
+    /// Runs `f` on the open file of a handle, checking the handle validity.
+    fn with_open_file<T>(
+        &self,
+        handle: Handle,
+        f: impl FnOnce(&mut OpenFileInfo) -> T,
+    ) -> FileResult<T> {
+        let mut open_files = self.open_handles.write();
+        let open_file = open_files
+            .get_mut(&Self::key(handle))
+            .ok_or(FileError::InvalidHandle)?;
+        Ok(f(open_file))
     }
 
     /// Converts a path to a filesystem, checking the path validity.
@@ -156,13 +274,23 @@ impl<H: VfsHelper> Vfs<H> {
         f: impl FnOnce(&mut (dyn FileSystem + Send + Sync), Path) -> FileResult<T>,
     ) -> FileResult<T> {
         let mounts = self.mounts.read();
+        let (fs, mount_len) = Self::find_mount(&mounts, path)?;
+        let rel_path = Path::from(&path.as_str()[mount_len..]);
+        f(&mut **fs.write(), rel_path)
+    }
 
+    /// Returns the filesystem mounted closest to the given path,
+    /// and the length of its mount point.
+    fn find_mount<'m>(
+        mounts: &'m Mounts,
+        path: Path,
+    ) -> FileResult<(&'m RwLock<Box<dyn FileSystem + Send + Sync>>, usize)> {
         let mut best_match: Option<(&RwLock<Box<dyn FileSystem + Send + Sync>>, usize)> = None;
         let mut best_len = 0;
 
         let path_str = path.as_str();
 
-        for (mount_path, fs) in mounts.iter() {
+        for (mount_path, fs) in mounts {
             let mount_len = mount_path.as_path().len();
             if mount_len <= best_len {
                 continue;
@@ -184,9 +312,7 @@ impl<H: VfsHelper> Vfs<H> {
             }
         }
 
-        let (fs, mount_len) = best_match.ok_or(FileError::InvalidPath)?;
-        let rel_path = Path::from(&path_str[mount_len..]);
-        f(&mut **fs.write(), rel_path)
+        best_match.ok_or(FileError::InvalidPath)
     }
 
     #[inline]
@@ -198,7 +324,12 @@ impl<H: VfsHelper> Vfs<H> {
     #[inline]
     /// Opens a file at the given path.
     pub fn open(&self, path: Path) -> FileResult<Handle> {
-        let handle = self.new_handle(path)?;
+        self.open_with_mode(path, OpenMode::default())
+    }
+
+    /// Opens a file at the given path, in the given mode.
+    pub fn open_with_mode(&self, path: Path, mode: OpenMode) -> FileResult<Handle> {
+        let handle = self.new_handle(path, mode)?;
         self.path_to_fs(path, |fs, rel_path| fs.open(rel_path))?;
         Ok(handle)
     }
@@ -206,6 +337,9 @@ impl<H: VfsHelper> Vfs<H> {
     #[inline]
     /// Closes a file associated with the given handle.
     pub fn close(&self, handle: Handle) -> FileResult<()> {
+        if let Some(res) = self.close_pipe(handle) {
+            return res;
+        }
         let path = self.handle_to_path(handle)?;
         self.delete_handle(handle)?;
         self.path_to_fs(path.as_path(), |fs, rel_path| fs.close(rel_path))?;
@@ -217,14 +351,129 @@ impl<H: VfsHelper> Vfs<H> {
     ///
     /// This function should only be called with a `u64` of a process that has completed its execution.
     pub fn close_all_from_process(&self, pid: u64) {
-        self.open_handles.write().retain(|_handle, open_file| {
-            let retained = open_file.process_id != pid;
+        self.open_handles.write().retain(|&key, open_file| {
+            let retained = key.0 != pid;
             if !retained {
+                self.release_lock(key, &open_file.path);
                 self.path_to_fs(open_file.path.as_path(), |fs, rel_path| fs.close(rel_path))
                     .unwrap();
             }
             retained
         });
+        self.pipes
+            .write()
+            .retain(|&(process_id, _handle), pipe_handle| {
+                let retained = process_id != pid;
+                if !retained {
+                    pipe_handle.pipe.close(pipe_handle.end);
+                }
+                retained
+            });
+    }
+
+    /// Duplicates a handle, and returns the new handle.
+    ///
+    /// The new handle refers to the same file or pipe end. A duplicated file handle starts
+    /// at the position of the original, but then moves on its own, and holds no lock.
+    pub fn dup(&self, handle: Handle, close_on_exec: bool) -> FileResult<Handle> {
+        let target = self.duplicate(Self::key(handle))?;
+        let new = Handle::new();
+        self.install(Self::key(new), target, close_on_exec);
+        Ok(new)
+    }
+
+    /// Makes `new` refer to what `old` refers to, closing `new` first if it is open.
+    ///
+    /// This is how the standard handles of a process are set.
+    /// If both handles are the same, only the close-on-exec flag of the handle is set.
+    pub fn dup2(&self, old: Handle, new: Handle, close_on_exec: bool) -> FileResult<()> {
+        if old == new {
+            return self.set_close_on_exec(old, close_on_exec);
+        }
+        let target = self.duplicate(Self::key(old))?;
+        // The handle is free once closed, even if its filesystem fails to close the file
+        let _ = self.close(new);
+        self.install(Self::key(new), target, close_on_exec);
+        Ok(())
+    }
+
+    /// Copies the handles of a process into the handle table of its child, at the same numbers.
+    ///
+    /// Handles with the close-on-exec flag are left out, and locks are not inherited.
+    pub fn inherit_handles(&self, parent: u64, child: u64) {
+        let inherited = self
+            .open_handles
+            .read()
+            .keys()
+            .chain(self.pipes.read().keys())
+            .filter(|&&(pid, _handle)| pid == parent)
+            .copied()
+            .collect::<Vec<_>>();
+
+        for key in inherited {
+            let Ok(target) = self.duplicate(key) else {
+                // The file cannot be opened again, so the child goes without it
+                continue;
+            };
+            let close_on_exec = match &target {
+                HandleTarget::File(open_file) => open_file.close_on_exec,
+                HandleTarget::Pipe(pipe_handle) => pipe_handle.close_on_exec,
+            };
+            if close_on_exec {
+                self.release(target);
+            } else {
+                self.install((child, key.1), target, false);
+            }
+        }
+    }
+
+    /// Takes a new reference to what a handle refers to,
+    /// which is given back by installing it or by `release`.
+    fn duplicate(&self, key: HandleKey) -> FileResult<HandleTarget> {
+        if let Some(pipe_handle) = self.pipes.read().get(&key) {
+            pipe_handle.pipe.open(pipe_handle.end);
+            return Ok(HandleTarget::Pipe(pipe_handle.clone()));
+        }
+        let open_file = self
+            .open_handles
+            .read()
+            .get(&key)
+            .cloned()
+            .ok_or(FileError::InvalidHandle)?;
+        self.path_to_fs(open_file.path.as_path(), |fs, rel_path| fs.open(rel_path))?;
+        Ok(HandleTarget::File(open_file))
+    }
+
+    /// Gives back a reference taken by `duplicate` that is not installed.
+    fn release(&self, target: HandleTarget) {
+        match target {
+            HandleTarget::File(open_file) => {
+                let _ =
+                    self.path_to_fs(open_file.path.as_path(), |fs, rel_path| fs.close(rel_path));
+            }
+            HandleTarget::Pipe(pipe_handle) => pipe_handle.pipe.close(pipe_handle.end),
+        }
+    }
+
+    fn install(&self, key: HandleKey, target: HandleTarget, close_on_exec: bool) {
+        match target {
+            HandleTarget::File(mut open_file) => {
+                open_file.close_on_exec = close_on_exec;
+                self.open_handles.write().insert(key, open_file);
+            }
+            HandleTarget::Pipe(mut pipe_handle) => {
+                pipe_handle.close_on_exec = close_on_exec;
+                self.pipes.write().insert(key, pipe_handle);
+            }
+        }
+    }
+
+    fn set_close_on_exec(&self, handle: Handle, close_on_exec: bool) -> FileResult<()> {
+        if let Some(pipe_handle) = self.pipes.write().get_mut(&Self::key(handle)) {
+            pipe_handle.close_on_exec = close_on_exec;
+            return Ok(());
+        }
+        self.with_open_file(handle, |open_file| open_file.close_on_exec = close_on_exec)
     }
 
     /// Deletes a file at the given path.
@@ -236,27 +485,375 @@ impl<H: VfsHelper> Vfs<H> {
         self.path_to_fs(path, |fs, rel_path| fs.delete(rel_path))
     }
 
+    /// Takes an advisory lock on a file associated with the given handle.
+    ///
+    /// Locks only exclude other locks: reads and writes are never blocked by them.
+    /// A handle holds at most one lock, so locking a file again converts the lock
+    /// to the given kind. Locks are released when their handle is closed,
+    /// which includes the exit of its process.
+    ///
+    /// Returns false, without waiting, if another handle holds a conflicting lock.
+    pub fn try_lock(&self, handle: Handle, kind: LockKind) -> FileResult<bool> {
+        // The handle cannot be closed, and its lock released, before the lock is taken
+        let key = Self::key(handle);
+        let open_files = self.open_handles.read();
+        let open_file = open_files.get(&key).ok_or(FileError::InvalidHandle)?;
+
+        let mut locks = self.locks.write();
+        let holders = locks.entry(open_file.path.clone()).or_default();
+        let conflict = holders.iter().any(|&(holder, held)| {
+            holder != key && (kind == LockKind::Exclusive || held == LockKind::Exclusive)
+        });
+        if conflict {
+            return Ok(false);
+        }
+
+        holders.retain(|(holder, _kind)| *holder != key);
+        holders.push((key, kind));
+        Ok(true)
+    }
+
+    /// Releases the lock held by the given handle, if any.
+    pub fn unlock(&self, handle: Handle) -> FileResult<()> {
+        let path = self.handle_to_path(handle)?;
+        self.release_lock(Self::key(handle), &path);
+        Ok(())
+    }
+
+    /// Renames a file, replacing the file at `to` if there is one.
+    ///
+    /// Both paths must be on the same filesystem.
+    pub fn rename(&self, from: Path, to: Path) -> FileResult<()> {
+        if self.check_file_opened(from) || self.check_file_opened(to) {
+            return Err(FileError::PermissionDenied);
+        }
+
+        let mounts = self.mounts.read();
+        let (fs, mount_len) = Self::find_mount(&mounts, from)?;
+        let (to_fs, to_mount_len) = Self::find_mount(&mounts, to)?;
+        if !core::ptr::eq(fs, to_fs) {
+            return Err(FileError::UnsupportedOperation);
+        }
+
+        fs.write().rename(
+            Path::from(&from.as_str()[mount_len..]),
+            Path::from(&to.as_str()[to_mount_len..]),
+        )
+    }
+
+    /// Replaces the content of the file at the given path with `data`.
+    ///
+    /// The data is written to a temporary file next to the target, which is flushed
+    /// and then renamed over the target, so that after a crash the file holds either
+    /// its old content or `data`.
+    ///
+    /// The temporary file is named after the target, with a `.tmp` suffix.
+    /// If it already exists, it is the leftover of an interrupted write and is replaced.
+    pub fn write_atomic(&self, path: Path, data: &[u8]) -> FileResult<()> {
+        let temp = path.to_owned().join(".tmp");
+        let temp = temp.as_path();
+
+        if self.exists(temp)? {
+            self.delete(temp)?;
+        }
+        self.create(temp)?;
+
+        let res = self.write_and_rename(temp, path, data);
+        if res.is_err() {
+            // Best effort, the target is left untouched either way
+            let _ = self.delete(temp);
+        }
+        res
+    }
+
+    fn write_and_rename(&self, temp: Path, path: Path, data: &[u8]) -> FileResult<()> {
+        let handle = self.open(temp)?;
+
+        let mut written = 0;
+        let res = loop {
+            if written == data.len() {
+                break self.sync(handle);
+            }
+            match self.write(handle, &data[written..], written) {
+                Ok(0) => break Err(FileError::NotEnoughSpace),
+                Ok(n) => written += n,
+                Err(err) => break Err(err),
+            }
+        };
+        // The file must be closed before it is renamed
+        let closed = self.close(handle);
+        res.and(closed)?;
+
+        self.rename(temp, path)
+    }
+
     /// Deletes a file at the given path.
     pub fn exists(&self, path: Path) -> FileResult<bool> {
         self.path_to_fs(path, |fs, rel_path| fs.exists(rel_path))
     }
 
+    #[must_use]
+    #[inline]
+    /// Checks that the handle is open and owned by the current process.
+    pub fn is_handle_valid(&self, handle: Handle) -> bool {
+        self.handle_to_path(handle).is_ok() || self.pipe_readiness(handle).is_some()
+    }
+
+    /// Creates a pipe, and returns handles to its read end and to its write end.
+    ///
+    /// Pipe handles are used like file handles, except that they have no position
+    /// and ignore offsets. Their reads and writes fail with `WouldBlock` instead of waiting.
+    pub fn create_pipe(&self) -> (Handle, Handle) {
+        let pipe = Arc::new(Pipe::new());
+        let mut pipes = self.pipes.write();
+        [PipeEnd::Read, PipeEnd::Write]
+            .map(|end| {
+                let handle = Handle::new();
+                let pipe_handle = PipeHandle {
+                    pipe: pipe.clone(),
+                    end,
+                    close_on_exec: false,
+                };
+                pipes.insert(Self::key(handle), pipe_handle);
+                handle
+            })
+            .into()
+    }
+
+    /// Returns the end of a pipe handle, and whether using it would not block.
+    ///
+    /// Returns `None` if the handle is not a pipe handle of the current process.
+    pub fn pipe_readiness(&self, handle: Handle) -> Option<(PipeEnd, bool)> {
+        let pipes = self.pipes.read();
+        let pipe_handle = pipes.get(&Self::key(handle))?;
+        Some((pipe_handle.end, pipe_handle.pipe.is_ready(pipe_handle.end)))
+    }
+
+    /// Returns the pipe of a handle, checking that it is the given end.
+    ///
+    /// Returns `None` if the handle is not a pipe handle.
+    fn pipe(&self, handle: Handle, end: PipeEnd) -> Option<FileResult<Arc<Pipe>>> {
+        let pipes = self.pipes.read();
+        let pipe_handle = pipes.get(&Self::key(handle))?;
+        Some(
+            (pipe_handle.end == end)
+                .then(|| pipe_handle.pipe.clone())
+                .ok_or(FileError::PermissionDenied),
+        )
+    }
+
+    /// Closes a pipe handle.
+    ///
+    /// Returns `None` if the handle is not a pipe handle.
+    fn close_pipe(&self, handle: Handle) -> Option<FileResult<()>> {
+        let pipe_handle = self.pipes.write().remove(&Self::key(handle))?;
+        pipe_handle.pipe.close(pipe_handle.end);
+        Some(Ok(()))
+    }
+
     /// Reads from a file associated with the given handle into the given buffer.
     pub fn read(&self, handle: Handle, buffer: &mut [u8], offset: usize) -> FileResult<usize> {
+        if let Some(pipe) = self.pipe(handle, PipeEnd::Read) {
+            return pipe?.read(&mut [buffer]);
+        }
         let path = self.handle_to_path(handle)?;
         self.path_to_fs(path.as_path(), |fs, rel_path| {
             fs.read(rel_path, buffer, offset)
         })
     }
 
+    /// Fills the given buffer with the content of a file, starting at the given offset.
+    ///
+    /// The part of the buffer past the end of the file is zeroed.
+    /// Returns the number of bytes read from the file.
+    pub fn read_fill(&self, handle: Handle, buffer: &mut [u8], offset: usize) -> FileResult<usize> {
+        let path = self.handle_to_path(handle)?;
+        self.path_to_fs(path.as_path(), |fs, rel_path| {
+            let mut filled = 0;
+            while filled < buffer.len() {
+                let read = fs.read(rel_path, &mut buffer[filled..], offset + filled)?;
+                if read == 0 {
+                    break;
+                }
+                filled += read;
+            }
+            buffer[filled..].fill(0);
+            Ok(filled)
+        })
+    }
+
+    /// Returns the size of a file associated with the given handle.
+    pub fn file_size(&self, handle: Handle) -> FileResult<usize> {
+        let path = self.handle_to_path(handle)?;
+        self.metadata(path.as_path())
+            .map(|metadata| metadata.size())
+    }
+
     /// Writes the given buffer to a file at the given path.
+    ///
+    /// If the file is opened in append mode, `offset` is ignored.
     pub fn write(&self, handle: Handle, buffer: &[u8], offset: usize) -> FileResult<usize> {
+        if let Some(pipe) = self.pipe(handle, PipeEnd::Write) {
+            return pipe?.write(&[buffer]);
+        }
+        self.write_with(handle, offset, |fs, rel_path, offset| {
+            fs.write(rel_path, buffer, offset)
+        })
+    }
+
+    /// Runs the write `f` at the given offset, or at the end of the file in append mode.
+    ///
+    /// In append mode, the position of the handle is moved past the bytes written.
+    fn write_with(
+        &self,
+        handle: Handle,
+        offset: usize,
+        f: impl FnOnce(&mut (dyn FileSystem + Send + Sync), Path, usize) -> FileResult<usize>,
+    ) -> FileResult<usize> {
+        let path = self.handle_to_path(handle)?;
+        let append = self.with_open_file(handle, |open_file| open_file.mode == OpenMode::Append)?;
+        let (offset, written) = self.path_to_fs(path.as_path(), |fs, rel_path| {
+            // The end is found under the filesystem lock, so that no other write can move it
+            let offset = if append {
+                fs.metadata(rel_path)?.size()
+            } else {
+                offset
+            };
+            Ok((offset, f(fs, rel_path, offset)?))
+        })?;
+        if append {
+            self.with_open_file(handle, |open_file| open_file.position = offset + written)?;
+        }
+        Ok(written)
+    }
+
+    /// Flushes the data and metadata of a file associated with the given handle.
+    pub fn sync(&self, handle: Handle) -> FileResult<()> {
+        let path = self.handle_to_path(handle)?;
+        self.path_to_fs(path.as_path(), |fs, rel_path| fs.sync(rel_path))
+    }
+
+    /// Reads a file into the given buffers, one after the other, starting at the given offset.
+    ///
+    /// Stops at the end of the file. Returns the total number of bytes read.
+    pub fn read_vectored(
+        &self,
+        handle: Handle,
+        buffers: &mut [&mut [u8]],
+        offset: usize,
+    ) -> FileResult<usize> {
+        if let Some(pipe) = self.pipe(handle, PipeEnd::Read) {
+            return pipe?.read(buffers);
+        }
         let path = self.handle_to_path(handle)?;
         self.path_to_fs(path.as_path(), |fs, rel_path| {
-            fs.write(rel_path, buffer, offset)
+            let mut total = 0;
+            for buffer in buffers.iter_mut() {
+                let read = match fs.read(rel_path, buffer, offset + total) {
+                    Ok(read) => read,
+                    // Report what was already read.
+                    Err(_) if total > 0 => break,
+                    Err(err) => return Err(err),
+                };
+                total += read;
+                if read < buffer.len() {
+                    break;
+                }
+            }
+            Ok(total)
+        })
+    }
+
+    /// Writes the given buffers to a file, one after the other, starting at the given offset.
+    ///
+    /// Stops at the first short write. Returns the total number of bytes written.
+    ///
+    /// If the file is opened in append mode, `offset` is ignored.
+    pub fn write_vectored(
+        &self,
+        handle: Handle,
+        buffers: &[&[u8]],
+        offset: usize,
+    ) -> FileResult<usize> {
+        if let Some(pipe) = self.pipe(handle, PipeEnd::Write) {
+            return pipe?.write(buffers);
+        }
+        self.write_with(handle, offset, |fs, rel_path, offset| {
+            let mut total = 0;
+            for buffer in buffers {
+                let written = match fs.write(rel_path, buffer, offset + total) {
+                    Ok(written) => written,
+                    // Report what was already written.
+                    Err(_) if total > 0 => break,
+                    Err(err) => return Err(err),
+                };
+                total += written;
+                if written < buffer.len() {
+                    break;
+                }
+            }
+            Ok(total)
         })
     }
 
+    /// Moves the position of a handle, and returns the new position.
+    ///
+    /// Seeking past the end of the file is allowed: reads there return no data,
+    /// and writes extend the file if the filesystem supports it.
+    /// Seeking before the start of the file fails with `InvalidOffset`.
+    ///
+    /// Every handle has its own position, even if several handles refer to the same file.
+    pub fn seek(&self, handle: Handle, pos: SeekFrom) -> FileResult<usize> {
+        let end = match pos {
+            SeekFrom::End(_) => self.file_size(handle)?,
+            SeekFrom::Start(_) | SeekFrom::Current(_) => 0,
+        };
+        self.with_open_file(handle, |open_file| {
+            let position = match pos {
+                SeekFrom::Start(offset) => Some(offset),
+                SeekFrom::Current(offset) => open_file.position.checked_add_signed(offset),
+                SeekFrom::End(offset) => end.checked_add_signed(offset),
+            }
+            .ok_or(FileError::InvalidOffset)?;
+            open_file.position = position;
+            Ok(position)
+        })?
+    }
+
+    /// Runs a transfer of `f` at the current position of a handle,
+    /// and moves the position past the bytes transferred.
+    ///
+    /// `f` is given the offset to transfer at and returns the number of bytes transferred,
+    /// like `read`, `write` and their vectored counterparts.
+    pub fn at_position(
+        &self,
+        handle: Handle,
+        f: impl FnOnce(usize) -> FileResult<usize>,
+    ) -> FileResult<usize> {
+        // Pipes have no position
+        if self.pipes.read().contains_key(&Self::key(handle)) {
+            return f(0);
+        }
+        let position = self.with_open_file(handle, |open_file| open_file.position)?;
+        let transferred = f(position)?;
+        self.with_open_file(handle, |open_file| {
+            // Appending writes have already moved the position to the end of their data
+            if open_file.position == position {
+                open_file.position = position + transferred;
+            }
+        })?;
+        Ok(transferred)
+    }
+
+    /// Returns the page cache key of the page at the given offset of a file.
+    ///
+    /// Returns `None` if the file may change, in which case its pages must not be shared.
+    pub fn page_key(&self, handle: Handle, offset: usize) -> FileResult<Option<PageKey>> {
+        let path = self.handle_to_path(handle)?;
+        let read_only = self.path_to_fs(path.as_path(), |fs, _rel_path| Ok(fs.is_read_only()))?;
+        Ok(read_only.then(|| PageKey::new(path, offset)))
+    }
+
     pub fn metadata(&self, path: Path) -> FileResult<crate::fs::FileMetadata> {
         self.path_to_fs(path, |fs, rel_path| fs.metadata(rel_path))
     }
@@ -265,3 +862,750 @@ impl<H: VfsHelper> Vfs<H> {
         self.path_to_fs(path, |fs, rel_path| fs.read_dir(rel_path))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::in_mem::{InMemoryFS, RawHeader};
+    use alloc::{string::String, vec::Vec};
+
+    struct TestHelper;
+
+    impl VfsHelper for TestHelper {
+        fn get_current_process_id() -> u64 {
+            0
+        }
+    }
+
+    /// Builds a ramdisk image holding a single file.
+    fn ramdisk(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut raw_name = [0; 32];
+        raw_name[..name.len()].copy_from_slice(name.as_bytes());
+        let header = RawHeader::new(content.len(), raw_name);
+
+        // Safety: `RawHeader` is `repr(C, packed)` plain old data.
+        let header_bytes = unsafe {
+            core::slice::from_raw_parts((&raw const header).cast::<u8>(), size_of::<RawHeader>())
+        };
+        let mut image = Vec::from(header_bytes);
+        image.extend_from_slice(content);
+        image
+    }
+
+    #[test]
+    fn test_mapped_bytes_match_file() {
+        let content: Vec<u8> = (0..10_000_u32).map(|i| (i % 251) as u8).collect();
+        let image = ramdisk("/wad", &content);
+        // The file system borrows the image for the lifetime of the VFS.
+        let image = Vec::leak(image);
+
+        let vfs = Vfs::<TestHelper>::new();
+        vfs.mount(
+            PathBuf::new("/ramdisk"),
+            Box::new(InMemoryFS::new(image).unwrap()),
+        );
+        let handle = vfs.open(Path::from("/ramdisk/wad")).unwrap();
+        assert_eq!(vfs.file_size(handle), Ok(content.len()));
+
+        // A page-aligned window fully inside the file.
+        let mut page = [0xAA; 4096];
+        assert_eq!(vfs.read_fill(handle, &mut page, 4096), Ok(4096));
+        assert_eq!(page, content[4096..8192]);
+
+        // A window crossing the end of the file is zero-filled.
+        let mut pages = [0xAA; 2 * 4096];
+        let read = vfs.read_fill(handle, &mut pages, 8192).unwrap();
+        assert_eq!(read, content.len() - 8192);
+        assert_eq!(pages[..read], content[8192..]);
+        assert!(pages[read..].iter().all(|&b| b == 0));
+
+        vfs.close(handle).unwrap();
+        assert_eq!(
+            vfs.read_fill(handle, &mut page, 0),
+            Err(FileError::InvalidHandle)
+        );
+    }
+
+    /// A device file backed by a growable buffer.
+    struct MemoryDevice(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl crate::KernelDevice for MemoryDevice {
+        fn read(&mut self, dst: &mut [u8], offset: usize) -> Result<(), crate::BlockDeviceError> {
+            let data = self.0.lock().unwrap();
+            let src = data
+                .get(offset..offset + dst.len())
+                .ok_or(crate::BlockDeviceError::OutOfBounds)?;
+            dst.copy_from_slice(src);
+            Ok(())
+        }
+
+        fn write(&mut self, src: &[u8], offset: usize) -> Result<(), crate::BlockDeviceError> {
+            let mut data = self.0.lock().unwrap();
+            if data.len() < offset + src.len() {
+                data.resize(offset + src.len(), 0);
+            }
+            data[offset..offset + src.len()].copy_from_slice(src);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_vectored_concatenates() {
+        let data = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut devices = crate::fs::dev::DeviceFS::new();
+        devices.add_device(PathBuf::new("/file"), Box::new(MemoryDevice(data.clone())));
+
+        let vfs = Vfs::<TestHelper>::new();
+        vfs.mount(PathBuf::new("/dev"), Box::new(devices));
+        let handle = vfs.open(Path::from("/dev/file")).unwrap();
+
+        let written = vfs
+            .write_vectored(handle, &[b"header:", b"", b"body", b"!"], 2)
+            .unwrap();
+        assert_eq!(written, 12);
+        assert_eq!(&*data.lock().unwrap(), b"\0\0header:body!");
+
+        let (mut a, mut b) = ([0; 4], [0; 8]);
+        let read = vfs.read_vectored(handle, &mut [&mut a, &mut b], 2).unwrap();
+        assert_eq!(read, 12);
+        assert_eq!(&a, b"head");
+        assert_eq!(&b, b"er:body!");
+    }
+
+    /// A device file whose buffered writes never reach the hardware.
+    struct UnflushableDevice;
+
+    impl crate::KernelDevice for UnflushableDevice {
+        fn read(&mut self, _dst: &mut [u8], _offset: usize) -> Result<(), crate::BlockDeviceError> {
+            Ok(())
+        }
+
+        fn write(&mut self, _src: &[u8], _offset: usize) -> Result<(), crate::BlockDeviceError> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), crate::BlockDeviceError> {
+            Err(crate::BlockDeviceError::Io)
+        }
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut devices = crate::fs::dev::DeviceFS::new();
+        devices.add_device(
+            PathBuf::new("/file"),
+            Box::new(MemoryDevice(std::sync::Arc::default())),
+        );
+        devices.add_device(PathBuf::new("/broken"), Box::new(UnflushableDevice));
+
+        let vfs = Vfs::<TestHelper>::new();
+        vfs.mount(PathBuf::new("/dev"), Box::new(devices));
+
+        let handle = vfs.open(Path::from("/dev/file")).unwrap();
+        assert_eq!(vfs.write(handle, b"config", 0), Ok(6));
+        assert_eq!(vfs.sync(handle), Ok(()));
+        vfs.close(handle).unwrap();
+        assert_eq!(vfs.sync(handle), Err(FileError::InvalidHandle));
+
+        let broken = vfs.open(Path::from("/dev/broken")).unwrap();
+        assert_eq!(vfs.write(broken, b"config", 0), Ok(6));
+        assert_eq!(vfs.sync(broken), Err(FileError::Io));
+    }
+
+    /// Maps a file the way the kernel does, with frames being indices into `memory`.
+    fn map_file(
+        vfs: &Vfs<TestHelper>,
+        cache: &mut crate::page_cache::PageCache<usize>,
+        memory: &mut Vec<[u8; 4096]>,
+        handle: Handle,
+        len: usize,
+    ) -> Vec<usize> {
+        (0..len.div_ceil(4096))
+            .map(|i| {
+                let key = vfs.page_key(handle, i * 4096).unwrap().unwrap();
+                cache.acquire(&key).unwrap_or_else(|| {
+                    let mut page = [0; 4096];
+                    vfs.read_fill(handle, &mut page, key.offset()).unwrap();
+                    memory.push(page);
+                    cache.insert(key, memory.len() - 1)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_only_maps_share_frames() {
+        let content: Vec<u8> = (0..10_000_u32).map(|i| (i % 251) as u8).collect();
+        let image = Vec::leak(ramdisk("/bin", &content));
+
+        let vfs = Vfs::<TestHelper>::new();
+        vfs.mount(
+            PathBuf::new("/ramdisk"),
+            Box::new(InMemoryFS::new(image).unwrap()),
+        );
+        let mut cache = crate::page_cache::PageCache::new();
+        let mut memory = Vec::new();
+
+        let handle = vfs.open(Path::from("/ramdisk/bin")).unwrap();
+        let first = map_file(&vfs, &mut cache, &mut memory, handle, content.len());
+        vfs.close(handle).unwrap();
+        assert_eq!(memory.len(), 3);
+
+        let handle = vfs.open(Path::from("/ramdisk/bin")).unwrap();
+        let second = map_file(&vfs, &mut cache, &mut memory, handle, content.len());
+        assert_eq!(first, second);
+        assert_eq!(memory.len(), 3);
+        assert_eq!(memory[second[1]], content[4096..8192]);
+
+        let key = vfs.page_key(handle, 0).unwrap().unwrap();
+        assert_eq!(cache.mappings(&key), 2);
+
+        // Device files may change, so their pages are never shared.
+        let mut devices = crate::fs::dev::DeviceFS::new();
+        devices.add_device(
+            PathBuf::new("/file"),
+            Box::new(MemoryDevice(std::sync::Arc::default())),
+        );
+        vfs.mount(PathBuf::new("/dev"), Box::new(devices));
+        let device = vfs.open(Path::from("/dev/file")).unwrap();
+        assert_eq!(vfs.page_key(device, 0), Ok(None));
+    }
+
+    /// Content of the files that survives a crash.
+    type Disk = std::sync::Arc<std::sync::Mutex<BTreeMap<String, Vec<u8>>>>;
+
+    /// A file system whose writes reach the disk when the file is synced.
+    ///
+    /// Creations, deletions and renames reach the disk immediately, as with a journal.
+    struct VolatileFs {
+        disk: Disk,
+        /// Files with their unsynced writes.
+        files: BTreeMap<String, Vec<u8>>,
+        /// Crashes instead of performing the next rename.
+        crash_on_rename: std::sync::Arc<core::sync::atomic::AtomicBool>,
+        crashed: bool,
+    }
+
+    impl VolatileFs {
+        fn new(
+            disk: &Disk,
+            crash_on_rename: &std::sync::Arc<core::sync::atomic::AtomicBool>,
+        ) -> Self {
+            Self {
+                files: disk.lock().unwrap().clone(),
+                disk: disk.clone(),
+                crash_on_rename: crash_on_rename.clone(),
+                crashed: false,
+            }
+        }
+
+        fn check(&self, path: Path) -> FileResult<()> {
+            if self.crashed {
+                Err(FileError::Io)
+            } else if self.files.contains_key(path.as_str()) {
+                Ok(())
+            } else {
+                Err(FileError::NotFound)
+            }
+        }
+    }
+
+    impl FileSystem for VolatileFs {
+        fn create(&mut self, path: Path) -> FileResult<()> {
+            if self.crashed {
+                return Err(FileError::Io);
+            }
+            self.files.entry(path.as_str().into()).or_default();
+            self.disk
+                .lock()
+                .unwrap()
+                .entry(path.as_str().into())
+                .or_default();
+            Ok(())
+        }
+
+        fn delete(&mut self, path: Path) -> FileResult<()> {
+            self.check(path)?;
+            self.files.remove(path.as_str());
+            self.disk.lock().unwrap().remove(path.as_str());
+            Ok(())
+        }
+
+        fn rename(&mut self, from: Path, to: Path) -> FileResult<()> {
+            self.check(from)?;
+            if self.crash_on_rename.load(Ordering::Relaxed) {
+                self.crashed = true;
+                return Err(FileError::Io);
+            }
+            let file = self.files.remove(from.as_str()).unwrap();
+            self.files.insert(to.as_str().into(), file);
+            let mut disk = self.disk.lock().unwrap();
+            let file = disk.remove(from.as_str()).unwrap();
+            disk.insert(to.as_str().into(), file);
+            Ok(())
+        }
+
+        fn exists(&mut self, path: Path) -> FileResult<bool> {
+            match self.check(path) {
+                Ok(()) => Ok(true),
+                Err(FileError::NotFound) => Ok(false),
+                Err(err) => Err(err),
+            }
+        }
+
+        fn open(&mut self, path: Path) -> FileResult<()> {
+            self.check(path)
+        }
+
+        fn close(&mut self, _path: Path) -> FileResult<()> {
+            Ok(())
+        }
+
+        fn read(&mut self, path: Path, buffer: &mut [u8], offset: usize) -> FileResult<usize> {
+            self.check(path)?;
+            let file = &self.files[path.as_str()];
+            let src = file.get(offset..).unwrap_or_default();
+            let read = src.len().min(buffer.len());
+            buffer[..read].copy_from_slice(&src[..read]);
+            Ok(read)
+        }
+
+        fn write(&mut self, path: Path, buffer: &[u8], offset: usize) -> FileResult<usize> {
+            self.check(path)?;
+            let file = self.files.get_mut(path.as_str()).unwrap();
+            if file.len() < offset + buffer.len() {
+                file.resize(offset + buffer.len(), 0);
+            }
+            file[offset..offset + buffer.len()].copy_from_slice(buffer);
+            Ok(buffer.len())
+        }
+
+        fn sync(&mut self, path: Path) -> FileResult<()> {
+            self.check(path)?;
+            let file = self.files[path.as_str()].clone();
+            self.disk.lock().unwrap().insert(path.as_str().into(), file);
+            Ok(())
+        }
+
+        fn metadata(&mut self, path: Path) -> FileResult<crate::fs::FileMetadata> {
+            self.check(path)?;
+            Ok(crate::fs::FileMetadata::new(
+                self.files[path.as_str()].len(),
+                crate::fs::FileType::File,
+            ))
+        }
+
+        fn read_dir(&mut self, _path: Path) -> FileResult<Vec<PathBuf>> {
+            Ok(self.files.keys().map(|name| PathBuf::new(name)).collect())
+        }
+    }
+
+    fn read_file(vfs: &Vfs<TestHelper>, path: Path) -> Vec<u8> {
+        let handle = vfs.open(path).unwrap();
+        let mut content = alloc::vec![0; vfs.file_size(handle).unwrap()];
+        vfs.read_fill(handle, &mut content, 0).unwrap();
+        vfs.close(handle).unwrap();
+        content
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let vfs = Vfs::<TestHelper>::new();
+        vfs.mount(
+            PathBuf::new("/data"),
+            Box::new(VolatileFs::new(
+                &Disk::default(),
+                &std::sync::Arc::default(),
+            )),
+        );
+        let path = Path::from("/data/settings");
+        let temp = Path::from("/data/settings.tmp");
+
+        vfs.write_atomic(path, b"first settings").unwrap();
+        vfs.write_atomic(path, b"new").unwrap();
+        assert_eq!(read_file(&vfs, path), b"new");
+        assert!(!vfs.exists(temp).unwrap());
+
+        // A leftover temporary file is replaced, not written over
+        vfs.create(temp).unwrap();
+        let handle = vfs.open(temp).unwrap();
+        vfs.write(handle, b"stale and longer", 0).unwrap();
+        vfs.close(handle).unwrap();
+        vfs.write_atomic(path, b"newer").unwrap();
+        assert_eq!(read_file(&vfs, path), b"newer");
+
+        // The target is checked like any other path
+        let handle = vfs.open(path).unwrap();
+        assert_eq!(
+            vfs.write_atomic(path, b"locked"),
+            Err(FileError::PermissionDenied)
+        );
+        vfs.close(handle).unwrap();
+        assert!(!vfs.exists(temp).unwrap());
+
+        vfs.mount(
+            PathBuf::new("/dev"),
+            Box::new(crate::fs::dev::DeviceFS::new()),
+        );
+        assert_eq!(
+            vfs.rename(path, Path::from("/dev/settings")),
+            Err(FileError::UnsupportedOperation)
+        );
+    }
+
+    #[test]
+    fn test_write_atomic_crash() {
+        let disk = Disk::default();
+        let crash = std::sync::Arc::default();
+        let vfs = Vfs::<TestHelper>::new();
+        vfs.mount(
+            PathBuf::new("/data"),
+            Box::new(VolatileFs::new(&disk, &crash)),
+        );
+        let path = Path::from("/data/settings");
+
+        vfs.write_atomic(path, b"old settings").unwrap();
+
+        // Crash once the new content is written and synced, before the rename
+        crash.store(true, Ordering::Relaxed);
+        assert_eq!(vfs.write_atomic(path, b"new"), Err(FileError::Io));
+
+        // Reboot
+        vfs.unmount("/data").unwrap();
+        let crash = std::sync::Arc::default();
+        vfs.mount(
+            PathBuf::new("/data"),
+            Box::new(VolatileFs::new(&disk, &crash)),
+        );
+        assert_eq!(read_file(&vfs, path), b"old settings");
+        assert_eq!(read_file(&vfs, Path::from("/data/settings.tmp")), b"new");
+
+        vfs.write_atomic(path, b"new").unwrap();
+        assert_eq!(read_file(&vfs, path), b"new");
+    }
+
+    std::thread_local! {
+        static PROCESS_ID: core::cell::Cell<u64> = const { core::cell::Cell::new(0) };
+    }
+
+    /// A helper whose current process is set by the test.
+    struct ProcessHelper;
+
+    impl VfsHelper for ProcessHelper {
+        fn get_current_process_id() -> u64 {
+            PROCESS_ID.get()
+        }
+    }
+
+    #[test]
+    fn test_lock_compatibility() {
+        let mut devices = crate::fs::dev::DeviceFS::new();
+        devices.add_device(
+            PathBuf::new("/file"),
+            Box::new(MemoryDevice(std::sync::Arc::default())),
+        );
+        let vfs = Vfs::<ProcessHelper>::new();
+        vfs.mount(PathBuf::new("/dev"), Box::new(devices));
+        let path = Path::from("/dev/file");
+
+        let handles = [1, 2, 3].map(|pid| {
+            PROCESS_ID.set(pid);
+            vfs.open(path).unwrap()
+        });
+        let try_lock = |pid: u64, kind| {
+            PROCESS_ID.set(pid);
+            vfs.try_lock(handles[usize::try_from(pid).unwrap() - 1], kind)
+        };
+
+        // Shared locks are compatible with each other only
+        assert_eq!(try_lock(1, LockKind::Shared), Ok(true));
+        assert_eq!(try_lock(2, LockKind::Shared), Ok(true));
+        assert_eq!(try_lock(3, LockKind::Exclusive), Ok(false));
+        assert_eq!(try_lock(1, LockKind::Exclusive), Ok(false));
+
+        // Locks are advisory
+        PROCESS_ID.set(3);
+        assert_eq!(vfs.write(handles[2], b"data", 0), Ok(4));
+
+        PROCESS_ID.set(1);
+        vfs.unlock(handles[0]).unwrap();
+        assert_eq!(try_lock(3, LockKind::Exclusive), Ok(false));
+        PROCESS_ID.set(2);
+        vfs.close(handles[1]).unwrap();
+        assert_eq!(try_lock(3, LockKind::Exclusive), Ok(true));
+        assert_eq!(try_lock(1, LockKind::Shared), Ok(false));
+        assert_eq!(try_lock(1, LockKind::Exclusive), Ok(false));
+
+        // Locking again converts the lock
+        assert_eq!(try_lock(3, LockKind::Shared), Ok(true));
+        assert_eq!(try_lock(1, LockKind::Shared), Ok(true));
+        assert_eq!(try_lock(3, LockKind::Exclusive), Ok(false));
+
+        // Handles belong to their process
+        PROCESS_ID.set(1);
+        assert_eq!(
+            vfs.try_lock(handles[2], LockKind::Shared),
+            Err(FileError::InvalidHandle)
+        );
+
+        // Locks of a process that exits are released
+        vfs.close_all_from_process(3);
+        assert_eq!(try_lock(1, LockKind::Exclusive), Ok(true));
+        assert_eq!(try_lock(3, LockKind::Shared), Err(FileError::InvalidHandle));
+    }
+
+    #[test]
+    fn test_seek() {
+        let vfs = Vfs::<ProcessHelper>::new();
+        vfs.mount(
+            PathBuf::new("/data"),
+            Box::new(VolatileFs::new(
+                &Disk::default(),
+                &std::sync::Arc::default(),
+            )),
+        );
+        let path = Path::from("/data/file");
+        PROCESS_ID.set(1);
+        vfs.create(path).unwrap();
+        let handle = vfs.open(path).unwrap();
+        let read =
+            |buffer: &mut [u8]| vfs.at_position(handle, |offset| vfs.read(handle, buffer, offset));
+        let write =
+            |buffer: &[u8]| vfs.at_position(handle, |offset| vfs.write(handle, buffer, offset));
+
+        // Transfers advance the position
+        assert_eq!(write(b"0123456789"), Ok(10));
+        assert_eq!(vfs.seek(handle, SeekFrom::Current(0)), Ok(10));
+
+        let mut buffer = [0; 3];
+        assert_eq!(vfs.seek(handle, SeekFrom::Start(2)), Ok(2));
+        assert_eq!(read(&mut buffer), Ok(3));
+        assert_eq!(&buffer, b"234");
+        assert_eq!(vfs.seek(handle, SeekFrom::Current(-4)), Ok(1));
+        assert_eq!(read(&mut buffer), Ok(3));
+        assert_eq!(&buffer, b"123");
+        assert_eq!(vfs.seek(handle, SeekFrom::End(-2)), Ok(8));
+        assert_eq!(read(&mut buffer), Ok(2));
+        assert_eq!(&buffer[..2], b"89");
+
+        // Seeking before the start fails and leaves the position untouched
+        assert_eq!(
+            vfs.seek(handle, SeekFrom::Current(-11)),
+            Err(FileError::InvalidOffset)
+        );
+        assert_eq!(
+            vfs.seek(handle, SeekFrom::End(-11)),
+            Err(FileError::InvalidOffset)
+        );
+        assert_eq!(vfs.seek(handle, SeekFrom::Current(0)), Ok(10));
+
+        // Past the end, reads return nothing and writes extend the file
+        assert_eq!(vfs.seek(handle, SeekFrom::End(4)), Ok(14));
+        assert_eq!(read(&mut buffer), Ok(0));
+        assert_eq!(vfs.seek(handle, SeekFrom::Current(0)), Ok(14));
+        assert_eq!(write(b"end"), Ok(3));
+        assert_eq!(vfs.file_size(handle), Ok(17));
+        let mut content = [0xFF; 17];
+        assert_eq!(vfs.read_fill(handle, &mut content, 0), Ok(17));
+        assert_eq!(&content, b"0123456789\0\0\0\0end");
+
+        // Handles to the same file have their own position
+        PROCESS_ID.set(2);
+        let other = vfs.open(path).unwrap();
+        assert_eq!(vfs.seek(other, SeekFrom::Current(0)), Ok(0));
+        assert_eq!(vfs.seek(other, SeekFrom::Start(5)), Ok(5));
+        PROCESS_ID.set(1);
+        assert_eq!(vfs.seek(handle, SeekFrom::Current(0)), Ok(17));
+        assert_eq!(
+            vfs.seek(other, SeekFrom::Start(0)),
+            Err(FileError::InvalidHandle)
+        );
+
+        // A new handle starts at the beginning of the file
+        vfs.close(handle).unwrap();
+        let handle = vfs.open(path).unwrap();
+        assert_eq!(vfs.seek(handle, SeekFrom::Current(0)), Ok(0));
+    }
+
+    #[test]
+    fn test_append() {
+        let vfs = Vfs::<ProcessHelper>::new();
+        vfs.mount(
+            PathBuf::new("/data"),
+            Box::new(VolatileFs::new(
+                &Disk::default(),
+                &std::sync::Arc::default(),
+            )),
+        );
+        let path = Path::from("/data/log");
+        PROCESS_ID.set(1);
+        vfs.create(path).unwrap();
+        let first = vfs.open_with_mode(path, OpenMode::Append).unwrap();
+        PROCESS_ID.set(2);
+        let second = vfs.open_with_mode(path, OpenMode::Append).unwrap();
+        let append = |pid: u64, handle: Handle, line: &[u8]| {
+            PROCESS_ID.set(pid);
+            vfs.at_position(handle, |offset| vfs.write(handle, line, offset))
+        };
+
+        // Neither handle overwrites the lines of the other
+        assert_eq!(append(1, first, b"a1\n"), Ok(3));
+        assert_eq!(append(2, second, b"b1\n"), Ok(3));
+        assert_eq!(append(1, first, b"a2\n"), Ok(3));
+        PROCESS_ID.set(2);
+        assert_eq!(vfs.write_vectored(second, &[b"b2", b"\n"], 0), Ok(3));
+
+        // Explicit offsets and seeks are ignored by writes, and the position follows the data
+        PROCESS_ID.set(1);
+        assert_eq!(vfs.seek(first, SeekFrom::Start(0)), Ok(0));
+        assert_eq!(vfs.write(first, b"a3\n", 1), Ok(3));
+        assert_eq!(vfs.seek(first, SeekFrom::Current(0)), Ok(15));
+
+        // Reads are not affected
+        let mut content = [0; 15];
+        assert_eq!(vfs.read_fill(first, &mut content, 0), Ok(15));
+        assert_eq!(&content, b"a1\nb1\na2\nb2\na3\n");
+        assert_eq!(vfs.seek(first, SeekFrom::Start(3)), Ok(3));
+        let mut line = [0; 3];
+        assert_eq!(
+            vfs.at_position(first, |offset| vfs.read(first, &mut line, offset)),
+            Ok(3)
+        );
+        assert_eq!(&line, b"b1\n");
+        assert_eq!(vfs.seek(first, SeekFrom::Current(0)), Ok(6));
+    }
+
+    #[test]
+    fn test_pipe_handles() {
+        let vfs = Vfs::<ProcessHelper>::new();
+        PROCESS_ID.set(1);
+        let (read, write) = vfs.create_pipe();
+        let mut buffer = [0; 4];
+
+        assert_eq!(vfs.read(read, &mut buffer, 0), Err(FileError::WouldBlock));
+        assert_eq!(vfs.write(write, b"data", 42), Ok(4));
+        assert_eq!(vfs.pipe_readiness(read), Some((PipeEnd::Read, true)));
+        assert_eq!(vfs.read(read, &mut buffer, 42), Ok(4));
+        assert_eq!(&buffer, b"data");
+
+        // Each handle is a single end, owned by its process
+        assert_eq!(
+            vfs.read(write, &mut buffer, 0),
+            Err(FileError::PermissionDenied)
+        );
+        assert_eq!(
+            vfs.write(read, b"data", 0),
+            Err(FileError::PermissionDenied)
+        );
+        assert_eq!(
+            vfs.seek(read, SeekFrom::Start(0)),
+            Err(FileError::InvalidHandle)
+        );
+        PROCESS_ID.set(2);
+        assert!(!vfs.is_handle_valid(read));
+        assert_eq!(vfs.close(read), Err(FileError::InvalidHandle));
+
+        // Closing the write end gives an end of file
+        PROCESS_ID.set(1);
+        assert!(vfs.is_handle_valid(read));
+        assert_eq!(vfs.write(write, b"end", 0), Ok(3));
+        vfs.close(write).unwrap();
+        assert_eq!(vfs.read_vectored(read, &mut [&mut buffer], 0), Ok(3));
+        assert_eq!(vfs.read(read, &mut buffer, 0), Ok(0));
+        assert_eq!(vfs.close(write), Err(FileError::InvalidHandle));
+
+        // Ends are closed when their process exits
+        let (read, write) = vfs.create_pipe();
+        vfs.close_all_from_process(1);
+        assert!(!vfs.is_handle_valid(read));
+        assert_eq!(vfs.write(write, b"lost", 0), Err(FileError::InvalidHandle));
+    }
+
+    #[test]
+    fn test_dup2() {
+        let vfs = Vfs::<ProcessHelper>::new();
+        vfs.mount(
+            PathBuf::new("/data"),
+            Box::new(VolatileFs::new(
+                &Disk::default(),
+                &std::sync::Arc::default(),
+            )),
+        );
+        let log = Path::from("/data/log");
+        let other = Path::from("/data/other");
+        PROCESS_ID.set(1);
+        vfs.create(log).unwrap();
+        vfs.create(other).unwrap();
+        let log_handle = vfs.open(log).unwrap();
+        let other_handle = vfs.open(other).unwrap();
+
+        // Redirect the standard output to the log
+        assert_eq!(vfs.dup2(log_handle, Handle::STDOUT, false), Ok(()));
+        assert_eq!(vfs.write(Handle::STDOUT, b"out", 0), Ok(3));
+        assert_eq!(read_file_by_handle(&vfs, log_handle), b"out");
+
+        // The replaced handle is closed, so its file can be opened again
+        assert_eq!(vfs.dup2(log_handle, other_handle, false), Ok(()));
+        assert_eq!(read_file_by_handle(&vfs, other_handle), b"out");
+        assert_eq!(vfs.open(other).map(|_| ()), Ok(()));
+
+        // Duplicates move on their own
+        let dup = vfs.dup(log_handle, false).unwrap();
+        assert_eq!(vfs.seek(dup, SeekFrom::End(0)), Ok(3));
+        assert_eq!(vfs.seek(log_handle, SeekFrom::Current(0)), Ok(0));
+
+        // Closing a duplicate leaves the original open
+        vfs.close(dup).unwrap();
+        assert!(vfs.is_handle_valid(log_handle));
+        assert_eq!(
+            vfs.dup2(Handle::STDERR, dup, false),
+            Err(FileError::InvalidHandle)
+        );
+        assert!(!vfs.is_handle_valid(dup));
+    }
+
+    #[test]
+    fn test_close_on_exec() {
+        let vfs = Vfs::<ProcessHelper>::new();
+        vfs.mount(
+            PathBuf::new("/data"),
+            Box::new(VolatileFs::new(
+                &Disk::default(),
+                &std::sync::Arc::default(),
+            )),
+        );
+        let path = Path::from("/data/file");
+        PROCESS_ID.set(1);
+        vfs.create(path).unwrap();
+        let file = vfs.open(path).unwrap();
+        let (read, write) = vfs.create_pipe();
+        let private = vfs.dup(file, true).unwrap();
+        vfs.dup2(write, Handle::STDOUT, false).unwrap();
+        vfs.dup2(read, read, true).unwrap();
+
+        vfs.inherit_handles(1, 2);
+        PROCESS_ID.set(2);
+        assert!(vfs.is_handle_valid(file));
+        assert!(vfs.is_handle_valid(write));
+        assert!(vfs.is_handle_valid(Handle::STDOUT));
+        assert!(!vfs.is_handle_valid(private));
+        assert!(!vfs.is_handle_valid(read));
+
+        // The pipe stays open until every writer, in both processes, is closed
+        assert_eq!(vfs.write(Handle::STDOUT, b"child", 0), Ok(5));
+        vfs.close_all_from_process(2);
+        PROCESS_ID.set(1);
+        vfs.close(Handle::STDOUT).unwrap();
+        let mut buffer = [0; 8];
+        assert_eq!(vfs.read(read, &mut buffer, 0), Ok(5));
+        assert_eq!(vfs.read(read, &mut buffer, 0), Err(FileError::WouldBlock));
+        vfs.close(write).unwrap();
+        assert_eq!(vfs.read(read, &mut buffer, 0), Ok(0));
+    }
+
+    fn read_file_by_handle(vfs: &Vfs<ProcessHelper>, handle: Handle) -> Vec<u8> {
+        let mut content = alloc::vec![0; vfs.file_size(handle).unwrap()];
+        vfs.read_fill(handle, &mut content, 0).unwrap();
+        content
+    }
+}
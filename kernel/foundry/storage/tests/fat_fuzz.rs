@@ -0,0 +1,200 @@
+//! Fuzzing of the FAT boot sector parsers, run on the host.
+//!
+//! Random sectors are almost always rejected by the boot signature check, so most
+//! inputs are valid boot sectors with a few bytes of their BPB overwritten.
+//!
+//! Inputs are drawn from a generator seeded with `SEED`, which can be set with the
+//! `FAT_FUZZ_SEED` environment variable. A panicking input is printed, so that it can
+//! be added to the regression tests at the end of this file.
+use std::panic::{self, AssertUnwindSafe};
+use storage::fs::fat::{
+    FatError,
+    bs::{BootSector, ExtendedBootSector},
+};
+
+/// Default seed of the runs.
+const SEED: u64 = 0xFA7_F022;
+
+/// Number of inputs of every run.
+const ITERATIONS: usize = 100_000;
+
+const SECTOR_SIZE: usize = 512;
+
+/// Range of the BPB fields in the boot sector.
+const BPB_RANGE: core::ops::Range<usize> = 11..90;
+
+type Sector = [u8; SECTOR_SIZE];
+
+/// A xorshift64* generator.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state of xorshift must not be zero.
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        usize::try_from(self.next_u64() % n as u64).unwrap()
+    }
+
+    fn byte(&mut self) -> u8 {
+        self.next_u64().to_le_bytes()[0]
+    }
+}
+
+fn seed() -> u64 {
+    std::env::var("FAT_FUZZ_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(SEED)
+}
+
+fn to_sector<T: Copy>(boot_sector: &T) -> Sector {
+    assert_eq!(size_of::<T>(), SECTOR_SIZE);
+    let mut sector = [0; SECTOR_SIZE];
+    // Safety: Boot sectors are packed structures of integers, without padding.
+    let bytes =
+        unsafe { core::slice::from_raw_parts((&raw const *boot_sector).cast(), SECTOR_SIZE) };
+    sector.copy_from_slice(bytes);
+    sector
+}
+
+/// Valid boot sectors, that the mutations start from.
+fn seeds() -> [Sector; 3] {
+    [
+        to_sector(
+            &BootSector::new_fat12()
+                .configure_for_volume_size(1024 * 1024)
+                .unwrap(),
+        ),
+        to_sector(
+            &BootSector::new_fat16()
+                .configure_for_volume_size(16 * 1024 * 1024)
+                .unwrap(),
+        ),
+        to_sector(
+            &ExtendedBootSector::new_fat32()
+                .configure_for_volume_size(512 * 1024 * 1024)
+                .unwrap(),
+        ),
+    ]
+}
+
+/// Parses `sector` and uses every field derived from it.
+///
+/// This must not panic, whatever the input.
+fn check(sector: &Sector) {
+    match BootSector::from_bytes(sector) {
+        Ok(boot_sector) => {
+            let bpb = boot_sector.bpb();
+            let _ = bpb.bytes_per_cluster();
+            let _ = bpb.total_sectors();
+            assert!(bpb.cluster_count().is_ok_and(|clusters| clusters > 0));
+            let _ = bpb.calculate_sectors_per_fat_fat12();
+            let _ = bpb.calculate_sectors_per_fat_fat16();
+        }
+        Err(err) => assert_eq!(err, FatError::InvalidBootSector),
+    }
+
+    match ExtendedBootSector::from_bytes(sector) {
+        Ok(boot_sector) => {
+            let bpb = boot_sector.bpb();
+            let _ = bpb.bytes_per_cluster();
+            let _ = bpb.total_sectors();
+            assert!(bpb.cluster_count().is_ok_and(|clusters| clusters > 0));
+            let _ = bpb.calculate_sectors_per_fat();
+        }
+        Err(err) => assert_eq!(err, FatError::InvalidBootSector),
+    }
+}
+
+/// Runs `check` on `sector`, printing the sector if it panics.
+fn check_or_report(sector: &Sector) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| check(sector))) {
+        eprintln!("Panicking input (FAT_FUZZ_SEED={}): {sector:02X?}", seed());
+        panic::resume_unwind(payload);
+    }
+}
+
+#[test]
+fn fuzz_random_sectors() {
+    let mut rng = Rng::new(seed());
+    let mut sector = [0; SECTOR_SIZE];
+
+    for _ in 0..ITERATIONS {
+        sector.fill_with(|| rng.byte());
+        // Give the parser a chance to look past the signature
+        if rng.below(2) == 0 {
+            sector[510..].copy_from_slice(&[0x55, 0xAA]);
+        }
+        check_or_report(&sector);
+    }
+}
+
+#[test]
+fn fuzz_mutated_boot_sectors() {
+    let mut rng = Rng::new(seed());
+    let seeds = seeds();
+
+    for sector in &seeds {
+        check_or_report(sector);
+    }
+
+    for _ in 0..ITERATIONS {
+        let mut sector = seeds[rng.below(seeds.len())];
+        for _ in 0..=rng.below(4) {
+            let index = BPB_RANGE.start + rng.below(BPB_RANGE.len());
+            sector[index] = rng.byte();
+        }
+        check_or_report(&sector);
+    }
+}
+
+#[test]
+fn test_truncated_sector() {
+    let sector = seeds()[0];
+    assert_eq!(
+        BootSector::from_bytes(&sector[..SECTOR_SIZE - 1]).unwrap_err(),
+        FatError::UnexpectedEOF
+    );
+    assert!(BootSector::from_bytes(&sector).is_ok());
+}
+
+/// Builds a sector from the BPB bytes of an input found by the fuzzer.
+fn regression_sector(bpb: &[u8]) -> Sector {
+    let mut sector = [0; SECTOR_SIZE];
+    sector[..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+    sector[BPB_RANGE].copy_from_slice(bpb);
+    sector[510..].copy_from_slice(&[0x55, 0xAA]);
+    sector
+}
+
+#[test]
+fn test_regression_fat32_with_fat16_fields() {
+    // A FAT32 boot sector whose 16-bit sectors per FAT and total sectors were overwritten.
+    // It used to be accepted as FAT12/16, with more clusters than FAT16 can address,
+    // so that computing the size of its FAT overflowed.
+    let sector = regression_sector(&[
+        0x00, 0x02, 0x08, 0x20, 0xB4, 0x02, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x16, 0x00, 0x3F, 0x00,
+        0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x4D, 0x34, 0x04, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x29, 0x00, 0x00, 0x00, 0x00,
+        0x4E, 0x4F, 0x20, 0x4E, 0x41, 0x4D, 0x45, 0x20, 0x20, 0x20, 0xAC, 0x46, 0x41, 0x54, 0x33,
+        0x32, 0x20, 0x20, 0x20,
+    ]);
+
+    assert_eq!(
+        BootSector::from_bytes(&sector).unwrap_err(),
+        FatError::InvalidBootSector
+    );
+    check(&sector);
+}
@@ -102,6 +102,15 @@ impl<B: BlockDevice> FileSystem for MockFS<B> {
         Err(FileError::NotFound)
     }
 
+    fn sync(&mut self, path: Path) -> FileResult<()> {
+        if self.files.iter().any(|file| file.name == path.as_str()) {
+            // Writes go straight to the block device.
+            Ok(())
+        } else {
+            Err(FileError::NotFound)
+        }
+    }
+
     fn close(&mut self, path: Path) -> FileResult<()> {
         for file in &self.files {
             if file.name == path.as_str() {
@@ -137,6 +146,22 @@ impl<B: BlockDevice> FileSystem for MockFS<B> {
         Err(FileError::NotFound)
     }
 
+    fn rename(&mut self, from: Path, to: Path) -> FileResult<()> {
+        if !self.files.iter().any(|file| file.name == from.as_str()) {
+            return Err(FileError::NotFound);
+        }
+        if from != to {
+            // The renamed file replaces the old one
+            self.files.retain(|file| file.name != to.as_str());
+        }
+        for file in &mut self.files {
+            if file.name == from.as_str() {
+                file.name = String::from(to.as_str());
+            }
+        }
+        Ok(())
+    }
+
     fn exists(&mut self, path: Path) -> FileResult<bool> {
         for file in &self.files {
             if file.name == path.as_str() {
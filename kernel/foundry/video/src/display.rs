@@ -0,0 +1,250 @@
+//! Arbitration of the screen between fullscreen apps.
+//!
+//! Apps acquire the display to be brought to the foreground.
+//! Only the foreground app may draw, while the others wait in the background,
+//! from the most recently active one.
+//! When no app holds the display, every app may draw.
+use beskar_core::syscall::display::DisplayEvent;
+
+/// Maximum number of apps holding the display, foreground and background included.
+pub const MAX_APPS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcquireError {
+    /// Too many apps hold the display.
+    TooManyApps,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct App {
+    pid: u64,
+    /// The last event that the app has not taken yet.
+    pending: Option<DisplayEvent>,
+}
+
+impl App {
+    const EMPTY: Self = Self {
+        pid: 0,
+        pending: None,
+    };
+}
+
+#[derive(Debug)]
+pub struct DisplayArbiter {
+    /// The foreground app first, then the background apps, from the most recently active.
+    apps: [App; MAX_APPS],
+    len: usize,
+}
+
+impl Default for DisplayArbiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DisplayArbiter {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            apps: [App::EMPTY; MAX_APPS],
+            len: 0,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    fn apps(&self) -> &[App] {
+        &self.apps[..self.len]
+    }
+
+    #[must_use]
+    #[inline]
+    fn position(&self, pid: u64) -> Option<usize> {
+        self.apps().iter().position(|app| app.pid == pid)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the PID of the foreground app, if any.
+    pub fn owner(&self) -> Option<u64> {
+        self.apps().first().map(|app| app.pid)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether the given process may draw on the screen.
+    pub fn may_draw(&self, pid: u64) -> bool {
+        self.owner().is_none_or(|owner| owner == pid)
+    }
+
+    /// Brings the given process to the foreground.
+    ///
+    /// The previous foreground app is notified that it lost the display.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process does not hold the display yet and `MAX_APPS` apps do.
+    pub fn acquire(&mut self, pid: u64) -> Result<(), AcquireError> {
+        let index = if let Some(index) = self.position(pid) {
+            index
+        } else {
+            if self.len == MAX_APPS {
+                return Err(AcquireError::TooManyApps);
+            }
+            self.apps[self.len] = App { pid, pending: None };
+            self.len += 1;
+            self.len - 1
+        };
+        if index == 0 {
+            return Ok(());
+        }
+
+        self.apps[..=index].rotate_right(1);
+        self.apps[0].pending = None;
+        self.apps[1].pending = Some(DisplayEvent::Lost);
+        Ok(())
+    }
+
+    /// Removes the given process, for instance when it exits.
+    ///
+    /// If it was in the foreground, the most recent background app gets the display back.
+    pub fn release(&mut self, pid: u64) {
+        let Some(index) = self.position(pid) else {
+            return;
+        };
+
+        self.apps[index..self.len].rotate_left(1);
+        self.len -= 1;
+        if index == 0 && self.len > 0 {
+            self.apps[0].pending = Some(DisplayEvent::Gained);
+        }
+    }
+
+    /// Sends the foreground app to the back, bringing the next one to the foreground.
+    ///
+    /// Returns the PID of the new foreground app, or `None` if nothing changed.
+    pub fn switch_next(&mut self) -> Option<u64> {
+        if self.len < 2 {
+            return None;
+        }
+
+        self.apps[..self.len].rotate_left(1);
+        self.apps[self.len - 1].pending = Some(DisplayEvent::Lost);
+        self.apps[0].pending = Some(DisplayEvent::Gained);
+        self.owner()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether the given process has a pending event.
+    pub fn has_event(&self, pid: u64) -> bool {
+        self.position(pid)
+            .is_some_and(|index| self.apps[index].pending.is_some())
+    }
+
+    /// Takes the pending event of the given process.
+    ///
+    /// Only the last event is kept: a process that lost then gained the display back
+    /// only sees `DisplayEvent::Gained`.
+    pub fn take_event(&mut self, pid: u64) -> Option<DisplayEvent> {
+        let index = self.position(pid)?;
+        self.apps[index].pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_release() {
+        let mut display = DisplayArbiter::new();
+        assert_eq!(display.owner(), None);
+        assert!(display.may_draw(1));
+
+        display.acquire(1).unwrap();
+        assert_eq!(display.owner(), Some(1));
+        assert!(display.may_draw(1));
+        assert!(!display.may_draw(2));
+        assert!(!display.has_event(1));
+
+        // Another app takes over.
+        display.acquire(2).unwrap();
+        assert_eq!(display.owner(), Some(2));
+        assert!(!display.may_draw(1));
+        assert_eq!(display.take_event(1), Some(DisplayEvent::Lost));
+        assert_eq!(display.take_event(1), None);
+        assert_eq!(display.take_event(2), None);
+
+        // Acquiring again is a no-op.
+        display.acquire(2).unwrap();
+        assert!(!display.has_event(1));
+
+        // The previous app gets the display back.
+        display.release(2);
+        assert_eq!(display.owner(), Some(1));
+        assert_eq!(display.take_event(1), Some(DisplayEvent::Gained));
+        assert_eq!(display.take_event(2), None);
+
+        display.release(1);
+        assert_eq!(display.owner(), None);
+        assert!(display.may_draw(3));
+
+        // Releasing an unknown process does nothing.
+        display.release(4);
+        assert_eq!(display.owner(), None);
+    }
+
+    #[test]
+    fn test_background_order() {
+        let mut display = DisplayArbiter::new();
+        display.acquire(1).unwrap();
+        display.acquire(2).unwrap();
+        display.acquire(3).unwrap();
+
+        // A background app coming back to the foreground.
+        display.acquire(1).unwrap();
+        assert_eq!(display.owner(), Some(1));
+        assert_eq!(display.take_event(3), Some(DisplayEvent::Lost));
+        // The app asked for the display itself, so it is not notified.
+        assert_eq!(display.take_event(1), None);
+
+        // Releasing a background app keeps the foreground.
+        display.release(2);
+        assert_eq!(display.owner(), Some(1));
+        assert!(!display.has_event(1));
+
+        display.release(1);
+        assert_eq!(display.owner(), Some(3));
+        assert_eq!(display.take_event(3), Some(DisplayEvent::Gained));
+    }
+
+    #[test]
+    fn test_switch() {
+        let mut display = DisplayArbiter::new();
+        assert_eq!(display.switch_next(), None);
+
+        display.acquire(1).unwrap();
+        assert_eq!(display.switch_next(), None);
+
+        display.acquire(2).unwrap();
+        let _ = display.take_event(1);
+        assert_eq!(display.switch_next(), Some(1));
+        assert_eq!(display.take_event(1), Some(DisplayEvent::Gained));
+        assert_eq!(display.take_event(2), Some(DisplayEvent::Lost));
+        assert_eq!(display.switch_next(), Some(2));
+    }
+
+    #[test]
+    fn test_too_many_apps() {
+        let mut display = DisplayArbiter::new();
+        for pid in 0..MAX_APPS as u64 {
+            display.acquire(pid).unwrap();
+        }
+        assert_eq!(display.acquire(100), Err(AcquireError::TooManyApps));
+        // Apps that already hold the display can still come to the foreground.
+        display.acquire(0).unwrap();
+        assert_eq!(display.owner(), Some(0));
+    }
+}
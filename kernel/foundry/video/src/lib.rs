@@ -4,5 +4,6 @@
 #![allow(clippy::missing_panics_doc)]
 #![feature(pointer_try_cast_aligned)]
 
+pub mod display;
 pub mod log;
 pub mod screen;
@@ -0,0 +1,184 @@
+//! Virtio block devices.
+//!
+//! A request is a chain of three buffers: a header read by the device,
+//! the data (read by the device for writes, written for reads),
+//! and a status byte written by the device.
+
+use crate::queue::Buffer;
+use beskar_core::storage::BlockDeviceError;
+
+/// Size of a sector, which request offsets and the capacity are expressed in.
+pub const SECTOR_SIZE: usize = 512;
+
+/// Feature bit: the device is read-only.
+pub const F_RO: u64 = 1 << 5;
+
+/// Offset of the capacity of the device (a `u64`, in sectors) in its configuration.
+pub const CONFIG_CAPACITY: usize = 0;
+
+/// Request completed successfully.
+pub const STATUS_OK: u8 = 0;
+/// Request failed.
+pub const STATUS_IOERR: u8 = 1;
+/// Request is not supported by the device.
+pub const STATUS_UNSUPP: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RequestType {
+    /// Read sectors from the device.
+    In = 0,
+    /// Write sectors to the device.
+    Out = 1,
+}
+
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "The header is 16 bytes long"
+)]
+const HEADER_LEN: u32 = size_of::<RequestHeader>() as u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// Header of a request.
+pub struct RequestHeader {
+    request_type: u32,
+    _reserved: u32,
+    /// Offset of the request, in sectors.
+    sector: u64,
+}
+
+impl RequestHeader {
+    #[must_use]
+    #[inline]
+    pub const fn new(request_type: RequestType, sector: u64) -> Self {
+        Self {
+            request_type: request_type as u32,
+            _reserved: 0,
+            sector,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn sector(&self) -> u64 {
+        self.sector
+    }
+
+    #[must_use]
+    /// Serialize the header as read by the device.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[0..4].copy_from_slice(&self.request_type.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.sector.to_le_bytes());
+        bytes
+    }
+}
+
+#[must_use]
+/// Builds the descriptor chain of a read or write request.
+///
+/// The header and status buffers are at the given physical addresses,
+/// and `data_len` bytes are transferred to or from `data_addr`.
+pub const fn request_chain(
+    request_type: RequestType,
+    header_addr: u64,
+    data_addr: u64,
+    data_len: u32,
+    status_addr: u64,
+) -> [Buffer; 3] {
+    let data = match request_type {
+        RequestType::In => Buffer::writable(data_addr, data_len),
+        RequestType::Out => Buffer::readable(data_addr, data_len),
+    };
+    [
+        Buffer::readable(header_addr, HEADER_LEN),
+        data,
+        Buffer::writable(status_addr, 1),
+    ]
+}
+
+/// Checks the status written by the device once a request completed.
+pub const fn check_status(status: u8) -> Result<(), BlockDeviceError> {
+    match status {
+        STATUS_OK => Ok(()),
+        STATUS_UNSUPP => Err(BlockDeviceError::Unsupported),
+        _ => Err(BlockDeviceError::Io),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::{DESC_F_NEXT, DESC_F_WRITE, Descriptor, tests::Memory};
+
+    #[test]
+    fn test_header_bytes() {
+        assert_eq!(size_of::<RequestHeader>(), 16);
+        let header = RequestHeader::new(RequestType::Out, 0x0102_0304_0506);
+        assert_eq!(
+            header.to_bytes(),
+            [1, 0, 0, 0, 0, 0, 0, 0, 6, 5, 4, 3, 2, 1, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_read_request_chain() {
+        let memory = Memory::new();
+        let mut queue = memory.queue(8);
+
+        let chain = request_chain(RequestType::In, 0x1000, 0x2000, 4096, 0x1010);
+        let head = queue.push(&chain).unwrap();
+        assert_eq!(queue.num_free(), 5);
+
+        let header = queue.read_desc(head);
+        assert_eq!(
+            header,
+            Descriptor {
+                addr: 0x1000,
+                len: 16,
+                flags: DESC_F_NEXT,
+                next: header.next,
+            }
+        );
+        let data = queue.read_desc(header.next);
+        assert_eq!(
+            data,
+            Descriptor {
+                addr: 0x2000,
+                len: 4096,
+                flags: DESC_F_NEXT | DESC_F_WRITE,
+                next: data.next,
+            }
+        );
+        assert_eq!(
+            queue.read_desc(data.next),
+            Descriptor {
+                addr: 0x1010,
+                len: 1,
+                flags: DESC_F_WRITE,
+                next: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_write_request_chain() {
+        let chain = request_chain(RequestType::Out, 0x1000, 0x2000, 512, 0x1010);
+        assert!(!chain[0].is_writable());
+        assert!(!chain[1].is_writable());
+        assert!(chain[2].is_writable());
+        assert_eq!(chain[1].len(), 512);
+    }
+
+    #[test]
+    fn test_check_status() {
+        assert_eq!(check_status(STATUS_OK), Ok(()));
+        assert_eq!(check_status(STATUS_IOERR), Err(BlockDeviceError::Io));
+        assert_eq!(
+            check_status(STATUS_UNSUPP),
+            Err(BlockDeviceError::Unsupported)
+        );
+        assert_eq!(check_status(0xFF), Err(BlockDeviceError::Io));
+    }
+}
@@ -0,0 +1,92 @@
+//! Virtio devices, as emulated by QEMU.
+//!
+//! This crate holds the structures shared with the device: virtqueues and device-specific requests.
+//! Transports (legacy I/O ports or modern PCI capabilities) are left to the drivers.
+#![cfg_attr(not(test), no_std)]
+#![forbid(unsafe_op_in_unsafe_fn)]
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+pub mod blk;
+pub mod net;
+pub mod queue;
+
+/// PCI vendor ID of virtio devices.
+pub const PCI_VENDOR_ID: u16 = 0x1AF4;
+
+/// Feature bit: the device complies with the virtio 1.0 specification (modern interface).
+pub const F_VERSION_1: u64 = 1 << 32;
+
+/// Bits of the device status register.
+pub mod status {
+    /// The driver has noticed the device.
+    pub const ACKNOWLEDGE: u8 = 1;
+    /// The driver knows how to drive the device.
+    pub const DRIVER: u8 = 2;
+    /// The driver is ready to drive the device.
+    pub const DRIVER_OK: u8 = 4;
+    /// The driver has acknowledged the features it understands (modern interface only).
+    pub const FEATURES_OK: u8 = 8;
+    /// The driver gave up on the device.
+    pub const FAILED: u8 = 0x80;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Type of a virtio device.
+pub enum DeviceType {
+    Net,
+    Block,
+}
+
+impl DeviceType {
+    #[must_use]
+    /// Returns the type of a device from its PCI device ID.
+    ///
+    /// Transitional devices (0x1000-0x103F) support both interfaces,
+    /// while modern devices (0x1040 + type) only support the modern interface.
+    pub const fn from_pci_id(id: u16) -> Option<Self> {
+        match id {
+            0x1000 | 0x1041 => Some(Self::Net),
+            0x1001 | 0x1042 => Some(Self::Block),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+/// Type of the structure described by a vendor-specific PCI capability of a modern device.
+pub enum PciCapabilityType {
+    Common = 1,
+    Notify = 2,
+    Isr = 3,
+    Device = 4,
+    PciConfig = 5,
+}
+
+impl PciCapabilityType {
+    #[must_use]
+    pub const fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Common),
+            2 => Some(Self::Notify),
+            3 => Some(Self::Isr),
+            4 => Some(Self::Device),
+            5 => Some(Self::PciConfig),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_type() {
+        assert_eq!(DeviceType::from_pci_id(0x1001), Some(DeviceType::Block));
+        assert_eq!(DeviceType::from_pci_id(0x1042), Some(DeviceType::Block));
+        assert_eq!(DeviceType::from_pci_id(0x1041), Some(DeviceType::Net));
+        assert_eq!(DeviceType::from_pci_id(0x1002), None);
+    }
+}
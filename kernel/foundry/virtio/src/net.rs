@@ -0,0 +1,126 @@
+//! Virtio network devices.
+//!
+//! Frames are preceded by a header describing offloads, in both directions.
+//! Offloads are not negotiated, so the header is always zeroed on transmission
+//! and ignored on reception.
+
+use crate::{F_VERSION_1, queue::Buffer};
+
+/// Feature bit: the device has a MAC address in its configuration.
+pub const F_MAC: u64 = 1 << 5;
+/// Feature bit: received frames can span several buffers.
+pub const F_MRG_RXBUF: u64 = 1 << 15;
+
+/// Offset of the MAC address of the device (6 bytes) in its configuration.
+pub const CONFIG_MAC: usize = 0;
+
+/// Index of the receive queue.
+pub const RX_QUEUE: u16 = 0;
+/// Index of the transmit queue.
+pub const TX_QUEUE: u16 = 1;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// Header preceding frames.
+///
+/// `num_buffers` is only part of the header with modern devices or mergeable buffers,
+/// see `header_len`.
+pub struct NetHeader {
+    pub flags: u8,
+    pub gso_type: u8,
+    pub hdr_len: u16,
+    pub gso_size: u16,
+    pub csum_start: u16,
+    pub csum_offset: u16,
+    pub num_buffers: u16,
+}
+
+#[must_use]
+#[inline]
+/// Length of the header preceding frames, depending on the negotiated features.
+pub const fn header_len(features: u64) -> usize {
+    if features & (F_VERSION_1 | F_MRG_RXBUF) == 0 {
+        size_of::<NetHeader>() - size_of::<u16>()
+    } else {
+        size_of::<NetHeader>()
+    }
+}
+
+#[must_use]
+/// Builds the descriptor chain of a frame to transmit.
+///
+/// The buffer at `addr` holds a zeroed header of `header_len` bytes,
+/// directly followed by the `frame_len` bytes of the frame.
+pub fn tx_chain(addr: u64, header_len: usize, frame_len: usize) -> [Buffer; 1] {
+    let len = u32::try_from(header_len + frame_len).unwrap();
+    [Buffer::readable(addr, len)]
+}
+
+#[must_use]
+/// Builds the descriptor chain of a receive buffer of `len` bytes.
+pub fn rx_chain(addr: u64, len: usize) -> [Buffer; 1] {
+    [Buffer::writable(addr, u32::try_from(len).unwrap())]
+}
+
+#[must_use]
+/// Strips the header off a received buffer, of which the device wrote `written` bytes.
+///
+/// Returns `None` if the buffer does not hold a frame.
+pub fn rx_frame(buffer: &[u8], written: usize, header_len: usize) -> Option<&[u8]> {
+    buffer
+        .get(header_len..written)
+        .filter(|frame| !frame.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::{DESC_F_WRITE, Descriptor, tests::Memory};
+    use core::mem::offset_of;
+
+    #[test]
+    fn test_header_layout() {
+        assert_eq!(size_of::<NetHeader>(), 12);
+        assert_eq!(offset_of!(NetHeader, gso_type), 1);
+        assert_eq!(offset_of!(NetHeader, hdr_len), 2);
+        assert_eq!(offset_of!(NetHeader, gso_size), 4);
+        assert_eq!(offset_of!(NetHeader, csum_start), 6);
+        assert_eq!(offset_of!(NetHeader, csum_offset), 8);
+        assert_eq!(offset_of!(NetHeader, num_buffers), 10);
+
+        assert_eq!(header_len(F_MAC), 10);
+        assert_eq!(header_len(F_MAC | F_MRG_RXBUF), 12);
+        assert_eq!(header_len(F_VERSION_1), 12);
+    }
+
+    #[test]
+    fn test_tx_descriptor() {
+        let memory = Memory::new();
+        let mut queue = memory.queue(4);
+
+        let head = queue.push(&tx_chain(0x4000, 12, 60)).unwrap();
+        assert_eq!(queue.num_free(), 3);
+        assert_eq!(
+            queue.read_desc(head),
+            Descriptor {
+                addr: 0x4000,
+                len: 72,
+                flags: 0,
+                next: 0,
+            }
+        );
+
+        let head = queue.push(&rx_chain(0x5000, 2048)).unwrap();
+        assert_eq!(queue.read_desc(head).flags, DESC_F_WRITE);
+    }
+
+    #[test]
+    fn test_rx_frame() {
+        let mut buffer = [0; 32];
+        buffer[10..14].copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(rx_frame(&buffer, 14, 10), Some([1, 2, 3, 4].as_slice()));
+        assert_eq!(rx_frame(&buffer, 10, 10), None);
+        assert_eq!(rx_frame(&buffer, 4, 10), None);
+        assert_eq!(rx_frame(&buffer, 64, 10), None);
+    }
+}
@@ -0,0 +1,512 @@
+//! Split virtqueues.
+//!
+//! A virtqueue is made of three parts shared with the device:
+//! - the descriptor table, describing buffers, which can be chained;
+//! - the available ring, where the driver puts the heads of the chains it offers;
+//! - the used ring, where the device puts the heads of the chains it is done with.
+//!
+//! The queue is laid out contiguously, as expected by the legacy interface,
+//! which the modern interface is also happy with.
+
+use core::ptr::NonNull;
+use core::sync::atomic::{Ordering, fence};
+
+/// Descriptor flag: the buffer continues in the `next` descriptor.
+pub const DESC_F_NEXT: u16 = 1;
+/// Descriptor flag: the buffer is written by the device.
+pub const DESC_F_WRITE: u16 = 2;
+
+/// Alignment of the used ring, and of the whole queue, required by the legacy interface.
+pub const LEGACY_ALIGN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+/// An entry of the descriptor table.
+pub struct Descriptor {
+    /// Physical address of the buffer.
+    pub addr: u64,
+    pub len: u32,
+    pub flags: u16,
+    /// Index of the next descriptor of the chain, if `DESC_F_NEXT` is set.
+    pub next: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A buffer of a descriptor chain.
+pub struct Buffer {
+    addr: u64,
+    len: u32,
+    writable: bool,
+}
+
+impl Buffer {
+    #[must_use]
+    #[inline]
+    /// A buffer read by the device.
+    pub const fn readable(addr: u64, len: u32) -> Self {
+        Self {
+            addr,
+            len,
+            writable: false,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// A buffer written by the device.
+    pub const fn writable(addr: u64, len: u32) -> Self {
+        Self {
+            addr,
+            len,
+            writable: true,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn len(&self) -> u32 {
+        self.len
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_writable(&self) -> bool {
+        self.writable
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Layout of a virtqueue in memory.
+pub struct QueueLayout {
+    size: u16,
+}
+
+impl QueueLayout {
+    #[must_use]
+    #[inline]
+    /// Layout of a queue of `size` descriptors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not a power of two.
+    pub const fn new(size: u16) -> Self {
+        assert!(size.is_power_of_two(), "Queue size must be a power of two");
+        Self { size }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of descriptors of the queue.
+    pub const fn size(&self) -> u16 {
+        self.size
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn desc_offset(&self) -> usize {
+        0
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn avail_offset(&self) -> usize {
+        self.size as usize * size_of::<Descriptor>()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn used_offset(&self) -> usize {
+        // flags, idx, ring and used_event
+        let avail_end = self.avail_offset() + 6 + 2 * self.size as usize;
+        avail_end.next_multiple_of(LEGACY_ALIGN)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Size of the whole queue, in bytes.
+    pub const fn total_size(&self) -> usize {
+        // flags, idx, ring and avail_event
+        self.used_offset() + 6 + 8 * self.size as usize
+    }
+}
+
+/// A split virtqueue.
+///
+/// Chains of buffers are offered to the device with `push`,
+/// and reclaimed with `pop_used` once the device is done with them.
+pub struct VirtQueue {
+    /// Start of the queue, which is the descriptor table.
+    base: NonNull<Descriptor>,
+    layout: QueueLayout,
+    /// Head of the list of free descriptors, linked by their `next` field.
+    free_head: u16,
+    num_free: u16,
+    /// Shadow of the index of the available ring.
+    avail_idx: u16,
+    /// Index of the next entry of the used ring to reclaim.
+    last_used_idx: u16,
+}
+
+// Safety: The memory of the queue is exclusively owned by the queue (and the device).
+unsafe impl Send for VirtQueue {}
+
+impl VirtQueue {
+    #[must_use]
+    /// Creates a queue in the given memory.
+    ///
+    /// # Safety
+    ///
+    /// `base` must point to `layout.total_size()` bytes of zeroed memory, aligned on 16 bytes,
+    /// and only used by this queue and the device for the lifetime of the queue.
+    pub unsafe fn new(base: NonNull<u8>, layout: QueueLayout) -> Self {
+        let queue = Self {
+            base: base.cast(),
+            layout,
+            free_head: 0,
+            num_free: layout.size(),
+            avail_idx: 0,
+            last_used_idx: 0,
+        };
+        for i in 0..layout.size() {
+            queue.write_next(i, i.wrapping_add(1));
+        }
+        queue
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn layout(&self) -> QueueLayout {
+        self.layout
+    }
+
+    #[must_use]
+    #[inline]
+    /// Number of descriptors available for new chains.
+    pub const fn num_free(&self) -> u16 {
+        self.num_free
+    }
+
+    /// Offers a chain of buffers to the device.
+    ///
+    /// Returns the index of the head of the chain, which `pop_used` returns once the device is done,
+    /// or `None` if the chain is empty or there are not enough free descriptors.
+    /// The device must then be notified.
+    pub fn push(&mut self, chain: &[Buffer]) -> Option<u16> {
+        let len = u16::try_from(chain.len()).ok()?;
+        if len == 0 || len > self.num_free {
+            return None;
+        }
+
+        let head = self.free_head;
+        let mut idx = head;
+        for (i, buffer) in chain.iter().enumerate() {
+            let next_free = self.read_desc(idx).next;
+            let last = i + 1 == chain.len();
+
+            let mut flags = 0;
+            if !last {
+                flags |= DESC_F_NEXT;
+            }
+            if buffer.writable {
+                flags |= DESC_F_WRITE;
+            }
+            self.write_desc(
+                idx,
+                Descriptor {
+                    addr: buffer.addr,
+                    len: buffer.len,
+                    flags,
+                    next: if last { 0 } else { next_free },
+                },
+            );
+
+            if last {
+                self.free_head = next_free;
+            } else {
+                idx = next_free;
+            }
+        }
+        self.num_free -= len;
+
+        let slot = self.avail_idx % self.layout.size();
+        // Safety: The slot is in the ring.
+        unsafe {
+            self.avail_ptr()
+                .add(2 + usize::from(slot))
+                .write_volatile(head);
+        }
+        // The entry must be visible before the index is
+        fence(Ordering::Release);
+        self.avail_idx = self.avail_idx.wrapping_add(1);
+        // Safety: The index is the second field of the ring.
+        unsafe { self.avail_ptr().add(1).write_volatile(self.avail_idx) };
+
+        Some(head)
+    }
+
+    #[must_use]
+    /// Returns the next chain the device is done with, without reclaiming it.
+    ///
+    /// Returns the index of the head of the chain and the number of bytes written by the device.
+    pub fn peek_used(&self) -> Option<(u16, u32)> {
+        // Safety: The index is the second field of the ring.
+        let used_idx = unsafe { self.used_ptr().cast::<u16>().add(1).read_volatile() };
+        if used_idx == self.last_used_idx {
+            return None;
+        }
+        // The entry must not be read before the index
+        fence(Ordering::Acquire);
+
+        let slot = usize::from(self.last_used_idx % self.layout.size());
+        // Safety: The slot is in the ring, whose entries are made of two u32 after two u16.
+        let (id, len) = unsafe {
+            let elem = self.used_ptr().byte_add(4).add(2 * slot);
+            (elem.read_volatile(), elem.add(1).read_volatile())
+        };
+
+        let head = u16::try_from(id).ok()?;
+        Some((head, len))
+    }
+
+    /// Reclaims a chain the device is done with.
+    ///
+    /// Returns the index of the head of the chain and the number of bytes written by the device.
+    pub fn pop_used(&mut self) -> Option<(u16, u32)> {
+        let (head, len) = self.peek_used()?;
+        self.last_used_idx = self.last_used_idx.wrapping_add(1);
+        self.free_chain(head);
+        Some((head, len))
+    }
+
+    /// Puts a chain back in the list of free descriptors.
+    fn free_chain(&mut self, head: u16) {
+        let mut idx = head;
+        loop {
+            self.num_free += 1;
+            let desc = self.read_desc(idx);
+            if desc.flags & DESC_F_NEXT == 0 {
+                self.write_next(idx, self.free_head);
+                break;
+            }
+            idx = desc.next;
+        }
+        self.free_head = head;
+    }
+
+    #[must_use]
+    /// Reads a descriptor of the table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of the table.
+    pub fn read_desc(&self, idx: u16) -> Descriptor {
+        let desc = self.desc_ptr(idx);
+        // Safety: The descriptor is in the table.
+        unsafe {
+            Descriptor {
+                addr: (&raw const (*desc).addr).read_volatile(),
+                len: (&raw const (*desc).len).read_volatile(),
+                flags: (&raw const (*desc).flags).read_volatile(),
+                next: (&raw const (*desc).next).read_volatile(),
+            }
+        }
+    }
+
+    fn write_desc(&self, idx: u16, value: Descriptor) {
+        let desc = self.desc_ptr(idx);
+        // Safety: The descriptor is in the table.
+        unsafe {
+            (&raw mut (*desc).addr).write_volatile(value.addr);
+            (&raw mut (*desc).len).write_volatile(value.len);
+            (&raw mut (*desc).flags).write_volatile(value.flags);
+            (&raw mut (*desc).next).write_volatile(value.next);
+        }
+    }
+
+    fn write_next(&self, idx: u16, next: u16) {
+        let desc = self.desc_ptr(idx);
+        // Safety: The descriptor is in the table.
+        unsafe { (&raw mut (*desc).next).write_volatile(next) };
+    }
+
+    fn desc_ptr(&self, idx: u16) -> *mut Descriptor {
+        assert!(idx < self.layout.size(), "Descriptor index out of bounds");
+        // Safety: The table starts at the beginning of the queue.
+        unsafe {
+            self.base
+                .as_ptr()
+                .byte_add(self.layout.desc_offset())
+                .add(usize::from(idx))
+        }
+    }
+
+    const fn avail_ptr(&self) -> *mut u16 {
+        // Safety: The ring is in the queue.
+        unsafe {
+            self.base
+                .as_ptr()
+                .byte_add(self.layout.avail_offset())
+                .cast::<u16>()
+        }
+    }
+
+    /// The used ring is made of `u16` fields followed by pairs of `u32`.
+    const fn used_ptr(&self) -> *mut u32 {
+        // Safety: The ring is in the queue.
+        unsafe {
+            self.base
+                .as_ptr()
+                .byte_add(self.layout.used_offset())
+                .cast::<u32>()
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[repr(C, align(4096))]
+    struct Aligned([u8; 2 * LEGACY_ALIGN]);
+
+    /// Memory of a queue of up to 16 descriptors, shared with a fake device.
+    pub struct Memory(NonNull<Aligned>);
+
+    impl Drop for Memory {
+        fn drop(&mut self) {
+            drop(unsafe { Box::from_raw(self.0.as_ptr()) });
+        }
+    }
+
+    impl Memory {
+        pub fn new() -> Self {
+            let memory = Box::new(Aligned([0; 2 * LEGACY_ALIGN]));
+            Self(NonNull::new(Box::into_raw(memory)).unwrap())
+        }
+
+        pub fn queue(&self, size: u16) -> VirtQueue {
+            let layout = QueueLayout::new(size);
+            assert!(layout.total_size() <= size_of::<Aligned>());
+            unsafe { VirtQueue::new(self.0.cast(), layout) }
+        }
+
+        fn read<T: Copy>(&self, offset: usize) -> T {
+            unsafe { self.0.cast::<u8>().add(offset).cast::<T>().read_volatile() }
+        }
+
+        fn write<T: Copy>(&self, offset: usize, value: T) {
+            unsafe {
+                self.0
+                    .cast::<u8>()
+                    .add(offset)
+                    .cast::<T>()
+                    .write_volatile(value);
+            }
+        }
+
+        /// Completes the chain starting at `head`, as the device would.
+        pub fn complete(&self, layout: QueueLayout, head: u16, len: u32) {
+            let used = layout.used_offset();
+            let idx = self.read::<u16>(used + 2);
+            let slot = used + 4 + 8 * usize::from(idx % layout.size());
+            self.write(slot, u32::from(head));
+            self.write(slot + 4, len);
+            self.write(used + 2, idx.wrapping_add(1));
+        }
+
+        /// Returns the index and the entries of the available ring.
+        pub fn avail(&self, layout: QueueLayout) -> (u16, Vec<u16>) {
+            let avail = layout.avail_offset();
+            let idx = self.read::<u16>(avail + 2);
+            let ring = (0..usize::from(idx.min(layout.size())))
+                .map(|i| self.read(avail + 4 + 2 * i))
+                .collect();
+            (idx, ring)
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        // Sizes of the legacy rings of QEMU
+        let layout = QueueLayout::new(256);
+        assert_eq!(layout.avail_offset(), 4096);
+        assert_eq!(layout.used_offset(), 8192);
+        assert_eq!(layout.total_size(), 10246);
+
+        let layout = QueueLayout::new(8);
+        assert_eq!(layout.avail_offset(), 128);
+        assert_eq!(layout.used_offset(), 4096);
+        assert_eq!(layout.total_size(), 4096 + 6 + 64);
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let memory = Memory::new();
+        let mut queue = memory.queue(4);
+        let layout = queue.layout();
+
+        assert_eq!(queue.push(&[]), None);
+        let first = queue
+            .push(&[Buffer::readable(0x1000, 16), Buffer::writable(0x2000, 1)])
+            .unwrap();
+        assert_eq!(first, 0);
+        assert_eq!(queue.num_free(), 2);
+        assert_eq!(
+            queue.read_desc(0),
+            Descriptor {
+                addr: 0x1000,
+                len: 16,
+                flags: DESC_F_NEXT,
+                next: 1
+            }
+        );
+        assert_eq!(queue.read_desc(1).flags, DESC_F_WRITE);
+
+        let second = queue.push(&[Buffer::writable(0x3000, 8)]).unwrap();
+        assert_eq!(second, 2);
+        assert_eq!(memory.avail(layout), (2, vec![0, 2]));
+        // Not enough descriptors left
+        assert_eq!(
+            queue.push(&[Buffer::readable(0, 1), Buffer::readable(0, 1)]),
+            None
+        );
+
+        assert_eq!(queue.pop_used(), None);
+        memory.complete(layout, first, 1);
+        assert_eq!(queue.peek_used(), Some((first, 1)));
+        assert_eq!(queue.num_free(), 1);
+        assert_eq!(queue.pop_used(), Some((first, 1)));
+        assert_eq!(queue.num_free(), 3);
+        assert_eq!(queue.pop_used(), None);
+
+        // The freed descriptors are reused first
+        let third = queue
+            .push(&[
+                Buffer::readable(0, 1),
+                Buffer::readable(0, 1),
+                Buffer::readable(0, 1),
+            ])
+            .unwrap();
+        assert_eq!(third, 0);
+        assert_eq!(queue.read_desc(1).next, 3);
+        assert_eq!(queue.num_free(), 0);
+
+        memory.complete(layout, second, 8);
+        memory.complete(layout, third, 0);
+        assert_eq!(queue.pop_used(), Some((second, 8)));
+        assert_eq!(queue.pop_used(), Some((third, 0)));
+        assert_eq!(queue.num_free(), 4);
+    }
+}
@@ -12,11 +12,14 @@ use beskar_core::arch::{
     Alignment, PhysAddr,
     paging::{CacheFlush as _, Frame, M4KiB, Mapper as _, MemSize as _, Page},
 };
+use beskar_core::syscall::ExitCode;
 use beskar_hal::{
+    apic::{APIC_BASE_ENABLE, APIC_BASE_MSR, APIC_BASE_X2APIC, LapicReg},
     paging::page_table::Flags,
     port::{self, Port},
-    registers::Msr,
+    registers::{DynMsr, Msr},
     structures::InterruptStackFrame,
+    userspace::Ring,
 };
 use core::{
     ptr::NonNull,
@@ -33,7 +36,12 @@ pub mod ipi;
 pub mod timer;
 
 /// Amount of milliseconds per LAPIC timer interrupt
-pub const MS_PER_INTERRUPT: u32 = 30;
+///
+/// This is the granularity of the scheduler quanta, which are a whole number of ticks:
+/// the 10ms quantum of `Priority::Realtime` is a single tick.
+/// Sleeping threads are also woken up on ticks, so a sleep ends up to a tick late.
+/// A shorter period would make both finer, at the cost of more interrupts on every core.
+pub const MS_PER_INTERRUPT: u32 = 10;
 
 /// LAPIC timer divider
 const TIMER_DIVIDER: timer::Divider = timer::Divider::Eight;
@@ -45,20 +53,36 @@ const TIMER_DIVIDER: timer::Divider = timer::Divider::Eight;
 /// thread migrates between cores between obtaining the pointer and using it.
 static LAPIC_MMIO_BASE: Once<Page> = Once::uninit();
 
+/// Whether the Local APICs are driven in x2APIC mode.
+///
+/// This is decided by the BSP and followed by every AP.
+static X2APIC_MODE: Once<bool> = Once::uninit();
+
 #[must_use]
-pub fn apic_id() -> u8 {
-    let cpuid_res = cpuid::cpuid(cpuid::Leaf::new(1));
-    u8::try_from((cpuid_res.ebx >> 24) & 0xFF).unwrap()
+/// Returns the APIC ID of the current core.
+///
+/// If available, the 32-bit x2APIC ID is returned.
+pub fn apic_id() -> u32 {
+    cpuid::x2apic_id().unwrap_or_else(|| {
+        let cpuid_res = cpuid::cpuid(cpuid::Leaf::new(1));
+        (cpuid_res.ebx >> 24) & 0xFF
+    })
 }
 
 /// Initializes the Local APIC.
 ///
 /// This function must be called on each core.
 pub fn init_lapic() {
-    let x2apic_supported = cpuid::check_feature(cpuid::CpuFeature::X2APIC);
-    if locals!().core_id() == 0 && !x2apic_supported {
-        video::warn!("x2APIC not supported");
-    }
+    X2APIC_MODE.call_once(|| {
+        let supported = cpuid::check_feature(cpuid::CpuFeature::X2APIC);
+        if supported {
+            video::debug!("Using x2APIC");
+        } else {
+            video::warn!("x2APIC not supported");
+        }
+        supported
+    });
+    let x2apic = *X2APIC_MODE.get().unwrap();
 
     let lapic_paddr = ACPI
         .get()
@@ -68,15 +92,11 @@ pub fn init_lapic() {
 
     ensure_pic_disabled();
 
-    // TODO: ?
-    // if x2apic_supported {
-    //     unsafe {
-    //         let mut msr = x86_64::registers::model_specific::Msr::new(0x1B);
-    //         msr.write(lapic_paddr.as_u64());
-    //     }
-    // }
-
-    let mut lapic = LocalApic::from_paddr(lapic_paddr);
+    let mut lapic = if x2apic {
+        LocalApic::new_x2apic(lapic_paddr)
+    } else {
+        LocalApic::from_paddr(lapic_paddr)
+    };
 
     let timer = lapic.timer();
     timer.calibrate();
@@ -111,7 +131,7 @@ pub fn init_ioapic() {
 fn enable_disable_interrupts(enable: bool) {
     locals!().lapic().with_locked_if_init(|lapic| {
         unsafe {
-            lapic.base.byte_add(0xF0).update(|value| {
+            lapic.regs.update(LapicReg::SpuriousVector, |value| {
                 if enable {
                     // Enable spurious interrupt
                     value | 0x100
@@ -124,24 +144,141 @@ fn enable_disable_interrupts(enable: bool) {
     });
 }
 
+#[derive(Debug, Clone, Copy)]
+/// Access method to the Local APIC registers.
+pub enum LapicRegisters {
+    /// Registers are memory-mapped.
+    XApic(MmioRegister<ReadWrite, u32>),
+    /// Registers are MSRs.
+    X2Apic,
+}
+
+impl LapicRegisters {
+    #[must_use]
+    #[inline]
+    /// Reads a register.
+    ///
+    /// # Safety
+    ///
+    /// The register must be readable.
+    pub unsafe fn read(self, reg: LapicReg) -> u32 {
+        match self {
+            Self::XApic(base) => unsafe { base.byte_add(reg.mmio_offset()).read() },
+            Self::X2Apic => {
+                let msr = reg
+                    .x2apic_msr()
+                    .expect("Register unavailable in x2APIC mode");
+                // Registers are 32-bit wide, except for the ICR.
+                #[expect(clippy::cast_possible_truncation, reason = "Upper half is reserved")]
+                let value = unsafe { DynMsr::new(msr) }.read() as u32;
+                value
+            }
+        }
+    }
+
+    #[inline]
+    /// Writes a register.
+    ///
+    /// # Safety
+    ///
+    /// The register must be writable and the value must be valid for it.
+    pub unsafe fn write(self, reg: LapicReg, value: u32) {
+        match self {
+            Self::XApic(base) => unsafe { base.byte_add(reg.mmio_offset()).write(value) },
+            Self::X2Apic => {
+                let msr = reg
+                    .x2apic_msr()
+                    .expect("Register unavailable in x2APIC mode");
+                unsafe { DynMsr::new(msr).write(u64::from(value)) };
+            }
+        }
+    }
+
+    #[inline]
+    /// Updates a register.
+    ///
+    /// # Safety
+    ///
+    /// The register must be readable and writable and the new value must be valid for it.
+    pub unsafe fn update<F: FnOnce(u32) -> u32>(self, reg: LapicReg, f: F) {
+        unsafe { self.write(reg, f(self.read(reg))) };
+    }
+
+    /// Writes the Interrupt Command Register, sending an IPI.
+    ///
+    /// # Safety
+    ///
+    /// The command must be valid.
+    unsafe fn write_icr(self, low: u32, destination: u32) {
+        match self {
+            Self::XApic(_) => {
+                // Wait for the previous IPI to be delivered
+                while unsafe { self.read(LapicReg::IcrLow) >> 12 } & 1 == 1 {
+                    core::hint::spin_loop();
+                }
+                let destination = u8::try_from(destination)
+                    .expect("APIC ID too large to be addressed in xAPIC mode");
+                unsafe { self.write(LapicReg::IcrHigh, u32::from(destination) << 24) };
+                unsafe { self.write(LapicReg::IcrLow, low) };
+            }
+            Self::X2Apic => {
+                // In x2APIC mode, the ICR is a single 64-bit register
+                // and there is no delivery status to wait for.
+                let msr = LapicReg::IcrLow.x2apic_msr().unwrap();
+                let value = (u64::from(destination) << 32) | u64::from(low);
+                unsafe { DynMsr::new(msr).write(value) };
+            }
+        }
+    }
+}
+
 pub struct LocalApic {
-    base: MmioRegister<ReadWrite, u32>,
+    regs: LapicRegisters,
     timer: LapicTimer,
     paddr: PhysAddr,
-    acpi_id: u8,
+    acpi_id: u32,
 }
 
 impl LocalApic {
     #[must_use]
     fn get_paddr_from_msr() -> PhysAddr {
-        let msr = Msr::<0x1B>;
+        let msr = Msr::<APIC_BASE_MSR>;
         let base = msr.read();
 
-        assert!((base >> 11) & 1 == 1, "APIC not enabled");
+        assert!(base & APIC_BASE_ENABLE != 0, "APIC not enabled");
 
         PhysAddr::new_truncate(base & 0xF_FFFF_F000)
     }
 
+    #[must_use]
+    /// Returns the ACPI ID of the current core.
+    fn current_acpi_id() -> u32 {
+        ACPI.get()
+            .map(|acpi| {
+                let apic_id = locals!().apic_id();
+                acpi.madt()
+                    .lapics()
+                    .iter()
+                    .find(|candidate| candidate.id() == apic_id)
+                    .map(acpi::sdt::madt::ParsedLapic::acpi_id)
+                    .expect("APIC ACPI ID not found")
+            })
+            .unwrap()
+    }
+
+    #[must_use]
+    /// Switches the current core's Local APIC to x2APIC mode.
+    ///
+    /// The physical address is kept for MSI messages, which target the same range.
+    pub fn new_x2apic(paddr: PhysAddr) -> Self {
+        let msr = Msr::<APIC_BASE_MSR>;
+        // Going from xAPIC to x2APIC mode can be done with a single write,
+        // as long as the APIC is enabled.
+        unsafe { msr.write(msr.read() | APIC_BASE_ENABLE | APIC_BASE_X2APIC) };
+
+        Self::with_registers(LapicRegisters::X2Apic, paddr)
+    }
+
     #[must_use]
     pub fn from_paddr(paddr: PhysAddr) -> Self {
         let frame = Frame::<M4KiB>::containing_address(paddr);
@@ -166,28 +303,23 @@ impl LocalApic {
         });
         let page = *LAPIC_MMIO_BASE.get().unwrap();
 
-        let acpi_id = ACPI
-            .get()
-            .map(|acpi| {
-                let apic_id = locals!().apic_id();
-                acpi.madt()
-                    .lapics()
-                    .iter()
-                    .find(|candidate| candidate.id() == apic_id)
-                    .map(acpi::sdt::madt::ParsedLapic::acpi_id)
-                    .expect("APIC ACPI ID not found")
-            })
-            .unwrap();
+        let base = MmioRegister::new(NonNull::new(page.start_address().as_mut_ptr()).unwrap());
+
+        Self::with_registers(LapicRegisters::XApic(base), paddr)
+    }
+
+    #[must_use]
+    /// Finishes the Local APIC setup, once its registers are accessible.
+    fn with_registers(regs: LapicRegisters, paddr: PhysAddr) -> Self {
+        let acpi_id = Self::current_acpi_id();
 
         // Handle NMI sources
-        let apic_lint0 = unsafe { &mut *page.start_address().as_mut_ptr::<u32>().byte_add(0x350) };
-        let apic_lint1 = unsafe { &mut *page.start_address().as_mut_ptr::<u32>().byte_add(0x360) };
         if let Some(acpi) = ACPI.get() {
             let nmis = acpi
                 .madt()
                 .local_nmis()
                 .iter()
-                .filter(|nmi| nmi.acpi_id() == 0xFF || nmi.acpi_id() == acpi_id);
+                .filter(|nmi| nmi.targets(acpi_id));
 
             for nmi in nmis {
                 let triggermode: u32 = match nmi.flags().trigger_mode() {
@@ -211,43 +343,39 @@ impl LocalApic {
                 value |= 0b100 << 8; // NMI delivery mode
                 value |= u32::from(irq);
 
-                match nmi.lint() {
-                    Lint::Lint0 => {
-                        *apic_lint0 = value;
-                    }
-                    Lint::Lint1 => {
-                        *apic_lint1 = value;
-                    }
-                }
+                let reg = match nmi.lint() {
+                    Lint::Lint0 => LapicReg::LvtLint0,
+                    Lint::Lint1 => LapicReg::LvtLint1,
+                };
+                unsafe { regs.write(reg, value) };
             }
         }
 
         // Register spurious interrupt handler
-        let base_ptr: *mut u32 = page.start_address().as_mut_ptr();
-        let apic_spurious = unsafe { &mut *base_ptr.byte_add(0xF0) };
-        *apic_spurious &= !0xFF; // Clear spurious handler index
-        *apic_spurious |= u32::from(0xFF_u8); // Set spurious handler index
-        *apic_spurious |= 0x100; // Enable spurious interrupt
-
-        let base = MmioRegister::new(NonNull::new(base_ptr).unwrap());
+        unsafe {
+            regs.update(LapicReg::SpuriousVector, |value| {
+                let value = value & !0xFF; // Clear spurious handler index
+                value | u32::from(0xFF_u8) // Set spurious handler index
+                    | 0x100 // Enable spurious interrupt
+            });
+        };
 
         let (irq, _) =
             super::interrupts::new_irq(timer_interrupt_handler, Some(locals!().core_id()));
 
         Self {
-            base,
-            timer: timer::LapicTimer::new(timer::Configuration::new(base, irq)),
+            regs,
+            timer: timer::LapicTimer::new(timer::Configuration::new(regs, irq)),
             paddr,
             acpi_id,
         }
     }
 
     pub fn send_ipi(&self, ipi: &ipi::Ipi) {
-        let icr_low = unsafe { self.base.byte_add(0x300).as_volatile() };
-        let icr_high = unsafe { self.base.byte_add(0x310).lower_access().as_volatile() };
+        let (low, destination) = ipi.to_raw();
         // Safety:
-        // The ICR registers are read/write and their addresses are valid.
-        unsafe { ipi.send(icr_low, icr_high) };
+        // The IPI is well-formed.
+        unsafe { self.regs.write_icr(low, destination) };
     }
 
     #[must_use]
@@ -266,13 +394,13 @@ impl LocalApic {
     /// It is safe to call this function in threaded environments, even if the LAPIC is
     /// currently locked.
     pub fn send_eoi(&mut self) {
-        unsafe { self.base.byte_add(0xB0).write(0) };
+        unsafe { self.regs.write(LapicReg::Eoi, 0) };
     }
 
     #[must_use]
     #[inline]
-    pub const fn base(&self) -> MmioRegister<ReadWrite, u32> {
-        self.base
+    pub const fn registers(&self) -> LapicRegisters {
+        self.regs
     }
 
     #[must_use]
@@ -283,21 +411,48 @@ impl LocalApic {
 
     #[must_use]
     #[inline]
-    pub const fn acpi_id(&self) -> u8 {
+    pub const fn acpi_id(&self) -> u32 {
         self.acpi_id
     }
 }
 
 extern "x86-interrupt" fn local_nmi_handler(_stack_frame: InterruptStackFrame) {
-    video::info!("Local NMI on core {}", locals!().core_id());
-    unsafe { locals!().lapic().force_lock() }.send_eoi();
+    super::interrupts::in_handler(|| {
+        video::info!("Local NMI on core {}", locals!().core_id());
+        unsafe { locals!().lapic().force_lock() }.send_eoi();
+    });
 }
 
-extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    let rescheduling_result = crate::process::scheduler::scheduler_tick();
+extern "x86-interrupt" fn timer_interrupt_handler(stack_frame: InterruptStackFrame) {
+    // Rescheduling may switch to another thread, so it runs outside of interrupt context.
+    if locals!().core_id() == 0 {
+        super::interrupts::in_handler(|| {
+            crate::drivers::keyboard::with_keyboard_manager(
+                crate::drivers::keyboard::KeyboardManager::tick,
+            );
+            crate::process::timer::tick();
+        });
+    }
 
     unsafe { locals!().lapic().force_lock() }.send_eoi();
 
+    // The idle threads never run while a thread keeps the core busy,
+    // so deferred work also runs here.
+    let from_user = stack_frame.code_segment() & 0b11 == u16::from(Ring::User.as_u8());
+    if from_user {
+        let _ = crate::softirq::run_on_tick();
+    }
+
+    // A thread running in userspace may never make a syscall,
+    // so it is also stopped here when its process exits.
+    // This comes after the deferred work, which may have raised a terminating signal.
+    if from_user && process::current().is_exiting() {
+        // Safety: The thread was interrupted in userspace, so it holds no kernel lock.
+        unsafe { crate::process::scheduler::exit_current_thread(ExitCode::Failure) };
+    }
+
+    let rescheduling_result = crate::process::scheduler::scheduler_tick();
+
     if let Some(context_switch) = rescheduling_result {
         // Safety:
         // If rescheduling happened, interrupts were disabled.
@@ -562,13 +717,17 @@ impl IoApic {
 }
 
 extern "x86-interrupt" fn io_iso_handler(_stack_frame: InterruptStackFrame) {
-    video::info!("IO ISO on core {}", locals!().core_id());
-    unsafe { locals!().lapic().force_lock() }.send_eoi();
+    super::interrupts::in_handler(|| {
+        video::info!("IO ISO on core {}", locals!().core_id());
+        unsafe { locals!().lapic().force_lock() }.send_eoi();
+    });
 }
 
 extern "x86-interrupt" fn ps2_keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    crate::drivers::ps2::handle_keyboard_interrupt();
-    unsafe { locals!().lapic().force_lock() }.send_eoi();
+    super::interrupts::in_handler(|| {
+        crate::drivers::ps2::handle_keyboard_interrupt();
+        unsafe { locals!().lapic().force_lock() }.send_eoi();
+    });
 }
 
 // Safe register access
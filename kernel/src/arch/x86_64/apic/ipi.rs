@@ -1,7 +1,5 @@
 //! Inter-Processor Interrupts (IPIs)
 
-use hyperdrive::ptrs::volatile::{ReadWrite, Volatile, WriteOnly};
-
 /// Represents the delivery mode of an IPI.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DeliveryMode {
@@ -28,8 +26,8 @@ pub enum Destination {
     All,
     /// The IPI is sent to all processors except the current one.
     AllExcludingSelf,
-    /// The IPI is sent to the specified processor.
-    One(u8),
+    /// The IPI is sent to the processor with the specified APIC ID.
+    One(u32),
     /// The IPI is sent to the current processor.
     OneSelf,
 }
@@ -40,12 +38,6 @@ pub struct Ipi {
     destination: Destination,
 }
 
-/// Represents the raw format of an IPI.
-struct RawIpi {
-    low: u32,
-    high: u32,
-}
-
 impl Ipi {
     #[must_use]
     #[inline]
@@ -59,9 +51,11 @@ impl Ipi {
 
     #[must_use]
     /// Converts the IPI to its raw format.
-    fn to_raw(&self) -> RawIpi {
+    ///
+    /// Returns the low half of the ICR and the destination APIC ID.
+    pub(super) fn to_raw(&self) -> (u32, u32) {
         let mut low = 0;
-        let mut high = 0;
+        let mut destination_id = 0;
 
         low |= 1 << 14; // IPI assert bit
 
@@ -88,36 +82,13 @@ impl Ipi {
             Destination::All => 0b10,
             Destination::AllExcludingSelf => 0b11,
             Destination::One(cpu) => {
-                high |= u32::from(cpu) << 24;
+                destination_id = cpu;
                 0b00
             }
             Destination::OneSelf => 0b01,
         };
         low |= destination << 18;
 
-        RawIpi { low, high }
-    }
-
-    /// Sends the IPI using the specified ICR registers.
-    ///
-    /// # Safety
-    ///
-    /// ICR pointers must be valid.
-    pub(super) unsafe fn send(
-        &self,
-        icr_low: Volatile<ReadWrite, u32>,
-        icr_high: Volatile<WriteOnly, u32>,
-    ) {
-        // Assert Interrupts are ready to be received
-        while unsafe { icr_low.read() >> 12 } & 1 == 1 {
-            core::hint::spin_loop();
-        }
-
-        let RawIpi { low, high } = self.to_raw();
-
-        if let Destination::One(_) = self.destination {
-            unsafe { icr_high.write(high) };
-        }
-        unsafe { icr_low.write(low) };
+        (low, destination_id)
     }
 }
@@ -2,14 +2,9 @@
 //! Local APIC Timers must be a separate object
 //! instead of being a method of the Local APIC.
 
+use super::LapicRegisters;
+use beskar_hal::apic::LapicReg;
 use core::num::NonZeroU32;
-use driver_shared::mmio::MmioRegister;
-use hyperdrive::ptrs::volatile::{ReadWrite, WriteOnly};
-
-const TIMER_DIVIDE_CONFIG_REG: usize = 0x3E0;
-const TIMER_INIT_COUNT_REG: usize = 0x380;
-const TIMER_CURR_COUNT_REG: usize = 0x390;
-const TIMER_VECTOR_TABLE_REG: usize = 0x320;
 
 const MASK_IRQ_DISABLE: u32 = 1 << 16;
 const MASK_IRQ: u32 = 0xFF;
@@ -26,36 +21,12 @@ impl LapicTimer {
         Self { configuration }
     }
 
-    #[must_use]
-    pub const fn divider_config_reg(&mut self) -> MmioRegister<ReadWrite, u32> {
-        unsafe {
-            self.configuration
-                .apic_base
-                .byte_add(TIMER_DIVIDE_CONFIG_REG)
-        }
-    }
-
-    #[must_use]
-    pub const fn init_count_reg(&mut self) -> MmioRegister<WriteOnly, u32> {
-        unsafe { self.configuration.apic_base.byte_add(TIMER_INIT_COUNT_REG) }.lower_access()
-    }
-
     #[must_use]
     pub fn read_curr_count_reg(&mut self) -> u32 {
         unsafe {
             self.configuration
-                .apic_base
-                .byte_add(TIMER_CURR_COUNT_REG)
-                .read()
-        }
-    }
-
-    #[must_use]
-    pub const fn vector_table_reg(&mut self) -> MmioRegister<ReadWrite, u32> {
-        unsafe {
-            self.configuration
-                .apic_base
-                .byte_add(TIMER_VECTOR_TABLE_REG)
+                .apic_regs
+                .read(LapicReg::TimerCurrentCount)
         }
     }
 
@@ -144,32 +115,33 @@ impl LapicTimer {
         self.write_config();
     }
 
-    fn write_config(&mut self) {
+    fn write_config(&self) {
         match self.configuration.mode {
             Mode::Inactive => {
-                let apic_timer_vte = self.vector_table_reg();
-                let old_vte = unsafe { apic_timer_vte.read() };
+                let regs = self.configuration.apic_regs;
                 // Keep IRQ set but disable it
-                let new_vte = old_vte | MASK_IRQ_DISABLE;
-                unsafe { apic_timer_vte.write(new_vte) };
+                unsafe { regs.update(LapicReg::LvtTimer, |vte| vte | MASK_IRQ_DISABLE) };
 
-                unsafe { self.init_count_reg().write(0) };
+                unsafe { regs.write(LapicReg::TimerInitialCount, 0) };
             }
             Mode::OneShot(config) | Mode::Periodic(config) => {
-                let apic_timer_divide = self.divider_config_reg();
-                let old_divide = unsafe { apic_timer_divide.read() };
-                let new_divide = (old_divide & !0xF) | config.divider as u32;
-                unsafe { apic_timer_divide.write(new_divide) };
+                let regs = self.configuration.apic_regs;
+                unsafe {
+                    regs.update(LapicReg::TimerDivideConfig, |divide| {
+                        (divide & !0xF) | config.divider as u32
+                    });
+                };
 
-                let apic_timer_vt = self.vector_table_reg();
-                let old_vte = unsafe { apic_timer_vt.read() };
                 // Write IRQ and mode bits
-                let new_vte = (old_vte & !(MASK_IRQ | MASK_IRQ_DISABLE | MODE_MASK))
-                    | u32::from(self.configuration.ivt)
+                let vte_bits = u32::from(self.configuration.ivt)
                     | (self.configuration.mode.as_vte_bits() << 17);
-                unsafe { apic_timer_vt.write(new_vte) };
+                unsafe {
+                    regs.update(LapicReg::LvtTimer, |vte| {
+                        (vte & !(MASK_IRQ | MASK_IRQ_DISABLE | MODE_MASK)) | vte_bits
+                    });
+                };
 
-                unsafe { self.init_count_reg().write(config.duration) };
+                unsafe { regs.write(LapicReg::TimerInitialCount, config.duration) };
             }
             Mode::TscDeadline => {
                 unimplemented!("TSC_DEADLINE is not supported");
@@ -184,7 +156,7 @@ impl LapicTimer {
 }
 
 pub struct Configuration {
-    apic_base: MmioRegister<ReadWrite, u32>,
+    apic_regs: LapicRegisters,
     rate_mhz: u32,
     ivt: u8,
     mode: Mode,
@@ -192,9 +164,9 @@ pub struct Configuration {
 
 impl Configuration {
     #[must_use]
-    pub const fn new(apic_base: MmioRegister<ReadWrite, u32>, ivt: u8) -> Self {
+    pub const fn new(apic_regs: LapicRegisters, ivt: u8) -> Self {
         Self {
-            apic_base,
+            apic_regs,
             rate_mhz: 0,
             ivt,
             mode: Mode::Inactive,
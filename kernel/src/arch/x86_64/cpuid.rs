@@ -1,4 +1,8 @@
-use beskar_hal::registers::Rflags;
+use beskar_hal::{
+    registers::Rflags,
+    topology::{ApicIdLayout, TopologyLevel},
+    xsave::{Component, ComponentLayout, XSAVE_LEAF, XsaveError, XsaveLayout},
+};
 pub use core::arch::x86_64::CpuidResult;
 use core::sync::atomic::{AtomicU32, Ordering};
 
@@ -67,6 +71,25 @@ pub fn cpuid(leaf: Leaf) -> CpuidResult {
     core::arch::x86_64::__cpuid(leaf.as_u32())
 }
 
+#[must_use]
+#[inline]
+/// Stabilized version of the `__cpuid_count` intrinsic
+///
+/// # Panics
+///
+/// Panics if the CPUID leaf is not supported.
+pub fn cpuid_count(leaf: Leaf, subleaf: u32) -> CpuidResult {
+    assert!(
+        if leaf.is_extended() {
+            leaf <= get_highest_supported_xleaf()
+        } else {
+            leaf <= get_highest_supported_leaf()
+        },
+        "CPUID leaf is not supported"
+    );
+    core::arch::x86_64::__cpuid_count(leaf.as_u32(), subleaf)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code, reason = "Unused CPU features registers may be used later")]
 /// Every meaningful CPUID register
@@ -131,12 +154,24 @@ impl CpuFeature {
         bit: 5,
         name: "MSR",
     };
+    pub const MCE: Self = Self {
+        leaf: Leaf::new(1),
+        reg: CpuidReg::Edx,
+        bit: 7,
+        name: "MCE",
+    };
     pub const APIC_ONBOARD: Self = Self {
         leaf: Leaf::new(1),
         reg: CpuidReg::Edx,
         bit: 9,
         name: "APIC",
     };
+    pub const MCA: Self = Self {
+        leaf: Leaf::new(1),
+        reg: CpuidReg::Edx,
+        bit: 14,
+        name: "MCA",
+    };
     pub const PAT: Self = Self {
         leaf: Leaf::new(1),
         reg: CpuidReg::Edx,
@@ -228,6 +263,15 @@ impl CpuFeature {
         bit: 17,
         name: "TCE",
     };
+
+    // XLEAF 7
+
+    pub const INVARIANT_TSC: Self = Self {
+        leaf: Leaf::new(0x8000_0007),
+        reg: CpuidReg::Edx,
+        bit: 8,
+        name: "Invariant TSC",
+    };
 }
 
 /// List of required features for the kernel to run
@@ -361,3 +405,46 @@ pub fn get_highest_supported_leaf() -> Leaf {
 pub fn get_highest_supported_xleaf() -> Leaf {
     Leaf::new(EXTENDED_MAX_LEAF.load(Ordering::Acquire))
 }
+
+#[must_use]
+/// Returns the extended topology leaf to use, preferring V2 (`0x1F`) over `0xB`.
+fn topology_leaf() -> Option<Leaf> {
+    [Leaf::new(0x1F), Leaf::new(0xB)].into_iter().find(|&leaf| {
+        // A leaf is valid if it is supported and its first sub-leaf reports processors.
+        leaf <= get_highest_supported_leaf() && cpuid_count(leaf, 0).ebx != 0
+    })
+}
+
+#[must_use]
+/// Decodes the layout of the APIC ID fields from the extended topology leaves.
+///
+/// Falls back to a flat layout if the leaves are not supported.
+pub fn apic_id_layout() -> ApicIdLayout {
+    topology_leaf().map_or(ApicIdLayout::FLAT, |leaf| {
+        ApicIdLayout::from_levels((0..).map_while(|subleaf| {
+            let res = cpuid_count(leaf, subleaf);
+            TopologyLevel::from_registers(res.eax, res.ebx, res.ecx, res.edx)
+        }))
+    })
+}
+
+#[must_use]
+/// Returns the x2APIC ID of the current processor, if reported by CPUID.
+pub fn x2apic_id() -> Option<u32> {
+    topology_leaf().map(|leaf| cpuid_count(leaf, 0).edx)
+}
+
+/// Decodes the layout of the XSAVE area for the components currently enabled in `XCR0`.
+///
+/// # Errors
+///
+/// Returns an error if the sizes reported by the processor are inconsistent.
+pub fn xsave_layout() -> Result<XsaveLayout, XsaveError> {
+    let leaf = Leaf::new(XSAVE_LEAF);
+    let main = cpuid_count(leaf, 0);
+    let avx = cpuid_count(leaf, Component::Avx.index());
+    XsaveLayout::new(
+        [main.eax, main.ebx, main.ecx, main.edx],
+        ComponentLayout::from_registers(avx.eax, avx.ebx),
+    )
+}
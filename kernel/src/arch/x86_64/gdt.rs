@@ -11,6 +11,10 @@ use beskar_hal::{
 };
 use core::mem::MaybeUninit;
 
+/// Interrupt stack of the double fault handler.
+///
+/// Each core has its own, surrounded by guard pages, so that a double fault
+/// caused by a kernel stack overflow still has a valid stack.
 pub const DOUBLE_FAULT_IST: u8 = 0;
 pub const PAGE_FAULT_IST: u8 = 1;
 
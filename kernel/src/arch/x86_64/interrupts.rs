@@ -1,16 +1,21 @@
 use super::gdt::{DOUBLE_FAULT_IST, PAGE_FAULT_IST};
 use crate::locals;
-use beskar_core::arch::VirtAddr;
+use beskar_core::arch::{
+    VirtAddr,
+    irq::{FIRST_IRQ_VECTOR, InterruptNesting, SPURIOUS_VECTOR, UnhandledIrqs},
+    paging::{M4KiB, MemSize as _},
+};
+use beskar_core::syscall::ExitCode;
 use beskar_hal::{
+    gp,
     instructions::int_enable,
-    registers::{CS, Cr0, Cr2},
+    mce,
+    registers::{CS, Cr0, Cr2, GS},
     structures::{GateType, InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode},
     userspace::Ring,
 };
-use core::{
-    cell::UnsafeCell,
-    sync::atomic::{AtomicU8, Ordering},
-};
+use core::cell::UnsafeCell;
+use hyperdrive::locks::mcs::McsLock;
 
 pub fn init() {
     let interrupts = locals!().interrupts();
@@ -70,9 +75,11 @@ pub fn init() {
         idt.page_fault.set_stack_index(PAGE_FAULT_IST);
     }
 
-    idt.irq(0xFF)
-        .unwrap()
-        .set_handler_fn(spurious_interrupt_handler, cs);
+    for vector in FIRST_IRQ_VECTOR..=SPURIOUS_VECTOR {
+        idt.irq(vector)
+            .unwrap()
+            .set_handler_fn(UNHANDLED_IRQ_HANDLERS[usize::from(vector)], cs);
+    }
 
     idt.load();
 
@@ -82,6 +89,7 @@ pub fn init() {
 #[derive(Debug)]
 pub struct Interrupts {
     idt: UnsafeCell<InterruptDescriptorTable>,
+    nesting: InterruptNesting,
 }
 
 impl Default for Interrupts {
@@ -95,35 +103,160 @@ impl Interrupts {
     pub const fn new() -> Self {
         Self {
             idt: UnsafeCell::new(InterruptDescriptorTable::new()),
+            nesting: InterruptNesting::new(),
         }
     }
 }
 
+#[inline]
+/// Runs the body of an interrupt handler, in interrupt context.
+///
+/// It must not switch to another thread, which would then run in interrupt context.
+///
+/// The timing of the interrupt feeds the random number generator.
+pub fn in_handler<R>(f: impl FnOnce() -> R) -> R {
+    let _scope = locals!().interrupts().nesting.enter();
+    crate::rand::add_interrupt_entropy();
+    f()
+}
+
+#[must_use]
+#[inline]
+/// Returns the interrupt nesting of the current core, once its locals are set up.
+///
+/// Interrupt handlers only run after that, but the heap is used before.
+pub fn local_nesting() -> Option<&'static InterruptNesting> {
+    (GS::read_base().as_u64() != 0).then(|| &locals!().interrupts().nesting)
+}
+
+#[macro_export]
+/// Asserts that the current core is not running an interrupt handler, in debug builds only.
+///
+/// Code that allocates or waits for a blocking lock must not run in interrupt context.
+macro_rules! debug_assert_not_in_interrupt {
+    () => {
+        if cfg!(debug_assertions)
+            && let Some(nesting) = $crate::arch::interrupts::local_nesting()
+        {
+            nesting.debug_assert_outside();
+        }
+    };
+}
+
+/// Logs the double fault, then halts the core.
+///
+/// It runs on its own stack, so that a kernel stack overflow ends up here
+/// instead of in a triple fault. A double fault cannot be recovered from,
+/// and panicking would involve the scheduler, whose state is unknown.
 extern "x86-interrupt" fn double_fault_handler(
     stack_frame: InterruptStackFrame,
     error_code: u64,
 ) -> ! {
-    panic!(
-        "EXCEPTION: DOUBLE FAULT {:#x}\n{:#?}",
-        error_code, stack_frame
+    let core_id = locals!().core_id();
+
+    // A stack pointer right above a guard page hints at a stack overflow.
+    video::error!(
+        "EXCEPTION: DOUBLE FAULT {:#x} on core {} at {:#x} (stack pointer {:#x})\n{:#?}",
+        error_code,
+        core_id,
+        stack_frame.instruction_pointer().as_u64(),
+        stack_frame.stack_pointer().as_u64(),
+        stack_frame
     );
+
+    video::error!("Halting core {}", core_id);
+    loop {
+        beskar_hal::instructions::int_disable();
+        crate::arch::halt();
+    }
 }
 
 extern "x86-interrupt" fn page_fault_handler(
-    _stack_frame: InterruptStackFrame,
+    stack_frame: InterruptStackFrame,
     error_code: PageFaultErrorCode,
 ) {
+    use beskar_core::mem::fault::Resolution;
+
     let faulting_address = Cr2::read();
-    let thread_id = crate::process::scheduler::current_thread_id();
+    let fault =
+        crate::mem::fault::decode(faulting_address, error_code, stack_frame.stack_pointer());
+
+    let process = crate::process::scheduler::current_process();
+    match crate::mem::fault::handle(process.address_space(), &fault) {
+        Resolution::Retry => {}
+        Resolution::Kill(reason) => {
+            video::error!(
+                "Process {} killed: {} at {:#x} ({:?})",
+                process.name(),
+                reason.as_str(),
+                faulting_address.as_u64(),
+                fault.access
+            );
+            drop(process);
+            // Safety: the faulting thread cannot go on.
+            unsafe { crate::process::scheduler::exit_current_thread(ExitCode::Failure) };
+        }
+        _ => {
+            let thread_id = crate::process::scheduler::current_thread_id();
+            video::error!(
+                "EXCEPTION: PAGE FAULT ({:b}) at {:#x} in Thread {}",
+                error_code,
+                faulting_address.as_u64(),
+                thread_id.as_u64()
+            );
 
-    video::error!(
-        "EXCEPTION: PAGE FAULT ({:b}) at {:#x} in Thread {}",
+            panic!("Unrecoverable page fault");
+        }
+    }
+}
+
+/// Kills the faulting process on a userspace `#GP`, and panics on a kernel one,
+/// with the decoded cause.
+extern "x86-interrupt" fn general_protection_fault_handler(
+    stack_frame: InterruptStackFrame,
+    error_code: u64,
+) {
+    let rip = stack_frame.instruction_pointer();
+    let ring = Ring::from_u8(u8::try_from(stack_frame.code_segment() & 0b11).unwrap());
+    // Safety: the faulting instruction was fetched, so it is mapped.
+    let cause = gp::decode(error_code, unsafe { instruction_bytes(rip) }, ring);
+
+    if ring == Ring::User {
+        let process = crate::process::scheduler::current_process();
+        video::error!(
+            "Process {} killed: general protection fault at {:#x} ({}, error code {:#x})",
+            process.name(),
+            rip.as_u64(),
+            cause,
+            error_code
+        );
+        drop(process);
+        // Safety: the faulting thread cannot go on.
+        unsafe { crate::process::scheduler::exit_current_thread(ExitCode::Failure) };
+    }
+
+    panic!(
+        "EXCEPTION: GENERAL PROTECTION FAULT on core {} at {:#x}: {} (error code {:#x})\n{:#?}",
+        locals!().core_id(),
+        rip.as_u64(),
+        cause,
         error_code,
-        faulting_address.as_u64(),
-        thread_id.as_u64()
+        stack_frame
     );
+}
 
-    panic!("Unrecoverable page fault");
+/// Returns the bytes of the instruction at `rip`, or fewer of them
+/// if it ends on the next page, which may not be mapped.
+///
+/// # Safety
+///
+/// The page containing `rip` must be mapped.
+unsafe fn instruction_bytes(rip: VirtAddr) -> &'static [u8] {
+    let to_page_end = M4KiB::SIZE - rip.as_u64() % M4KiB::SIZE;
+    let len = usize::try_from(to_page_end)
+        .unwrap()
+        .min(gp::MAX_INSTRUCTION_LEN);
+    unsafe { core::slice::from_raw_parts(rip.as_ptr(), len) }
 }
 
 macro_rules! panic_isr {
@@ -174,7 +307,6 @@ panic_isr!(invalid_opcode_handler);
 panic_isr_with_errcode!(invalid_tss_handler);
 panic_isr_with_errcode!(segment_not_present_handler);
 panic_isr_with_errcode!(stack_segment_fault_handler);
-panic_isr_with_errcode!(general_protection_fault_handler);
 panic_isr!(x87_floating_point_handler);
 panic_isr_with_errcode!(alignment_check_handler);
 panic_isr!(simd_floating_point_handler);
@@ -317,11 +449,111 @@ extern "x86-interrupt" fn non_maskable_interrupt_handler(_stack_frame: Interrupt
     }
 }
 
-extern "x86-interrupt" fn machine_check_handler(_stack_frame: InterruptStackFrame) -> ! {
-    panic!("EXCEPTION: MACHINE CHECK");
+/// Logs the errors reported by the machine-check banks, then halts the core.
+///
+/// The processor may be in a bad state, so the handler does not allocate nor panic,
+/// which would involve the scheduler.
+extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame) -> ! {
+    let core_id = locals!().core_id();
+
+    if super::cpuid::check_feature(super::cpuid::CpuFeature::MCA) {
+        let global = mce::McgStatus::read();
+        video::error!(
+            "EXCEPTION: MACHINE CHECK on core {} (RIPV={} EIPV={}) at {:#x}",
+            core_id,
+            global.restart_ip_valid(),
+            global.error_ip_valid(),
+            stack_frame.instruction_pointer().as_u64()
+        );
+
+        for bank in 0..mce::bank_count() {
+            // Safety: the bank index is lower than the bank count.
+            let status = unsafe { mce::BankStatus::read(bank) };
+            let Some(severity) = status.severity(global) else {
+                continue;
+            };
+            // Safety: same as above.
+            let addr = unsafe { status.read_addr(bank) };
+            video::error!(
+                "MC bank {}: {:?} {:?} error (model code {:#x}, overflow={}, address {:#x?}), status {:#018x}",
+                bank,
+                severity,
+                status.kind(),
+                status.model_code(),
+                status.overflow(),
+                addr,
+                status.raw()
+            );
+        }
+    } else {
+        video::error!("EXCEPTION: MACHINE CHECK on core {}", core_id);
+    }
+
+    video::error!("Halting core {}", core_id);
+    loop {
+        beskar_hal::instructions::int_disable();
+        crate::arch::halt();
+    }
+}
+
+/// Interrupts raised on vectors without a handler, including spurious ones.
+static UNHANDLED: UnhandledIrqs = UnhandledIrqs::new();
+
+/// Default handler of the IRQ vectors, which acknowledges and ignores the interrupt.
+extern "x86-interrupt" fn unhandled_irq_handler<const VECTOR: u8>(
+    _stack_frame: InterruptStackFrame,
+) {
+    in_handler(|| {
+        let action = UNHANDLED.record(VECTOR);
+        if action.log {
+            video::warn!(
+                "Unhandled interrupt {:#x} on core {} ({} times)",
+                VECTOR,
+                locals!().core_id(),
+                action.count
+            );
+        }
+        if action.eoi {
+            unsafe { locals!().lapic().force_lock() }.send_eoi();
+        }
+    });
+}
+
+macro_rules! unhandled_irq_handlers {
+    ($($high:literal),*) => {
+        [$(
+            unhandled_irq_handler::<{ $high * 16 }>,
+            unhandled_irq_handler::<{ $high * 16 + 1 }>,
+            unhandled_irq_handler::<{ $high * 16 + 2 }>,
+            unhandled_irq_handler::<{ $high * 16 + 3 }>,
+            unhandled_irq_handler::<{ $high * 16 + 4 }>,
+            unhandled_irq_handler::<{ $high * 16 + 5 }>,
+            unhandled_irq_handler::<{ $high * 16 + 6 }>,
+            unhandled_irq_handler::<{ $high * 16 + 7 }>,
+            unhandled_irq_handler::<{ $high * 16 + 8 }>,
+            unhandled_irq_handler::<{ $high * 16 + 9 }>,
+            unhandled_irq_handler::<{ $high * 16 + 10 }>,
+            unhandled_irq_handler::<{ $high * 16 + 11 }>,
+            unhandled_irq_handler::<{ $high * 16 + 12 }>,
+            unhandled_irq_handler::<{ $high * 16 + 13 }>,
+            unhandled_irq_handler::<{ $high * 16 + 14 }>,
+            unhandled_irq_handler::<{ $high * 16 + 15 }>,
+        )*]
+    };
 }
 
-info_isr!(spurious_interrupt_handler);
+/// Default handlers, indexed by vector.
+///
+/// Each vector has its own handler so that it is known which one was raised.
+static UNHANDLED_IRQ_HANDLERS: [extern "x86-interrupt" fn(InterruptStackFrame); 256] =
+    unhandled_irq_handlers!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+
+/// IDT vectors in use, as a bitmap.
+///
+/// The first 32 entries, which are reserved for exceptions,
+/// and the spurious interrupt vector are always in use.
+// TODO: Per-core IRQ vectors
+static VECTORS: McsLock<[u64; 4]> = McsLock::new([u32::MAX as u64, 0, 0, 1 << 63]);
 
 #[inline]
 /// Allocates a new IRQ handler in the IDT and return its index.
@@ -331,23 +563,26 @@ pub fn new_irq(
     handler: extern "x86-interrupt" fn(InterruptStackFrame),
     core: Option<usize>,
 ) -> (u8, usize) {
-    /// IDT index counter.
-    ///
-    /// It skips the first 32 entries, which are reserved for exceptions.
-    // TODO: Per-core IRQ counters
-    static IDX: AtomicU8 = AtomicU8::new(32);
-
     let core_id = core.unwrap_or_else(|| locals!().core_id());
     let core_locals = crate::locals::get_specific_core_locals(core_id).unwrap();
 
-    let idx = IDX.fetch_add(1, Ordering::Relaxed);
+    let idx = VECTORS.with_locked(|vectors| {
+        let (word, bits) = vectors
+            .iter_mut()
+            .enumerate()
+            .find(|(_, bits)| **bits != u64::MAX)
+            .expect("No free IRQ vector");
+        let bit = bits.trailing_ones();
+        *bits |= 1 << bit;
+        u8::try_from(word * 64).unwrap() + u8::try_from(bit).unwrap()
+    });
 
     let idt = unsafe { &mut *core_locals.interrupts().idt.get() };
-    let idt_entry = idt.irq(idx).expect("IRQ counter has overflown");
+    let idt_entry = idt.irq(idx).unwrap();
 
     assert_eq!(
         idt_entry.handler_vaddr(),
-        VirtAddr::ZERO,
+        VirtAddr::from_ptr(UNHANDLED_IRQ_HANDLERS[usize::from(idx)] as *const ()),
         "IRQ {idx} is already used",
     );
     idt_entry.set_handler_fn(handler, CS::read());
@@ -355,5 +590,23 @@ pub fn new_irq(
     (idx, core_id)
 }
 
-// Safety: access to the IDT is synchronized by an atomic index counter
+/// Restores the default handler of an IRQ allocated by `new_irq`, and frees its index.
+///
+/// The source of the IRQ must not be able to raise it anymore.
+pub fn free_irq(idx: u8, core_id: usize) {
+    let core_locals = crate::locals::get_specific_core_locals(core_id).unwrap();
+
+    let idt = unsafe { &mut *core_locals.interrupts().idt.get() };
+    idt.irq(idx)
+        .unwrap()
+        .set_handler_fn(UNHANDLED_IRQ_HANDLERS[usize::from(idx)], CS::read());
+
+    VECTORS.with_locked(|vectors| {
+        let bits = &mut vectors[usize::from(idx / 64)];
+        debug_assert!(*bits & (1 << (idx % 64)) != 0, "IRQ {idx} is not used");
+        *bits &= !(1 << (idx % 64));
+    });
+}
+
+// Safety: access to the IDT is synchronized by the vector bitmap
 unsafe impl Sync for Interrupts {}
@@ -29,7 +29,7 @@ pub struct CoreLocalsInfo {
     scheduler: Once<crate::process::scheduler::Scheduler>,
 
     // Arch specific fields
-    apic_id: u8,
+    apic_id: u32,
     gdt: McsLock<super::gdt::Gdt>,
     interrupts: super::interrupts::Interrupts,
     lapic: MUMcsLock<super::apic::LocalApic>,
@@ -38,7 +38,7 @@ pub struct CoreLocalsInfo {
 impl CoreLocalsInfo {
     #[must_use]
     #[inline]
-    const fn new_impl(core_id: usize, apic_id: u8) -> Self {
+    const fn new_impl(core_id: usize, apic_id: u32) -> Self {
         Self {
             self_ptr: AtomicPtr::new(core::ptr::null_mut()),
             core_id,
@@ -51,7 +51,7 @@ impl CoreLocalsInfo {
     }
 
     #[must_use]
-    pub fn new(core_id: usize, apic_id: u8) -> &'static mut Self {
+    pub fn new(core_id: usize, apic_id: u32) -> &'static mut Self {
         let locals = Box::leak(Box::new(Self::new_impl(core_id, apic_id)));
         locals.set_self_ptr();
         locals
@@ -77,7 +77,7 @@ impl CoreLocalsInfo {
 
     #[must_use]
     #[inline]
-    pub const fn apic_id(&self) -> u8 {
+    pub const fn apic_id(&self) -> u32 {
         self.apic_id
     }
 
@@ -1,9 +1,8 @@
-use super::cpuid;
-use hyperdrive::once::Once;
+use beskar_core::rand::fill_bytes;
+use beskar_hal::cpu;
 use thiserror::Error;
 
-static RDRAND_SUPPORT: Once<bool> = Once::uninit();
-static RDSEED_SUPPORT: Once<bool> = Once::uninit();
+pub use cpu::{rdrand_supported, rdseed_supported};
 
 #[derive(Debug, Error)]
 pub enum RandError {
@@ -17,35 +16,6 @@ pub enum RandError {
     RdseedFailed,
 }
 
-/// The maximum number of retries for RDRAND
-///
-/// The value is Intel's recommendation
-const RETRY_LIMIT: u8 = 10;
-
-/// The maximum number of retries for RDSEED
-///
-/// RDSEED can take longer than RDRAND as it sources true entropy
-const RDSEED_RETRY_LIMIT: u16 = 100;
-
-fn rdrand(dst: &mut u64) -> Result<(), RandError> {
-    for _ in 0..RETRY_LIMIT {
-        if unsafe { core::arch::x86_64::_rdrand64_step(dst) } == 1 {
-            return Ok(());
-        }
-    }
-    Err(RandError::RdrandFailed)
-}
-
-fn rdseed(dst: &mut u64) -> Result<(), RandError> {
-    for _ in 0..RDSEED_RETRY_LIMIT {
-        if unsafe { core::arch::x86_64::_rdseed64_step(dst) } == 1 {
-            return Ok(());
-        }
-        core::hint::spin_loop();
-    }
-    Err(RandError::RdseedFailed)
-}
-
 /// Generates random bytes using RDRAND
 ///
 /// # Errors
@@ -57,7 +27,7 @@ pub fn rand_bytes(bytes: &mut [u8]) -> Result<(), RandError> {
         return Err(RandError::RdrandNotSupported);
     }
 
-    rand_bytes_with(bytes, rdrand)
+    fill_bytes(bytes, || cpu::rdrand_u64().ok_or(RandError::RdrandFailed))
 }
 
 /// Generates cryptographic seed bytes using RDSEED
@@ -75,48 +45,5 @@ pub fn rand_seed_bytes(bytes: &mut [u8]) -> Result<(), RandError> {
         return Err(RandError::RdseedNotSupported);
     }
 
-    rand_bytes_with(bytes, rdseed)
-}
-
-/// Generic implementation for filling bytes using a random number generator
-fn rand_bytes_with(
-    bytes: &mut [u8],
-    mut rng: impl FnMut(&mut u64) -> Result<(), RandError>,
-) -> Result<(), RandError> {
-    // It is safe to cast 8 packed `u8`s to a `u64`
-    let (start_u8, middle_u64, end_u8) = unsafe { bytes.align_to_mut::<u64>() };
-
-    // Quickly fill the middle part of the slice with random u64s
-    for qword in middle_u64 {
-        rng(qword)?;
-    }
-
-    // Now fill the unaligned start and end of the slice
-    let mut randomized = 0;
-
-    if !start_u8.is_empty() {
-        // One call to `rng` is enough as `start_u8.len()` is less than 8
-        rng(&mut randomized)?;
-        start_u8.copy_from_slice(&randomized.to_ne_bytes()[..start_u8.len()]);
-    }
-
-    if !end_u8.is_empty() {
-        // One call to `rng` is enough as `end_u8.len()` is less than 8
-        rng(&mut randomized)?;
-        end_u8.copy_from_slice(&randomized.to_ne_bytes()[..end_u8.len()]);
-    }
-
-    Ok(())
-}
-
-#[inline]
-pub fn rdrand_supported() -> bool {
-    RDRAND_SUPPORT.call_once(|| cpuid::check_feature(cpuid::CpuFeature::RDRAND));
-    *RDRAND_SUPPORT.get().unwrap()
-}
-
-#[inline]
-pub fn rdseed_supported() -> bool {
-    RDSEED_SUPPORT.call_once(|| cpuid::check_feature(cpuid::CpuFeature::RDSEED));
-    *RDSEED_SUPPORT.get().unwrap()
+    fill_bytes(bytes, || cpu::rdseed_u64().ok_or(RandError::RdseedFailed))
 }
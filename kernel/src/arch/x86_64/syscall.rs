@@ -1,9 +1,16 @@
 use crate::{
     locals,
-    syscall::{Arguments, syscall},
+    syscall::{Arguments, probe, syscall},
+};
+use beskar_core::{
+    arch::{Alignment, VirtAddr},
+    process::signal::{Delivery, FX_AREA_SIZE, FxArea, SignalFrame, UserContext},
+    syscall::{ExitCode, Syscall, SyscallExitCode, SyscallReturnValue},
+};
+use beskar_hal::{
+    registers::{Efer, LStar, Rflags, SFMask, Star, StarSelectors},
+    userspace::is_sysret_safe,
 };
-use beskar_core::syscall::{Syscall, SyscallExitCode, SyscallReturnValue};
-use beskar_hal::registers::{Efer, LStar, Rflags, SFMask, Star, StarSelectors};
 
 #[derive(Debug, Clone, Copy)]
 #[repr(C, align(8))]
@@ -23,6 +30,8 @@ struct SyscallRegisters {
     rcx: u64,
     /// Contains previous value of RFLAGS
     r11: u64,
+    /// Stack pointer to return to
+    rsp: u64,
 }
 
 #[unsafe(naked)]
@@ -33,6 +42,7 @@ struct SyscallRegisters {
 /// This function should not be called directly.
 unsafe extern "sysv64" fn syscall_handler_arch() {
     core::arch::naked_asm!(
+        "push rsp", // Previous RSP
         "push r11", // Previous RFLAGS
         "push rcx", // Previous RIP
         "push r9",
@@ -53,6 +63,7 @@ unsafe extern "sysv64" fn syscall_handler_arch() {
         "pop r9",
         "pop rcx", // RIP used by sysret
         "pop r11", // r11 contains previous RFLAGS
+        "pop rsp", // Signal delivery may move the user stack
         "sysretq",
         sym syscall_handler_impl,
     );
@@ -73,6 +84,7 @@ extern "sysv64" fn syscall_handler_impl(regs: &mut SyscallRegisters) {
         // correctly keeps the 16-byte alignment of the stack.
         core::arch::asm!(
             "mov {ustack}, rsp", // Keep track of user stack (0)
+            "mov rsi, rsp", // Lowest address of the user stack in use
             "mov rsp, {}", // Switch to kernel stack
             "sti",
             "push {ustack}", // Keep track of user stack (1)
@@ -82,6 +94,7 @@ extern "sysv64" fn syscall_handler_impl(regs: &mut SyscallRegisters) {
             in(reg) kernel_stack.as_ptr(),
             sym syscall_handler_inner,
             in("rdi") regs,
+            out("rsi") _,
             ustack = out(reg) _,
         );
     }
@@ -89,8 +102,9 @@ extern "sysv64" fn syscall_handler_impl(regs: &mut SyscallRegisters) {
 
 /// Performs the standardization of arguments and call to the kernel syscall handler.
 ///
-/// Called by the above function after stack switching
-extern "sysv64" fn syscall_handler_inner(regs: &mut SyscallRegisters) {
+/// Called by the above function after stack switching.
+/// `stack_floor` is the lowest address of the user stack used by the syscall path.
+extern "sysv64" fn syscall_handler_inner(regs: &mut SyscallRegisters, stack_floor: u64) {
     let args = Arguments {
         one: regs.rdi,
         two: regs.rsi,
@@ -102,15 +116,172 @@ extern "sysv64" fn syscall_handler_inner(regs: &mut SyscallRegisters) {
 
     let ssn = Syscall::try_from(regs.rax);
 
-    let res = ssn.map_or(
-        SyscallReturnValue::Code(SyscallExitCode::InvalidSyscallNumber),
-        |ssn| syscall(ssn, &args),
-    );
+    if ssn == Ok(Syscall::SigReturn) {
+        if !sigreturn(regs) {
+            video::error!(
+                "Thread {} killed: invalid signal frame",
+                crate::process::scheduler::current_thread_id().as_u64()
+            );
+            // Safety: the syscall is over, so no lock is held.
+            unsafe { crate::process::scheduler::exit_current_thread(ExitCode::Failure) };
+        }
+    } else {
+        let res = ssn.map_or(
+            SyscallReturnValue::Code(SyscallExitCode::InvalidSyscallNumber),
+            |ssn| syscall(ssn, &args),
+        );
+
+        // Store result
+        regs.rax = res.as_u64();
+    }
+
+    deliver_signal(regs, stack_floor);
+
+    // Another thread may have exited the process during the syscall
+    unsafe { crate::process::scheduler::exit_if_process_exiting() };
+
+    // `SYSRET` to a non-canonical address faults in ring 0, on the user stack.
+    // `IRETQ` would fault in ring 0 as well, and the return path runs on the user stack,
+    // so the thread is killed here, on the kernel stack, as the fault would do.
+    if !is_sysret_safe(regs.rcx) {
+        video::error!(
+            "Thread {} killed: syscall return address {:#x} is not a canonical user address",
+            crate::process::scheduler::current_thread_id().as_u64(),
+            regs.rcx
+        );
+        // Safety: the syscall is over, so no lock is held.
+        unsafe { crate::process::scheduler::exit_current_thread(ExitCode::Failure) };
+    }
+}
+
+/// Returns to the handler of a pending signal instead of the caller of the syscall.
+///
+/// The interrupted state is saved in a `SignalFrame`, pushed on the user stack below
+/// `stack_floor`. If the frame does not fit, the process is terminated.
+fn deliver_signal(regs: &mut SyscallRegisters, stack_floor: u64) {
+    let process = crate::process::current();
+    let (signal, handler, blocked) = match process.take_signal() {
+        None => return,
+        Some(Delivery::Terminate(signal)) => {
+            video::debug!("Process {} terminated by {:?}", process.name(), signal);
+            process.request_exit();
+            return;
+        }
+        Some(Delivery::Handle {
+            signal,
+            handler,
+            blocked,
+        }) => (signal, handler, blocked),
+    };
+
+    let placement = SignalFrame::place(stack_floor).and_then(|placement| {
+        let start = VirtAddr::try_new(placement.frame())?;
+        let end = VirtAddr::try_new(placement.end())?;
+        probe(start, end).then_some((placement, start))
+    });
+    let Some((placement, frame_start)) = placement else {
+        video::debug!(
+            "Process {} terminated: no room on the stack for the {:?} handler",
+            process.name(),
+            signal
+        );
+        process.request_exit();
+        return;
+    };
+
+    let context = UserContext {
+        rax: regs.rax,
+        rdi: regs.rdi,
+        rsi: regs.rsi,
+        rdx: regs.rdx,
+        r8: regs.r8,
+        r9: regs.r9,
+        r10: regs.r10,
+        rip: regs.rcx,
+        rflags: regs.r11,
+        rsp: regs.rsp,
+    };
+    let frame = SignalFrame::new(signal, handler, blocked, placement.fx_area(), context);
 
-    // Store result
-    regs.rax = res.as_u64();
+    // Safety:
+    // The range is owned by the current process, and the `FxArea` is aligned.
+    // The kernel does not use SSE, so the registers still hold the state of userspace.
+    unsafe {
+        frame_start
+            .as_mut_ptr::<SignalFrame>()
+            .write_unaligned(frame);
+        core::arch::asm!(
+            "fxsave64 [{}]",
+            in(reg) placement.fx_area(),
+            options(nostack, preserves_flags)
+        );
+    }
+
+    regs.rcx = handler.entry();
+    regs.rdi = u64::from(signal);
+    regs.rsp = placement.frame();
+    // The ABI requires the direction flag to be clear on function entry
+    regs.r11 &= !Rflags::DF;
 }
 
+/// Restores the state saved by `deliver_signal`, once the handler returned.
+///
+/// Returns `false` if the signal frame is not valid.
+fn sigreturn(regs: &mut SyscallRegisters) -> bool {
+    let Some(frame_start) = VirtAddr::try_new(SignalFrame::address_at_sigreturn(regs.rsp)) else {
+        return false;
+    };
+    if !probe(frame_start, frame_start + size_of::<SignalFrame>() as u64) {
+        return false;
+    }
+    // Safety: The frame's range is owned by the current process.
+    let frame = unsafe { frame_start.as_ptr::<SignalFrame>().read_unaligned() };
+
+    let Some(fx_start) = VirtAddr::try_new(frame.fx_area()) else {
+        return false;
+    };
+    if !fx_start.is_aligned(Alignment::Align16) || !probe(fx_start, fx_start + FX_AREA_SIZE as u64)
+    {
+        return false;
+    }
+    // Safety: The area's range is owned by the current process and is aligned.
+    let mut fx = unsafe { fx_start.as_ptr::<FxArea>().read() };
+    fx.sanitize();
+    // Safety: Reserved bits of the area were cleared.
+    unsafe {
+        core::arch::asm!(
+            "fxrstor64 [{}]",
+            in(reg) &raw const fx,
+            options(nostack, readonly, preserves_flags)
+        );
+    }
+
+    let context = frame.context();
+    regs.rax = context.rax;
+    regs.rdi = context.rdi;
+    regs.rsi = context.rsi;
+    regs.rdx = context.rdx;
+    regs.r8 = context.r8;
+    regs.r9 = context.r9;
+    regs.r10 = context.r10;
+    regs.rcx = context.rip;
+    regs.r11 = context.rflags;
+    regs.rsp = context.rsp;
+
+    crate::process::current().restore_blocked_signals(frame.blocked());
+    true
+}
+
+/// Sets up the `SYSCALL`/`SYSRET` fast path on the current core.
+///
+/// Two requirements come with it:
+/// - `SYSCALL` does not switch stacks, so every thread needs its own kernel stack,
+///   which `syscall_handler_impl` switches to before re-enabling interrupts.
+/// - RFLAGS bits in SFMASK are cleared on entry. Interrupts stay disabled until the
+///   stack switch, and the direction, trap and alignment check flags set by userspace
+///   must not leak into the kernel.
+///
+/// There is no software interrupt fallback: every x86_64 CPU supports `SYSCALL` in long mode.
 pub fn init_syscalls() {
     LStar::write(syscall_handler_arch);
 
@@ -123,7 +294,7 @@ pub fn init_syscalls() {
         ));
     });
 
-    unsafe { SFMask::write(Rflags::IF) };
+    unsafe { SFMask::write(Rflags::IF | Rflags::DF | Rflags::TF | Rflags::AC) };
 
     unsafe { Efer::insert_flags(Efer::SYSTEM_CALL_EXTENSIONS) };
 }
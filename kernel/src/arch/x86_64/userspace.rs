@@ -18,3 +18,27 @@ pub unsafe extern "C" fn enter_usermode(entry: extern "C" fn(), rsp: *mut u8) ->
         "sysretq",
     );
 }
+
+/// Enter usermode, calling `entry` with `arg` as its first argument.
+///
+/// A null return address is pushed on the stack, as `entry` must never return.
+///
+/// # Safety
+///
+/// Same as `enter_usermode`. Moreover, the stack must have room for the return address.
+#[unsafe(naked)]
+pub unsafe extern "C" fn enter_usermode_with_arg(entry: u64, rsp: *mut u8, arg: u64) -> ! {
+    // RDI contains the address of the entry point
+    // RSI contains the stack pointer
+    // RDX contains the argument
+    core::arch::naked_asm!(
+        "mov rcx, rdi",
+        "mov rdi, rdx",
+        "sub rsi, 8",
+        "mov qword ptr [rsi], 0",
+        "pushfq",
+        "pop r11",
+        "mov rsp, rsi",
+        "sysretq",
+    );
+}
@@ -1,6 +1,6 @@
 use crate::{
     arch::{self, apic, interrupts},
-    drivers, locals, mem, process, storage, syscall, time,
+    drivers, locals, mem, network, process, rand, storage, syscall, time,
 };
 use bootloader_api::{BootInfo, RamdiskInfo};
 use core::sync::atomic::{AtomicUsize, Ordering};
@@ -70,11 +70,15 @@ fn bsp_init(boot_info: &'static mut BootInfo) {
     time::init();
     video::info!("Time subsystem initialized");
 
+    rand::init();
+
     process::init();
     video::info!("Process subsystem initialized");
 
-    // If the bootloader provided an RSDP address, we can initialize ACPI.
-    rsdp_paddr.map(drivers::acpi::init);
+    // If the bootloader did not provide an RSDP address, look for it in legacy BIOS memory.
+    if let Some(rsdp_paddr) = rsdp_paddr.or_else(drivers::acpi::scan_rsdp) {
+        drivers::acpi::init(rsdp_paddr);
+    }
 
     interrupts::init();
     video::info!("Interrupts initialized");
@@ -87,6 +91,9 @@ fn bsp_init(boot_info: &'static mut BootInfo) {
 
     storage::init();
     video::info!("Storage subsystem initialized");
+
+    network::init();
+    video::info!("Network stack initialized");
 }
 
 /// Rust entry point for APs
@@ -150,9 +157,45 @@ pub(crate) fn enter_kmain() -> ! {
         core::hint::spin_loop();
     }
 
+    if locals!().core_id() == 0 {
+        // Every core can now tell its ID
+        #[cfg(debug_assertions)]
+        track_lock_order();
+
+        let topology = crate::cpu::topology();
+        video::debug!(
+            "CPU topology: {} package(s), {} core(s), {} thread(s)",
+            topology.packages(),
+            topology.cores(),
+            topology.threads()
+        );
+    }
+
     (KERNEL_MAIN.get().unwrap())()
 }
 
+#[cfg(debug_assertions)]
+/// Logs locks acquired in inconsistent orders, which may deadlock.
+fn track_lock_order() {
+    use hyperdrive::locks::order::{self, Hooks};
+
+    order::install(Hooks {
+        context: || locals!().core_id(),
+        report: |inversion| {
+            video::warn!(
+                "Potential deadlock: lock {:#x} acquired at {} while holding lock {:#x} acquired at {}, \
+                but previously acquired at {} before {}",
+                inversion.acquired.lock,
+                inversion.acquired.location,
+                inversion.held.lock,
+                inversion.held.location,
+                inversion.previous.0.location,
+                inversion.previous.1.location,
+            );
+        },
+    });
+}
+
 #[macro_export]
 /// Macro to define the kernel main function.
 ///
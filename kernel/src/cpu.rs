@@ -0,0 +1,36 @@
+//! CPU topology information.
+//!
+//! The topology is built as cores come up, so that the scheduler can tell
+//! SMT siblings apart from physical cores.
+pub use beskar_hal::topology::{CpuLocation, Topology};
+use hyperdrive::once::Once;
+
+static TOPOLOGY: Once<Topology> = Once::uninit();
+
+/// Registers the current core in the system topology.
+///
+/// The APIC ID layout is decoded by the first core to call this function.
+pub fn init(core_id: usize, apic_id: u32) {
+    TOPOLOGY.call_once(|| Topology::new(crate::arch::cpuid::apic_id_layout()));
+    topology().register(core_id, apic_id);
+}
+
+#[must_use]
+#[inline]
+/// Returns the topology of the currently active cores.
+pub fn topology() -> &'static Topology {
+    TOPOLOGY.get().unwrap()
+}
+
+#[must_use]
+/// Returns the size of the XSAVE area for the components enabled in `XCR0`.
+///
+/// The size is read again on each call, as it changes with `XCR0`.
+///
+/// # Panics
+///
+/// Panics if the layout reported by the processor is inconsistent.
+pub fn xsave_area_size() -> usize {
+    let layout = crate::arch::cpuid::xsave_layout().expect("Invalid XSAVE layout");
+    usize::try_from(layout.size()).unwrap()
+}
@@ -0,0 +1,95 @@
+//! Display handoff between fullscreen apps.
+//!
+//! The keyboard focus follows the display: it belongs to the foreground app.
+use ::storage::{BlockDeviceError, KernelDevice};
+use beskar_core::syscall::display::DisplayEvent;
+use hyperdrive::locks::mcs::McsLock;
+use video::display::{AcquireError, DisplayArbiter};
+
+static ARBITER: McsLock<DisplayArbiter> = McsLock::new(DisplayArbiter::new());
+
+#[must_use]
+#[inline]
+fn current_pid() -> u64 {
+    crate::process::current().pid().as_u64()
+}
+
+/// Gives the keyboard focus to the foreground app.
+///
+/// Must be called without holding the arbiter lock.
+fn sync_focus(owner: Option<u64>) {
+    crate::drivers::keyboard::with_keyboard_manager(|manager| manager.set_focus(owner));
+}
+
+/// Brings the current process to the foreground.
+pub fn acquire() -> Result<(), AcquireError> {
+    let pid = current_pid();
+    let owner = ARBITER.with_locked(|arbiter| arbiter.acquire(pid).map(|()| arbiter.owner()))?;
+    sync_focus(owner);
+    Ok(())
+}
+
+/// Removes the given process from the display holders.
+pub fn release(pid: u64) {
+    let owner = ARBITER.with_locked(|arbiter| {
+        arbiter.release(pid);
+        arbiter.owner()
+    });
+    sync_focus(owner);
+}
+
+/// Brings the next background app to the foreground.
+///
+/// This is the action of the focus switch hotkey.
+pub fn switch_next() {
+    let owner = ARBITER.with_locked(|arbiter| {
+        arbiter.switch_next();
+        arbiter.owner()
+    });
+    sync_focus(owner);
+}
+
+#[must_use]
+#[inline]
+pub fn has_event() -> bool {
+    let pid = current_pid();
+    ARBITER.with_locked(|arbiter| arbiter.has_event(pid))
+}
+
+#[must_use]
+#[inline]
+pub fn take_event() -> Option<DisplayEvent> {
+    let pid = current_pid();
+    ARBITER.with_locked(|arbiter| arbiter.take_event(pid))
+}
+
+/// The framebuffer device, discarding the writes of processes that do not hold the display.
+pub struct DisplayDevice;
+
+impl KernelDevice for DisplayDevice {
+    #[inline]
+    fn read(&mut self, dst: &mut [u8], offset: usize) -> Result<(), BlockDeviceError> {
+        video::screen::ScreenDevice.read(dst, offset)
+    }
+
+    fn write(&mut self, src: &[u8], offset: usize) -> Result<(), BlockDeviceError> {
+        let pid = current_pid();
+        if ARBITER.with_locked(|arbiter| arbiter.may_draw(pid)) {
+            video::screen::ScreenDevice.write(src, offset)
+        } else {
+            // Background apps keep drawing to their own buffer,
+            // and redraw when they get the display back.
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn on_open(&mut self) {
+        video::screen::ScreenDevice.on_open();
+    }
+
+    #[inline]
+    fn on_close(&mut self) {
+        video::screen::ScreenDevice.on_close();
+    }
+}
@@ -8,6 +8,9 @@ pub mod ps2;
 pub mod storage;
 pub mod tsc;
 pub mod usb;
+mod virtio;
+
+use beskar_core::syscall::ExitCode;
 
 pub extern "C" fn init() -> ! {
     let pci_init_result = pci::init();
@@ -26,5 +29,5 @@ pub extern "C" fn init() -> ! {
     let _ = usb::init();
     let _ = nic::init();
 
-    unsafe { crate::process::scheduler::exit_current_thread() };
+    unsafe { crate::process::scheduler::exit_current_thread(ExitCode::Success) };
 }
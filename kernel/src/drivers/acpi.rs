@@ -5,8 +5,23 @@ use hyperdrive::once::Once;
 
 pub static ACPI: Once<Acpi<PhysicalMapping<M4KiB>>> = Once::uninit();
 
+/// Looks for the RSDP in legacy BIOS memory.
+///
+/// Should only be used if the bootloader did not report the RSDP.
+#[must_use]
+pub fn scan_rsdp() -> Option<PhysAddr> {
+    let rsdp_paddr = acpi::scan_rsdp::<PhysicalMapping<M4KiB>>();
+    if let Some(paddr) = rsdp_paddr {
+        video::debug!("RSDP found by legacy scan at {:#x}", paddr.as_u64());
+    }
+    rsdp_paddr
+}
+
 pub fn init(rsdp_paddr: PhysAddr) {
     let acpi = Acpi::from_rsdp_paddr(rsdp_paddr);
+    if let Some(srat) = acpi.srat() {
+        video::debug!("NUMA proximity domains: {}", srat.domain_count());
+    }
     ACPI.call_once(|| acpi);
     video::debug!("ACPI initialized");
 }
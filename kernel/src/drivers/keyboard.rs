@@ -1,6 +1,16 @@
-use beskar_core::drivers::keyboard::KeyEvent;
+use beskar_core::{
+    drivers::keyboard::{
+        KeyEvent,
+        focus::{BlockingRead, FocusRouter, ReadStep, Route},
+        typematic::{Typematic, TypematicConfig},
+    },
+    process::signal::Signal,
+    time::Duration,
+};
+use beskar_hal::instructions::without_interrupts;
+use core::sync::atomic::{AtomicBool, Ordering};
 use driver_api::DriverResult;
-use hyperdrive::{once::Once, queues::mpmc::MpmcQueue};
+use hyperdrive::{locks::mcs::McsLock, once::Once, queues::mpmc::MpmcQueue};
 
 const QUEUE_SIZE: usize = 25;
 
@@ -13,6 +23,10 @@ pub fn init() -> DriverResult<()> {
 
 pub struct KeyboardManager {
     event_queue: MpmcQueue<QUEUE_SIZE, KeyEvent>,
+    typematic: McsLock<Typematic>,
+    focus: McsLock<FocusRouter>,
+    /// Set when an event was dropped because the queue was full.
+    overflowed: AtomicBool,
 }
 
 impl Default for KeyboardManager {
@@ -27,27 +41,194 @@ impl KeyboardManager {
     pub fn new() -> Self {
         Self {
             event_queue: MpmcQueue::new(),
+            typematic: McsLock::new(Typematic::new(TypematicConfig::default())),
+            focus: McsLock::new(FocusRouter::new()),
+            overflowed: AtomicBool::new(false),
         }
     }
 
     #[inline]
     pub fn push_event(&self, event: KeyEvent) {
-        let push_res = self.event_queue.try_push(event);
-        if cfg!(debug_assertions) && push_res.is_err() {
-            // FIXME: Override old events instead of dropping new ones.
-            video::debug!("Keyboard event queue is full, dropping event: {:?}", event);
+        // The focus switch hotkey is handled by the kernel and never reaches apps.
+        match self.focus.with_locked(|focus| focus.on_event(event)) {
+            Route::Deliver => {}
+            Route::SwitchFocus => {
+                crate::display::switch_next();
+                return;
+            }
+            Route::Discard => return,
+            Route::Signal { signal, pid } => {
+                defer_signal(signal, pid);
+                return;
+            }
         }
 
-        crate::process::scheduler::wake_event_single(
-            beskar_core::process::SleepHandle::SLEEP_HANDLE_KEYBOARD_INTERRUPT,
-        );
+        let now = crate::time::now();
+        if !self
+            .typematic
+            .with_locked(|typematic| typematic.on_event(event, now))
+        {
+            return;
+        }
+        self.enqueue(event);
+    }
+
+    /// Emits the repeat of the held key, if one is due.
+    ///
+    /// This function is meant to be called periodically.
+    pub fn tick(&self) {
+        let now = crate::time::now();
+        // Without a timer, every key would repeat endlessly.
+        if now == beskar_core::time::Instant::MAX {
+            return;
+        }
+
+        if let Some(Some(event)) = self
+            .typematic
+            .try_with_locked(|typematic| typematic.poll(now))
+        {
+            self.enqueue(event);
+        } else if !self.event_queue.is_empty() {
+            // A reader may have checked the queue right before an event arrived,
+            // and parked after its wake up.
+            defer_wake_readers();
+        }
+    }
+
+    /// Changes the delay before a held key starts repeating and the interval between repeats.
+    ///
+    /// A null `interval` disables key repeat.
+    pub fn set_typematic(&self, delay: Duration, interval: Duration) {
+        self.typematic.with_locked(|typematic| {
+            typematic.set_config(TypematicConfig::new(delay, interval));
+        });
+    }
+
+    fn enqueue(&self, event: KeyEvent) {
+        // When the queue is full, the oldest event is dropped to keep the most recent input.
+        if let Some(dropped) = self.event_queue.force_push(event) {
+            self.overflowed.store(true, Ordering::Release);
+            if cfg!(debug_assertions) {
+                video::debug!(
+                    "Keyboard event queue is full, dropping event: {:?}",
+                    dropped
+                );
+            }
+        }
+
+        defer_wake_readers();
+    }
+
+    /// Gives the focus to the given process, or to none.
+    ///
+    /// On change, buffered events and the key repeat are dropped,
+    /// so that they do not reach the newly focused process.
+    pub fn set_focus(&self, pid: Option<u64>) {
+        // The locks are also taken by the keyboard interrupt handler.
+        without_interrupts(|| {
+            if !self.focus.with_locked(|focus| focus.set_focus(pid)) {
+                return;
+            }
+            while self.event_queue.pop().is_some() {}
+            self.overflowed.store(false, Ordering::Release);
+            self.typematic.with_locked(Typematic::cancel);
+        });
+        // Blocked readers that lost the focus must return.
+        wake_readers();
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether the given process may read key events.
+    pub fn may_receive(&self, pid: u64) -> bool {
+        without_interrupts(|| self.focus.with_locked(|focus| focus.may_receive(pid)))
     }
 
     #[must_use]
     #[inline]
-    pub fn poll_event(&self) -> Option<KeyEvent> {
+    /// Pops an event for the given process, if it has the focus.
+    pub fn poll_event(&self, pid: u64) -> Option<KeyEvent> {
+        if !self.may_receive(pid) {
+            return None;
+        }
         self.event_queue.pop()
     }
+
+    #[must_use]
+    #[inline]
+    /// Returns whether events are waiting for the given process.
+    pub fn has_events(&self, pid: u64) -> bool {
+        self.may_receive(pid) && !self.event_queue.is_empty()
+    }
+
+    /// Attempts a blocking read of the given process.
+    pub fn read_step(&self, read: &mut BlockingRead) -> ReadStep {
+        // Holding the focus lock, the focus cannot change between the check and the pop.
+        without_interrupts(|| {
+            self.focus
+                .with_locked(|focus| read.step(focus, || self.event_queue.pop()))
+        })
+    }
+
+    /// Moves buffered events into `dst`, packed, until it is full or the queue is empty.
+    ///
+    /// Returns the number of events written and whether events were dropped
+    /// since the last call.
+    /// Processes without the focus get no events.
+    pub fn poll_batch(&self, pid: u64, dst: &mut [u64]) -> (usize, bool) {
+        if !self.may_receive(pid) {
+            return (0, false);
+        }
+        let overflowed = self.overflowed.swap(false, Ordering::AcqRel);
+
+        let mut count = 0;
+        for slot in dst.iter_mut() {
+            let Some(event) = self.event_queue.pop() else {
+                break;
+            };
+            *slot = KeyEvent::pack_option(Some(event));
+            count += 1;
+        }
+
+        (count, overflowed)
+    }
+}
+
+/// Wakes up every thread waiting for a keyboard event.
+///
+/// Only readers with the focus get an event, the others park again.
+fn wake_readers() {
+    crate::process::scheduler::wake_event_all(
+        beskar_core::process::SleepHandle::SLEEP_HANDLE_KEYBOARD_INTERRUPT,
+    );
+}
+
+/// Wakes up the readers from an interrupt handler.
+///
+/// Waking threads allocates, which interrupt handlers must not do, so it is deferred.
+/// If too much work is pending, the next tick tries again.
+fn defer_wake_readers() {
+    let _ = crate::softirq::schedule(crate::softirq::Work::new(|_| wake_readers(), 0));
+}
+
+/// Raises a signal generated from the keyboard in the group of the focused process.
+///
+/// As with waking readers, raising a signal allocates, so it is deferred.
+/// If too much work is pending, the signal is lost, as would be a key.
+///
+/// The work runs within a tick, even if the process busy-loops in userspace, see
+/// `softirq::run_on_tick`. A signal without a handler then stops its threads when
+/// they are next preempted, while a handler only runs once a thread makes a syscall.
+fn defer_signal(signal: Signal, pid: u64) {
+    let raise: fn(usize) = match signal {
+        Signal::Interrupt => |pid| crate::process::signal_group_of(pid as u64, Signal::Interrupt),
+        Signal::Suspend => |pid| crate::process::signal_group_of(pid as u64, Signal::Suspend),
+        Signal::Alarm | Signal::BrokenPipe => return,
+    };
+    let Ok(pid) = usize::try_from(pid) else {
+        return;
+    };
+    let _ = crate::softirq::schedule(crate::softirq::Work::new(raise, pid));
 }
 
 /// Operate on the keyboard manager.
@@ -70,8 +251,9 @@ impl ::storage::KernelDevice for KeyboardDevice {
             return Err(::storage::BlockDeviceError::UnalignedAccess);
         }
 
+        let pid = crate::process::current().pid().as_u64();
         for block in dst.iter_mut() {
-            let key_event = with_keyboard_manager(KeyboardManager::poll_event).flatten();
+            let key_event = with_keyboard_manager(|manager| manager.poll_event(pid)).flatten();
             *block = KeyEvent::pack_option(key_event);
         }
 
@@ -2,7 +2,9 @@ use crate::drivers::pci;
 use beskar_core::drivers::{DriverError, DriverResult};
 use holonet::Nic;
 
+mod e1000;
 mod e1000e;
+mod virtio_net;
 
 pub fn init() -> DriverResult<()> {
     let Some(network_controller) = pci::with_pci_handler(|handler| {
@@ -23,7 +25,13 @@ pub fn init() -> DriverResult<()> {
 
     match (network_controller.vendor_id(), network_controller.id()) {
         // TODO: Add more e1000e network controllers
+        (0x8086, 0x100E) => e1000::init(network_controller),
         (0x8086, 0x10D3) => e1000e::init(network_controller),
+        (::virtio::PCI_VENDOR_ID, id)
+            if ::virtio::DeviceType::from_pci_id(id) == Some(::virtio::DeviceType::Net) =>
+        {
+            virtio_net::init(network_controller)
+        }
         (0x8086, _) => {
             video::warn!(
                 // Most Intel network controllers should be either e1000 or e1000e
@@ -48,8 +56,12 @@ pub fn with_nic<F, R>(f: F) -> Option<R>
 where
     F: FnOnce(&mut dyn Nic) -> R,
 {
-    if e1000e::e1000e_available() {
+    if e1000::e1000_available() {
+        Some(e1000::with_e1000(|nic| f(nic)))
+    } else if e1000e::e1000e_available() {
         Some(e1000e::with_e1000e(|nic| f(nic)))
+    } else if virtio_net::virtio_net_available() {
+        Some(virtio_net::with_virtio_net(|nic| f(nic)))
     } else {
         None
     }
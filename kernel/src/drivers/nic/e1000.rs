@@ -0,0 +1,346 @@
+//! Driver for the Intel e1000 network controller (82540EM), as emulated by QEMU.
+//!
+//! See <https://pdos.csail.mit.edu/6.828/2019/readings/hardware/8254x_GBe_SDM.pdf>
+//! chapter 14 (p.377) for the initialization sequence.
+//!
+//! The 82540EM has no MSI capability, so the driver only polls its descriptor rings.
+
+use super::Nic;
+use crate::mem::{dma::DmaBuffer, page_alloc::pmap::PhysicalMapping};
+use ::pci::Bar;
+use beskar_core::{
+    arch::{Alignment, PhysAddr, paging::M4KiB},
+    drivers::{
+        DriverError, DriverResult,
+        e1000::{
+            RingIndex,
+            descriptors::{RxDescriptor, TxDescriptor},
+            registers::{CtrlFlags, EerdFlags, RahFlags, RctlFlags, Registers, TctlFlags},
+        },
+    },
+};
+use beskar_hal::paging::page_table::Flags;
+use core::ptr::NonNull;
+use driver_shared::mmio::MmioRegister;
+use holonet::l2::ethernet::MacAddress;
+use hyperdrive::{locks::mcs::MUMcsLock, ptrs::volatile::ReadWrite};
+
+/// Both rings must be a multiple of 128 bytes long, that is 8 descriptors
+const RX_BUFFERS: usize = 32;
+const TX_BUFFERS: usize = 8;
+/// Size of a buffer, which fits a full Ethernet frame
+const BUFFER_SIZE: usize = 2048;
+/// Number of polls of the EEPROM before giving up on it
+const EEPROM_TIMEOUT: usize = 10_000;
+
+static E1000: MUMcsLock<E1000> = MUMcsLock::uninit();
+
+pub fn init(network_controller: pci::Device) -> DriverResult<()> {
+    let Some(Bar::Memory(bar_reg)) =
+        crate::drivers::pci::with_pci_handler(|handler| handler.read_bar(&network_controller, 0))
+    else {
+        video::warn!("Network controller does not have a memory BAR");
+        return Err(DriverError::Absent);
+    };
+    crate::drivers::pci::with_pci_handler(|handler| {
+        handler.enable_bus_master(&network_controller);
+    });
+
+    let reg_paddr = bar_reg.base_address();
+    // Registers span 128 KiB
+    let pmap = PhysicalMapping::<M4KiB>::new(reg_paddr, 128 * 1024, Flags::MMIO_SUITABLE)
+        .map_err(|_| DriverError::Unknown)?;
+    let reg_vaddr = pmap.translate(reg_paddr).ok_or(DriverError::Unknown)?;
+
+    let mut e1000 = E1000 {
+        base: MmioRegister::new(NonNull::new(reg_vaddr.as_mut_ptr()).unwrap()),
+        _physical_mapping: pmap,
+        rx_ring: DescriptorRing::new(RX_BUFFERS)?,
+        tx_ring: DescriptorRing::new(TX_BUFFERS)?,
+        rx_curr: RingIndex::new(RX_BUFFERS),
+        tx_curr: RingIndex::new(TX_BUFFERS),
+        mac_address: MacAddress::BROADCAST,
+    };
+    e1000.init();
+
+    video::info!(
+        "Intel e1000 network controller initialized. MAC: {}",
+        e1000.mac_address
+    );
+
+    E1000.init(e1000);
+
+    Ok(())
+}
+
+pub struct E1000 {
+    base: MmioRegister<ReadWrite, u32>,
+    _physical_mapping: PhysicalMapping<M4KiB>,
+    rx_ring: DescriptorRing<RxDescriptor>,
+    tx_ring: DescriptorRing<TxDescriptor>,
+    rx_curr: RingIndex,
+    tx_curr: RingIndex,
+    mac_address: MacAddress,
+}
+
+impl E1000 {
+    fn init(&mut self) {
+        self.reset();
+        // Interrupts are not used
+        self.write_reg(Registers::IMC, u32::MAX);
+        self.update_reg(Registers::CTRL, |ctrl| ctrl | CtrlFlags::SLU);
+
+        self.mac_address = self.read_mac_address();
+
+        for i in 0..RX_BUFFERS {
+            let paddr = self.rx_ring.buffer_paddr(i);
+            *self.rx_ring.desc_mut(i) =
+                RxDescriptor::new(paddr, u16::try_from(BUFFER_SIZE).unwrap());
+        }
+        for i in 0..TX_BUFFERS {
+            let paddr = self.tx_ring.buffer_paddr(i);
+            *self.tx_ring.desc_mut(i) =
+                TxDescriptor::new(paddr, u16::try_from(BUFFER_SIZE).unwrap());
+        }
+
+        self.configure_rx();
+        self.configure_tx();
+    }
+
+    fn reset(&mut self) {
+        self.update_reg(Registers::CTRL, |ctrl| ctrl | CtrlFlags::RST);
+        while self.read_reg(Registers::CTRL) & CtrlFlags::RST != 0 {
+            core::hint::spin_loop();
+        }
+    }
+
+    fn configure_rx(&mut self) {
+        let paddr = self.rx_ring.desc_paddr().as_u64();
+        self.write_reg(
+            Registers::RDBAL0,
+            u32::try_from(paddr & 0xFFFF_FFFF).unwrap(),
+        );
+        self.write_reg(Registers::RDBAH0, u32::try_from(paddr >> 32).unwrap());
+        self.write_reg(
+            Registers::RDLEN,
+            u32::try_from(RX_BUFFERS * size_of::<RxDescriptor>()).unwrap(),
+        );
+        self.write_reg(Registers::RDH, 0);
+        self.write_reg(
+            Registers::RDT,
+            u32::try_from(self.rx_curr.initial_rx_tail()).unwrap(),
+        );
+
+        self.write_reg(
+            Registers::RCTL,
+            RctlFlags::EN
+                | RctlFlags::UPE
+                | RctlFlags::MPE
+                | RctlFlags::LBM_PHY
+                | RctlFlags::RDMTS_HALF
+                | RctlFlags::BAM
+                | RctlFlags::BSIZE_2048
+                | RctlFlags::SECRC,
+        );
+    }
+
+    fn configure_tx(&mut self) {
+        let paddr = self.tx_ring.desc_paddr().as_u64();
+        self.write_reg(
+            Registers::TDBAL0,
+            u32::try_from(paddr & 0xFFFF_FFFF).unwrap(),
+        );
+        self.write_reg(Registers::TDBAH0, u32::try_from(paddr >> 32).unwrap());
+        self.write_reg(
+            Registers::TDLEN,
+            u32::try_from(TX_BUFFERS * size_of::<TxDescriptor>()).unwrap(),
+        );
+        // The ring starts empty
+        self.write_reg(Registers::TDH, 0);
+        self.write_reg(Registers::TDT, 0);
+
+        self.write_reg(
+            Registers::TCTL,
+            TctlFlags::EN
+                | TctlFlags::PSP
+                | (15 << TctlFlags::CT_SHIFT)
+                | (64 << TctlFlags::COLD_SHIFT),
+        );
+        // Section 13.4.34 (p.312): IPGT = 10, IPGR1 = 8, IPGR2 = 6
+        self.write_reg(Registers::TIPG, 0x0060_200A);
+    }
+
+    /// Read the MAC address from the EEPROM, or from the receive address registers
+    /// if the EEPROM does not answer.
+    fn read_mac_address(&mut self) -> MacAddress {
+        let words = [0, 1, 2].map(|word| self.read_eeprom(word));
+        if let [Some(w0), Some(w1), Some(w2)] = words {
+            return MacAddress::new(beskar_core::drivers::e1000::mac_from_eeprom([w0, w1, w2]));
+        }
+
+        let low = self.read_reg(Registers::RAL0);
+        let high = self.read_reg(Registers::RAH0);
+        if high & RahFlags::AV == 0 {
+            video::warn!("e1000 MAC address is not valid");
+        }
+
+        let [b0, b1, b2, b3] = low.to_le_bytes();
+        let [b4, b5, _, _] = high.to_le_bytes();
+        MacAddress::new([b0, b1, b2, b3, b4, b5])
+    }
+
+    fn read_eeprom(&mut self, word: u8) -> Option<u16> {
+        self.write_reg(
+            Registers::EERD,
+            (u32::from(word) << EerdFlags::ADDR_SHIFT) | EerdFlags::START,
+        );
+        for _ in 0..EEPROM_TIMEOUT {
+            let eerd = self.read_reg(Registers::EERD);
+            if eerd & EerdFlags::DONE != 0 {
+                return Some(u16::try_from(eerd >> EerdFlags::DATA_SHIFT).unwrap());
+            }
+            core::hint::spin_loop();
+        }
+        None
+    }
+
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { self.base.byte_add(offset).read() }
+    }
+
+    fn write_reg(&mut self, offset: usize, value: u32) {
+        unsafe { self.base.byte_add(offset).write(value) };
+    }
+
+    fn update_reg<F>(&mut self, offset: usize, f: F)
+    where
+        F: FnOnce(u32) -> u32,
+    {
+        unsafe { self.base.byte_add(offset).update(f) };
+    }
+}
+
+impl Nic for E1000 {
+    fn poll_frame(&self) -> Option<&[u8]> {
+        let rx_idx = self.rx_curr.current();
+        let packet_len = self.rx_ring.desc(rx_idx).received_length()?;
+        Some(&self.rx_ring.buffer(rx_idx)[..usize::from(packet_len)])
+    }
+
+    fn consume_frame(&mut self) {
+        let rx_idx = self.rx_curr.current();
+        if !self.rx_ring.desc(rx_idx).is_done() {
+            return;
+        }
+        self.rx_ring.desc_mut(rx_idx).reset();
+
+        // Give the descriptor back to the controller
+        let processed = self.rx_curr.advance();
+        self.write_reg(Registers::RDT, u32::try_from(processed).unwrap());
+    }
+
+    fn send_frame(&mut self, frame: &[u8]) {
+        if frame.is_empty() || frame.len() > BUFFER_SIZE {
+            video::warn!("Invalid frame size: {}", frame.len());
+            return;
+        }
+
+        let tx_idx = self.tx_curr.current();
+        if !self.tx_ring.desc(tx_idx).is_done() {
+            video::warn!("No free TX descriptor available");
+            return;
+        }
+
+        self.tx_ring.buffer_mut(tx_idx)[..frame.len()].copy_from_slice(frame);
+        self.tx_ring
+            .desc_mut(tx_idx)
+            .prepare_for_send(u16::try_from(frame.len()).unwrap());
+
+        self.tx_curr.advance();
+        self.write_reg(
+            Registers::TDT,
+            u32::try_from(self.tx_curr.current()).unwrap(),
+        );
+    }
+
+    fn mac_address(&self) -> MacAddress {
+        self.mac_address
+    }
+}
+
+/// A ring of descriptors, each with its own buffer.
+struct DescriptorRing<D> {
+    descriptors: DmaBuffer,
+    buffers: DmaBuffer,
+    len: usize,
+    _marker: core::marker::PhantomData<D>,
+}
+
+impl<D> DescriptorRing<D> {
+    fn new(len: usize) -> DriverResult<Self> {
+        let descriptors = DmaBuffer::new(len * size_of::<D>(), Alignment::Align128)
+            .ok_or(DriverError::Unknown)?;
+        let buffers =
+            DmaBuffer::new(len * BUFFER_SIZE, Alignment::Align4K).ok_or(DriverError::Unknown)?;
+        Ok(Self {
+            descriptors,
+            buffers,
+            len,
+            _marker: core::marker::PhantomData,
+        })
+    }
+
+    const fn desc_paddr(&self) -> PhysAddr {
+        self.descriptors.paddr()
+    }
+
+    fn buffer_paddr(&self, index: usize) -> PhysAddr {
+        assert!(index < self.len);
+        self.buffers.paddr() + u64::try_from(index * BUFFER_SIZE).unwrap()
+    }
+
+    fn desc(&self, index: usize) -> &D {
+        assert!(index < self.len);
+        // Safety: The descriptor is in the ring, and descriptors are packed.
+        unsafe { &*self.descriptors.as_mut_ptr::<D>().add(index) }
+    }
+
+    fn desc_mut(&mut self, index: usize) -> &mut D {
+        assert!(index < self.len);
+        // Safety: The descriptor is in the ring, and descriptors are packed.
+        unsafe { &mut *self.descriptors.as_mut_ptr::<D>().add(index) }
+    }
+
+    fn buffer(&self, index: usize) -> &[u8] {
+        assert!(index < self.len);
+        // Safety: The buffer is in the ring.
+        unsafe {
+            core::slice::from_raw_parts(
+                self.buffers.as_mut_ptr::<u8>().add(index * BUFFER_SIZE),
+                BUFFER_SIZE,
+            )
+        }
+    }
+
+    fn buffer_mut(&mut self, index: usize) -> &mut [u8] {
+        assert!(index < self.len);
+        // Safety: The buffer is in the ring.
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.buffers.as_mut_ptr::<u8>().add(index * BUFFER_SIZE),
+                BUFFER_SIZE,
+            )
+        }
+    }
+}
+
+pub fn with_e1000<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut E1000) -> R,
+{
+    E1000.with_locked(f)
+}
+
+pub fn e1000_available() -> bool {
+    E1000.is_initialized()
+}
@@ -5,23 +5,27 @@
 //!
 //! NB: All registers use host-endianess (LE), except for `ETherType` fields, which use network-endianess (BE).
 
-mod descriptors;
-mod registers;
-
-use self::{
-    descriptors::{RxDescriptor, TxDescriptor},
-    registers::{CtrlFlags, IntFlags, RctlFlags, Registers, TctlFlags},
-};
 use super::Nic;
-use crate::{drivers::pci::MsiHelper, locals, mem::page_alloc::pmap::PhysicalMapping, process};
+use crate::{
+    drivers::pci::MsiInterrupt,
+    locals,
+    mem::{dma::DmaBuffer, page_alloc::pmap::PhysicalMapping},
+    process,
+};
 use ::pci::Bar;
 use alloc::vec::Vec;
 use beskar_core::{
     arch::{
-        PhysAddr, VirtAddr,
+        Alignment, PhysAddr, VirtAddr,
         paging::{CacheFlush as _, M4KiB, Mapper, MemSize as _, Page},
     },
-    drivers::{DriverError, DriverResult},
+    drivers::{
+        DriverError, DriverResult,
+        e1000::{
+            descriptors::{RxDescriptor, TxDescriptor},
+            registers::{CtrlFlags, IntFlags, RctlFlags, Registers, TctlFlags},
+        },
+    },
 };
 use beskar_hal::{paging::page_table::Flags, structures::InterruptStackFrame};
 use core::ptr::NonNull;
@@ -65,6 +69,7 @@ pub fn init(network_controller: pci::Device) -> DriverResult<()> {
         buffer_set,
         rx_curr: core::cell::Cell::new(0),
         tx_curr: core::cell::Cell::new(0),
+        interrupt: None,
     };
     e1000e.init(rxdesc_paddr, txdesc_paddr, nb_rx, nb_tx);
 
@@ -85,6 +90,7 @@ pub struct E1000e<'a> {
     buffer_set: BufferSet<'a>,
     rx_curr: core::cell::Cell<usize>,
     tx_curr: core::cell::Cell<usize>,
+    interrupt: Option<MsiInterrupt>,
 }
 
 impl E1000e<'_> {
@@ -103,30 +109,9 @@ impl E1000e<'_> {
     }
 
     fn enable_int(&mut self) {
-        let msix = crate::drivers::pci::with_pci_handler(|handler| {
-            pci::msix::MsiX::<PhysicalMapping<M4KiB>, MsiHelper>::new(handler, &self.pci_device)
-        });
-        let msi = if msix.is_none() {
-            crate::drivers::pci::with_pci_handler(|handler| {
-                pci::msi::Msi::<MsiHelper>::new(handler, &self.pci_device)
-            })
-        } else {
-            None
-        };
-
-        let (irq, core_id) = crate::arch::interrupts::new_irq(nic_interrupt_handler, None);
-
-        if let Some(msix) = msix {
-            msix.setup_int(irq, 0, core_id);
-            crate::drivers::pci::with_pci_handler(|handler| msix.enable(handler));
-        } else if let Some(msi) = msi {
-            crate::drivers::pci::with_pci_handler(|handler| {
-                msi.setup_int(irq, handler, core_id);
-                msi.enable(handler);
-            });
-        } else {
-            unreachable!("No MSI or MSI-X capability found for the network controller.");
-        }
+        let interrupt = MsiInterrupt::new(&self.pci_device, nic_interrupt_handler, None)
+            .expect("No MSI or MSI-X capability found for the network controller.");
+        self.interrupt = Some(interrupt);
 
         self.write_reg(
             Registers::IMS,
@@ -237,31 +222,33 @@ impl E1000e<'_> {
 }
 
 extern "x86-interrupt" fn nic_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    E1000E.with_locked(|e1000e| {
-        // Read and acknowledge interrupt cause
-        let icr = e1000e.read_reg(Registers::ICR);
+    crate::arch::interrupts::in_handler(|| {
+        E1000E.with_locked(|e1000e| {
+            // Read and acknowledge interrupt cause
+            let icr = e1000e.read_reg(Registers::ICR);
 
-        if icr & IntFlags::RXT0 != 0 || icr & IntFlags::RXDMT0 != 0 {
-            // TODO: Packet received (notify network stack)
-        }
+            if icr & IntFlags::RXT0 != 0 || icr & IntFlags::RXDMT0 != 0 {
+                // TODO: Packet received (notify network stack)
+            }
 
-        if icr & IntFlags::TXDW != 0 {
-            // TODO: Transmit done
-        }
+            if icr & IntFlags::TXDW != 0 {
+                // TODO: Transmit done
+            }
 
-        if icr & IntFlags::LSC != 0 {
-            // Link status changed
-            let status = e1000e.read_reg(Registers::STATUS);
-            let link_up = (status & 0x02) != 0;
-            if link_up {
-                video::debug!("Network link is up");
-            } else {
-                video::debug!("Network link is down");
+            if icr & IntFlags::LSC != 0 {
+                // Link status changed
+                let status = e1000e.read_reg(Registers::STATUS);
+                let link_up = (status & 0x02) != 0;
+                if link_up {
+                    video::debug!("Network link is up");
+                } else {
+                    video::debug!("Network link is down");
+                }
             }
-        }
-    });
+        });
 
-    unsafe { locals!().lapic().force_lock() }.send_eoi();
+        unsafe { locals!().lapic().force_lock() }.send_eoi();
+    });
 }
 
 impl Nic for E1000e<'_> {
@@ -333,57 +320,38 @@ struct BufferSet<'a> {
     rx_buffers: Vec<&'a mut [u8]>,
     tx_descriptors: &'a mut [TxDescriptor],
     tx_buffers: Vec<&'a mut [u8]>,
+    /// Backing memory of both descriptor rings
+    _descriptors: DmaBuffer,
 }
 
 impl BufferSet<'_> {
     #[must_use]
     pub fn new(nb_rx: usize, nb_tx: usize) -> (Self, PhysAddr, PhysAddr) {
-        assert!(
-            nb_rx * size_of::<RxDescriptor>() + nb_tx * size_of::<TxDescriptor>()
-                < M4KiB::SIZE.try_into().unwrap()
-        );
-
         let mut rx_buffers = Vec::with_capacity(nb_rx);
         let mut tx_buffers = Vec::with_capacity(nb_tx);
 
         // The NIC will use physical address to access buffers.
         // Thus, we must make sure that everything is physically contiguous.
-        // The easiest way I found to do this is to allocate a full frame for each object.
+        // The easiest way I found to do this is to allocate a full frame for each buffer.
         // It gives a very nice 4096 bytes buffer, which is common.
+        // Descriptor rings are allocated as a single contiguous DMA buffer.
 
-        let descriptor_page = process::current()
-            .address_space()
-            .with_pgalloc(|palloc| palloc.allocate_pages(1).unwrap())
-            .start();
+        let rings_size = nb_rx * size_of::<RxDescriptor>() + nb_tx * size_of::<TxDescriptor>();
+        let descriptors = DmaBuffer::new(rings_size, Alignment::Align128)
+            .expect("Failed to allocate e1000e descriptor rings");
         let flags = Flags::MMIO_SUITABLE;
-        let descriptor_frame = crate::mem::frame_alloc::with_frame_allocator(|fralloc| {
-            let frame = fralloc.alloc::<M4KiB>().unwrap();
-            process::current()
-                .address_space()
-                .with_page_table(|page_table| {
-                    page_table
-                        .map(descriptor_page, frame, flags, fralloc)
-                        .unwrap()
-                        .flush();
-                });
-            frame
-        });
 
-        // SAFETY: We just allocated and mapped this page. The memory is valid and properly aligned.
-        // The lifetime 'a is tied to BufferSet, ensuring these slices don't outlive the allocation.
+        // SAFETY: We just allocated and mapped this buffer. The memory is valid and properly aligned.
+        // The buffer is owned by BufferSet, ensuring these slices don't outlive the allocation.
         let rx_descriptors = unsafe {
-            core::slice::from_raw_parts_mut(
-                descriptor_page.start_address().as_mut_ptr::<RxDescriptor>(),
-                nb_rx,
-            )
+            core::slice::from_raw_parts_mut(descriptors.as_mut_ptr::<RxDescriptor>(), nb_rx)
         };
 
-        // SAFETY: Same page as rx_descriptors, offset by nb_rx descriptors.
-        // The size check at the beginning ensures this doesn't overflow the page.
+        // SAFETY: Same buffer as rx_descriptors, offset by nb_rx descriptors.
+        // The buffer was sized to hold both rings.
         let tx_descriptors = unsafe {
             core::slice::from_raw_parts_mut(
-                descriptor_page
-                    .start_address()
+                descriptors
                     .as_mut_ptr::<RxDescriptor>()
                     .add(nb_rx)
                     .cast::<TxDescriptor>(),
@@ -443,16 +411,19 @@ impl BufferSet<'_> {
 
         assert_eq!(rx_buffers.len(), nb_rx);
 
+        let rxdesc_paddr = descriptors.paddr();
+        let txdesc_paddr = rxdesc_paddr + u64::try_from(nb_rx * size_of::<RxDescriptor>()).unwrap();
+
         (
             Self {
                 rx_descriptors,
                 rx_buffers,
                 tx_descriptors,
                 tx_buffers,
+                _descriptors: descriptors,
             },
-            descriptor_frame.start_address(),
-            descriptor_frame.start_address()
-                + u64::try_from(nb_rx * size_of::<RxDescriptor>()).unwrap(),
+            rxdesc_paddr,
+            txdesc_paddr,
         )
     }
 
@@ -515,18 +486,6 @@ impl Drop for BufferSet<'_> {
         process::current()
             .address_space()
             .with_pgalloc(|palloc| palloc.free_pages(buffer_page_range));
-
-        let descriptors_page =
-            Page::<M4KiB>::containing_address(VirtAddr::from_ptr(self.rx_descriptors.as_ptr()));
-        let descriptors_frame = process::current().address_space().with_page_table(|pt| {
-            let (frame, tlb) = pt.unmap(descriptors_page).unwrap();
-            tlb.flush();
-            frame
-        });
-        crate::mem::frame_alloc::with_frame_allocator(|fralloc| fralloc.free(descriptors_frame));
-        process::current().address_space().with_pgalloc(|palloc| {
-            palloc.free_pages(Page::range_inclusive(descriptors_page, descriptors_page));
-        });
     }
 }
 
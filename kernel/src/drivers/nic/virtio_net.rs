@@ -0,0 +1,206 @@
+//! Driver for virtio network devices.
+//!
+//! Frames are received in and transmitted from fixed-size buffers, one descriptor each.
+//! Mergeable receive buffers are not negotiated, so that a frame always fits in a single buffer.
+
+use super::Nic;
+use crate::{
+    drivers::virtio::{Queue, Transport},
+    mem::dma::DmaBuffer,
+};
+use alloc::vec::Vec;
+use beskar_core::{
+    arch::Alignment,
+    drivers::{DriverError, DriverResult},
+};
+use holonet::l2::ethernet::MacAddress;
+use hyperdrive::locks::mcs::MUMcsLock;
+use virtio::net;
+
+const RX_BUFFERS: usize = 32;
+const TX_BUFFERS: usize = 8;
+/// Size of a buffer, which fits the header and a full Ethernet frame
+const BUFFER_SIZE: usize = 2048;
+
+static VIRTIO_NET: MUMcsLock<VirtioNet> = MUMcsLock::uninit();
+
+pub fn init(network_controller: pci::Device) -> DriverResult<()> {
+    let virtio_net = VirtioNet::new(&network_controller)?;
+
+    video::info!(
+        "Virtio network device initialized. MAC: {}",
+        virtio_net.mac_address()
+    );
+
+    VIRTIO_NET.init(virtio_net);
+
+    Ok(())
+}
+
+pub struct VirtioNet {
+    transport: Transport,
+    rx_queue: Queue,
+    tx_queue: Queue,
+    rx_buffers: DmaBuffer,
+    tx_buffers: DmaBuffer,
+    /// Buffer of each in-flight chain of the receive queue, by head descriptor
+    rx_slots: Vec<usize>,
+    /// Buffer of each in-flight chain of the transmit queue, by head descriptor
+    tx_slots: Vec<Option<usize>>,
+    /// Transmit buffers not in use by the device
+    tx_free: Vec<usize>,
+    header_len: usize,
+    mac_address: MacAddress,
+}
+
+impl VirtioNet {
+    fn new(device: &pci::Device) -> DriverResult<Self> {
+        let mut transport = Transport::new(device)?;
+        // Mergeable receive buffers are left out
+        let features = transport.init(net::F_MAC)?;
+        if features & net::F_MAC == 0 {
+            video::warn!("Virtio network device does not have a MAC address");
+            return Err(DriverError::Invalid);
+        }
+
+        let rx_queue = transport.setup_queue(net::RX_QUEUE)?;
+        let tx_queue = transport.setup_queue(net::TX_QUEUE)?;
+
+        let rx_buffers = DmaBuffer::new(RX_BUFFERS * BUFFER_SIZE, Alignment::Align4K)
+            .ok_or(DriverError::Unknown)?;
+        let tx_buffers = DmaBuffer::new(TX_BUFFERS * BUFFER_SIZE, Alignment::Align4K)
+            .ok_or(DriverError::Unknown)?;
+
+        let mac_address = MacAddress::new(core::array::from_fn(|i| {
+            transport.read_config_u8(net::CONFIG_MAC + i)
+        }));
+
+        let mut virtio_net = Self {
+            rx_slots: alloc::vec![0; usize::from(rx_queue.size())],
+            tx_slots: alloc::vec![None; usize::from(tx_queue.size())],
+            tx_free: (0..TX_BUFFERS).collect(),
+            transport,
+            rx_queue,
+            tx_queue,
+            rx_buffers,
+            tx_buffers,
+            header_len: net::header_len(features),
+            mac_address,
+        };
+
+        for slot in 0..RX_BUFFERS {
+            virtio_net.offer_rx(slot)?;
+        }
+        virtio_net.transport.driver_ok();
+        virtio_net.transport.notify(&virtio_net.rx_queue);
+
+        Ok(virtio_net)
+    }
+
+    /// Offers a receive buffer to the device, which must then be notified.
+    fn offer_rx(&mut self, slot: usize) -> DriverResult<()> {
+        let addr = self.rx_buffers.paddr().as_u64() + (slot * BUFFER_SIZE) as u64;
+        let head = self
+            .rx_queue
+            .push(&net::rx_chain(addr, BUFFER_SIZE))
+            .ok_or(DriverError::Invalid)?;
+        self.rx_slots[usize::from(head)] = slot;
+        Ok(())
+    }
+
+    /// Reclaims the transmit buffers the device is done with.
+    fn reclaim_tx(&mut self) {
+        while let Some((head, _)) = self.tx_queue.pop_used() {
+            if let Some(slot) = self.tx_slots[usize::from(head)].take() {
+                self.tx_free.push(slot);
+            }
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn mac_address(&self) -> MacAddress {
+        self.mac_address
+    }
+}
+
+impl Nic for VirtioNet {
+    fn poll_frame(&self) -> Option<&[u8]> {
+        let (head, written) = self.rx_queue.peek_used()?;
+        let slot = self.rx_slots[usize::from(head)];
+
+        // Safety: The device is done with the buffer, which is not offered again
+        // until the frame is consumed.
+        let buffer = unsafe {
+            core::slice::from_raw_parts(
+                self.rx_buffers.as_mut_ptr::<u8>().add(slot * BUFFER_SIZE),
+                BUFFER_SIZE,
+            )
+        };
+
+        // Invalid frames are skipped on `consume_frame()`
+        net::rx_frame(buffer, usize::try_from(written).unwrap(), self.header_len)
+    }
+
+    fn consume_frame(&mut self) {
+        let Some((head, _)) = self.rx_queue.pop_used() else {
+            return;
+        };
+        let slot = self.rx_slots[usize::from(head)];
+        if self.offer_rx(slot).is_ok() {
+            self.transport.notify(&self.rx_queue);
+        }
+    }
+
+    fn send_frame(&mut self, frame: &[u8]) {
+        if frame.is_empty() || self.header_len + frame.len() > BUFFER_SIZE {
+            video::warn!("Invalid frame size: {}", frame.len());
+            return;
+        }
+
+        self.reclaim_tx();
+        let Some(slot) = self.tx_free.pop() else {
+            video::warn!("No free TX buffer available");
+            return;
+        };
+
+        // Safety: The buffer is not in use by the device and fits the header and the frame.
+        unsafe {
+            let buffer = self.tx_buffers.as_mut_ptr::<u8>().add(slot * BUFFER_SIZE);
+            buffer.write_bytes(0, self.header_len);
+            core::ptr::copy_nonoverlapping(
+                frame.as_ptr(),
+                buffer.add(self.header_len),
+                frame.len(),
+            );
+        }
+
+        let addr = self.tx_buffers.paddr().as_u64() + (slot * BUFFER_SIZE) as u64;
+        let Some(head) = self
+            .tx_queue
+            .push(&net::tx_chain(addr, self.header_len, frame.len()))
+        else {
+            self.tx_free.push(slot);
+            video::warn!("No free TX descriptor available");
+            return;
+        };
+        self.tx_slots[usize::from(head)] = Some(slot);
+
+        self.transport.notify(&self.tx_queue);
+    }
+
+    fn mac_address(&self) -> MacAddress {
+        self.mac_address
+    }
+}
+
+pub fn with_virtio_net<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut VirtioNet) -> R,
+{
+    VIRTIO_NET.with_locked(f)
+}
+
+pub fn virtio_net_available() -> bool {
+    VIRTIO_NET.is_initialized()
+}
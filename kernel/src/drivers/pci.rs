@@ -1,6 +1,13 @@
 use crate::mem::page_alloc::pmap::PhysicalMapping;
-use ::pci::{LegacyPciHandler, PciExpressHandler, PciHandler};
-use beskar_core::{arch::paging::M2MiB, drivers::DriverResult};
+use ::pci::{
+    Device, DeviceEvent, LegacyPciHandler, PciExpressHandler, PciHandler, msi::Msi, msix::MsiX,
+};
+use alloc::vec::Vec;
+use beskar_core::{
+    arch::paging::{M2MiB, M4KiB},
+    drivers::DriverResult,
+};
+use beskar_hal::structures::InterruptStackFrame;
 use driver_api::DriverError;
 use hyperdrive::locks::mcs::{MUMcsLock, McsLock};
 
@@ -83,6 +90,22 @@ pub fn with_pci_handler<T, F: FnOnce(&mut dyn PciHandler) -> T>(f: F) -> T {
     }
 }
 
+/// Enumerates the PCI devices again, and returns which ones were added or removed.
+///
+/// Drivers of removed devices must stop using them.
+pub fn rescan() -> Vec<DeviceEvent> {
+    let events = with_pci_handler(|handler| handler.rescan());
+    for event in &events {
+        match event {
+            DeviceEvent::Added(device) => video::info!("PCI device added: {:?}", device.sbdf()),
+            DeviceEvent::Removed(device) => {
+                video::info!("PCI device removed: {:?}", device.sbdf());
+            }
+        }
+    }
+    events
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MsiHelper;
 
@@ -90,7 +113,86 @@ impl ::pci::MsiHelper for MsiHelper {
     fn get_lapic_info(core_id: usize) -> Option<(beskar_core::arch::PhysAddr, u8)> {
         let core_locals = crate::locals::get_specific_core_locals(core_id)?;
         let lapic_paddr = unsafe { core_locals.lapic().force_lock() }.paddr();
-        let lapic_id = core_locals.apic_id();
+        // MSI messages can only target 8-bit APIC IDs without interrupt remapping.
+        let lapic_id = u8::try_from(core_locals.apic_id()).ok()?;
         Some((lapic_paddr, lapic_id))
     }
 }
+
+enum MsiKind {
+    Msi(Msi<MsiHelper>),
+    MsiX(MsiX<PhysicalMapping<M4KiB>, MsiHelper>),
+}
+
+/// An MSI or MSI-X interrupt of a device, routed to a handler in the IDT.
+///
+/// Dropping it masks the interrupt and frees its vector.
+pub struct MsiInterrupt {
+    kind: MsiKind,
+    vector: u8,
+    core_id: usize,
+}
+
+impl MsiInterrupt {
+    #[must_use]
+    /// Routes the interrupts of a device to the given handler.
+    ///
+    /// A CPU index may be passed to bind the interrupt to a specific CPU core.
+    /// MSI-X is preferred over MSI.
+    ///
+    /// Returns `None` if the device supports neither.
+    pub fn new(
+        device: &Device,
+        handler: extern "x86-interrupt" fn(InterruptStackFrame),
+        core: Option<usize>,
+    ) -> Option<Self> {
+        let kind = with_pci_handler(|pci_handler| {
+            MsiX::new(pci_handler, device)
+                .map(MsiKind::MsiX)
+                .or_else(|| Msi::new(pci_handler, device).map(MsiKind::Msi))
+        })?;
+
+        let (vector, core_id) = crate::arch::interrupts::new_irq(handler, core);
+
+        with_pci_handler(|pci_handler| match &kind {
+            MsiKind::MsiX(msix) => {
+                msix.setup_int(vector, 0, core_id);
+                msix.enable(pci_handler);
+            }
+            MsiKind::Msi(msi) => {
+                msi.setup_int(vector, pci_handler, core_id);
+                msi.enable(pci_handler);
+            }
+        });
+
+        Some(Self {
+            kind,
+            vector,
+            core_id,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the IDT vector of the interrupt.
+    pub const fn vector(&self) -> u8 {
+        self.vector
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the CPU core the interrupt is delivered to.
+    pub const fn core_id(&self) -> usize {
+        self.core_id
+    }
+}
+
+impl Drop for MsiInterrupt {
+    fn drop(&mut self) {
+        match &self.kind {
+            MsiKind::MsiX(msix) => msix.mask(0),
+            MsiKind::Msi(msi) => with_pci_handler(|pci_handler| msi.disable(pci_handler)),
+        }
+        crate::arch::interrupts::free_irq(self.vector, self.core_id);
+    }
+}
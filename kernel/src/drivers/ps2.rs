@@ -411,6 +411,7 @@ impl<'a> Ps2Keyboard<'a> {
             (false, 0x33) => Some(KeyCode::Comma),
             (false, 0x34) => Some(KeyCode::Dot),
             (false, 0x35) => Some(KeyCode::Slash),
+            (false, 0x56) => Some(KeyCode::NonUsBackslash),
 
             (false, 0x1C) => Some(KeyCode::Enter),
             (false, 0x39) => Some(KeyCode::Space),
@@ -452,6 +453,7 @@ impl<'a> Ps2Keyboard<'a> {
             (false, 0x53) => Some(KeyCode::NumpadDot),
 
             (true, 0x1D) => Some(KeyCode::CtrlRight),
+            (true, 0x38) => Some(KeyCode::AltRight),
 
             (true, 0x48) => Some(KeyCode::ArrowUp),
             (true, 0x50) => Some(KeyCode::ArrowDown),
@@ -5,10 +5,12 @@ use beskar_core::drivers::{DriverError, DriverResult};
 
 pub mod ahci;
 pub mod nvme;
+pub mod virtio_blk;
 
 pub fn init() -> DriverResult<()> {
     let mut ahci_controllers = Vec::new();
     let mut nvme = Vec::new();
+    let mut virtio_blk = Vec::new();
 
     pci::with_pci_handler(|handler| {
         handler
@@ -17,7 +19,13 @@ pub fn init() -> DriverResult<()> {
             .filter(|device| device.csp().class() == ::pci::Class::MassStorage)
             .copied()
             .for_each(|d| {
-                if d.csp().subclass() == 0x06 && d.csp().prog_if() == 0x01 {
+                if d.vendor_id() == ::virtio::PCI_VENDOR_ID {
+                    if ::virtio::DeviceType::from_pci_id(d.id())
+                        == Some(::virtio::DeviceType::Block)
+                    {
+                        virtio_blk.push(d);
+                    }
+                } else if d.csp().subclass() == 0x06 && d.csp().prog_if() == 0x01 {
                     ahci_controllers.push(d);
                 } else if d.csp().subclass() == 0x08 && d.csp().prog_if() == 0x02 {
                     nvme.push(d);
@@ -27,8 +35,9 @@ pub fn init() -> DriverResult<()> {
 
     let ahci_res = ahci::init(&ahci_controllers);
     let nvme_res = nvme::init(&nvme);
+    let virtio_blk_res = virtio_blk::init(&virtio_blk);
 
-    if ahci_res.is_err() && nvme_res.is_err() {
+    if ahci_res.is_err() && nvme_res.is_err() && virtio_blk_res.is_err() {
         video::warn!("No storage controllers found");
         Err(DriverError::Absent)
     } else {
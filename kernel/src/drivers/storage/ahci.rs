@@ -1,19 +1,24 @@
 use crate::mem::page_alloc::pmap::PhysicalMapping;
 use ::pci::{Bar, Device};
+use alloc::vec::Vec;
 use beskar_core::{
     arch::{VirtAddr, paging::M4KiB},
     drivers::{DriverError, DriverResult},
 };
 use beskar_hal::paging::page_table::Flags;
+use hyperdrive::locks::mcs::MUMcsLock;
 
-mod command;
-mod fis;
-pub use fis::{AtaCommand, FisD2H, FisH2D, FisType};
+mod drive;
+pub use drive::AhciDrive;
 mod port;
 use port::AhciPort;
 mod registers;
 use registers::AhciRegisters;
 
+static AHCI_CONTROLLER: MUMcsLock<Ahci> = MUMcsLock::uninit();
+
+/// Size of the ABAR: generic host control registers followed by the registers of up to 32 ports
+const ABAR_SIZE: usize = 0x100 + 32 * 0x80;
 /// Timeout for controller operations (in iterations)
 const CONTROLLER_TIMEOUT: usize = 100_000_000;
 
@@ -39,15 +44,20 @@ pub fn init(ahci_controllers: &[Device]) -> DriverResult<()> {
 
     let ahci_paddr = bar.base_address();
     let flags = Flags::MMIO_SUITABLE;
-    let pmap =
-        PhysicalMapping::<M4KiB>::new(ahci_paddr, 256, flags).map_err(|_| DriverError::Unknown)?;
+    let pmap = PhysicalMapping::<M4KiB>::new(ahci_paddr, ABAR_SIZE, flags)
+        .map_err(|_| DriverError::Unknown)?;
 
     let ahci_base = pmap.translate(ahci_paddr).ok_or(DriverError::Unknown)?;
 
     let mut ahci = Ahci::new(ahci_base, pmap);
     ahci.initialize()?;
 
-    video::info!("AHCI controller initialized successfully");
+    video::info!(
+        "AHCI controller initialized with {} drive(s)",
+        ahci.drives.len()
+    );
+
+    AHCI_CONTROLLER.init(ahci);
 
     Ok(())
 }
@@ -58,6 +68,8 @@ pub struct Ahci {
     pmap: PhysicalMapping,
     /// Number of ports supported by this controller
     port_count: u32,
+    /// SATA drives attached to the controller
+    drives: Vec<AhciDrive>,
 }
 
 impl Ahci {
@@ -76,6 +88,7 @@ impl Ahci {
             base,
             pmap,
             port_count,
+            drives: Vec::new(),
         }
     }
 
@@ -102,13 +115,13 @@ impl Ahci {
         regs.set_ghc(ghc | 0x2);
 
         // Detect and initialize ports
-        self.probe_ports()?;
+        self.probe_ports();
 
         Ok(())
     }
 
     /// Probe all AHCI ports and initialize any attached drives
-    fn probe_ports(&self) -> DriverResult<()> {
+    fn probe_ports(&mut self) {
         let regs = unsafe { AhciRegisters::from_base(self.base) };
         let ports_implemented = regs.ports_implemented();
 
@@ -122,13 +135,38 @@ impl Ahci {
             let port_addr = self.base + port_offset;
             let port = AhciPort::new(port_addr, port_idx);
 
-            // Check if a device is present
-            if port.is_device_present() {
-                port.initialize()?;
-                video::debug!("AHCI port {} initialized with device", port_idx);
+            // Check if a SATA drive is present
+            if !port.is_device_present() || !port.is_sata() {
+                continue;
+            }
+            match AhciDrive::new(port) {
+                Ok(drive) => {
+                    video::debug!(
+                        "AHCI port {} initialized with a drive of {} sectors",
+                        port_idx,
+                        drive.sectors()
+                    );
+                    self.drives.push(drive);
+                }
+                Err(err) => {
+                    video::warn!("AHCI port {} drive failed to initialize: {}", port_idx, err);
+                }
             }
         }
+    }
 
-        Ok(())
+    #[must_use]
+    #[inline]
+    /// SATA drives attached to the controller
+    pub fn drives(&mut self) -> &mut [AhciDrive] {
+        &mut self.drives
     }
 }
+
+#[inline]
+pub fn with_ahci_controller<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&mut Ahci) -> R,
+{
+    AHCI_CONTROLLER.with_locked_if_init(f)
+}
@@ -0,0 +1,141 @@
+//! AHCI Drives
+//!
+//! SATA drives attached to an AHCI port, accessed one command at a time.
+
+use super::port::{AhciPort, PortMemory};
+use crate::mem::dma::DmaBuffer;
+use beskar_core::{
+    arch::Alignment,
+    drivers::{
+        DriverError, DriverResult,
+        ahci::{IdentifyData, command::AhciCommand},
+    },
+    storage::{BlockDevice, BlockDeviceError},
+};
+
+/// Size of the buffer data is transferred through
+const DATA_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A SATA drive attached to an AHCI port
+pub struct AhciDrive {
+    port: AhciPort,
+    memory: PortMemory,
+    /// DMA buffer data is transferred through
+    buffer: DmaBuffer,
+    /// Number of sectors of the drive
+    sectors: u64,
+}
+
+impl AhciDrive {
+    /// Initialize the port and identify the attached drive
+    pub fn new(port: AhciPort) -> DriverResult<Self> {
+        let memory = port.initialize()?;
+        let buffer =
+            DmaBuffer::new(DATA_BUFFER_SIZE, Alignment::Align2).ok_or(DriverError::Unknown)?;
+
+        port.issue(
+            &memory,
+            &AhciCommand::identify_device(),
+            false,
+            &buffer,
+            u32::try_from(size_of::<IdentifyData>()).unwrap(),
+        )
+        .map_err(|_| DriverError::Invalid)?;
+        // Safety: The device has just written the identify data to the buffer.
+        let identify = unsafe { buffer.as_mut_ptr::<IdentifyData>().read() };
+
+        if !identify.supports_lba48() || identify.sector_size() as usize != Self::BLOCK_SIZE {
+            video::warn!("AHCI port {} drive is not supported", port.id());
+            return Err(DriverError::Invalid);
+        }
+
+        Ok(Self {
+            port,
+            memory,
+            buffer,
+            sectors: identify.sectors(),
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the number of sectors of the drive
+    pub const fn sectors(&self) -> u64 {
+        self.sectors
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the ID of the port the drive is attached to
+    pub const fn port_id(&self) -> u32 {
+        self.port.id()
+    }
+
+    /// Check that an access of `len` bytes at sector `offset` fits in the drive
+    fn check_access(&self, len: usize, offset: usize) -> Result<(), BlockDeviceError> {
+        if !len.is_multiple_of(Self::BLOCK_SIZE) {
+            return Err(BlockDeviceError::UnalignedAccess);
+        }
+        let end = u64::try_from(offset + len / Self::BLOCK_SIZE).unwrap();
+        if end > self.sectors {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for AhciDrive {
+    const BLOCK_SIZE: usize = 512;
+
+    fn read(&mut self, dst: &mut [u8], offset: usize) -> Result<(), BlockDeviceError> {
+        self.check_access(dst.len(), offset)?;
+
+        for (i, chunk) in dst.chunks_mut(DATA_BUFFER_SIZE).enumerate() {
+            let lba = offset + i * (DATA_BUFFER_SIZE / Self::BLOCK_SIZE);
+            let count = u16::try_from(chunk.len() / Self::BLOCK_SIZE).unwrap();
+            self.port.issue(
+                &self.memory,
+                &AhciCommand::read_dma_ext(lba as u64, count),
+                false,
+                &self.buffer,
+                u32::try_from(chunk.len()).unwrap(),
+            )?;
+            // Safety: The device has just written `chunk.len()` bytes to the buffer.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    self.buffer.as_mut_ptr::<u8>(),
+                    chunk.as_mut_ptr(),
+                    chunk.len(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, src: &[u8], offset: usize) -> Result<(), BlockDeviceError> {
+        self.check_access(src.len(), offset)?;
+
+        for (i, chunk) in src.chunks(DATA_BUFFER_SIZE).enumerate() {
+            let lba = offset + i * (DATA_BUFFER_SIZE / Self::BLOCK_SIZE);
+            let count = u16::try_from(chunk.len() / Self::BLOCK_SIZE).unwrap();
+            // Safety: The buffer is `DATA_BUFFER_SIZE` bytes long and not in use by the device.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    chunk.as_ptr(),
+                    self.buffer.as_mut_ptr::<u8>(),
+                    chunk.len(),
+                );
+            }
+            self.port.issue(
+                &self.memory,
+                &AhciCommand::write_dma_ext(lba as u64, count),
+                true,
+                &self.buffer,
+                u32::try_from(chunk.len()).unwrap(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -3,14 +3,49 @@
 //! Each port represents a single SATA device connection.
 
 use super::registers::{PortRegisters, SataDet};
+use crate::mem::dma::DmaBuffer;
 use beskar_core::{
-    arch::VirtAddr,
-    drivers::{DriverError, DriverResult},
+    arch::{Alignment, VirtAddr},
+    drivers::{
+        DriverError, DriverResult,
+        ahci::{
+            check_task_file,
+            command::{AhciCommand, CommandHeader, CommandTable, PrdTableEntry, ReceivedFis},
+        },
+    },
+    storage::BlockDeviceError,
 };
 
 /// Timeout for port operations (in iterations)
 pub const PORT_TIMEOUT: usize = 1_000_000_000;
 
+/// Signature of a SATA drive
+const SATA_SIGNATURE: u32 = 0x0000_0101;
+
+/// Command: Start
+const CMD_ST: u32 = 1 << 0;
+/// Command: FIS Receive Enable
+const CMD_FRE: u32 = 1 << 4;
+/// Command: FIS Receive Running
+const CMD_FR: u32 = 1 << 14;
+/// Command: Command List Running
+const CMD_CR: u32 = 1 << 15;
+
+/// Task file status: Data Transfer Requested
+const TFD_DRQ: u32 = 1 << 3;
+/// Task file status: Busy
+const TFD_BSY: u32 = 1 << 7;
+
+/// Interrupt status: Task File Error Status
+const IS_TFES: u32 = 1 << 30;
+
+/// Offset of the received FIS in the port memory
+const FB_OFFSET: usize = 0x400;
+/// Offset of the command table of slot 0 in the port memory
+const CT_OFFSET: usize = 0x500;
+/// Size of the port memory: the command list, the received FIS and a single command table
+const PORT_MEMORY_SIZE: usize = CT_OFFSET + size_of::<CommandTable>();
+
 /// Represents a single AHCI port with an attached device
 pub struct AhciPort {
     regs: PortRegisters,
@@ -35,8 +70,24 @@ impl AhciPort {
         matches!(det, SataDet::DevicePresent | SataDet::DevicePresentComm)
     }
 
+    #[must_use]
+    #[inline]
+    /// Check if the attached device is a SATA drive (rather than e.g. an ATAPI device)
+    pub fn is_sata(&self) -> bool {
+        self.regs.sig() == SATA_SIGNATURE
+    }
+
     /// Initialize the AHCI port
-    pub fn initialize(&self) -> DriverResult<()> {
+    ///
+    /// The returned memory holds the command list and received FIS of the port,
+    /// and must live as long as the command engine runs.
+    pub fn initialize(&self) -> DriverResult<PortMemory> {
+        self.stop_command_engine()?;
+
+        let memory = PortMemory::new().ok_or(DriverError::Unknown)?;
+        self.regs.set_clb(memory.command_list_paddr());
+        self.regs.set_fb(memory.received_fis_paddr());
+
         // Clear any pending errors
         let sata_error = self.regs.sata_error();
         if sata_error != 0 {
@@ -80,28 +131,23 @@ impl AhciPort {
             self.regs.sata_status()
         );
 
-        Ok(())
+        Ok(memory)
     }
 
     /// Start the port's command engine
     fn start_command_engine(&self) -> DriverResult<()> {
-        // Clear command list base address first
-        self.regs.set_clb(0);
-        self.regs.set_fb(0);
-
-        let mut cmd = self.regs.cmd();
+        // The command list must not be running anymore
+        self.wait_cmd_clear(CMD_CR)?;
 
-        // Set start (ST) and FIS receive enable (FRE) bits
-        cmd |= 0x0001; // ST
-        cmd |= 0x0010; // FRE
-
-        self.regs.set_cmd(cmd);
+        // FIS receive must be enabled before the command list is started
+        self.regs.set_cmd(self.regs.cmd() | CMD_FRE);
+        self.regs.set_cmd(self.regs.cmd() | CMD_ST);
 
         // Verify command engine started
         let mut timeout = PORT_TIMEOUT;
         loop {
             let cmd = self.regs.cmd();
-            if (cmd & 0x0001) != 0 {
+            if (cmd & CMD_ST) != 0 {
                 break;
             }
 
@@ -117,32 +163,85 @@ impl AhciPort {
 
     /// Stop the port's command engine
     pub fn stop_command_engine(&self) -> DriverResult<()> {
-        let mut cmd = self.regs.cmd();
+        self.regs.set_cmd(self.regs.cmd() & !CMD_ST);
+        self.wait_cmd_clear(CMD_CR)?;
 
-        // Clear start (ST) and FIS receive enable (FRE) bits
-        cmd &= !0x0001; // Clear ST
-        cmd &= !0x0010; // Clear FRE
+        self.regs.set_cmd(self.regs.cmd() & !CMD_FRE);
+        self.wait_cmd_clear(CMD_FR)?;
 
-        self.regs.set_cmd(cmd);
+        Ok(())
+    }
 
-        // Verify command engine stopped
+    /// Wait for bits of the command register to be cleared by the controller
+    fn wait_cmd_clear(&self, bits: u32) -> DriverResult<()> {
         let mut timeout = PORT_TIMEOUT;
-        loop {
-            let cmd = self.regs.cmd();
-            if (cmd & 0x0001) == 0 {
-                break;
-            }
-
+        while self.regs.cmd() & bits != 0 {
             timeout -= 1;
             if timeout == 0 {
                 video::warn!("AHCI port {} command engine stop timeout", self.port_id);
                 return Err(DriverError::Unknown);
             }
         }
-
         Ok(())
     }
 
+    /// Issue a command in slot 0 and wait for its completion
+    ///
+    /// `len` bytes are transferred between the device and the data buffer at `data`.
+    /// Commands are issued one at a time, so slot 0 is the only one ever used.
+    pub fn issue(
+        &self,
+        memory: &PortMemory,
+        command: &AhciCommand,
+        write: bool,
+        data: &DmaBuffer,
+        len: u32,
+    ) -> Result<(), BlockDeviceError> {
+        let mut timeout = PORT_TIMEOUT;
+        while self.regs.tfd() & (TFD_BSY | TFD_DRQ) != 0 {
+            timeout -= 1;
+            if timeout == 0 {
+                video::warn!("AHCI port {} is busy", self.port_id);
+                return Err(BlockDeviceError::Io);
+            }
+        }
+
+        let prd = PrdTableEntry::new(data.paddr().as_u64(), len, false);
+        memory.write_slot(
+            CommandHeader::new(memory.command_table_paddr(), 1, write),
+            CommandTable::new(command, prd),
+        );
+
+        self.regs.set_is(u32::MAX);
+        self.regs.set_ci(1);
+
+        let mut timeout = PORT_TIMEOUT;
+        while self.regs.ci() & 1 != 0 {
+            if self.regs.is() & IS_TFES != 0 {
+                break;
+            }
+            timeout -= 1;
+            if timeout == 0 {
+                video::warn!("AHCI port {} command timeout", self.port_id);
+                self.recover();
+                return Err(BlockDeviceError::Io);
+            }
+        }
+
+        let res = check_task_file(self.regs.tfd());
+        if res.is_err() {
+            self.recover();
+        }
+        res
+    }
+
+    /// Restart the command engine after an error, as the controller stops processing commands
+    fn recover(&self) {
+        let _ = self.stop_command_engine();
+        self.clear_errors();
+        let _ = self.start_command_engine();
+    }
+
     #[must_use]
     #[inline]
     /// Get the port ID
@@ -188,141 +287,52 @@ impl AhciPort {
     }
 }
 
-/// Port command list entry header
-#[repr(C, packed)]
-pub struct CommandHeader {
-    /// Bit 0: Command FIS length (in DWORDs)
-    /// Bits 5: Write (1=Host to device)
-    /// Bits 10-15: Port multiplier port
-    pub cmd_fis_len_flags: u16,
-    /// Physical region descriptor table length
-    pub prdt_len: u16,
-    /// Physical region descriptor byte count
-    pub prd_byte_count: u32,
-    /// Command table base address (lower 32-bits)
-    pub ctba_low: u32,
-    /// Command table base address (upper 32-bits)
-    pub ctba_high: u32,
-    _reserved: [u32; 4],
-}
-
-impl CommandHeader {
-    #[must_use]
-    #[inline]
-    /// Get FIS length in DWORDs
-    pub const fn fis_length(&self) -> u8 {
-        (self.cmd_fis_len_flags & 0x1F) as u8
-    }
-
-    #[inline]
-    /// Set FIS length in DWORDs
-    pub const fn set_fis_length(&mut self, len: u8) {
-        self.cmd_fis_len_flags = (self.cmd_fis_len_flags & !0x1F) | (len as u16);
-    }
-
-    #[must_use]
-    #[inline]
-    /// Check if this is a write (host to device)
-    pub const fn is_write(&self) -> bool {
-        (self.cmd_fis_len_flags & (1 << 6)) != 0
-    }
-
-    #[inline]
-    /// Set write flag
-    pub const fn set_write(&mut self, write: bool) {
-        if write {
-            self.cmd_fis_len_flags |= 1 << 6;
-        } else {
-            self.cmd_fis_len_flags &= !(1 << 6);
-        }
-    }
+/// Memory shared between a port and the host
+///
+/// It holds the command list, the received FIS and the command table of slot 0.
+pub struct PortMemory(DmaBuffer);
 
+impl PortMemory {
     #[must_use]
-    #[inline]
-    /// Get command table address (48-bit physical address)
-    pub const fn ctba(&self) -> u64 {
-        ((self.ctba_high as u64) << 32) | (self.ctba_low as u64)
-    }
+    fn new() -> Option<Self> {
+        const _: () = assert!(FB_OFFSET.is_multiple_of(256) && CT_OFFSET.is_multiple_of(128));
+        const _: () = assert!(CT_OFFSET - FB_OFFSET >= size_of::<ReceivedFis>());
 
-    #[inline]
-    /// Set command table address
-    pub const fn set_ctba(&mut self, addr: u64) {
-        self.ctba_low = (addr & 0xFFFF_FFFF) as u32;
-        self.ctba_high = ((addr >> 32) & 0xFFFF_FFFF) as u32;
+        // The command list must be aligned on 1 KiB
+        DmaBuffer::new(PORT_MEMORY_SIZE, Alignment::Align1K).map(Self)
     }
-}
-
-/// Received FIS structure (typically 256 bytes per port)
-#[repr(C, packed)]
-pub struct ReceivedFis {
-    pub dma_setup: [u8; 28],
-    _pad1: [u8; 4],
-    pub pio_setup: [u8; 20],
-    _pad2: [u8; 4],
-    pub d2h_register: [u8; 20],
-    _pad3: [u8; 4],
-    pub set_device_bits: [u8; 8],
-    pub unknown_fis: [u8; 64],
-    _reserved: [u8; 96],
-}
 
-/// Physical Region Descriptor Table entry
-#[repr(C, packed)]
-pub struct PrdTableEntry {
-    /// Data base address (lower 32-bits)
-    pub dba_low: u32,
-    /// Data base address (upper 32-bits)
-    pub dba_high: u32,
-    _reserved: u32,
-    /// Bits 21-0: byte count (0 means 4MB)
-    /// Bit 31: Interrupt on completion
-    pub dbc_ioc: u32,
-}
-
-impl PrdTableEntry {
     #[must_use]
     #[inline]
-    /// Get data buffer address (48-bit physical address)
-    pub const fn dba(&self) -> u64 {
-        ((self.dba_high as u64) << 32) | (self.dba_low as u64)
-    }
-
-    #[inline]
-    /// Set data buffer address
-    pub fn set_dba(&mut self, addr: u64) {
-        self.dba_low = u32::try_from(addr & 0xFFFF_FFFF).unwrap();
-        self.dba_high = u32::try_from((addr >> 32) & 0xFFFF_FFFF).unwrap();
+    const fn command_list_paddr(&self) -> u64 {
+        self.0.paddr().as_u64()
     }
 
     #[must_use]
     #[inline]
-    /// Get byte count
-    pub const fn byte_count(&self) -> u32 {
-        let bc = self.dbc_ioc & 0x3F_FFFF;
-        if bc == 0 { 0x40_0000 } else { bc }
-    }
-
-    #[inline]
-    /// Set byte count
-    pub fn set_byte_count(&mut self, count: u32) {
-        let count = count.min(0x40_0000);
-        self.dbc_ioc = (self.dbc_ioc & 0xFFC0_0000) | (count & 0x3F_FFFF);
+    const fn received_fis_paddr(&self) -> u64 {
+        self.0.paddr().as_u64() + FB_OFFSET as u64
     }
 
     #[must_use]
     #[inline]
-    /// Check interrupt on completion flag
-    pub const fn ioc(&self) -> bool {
-        (self.dbc_ioc & (1 << 31)) != 0
+    const fn command_table_paddr(&self) -> u64 {
+        self.0.paddr().as_u64() + CT_OFFSET as u64
     }
 
-    #[inline]
-    /// Set interrupt on completion flag
-    pub const fn set_ioc(&mut self, ioc: bool) {
-        if ioc {
-            self.dbc_ioc |= 1 << 31;
-        } else {
-            self.dbc_ioc &= !(1 << 31);
+    /// Write the command header and table of slot 0
+    fn write_slot(&self, header: CommandHeader, table: CommandTable) {
+        // Safety: The buffer is large enough to hold the command list and the command table,
+        // and the controller does not access slot 0 while it is not issued.
+        unsafe {
+            self.0.as_mut_ptr::<CommandHeader>().write(header);
+            self.0
+                .as_mut_ptr::<u8>()
+                .add(CT_OFFSET)
+                .cast::<CommandTable>()
+                .write(table);
         }
+        // The slot must be written before it is issued
+        core::sync::atomic::fence(core::sync::atomic::Ordering::Release);
     }
 }
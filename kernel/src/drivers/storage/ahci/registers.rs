@@ -332,6 +332,13 @@ impl PortRegisters {
         self.read_u32(0x20)
     }
 
+    #[must_use]
+    #[inline]
+    /// Signature (0x24)
+    pub fn sig(&self) -> u32 {
+        self.read_u32(0x24)
+    }
+
     #[must_use]
     #[inline]
     /// Serial ATA Status (0x28)
@@ -393,8 +393,10 @@ impl NvmeControllers {
 }
 
 extern "x86-interrupt" fn nvme_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    video::debug!("NVMe INTERRUPT on core {}", locals!().core_id());
-    unsafe { locals!().lapic().force_lock() }.send_eoi();
+    crate::arch::interrupts::in_handler(|| {
+        video::debug!("NVMe INTERRUPT on core {}", locals!().core_id());
+        unsafe { locals!().lapic().force_lock() }.send_eoi();
+    });
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
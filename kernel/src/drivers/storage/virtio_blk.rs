@@ -0,0 +1,205 @@
+//! Virtio block devices, one request at a time.
+
+use crate::drivers::virtio::{Queue, Transport};
+use crate::mem::dma::DmaBuffer;
+use ::pci::Device;
+use beskar_core::{
+    arch::Alignment,
+    drivers::{DriverError, DriverResult},
+    storage::{BlockDevice, BlockDeviceError},
+};
+use hyperdrive::locks::mcs::MUMcsLock;
+use virtio::blk::{self, RequestHeader, RequestType};
+
+static VIRTIO_BLK: MUMcsLock<VirtioBlk> = MUMcsLock::uninit();
+
+/// Size of the buffer data is transferred through
+const DATA_BUFFER_SIZE: usize = 64 * 1024;
+/// Offset of the status byte in the request buffer, right after the header
+const STATUS_OFFSET: usize = size_of::<RequestHeader>();
+
+pub fn init(devices: &[Device]) -> DriverResult<()> {
+    if devices.len() > 1 {
+        video::warn!("Multiple virtio block devices found, using the first one");
+    }
+    let Some(device) = devices.first() else {
+        return Err(DriverError::Absent);
+    };
+
+    let blk = VirtioBlk::new(device)?;
+
+    video::info!(
+        "Virtio block device initialized with {} sectors",
+        blk.capacity()
+    );
+
+    VIRTIO_BLK.init(blk);
+
+    Ok(())
+}
+
+pub struct VirtioBlk {
+    transport: Transport,
+    /// Request queue
+    queue: Queue,
+    /// Header and status of the request
+    request: DmaBuffer,
+    /// DMA buffer data is transferred through
+    buffer: DmaBuffer,
+    /// Number of sectors of the device
+    capacity: u64,
+    read_only: bool,
+}
+
+impl VirtioBlk {
+    fn new(device: &Device) -> DriverResult<Self> {
+        let mut transport = Transport::new(device)?;
+        let features = transport.init(blk::F_RO)?;
+        let queue = transport.setup_queue(0)?;
+        transport.driver_ok();
+
+        let capacity = transport.read_config_u64(blk::CONFIG_CAPACITY);
+
+        let request =
+            DmaBuffer::new(STATUS_OFFSET + 1, Alignment::Align16).ok_or(DriverError::Unknown)?;
+        let buffer =
+            DmaBuffer::new(DATA_BUFFER_SIZE, Alignment::Align16).ok_or(DriverError::Unknown)?;
+
+        Ok(Self {
+            transport,
+            queue,
+            request,
+            buffer,
+            capacity,
+            read_only: features & blk::F_RO != 0,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Get the number of sectors of the device
+    pub const fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Submit a request transferring `len` bytes of the data buffer, and wait for its completion
+    fn submit(
+        &mut self,
+        request_type: RequestType,
+        sector: u64,
+        len: usize,
+    ) -> Result<(), BlockDeviceError> {
+        let header = RequestHeader::new(request_type, sector).to_bytes();
+        // Safety: The request buffer holds the header and the status, and is not in use by the device.
+        unsafe {
+            self.request
+                .as_mut_ptr::<[u8; STATUS_OFFSET]>()
+                .write(header);
+            // The device overwrites this invalid status on completion
+            self.request
+                .as_mut_ptr::<u8>()
+                .add(STATUS_OFFSET)
+                .write_volatile(0xFF);
+        }
+
+        let request_paddr = self.request.paddr().as_u64();
+        let chain = blk::request_chain(
+            request_type,
+            request_paddr,
+            self.buffer.paddr().as_u64(),
+            u32::try_from(len).unwrap(),
+            request_paddr + STATUS_OFFSET as u64,
+        );
+        let head = self.queue.push(&chain).ok_or(BlockDeviceError::Io)?;
+        self.transport.notify(&self.queue);
+
+        // Requests are submitted one at a time, so the next used chain is this one
+        let (id, _written) = loop {
+            if let Some(used) = self.queue.pop_used() {
+                break used;
+            }
+            core::hint::spin_loop();
+        };
+        debug_assert_eq!(id, head);
+
+        // Safety: The device has completed the request.
+        let status = unsafe {
+            self.request
+                .as_mut_ptr::<u8>()
+                .add(STATUS_OFFSET)
+                .read_volatile()
+        };
+        blk::check_status(status)
+    }
+
+    /// Check that an access of `len` bytes at sector `offset` fits in the device
+    fn check_access(&self, len: usize, offset: usize) -> Result<(), BlockDeviceError> {
+        if !len.is_multiple_of(Self::BLOCK_SIZE) {
+            return Err(BlockDeviceError::UnalignedAccess);
+        }
+        let end = u64::try_from(offset + len / Self::BLOCK_SIZE).unwrap();
+        if end > self.capacity {
+            return Err(BlockDeviceError::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for VirtioBlk {
+    const BLOCK_SIZE: usize = blk::SECTOR_SIZE;
+
+    fn read(&mut self, dst: &mut [u8], offset: usize) -> Result<(), BlockDeviceError> {
+        self.check_access(dst.len(), offset)?;
+
+        for (i, chunk) in dst.chunks_mut(DATA_BUFFER_SIZE).enumerate() {
+            let sector = offset + i * (DATA_BUFFER_SIZE / Self::BLOCK_SIZE);
+            self.submit(RequestType::In, sector as u64, chunk.len())?;
+            // Safety: The device has just written `chunk.len()` bytes to the buffer.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    self.buffer.as_mut_ptr::<u8>(),
+                    chunk.as_mut_ptr(),
+                    chunk.len(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, src: &[u8], offset: usize) -> Result<(), BlockDeviceError> {
+        if self.read_only {
+            return Err(BlockDeviceError::Unsupported);
+        }
+        self.check_access(src.len(), offset)?;
+
+        for (i, chunk) in src.chunks(DATA_BUFFER_SIZE).enumerate() {
+            let sector = offset + i * (DATA_BUFFER_SIZE / Self::BLOCK_SIZE);
+            // Safety: The buffer is `DATA_BUFFER_SIZE` bytes long and not in use by the device.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    chunk.as_ptr(),
+                    self.buffer.as_mut_ptr::<u8>(),
+                    chunk.len(),
+                );
+            }
+            self.submit(RequestType::Out, sector as u64, chunk.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[inline]
+pub fn with_virtio_blk<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&mut VirtioBlk) -> R,
+{
+    VIRTIO_BLK.with_locked_if_init(f)
+}
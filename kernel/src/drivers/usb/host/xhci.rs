@@ -235,9 +235,11 @@ impl Xhci {
 }
 
 extern "x86-interrupt" fn xhci_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    video::info!("xHCI INTERRUPT on core {}", locals!().core_id());
-    handle_xhci_interrupt();
-    unsafe { locals!().lapic().force_lock() }.send_eoi();
+    crate::arch::interrupts::in_handler(|| {
+        video::info!("xHCI INTERRUPT on core {}", locals!().core_id());
+        handle_xhci_interrupt();
+        unsafe { locals!().lapic().force_lock() }.send_eoi();
+    });
 }
 
 pub const fn handle_xhci_interrupt() {
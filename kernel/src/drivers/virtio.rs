@@ -0,0 +1,199 @@
+//! Virtio PCI transports.
+//!
+//! Transitional devices expose the legacy interface through I/O ports in BAR0,
+//! and modern devices expose the modern interface through vendor-specific PCI capabilities.
+//! The modern interface is preferred when both are available.
+
+use crate::mem::dma::DmaBuffer;
+use ::pci::Device;
+use beskar_core::{
+    arch::Alignment,
+    drivers::{DriverError, DriverResult},
+};
+use core::ptr::NonNull;
+use virtio::{
+    F_VERSION_1,
+    queue::{Buffer, LEGACY_ALIGN, QueueLayout, VirtQueue},
+    status,
+};
+
+mod legacy;
+use legacy::LegacyTransport;
+mod modern;
+use modern::ModernTransport;
+
+/// Largest queue the drivers use, to bound the memory of modern devices' queues.
+const MAX_QUEUE_SIZE: u16 = 256;
+
+pub enum Transport {
+    Legacy(LegacyTransport),
+    Modern(ModernTransport),
+}
+
+impl Transport {
+    /// Detects the interface of a virtio device.
+    pub fn new(device: &Device) -> DriverResult<Self> {
+        crate::drivers::pci::with_pci_handler(|handler| handler.enable_bus_master(device));
+
+        ModernTransport::new(device)?.map_or_else(
+            || {
+                video::debug!("Using the legacy virtio interface");
+                LegacyTransport::new(device).map(Self::Legacy)
+            },
+            |modern| {
+                video::debug!("Using the modern virtio interface");
+                Ok(Self::Modern(modern))
+            },
+        )
+    }
+
+    /// Resets the device and negotiates features.
+    ///
+    /// The device is offered the `supported` features it also supports.
+    /// Returns the negotiated features.
+    pub fn init(&mut self, supported: u64) -> DriverResult<u64> {
+        self.set_status(0);
+        while self.status() != 0 {
+            core::hint::spin_loop();
+        }
+        self.set_status(status::ACKNOWLEDGE);
+        self.set_status(status::ACKNOWLEDGE | status::DRIVER);
+
+        let features = match self {
+            Self::Legacy(legacy) => {
+                let features = legacy.device_features() & supported & !F_VERSION_1;
+                legacy.set_driver_features(features);
+                features
+            }
+            Self::Modern(modern) => {
+                let device_features = modern.device_features();
+                if device_features & F_VERSION_1 == 0 {
+                    modern.set_status(status::FAILED);
+                    return Err(DriverError::Invalid);
+                }
+                let features = device_features & (supported | F_VERSION_1);
+                modern.set_driver_features(features);
+
+                modern.set_status(status::ACKNOWLEDGE | status::DRIVER | status::FEATURES_OK);
+                if modern.status() & status::FEATURES_OK == 0 {
+                    modern.set_status(status::FAILED);
+                    return Err(DriverError::Invalid);
+                }
+                features
+            }
+        };
+
+        Ok(features)
+    }
+
+    /// Tells the device that the driver is ready, once its queues are set up.
+    pub fn driver_ok(&self) {
+        self.set_status(self.status() | status::DRIVER_OK);
+    }
+
+    /// Allocates and enables the queue of the given index.
+    pub fn setup_queue(&mut self, index: u16) -> DriverResult<Queue> {
+        let size = match self {
+            Self::Legacy(legacy) => legacy.queue_size(index),
+            Self::Modern(modern) => modern.queue_size(index).min(MAX_QUEUE_SIZE),
+        };
+        if !size.is_power_of_two() {
+            return Err(DriverError::Invalid);
+        }
+        let layout = QueueLayout::new(size);
+
+        let memory =
+            DmaBuffer::new(layout.total_size(), Alignment::Align4K).ok_or(DriverError::Unknown)?;
+        const { assert!(LEGACY_ALIGN == Alignment::Align4K as usize) };
+
+        match self {
+            Self::Legacy(legacy) => legacy.setup_queue(index, memory.paddr()),
+            Self::Modern(modern) => modern.setup_queue(index, layout, memory.paddr()),
+        }
+
+        // Safety: The buffer is zeroed, page-aligned, large enough, and owned by the queue.
+        let queue =
+            unsafe { VirtQueue::new(NonNull::new(memory.as_mut_ptr::<u8>()).unwrap(), layout) };
+
+        Ok(Queue {
+            index,
+            virtqueue: queue,
+            _memory: memory,
+        })
+    }
+
+    /// Notifies the device that new buffers are available in a queue.
+    pub fn notify(&self, queue: &Queue) {
+        match self {
+            Self::Legacy(legacy) => legacy.notify(queue.index),
+            Self::Modern(modern) => modern.notify(queue.index),
+        }
+    }
+
+    #[must_use]
+    /// Reads a byte of the device-specific configuration.
+    pub fn read_config_u8(&self, offset: usize) -> u8 {
+        match self {
+            Self::Legacy(legacy) => legacy.read_config_u8(offset),
+            Self::Modern(modern) => modern.read_config_u8(offset),
+        }
+    }
+
+    #[must_use]
+    /// Reads a 64-bit field of the device-specific configuration.
+    pub fn read_config_u64(&self, offset: usize) -> u64 {
+        let read_u32 = |offset| match self {
+            Self::Legacy(legacy) => legacy.read_config_u32(offset),
+            Self::Modern(modern) => modern.read_config_u32(offset),
+        };
+        u64::from(read_u32(offset)) | (u64::from(read_u32(offset + 4)) << 32)
+    }
+
+    fn status(&self) -> u8 {
+        match self {
+            Self::Legacy(legacy) => legacy.status(),
+            Self::Modern(modern) => modern.status(),
+        }
+    }
+
+    fn set_status(&self, status: u8) {
+        match self {
+            Self::Legacy(legacy) => legacy.set_status(status),
+            Self::Modern(modern) => modern.set_status(status),
+        }
+    }
+}
+
+/// A virtqueue of a device, along with its memory.
+pub struct Queue {
+    index: u16,
+    virtqueue: VirtQueue,
+    _memory: DmaBuffer,
+}
+
+impl Queue {
+    #[inline]
+    /// Offers a chain of buffers to the device, which must then be notified.
+    pub fn push(&mut self, chain: &[Buffer]) -> Option<u16> {
+        self.virtqueue.push(chain)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the next chain the device is done with, without reclaiming it.
+    pub fn peek_used(&self) -> Option<(u16, u32)> {
+        self.virtqueue.peek_used()
+    }
+
+    #[inline]
+    /// Reclaims a chain the device is done with.
+    pub fn pop_used(&mut self) -> Option<(u16, u32)> {
+        self.virtqueue.pop_used()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn size(&self) -> u16 {
+        self.virtqueue.layout().size()
+    }
+}
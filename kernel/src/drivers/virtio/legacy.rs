@@ -0,0 +1,98 @@
+//! Legacy virtio interface, through I/O ports.
+
+use ::pci::{Bar, Device};
+use beskar_core::{
+    arch::PhysAddr,
+    drivers::{DriverError, DriverResult},
+};
+use beskar_hal::port::{self, Port, PortAccessible};
+
+/// Device features (32 bits)
+const DEVICE_FEATURES: u16 = 0x00;
+/// Driver features (32 bits)
+const DRIVER_FEATURES: u16 = 0x04;
+/// Page frame number of the selected queue (32 bits)
+const QUEUE_ADDRESS: u16 = 0x08;
+/// Size of the selected queue (16 bits)
+const QUEUE_SIZE: u16 = 0x0C;
+/// Queue selector (16 bits)
+const QUEUE_SELECT: u16 = 0x0E;
+/// Queue notifier (16 bits)
+const QUEUE_NOTIFY: u16 = 0x10;
+/// Device status (8 bits)
+const DEVICE_STATUS: u16 = 0x12;
+/// Device-specific configuration, when MSI-X is disabled
+const DEVICE_CONFIG: u16 = 0x14;
+
+pub struct LegacyTransport {
+    base: u16,
+}
+
+impl LegacyTransport {
+    pub fn new(device: &Device) -> DriverResult<Self> {
+        let Some(Bar::Io(bar)) =
+            crate::drivers::pci::with_pci_handler(|handler| handler.read_bar(device, 0))
+        else {
+            return Err(DriverError::Absent);
+        };
+        let base = u16::try_from(bar.base_address().as_u64()).map_err(|_| DriverError::Invalid)?;
+        Ok(Self { base })
+    }
+
+    fn read<T: PortAccessible>(&self, offset: u16) -> T {
+        // Safety: The port belongs to the device.
+        unsafe { Port::<T, port::ReadWrite>::new(self.base + offset).read() }
+    }
+
+    fn write<T: PortAccessible>(&self, offset: u16, value: T) {
+        // Safety: The port belongs to the device.
+        unsafe { Port::<T, port::ReadWrite>::new(self.base + offset).write(value) };
+    }
+
+    #[must_use]
+    /// The legacy interface only exposes the lower 32 feature bits.
+    pub fn device_features(&self) -> u64 {
+        u64::from(self.read::<u32>(DEVICE_FEATURES))
+    }
+
+    pub fn set_driver_features(&self, features: u64) {
+        self.write(DRIVER_FEATURES, u32::try_from(features).unwrap());
+    }
+
+    #[must_use]
+    pub fn status(&self) -> u8 {
+        self.read(DEVICE_STATUS)
+    }
+
+    pub fn set_status(&self, status: u8) {
+        self.write(DEVICE_STATUS, status);
+    }
+
+    #[must_use]
+    /// Size of a queue, which is imposed by the device.
+    pub fn queue_size(&self, index: u16) -> u16 {
+        self.write(QUEUE_SELECT, index);
+        self.read(QUEUE_SIZE)
+    }
+
+    /// Gives the device the address of a queue, which must be page-aligned.
+    pub fn setup_queue(&self, index: u16, paddr: PhysAddr) {
+        self.write(QUEUE_SELECT, index);
+        let pfn = u32::try_from(paddr.as_u64() >> 12).unwrap();
+        self.write(QUEUE_ADDRESS, pfn);
+    }
+
+    pub fn notify(&self, index: u16) {
+        self.write(QUEUE_NOTIFY, index);
+    }
+
+    #[must_use]
+    pub fn read_config_u8(&self, offset: usize) -> u8 {
+        self.read(DEVICE_CONFIG + u16::try_from(offset).unwrap())
+    }
+
+    #[must_use]
+    pub fn read_config_u32(&self, offset: usize) -> u32 {
+        self.read(DEVICE_CONFIG + u16::try_from(offset).unwrap())
+    }
+}
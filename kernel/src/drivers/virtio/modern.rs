@@ -0,0 +1,230 @@
+//! Modern virtio interface, through memory regions described by PCI capabilities.
+
+use crate::mem::page_alloc::pmap::PhysicalMapping;
+use ::pci::{Bar, CapabilityHeader, Device};
+use alloc::vec::Vec;
+use beskar_core::{
+    arch::{PhysAddr, VirtAddr, paging::M4KiB},
+    drivers::{DriverError, DriverResult},
+};
+use beskar_hal::paging::page_table::Flags;
+use core::ptr::{read_volatile, write_volatile};
+use virtio::{PciCapabilityType, queue::QueueLayout};
+
+/// Device feature selector (32 bits)
+const DEVICE_FEATURE_SELECT: usize = 0x00;
+/// Device features, as selected (32 bits)
+const DEVICE_FEATURE: usize = 0x04;
+/// Driver feature selector (32 bits)
+const DRIVER_FEATURE_SELECT: usize = 0x08;
+/// Driver features, as selected (32 bits)
+const DRIVER_FEATURE: usize = 0x0C;
+/// Device status (8 bits)
+const DEVICE_STATUS: usize = 0x14;
+/// Queue selector (16 bits)
+const QUEUE_SELECT: usize = 0x16;
+/// Size of the selected queue (16 bits)
+const QUEUE_SIZE: usize = 0x18;
+/// Whether the selected queue is enabled (16 bits)
+const QUEUE_ENABLE: usize = 0x1C;
+/// Offset of the notifier of the selected queue, in multiples of the notify multiplier (16 bits)
+const QUEUE_NOTIFY_OFF: usize = 0x1E;
+/// Address of the descriptor table of the selected queue (64 bits)
+const QUEUE_DESC: usize = 0x20;
+/// Address of the available ring of the selected queue (64 bits)
+const QUEUE_DRIVER: usize = 0x28;
+/// Address of the used ring of the selected queue (64 bits)
+const QUEUE_DEVICE: usize = 0x30;
+
+/// A vendor-specific capability describing a region of a BAR.
+struct Capability {
+    cfg_type: PciCapabilityType,
+    bar: u8,
+    offset: u32,
+    length: u32,
+    /// Only meaningful for the notify capability.
+    notify_off_multiplier: u32,
+}
+
+/// A mapped region of a BAR.
+struct Region {
+    base: VirtAddr,
+    _pmap: PhysicalMapping,
+}
+
+impl Region {
+    fn map(device: &Device, capability: &Capability) -> DriverResult<Self> {
+        let Some(Bar::Memory(bar)) = crate::drivers::pci::with_pci_handler(|handler| {
+            handler.read_bar(device, capability.bar)
+        }) else {
+            return Err(DriverError::Invalid);
+        };
+
+        let paddr = bar.base_address() + u64::from(capability.offset);
+        let length = usize::try_from(capability.length).unwrap();
+        let pmap = PhysicalMapping::<M4KiB>::new(paddr, length, Flags::MMIO_SUITABLE)
+            .map_err(|_| DriverError::Unknown)?;
+        let base = pmap.translate(paddr).ok_or(DriverError::Unknown)?;
+
+        Ok(Self { base, _pmap: pmap })
+    }
+
+    fn read<T: Copy>(&self, offset: usize) -> T {
+        unsafe { read_volatile(self.base.as_ptr::<T>().byte_add(offset)) }
+    }
+
+    fn write<T: Copy>(&self, offset: usize, value: T) {
+        unsafe { write_volatile(self.base.as_mut_ptr::<T>().byte_add(offset), value) };
+    }
+
+    /// 64-bit fields are written as two 32-bit halves, low half first.
+    fn write_u64(&self, offset: usize, value: u64) {
+        self.write(offset, u32::try_from(value & 0xFFFF_FFFF).unwrap());
+        self.write(offset + 4, u32::try_from(value >> 32).unwrap());
+    }
+}
+
+pub struct ModernTransport {
+    common: Region,
+    notify: Region,
+    notify_off_multiplier: u32,
+    device: Region,
+    /// Notifier offsets of the queues, by index
+    notify_offsets: Vec<u16>,
+}
+
+impl ModernTransport {
+    /// Maps the regions of the modern interface.
+    ///
+    /// Returns `None` if the device does not expose the modern interface.
+    pub fn new(device: &Device) -> DriverResult<Option<Self>> {
+        let capabilities = crate::drivers::pci::with_pci_handler(|handler| {
+            #[expect(
+                clippy::needless_collect,
+                reason = "The iterator borrows the handler, which reads the capabilities"
+            )]
+            let offsets = ::pci::iter_capabilities(handler, device)
+                .filter(|capability| capability.id() == CapabilityHeader::ID_VNDR)
+                .map(|capability| capability.offset())
+                .collect::<Vec<_>>();
+
+            offsets
+                .into_iter()
+                .filter_map(|offset| {
+                    let header = handler.read_config(device, offset)?;
+                    let cfg_type =
+                        PciCapabilityType::from_raw(u8::try_from(header >> 24).unwrap())?;
+                    let bar =
+                        u8::try_from(handler.read_config(device, offset + 4)? & 0xFF).unwrap();
+                    let notify_off_multiplier = if cfg_type == PciCapabilityType::Notify {
+                        handler.read_config(device, offset + 16)?
+                    } else {
+                        0
+                    };
+                    Some(Capability {
+                        cfg_type,
+                        bar,
+                        offset: handler.read_config(device, offset + 8)?,
+                        length: handler.read_config(device, offset + 12)?,
+                        notify_off_multiplier,
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // When a structure is described several times, the first capability is preferred
+        let find = |cfg_type| {
+            capabilities
+                .iter()
+                .find(|capability| capability.cfg_type == cfg_type)
+        };
+        let (Some(common), Some(notify), Some(device_cfg)) = (
+            find(PciCapabilityType::Common),
+            find(PciCapabilityType::Notify),
+            find(PciCapabilityType::Device),
+        ) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            common: Region::map(device, common)?,
+            notify: Region::map(device, notify)?,
+            notify_off_multiplier: notify.notify_off_multiplier,
+            device: Region::map(device, device_cfg)?,
+            notify_offsets: Vec::new(),
+        }))
+    }
+
+    #[must_use]
+    pub fn device_features(&self) -> u64 {
+        self.common.write(DEVICE_FEATURE_SELECT, 0_u32);
+        let low = self.common.read::<u32>(DEVICE_FEATURE);
+        self.common.write(DEVICE_FEATURE_SELECT, 1_u32);
+        let high = self.common.read::<u32>(DEVICE_FEATURE);
+        u64::from(low) | (u64::from(high) << 32)
+    }
+
+    pub fn set_driver_features(&self, features: u64) {
+        self.common.write(DRIVER_FEATURE_SELECT, 0_u32);
+        self.common.write(
+            DRIVER_FEATURE,
+            u32::try_from(features & 0xFFFF_FFFF).unwrap(),
+        );
+        self.common.write(DRIVER_FEATURE_SELECT, 1_u32);
+        self.common
+            .write(DRIVER_FEATURE, u32::try_from(features >> 32).unwrap());
+    }
+
+    #[must_use]
+    pub fn status(&self) -> u8 {
+        self.common.read(DEVICE_STATUS)
+    }
+
+    pub fn set_status(&self, status: u8) {
+        self.common.write(DEVICE_STATUS, status);
+    }
+
+    #[must_use]
+    /// Largest size of a queue, or 0 if the queue does not exist.
+    pub fn queue_size(&self, index: u16) -> u16 {
+        self.common.write(QUEUE_SELECT, index);
+        self.common.read(QUEUE_SIZE)
+    }
+
+    /// Gives the device the addresses of the parts of a queue, and enables it.
+    pub fn setup_queue(&mut self, index: u16, layout: QueueLayout, paddr: PhysAddr) {
+        self.common.write(QUEUE_SELECT, index);
+        self.common.write(QUEUE_SIZE, layout.size());
+        let base = paddr.as_u64();
+        self.common
+            .write_u64(QUEUE_DESC, base + layout.desc_offset() as u64);
+        self.common
+            .write_u64(QUEUE_DRIVER, base + layout.avail_offset() as u64);
+        self.common
+            .write_u64(QUEUE_DEVICE, base + layout.used_offset() as u64);
+
+        let index = usize::from(index);
+        if self.notify_offsets.len() <= index {
+            self.notify_offsets.resize(index + 1, 0);
+        }
+        self.notify_offsets[index] = self.common.read(QUEUE_NOTIFY_OFF);
+
+        self.common.write(QUEUE_ENABLE, 1_u16);
+    }
+
+    pub fn notify(&self, index: u16) {
+        let offset = self.notify_offsets[usize::from(index)];
+        let offset = usize::from(offset) * usize::try_from(self.notify_off_multiplier).unwrap();
+        self.notify.write(offset, index);
+    }
+
+    #[must_use]
+    pub fn read_config_u8(&self, offset: usize) -> u8 {
+        self.device.read(offset)
+    }
+
+    #[must_use]
+    pub fn read_config_u32(&self, offset: usize) -> u32 {
+        self.device.read(offset)
+    }
+}
@@ -9,15 +9,20 @@
     clippy::doc_markdown
 )]
 extern crate alloc;
+use beskar_core::syscall::ExitCode;
 use hyperdrive::once::Once;
 
 mod arch;
 pub mod boot;
+pub mod cpu;
+mod display;
 pub mod drivers;
 pub mod locals;
 mod mem;
 pub mod network;
 pub mod process;
+pub mod rand;
+pub mod softirq;
 pub mod storage;
 mod syscall;
 mod time;
@@ -51,7 +56,7 @@ fn panic(panic_info: &core::panic::PanicInfo) -> ! {
             });
         } else if !kernel_has_panicked() {
             // Otherwise, it should be safe to kill the process and proceed.
-            unsafe { process::scheduler::exit_current_thread() };
+            unsafe { process::scheduler::exit_current_thread(ExitCode::Panicked) };
         }
     }
 
@@ -14,6 +14,8 @@ pub fn init() {
     let core_locals = crate::arch::locals::init(core_id);
 
     ALL_CORE_LOCALS[core_id].call_once(|| core_locals);
+
+    crate::cpu::init(core_id, core_locals.apic_id());
 }
 
 #[must_use]
@@ -3,7 +3,8 @@
 
 extern crate alloc;
 
-use alloc::{boxed::Box, sync::Arc};
+use alloc::boxed::Box;
+use beskar_core::syscall::ExitCode;
 use hyperdrive::call_once;
 use kernel::{
     locals,
@@ -34,7 +35,7 @@ fn kmain() -> ! {
     // (GUI, ...)
 
     call_once!({
-        let driver_proc = Arc::new(Process::new(
+        let driver_proc = kernel::process::register(Process::new(
             "Drivers",
             beskar_hal::process::Kind::Driver,
             None,
@@ -57,8 +58,8 @@ fn kmain() -> ! {
                     "Starting user process for file: {}",
                     full_path.as_path().as_str()
                 );
-                let user_proc = Arc::new(Process::new(
-                    "User",
+                let user_proc = kernel::process::register(kernel::process::kernel().new_child(
+                    file.as_path().as_str(),
                     beskar_hal::process::Kind::User,
                     Some(full_path),
                 ));
@@ -72,5 +73,5 @@ fn kmain() -> ! {
         }
     });
 
-    unsafe { kernel::process::scheduler::exit_current_thread() }
+    unsafe { kernel::process::scheduler::exit_current_thread(ExitCode::Success) }
 }
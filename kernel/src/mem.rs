@@ -2,6 +2,8 @@ use beskar_core::mem::ranges::MemoryRange;
 use bootloader_api::KernelInfo;
 
 pub mod address_space;
+pub mod dma;
+pub mod fault;
 pub mod frame_alloc;
 mod heap;
 pub mod page_alloc;
@@ -1,8 +1,17 @@
 use super::{frame_alloc, page_alloc};
 use crate::{arch::cpuid, process::scheduler};
-use beskar_core::arch::{
-    PhysAddr, VirtAddr,
-    paging::{CacheFlush as _, M4KiB, Mapper, MemSize, Page, PageRangeInclusive},
+use beskar_core::{
+    arch::{
+        PhysAddr, VirtAddr,
+        paging::{CacheFlush as _, Frame, M4KiB, Mapper, MemSize, Page, PageRangeInclusive},
+    },
+    mem::{
+        brk::{BreakMove, BrkError, ProgramBreak},
+        fault::Region,
+        ranges::MemoryRange,
+        vma::{Backing, Vma, VmaFlags, VmaSet},
+    },
+    process::accounting::UsageCounter,
 };
 use beskar_hal::{
     paging::page_table::{Entries, Flags, PageTable},
@@ -21,6 +30,9 @@ static KERNEL_PT_RECURSIVE_INDEX: Once<u16> = Once::uninit();
 
 const PROCESS_PGALLOC_VRANGES: usize = 64;
 
+/// Maximum number of memory areas of an address space.
+const MAX_VMAS: usize = 64;
+
 pub fn init(recursive_index: u16, kernel_info: &KernelInfo) {
     KERNEL_CODE_INFO.call_once(|| *kernel_info);
     KERNEL_PT_RECURSIVE_INDEX.call_once(|| recursive_index);
@@ -52,6 +64,9 @@ pub fn init(recursive_index: u16, kernel_info: &KernelInfo) {
             pt: McsLock::new(kernel_pt),
             lvl4_paddr: frame.start_address(),
             pgalloc,
+            resident_bytes: UsageCounter::new(),
+            vmas: McsLock::new(VmaSet::new()),
+            brk: McsLock::new(None),
         }
     });
 }
@@ -69,6 +84,12 @@ pub struct AddressSpace {
     // FIXME: Make it less than 1KiB!
     /// The process-specific page allocator
     pgalloc: McsLock<super::page_alloc::PageAllocator<PROCESS_PGALLOC_VRANGES>>,
+    /// Amount of memory backed by physical frames, in bytes.
+    resident_bytes: UsageCounter,
+    /// Memory areas the process intends to use.
+    vmas: McsLock<VmaSet<MAX_VMAS>>,
+    /// Heap of the process, reserved on the first move of the program break.
+    brk: McsLock<Option<ProgramBreak>>,
 }
 
 impl Default for AddressSpace {
@@ -150,6 +171,9 @@ impl AddressSpace {
             pt: McsLock::new(PageTable::new(unsafe { &mut *lvl4_vaddr.as_mut_ptr() })),
             lvl4_paddr: frame.start_address(),
             pgalloc: McsLock::new(pgalloc),
+            resident_bytes: UsageCounter::new(),
+            vmas: McsLock::new(VmaSet::new()),
+            brk: McsLock::new(None),
         }
     }
 
@@ -162,6 +186,20 @@ impl AddressSpace {
         start <= end && end < KERNEL_AS_BASE
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the memory region containing `addr`, used to service page faults.
+    pub fn region_at(&self, addr: VirtAddr) -> Option<Region> {
+        self.vmas
+            .with_locked(|vmas| vmas.lookup(addr.as_u64()).map(Vma::region))
+    }
+
+    #[inline]
+    /// Operate on the memory areas of the address space.
+    pub fn with_vmas<R>(&self, f: impl FnOnce(&mut VmaSet<MAX_VMAS>) -> R) -> R {
+        self.vmas.with_locked(f)
+    }
+
     #[must_use]
     #[inline]
     pub fn is_active(&self) -> bool {
@@ -194,6 +232,28 @@ impl AddressSpace {
         self.pt.with_locked(f)
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the amount of memory backed by physical frames, in bytes.
+    pub fn resident_bytes(&self) -> u64 {
+        self.resident_bytes.get()
+    }
+
+    #[inline]
+    /// Records that `bytes` of memory have been mapped to newly allocated frames.
+    ///
+    /// This is done by `alloc_map`, and must be done by callers
+    /// that map frames through `with_page_table`.
+    pub fn record_mapped(&self, bytes: u64) {
+        self.resident_bytes.add(bytes);
+    }
+
+    #[inline]
+    /// Records that `bytes` of memory have been unmapped and their frames freed.
+    pub fn record_unmapped(&self, bytes: u64) {
+        self.resident_bytes.sub(bytes);
+    }
+
     #[inline]
     /// Operate on the process' page allocator.
     pub fn with_pgalloc<R>(
@@ -227,6 +287,7 @@ impl AddressSpace {
                 Some(())
             })
         })?;
+        self.record_mapped(page_range.size());
 
         Some(page_range)
     }
@@ -257,16 +318,91 @@ impl AddressSpace {
         Some(page_range)
     }
 
-    /// Unmap and free a memory region.
+    #[must_use]
+    /// Map a frame that is shared with other address spaces.
     ///
-    /// Note that it acquires locks on both the system-wide frame allocator and
-    /// the process-specific page allocator, then on the process-specific page allocator.
+    /// The frame is not accounted as resident memory of the address space.
+    pub fn map_shared(&self, frame: Frame<M4KiB>, flags: Flags) -> Option<Page<M4KiB>> {
+        let page = self
+            .with_pgalloc(|pgalloc| pgalloc.allocate_pages::<M4KiB>(1))?
+            .start();
+
+        let mapped = frame_alloc::with_frame_allocator(|frame_allocator| {
+            self.with_page_table(|page_table| {
+                page_table
+                    .map(page, frame, flags | Flags::PRESENT, frame_allocator)
+                    .map(|flush| flush.flush())
+                    .is_ok()
+            })
+        });
+
+        if mapped {
+            Some(page)
+        } else {
+            self.with_pgalloc(|pgalloc| pgalloc.free_pages(Page::range_inclusive(page, page)));
+            None
+        }
+    }
+
+    /// Moves the program break with `f`.
+    ///
+    /// The address space of the heap is reserved on the first call, and the pages between
+    /// the old and the new break are mapped on demand, as an anonymous area.
+    ///
+    /// Returns `None` if the heap cannot be reserved, if `f` fails,
+    /// or if the area cannot be recorded. The break is left unchanged in that case.
+    pub fn move_break(
+        &self,
+        f: impl FnOnce(&mut ProgramBreak) -> Result<BreakMove, BrkError>,
+    ) -> Option<BreakMove> {
+        let max_size = beskar_core::syscall::consts::BRK_MAX_HEAP_SIZE;
+
+        self.brk.with_locked(|brk| {
+            if brk.is_none() {
+                let start = self
+                    .with_pgalloc(|pgalloc| {
+                        pgalloc.allocate_pages::<M4KiB>(max_size / M4KiB::SIZE)
+                    })?
+                    .start();
+                *brk = Some(ProgramBreak::new(start.start_address().as_u64(), max_size));
+            }
+            let brk = brk.as_mut().unwrap();
+
+            let mv = f(brk).ok()?;
+            let Some(pages) = mv.pages(M4KiB::SIZE) else {
+                return Some(mv);
+            };
+
+            if mv.grows() {
+                let vma = Vma::new(pages, VmaFlags::READ | VmaFlags::WRITE, Backing::Anonymous);
+                if self.with_vmas(|vmas| vmas.insert(vma)).is_err() {
+                    // Setting the break back to where it was cannot fail
+                    let _ = brk.set(mv.old);
+                    return None;
+                }
+            } else {
+                // The pages leave the heap area, so that growing the break again can add them back.
+                // Shrinking an area from its end never splits it, so it cannot fail.
+                let _ = self.with_vmas(|vmas| vmas.remove(pages));
+                let page_range = Page::range_inclusive(
+                    Page::<M4KiB>::containing_address(VirtAddr::new_extend(pages.start())),
+                    Page::containing_address(VirtAddr::new_extend(pages.end())),
+                );
+                // Safety: The pages are past the break, so the process gave them up.
+                unsafe { self.unmap(page_range) };
+            }
+
+            Some(mv)
+        })
+    }
+
+    /// Unmap a memory region, freeing its frames but keeping its pages reserved.
     ///
     /// # Safety
     ///
     /// The caller must ensure that the pages are not in use after this call.
     /// Furthermore, the mapped physical frames must not be mapped elsewhere.
-    pub unsafe fn unmap_free<S: MemSize>(&self, page_range: PageRangeInclusive<S>)
+    unsafe fn unmap<S: MemSize>(&self, page_range: PageRangeInclusive<S>)
     where
         PageTable<'static>: Mapper<S, beskar_hal::paging::page_table::Flags>,
     {
@@ -276,10 +412,34 @@ impl AddressSpace {
                     if let Ok((frame, flush)) = page_table.unmap(page) {
                         flush.flush();
                         frame_allocator.free(frame);
+                        self.record_unmapped(S::SIZE);
                     }
                 }
             });
         });
+        // Removing a range never fails, unless it splits an area while there are too many.
+        let _ = self.with_vmas(|vmas| {
+            vmas.remove(MemoryRange::new(
+                page_range.start().start_address().as_u64(),
+                page_range.end().start_address().as_u64() + (S::SIZE - 1),
+            ))
+        });
+    }
+
+    /// Unmap and free a memory region.
+    ///
+    /// Note that it acquires locks on both the system-wide frame allocator and
+    /// the process-specific page allocator, then on the process-specific page allocator.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the pages are not in use after this call.
+    /// Furthermore, the mapped physical frames must not be mapped elsewhere.
+    pub unsafe fn unmap_free<S: MemSize>(&self, page_range: PageRangeInclusive<S>)
+    where
+        PageTable<'static>: Mapper<S, beskar_hal::paging::page_table::Flags>,
+    {
+        unsafe { self.unmap(page_range) };
         self.with_pgalloc(|pgalloc| {
             pgalloc.free_pages(page_range);
         });
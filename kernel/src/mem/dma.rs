@@ -0,0 +1,109 @@
+//! Physically contiguous buffers for device DMA.
+//!
+//! Devices access memory through physical addresses and cannot follow the
+//! page table, so buffers spanning multiple frames must be physically contiguous.
+
+use super::frame_alloc;
+use crate::process;
+use beskar_core::arch::{
+    Alignment, PhysAddr, VirtAddr,
+    paging::{CacheFlush as _, Frame, M4KiB, Mapper as _, MemSize as _, PageRangeInclusive},
+};
+use beskar_hal::paging::page_table::Flags;
+
+/// A zeroed, physically contiguous buffer mapped into the current address space.
+///
+/// The memory is mapped uncached (`Flags::MMIO_SUITABLE`) and freed on drop.
+pub struct DmaBuffer {
+    first_frame: Frame<M4KiB>,
+    pages: PageRangeInclusive<M4KiB>,
+}
+
+impl DmaBuffer {
+    #[must_use]
+    /// Allocate a buffer of at least `len` bytes, whose physical start address is aligned on `align`.
+    ///
+    /// Returns `None` if `len` is zero or if no physically contiguous run is available.
+    pub fn new(len: usize, align: Alignment) -> Option<Self> {
+        if len == 0 {
+            return None;
+        }
+        let count = u64::try_from(len).ok()?.div_ceil(M4KiB::SIZE);
+
+        let pages = process::current()
+            .address_space()
+            .with_pgalloc(|palloc| palloc.allocate_pages::<M4KiB>(count))?;
+
+        let first_frame = frame_alloc::with_frame_allocator(|fralloc| {
+            let first_frame = fralloc.allocate_contiguous::<M4KiB>(count, align)?;
+            process::current()
+                .address_space()
+                .with_page_table(|page_table| {
+                    for (i, page) in pages.into_iter().enumerate() {
+                        page_table
+                            .map(page, first_frame + i as u64, Flags::MMIO_SUITABLE, fralloc)
+                            .unwrap()
+                            .flush();
+                    }
+                });
+            Some(first_frame)
+        });
+        let Some(first_frame) = first_frame else {
+            process::current()
+                .address_space()
+                .with_pgalloc(|palloc| palloc.free_pages(pages));
+            return None;
+        };
+
+        // Safety: The pages have just been mapped and are exclusively owned.
+        unsafe {
+            pages
+                .start()
+                .start_address()
+                .as_mut_ptr::<u8>()
+                .write_bytes(0, usize::try_from(pages.size()).unwrap());
+        }
+
+        Some(Self { first_frame, pages })
+    }
+
+    #[must_use]
+    #[inline]
+    /// Physical address of the start of the buffer, to be handed to the device.
+    pub const fn paddr(&self) -> PhysAddr {
+        self.first_frame.start_address()
+    }
+
+    #[must_use]
+    #[inline]
+    /// Virtual address of the start of the buffer.
+    pub const fn vaddr(&self) -> VirtAddr {
+        self.pages.start().start_address()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn as_mut_ptr<T>(&self) -> *mut T {
+        self.vaddr().as_mut_ptr()
+    }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        process::current()
+            .address_space()
+            .with_page_table(|page_table| {
+                for page in self.pages {
+                    if let Ok((_frame, tlb)) = page_table.unmap(page) {
+                        tlb.flush();
+                    }
+                }
+            });
+        frame_alloc::with_frame_allocator(|fralloc| {
+            fralloc.deallocate_contiguous(self.first_frame, self.pages.len());
+        });
+        process::current()
+            .address_space()
+            .with_pgalloc(|palloc| palloc.free_pages(self.pages));
+    }
+}
@@ -0,0 +1,174 @@
+//! Servicing of page faults.
+use super::{address_space::AddressSpace, frame_alloc};
+use beskar_core::{
+    arch::{
+        VirtAddr,
+        paging::{CacheFlush as _, M4KiB, Mapper as _, MemSize as _, Page},
+    },
+    mem::fault::{Access, KillReason, PageFault, Region, Resolution, classify},
+};
+use beskar_hal::{paging::page_table::Flags, structures::PageFaultErrorCode};
+
+#[must_use]
+/// Decodes the error code of a `#PF`.
+pub const fn decode(
+    addr: VirtAddr,
+    error_code: PageFaultErrorCode,
+    stack_pointer: VirtAddr,
+) -> PageFault {
+    let access = if error_code.contains(PageFaultErrorCode::INSTRUCTION_FETCH) {
+        Access::Execute
+    } else if error_code.contains(PageFaultErrorCode::WRITE) {
+        Access::Write
+    } else {
+        Access::Read
+    };
+    PageFault {
+        addr: addr.as_u64(),
+        access,
+        present: error_code.contains(PageFaultErrorCode::PROTECTION_VIOLATION),
+        user: error_code.contains(PageFaultErrorCode::USER_MODE),
+        reserved_bit: error_code.contains(PageFaultErrorCode::MALFORMED_TABLE),
+        stack_pointer: stack_pointer.as_u64(),
+    }
+}
+
+/// Classifies a fault against the regions of `address_space`, and services it if possible.
+///
+/// Returns the resolution that could not be carried out by the kernel alone,
+/// i.e. `Kill` or `Fatal`, or `Retry` once the fault is serviced.
+pub fn handle(address_space: &AddressSpace, fault: &PageFault) -> Resolution {
+    let region = address_space.region_at(VirtAddr::new_extend(fault.addr));
+    let resolution = classify(fault, region.as_ref());
+
+    let page = Page::<M4KiB>::containing_address(VirtAddr::new_extend(fault.addr));
+    let serviced = match (resolution, region) {
+        (Resolution::MapZeroed | Resolution::GrowStack, Some(region)) => {
+            map_zeroed(address_space, page, region)
+        }
+        (Resolution::CopyOnWrite, Some(region)) => copy_on_write(address_space, page, region),
+        _ => return resolution,
+    };
+
+    if serviced {
+        Resolution::Retry
+    } else if fault.user {
+        Resolution::Kill(KillReason::OutOfMemory)
+    } else {
+        Resolution::Fatal
+    }
+}
+
+#[must_use]
+const fn region_flags(region: Region) -> Flags {
+    let mut flags = Flags::PRESENT.union(Flags::USER_ACCESSIBLE);
+    if region.writable {
+        flags = flags.union(Flags::WRITABLE);
+    }
+    if !region.executable {
+        flags = flags.union(Flags::NO_EXECUTE);
+    }
+    flags
+}
+
+/// Backs `page` with a new zeroed frame.
+#[must_use]
+fn map_zeroed(address_space: &AddressSpace, page: Page<M4KiB>, region: Region) -> bool {
+    let mapped = frame_alloc::with_frame_allocator(|frame_allocator| {
+        let Some(frame) = frame_allocator.alloc::<M4KiB>() else {
+            return false;
+        };
+        address_space.with_page_table(|pt| {
+            // The page is mapped writable for the kernel to zero it,
+            // user-space cannot access it before the fault returns.
+            let flags = region_flags(region).union(Flags::WRITABLE);
+            let mapped = pt
+                .map(page, frame, flags, frame_allocator)
+                .map(|flush| flush.flush())
+                .is_ok();
+            if !mapped {
+                frame_allocator.free(frame);
+            }
+            mapped
+        })
+    });
+    if !mapped {
+        return false;
+    }
+
+    // Safety: the page was just mapped in the active address space.
+    unsafe {
+        page.start_address()
+            .as_mut_ptr::<u8>()
+            .write_bytes(0, usize::try_from(M4KiB::SIZE).unwrap());
+    }
+    if !region.writable {
+        address_space.with_page_table(|pt| {
+            if let Ok(flush) = pt.update_flags(page, region_flags(region)) {
+                flush.flush();
+            }
+        });
+    }
+    address_space.record_mapped(M4KiB::SIZE);
+    true
+}
+
+/// Replaces the frame shared by `page` with a private, writable copy.
+///
+/// Frames are not reference-counted: the shared frame stays owned by the mapping
+/// it was shared from.
+#[must_use]
+fn copy_on_write(address_space: &AddressSpace, page: Page<M4KiB>, region: Region) -> bool {
+    let Some(scratch) = address_space
+        .with_pgalloc(|pgalloc| pgalloc.allocate_pages::<M4KiB>(1))
+        .map(|range| range.start())
+    else {
+        return false;
+    };
+
+    let copied = frame_alloc::with_frame_allocator(|frame_allocator| {
+        let Some(frame) = frame_allocator.alloc::<M4KiB>() else {
+            return false;
+        };
+        address_space.with_page_table(|pt| {
+            let scratch_flags = Flags::PRESENT | Flags::WRITABLE | Flags::NO_EXECUTE;
+            if let Ok(flush) = pt.map(scratch, frame, scratch_flags, frame_allocator) {
+                flush.flush();
+            } else {
+                frame_allocator.free(frame);
+                return false;
+            }
+
+            // Safety: both pages are mapped in the active address space,
+            // and the faulting page is at least readable.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    page.start_address().as_ptr::<u8>(),
+                    scratch.start_address().as_mut_ptr::<u8>(),
+                    usize::try_from(M4KiB::SIZE).unwrap(),
+                );
+            }
+            pt.unmap(scratch).unwrap().1.flush();
+
+            if let Ok((_shared, flush)) = pt.unmap(page) {
+                flush.flush();
+            }
+            let mapped = pt
+                .map(page, frame, region_flags(region), frame_allocator)
+                .map(|flush| flush.flush())
+                .is_ok();
+            if !mapped {
+                frame_allocator.free(frame);
+            }
+            mapped
+        })
+    });
+
+    address_space.with_pgalloc(|pgalloc| {
+        pgalloc.free_pages(Page::range_inclusive(scratch, scratch));
+    });
+    if copied {
+        address_space.record_mapped(M4KiB::SIZE);
+    }
+    copied
+}
@@ -3,10 +3,11 @@
 //! A frame allocator should allow the allocation of physical frames and keep track of the
 //! allocated frames. It should also provide a way to free frames.
 //!
-//! Allocated frames do not need to be contiguous.
+//! Allocated frames do not need to be contiguous, except when explicitly requested
+//! through `FrameAllocator::allocate_contiguous`.
 
 use beskar_core::arch::{
-    PhysAddr,
+    Alignment, PhysAddr,
     paging::{Frame, M4KiB, MemSize},
 };
 use beskar_core::mem::ranges::{MemoryRange, MemoryRanges};
@@ -74,6 +75,27 @@ impl FrameAllocator {
         Some(paddr.frame())
     }
 
+    #[must_use]
+    /// Allocate `count` physically contiguous frames.
+    ///
+    /// The returned frame is the first of the run. Its start address is aligned on
+    /// `align`, or on the frame size if it is stricter.
+    ///
+    /// ## Fragmentation
+    ///
+    /// Free memory is tracked as ranges, so the search itself is cheap, but the run
+    /// must fit in a single free range: once physical memory gets fragmented,
+    /// large requests can fail even though enough frames are free in total.
+    /// Contiguous allocations should thus be kept small and done early
+    /// (e.g. when a driver is initialized) rather than on hot paths.
+    pub fn allocate_contiguous<S: MemSize>(
+        &mut self,
+        count: u64,
+        align: Alignment,
+    ) -> Option<Frame<S>> {
+        self.memory_ranges.allocate_frames(count, align)
+    }
+
     /// Free a frame
     pub fn free<S: MemSize>(&mut self, frame: Frame<S>) {
         self.memory_ranges.insert(MemoryRange::new(
@@ -81,6 +103,11 @@ impl FrameAllocator {
             frame.start_address().as_u64() + (frame.size() - 1),
         ));
     }
+
+    /// Free `count` contiguous frames previously allocated with `allocate_contiguous`.
+    pub fn deallocate_contiguous<S: MemSize>(&mut self, first: Frame<S>, count: u64) {
+        self.memory_ranges.free_frames(first, count);
+    }
 }
 
 impl<S: MemSize> beskar_core::arch::paging::FrameAllocator<S> for FrameAllocator {
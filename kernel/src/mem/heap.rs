@@ -43,6 +43,7 @@ struct HeapGA;
 
 unsafe impl GlobalAlloc for HeapGA {
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        crate::debug_assert_not_in_interrupt!();
         KERNEL_HEAP
             .with_locked_if_init(|heap| heap.allocate(layout).ok())
             .flatten()
@@ -50,6 +51,8 @@ unsafe impl GlobalAlloc for HeapGA {
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
+        crate::debug_assert_not_in_interrupt!();
+
         // Safety: `ptr` is guaranteed to be valid as it was returned by `alloc`.
         let ptr = unsafe { NonNull::new_unchecked(ptr) };
         // Safety: `GlobalAlloc` guarantees that the pointer is valid and the layout is correct.
@@ -1 +1,90 @@
+//! Network plumbing of the kernel.
+use crate::drivers::nic;
+use beskar_core::syscall::capture::FrameRecord;
+use holonet::{capture::PacketCapture, stack::NetStack};
+use hyperdrive::locks::mcs::McsLock;
 
+/// Number of frames the capture ring holds.
+const CAPTURE_FRAMES: usize = 256;
+/// Number of bytes kept from each captured frame.
+const CAPTURE_SNAP_LEN: usize = 256;
+/// Maximum number of frames drained from the interface at once,
+/// so that a flood of frames cannot keep the caller busy forever.
+const POLL_BUDGET: usize = 64;
+
+/// Packet capture, started on the first `PacketCapture` syscall.
+static CAPTURE: McsLock<Option<PacketCapture>> = McsLock::new(None);
+
+/// Network stack of the kernel.
+///
+/// The hardware interface is still driven through `drivers::nic`,
+/// so only the loopback interface is registered.
+static STACK: McsLock<NetStack> = McsLock::new(NetStack::new());
+
+pub fn init() {
+    STACK.with_locked(|stack| {
+        stack.add_loopback();
+    });
+}
+
+pub fn with_stack<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut NetStack) -> R,
+{
+    STACK.with_locked(f)
+}
+
+/// Drains the frames received by the network interface.
+///
+/// There is no consumer of incoming frames yet: they are only handed to the packet capture.
+pub fn poll() {
+    nic::with_nic(|nic| {
+        for _ in 0..POLL_BUDGET {
+            let Some(frame) = nic.poll_frame() else {
+                break;
+            };
+            tap(frame);
+            nic.consume_frame();
+        }
+    });
+}
+
+/// Copies a received frame into the packet capture, if it is running.
+fn tap(frame: &[u8]) {
+    let timestamp = core::time::Duration::from_micros(crate::time::now().total_micros());
+    CAPTURE.with_locked(|capture| {
+        if let Some(capture) = capture {
+            capture.record(frame, timestamp);
+        }
+    });
+}
+
+/// Starts the packet capture if needed, sets its `EtherType` filter,
+/// then moves as many captured frames as fit into `buffer`.
+///
+/// Returns the number of bytes written.
+pub fn take_captured(filter: Option<u16>, buffer: &mut [u8]) -> usize {
+    poll();
+
+    CAPTURE.with_locked(|capture| {
+        let capture =
+            capture.get_or_insert_with(|| PacketCapture::new(CAPTURE_FRAMES, CAPTURE_SNAP_LEN));
+        capture.set_filter(filter);
+
+        let mut written = 0;
+        while let Some(frame) = capture.peek() {
+            let record = FrameRecord::new(
+                frame.timestamp().as_micros().try_into().unwrap_or(u64::MAX),
+                frame.original_len().try_into().unwrap_or(u16::MAX),
+                frame.data().len().try_into().unwrap(),
+                frame.dropped_before().try_into().unwrap_or(u32::MAX),
+            );
+            let Some(len) = record.write(frame.data(), &mut buffer[written..]) else {
+                break;
+            };
+            written += len;
+            let _ = capture.pop();
+        }
+        written
+    })
+}
@@ -1,26 +1,62 @@
 use crate::mem::address_space::{self, AddressSpace};
 use alloc::{
+    collections::btree_map::BTreeMap,
     string::{String, ToString},
-    sync::Arc,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use beskar_core::{
+    process::{
+        accounting::UsageCounter,
+        env::Environment,
+        join::{JoinError, JoinTable},
+        signal::{Delivery, Disposition, Raised, Signal, SignalSet, SignalState},
+    },
+    syscall::{
+        ExitCode,
+        poll::PollSet,
+        process::{ProcessInfo, ProcessKind, ProcessState},
+    },
+    time::{Duration, Instant, IntervalTimer},
 };
 use beskar_hal::process::Kind;
-use core::sync::atomic::{AtomicU16, AtomicU64, Ordering};
-use hyperdrive::{once::Once, ptrs::view::ViewRef};
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicUsize, Ordering};
+use hyperdrive::{locks::mcs::McsLock, once::Once, ptrs::view::ViewRef};
 use storage::fs::{Path, PathBuf};
 
 pub mod binary;
 pub mod scheduler;
+pub mod timer;
+
+use beskar_core::syscall::consts::{MAX_JOINABLE_THREADS, MAX_POLL_SET_SOURCES, MAX_POLL_SETS};
 
 static KERNEL_PROCESS: Once<Arc<Process>> = Once::uninit();
 
+/// Every process that has not been dropped yet, by PID.
+static PROCESSES: McsLock<BTreeMap<u64, Weak<Process>>> = McsLock::new(BTreeMap::new());
+
 pub fn init() {
     KERNEL_PROCESS.call_once(|| {
-        Arc::new(Process {
+        let mut env = Environment::new();
+        env.set("PATH", "/ramdisk").unwrap();
+
+        let pid = ProcessId::new();
+        register(Process {
             name: "kernel".to_string(),
-            pid: ProcessId::new(),
+            pid,
+            group: pid.as_u64(),
             address_space: ViewRef::new_borrow(address_space::get_kernel_address_space()),
             kind: Kind::Kernel,
             binary: None,
+            env: McsLock::new(env),
+            threads: AtomicUsize::new(0),
+            cpu_time_us: UsageCounter::new(),
+            joinable: McsLock::new(JoinTable::new()),
+            tls_template: Once::uninit(),
+            exiting: AtomicBool::new(false),
+            poll_sets: McsLock::new(Vec::new()),
+            signals: McsLock::new(SignalState::new()),
+            timer: McsLock::new(IntervalTimer::DISARMED),
         })
     });
 
@@ -81,22 +117,76 @@ impl ProcessId {
 pub struct Process {
     name: String,
     pid: ProcessId,
+    /// Process group, which receives the signals generated from the keyboard as a whole.
+    group: u64,
     address_space: ViewRef<'static, AddressSpace>,
     kind: Kind,
     binary: Option<PathBuf>,
+    env: McsLock<Environment>,
+    /// Number of threads that have not exited yet.
+    threads: AtomicUsize,
+    /// CPU time used by every thread of the process, in microseconds.
+    cpu_time_us: UsageCounter,
+    /// Threads created by userspace that have not been joined yet.
+    joinable: McsLock<JoinTable<MAX_JOINABLE_THREADS>>,
+    /// TLS template of the binary, copied for every thread.
+    tls_template: Once<binary::TlsTemplate>,
+    /// Set when the process exits, for its threads to exit.
+    exiting: AtomicBool,
+    /// Poll sets created with `PollCreate`, by ID.
+    poll_sets: McsLock<Vec<PollSet<MAX_POLL_SET_SOURCES>>>,
+    /// Signal handlers, and signals waiting to be delivered.
+    signals: McsLock<SignalState>,
+    /// Timer armed with `Syscall::SetTimer`, see the `timer` module.
+    timer: McsLock<IntervalTimer>,
 }
 
 impl Process {
     #[must_use]
     #[inline]
+    /// Creates a process with an empty environment, in a process group of its own.
     pub fn new(name: &str, kind: Kind, binary: Option<PathBuf>) -> Self {
+        let pid = ProcessId::new();
         Self {
             name: String::from(name),
-            pid: ProcessId::new(),
+            pid,
+            group: pid.as_u64(),
             address_space: ViewRef::new_owned(AddressSpace::new()),
             kind,
             binary,
+            env: McsLock::new(Environment::new()),
+            threads: AtomicUsize::new(0),
+            cpu_time_us: UsageCounter::new(),
+            joinable: McsLock::new(JoinTable::new()),
+            tls_template: Once::uninit(),
+            exiting: AtomicBool::new(false),
+            poll_sets: McsLock::new(Vec::new()),
+            signals: McsLock::new(SignalState::new()),
+            timer: McsLock::new(IntervalTimer::DISARMED),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Creates a process that inherits a copy of the environment of `self`,
+    /// and its handles that are not close-on-exec, at the same numbers.
+    ///
+    /// The child joins the process group of `self`, unless `self` is the kernel:
+    /// the apps it starts are independent of one another.
+    pub fn new_child(&self, name: &str, kind: Kind, binary: Option<PathBuf>) -> Self {
+        let mut child = Self::new(name, kind, binary);
+        if self.kind != Kind::Kernel {
+            child.group = self.group;
         }
+        child.env = McsLock::new(self.with_env(|env| env.clone()));
+        crate::storage::vfs().inherit_handles(self.pid.as_u64(), child.pid.as_u64());
+        child
+    }
+
+    #[inline]
+    /// Operates on the environment of the process.
+    pub fn with_env<R>(&self, f: impl FnOnce(&mut Environment) -> R) -> R {
+        self.env.with_locked(f)
     }
 
     #[must_use]
@@ -111,6 +201,12 @@ impl Process {
         self.pid
     }
 
+    #[must_use]
+    #[inline]
+    pub const fn group(&self) -> u64 {
+        self.group
+    }
+
     #[must_use]
     #[inline]
     pub fn address_space(&self) -> &AddressSpace {
@@ -128,11 +224,236 @@ impl Process {
     pub fn binary(&self) -> Option<Path<'_>> {
         self.binary.as_ref().map(PathBuf::as_path)
     }
+
+    #[inline]
+    pub(crate) fn thread_started(&self) {
+        self.threads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn thread_exited(&self) {
+        let previous = self.threads.fetch_sub(1, Ordering::Relaxed);
+        debug_assert!(previous > 0, "More threads exited than started");
+    }
+
+    /// Registers a thread created by userspace, so that it can be joined.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if too many threads are waiting to be joined.
+    pub(crate) fn register_joinable(&self, tid: u64) -> Result<(), JoinError> {
+        self.joinable.with_locked(|table| table.register(tid))
+    }
+
+    /// Records that a thread exited with `exit_code`, for it to be joined.
+    pub(crate) fn mark_exited(&self, tid: u64, exit_code: ExitCode) {
+        self.joinable
+            .with_locked(|table| table.mark_exited(tid, exit_code));
+    }
+
+    /// Joins a thread created by userspace if it has exited.
+    ///
+    /// Returns the exit code of the thread if it was joined.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread is not joinable.
+    pub(crate) fn try_join(&self, tid: u64) -> Result<Option<ExitCode>, JoinError> {
+        self.joinable.with_locked(|table| table.try_join(tid))
+    }
+
+    /// Forgets a thread created by userspace, which will not be joined.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread is not joinable.
+    pub(crate) fn detach_thread(&self, tid: u64) -> Result<(), JoinError> {
+        self.joinable.with_locked(|table| table.detach(tid))
+    }
+
+    /// Creates an empty poll set.
+    ///
+    /// Returns the ID of the set, or `None` if the process already has `MAX_POLL_SETS` sets.
+    pub(crate) fn create_poll_set(&self) -> Option<u64> {
+        self.poll_sets.with_locked(|sets| {
+            if sets.len() >= MAX_POLL_SETS {
+                return None;
+            }
+            sets.push(PollSet::new());
+            u64::try_from(sets.len() - 1).ok()
+        })
+    }
+
+    /// Operates on a poll set of the process.
+    ///
+    /// Returns `None` if there is no set with the given ID.
+    pub(crate) fn with_poll_set<R>(
+        &self,
+        id: u64,
+        f: impl FnOnce(&mut PollSet<MAX_POLL_SET_SOURCES>) -> R,
+    ) -> Option<R> {
+        let id = usize::try_from(id).ok()?;
+        self.poll_sets.with_locked(|sets| sets.get_mut(id).map(f))
+    }
+
+    /// Raises a signal in the process.
+    ///
+    /// Signals without a handler terminate the process right away, see `request_exit`.
+    pub(crate) fn raise_signal(&self, signal: Signal) {
+        if self.signals.with_locked(|signals| signals.raise(signal)) == Raised::Terminate {
+            video::debug!("Process {} terminated by {:?}", self.name, signal);
+            self.request_exit();
+        }
+    }
+
+    #[inline]
+    /// Sets what the process does when a signal is raised.
+    pub(crate) fn set_signal_disposition(&self, signal: Signal, disposition: Disposition) {
+        self.signals
+            .with_locked(|signals| signals.set_disposition(signal, disposition));
+    }
+
+    #[must_use]
+    #[inline]
+    /// Takes a pending signal to deliver to the current thread.
+    pub(crate) fn take_signal(&self) -> Option<Delivery> {
+        self.signals.with_locked(SignalState::take_deliverable)
+    }
+
+    #[inline]
+    /// Restores the blocked signals, once a handler returned.
+    pub(crate) fn restore_blocked_signals(&self, blocked: SignalSet) {
+        self.signals
+            .with_locked(|signals| signals.restore_blocked(blocked));
+    }
+
+    #[inline]
+    /// Arms the timer of the process, returning its deadline.
+    ///
+    /// See `IntervalTimer::arm`.
+    pub(crate) fn arm_timer(
+        &self,
+        now: Instant,
+        delay: Duration,
+        period: Duration,
+    ) -> Option<Instant> {
+        self.timer
+            .with_locked(|timer| timer.arm(now, delay, period))
+    }
+
+    /// Raises `Signal::Alarm` if the deadline of the timer of the process is reached.
+    ///
+    /// Returns the next deadline of a periodic timer.
+    pub(crate) fn expire_timer(&self, now: Instant) -> Option<Instant> {
+        let expiration = self.timer.with_locked(|timer| timer.expire(now))?;
+        self.raise_signal(Signal::Alarm);
+        expiration.next
+    }
+
+    #[inline]
+    /// Makes every thread of the process exit.
+    ///
+    /// Threads check it when they leave a syscall or are preempted in userspace,
+    /// see `scheduler::exit_if_process_exiting`.
+    pub(crate) fn request_exit(&self) {
+        self.exiting.store(true, Ordering::Release);
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns true if the process is exiting.
+    pub fn is_exiting(&self) -> bool {
+        self.exiting.load(Ordering::Acquire)
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the TLS template of the binary, once it is loaded.
+    pub fn tls_template(&self) -> Option<binary::TlsTemplate> {
+        self.tls_template.get().copied()
+    }
+
+    #[inline]
+    pub(crate) fn set_tls_template(&self, template: binary::TlsTemplate) {
+        self.tls_template.call_once(|| template);
+    }
+
+    #[inline]
+    /// Adds CPU time spent by one of the threads of the process, in microseconds.
+    pub(crate) fn add_cpu_time(&self, us: u64) {
+        self.cpu_time_us.add(us);
+    }
+
+    #[must_use]
+    /// Returns a snapshot of the process, as reported by the `ProcessList` syscall.
+    pub fn info(&self) -> ProcessInfo {
+        let kind = match self.kind {
+            Kind::Kernel => ProcessKind::Kernel,
+            Kind::Driver => ProcessKind::Driver,
+            _ => ProcessKind::User,
+        };
+        let state = if self.threads.load(Ordering::Relaxed) == 0 {
+            ProcessState::Zombie
+        } else {
+            ProcessState::Running
+        };
+        ProcessInfo::new(
+            self.pid.as_u64(),
+            &self.name,
+            kind,
+            state,
+            self.cpu_time_us.get() / 1_000,
+            self.address_space.resident_bytes(),
+        )
+    }
 }
 
 impl Drop for Process {
     fn drop(&mut self) {
         crate::storage::vfs().close_all_from_process(self.pid.as_u64());
+        crate::display::release(self.pid.as_u64());
+        PROCESSES.with_locked(|processes| processes.remove(&self.pid.as_u64()));
+    }
+}
+
+#[must_use]
+/// Makes a process visible to the `ProcessList` syscall.
+pub fn register(process: Process) -> Arc<Process> {
+    let process = Arc::new(process);
+    PROCESSES.with_locked(|processes| {
+        processes.insert(process.pid.as_u64(), Arc::downgrade(&process));
+    });
+    process
+}
+
+#[must_use]
+/// Returns at most `max` processes whose PID is at least `cursor`, by increasing PID.
+pub fn list(cursor: u64, max: usize) -> Vec<Arc<Process>> {
+    // The processes must be dropped after the lock is released,
+    // as dropping the last reference to a process locks the table.
+    PROCESSES.with_locked(|processes| {
+        processes
+            .range(cursor..)
+            .filter_map(|(_, process)| process.upgrade())
+            .take(max)
+            .collect()
+    })
+}
+
+/// Raises a signal in every process of the group of the given process.
+///
+/// The group is that of the foreground app, for signals generated from the keyboard.
+pub fn signal_group_of(pid: u64, signal: Signal) {
+    let processes = list(0, usize::MAX);
+    let Some(group) = processes
+        .iter()
+        .find(|process| process.pid.as_u64() == pid)
+        .map(|process| process.group)
+    else {
+        return;
+    };
+    for process in processes.iter().filter(|process| process.group == group) {
+        process.raise_signal(signal);
     }
 }
 
@@ -142,6 +463,17 @@ pub fn current() -> Arc<Process> {
     scheduler::current_process()
 }
 
+#[must_use]
+#[inline]
+/// Returns the kernel process.
+///
+/// # Panics
+///
+/// Panics if the process subsystem has not been initialized.
+pub fn kernel() -> Arc<Process> {
+    KERNEL_PROCESS.get().unwrap().clone()
+}
+
 /// A struct representing a PCID.
 ///
 /// Its valid values are 0 to 4095.
@@ -206,7 +538,7 @@ impl ::storage::KernelDevice for RandFile {
         if dst.is_empty() {
             Ok(())
         } else {
-            crate::arch::rand::rand_bytes(dst).map_err(|_| ::storage::BlockDeviceError::Io)
+            crate::rand::fill(dst).map_err(|_| ::storage::BlockDeviceError::Io)
         }
     }
 
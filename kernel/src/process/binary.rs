@@ -59,6 +59,7 @@ pub struct TlsTemplate {
     start: VirtAddr,
     file_size: u64,
     mem_size: u64,
+    align: u64,
 }
 
 impl TlsTemplate {
@@ -79,4 +80,10 @@ impl TlsTemplate {
     pub const fn mem_size(&self) -> u64 {
         self.mem_size
     }
+
+    #[must_use]
+    #[inline]
+    pub const fn align(&self) -> u64 {
+        self.align
+    }
 }
@@ -54,6 +54,9 @@ impl MemoryMapper for ElfMemoryMapper {
                             .allocate_frame()
                             .ok_or(MappingError::FrameAllocationFailed)?;
                         pt.map(page, frame, initial_flags, fralloc)?.flush();
+                        process::current()
+                            .address_space()
+                            .record_mapped(M4KiB::SIZE);
                     }
                     Ok(())
                 })
@@ -172,6 +175,9 @@ fn release_region(base: VirtAddr, size: u64) {
                 if let Ok((frame, tlb)) = pt.unmap(page) {
                     tlb.flush();
                     fralloc.free(frame);
+                    process::current()
+                        .address_space()
+                        .record_unmapped(M4KiB::SIZE);
                 }
             }
         });
@@ -188,6 +194,7 @@ impl From<::elf::segments::TlsTemplate> for super::TlsTemplate {
             start: tls.start,
             file_size: tls.file_size,
             mem_size: tls.mem_size,
+            align: tls.align,
         }
     }
 }
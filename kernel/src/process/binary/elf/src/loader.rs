@@ -170,6 +170,7 @@ impl ElfLoader {
                         start: region_addr + (ph.virtual_addr() - min_vaddr.as_u64()),
                         file_size: ph.file_size(),
                         mem_size: ph.mem_size(),
+                        align: ph.align().max(1),
                     });
                 }
                 Type::Dynamic => {
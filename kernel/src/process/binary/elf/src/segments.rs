@@ -11,6 +11,8 @@ pub struct TlsTemplate {
     pub file_size: u64,
     /// Total size allocated for TLS
     pub mem_size: u64,
+    /// Required alignment of the TLS block
+    pub align: u64,
 }
 
 /// Information about a loaded ELF binary
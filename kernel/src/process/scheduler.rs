@@ -4,36 +4,45 @@
 )]
 
 use crate::{locals, time::Duration};
-use alloc::{boxed::Box, sync::Arc};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use beskar_core::{
-    process::{AtomicSleepReason, SleepHandle, SleepReason},
+    process::{
+        AtomicSleepReason, SleepHandle, SleepReason,
+        affinity::{AffinityError, CpuMask},
+    },
+    syscall::ExitCode,
     time::Instant,
 };
 use beskar_hal::instructions::without_interrupts;
 use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
 use hyperdrive::{call_once, locks::mcs::McsLock, once::Once, queues::mpsc::MpscQueue};
-use priority::ThreadQueue;
+use priority::{InheritedPriority, ThreadQueue};
 use thread::{Thread, ThreadId};
 
+mod mutex;
+pub use mutex::{Mutex, MutexGuard};
 mod priority;
-pub use priority::Priority;
+pub use priority::{Priority, set_quantum};
 mod sleep;
 use sleep::SleepQueues;
 pub mod thread;
 
 static SCHEDULER_SWITCH: AtomicBool = AtomicBool::new(false);
 
-/// The time quantum for the scheduler, in milliseconds.
+/// The period of the scheduler tick, in milliseconds.
 ///
+/// Quanta are a whole number of ticks, see `set_quantum`.
 /// According to the Internet, Windows uses 20-60ms, Linux uses 0.75-6ms.
-pub const SCHEDULER_QUANTUM_MS: u32 = crate::arch::apic::MS_PER_INTERRUPT;
+pub const SCHEDULER_TICK_MS: u32 = crate::arch::apic::MS_PER_INTERRUPT;
 
 const IDLE_THREADS_PER_CORE: usize = 2;
 
 // It is backed by a Multiple Producer Single Consumer queue.
 // It would be a better choice to use a Multiple Producer Multiple Consumer queue,
 // but the only implemention I know uses a fixed size buffer and I don't want to bound the number of threads.
-/// A queue for threads.
+/// A queue for threads that may run on every core.
+///
+/// Threads restricted to some cores are queued on the `pinned` queue of a `Scheduler` instead.
 static QUEUE: Once<priority::RoundRobinQueues> = Once::uninit();
 
 /// A queue for finished threads.
@@ -84,7 +93,7 @@ pub fn scheduler_tick() -> Option<ContextSwitch> {
     wake_sleeping_threads();
 
     // Attempt to reschedule
-    crate::process::scheduler::reschedule(RescheduleReason::QuantumExpired)
+    crate::process::scheduler::reschedule(RescheduleReason::Tick)
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -108,6 +117,10 @@ impl ContextSwitch {
 
 pub struct Scheduler {
     current: McsLock<Box<Thread>>,
+    /// Ready threads that may only run on some cores, including this one.
+    ///
+    /// Other cores only take the threads they are allowed to run, see `Scheduler::steal`.
+    pinned: priority::RoundRobinQueues,
     should_exit: AtomicBool,
     sleep_intent: AtomicSleepReason,
 }
@@ -117,6 +130,7 @@ impl Scheduler {
     #[inline]
     fn new(kernel_thread: thread::Thread) -> Self {
         Self {
+            pinned: priority::RoundRobinQueues::new(kernel_thread.process()),
             current: McsLock::new(Box::new(kernel_thread)),
             should_exit: AtomicBool::new(false),
             sleep_intent: AtomicSleepReason::new(None),
@@ -151,10 +165,26 @@ impl Scheduler {
     fn reschedule(&self, reason: RescheduleReason) -> Option<ContextSwitch> {
         self.current
             .try_with_locked(|thread| {
-                thread.stats_mut().cpu_time_ms += u64::from(SCHEDULER_QUANTUM_MS);
+                // Account the time the current thread ran for, and restart its timer
+                // in case it keeps running.
+                let now = crate::time::now().total_micros();
+                let ran = thread.stats_mut().cpu.switch_out(now);
+                thread.root_proc().add_cpu_time(ran);
+                thread.stats_mut().cpu.switch_in(now);
+
+                // The tick is charged to the current thread, which is only rotated
+                // with threads of the same priority once its quantum is used up.
+                let quantum = thread.priority().quantum_ticks();
+                let reason = if matches!(reason, RescheduleReason::Tick)
+                    && thread.stats_mut().slice.tick(quantum)
+                {
+                    RescheduleReason::QuantumExpired
+                } else {
+                    reason
+                };
 
                 let queue = QUEUE.get()?;
-                let Some(mut candidate) = queue.pop_best() else {
+                let Some(mut candidate) = self.pop_candidate(queue) else {
                     // No runnable threads available. This can happen when all idle threads
                     // are already running on other cores. Keep the current thread running.
                     debug_assert!(thread.priority() == Priority::Idle);
@@ -163,10 +193,12 @@ impl Scheduler {
 
                 let action = self.next_action();
 
+                // A thread whose affinity changed may no longer run on this core.
                 let should_stay = matches!(action, ThreadAction::Ready)
+                    && thread.affinity().allows(locals!().core_id())
                     && !queue.should_switch(thread, &candidate, reason);
                 if should_stay {
-                    queue.append(candidate);
+                    append_ready_thread(candidate);
                     return None;
                 }
 
@@ -176,6 +208,8 @@ impl Scheduler {
 
                 debug_assert_eq!(thread.state(), thread::ThreadState::Ready);
                 unsafe { thread.set_state(thread::ThreadState::Running) };
+                old_thread.stats_mut().cpu.switch_out(now);
+                thread.stats_mut().cpu.switch_in(now);
 
                 // Handle stack pointers.
                 let old_stack = Self::old_stack_pointer(&action, &mut old_thread);
@@ -199,6 +233,46 @@ impl Scheduler {
             .flatten()
     }
 
+    #[must_use]
+    /// Returns the best thread to run next on this core.
+    ///
+    /// Among threads of the same priority, those pinned to this core come first,
+    /// as fewer cores can run them. Threads pinned to other cores are only
+    /// stolen if there is no local thread of the same priority.
+    fn pop_candidate(&self, queue: &priority::RoundRobinQueues) -> Option<Box<Thread>> {
+        Priority::DESCENDING.into_iter().find_map(|priority| {
+            self.pinned
+                .pop_priority(priority)
+                .or_else(|| queue.pop_priority(priority))
+                .or_else(|| Self::steal(priority))
+        })
+    }
+
+    #[must_use]
+    /// Takes a ready thread of the given priority, that this core is allowed to run,
+    /// from the pinned queue of another core.
+    ///
+    /// Victims are tried from the closest core in the topology, starting with the SMT siblings,
+    /// so that the stolen thread stays near its caches.
+    /// A thread that may not run on this core is put back at the end of its queue.
+    fn steal(priority: Priority) -> Option<Box<Thread>> {
+        let current = locals!().core_id();
+        crate::cpu::topology()
+            .steal_order(current)
+            .find_map(|victim| {
+                let victim = crate::locals::get_specific_core_locals(victim)?
+                    .scheduler()
+                    .get()?;
+                let thread = victim.pinned.pop_priority(priority)?;
+                if thread.affinity().allows(current) {
+                    Some(thread)
+                } else {
+                    victim.pinned.append(thread);
+                    None
+                }
+            })
+    }
+
     #[inline]
     fn next_action(&self) -> ThreadAction {
         if self.should_exit.swap(false, Ordering::Relaxed) {
@@ -231,17 +305,22 @@ impl Scheduler {
     fn stage_old_thread(action: ThreadAction, mut old_thread: Box<Thread>) {
         match action {
             ThreadAction::Exit => {
+                old_thread.root_proc().thread_exited();
                 // As the scheduler must not acquire locks, it cannot drop heap-allocated memory.
                 // This job should be done by a cleaning thread.
                 FINISHED.get().unwrap().enqueue(old_thread);
             }
             ThreadAction::Sleep(reason) => {
                 unsafe { old_thread.set_state(thread::ThreadState::Sleeping) };
-                SLEEPING.with_locked(|queues| queues.insert(reason, old_thread));
+                if let Some(thread) =
+                    SLEEPING.with_locked(|queues| queues.insert(reason, old_thread))
+                {
+                    enqueue_ready_thread(thread);
+                }
             }
             ThreadAction::Ready => {
                 unsafe { old_thread.set_state(thread::ThreadState::Ready) };
-                QUEUE.get().unwrap().append(old_thread);
+                append_ready_thread(old_thread);
             }
         }
     }
@@ -256,7 +335,9 @@ enum ThreadAction {
 
 #[derive(Debug, Clone, Copy)]
 enum RescheduleReason {
-    /// Periodic timer tick (time slice expired).
+    /// Periodic timer tick.
+    Tick,
+    /// Periodic timer tick, after which the current thread has used up its quantum.
     QuantumExpired,
     /// Explicit yield request from the running thread.
     ExplicitYield,
@@ -292,7 +373,56 @@ fn wake_sleeping_threads() {
 
 fn enqueue_ready_thread(mut thread: Box<Thread>) {
     unsafe { thread.set_state(thread::ThreadState::Ready) };
-    QUEUE.get().unwrap().append(thread);
+    append_ready_thread(thread);
+}
+
+/// Appends a ready thread to the queue of its home core, or to the shared queue
+/// if it may run on every core.
+///
+/// See `CpuMask::home_core`.
+fn append_ready_thread(thread: Box<Thread>) {
+    let home_core = thread
+        .affinity()
+        .home_core(locals!().core_id(), crate::locals::core_count());
+    // Cores start their scheduler before userspace can change an affinity.
+    let home_scheduler = home_core
+        .and_then(crate::locals::get_specific_core_locals)
+        .and_then(|locals| locals.scheduler().get());
+
+    if let Some(scheduler) = home_scheduler {
+        scheduler.pinned.append(thread);
+    } else {
+        QUEUE.get().unwrap().append(thread);
+    }
+}
+
+/// Moves the ready threads that used to run at `previous` to the queue of their priority,
+/// after one of them inherited a higher priority.
+///
+/// Run queues cannot remove a given thread, so the queues of `previous` are drained and refilled.
+fn requeue_raised(previous: Priority) {
+    let mut drained = Vec::new();
+    let mut requeue = |queue: &priority::RoundRobinQueues| {
+        while let Some(thread) = queue.pop_priority(previous) {
+            drained.push(thread);
+        }
+        for thread in drained.drain(..) {
+            queue.append(thread);
+        }
+    };
+
+    // Interrupts are disabled so that the scheduler of this core does not
+    // wait on a queue that is being drained.
+    without_interrupts(|| {
+        requeue(QUEUE.get().unwrap());
+        for core_id in 0..crate::locals::core_count() {
+            if let Some(scheduler) = crate::locals::get_specific_core_locals(core_id)
+                .and_then(|locals| locals.scheduler().get())
+            {
+                requeue(&scheduler.pinned);
+            }
+        }
+    });
 }
 
 /// A thread should be spawned with this function.
@@ -311,6 +441,7 @@ extern "C" fn guard_thread() -> ! {
 
 extern "C" fn idle() -> ! {
     loop {
+        let _ = crate::softirq::run_pending();
         crate::arch::halt();
     }
 }
@@ -343,6 +474,30 @@ pub fn current_thread_id() -> ThreadId {
     })
 }
 
+#[must_use]
+#[inline]
+/// Returns the current thread's priority.
+pub fn current_thread_priority() -> Priority {
+    with_scheduler(|scheduler| {
+        // Safety:
+        // Interrupts are disabled, so the current thread cannot change.
+        unsafe { scheduler.current.force_lock() }.priority()
+    })
+}
+
+#[must_use]
+#[inline]
+/// Returns the current thread's ID and base priority, along with the priority it inherits.
+fn current_inheritance() -> (ThreadId, Priority, Arc<InheritedPriority>) {
+    with_scheduler(|scheduler| {
+        // Safety:
+        // Interrupts are disabled, so the current thread cannot change.
+        let thread = unsafe { scheduler.current.force_lock() };
+        let inherited = thread.inherited_priority().unwrap();
+        (thread.id(), thread.base_priority(), inherited)
+    })
+}
+
 #[must_use]
 #[inline]
 /// Returns the current thread's state.
@@ -383,7 +538,12 @@ pub fn set_scheduling(enable: bool) {
 ///
 /// The context will be brutally switched without returning.
 /// If any locks are acquired, they will be poisoned.
-pub unsafe fn exit_current_thread() -> ! {
+pub unsafe fn exit_current_thread(exit_code: ExitCode) -> ! {
+    // Let a joiner know about the exit
+    let process = current_process();
+    process.mark_exited(current_thread_id().as_u64(), exit_code);
+    drop(process);
+
     with_scheduler(Scheduler::set_exit);
 
     // Try to reschedule the thread.
@@ -396,6 +556,40 @@ pub unsafe fn exit_current_thread() -> ! {
     }
 }
 
+/// Exits the current thread if its process is exiting.
+///
+/// # Safety
+///
+/// See `exit_current_thread`.
+pub unsafe fn exit_if_process_exiting() {
+    if current_process().is_exiting() {
+        unsafe { exit_current_thread(ExitCode::Failure) };
+    }
+}
+
+/// Restricts the cores the current thread may run on.
+///
+/// If the current core is not allowed, the thread moves to an allowed one right away.
+///
+/// # Errors
+///
+/// Returns an error if the mask does not allow any online core.
+pub fn set_current_affinity(affinity: CpuMask) -> Result<(), AffinityError> {
+    affinity.check(crate::locals::core_count())?;
+
+    let allowed_here = with_scheduler(|scheduler| {
+        // Safety:
+        // Interrupts are disabled, so the current thread cannot change.
+        unsafe { scheduler.current.force_lock() }.set_affinity(affinity);
+        affinity.allows(locals!().core_id())
+    });
+    if !allowed_here {
+        thread_yield();
+    }
+
+    Ok(())
+}
+
 /// Hint to the scheduler to reschedule the current thread.
 pub fn thread_yield() {
     let context_switch = reschedule(RescheduleReason::ExplicitYield);
@@ -417,6 +611,7 @@ pub struct Yield;
 impl hyperdrive::locks::RelaxStrategy for Yield {
     #[inline]
     fn relax() {
+        crate::debug_assert_not_in_interrupt!();
         thread_yield();
     }
 }
@@ -442,6 +637,7 @@ pub fn sleep_on(handle: SleepHandle) {
 }
 
 fn request_sleep(reason: SleepReason) {
+    crate::debug_assert_not_in_interrupt!();
     with_scheduler(|scheduler| scheduler.set_sleep(reason));
     thread_yield();
 }
@@ -467,8 +663,9 @@ pub fn wake_event_all(handle: SleepHandle) -> usize {
 
 /// Wakes up a thread that is sleeping.
 ///
-/// Returns `true` if the thread was woken up,
-/// `false` if the thread was not sleeping.
+/// Returns `true` if the thread was woken up, `false` if the thread was not sleeping.
+/// In that case, its next sleep returns right away, so that a wake-up sent
+/// between its decision to sleep and the sleep itself is not lost.
 pub fn wake_up(thread: ThreadId) -> bool {
     SLEEPING
         .with_locked(|sleepers| sleepers.wake_thread(thread))
@@ -0,0 +1,284 @@
+//! Sleeping mutex with priority inheritance.
+//!
+//! Unlike the spinning locks of `hyperdrive`, a thread blocked on a `Mutex` sleeps.
+//! While it sleeps, it lends its priority to the holder, and transitively to the
+//! holders the holder waits on, see `beskar_core::process::inheritance`.
+use super::{Priority, priority::InheritedPriority, thread::ThreadId};
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
+use beskar_core::process::inheritance::{self, LockGraph};
+use beskar_hal::instructions::without_interrupts;
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use hyperdrive::locks::mcs::McsLock;
+
+/// Threads and mutexes involved in priority inheritance.
+static TABLE: McsLock<Table> = McsLock::new(Table::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct MutexId(u64);
+
+struct ThreadEntry {
+    base: Priority,
+    inherited: Arc<InheritedPriority>,
+    blocked_on: Option<MutexId>,
+    /// Number of mutexes the thread holds.
+    held: usize,
+}
+
+#[derive(Default)]
+struct MutexEntry {
+    holder: Option<ThreadId>,
+    waiters: VecDeque<ThreadId>,
+}
+
+/// Entries only exist while a thread holds or waits on a mutex.
+struct Table {
+    threads: BTreeMap<ThreadId, ThreadEntry>,
+    mutexes: BTreeMap<MutexId, MutexEntry>,
+}
+
+impl Table {
+    #[must_use]
+    #[inline]
+    const fn new() -> Self {
+        Self {
+            threads: BTreeMap::new(),
+            mutexes: BTreeMap::new(),
+        }
+    }
+
+    fn register(&mut self, thread: ThreadId, base: Priority, inherited: &Arc<InheritedPriority>) {
+        self.threads.entry(thread).or_insert_with(|| ThreadEntry {
+            base,
+            inherited: inherited.clone(),
+            blocked_on: None,
+            held: 0,
+        });
+    }
+
+    fn thread(&mut self, thread: ThreadId) -> &mut ThreadEntry {
+        self.threads.get_mut(&thread).unwrap()
+    }
+
+    /// Removes the entries of `mutex` and `threads` that are no longer in use.
+    fn prune(&mut self, mutex: MutexId, threads: impl IntoIterator<Item = ThreadId>) {
+        if self
+            .mutexes
+            .get(&mutex)
+            .is_some_and(|entry| entry.holder.is_none() && entry.waiters.is_empty())
+        {
+            self.mutexes.remove(&mutex);
+        }
+        for thread in threads {
+            if self
+                .threads
+                .get(&thread)
+                .is_some_and(|entry| entry.held == 0 && entry.blocked_on.is_none())
+            {
+                self.threads.remove(&thread);
+            }
+        }
+    }
+}
+
+impl LockGraph for Table {
+    type Thread = ThreadId;
+    type Lock = MutexId;
+    type Priority = Priority;
+
+    fn holder(&self, lock: MutexId) -> Option<ThreadId> {
+        self.mutexes.get(&lock).and_then(|entry| entry.holder)
+    }
+
+    fn set_holder(&mut self, lock: MutexId, holder: Option<ThreadId>) {
+        let entry = self.mutexes.entry(lock).or_default();
+        let previous = core::mem::replace(&mut entry.holder, holder);
+        if let Some(previous) = previous {
+            self.thread(previous).held -= 1;
+        }
+        if let Some(holder) = holder {
+            self.thread(holder).held += 1;
+        }
+    }
+
+    fn waiters(&self, lock: MutexId) -> impl Iterator<Item = ThreadId> {
+        self.mutexes
+            .get(&lock)
+            .into_iter()
+            .flat_map(|entry| entry.waiters.iter().copied())
+    }
+
+    fn add_waiter(&mut self, lock: MutexId, thread: ThreadId) {
+        self.mutexes
+            .entry(lock)
+            .or_default()
+            .waiters
+            .push_back(thread);
+        self.thread(thread).blocked_on = Some(lock);
+    }
+
+    fn remove_waiter(&mut self, lock: MutexId, thread: ThreadId) {
+        if let Some(entry) = self.mutexes.get_mut(&lock) {
+            entry.waiters.retain(|&waiter| waiter != thread);
+        }
+        self.thread(thread).blocked_on = None;
+    }
+
+    fn blocked_on(&self, thread: ThreadId) -> Option<MutexId> {
+        self.threads.get(&thread).and_then(|entry| entry.blocked_on)
+    }
+
+    fn held(&self, thread: ThreadId) -> impl Iterator<Item = MutexId> {
+        self.mutexes
+            .iter()
+            .filter(move |(_, entry)| entry.holder == Some(thread))
+            .map(|(&id, _)| id)
+    }
+
+    fn base_priority(&self, thread: ThreadId) -> Priority {
+        self.threads[&thread].base
+    }
+
+    fn boost(&self, thread: ThreadId) -> Option<Priority> {
+        self.threads[&thread].inherited.get()
+    }
+
+    fn set_boost(&mut self, thread: ThreadId, boost: Option<Priority>) {
+        self.threads[&thread].inherited.set(boost);
+    }
+}
+
+/// A mutual exclusion lock whose waiters sleep, and lend their priority to the holder.
+///
+/// It must not be used in interrupt handlers.
+pub struct Mutex<T: ?Sized> {
+    /// Assigned on first use, so that mutexes can be created in constant contexts.
+    id: AtomicU64,
+    value: UnsafeCell<T>,
+}
+
+// Safety: The value is only reachable through a guard, of which there is at most one.
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    #[must_use]
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            id: AtomicU64::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    #[must_use]
+    fn id(&self) -> MutexId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        let id = self.id.load(Ordering::Relaxed);
+        if id != 0 {
+            return MutexId(id);
+        }
+        let new = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        match self
+            .id
+            .compare_exchange(0, new, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => MutexId(new),
+            Err(id) => MutexId(id),
+        }
+    }
+
+    /// Locks the mutex, sleeping until it is available.
+    ///
+    /// While the current thread sleeps, the holder runs at least at its priority.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread already holds the mutex.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        crate::debug_assert_not_in_interrupt!();
+        let (me, base, inherited) = super::current_inheritance();
+        let id = self.id();
+
+        let mut raised = Vec::new();
+        let acquired = without_interrupts(|| {
+            TABLE.with_locked(|table| {
+                table.register(me, base, &inherited);
+                inheritance::acquire(table, id, me, |_, previous| raised.push(previous))
+            })
+        });
+        if !acquired {
+            for previous in raised {
+                super::requeue_raised(previous);
+            }
+            // The mutex is handed over by its holder, which then wakes the waiter up.
+            // A wake-up sent before the waiter is asleep cancels its sleep instead.
+            loop {
+                super::sleep();
+                if without_interrupts(|| {
+                    TABLE.with_locked(|table| inheritance::granted(table, id, me))
+                }) {
+                    break;
+                }
+            }
+        }
+
+        MutexGuard { mutex: self }
+    }
+}
+
+/// Gives access to the value of a locked `Mutex`, and unlocks it when dropped.
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: The guard is the only way to the value while the mutex is locked.
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: The guard is the only way to the value while the mutex is locked.
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        let me = super::current_thread_id();
+        let id = self.mutex.id();
+
+        let (next, lowered) = without_interrupts(|| {
+            TABLE.with_locked(|table| {
+                let before = inheritance::effective_priority(table, me);
+                let next = inheritance::release(table, id, me);
+                let lowered = inheritance::effective_priority(table, me) < before;
+                table.prune(id, [me].into_iter().chain(next));
+                (next, lowered)
+            })
+        });
+
+        if let Some(next) = next {
+            super::wake_up(next);
+        }
+        // The thread that was lending its priority should run before this one.
+        if lowered {
+            super::thread_yield();
+        }
+    }
+}
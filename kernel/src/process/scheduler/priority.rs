@@ -4,8 +4,18 @@
 use super::thread::Thread;
 use crate::process::Process;
 use alloc::{boxed::Box, sync::Arc};
+use core::sync::atomic::{AtomicU8, AtomicU64, Ordering};
 use hyperdrive::queues::mpsc::MpscQueue;
 
+/// Quantum of each priority level, in milliseconds.
+static QUANTA_MS: [AtomicU64; 5] = [
+    AtomicU64::new(Priority::Idle.default_quantum_ms()),
+    AtomicU64::new(Priority::Low.default_quantum_ms()),
+    AtomicU64::new(Priority::Normal.default_quantum_ms()),
+    AtomicU64::new(Priority::High.default_quantum_ms()),
+    AtomicU64::new(Priority::Realtime.default_quantum_ms()),
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Priority {
     Idle = 0,
@@ -18,6 +28,54 @@ pub enum Priority {
     Realtime = 4,
 }
 
+impl Priority {
+    /// Every priority, from the highest to the lowest.
+    pub const DESCENDING: [Self; 5] = [
+        Self::Realtime,
+        Self::High,
+        Self::Normal,
+        Self::Low,
+        Self::Idle,
+    ];
+
+    #[must_use]
+    #[inline]
+    /// The quantum a thread of this priority runs for before it is preempted,
+    /// in milliseconds, unless changed with `set_quantum`.
+    ///
+    /// Threads of high priority are expected to block soon, so they get short quanta
+    /// and take turns quickly. Threads of low priority are expected to run for long,
+    /// so they get long quanta and are switched less often.
+    /// Idle threads always give way to other threads.
+    pub const fn default_quantum_ms(self) -> u64 {
+        match self {
+            Self::Idle | Self::Normal => 30,
+            Self::Low => 60,
+            Self::High => 20,
+            Self::Realtime => 10,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// The current quantum of this priority, in timer ticks.
+    ///
+    /// It is rounded up to a whole number of ticks, and is at least one tick.
+    pub fn quantum_ticks(self) -> u32 {
+        let ms = QUANTA_MS[self as usize].load(Ordering::Relaxed);
+        let ticks = ms.div_ceil(u64::from(crate::arch::apic::MS_PER_INTERRUPT));
+        u32::try_from(ticks).unwrap_or(u32::MAX).max(1)
+    }
+}
+
+/// Sets the quantum of a priority level.
+///
+/// It is rounded up to a whole number of timer ticks, and applies to the running threads
+/// from the next tick on.
+pub fn set_quantum(priority: Priority, quantum: crate::time::Duration) {
+    QUANTA_MS[priority as usize].store(quantum.total_millis(), Ordering::Relaxed);
+}
+
 impl TryFrom<u8> for Priority {
     type Error = ();
 
@@ -38,6 +96,38 @@ impl From<Priority> for u8 {
     }
 }
 
+/// The priority a thread inherited from the waiters of the mutexes it holds.
+///
+/// It is shared with the mutexes, so that it can be raised while the thread
+/// sits in a run queue or runs on another core.
+pub struct InheritedPriority(AtomicU8);
+
+impl InheritedPriority {
+    /// Raw value standing for no inherited priority.
+    const NONE: u8 = u8::MAX;
+
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self(AtomicU8::new(Self::NONE))
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn get(&self) -> Option<Priority> {
+        let raw = self.0.load(Ordering::Acquire);
+        Priority::DESCENDING
+            .into_iter()
+            .find(|&priority| u8::from(priority) == raw)
+    }
+
+    #[inline]
+    pub fn set(&self, priority: Option<Priority>) {
+        self.0
+            .store(priority.map_or(Self::NONE, u8::from), Ordering::Release);
+    }
+}
+
 /// A trait for managing thread queues.
 ///
 /// # Safety
@@ -79,6 +169,18 @@ impl RoundRobinQueues {
             realtime: MpscQueue::new(Box::new(Thread::new_stub(root_proc))),
         }
     }
+
+    #[must_use]
+    /// Returns the next thread of the given priority, if any.
+    pub fn pop_priority(&self, priority: Priority) -> Option<Box<Thread>> {
+        match priority {
+            Priority::Idle => self.idle.dequeue(),
+            Priority::Low => self.low.dequeue(),
+            Priority::Normal => self.normal.dequeue(),
+            Priority::High => self.high.dequeue(),
+            Priority::Realtime => self.realtime.dequeue(),
+        }
+    }
 }
 
 unsafe impl ThreadQueue for RoundRobinQueues {
@@ -104,14 +206,9 @@ unsafe impl ThreadQueue for RoundRobinQueues {
 
     fn pop_best(&self) -> Option<Box<Thread>> {
         // Try each queue in order of priority
-        for queue in [&self.realtime, &self.high, &self.normal, &self.low] {
-            if let Some(thread) = queue.dequeue() {
-                return Some(thread);
-            }
-        }
-
-        // Finally, try idle
-        self.idle.dequeue()
+        Priority::DESCENDING
+            .into_iter()
+            .find_map(|priority| self.pop_priority(priority))
     }
 
     fn should_switch(
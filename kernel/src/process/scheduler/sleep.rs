@@ -2,7 +2,9 @@ use super::Thread;
 use crate::process::scheduler::thread::ThreadId;
 use alloc::{
     boxed::Box,
-    collections::{binary_heap::BinaryHeap, btree_map::BTreeMap, vec_deque::VecDeque},
+    collections::{
+        binary_heap::BinaryHeap, btree_map::BTreeMap, btree_set::BTreeSet, vec_deque::VecDeque,
+    },
     vec::Vec,
 };
 use beskar_core::{
@@ -41,6 +43,8 @@ pub struct SleepQueues {
     timers: BinaryHeap<Reverse<TimerKey>>, // Min-heap via Reverse.
     events: BTreeMap<SleepHandle, VecDeque<ThreadId>>,
     indefinite: Vec<ThreadId>,
+    /// Threads woken up before they were put to sleep, whose next sleep is cancelled.
+    woken_early: BTreeSet<ThreadId>,
 }
 
 impl SleepQueues {
@@ -52,11 +56,21 @@ impl SleepQueues {
             timers: BinaryHeap::new(),
             events: BTreeMap::new(),
             indefinite: Vec::new(),
+            woken_early: BTreeSet::new(),
         }
     }
 
-    pub fn insert(&mut self, reason: SleepReason, mut thread: Box<Thread>) {
+    #[must_use]
+    /// Puts `thread` to sleep.
+    ///
+    /// If it was woken up while falling asleep, see `wake_thread`, it is returned
+    /// and should be made ready again.
+    pub fn insert(&mut self, reason: SleepReason, mut thread: Box<Thread>) -> Option<Box<Thread>> {
         let tid = thread.id();
+        if self.woken_early.remove(&tid) {
+            return Some(thread);
+        }
+
         if let Some(deadline) = reason.deadline() {
             thread.stats_mut().wake_time = deadline;
         } else {
@@ -74,6 +88,7 @@ impl SleepQueues {
             }
             SleepReason::Indefinite => self.indefinite.push(tid),
         }
+        None
     }
 
     pub fn pop_ready(&mut self, now: Instant) -> Option<Box<Thread>> {
@@ -119,8 +134,14 @@ impl SleepQueues {
         ready
     }
 
+    /// Wakes up `tid`, returning it if it was asleep.
+    ///
+    /// Otherwise, the thread may be about to sleep, so its next sleep is cancelled.
     pub fn wake_thread(&mut self, tid: ThreadId) -> Option<Box<Thread>> {
-        let sleeper = self.sleepers.remove(&tid)?;
+        let Some(sleeper) = self.sleepers.remove(&tid) else {
+            self.woken_early.insert(tid);
+            return None;
+        };
 
         match sleeper.reason {
             SleepReason::Event(handle) => {
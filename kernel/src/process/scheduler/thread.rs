@@ -1,13 +1,24 @@
 use crate::{
     arch::context::ThreadRegisters,
     mem::frame_alloc,
-    process::binary::{Binary, BinaryType, LoadedBinary},
+    process::binary::{Binary, BinaryType, LoadedBinary, TlsTemplate},
     storage::vfs,
 };
 use alloc::{boxed::Box, sync::Arc, vec::Vec};
-use beskar_core::arch::{
-    Alignment, VirtAddr,
-    paging::{CacheFlush, FrameAllocator, M4KiB, Mapper, MemSize, PageRangeInclusive},
+use beskar_core::{
+    arch::{
+        Alignment, VirtAddr,
+        paging::{CacheFlush, FrameAllocator, M4KiB, Mapper, MemSize, Page, PageRangeInclusive},
+    },
+    mem::{
+        ranges::MemoryRange,
+        vma::{Backing, Vma, VmaFlags},
+    },
+    process::{
+        accounting::{CpuTimer, TimeSlice},
+        affinity::CpuMask,
+        tls::{Tcb, TlsLayout},
+    },
 };
 #[cfg(debug_assertions)]
 use beskar_hal::instructions::STACK_DEBUG_INSTR;
@@ -23,15 +34,30 @@ use hyperdrive::{
 };
 use storage::fs::Path;
 
-use super::{super::Process, priority::Priority};
+use super::{
+    super::Process,
+    priority::{InheritedPriority, Priority},
+};
 
 /// The minimum amount of stack space that must be left unused on thread creation.
 const MINIMUM_LEFTOVER_STACK: usize = 0x100; // 256 bytes
 
+/// Maximum size of a user stack.
+///
+/// This much address space is reserved for the stack of the main thread, but pages
+/// are only mapped as the stack grows down.
+const USER_STACK_MAX_SIZE: u64 = beskar_core::syscall::consts::THREAD_STACK_MAX_SIZE;
+
+/// Size of the top of a user stack that is mapped upfront.
+const USER_STACK_INITIAL_SIZE: u64 = 4 * M4KiB::SIZE;
+
 /// Thread statistics
 #[derive(Debug, Clone, Copy)]
 pub struct ThreadStats {
-    pub cpu_time_ms: u64,
+    /// CPU time of the thread, in microseconds.
+    pub cpu: CpuTimer,
+    /// Timer ticks left before the thread is preempted.
+    pub slice: TimeSlice,
     pub wake_time: beskar_core::time::Instant,
 }
 
@@ -40,7 +66,8 @@ impl ThreadStats {
     #[inline]
     pub const fn new() -> Self {
         Self {
-            cpu_time_ms: 0,
+            cpu: CpuTimer::new(u64::BITS),
+            slice: TimeSlice::new(),
             wake_time: beskar_core::time::Instant::ZERO,
         }
     }
@@ -59,6 +86,8 @@ pub struct Thread {
     root_proc: Arc<Process>,
     /// The priority of the thread.
     priority: Priority,
+    /// The cores the thread may run on.
+    affinity: CpuMask,
     /// The state of the thread.
     state: ThreadState,
     /// Used to keep ownership of the stacks when needed.
@@ -67,8 +96,12 @@ pub struct Thread {
     last_stack_ptr: AtomicPtr<u8>,
     /// Thread Local Storage
     tls: Once<Tls>,
+    /// Where the thread enters userspace, if it was created by userspace.
+    user_entry: Option<UserEntry>,
     /// Thread statistics for scheduling
     stats: ThreadStats,
+    /// The priority inherited through mutexes, `None` for stubs.
+    inherited: Option<Arc<InheritedPriority>>,
 
     /// Link to the next thread in the queue.
     link: Link<Self>,
@@ -113,17 +146,21 @@ impl Thread {
     #[must_use]
     #[inline]
     pub(in super::super) fn new_kernel(kernel_process: Arc<Process>) -> Self {
+        kernel_process.thread_started();
         Self {
             id: ThreadId::new(),
             root_proc: kernel_process,
             priority: Priority::High,
+            affinity: CpuMask::ALL,
             state: ThreadState::Running,
             stack: None,
             // Will be overwritten before being used.
             last_stack_ptr: AtomicPtr::new(core::ptr::null_mut()),
             link: Link::new(),
             tls: Once::uninit(),
+            user_entry: None,
             stats: ThreadStats::new(),
+            inherited: Some(Arc::new(InheritedPriority::new())),
         }
     }
 
@@ -140,19 +177,37 @@ impl Thread {
         let stack_unused = Self::setup_stack(stack_ptr, &mut stack, entry_point);
         stack_ptr = unsafe { stack_ptr.byte_add(stack_unused) }; // Move stack pointer to the end of the stack
 
+        root_proc.thread_started();
+
         Self {
             id: ThreadId::new(),
             root_proc,
             priority,
+            affinity: CpuMask::ALL,
             state: ThreadState::Ready,
             stack: Some(ThreadStacks::new(stack)),
             last_stack_ptr: AtomicPtr::new(stack_ptr),
             link: Link::new(),
             tls: Once::uninit(),
+            user_entry: None,
             stats: ThreadStats::new(),
+            inherited: Some(Arc::new(InheritedPriority::new())),
         }
     }
 
+    #[must_use]
+    /// Create a new thread of a user process, entering userspace at `user_entry`.
+    pub fn new_user(
+        root_proc: Arc<Process>,
+        priority: Priority,
+        stack: Vec<u8>,
+        user_entry: UserEntry,
+    ) -> Self {
+        let mut thread = Self::new(root_proc, priority, stack, user_thread_trampoline);
+        thread.user_entry = Some(user_entry);
+        thread
+    }
+
     /// Setup the stack and move stack pointer to the end of the stack.
     fn setup_stack(
         stack_ptr: *mut u8,
@@ -194,12 +249,15 @@ impl Thread {
             id: ThreadId(0),
             root_proc,
             priority: Priority::Low,
+            affinity: CpuMask::ALL,
             state: ThreadState::Ready,
             stack: None,
             last_stack_ptr: AtomicPtr::new(core::ptr::null_mut()),
             link: Link::new(),
             tls: Once::uninit(),
+            user_entry: None,
             stats: ThreadStats::new(),
+            inherited: None,
         }
     }
 
@@ -221,10 +279,41 @@ impl Thread {
 
     #[must_use]
     #[inline]
-    pub const fn priority(&self) -> Priority {
+    /// Returns the priority the thread runs at, including what it inherited through mutexes.
+    pub fn priority(&self) -> Priority {
+        self.inherited
+            .as_ref()
+            .and_then(|inherited| inherited.get())
+            .map_or(self.priority, |inherited| inherited.max(self.priority))
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the priority the thread was given, regardless of inheritance.
+    pub const fn base_priority(&self) -> Priority {
         self.priority
     }
 
+    #[must_use]
+    #[inline]
+    pub(super) fn inherited_priority(&self) -> Option<Arc<InheritedPriority>> {
+        self.inherited.clone()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn affinity(&self) -> CpuMask {
+        self.affinity
+    }
+
+    #[inline]
+    /// Restricts the cores the thread may run on.
+    ///
+    /// It is honored the next time the thread is made ready.
+    pub(super) const fn set_affinity(&mut self, affinity: CpuMask) {
+        self.affinity = affinity;
+    }
+
     #[must_use]
     #[inline]
     pub const fn state(&self) -> ThreadState {
@@ -249,6 +338,13 @@ impl Thread {
         self.root_proc.clone()
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the process that this thread belongs to, without cloning the `Arc`.
+    pub fn root_proc(&self) -> &Process {
+        &self.root_proc
+    }
+
     #[must_use]
     #[inline]
     /// Returns the value of the last stack pointer.
@@ -392,65 +488,141 @@ pub extern "C" fn user_trampoline() -> ! {
     let loaded_binary = thread_load_binary(root_proc.binary().unwrap());
 
     // Allocate a user stack
-    let rsp = super::with_scheduler(|scheduler| {
+    let rsp = allocate_user_stack(USER_STACK_MAX_SIZE);
+
+    if let Some(tlst) = loaded_binary.tls_template() {
+        root_proc.set_tls_template(tlst);
+    }
+    setup_tls(&root_proc, loaded_binary.tls_template());
+
+    drop(root_proc); // Decrease the reference count of the process
+    unsafe { crate::arch::userspace::enter_usermode(loaded_binary.entry_point(), rsp) };
+}
+
+/// Trampoline function of the threads created by userspace.
+///
+/// The binary is already loaded, so the thread only needs a stack and a TLS block
+/// before entering userspace.
+///
+/// # Warning
+///
+/// This function should not be called directly, but rather be used
+/// as an entry point for threads.
+extern "C" fn user_thread_trampoline() -> ! {
+    let root_proc = super::current_process();
+    let user_entry = super::with_scheduler(|scheduler| {
+        scheduler
+            .current
+            .with_locked(|thread| thread.user_entry)
+            .expect("User thread has no entry point")
+    });
+
+    let rsp = allocate_user_stack(user_entry.stack_size);
+
+    setup_tls(&root_proc, root_proc.tls_template());
+
+    drop(root_proc); // Decrease the reference count of the process
+    unsafe {
+        crate::arch::userspace::enter_usermode_with_arg(
+            user_entry.entry.as_u64(),
+            rsp,
+            user_entry.arg,
+        )
+    };
+}
+
+/// Allocates the user stack of the current thread, which can grow up to `max_size` bytes.
+///
+/// Returns the top of the stack.
+fn allocate_user_stack(max_size: u64) -> *mut u8 {
+    super::with_scheduler(|scheduler| {
         scheduler.current.with_locked(|thread| {
             thread.stack.as_mut().map(|ts| {
-                ts.allocate_all(4 * M4KiB::SIZE);
+                ts.allocate_all(USER_STACK_INITIAL_SIZE, max_size);
                 ts.user_stack_top().unwrap()
             })
         })
     })
     .expect("Current thread stack allocation failed")
-    .as_ptr();
+    .as_ptr()
+}
 
-    if let Some(tlst) = loaded_binary.tls_template() {
-        let tls_size = tlst.mem_size();
-
-        let pages = root_proc
-            .address_space()
-            .alloc_map::<M4KiB>(
-                usize::try_from(tls_size).unwrap(),
-                Flags::PRESENT | Flags::WRITABLE | Flags::USER_ACCESSIBLE,
-            )
-            .unwrap();
-        let tls_vaddr = pages.start().start_address();
+/// Allocates the TLS area of the current thread, and loads it.
+///
+/// The area holds a copy of the TLS template, if there is one, followed by the TCB.
+/// See `beskar_core::process::tls` for the layout.
+fn setup_tls(root_proc: &Process, tlst: Option<TlsTemplate>) {
+    let (mem_size, align) = tlst.map_or((0, 1), |tlst| (tlst.mem_size(), tlst.align()));
+    // The area is page-aligned, which must be enough for the block
+    assert!(align <= M4KiB::SIZE, "TLS block is over-aligned");
+    let layout = TlsLayout::new(mem_size, align).expect("Invalid TLS template");
+
+    let pages = root_proc
+        .address_space()
+        .alloc_map::<M4KiB>(
+            usize::try_from(layout.size()).unwrap(),
+            Flags::PRESENT | Flags::WRITABLE | Flags::USER_ACCESSIBLE | Flags::NO_EXECUTE,
+        )
+        .unwrap();
+    let area = pages.start().start_address();
 
-        // Copy TLS initialization image from binary
+    // Zero the whole area, which also zeroes the uninitialized part of the block
+    unsafe {
+        area.as_mut_ptr::<u8>()
+            .write_bytes(0, usize::try_from(pages.size()).unwrap());
+    }
+    // Copy TLS initialization image from binary
+    if let Some(tlst) = tlst {
         unsafe {
-            tls_vaddr.as_mut_ptr::<u8>().copy_from_nonoverlapping(
+            area.as_mut_ptr::<u8>().copy_from_nonoverlapping(
                 tlst.start().as_ptr(),
                 tlst.file_size().try_into().unwrap(),
             );
         }
-        // Zero the rest of the TLS area
-        let allocated_size = usize::try_from(pages.size()).unwrap();
-        unsafe {
-            tls_vaddr
-                .as_mut_ptr::<u8>()
-                .byte_add(tlst.file_size().try_into().unwrap())
-                .write_bytes(
-                    0,
-                    allocated_size - usize::try_from(tlst.file_size()).unwrap(),
-                );
-        }
+    }
 
-        let tls = Tls {
-            addr: tls_vaddr,
-            size: tls_size,
-        };
+    let tp = area + layout.tp_offset();
+    let tcb = Tcb {
+        self_ptr: tp.as_u64(),
+        thread_id: super::current_thread_id().as_u64(),
+    };
+    unsafe { tp.as_mut_ptr::<Tcb>().write(tcb) };
 
-        // Locking the scheduler's current thread is a bit ugly, but it is better than force locking it
-        // (as otherwise the scheduler could get stuck on `Once::get`).
-        super::with_scheduler(|scheduler| {
-            scheduler.current.with_locked(|thread| {
-                thread.tls.call_once(|| tls);
-            });
+    let tls = Tls {
+        addr: tp,
+        size: layout.size(),
+    };
+
+    // Locking the scheduler's current thread is a bit ugly, but it is better than force locking it
+    // (as otherwise the scheduler could get stuck on `Once::get`).
+    super::with_scheduler(|scheduler| {
+        scheduler.current.with_locked(|thread| {
+            thread.tls.call_once(|| tls);
         });
-        crate::arch::locals::store_thread_locals(tls);
-    }
+    });
+    crate::arch::locals::store_thread_locals(tls);
+}
 
-    drop(root_proc); // Decrease the reference count of the process
-    unsafe { crate::arch::userspace::enter_usermode(loaded_binary.entry_point(), rsp) };
+#[derive(Debug, Clone, Copy)]
+/// Where a thread created by userspace starts.
+pub struct UserEntry {
+    /// Address of the entry point, called with `arg`.
+    entry: VirtAddr,
+    arg: u64,
+    /// Maximum size of the user stack.
+    stack_size: u64,
+}
+
+impl UserEntry {
+    #[must_use]
+    #[inline]
+    pub const fn new(entry: VirtAddr, arg: u64, stack_size: u64) -> Self {
+        Self {
+            entry,
+            arg,
+            stack_size,
+        }
+    }
 }
 
 struct ThreadStacks {
@@ -459,7 +631,9 @@ struct ThreadStacks {
     /// This can be the only stack used (ring0 processes) or
     /// only used by the trampoline function (ring3 processes).
     kernel: Vec<u8>,
-    /// Page range in the process' address space of the stack.
+    /// Page range reserved in the process' address space for the stack.
+    ///
+    /// Only the top of it is mapped at first, the rest is mapped on page faults.
     user_pages: Once<PageRangeInclusive>,
 }
 
@@ -475,13 +649,15 @@ impl ThreadStacks {
         }
     }
 
-    pub fn allocate_all(&self, size: u64) {
-        self.allocate_user(size);
+    pub fn allocate_all(&self, size: u64, max_size: u64) {
+        self.allocate_user(size, max_size);
     }
 
-    pub fn allocate_user(&self, size: u64) {
+    /// Reserves `max_size` bytes for the user stack, and maps its top `size` bytes.
+    pub fn allocate_user(&self, size: u64, max_size: u64) {
         let flags = Flags::PRESENT | Flags::WRITABLE | Flags::USER_ACCESSIBLE;
-        self.user_pages.call_once(|| Self::allocate(size, flags));
+        self.user_pages
+            .call_once(|| Self::allocate_growable(size, max_size, flags));
     }
 
     #[must_use]
@@ -501,25 +677,41 @@ impl ThreadStacks {
         }
     }
 
-    fn allocate(size: u64, flags: Flags) -> PageRangeInclusive {
+    fn allocate_growable(size: u64, max_size: u64, flags: Flags) -> PageRangeInclusive {
         assert!(size >= u64::from(Self::STACK_ALIGNMENT));
+        assert!(size <= max_size);
+
+        let process = super::current_process();
+        let address_space = process.address_space();
 
-        let (_guard_start, page_range, _guard_end) = super::current_process()
-            .address_space()
-            .with_pgalloc(|palloc| palloc.allocate_guarded(size.div_ceil(M4KiB::SIZE)))
+        let (_guard_start, reserved, _guard_end) = address_space
+            .with_pgalloc(|palloc| palloc.allocate_guarded(max_size.div_ceil(M4KiB::SIZE)))
             .unwrap();
+        let mapped_pages = size.div_ceil(M4KiB::SIZE);
+        let page_range = Page::range_inclusive(reserved.end() - (mapped_pages - 1), reserved.end());
 
         frame_alloc::with_frame_allocator(|fralloc| {
-            super::current_process()
-                .address_space()
-                .with_page_table(|pt| {
-                    for page in page_range {
-                        let frame = fralloc.allocate_frame().unwrap();
-                        pt.map(page, frame, flags, fralloc).unwrap().flush();
-                    }
-                });
+            address_space.with_page_table(|pt| {
+                for page in page_range {
+                    let frame = fralloc.allocate_frame().unwrap();
+                    pt.map(page, frame, flags, fralloc).unwrap().flush();
+                }
+            });
+            address_space.record_mapped(page_range.size());
         });
 
+        let vma = Vma::new(
+            MemoryRange::new(
+                reserved.start().start_address().as_u64(),
+                reserved.end().start_address().as_u64() + (M4KiB::SIZE - 1),
+            ),
+            VmaFlags::READ | VmaFlags::WRITE,
+            Backing::Stack,
+        );
+        address_space
+            .with_vmas(|vmas| vmas.insert(vma))
+            .expect("Failed to record the user stack area");
+
         #[cfg(debug_assertions)]
         unsafe {
             let stack_bottom = page_range.start().start_address();
@@ -529,13 +721,13 @@ impl ThreadStacks {
                 .write_bytes(STACK_DEBUG_INSTR, size.try_into().unwrap());
         }
 
-        page_range
+        reserved
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Tls {
-    /// The address of the TLS area.
+    /// The thread pointer, i.e. the address of the TCB.
     addr: VirtAddr,
     /// The size of the TLS area.
     size: u64,
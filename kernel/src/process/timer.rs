@@ -0,0 +1,82 @@
+//! Timers armed by processes with `Syscall::SetTimer`.
+//!
+//! Each process has a single `IntervalTimer`, and the deadlines of every armed timer
+//! are kept in a queue ordered by deadline.
+//! The queue is checked on each timer tick of the BSP. Expired timers raise `Signal::Alarm`
+//! as deferred work, because raising a signal needs to look the process up.
+//!
+//! The queue may hold outdated deadlines, for timers that were re-armed or whose process exited.
+//! They are skipped once they are reached, as the timer itself is not expired by them.
+use super::Process;
+use crate::time::{Duration, Instant};
+use alloc::collections::binary_heap::BinaryHeap;
+use core::{
+    cmp::Reverse,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use hyperdrive::locks::mcs::McsLock;
+
+/// Deadlines of the timers, with the PID of their process.
+static DEADLINES: McsLock<BinaryHeap<Reverse<(Instant, u64)>>> = McsLock::new(BinaryHeap::new());
+
+/// Whether expired timers are already scheduled to be handled.
+static EXPIRY_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+/// Arms the timer of a process, replacing its previous setting.
+///
+/// A null delay disarms the timer, and a null period makes it one-shot.
+pub fn arm(process: &Process, delay: Duration, period: Duration) {
+    if let Some(deadline) = process.arm_timer(crate::time::now(), delay, period) {
+        push_deadline(deadline, process.pid().as_u64());
+    }
+}
+
+fn push_deadline(deadline: Instant, pid: u64) {
+    DEADLINES.with_locked(|deadlines| deadlines.push(Reverse((deadline, pid))));
+}
+
+/// Schedules the handling of expired timers, if any.
+///
+/// This function is called on timer ticks: it neither allocates nor blocks.
+pub fn tick() {
+    let now = crate::time::now();
+    let expired = DEADLINES.try_with_locked(|deadlines| {
+        deadlines
+            .peek()
+            .is_some_and(|Reverse((deadline, _))| *deadline <= now)
+    });
+    if expired != Some(true) || EXPIRY_SCHEDULED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    let work = crate::softirq::Work::new(|_| expire_timers(), 0);
+    if crate::softirq::schedule(work).is_err() {
+        // Try again on the next tick
+        EXPIRY_SCHEDULED.store(false, Ordering::Release);
+    }
+}
+
+/// Expires the timers whose deadline is reached.
+fn expire_timers() {
+    EXPIRY_SCHEDULED.store(false, Ordering::Release);
+
+    let now = crate::time::now();
+    while let Some(pid) = DEADLINES.with_locked(|deadlines| {
+        deadlines
+            .peek()
+            .is_some_and(|Reverse((deadline, _))| *deadline <= now)
+            .then(|| deadlines.pop())
+            .flatten()
+            .map(|Reverse((_, pid))| pid)
+    }) {
+        let Some(process) = super::list(pid, 1)
+            .pop()
+            .filter(|process| process.pid().as_u64() == pid)
+        else {
+            continue;
+        };
+        if let Some(next) = process.expire_timer(now) {
+            push_deadline(next, pid);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Random bytes for the kernel and for userspace, through `Syscall::GetRandom` and `/dev/rand`.
+//!
+//! Bytes come from a ChaCha20 generator with fast key erasure, see `ChaChaRng`.
+//!
+//! ## Seeding
+//!
+//! Seeds are made of:
+//! - RDSEED, or RDRAND if RDSEED is not supported,
+//! - the TSC when seeding,
+//! - the timing of interrupts, mixed into a pool as they arrive.
+//!
+//! With a hardware source, the generator is seeded at boot. Otherwise, it is seeded
+//! once `SEED_SAMPLES` interrupts have been timed. Until then, `fill` fails,
+//! and callers that can wait use `wait_seeded`.
+//!
+//! The generator is reseeded from the same sources once `RESEED_INTERVAL` elapsed,
+//! the next time it is used.
+use crate::time::{Duration, Instant};
+use beskar_core::{crypto::chacha20::KEY_SIZE, rand::ChaChaRng};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use hyperdrive::locks::mcs::McsLock;
+use thiserror::Error;
+
+/// Time after which the generator is reseeded.
+const RESEED_INTERVAL: Duration = Duration::from_secs(60);
+/// Number of interrupt timings needed to seed the generator without a hardware source.
+const SEED_SAMPLES: usize = 256;
+/// Maximum number of bytes produced with the generator locked.
+///
+/// Large requests are split, so that other cores do not wait for them.
+const MAX_REQUEST_SIZE: usize = 4096;
+
+/// Interrupt timings, mixed as they arrive.
+static POOL: [AtomicU64; KEY_SIZE / 8] = [const { AtomicU64::new(0) }; KEY_SIZE / 8];
+/// Number of interrupt timings mixed into the pool.
+static POOL_SAMPLES: AtomicUsize = AtomicUsize::new(0);
+
+static SEEDED: AtomicBool = AtomicBool::new(false);
+static GENERATOR: McsLock<Option<Generator>> = McsLock::new(None);
+
+struct Generator {
+    rng: ChaChaRng,
+    seeded_at: Instant,
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum RandError {
+    #[error("The generator is not seeded yet")]
+    NotSeeded,
+}
+
+pub fn init() {
+    if hardware_source_available() {
+        seed();
+    }
+}
+
+#[must_use]
+#[inline]
+fn hardware_source_available() -> bool {
+    crate::arch::rand::rdseed_supported() || crate::arch::rand::rdrand_supported()
+}
+
+/// Adds the timing of an interrupt to the entropy pool.
+///
+/// This function is called by interrupt handlers: it neither allocates nor locks.
+pub fn add_interrupt_entropy() {
+    let tsc = crate::drivers::tsc::main_counter_value();
+    let sample = POOL_SAMPLES.fetch_add(1, Ordering::Relaxed);
+    // Only the low bits of the TSC vary from one interrupt to the next,
+    // so the rotation spreads them over the whole word.
+    let _ = POOL[sample % POOL.len()].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |word| {
+        Some(word.rotate_left(23) ^ tsc)
+    });
+}
+
+#[must_use]
+/// Gathers a seed from every source.
+fn gather_seed() -> [u8; KEY_SIZE] {
+    let mut seed = [0; KEY_SIZE];
+    // Without a working hardware source, the other sources remain.
+    let _ = crate::arch::rand::rand_seed_bytes(&mut seed)
+        .or_else(|_| crate::arch::rand::rand_bytes(&mut seed));
+
+    let tsc = crate::drivers::tsc::main_counter_value();
+    for (i, (chunk, word)) in seed.chunks_exact_mut(8).zip(&POOL).enumerate() {
+        let mut mixed = word.load(Ordering::Relaxed);
+        if i == 0 {
+            mixed ^= tsc;
+        }
+        for (byte, mixed) in chunk.iter_mut().zip(mixed.to_ne_bytes()) {
+            *byte ^= mixed;
+        }
+    }
+    seed
+}
+
+/// Seeds the generator, or reseeds it if it is already seeded.
+fn seed() {
+    let seed = gather_seed();
+    let now = crate::time::now();
+    GENERATOR.with_locked(|generator| match generator {
+        Some(generator) => {
+            generator.rng.reseed(&seed);
+            generator.seeded_at = now;
+        }
+        None => {
+            *generator = Some(Generator {
+                rng: ChaChaRng::new(seed),
+                seeded_at: now,
+            });
+        }
+    });
+    SEEDED.store(true, Ordering::Release);
+}
+
+#[must_use]
+/// Returns true if the generator can produce random bytes.
+///
+/// Without a hardware source, this seeds the generator once enough interrupts were timed.
+pub fn is_seeded() -> bool {
+    if SEEDED.load(Ordering::Acquire) {
+        return true;
+    }
+    if POOL_SAMPLES.load(Ordering::Relaxed) < SEED_SAMPLES {
+        return false;
+    }
+    seed();
+    true
+}
+
+/// Waits until the generator is seeded.
+pub fn wait_seeded() {
+    const RECHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+    while !is_seeded() {
+        crate::process::scheduler::sleep_for(RECHECK_INTERVAL);
+    }
+}
+
+/// Fills the whole buffer with random bytes.
+///
+/// # Errors
+///
+/// Returns `RandError::NotSeeded` if the generator is not seeded yet.
+pub fn fill(bytes: &mut [u8]) -> Result<(), RandError> {
+    if !is_seeded() {
+        return Err(RandError::NotSeeded);
+    }
+
+    let reseed_due = GENERATOR.with_locked(|generator| {
+        generator
+            .as_ref()
+            .is_some_and(|generator| crate::time::now() - generator.seeded_at >= RESEED_INTERVAL)
+    });
+    if reseed_due {
+        seed();
+    }
+
+    for chunk in bytes.chunks_mut(MAX_REQUEST_SIZE) {
+        GENERATOR.with_locked(|generator| {
+            generator
+                .as_mut()
+                .ok_or(RandError::NotSeeded)
+                .map(|generator| generator.rng.fill(chunk))
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Deferred work (bottom halves) of interrupt handlers.
+//!
+//! Interrupt handlers must return quickly and cannot take most locks,
+//! so they schedule the rest of their work here instead.
+//! Each core has its own queue: work scheduled on a core runs on that core,
+//! in the order it was scheduled, once interrupts are enabled again.
+//!
+//! Pending work is run by the idle threads of the core, and on return from a timer
+//! interrupt that preempted userspace, see `run_on_tick`. A thread that keeps the core
+//! busy in userspace thus only delays the work by a tick.
+use crate::locals;
+use hyperdrive::{once::Once, queues::deferred::DeferredQueue};
+
+pub use hyperdrive::queues::deferred::Work;
+
+/// Number of work items that can be pending on a core.
+const QUEUE_SIZE: usize = 128;
+/// Number of work items run on return from a timer interrupt,
+/// so that the preempted thread is not delayed for too long.
+const TICK_BUDGET: usize = 16;
+
+static QUEUES: [Once<DeferredQueue<QUEUE_SIZE>>; 256] = [const { Once::uninit() }; 256];
+
+#[must_use]
+#[inline]
+fn local_queue() -> &'static DeferredQueue<QUEUE_SIZE> {
+    let queue = &QUEUES[locals!().core_id()];
+    queue.call_once(DeferredQueue::new);
+    queue.get().unwrap()
+}
+
+/// Schedules work to run on the current core, after all the work already scheduled on it.
+///
+/// Safe to call from interrupt handlers: it neither allocates nor locks.
+///
+/// # Errors
+///
+/// If too much work is pending on the core, the work item is given back.
+pub fn schedule(work: Work) -> Result<(), Work> {
+    local_queue().schedule(work)
+}
+
+/// Runs the work pending on the current core.
+///
+/// Returns the number of work items run.
+///
+/// Interrupts must be enabled, so that work can take locks that interrupt handlers take.
+#[must_use]
+pub fn run_pending() -> usize {
+    local_queue().run()
+}
+
+/// Runs some of the work pending on the current core, on return from the timer interrupt.
+///
+/// Work may take locks, so the interrupt must have preempted userspace, where the thread
+/// holds no kernel lock. The end of interrupt must have been sent, as interrupts are
+/// enabled while the work runs, and disabled again before returning.
+///
+/// Returns the number of work items run.
+#[must_use]
+pub fn run_on_tick() -> usize {
+    beskar_hal::instructions::int_enable();
+    let count = local_queue().run_at_most(TICK_BUDGET);
+    beskar_hal::instructions::int_disable();
+    count
+}
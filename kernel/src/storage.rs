@@ -1,8 +1,11 @@
 use ::storage::{
     fs::{PathBuf, dev::DeviceFS},
+    page_cache::PageCache,
     vfs::{Vfs, VfsHelper},
 };
+use crate::process::scheduler::Mutex;
 use alloc::boxed::Box;
+use beskar_core::arch::paging::{Frame, M4KiB};
 
 struct VfsHelperStruct;
 
@@ -15,6 +18,11 @@ impl VfsHelper for VfsHelperStruct {
 
 static VFS: Vfs<VfsHelperStruct> = Vfs::new();
 
+/// Frames holding the pages of read-only files, shared between their mappings.
+///
+/// Processes of any priority map files, so a waiter lends its priority to the holder.
+static PAGE_CACHE: Mutex<PageCache<Frame<M4KiB>>> = Mutex::new(PageCache::new());
+
 pub fn init() {
     let mut device_fs = DeviceFS::new();
     device_fs.add_device(
@@ -27,7 +35,7 @@ pub fn init() {
         PathBuf::new("/randseed"),
         Box::new(crate::process::SeedFile),
     );
-    device_fs.add_device(PathBuf::new("/fb"), Box::new(video::screen::ScreenDevice));
+    device_fs.add_device(PathBuf::new("/fb"), Box::new(crate::display::DisplayDevice));
     VFS.mount(PathBuf::new("/dev"), Box::new(device_fs));
 }
 
@@ -37,3 +45,11 @@ pub fn init() {
 pub fn vfs() -> &'static Vfs<impl VfsHelper> {
     &VFS
 }
+
+#[inline]
+/// Runs the given function with the page cache locked.
+///
+/// The current thread may sleep, so it must not be called in interrupt handlers.
+pub fn with_page_cache<R>(f: impl FnOnce(&mut PageCache<Frame<M4KiB>>) -> R) -> R {
+    f(&mut PAGE_CACHE.lock())
+}
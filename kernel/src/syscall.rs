@@ -1,12 +1,28 @@
-use crate::process;
+use crate::{
+    mem::{address_space::AddressSpace, frame_alloc},
+    process,
+};
+use ::storage::{
+    fs::{FileError, FileResult},
+    page_cache::PageKey,
+    vfs::{LockKind, SeekFrom},
+};
 use beskar_core::{
     arch::{
-        VirtAddr,
-        paging::{CacheFlush, M4KiB, Mapper, MappingError, MemSize, Page},
+        AddrRange, VirtAddr,
+        paging::{CacheFlush, M4KiB, Mapper, MappingError, MemSize, Page, PageRangeInclusive},
+    },
+    mem::{
+        ranges::MemoryRange,
+        vma::{Backing, Vma, VmaFlags},
+    },
+    process::signal::{Disposition, Signal},
+    syscall::{
+        ExitCode, FileLockOperation, SeekWhence, Syscall, SyscallExitCode, SyscallReturnValue,
+        poll::PollTimeout,
     },
-    syscall::{Syscall, SyscallExitCode, SyscallReturnValue},
 };
-use beskar_hal::paging::page_table::Flags;
+use beskar_hal::{paging::page_table::Flags, userspace::is_sysret_safe};
 
 pub fn init() {
     crate::arch::syscall::init_syscalls();
@@ -32,27 +48,75 @@ pub fn probe(start: VirtAddr, end: VirtAddr) -> bool {
 }
 
 #[must_use]
+// The match must stay exhaustive, so that a new syscall cannot be left unhandled.
+#[deny(
+    clippy::wildcard_enum_match_arm,
+    clippy::match_wildcard_for_single_variants
+)]
 pub fn syscall(syscall: Syscall, args: &Arguments) -> SyscallReturnValue {
     match syscall {
         Syscall::Exit => sc_exit(args),
         Syscall::MemoryMap => SyscallReturnValue::ValueU(sc_mmap(args)),
         Syscall::MemoryProtect => SyscallReturnValue::Code(sc_mprotect(args)),
-        Syscall::Read => SyscallReturnValue::ValueI(sc_read(args)),
+        Syscall::Read => SyscallReturnValue::ValueI(sc_read(args, PollTimeout::Infinite)),
         Syscall::Write => SyscallReturnValue::ValueI(sc_write(args)),
         Syscall::Open => SyscallReturnValue::ValueI(sc_open(args)),
         Syscall::Close => SyscallReturnValue::Code(sc_close(args)),
         Syscall::Sleep => SyscallReturnValue::Code(sc_sleep(args)),
         Syscall::WaitOnEvent => SyscallReturnValue::Code(sc_wait_on_event(args)),
+        Syscall::PollKeyboardBatch => SyscallReturnValue::ValueI(sc_poll_keyboard_batch(args)),
+        Syscall::Poll => SyscallReturnValue::ValueI(sc_poll(args)),
+        Syscall::GetEnv => SyscallReturnValue::ValueI(sc_get_env(args)),
+        Syscall::SetEnv => SyscallReturnValue::Code(sc_set_env(args)),
+        Syscall::Metadata => SyscallReturnValue::Code(sc_metadata(args)),
+        Syscall::ReadDir => SyscallReturnValue::ValueI(sc_read_dir(args)),
+        Syscall::ProcessList => SyscallReturnValue::ValueI(sc_process_list(args)),
+        Syscall::MmapFile => SyscallReturnValue::ValueU(sc_mmap_file(args)),
+        Syscall::FramebufferInfo => SyscallReturnValue::Code(sc_framebuffer_info(args)),
+        Syscall::AcquireDisplay => SyscallReturnValue::Code(sc_acquire_display()),
+        Syscall::ReleaseDisplay => SyscallReturnValue::Code(sc_release_display()),
+        Syscall::DisplayEvent => SyscallReturnValue::ValueU(sc_display_event()),
+        Syscall::ReadKeyBlocking => SyscallReturnValue::ValueU(sc_read_key_blocking()),
+        Syscall::ClockMonotonic => SyscallReturnValue::ValueU(crate::time::now().total_micros()),
+        Syscall::MapClock => {
+            SyscallReturnValue::ValueU(crate::time::map_shared_clock().map_or(0, VirtAddr::as_u64))
+        }
+        Syscall::ReadV => SyscallReturnValue::ValueI(sc_readv(args)),
+        Syscall::WriteV => SyscallReturnValue::ValueI(sc_writev(args)),
+        Syscall::Fsync => SyscallReturnValue::Code(sc_fsync(args)),
+        Syscall::PacketCapture => SyscallReturnValue::ValueI(sc_packet_capture(args)),
+        Syscall::Brk => SyscallReturnValue::ValueU(sc_brk(args)),
+        Syscall::Sbrk => SyscallReturnValue::ValueU(sc_sbrk(args)),
+        Syscall::ThreadCreate => SyscallReturnValue::ValueI(sc_thread_create(args)),
+        Syscall::ThreadJoin => SyscallReturnValue::ValueI(sc_thread_join(args)),
+        Syscall::ProcessExit => sc_process_exit(args),
+        Syscall::Flock => SyscallReturnValue::ValueI(sc_flock(args)),
+        Syscall::Seek => SyscallReturnValue::ValueI(sc_seek(args)),
+        Syscall::Pipe => SyscallReturnValue::Code(sc_pipe(args)),
+        Syscall::Dup => SyscallReturnValue::ValueI(sc_dup(args)),
+        Syscall::Dup2 => SyscallReturnValue::Code(sc_dup2(args)),
+        Syscall::ReadTimeout => {
+            SyscallReturnValue::ValueI(sc_read(args, PollTimeout::from_raw(args.five)))
+        }
+        Syscall::PollCreate => SyscallReturnValue::ValueI(sc_poll_create()),
+        Syscall::PollAdd => SyscallReturnValue::Code(sc_poll_add(args)),
+        Syscall::PollWait => SyscallReturnValue::ValueI(sc_poll_wait(args)),
+        Syscall::PollDel => SyscallReturnValue::Code(sc_poll_del(args)),
+        Syscall::SetAffinity => SyscallReturnValue::Code(sc_set_affinity(args)),
+        Syscall::SigAction => SyscallReturnValue::Code(sc_sigaction(args)),
+        // Restoring the registers is up to the architecture-specific syscall handler
+        Syscall::SigReturn => SyscallReturnValue::Code(SyscallExitCode::Failure),
+        Syscall::SetTimer => SyscallReturnValue::Code(sc_set_timer(args)),
+        Syscall::GetRandom => SyscallReturnValue::ValueI(sc_get_random(args)),
+        Syscall::ThreadDetach => SyscallReturnValue::Code(sc_thread_detach(args)),
     }
 }
 
 fn sc_exit(args: &Arguments) -> ! {
-    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
-    let exit_code = args.one;
+    let exit_code = ExitCode::try_from(args.one);
 
     #[cfg(debug_assertions)]
     {
-        let exit_code = beskar_core::syscall::ExitCode::try_from(exit_code);
         let tid = crate::process::scheduler::current_thread_id();
 
         if let Ok(exit_code) = exit_code {
@@ -62,7 +126,22 @@ fn sc_exit(args: &Arguments) -> ! {
         }
     }
 
-    unsafe { crate::process::scheduler::exit_current_thread() }
+    // An invalid exit code is reported as a failure
+    let exit_code = exit_code.unwrap_or(ExitCode::Failure);
+    unsafe { crate::process::scheduler::exit_current_thread(exit_code) }
+}
+
+fn sc_process_exit(args: &Arguments) -> ! {
+    let process = process::current();
+    video::debug!(
+        "Process {} exiting with code {:?}",
+        process.name(),
+        ExitCode::try_from(args.one)
+    );
+    process.request_exit();
+    drop(process);
+
+    sc_exit(args)
 }
 
 #[must_use]
@@ -81,6 +160,42 @@ fn build_flags_from_us(raw: u64) -> Flags {
     flags
 }
 
+fn build_vma_flags_from_us(raw: u64) -> VmaFlags {
+    let mut flags = VmaFlags::NONE;
+    if raw & beskar_core::syscall::consts::MFLAGS_READ != 0 {
+        flags = flags | VmaFlags::READ;
+    }
+    if raw & beskar_core::syscall::consts::MFLAGS_WRITE != 0 {
+        flags = flags | VmaFlags::WRITE;
+    }
+    if raw & beskar_core::syscall::consts::MFLAGS_EXECUTE != 0 {
+        flags = flags | VmaFlags::EXECUTE;
+    }
+    flags
+}
+
+#[must_use]
+const fn page_range_to_memory_range(page_range: PageRangeInclusive<M4KiB>) -> MemoryRange {
+    MemoryRange::new(
+        page_range.start().start_address().as_u64(),
+        page_range.end().start_address().as_u64() + (M4KiB::SIZE - 1),
+    )
+}
+
+/// Records a new memory area for pages mapped in the current address space.
+///
+/// Returns whether the area could be recorded.
+#[must_use]
+fn add_vma(
+    address_space: &AddressSpace,
+    page_range: PageRangeInclusive<M4KiB>,
+    flags: VmaFlags,
+    backing: Backing,
+) -> bool {
+    let vma = Vma::new(page_range_to_memory_range(page_range), flags, backing);
+    address_space.with_vmas(|vmas| vmas.insert(vma)).is_ok()
+}
+
 #[must_use]
 fn sc_mmap(args: &Arguments) -> u64 {
     let len = args.one;
@@ -96,16 +211,448 @@ fn sc_mmap(args: &Arguments) -> u64 {
 
     let flags = build_flags_from_us(flags_raw);
 
-    let Some(page_range) = process::current()
-        .address_space()
-        .alloc_map::<M4KiB>(usize::try_from(len).unwrap(), flags)
+    let process = process::current();
+    let address_space = process.address_space();
+    let Some(page_range) = address_space.alloc_map::<M4KiB>(usize::try_from(len).unwrap(), flags)
     else {
         return 0;
     };
 
+    if !add_vma(
+        address_space,
+        page_range,
+        build_vma_flags_from_us(flags_raw),
+        Backing::Anonymous,
+    ) {
+        // Safety: The pages have not been handed to user-space.
+        unsafe { address_space.unmap_free(page_range) };
+        return 0;
+    }
+
     page_range.start().start_address().as_u64()
 }
 
+#[must_use]
+fn sc_brk(args: &Arguments) -> u64 {
+    let new = args.one;
+
+    let process = process::current();
+    let address_space = process.address_space();
+    let mv = if new == 0 {
+        address_space.move_break(|brk| brk.adjust(0))
+    } else {
+        address_space
+            .move_break(|brk| brk.set(new))
+            .or_else(|| address_space.move_break(|brk| brk.adjust(0)))
+    };
+    mv.map_or(0, |mv| mv.new)
+}
+
+#[must_use]
+fn sc_sbrk(args: &Arguments) -> u64 {
+    let increment = args.one.cast_signed();
+
+    process::current()
+        .address_space()
+        .move_break(|brk| brk.adjust(increment))
+        .map_or(0, |mv| mv.old)
+}
+
+#[must_use]
+fn sc_thread_create(args: &Arguments) -> i64 {
+    use crate::process::scheduler::{
+        self,
+        thread::{Thread, UserEntry},
+    };
+    use beskar_core::syscall::consts::{THREAD_STACK_MAX_SIZE, THREAD_STACK_MIN_SIZE};
+
+    /// Size of the kernel stack of a thread, used by its syscalls and interrupts.
+    const KERNEL_STACK_SIZE: usize = 64 * 1024;
+
+    let Some(entry) = VirtAddr::try_new(args.one).filter(|&e| probe(e, e)) else {
+        return -1;
+    };
+    let stack_size = match args.three {
+        0 => THREAD_STACK_MAX_SIZE,
+        size if (THREAD_STACK_MIN_SIZE..=THREAD_STACK_MAX_SIZE).contains(&size) => size,
+        _ => return -1,
+    };
+
+    let process = process::current();
+    let thread = Thread::new_user(
+        process.clone(),
+        scheduler::current_thread_priority(),
+        alloc::vec![0; KERNEL_STACK_SIZE],
+        UserEntry::new(entry, args.two, stack_size),
+    );
+    let tid = thread.id().as_u64();
+    if process.register_joinable(tid).is_err() {
+        return -1;
+    }
+    scheduler::spawn_thread(alloc::boxed::Box::new(thread));
+
+    tid.cast_signed()
+}
+
+#[must_use]
+fn sc_thread_join(args: &Arguments) -> i64 {
+    /// Exits cannot wake a joiner up yet, so the thread is checked periodically.
+    const RECHECK_INTERVAL: crate::time::Duration = crate::time::Duration::from_millis(10);
+
+    let tid = args.one;
+    if tid == crate::process::scheduler::current_thread_id().as_u64() {
+        return -1;
+    }
+
+    let process = process::current();
+    loop {
+        match process.try_join(tid) {
+            Ok(Some(exit_code)) => return u64::from(exit_code).cast_signed(),
+            Ok(None) if process.is_exiting() => return -1,
+            Ok(None) => crate::process::scheduler::sleep_for(RECHECK_INTERVAL),
+            Err(_) => return -1,
+        }
+    }
+}
+
+#[must_use]
+fn sc_thread_detach(args: &Arguments) -> SyscallExitCode {
+    if process::current().detach_thread(args.one).is_ok() {
+        SyscallExitCode::Success
+    } else {
+        SyscallExitCode::Failure
+    }
+}
+
+#[must_use]
+fn sc_set_affinity(args: &Arguments) -> SyscallExitCode {
+    let affinity = beskar_core::process::affinity::CpuMask::from_raw(args.one);
+    if crate::process::scheduler::set_current_affinity(affinity).is_ok() {
+        SyscallExitCode::Success
+    } else {
+        SyscallExitCode::Failure
+    }
+}
+
+#[must_use]
+fn sc_sigaction(args: &Arguments) -> SyscallExitCode {
+    let Ok(signal) = Signal::try_from(args.one) else {
+        return SyscallExitCode::Failure;
+    };
+    let disposition = Disposition::from_raw(args.two, args.three);
+    if let Disposition::Handler(handler) = disposition
+        && !(is_sysret_safe(handler.entry()) && is_sysret_safe(handler.restorer()))
+    {
+        return SyscallExitCode::Failure;
+    }
+
+    process::current().set_signal_disposition(signal, disposition);
+    SyscallExitCode::Success
+}
+
+#[must_use]
+fn sc_set_timer(args: &Arguments) -> SyscallExitCode {
+    let delay = crate::time::Duration::from_millis(args.one);
+    let period = crate::time::Duration::from_millis(args.two);
+
+    process::timer::arm(&process::current(), delay, period);
+    SyscallExitCode::Success
+}
+
+#[must_use]
+fn sc_get_random(args: &Arguments) -> i64 {
+    use beskar_core::syscall::consts::{GETRANDOM_NONBLOCKING, GETRANDOM_WOULD_BLOCK};
+
+    let buffer_start = VirtAddr::try_new(args.one).unwrap_or_default();
+    let buffer_len = args.two;
+    let Ok(len) = i64::try_from(buffer_len) else {
+        return -1;
+    };
+
+    if !probe(buffer_start, buffer_start + buffer_len) {
+        return -1;
+    }
+
+    if !crate::rand::is_seeded() {
+        if args.three & GETRANDOM_NONBLOCKING != 0 {
+            return GETRANDOM_WOULD_BLOCK;
+        }
+        crate::rand::wait_seeded();
+    }
+
+    // Safety: The buffer's range is owned by the current process.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(buffer_start.as_mut_ptr(), buffer_len.try_into().unwrap())
+    };
+    match crate::rand::fill(buffer) {
+        Ok(()) => len,
+        Err(_) => -1,
+    }
+}
+
+#[must_use]
+fn sc_mmap_file(args: &Arguments) -> u64 {
+    let file_handle = {
+        let raw = args.one.cast_signed();
+        if raw < 0 {
+            return 0;
+        }
+        // Safety: The handle is used for comparison only
+        // and the given value is positive.
+        unsafe { ::storage::vfs::Handle::from_raw(raw) }
+    };
+    let (Ok(len), Ok(offset)) = (usize::try_from(args.two), usize::try_from(args.three)) else {
+        return 0;
+    };
+    if len == 0
+        || !args
+            .three
+            .is_multiple_of(beskar_core::syscall::consts::MMAP_FILE_OFFSET_ALIGN)
+    {
+        return 0;
+    }
+
+    let vfs = crate::storage::vfs();
+    match vfs.file_size(file_handle) {
+        Ok(size) if offset < size => {}
+        _ => return 0,
+    }
+
+    match vfs.page_key(file_handle, offset) {
+        Ok(Some(key)) => return mmap_file_shared(file_handle, &key, len),
+        Ok(None) => {}
+        Err(_) => return 0,
+    }
+
+    // The kernel fills the pages before making them read-only.
+    let process = process::current();
+    let address_space = process.address_space();
+    let Some(page_range) = address_space.alloc_map::<M4KiB>(
+        len,
+        Flags::USER_ACCESSIBLE | Flags::WRITABLE | Flags::NO_EXECUTE,
+    ) else {
+        return 0;
+    };
+    let start = page_range.start().start_address();
+
+    // Safety: The pages were just mapped in the address space of the current process.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(
+            start.as_mut_ptr::<u8>(),
+            usize::try_from(page_range.size()).unwrap(),
+        )
+    };
+
+    let res = vfs.read_fill(file_handle, buffer, offset).is_ok()
+        && address_space.with_page_table(|pt| {
+            page_range.into_iter().all(|page| {
+                pt.update_flags(
+                    page,
+                    Flags::PRESENT | Flags::USER_ACCESSIBLE | Flags::NO_EXECUTE,
+                )
+                .map(|flush| flush.flush())
+                .is_ok()
+            })
+        });
+
+    let backing = Backing::File {
+        file: file_handle.id().cast_unsigned(),
+        offset: args.three,
+    };
+    if res && add_vma(address_space, page_range, VmaFlags::READ, backing) {
+        start.as_u64()
+    } else {
+        // Safety: The pages have not been handed to user-space.
+        unsafe { address_space.unmap_free(page_range) };
+        0
+    }
+}
+
+/// Maps the pages of a file that never changes, sharing their frames through the page cache.
+#[must_use]
+fn mmap_file_shared(file_handle: ::storage::vfs::Handle, key: &PageKey, len: usize) -> u64 {
+    let process = process::current();
+    let address_space = process.address_space();
+
+    let page_count = u64::try_from(len).unwrap().div_ceil(M4KiB::SIZE);
+    let Some(page_range) =
+        address_space.with_pgalloc(|pgalloc| pgalloc.allocate_pages::<M4KiB>(page_count))
+    else {
+        return 0;
+    };
+    let page_key = |page: Page<M4KiB>| {
+        let index = page.start_address() - page_range.start().start_address();
+        key.with_offset(key.offset() + usize::try_from(index).unwrap())
+    };
+
+    let mapped = page_range
+        .into_iter()
+        .take_while(|&page| map_cached_page(address_space, file_handle, page, &page_key(page)))
+        .count();
+    let backing = Backing::File {
+        file: file_handle.id().cast_unsigned(),
+        offset: u64::try_from(key.offset()).unwrap(),
+    };
+    if mapped == usize::try_from(page_count).unwrap()
+        && add_vma(address_space, page_range, VmaFlags::READ, backing)
+    {
+        return page_range.start().start_address().as_u64();
+    }
+
+    // Give back the pages mapped so far.
+    for page in page_range.into_iter().take(mapped) {
+        unmap_cached_page(address_space, page, &page_key(page));
+    }
+    address_space.with_pgalloc(|pgalloc| pgalloc.free_pages(page_range));
+    0
+}
+
+/// Maps a page of a file from the page cache, reading it into a new frame on a miss.
+///
+/// Returns whether the page was mapped, in which case it counts as a mapping of the cached page.
+#[must_use]
+fn map_cached_page(
+    address_space: &AddressSpace,
+    file_handle: ::storage::vfs::Handle,
+    page: Page<M4KiB>,
+    key: &PageKey,
+) -> bool {
+    let read_only = Flags::PRESENT | Flags::USER_ACCESSIBLE | Flags::NO_EXECUTE;
+    let map = |frame, flags| {
+        frame_alloc::with_frame_allocator(|frame_allocator| {
+            address_space.with_page_table(|pt| {
+                pt.map(page, frame, flags, frame_allocator)
+                    .map(|flush| flush.flush())
+                    .is_ok()
+            })
+        })
+    };
+    let unmap_free = |frame| {
+        address_space.with_page_table(|pt| {
+            if let Ok((_frame, flush)) = pt.unmap(page) {
+                flush.flush();
+            }
+        });
+        frame_alloc::with_frame_allocator(|frame_allocator| frame_allocator.free(frame));
+    };
+
+    if let Some(frame) = crate::storage::with_page_cache(|cache| cache.acquire(key)) {
+        if map(frame, read_only) {
+            return true;
+        }
+        unmap_cached_page(address_space, page, key);
+        return false;
+    }
+
+    // The kernel fills the page before making it read-only.
+    let Some(frame) = frame_alloc::with_frame_allocator(frame_alloc::FrameAllocator::alloc) else {
+        return false;
+    };
+    if !map(frame, read_only | Flags::WRITABLE) {
+        frame_alloc::with_frame_allocator(|frame_allocator| frame_allocator.free(frame));
+        return false;
+    }
+
+    // Safety: The page was just mapped in the address space of the current process.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(
+            page.start_address().as_mut_ptr::<u8>(),
+            usize::try_from(M4KiB::SIZE).unwrap(),
+        )
+    };
+    if crate::storage::vfs()
+        .read_fill(file_handle, buffer, key.offset())
+        .is_err()
+    {
+        unmap_free(frame);
+        return false;
+    }
+
+    let protected = address_space.with_page_table(|pt| {
+        pt.update_flags(page, read_only)
+            .map(|flush| flush.flush())
+            .is_ok()
+    });
+    if !protected {
+        unmap_free(frame);
+        return false;
+    }
+
+    let cached = crate::storage::with_page_cache(|cache| cache.insert(key.clone(), frame));
+    if cached == frame {
+        return true;
+    }
+
+    // Another process cached the page meanwhile.
+    unmap_free(frame);
+    if map(cached, read_only) {
+        return true;
+    }
+    unmap_cached_page(address_space, page, key);
+    false
+}
+
+/// Replaces a page mapped from the page cache by a private copy of it.
+///
+/// Pages that are not mapped from the page cache are left untouched.
+#[must_use]
+fn unshare_cached_page(address_space: &AddressSpace, page: Page<M4KiB>) -> bool {
+    let Some((frame, flags)) = address_space.with_page_table(|pt| pt.translate(page)) else {
+        return true;
+    };
+    let Some(key) = crate::storage::with_page_cache(|cache| cache.key_of(frame).cloned()) else {
+        return true;
+    };
+
+    let page_size = usize::try_from(M4KiB::SIZE).unwrap();
+    // Safety: The page is mapped in the address space of the current process.
+    let copy = alloc::boxed::Box::<[u8]>::from(unsafe {
+        core::slice::from_raw_parts(page.start_address().as_ptr::<u8>(), page_size)
+    });
+
+    let Some(private) = frame_alloc::with_frame_allocator(frame_alloc::FrameAllocator::alloc)
+    else {
+        return false;
+    };
+    unmap_cached_page(address_space, page, &key);
+    let mapped = frame_alloc::with_frame_allocator(|frame_allocator| {
+        address_space.with_page_table(|pt| {
+            pt.map(page, private, flags | Flags::WRITABLE, frame_allocator)
+                .map(|flush| flush.flush())
+                .is_ok()
+        })
+    });
+    if !mapped {
+        frame_alloc::with_frame_allocator(|frame_allocator| frame_allocator.free(private));
+        return false;
+    }
+    address_space.record_mapped(M4KiB::SIZE);
+
+    // Safety: The page was just mapped, writable, in the address space of the current process.
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            copy.as_ptr(),
+            page.start_address().as_mut_ptr::<u8>(),
+            page_size,
+        );
+    }
+    true
+}
+
+/// Unmaps a page of a file mapped from the page cache.
+///
+/// The frame is mapped elsewhere, so it is only freed if this was its last mapping.
+fn unmap_cached_page(address_space: &AddressSpace, page: Page<M4KiB>, key: &PageKey) {
+    address_space.with_page_table(|pt| {
+        if let Ok((_frame, flush)) = pt.unmap(page) {
+            flush.flush();
+        }
+    });
+    if let Some(frame) = crate::storage::with_page_cache(|cache| cache.release(key)) {
+        frame_alloc::with_frame_allocator(|frame_allocator| frame_allocator.free(frame));
+    }
+}
+
 #[must_use]
 fn sc_mprotect(args: &Arguments) -> SyscallExitCode {
     let ptr = args.one;
@@ -116,44 +663,58 @@ fn sc_mprotect(args: &Arguments) -> SyscallExitCode {
         return SyscallExitCode::Success;
     }
 
-    let Some(va) = VirtAddr::try_new(ptr) else {
+    let Some(range) = VirtAddr::try_new(ptr).and_then(|va| AddrRange::checked_from_len(va, size))
+    else {
         return SyscallExitCode::Failure;
     };
-    let end = va + (size - 1);
 
-    if !va.is_aligned(beskar_core::arch::Alignment::Align4K)
-        && !size.is_multiple_of(M4KiB::SIZE)
-        && !probe(va, end)
+    // Nothing may be unshared or remapped before the whole range is known to be valid.
+    if !range.is_aligned(beskar_core::arch::Alignment::Align4K)
+        || !probe(range.start(), range.end() - 1)
     {
         return SyscallExitCode::Failure;
     }
 
     let flags = build_flags_from_us(flags_raw);
 
-    let page_start = va.page::<M4KiB>();
-    let page_end = end.page::<M4KiB>();
+    let page_range = range.pages::<M4KiB>();
+
+    let process = process::current();
+    let address_space = process.address_space();
 
-    let page_range = Page::range_inclusive(page_start, page_end);
+    // Mappings of the page cache are private, so writable pages get their own copy.
+    if flags.contains(Flags::WRITABLE)
+        && !page_range
+            .into_iter()
+            .all(|page| unshare_cached_page(address_space, page))
+    {
+        return SyscallExitCode::Failure;
+    }
 
-    let res =
-        process::current()
-            .address_space()
-            .with_page_table(|pt| -> Result<_, MappingError<_>> {
-                for page in page_range {
-                    let cache_flush = pt.update_flags(page, flags)?;
-                    cache_flush.flush();
-                }
-                Ok(())
-            });
+    let res = address_space.with_page_table(|pt| -> Result<_, MappingError<_>> {
+        for page in page_range {
+            let cache_flush = pt.update_flags(page, flags)?;
+            cache_flush.flush();
+        }
+        Ok(())
+    });
 
-    match res {
-        Ok(()) => SyscallExitCode::Success,
-        Err(_) => SyscallExitCode::Failure,
+    if res.is_err() {
+        return SyscallExitCode::Failure;
     }
+
+    // Areas mapped by the kernel itself, such as the segments of the binary, are not tracked.
+    let _ = address_space.with_vmas(|vmas| {
+        vmas.protect(
+            page_range_to_memory_range(page_range),
+            build_vma_flags_from_us(flags_raw),
+        )
+    });
+    SyscallExitCode::Success
 }
 
 #[must_use]
-fn sc_read(args: &Arguments) -> i64 {
+fn sc_read(args: &Arguments, timeout: PollTimeout) -> i64 {
     let file_handle = {
         let raw = args.one.cast_signed();
         if raw < 0 {
@@ -176,12 +737,20 @@ fn sc_read(args: &Arguments) -> i64 {
         core::slice::from_raw_parts_mut(buffer_start.as_mut_ptr(), buffer_len.try_into().unwrap())
     };
 
-    let file_offset = usize::try_from(args.four).unwrap();
-
-    let res = crate::storage::vfs().read(file_handle, buffer, file_offset);
-    res.map_or(-1, |bytes_read| {
-        i64::try_from(bytes_read).unwrap_or(i64::MAX)
-    })
+    let vfs = crate::storage::vfs();
+    let res = wait_for_pipe(timeout, || {
+        if args.four == beskar_core::syscall::consts::FILE_OFFSET_CURRENT {
+            vfs.at_position(file_handle, |offset| vfs.read(file_handle, buffer, offset))
+        } else {
+            let file_offset = usize::try_from(args.four).unwrap();
+            vfs.read(file_handle, buffer, file_offset)
+        }
+    });
+    match res {
+        Ok(bytes_read) => i64::try_from(bytes_read).unwrap_or(i64::MAX),
+        Err(FileError::WouldBlock) => beskar_core::syscall::consts::READ_TIMED_OUT,
+        Err(_) => -1,
+    }
 }
 
 #[must_use]
@@ -207,72 +776,823 @@ fn sc_write(args: &Arguments) -> i64 {
         core::slice::from_raw_parts(buffer_start.as_ptr(), buffer_len.try_into().unwrap())
     };
 
-    let file_offset = usize::try_from(args.four).unwrap();
+    let vfs = crate::storage::vfs();
+    let res = wait_for_pipe(PollTimeout::Infinite, || {
+        if args.four == beskar_core::syscall::consts::FILE_OFFSET_CURRENT {
+            vfs.at_position(file_handle, |offset| vfs.write(file_handle, buffer, offset))
+        } else {
+            let file_offset = usize::try_from(args.four).unwrap();
+            vfs.write(file_handle, buffer, file_offset)
+        }
+    });
+    match res {
+        Ok(bytes_written) => i64::try_from(bytes_written).unwrap_or(i64::MAX),
+        Err(FileError::BrokenPipe) => broken_pipe(),
+        Err(_) => -1,
+    }
+}
 
-    let res = crate::storage::vfs().write(file_handle, buffer, file_offset);
-    res.map_or(-1, |bytes_written| {
-        i64::try_from(bytes_written).unwrap_or(i64::MAX)
-    })
+/// Raises `Signal::BrokenPipe`, which terminates the process unless it is handled or ignored.
+fn broken_pipe() -> i64 {
+    process::current().raise_signal(Signal::BrokenPipe);
+    beskar_core::syscall::consts::WRITE_BROKEN_PIPE
 }
 
-#[must_use]
-fn sc_open(args: &Arguments) -> i64 {
-    use ::storage::{fs::Path, vfs::Handle};
+/// Runs a transfer until it does not have to wait on a pipe, the timeout elapses,
+/// or the process exits.
+///
+/// Fails with `WouldBlock` if the transfer still has to wait when the timeout elapses.
+fn wait_for_pipe(
+    timeout: PollTimeout,
+    transfer: impl FnMut() -> FileResult<usize>,
+) -> FileResult<usize> {
+    /// Transfers cannot wake a waiter up yet, so the transfer is retried periodically.
+    const RECHECK_INTERVAL: crate::time::Duration = crate::time::Duration::from_millis(10);
 
-    let path_start = VirtAddr::try_new(args.one).unwrap_or_default();
-    let path_len = args.two;
+    let deadline = match timeout {
+        PollTimeout::Immediate => None,
+        PollTimeout::Infinite => Some(crate::time::Instant::MAX),
+        PollTimeout::After(duration) => Some(crate::time::now() + duration),
+    };
+    let process = process::current();
+    ::storage::pipe::retry_while_blocked(transfer, || {
+        let now = crate::time::now();
+        match deadline {
+            Some(deadline) if now < deadline && !process.is_exiting() => {
+                crate::process::scheduler::sleep_for(RECHECK_INTERVAL.min(deadline - now));
+                true
+            }
+            _ => false,
+        }
+    })
+}
 
-    if !probe(path_start, path_start + path_len) {
-        return Handle::INVALID.id();
+/// Copies the `IoVec`s of a vectored syscall, after checking every buffer they point to.
+///
+/// Nothing is returned unless all the buffers belong to the current process.
+fn user_iovecs(args: &Arguments) -> Option<alloc::vec::Vec<beskar_core::syscall::iovec::IoVec>> {
+    use beskar_core::syscall::iovec::{IoVec, MAX_IOVECS, total_len};
+
+    let count = usize::try_from(args.three).ok()?;
+    if count > MAX_IOVECS {
+        return None;
+    }
+    let iovecs_start = VirtAddr::try_new(args.two).unwrap_or_default();
+    let iovecs_len = (count * size_of::<IoVec>()) as u64;
+    if !iovecs_start.is_aligned(beskar_core::arch::Alignment::Align8)
+        || !probe(iovecs_start, iovecs_start + iovecs_len)
+    {
+        return None;
     }
 
-    // Safety: The buffer's range is owned by the curent process.
-    let raw_path =
-        unsafe { core::slice::from_raw_parts(path_start.as_ptr(), path_len.try_into().unwrap()) };
-    let Ok(path) = core::str::from_utf8(raw_path) else {
-        return Handle::INVALID.id();
-    };
+    // Safety: The array's range is owned by the current process and is aligned.
+    // It is copied so that userspace cannot change it after the checks.
+    let iovecs =
+        unsafe { core::slice::from_raw_parts(iovecs_start.as_ptr::<IoVec>(), count) }.to_vec();
 
-    let res = crate::storage::vfs().open(Path::from(path));
-    res.map_or(-1, |handle| handle.id())
+    total_len(&iovecs)?;
+    iovecs
+        .iter()
+        .all(|iovec| {
+            let start = VirtAddr::try_new(iovec.ptr()).unwrap_or_default();
+            iovec.is_empty() || probe(start, start + iovec.len())
+        })
+        .then_some(iovecs)
 }
 
 #[must_use]
-fn sc_close(args: &Arguments) -> SyscallExitCode {
+fn sc_readv(args: &Arguments) -> i64 {
     let file_handle = {
         let raw = args.one.cast_signed();
         if raw < 0 {
-            return SyscallExitCode::Failure;
+            return -1;
         }
         // Safety: The handle is used for comparison only
         // and the given value is positive.
         unsafe { ::storage::vfs::Handle::from_raw(raw) }
     };
-    let res = crate::storage::vfs().close(file_handle);
-
-    match res {
-        Ok(()) => SyscallExitCode::Success,
-        Err(_) => SyscallExitCode::Failure, // TODO: Differentiate between errors.
-    }
-}
-
-#[must_use]
-fn sc_sleep(args: &Arguments) -> SyscallExitCode {
-    let sleep_time_ms = args.one;
-
-    let sleep_time = crate::time::Duration::from_millis(sleep_time_ms);
+    let Some(iovecs) = user_iovecs(args) else {
+        return -1;
+    };
 
-    crate::process::scheduler::sleep_for(sleep_time);
+    let mut buffers: alloc::vec::Vec<&mut [u8]> = iovecs
+        .iter()
+        .filter(|iovec| !iovec.is_empty())
+        // Safety: Every buffer is owned by the current process.
+        .map(|iovec| unsafe {
+            core::slice::from_raw_parts_mut(
+                VirtAddr::new_extend(iovec.ptr()).as_mut_ptr(),
+                usize::try_from(iovec.len()).unwrap(),
+            )
+        })
+        .collect();
 
-    SyscallExitCode::Success
+    let vfs = crate::storage::vfs();
+    let res = wait_for_pipe(PollTimeout::Infinite, || {
+        if args.four == beskar_core::syscall::consts::FILE_OFFSET_CURRENT {
+            vfs.at_position(file_handle, |offset| {
+                vfs.read_vectored(file_handle, &mut buffers, offset)
+            })
+        } else {
+            let file_offset = usize::try_from(args.four).unwrap();
+            vfs.read_vectored(file_handle, &mut buffers, file_offset)
+        }
+    });
+    res.map_or(-1, |bytes_read| {
+        i64::try_from(bytes_read).unwrap_or(i64::MAX)
+    })
 }
 
 #[must_use]
-fn sc_wait_on_event(args: &Arguments) -> SyscallExitCode {
-    let handle_raw = args.one;
-    let handle = beskar_core::process::SleepHandle::from_raw(handle_raw);
+fn sc_writev(args: &Arguments) -> i64 {
+    let file_handle = {
+        let raw = args.one.cast_signed();
+        if raw < 0 {
+            return -1;
+        }
+        // Safety: The handle is used for comparison only
+        // and the given value is positive.
+        unsafe { ::storage::vfs::Handle::from_raw(raw) }
+    };
+    let Some(iovecs) = user_iovecs(args) else {
+        return -1;
+    };
+
+    let buffers: alloc::vec::Vec<&[u8]> = iovecs
+        .iter()
+        .filter(|iovec| !iovec.is_empty())
+        // Safety: Every buffer is owned by the current process.
+        .map(|iovec| unsafe {
+            core::slice::from_raw_parts(
+                VirtAddr::new_extend(iovec.ptr()).as_ptr(),
+                usize::try_from(iovec.len()).unwrap(),
+            )
+        })
+        .collect();
+
+    let vfs = crate::storage::vfs();
+    let res = wait_for_pipe(PollTimeout::Infinite, || {
+        if args.four == beskar_core::syscall::consts::FILE_OFFSET_CURRENT {
+            vfs.at_position(file_handle, |offset| {
+                vfs.write_vectored(file_handle, &buffers, offset)
+            })
+        } else {
+            let file_offset = usize::try_from(args.four).unwrap();
+            vfs.write_vectored(file_handle, &buffers, file_offset)
+        }
+    });
+    match res {
+        Ok(bytes_written) => i64::try_from(bytes_written).unwrap_or(i64::MAX),
+        Err(FileError::BrokenPipe) => broken_pipe(),
+        Err(_) => -1,
+    }
+}
+
+#[must_use]
+fn sc_open(args: &Arguments) -> i64 {
+    use ::storage::{
+        fs::Path,
+        vfs::{Handle, OpenMode},
+    };
+
+    let path_start = VirtAddr::try_new(args.one).unwrap_or_default();
+    let path_len = args.two;
+    let mode = match args.three {
+        0 => OpenMode::ReadWrite,
+        beskar_core::syscall::consts::OPEN_APPEND => OpenMode::Append,
+        _ => return Handle::INVALID.id(),
+    };
+
+    if !probe(path_start, path_start + path_len) {
+        return Handle::INVALID.id();
+    }
+
+    // Safety: The buffer's range is owned by the curent process.
+    let raw_path =
+        unsafe { core::slice::from_raw_parts(path_start.as_ptr(), path_len.try_into().unwrap()) };
+    let Ok(path) = core::str::from_utf8(raw_path) else {
+        return Handle::INVALID.id();
+    };
+
+    let res = crate::storage::vfs().open_with_mode(Path::from(path), mode);
+    res.map_or(-1, |handle| handle.id())
+}
+
+#[must_use]
+fn sc_close(args: &Arguments) -> SyscallExitCode {
+    let file_handle = {
+        let raw = args.one.cast_signed();
+        if raw < 0 {
+            return SyscallExitCode::Failure;
+        }
+        // Safety: The handle is used for comparison only
+        // and the given value is positive.
+        unsafe { ::storage::vfs::Handle::from_raw(raw) }
+    };
+    let res = crate::storage::vfs().close(file_handle);
+
+    match res {
+        Ok(()) => SyscallExitCode::Success,
+        Err(_) => SyscallExitCode::Failure, // TODO: Differentiate between errors.
+    }
+}
+
+#[must_use]
+fn sc_fsync(args: &Arguments) -> SyscallExitCode {
+    let file_handle = {
+        let raw = args.one.cast_signed();
+        if raw < 0 {
+            return SyscallExitCode::Failure;
+        }
+        // Safety: The handle is used for comparison only
+        // and the given value is positive.
+        unsafe { ::storage::vfs::Handle::from_raw(raw) }
+    };
+
+    match crate::storage::vfs().sync(file_handle) {
+        Ok(()) => SyscallExitCode::Success,
+        Err(_) => SyscallExitCode::Failure,
+    }
+}
+
+#[must_use]
+fn sc_flock(args: &Arguments) -> i64 {
+    /// Releases cannot wake a waiter up yet, so the lock is retried periodically.
+    const RECHECK_INTERVAL: crate::time::Duration = crate::time::Duration::from_millis(10);
+
+    let file_handle = {
+        let raw = args.one.cast_signed();
+        if raw < 0 {
+            return -1;
+        }
+        // Safety: The handle is used for comparison only
+        // and the given value is positive.
+        unsafe { ::storage::vfs::Handle::from_raw(raw) }
+    };
+    let kind = match FileLockOperation::try_from(args.two) {
+        Ok(FileLockOperation::Shared) => LockKind::Shared,
+        Ok(FileLockOperation::Exclusive) => LockKind::Exclusive,
+        Ok(FileLockOperation::Unlock) => {
+            return crate::storage::vfs().unlock(file_handle).map_or(-1, |()| 0);
+        }
+        Err(_) => return -1,
+    };
+    let nonblocking = args.three & beskar_core::syscall::consts::FLOCK_NONBLOCKING != 0;
+
+    let process = process::current();
+    loop {
+        match crate::storage::vfs().try_lock(file_handle, kind) {
+            Ok(true) => return 0,
+            Ok(false) if nonblocking => return beskar_core::syscall::consts::FLOCK_WOULD_BLOCK,
+            Ok(false) if process.is_exiting() => return -1,
+            Ok(false) => crate::process::scheduler::sleep_for(RECHECK_INTERVAL),
+            Err(_) => return -1,
+        }
+    }
+}
+
+fn sc_seek(args: &Arguments) -> i64 {
+    let file_handle = {
+        let raw = args.one.cast_signed();
+        if raw < 0 {
+            return -1;
+        }
+        // Safety: The handle is used for comparison only
+        // and the given value is positive.
+        unsafe { ::storage::vfs::Handle::from_raw(raw) }
+    };
+    let Ok(offset) = isize::try_from(args.three.cast_signed()) else {
+        return -1;
+    };
+    let pos = match SeekWhence::try_from(args.two) {
+        Ok(SeekWhence::Start) => match usize::try_from(offset) {
+            Ok(offset) => SeekFrom::Start(offset),
+            Err(_) => return -1,
+        },
+        Ok(SeekWhence::Current) => SeekFrom::Current(offset),
+        Ok(SeekWhence::End) => SeekFrom::End(offset),
+        Err(_) => return -1,
+    };
+
+    crate::storage::vfs()
+        .seek(file_handle, pos)
+        .map_or(-1, |position| i64::try_from(position).unwrap_or(-1))
+}
+
+fn sc_pipe(args: &Arguments) -> SyscallExitCode {
+    let handles_start = VirtAddr::try_new(args.one).unwrap_or_default();
+    if !handles_start.is_aligned(beskar_core::arch::Alignment::Align8)
+        || !probe(handles_start, handles_start + size_of::<[i64; 2]>() as u64)
+    {
+        return SyscallExitCode::Failure;
+    }
+
+    let (read, write) = crate::storage::vfs().create_pipe();
+
+    // Safety: The buffer's range is owned by the curent process and is aligned.
+    unsafe {
+        handles_start
+            .as_mut_ptr::<[i64; 2]>()
+            .write([read.id(), write.id()])
+    };
+
+    SyscallExitCode::Success
+}
+
+fn sc_dup(args: &Arguments) -> i64 {
+    let file_handle = {
+        let raw = args.one.cast_signed();
+        if raw < 0 {
+            return -1;
+        }
+        // Safety: The handle is used for comparison only
+        // and the given value is positive.
+        unsafe { ::storage::vfs::Handle::from_raw(raw) }
+    };
+    let Some(close_on_exec) = dup_flags(args.two) else {
+        return -1;
+    };
+
+    let res = crate::storage::vfs().dup(file_handle, close_on_exec);
+    res.map_or(-1, |handle| handle.id())
+}
+
+fn sc_dup2(args: &Arguments) -> SyscallExitCode {
+    let [old, new] = [args.one, args.two].map(u64::cast_signed);
+    if old < 0 || new < 0 {
+        return SyscallExitCode::Failure;
+    }
+    // Safety: The handles are used for comparison only
+    // and the given values are positive.
+    let [old, new] = [old, new].map(|raw| unsafe { ::storage::vfs::Handle::from_raw(raw) });
+    let Some(close_on_exec) = dup_flags(args.three) else {
+        return SyscallExitCode::Failure;
+    };
+
+    match crate::storage::vfs().dup2(old, new, close_on_exec) {
+        Ok(()) => SyscallExitCode::Success,
+        Err(_) => SyscallExitCode::Failure,
+    }
+}
+
+/// Parses the flags of `Dup` and `Dup2`, returning whether the handle is close-on-exec.
+fn dup_flags(flags: u64) -> Option<bool> {
+    use beskar_core::syscall::consts::DUP_CLOSE_ON_EXEC;
+
+    (flags & !DUP_CLOSE_ON_EXEC == 0).then_some(flags & DUP_CLOSE_ON_EXEC != 0)
+}
+
+#[must_use]
+fn sc_sleep(args: &Arguments) -> SyscallExitCode {
+    let sleep_time_ms = args.one;
+
+    let sleep_time = crate::time::Duration::from_millis(sleep_time_ms);
+
+    crate::process::scheduler::sleep_for(sleep_time);
+
+    SyscallExitCode::Success
+}
+
+#[must_use]
+fn sc_wait_on_event(args: &Arguments) -> SyscallExitCode {
+    let handle_raw = args.one;
+    let handle = beskar_core::process::SleepHandle::from_raw(handle_raw);
 
     crate::process::scheduler::sleep_on(handle);
 
     SyscallExitCode::Success
 }
+
+#[must_use]
+fn sc_poll_keyboard_batch(args: &Arguments) -> i64 {
+    let buffer_start = VirtAddr::try_new(args.one).unwrap_or_default();
+    let Some(buffer_len) = args.two.checked_mul(size_of::<u64>() as u64) else {
+        return -1;
+    };
+
+    if !buffer_start.is_aligned(beskar_core::arch::Alignment::Align8)
+        || !probe(buffer_start, buffer_start + buffer_len)
+    {
+        return -1;
+    }
+
+    // Safety: The buffer's range is owned by the curent process and is aligned.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(buffer_start.as_mut_ptr(), args.two.try_into().unwrap())
+    };
+
+    let pid = process::current().pid().as_u64();
+    let Some((count, overflowed)) =
+        crate::drivers::keyboard::with_keyboard_manager(|manager| manager.poll_batch(pid, buffer))
+    else {
+        return -1;
+    };
+
+    let mut res = u64::try_from(count).unwrap();
+    if overflowed {
+        res |= beskar_core::syscall::consts::KEYBOARD_BATCH_OVERFLOW;
+    }
+    res.cast_signed()
+}
+
+#[must_use]
+fn sc_read_key_blocking() -> u64 {
+    use beskar_core::drivers::keyboard::{
+        KeyEvent,
+        focus::{BlockingRead, ReadStep},
+    };
+
+    let mut read = BlockingRead::new(process::current().pid().as_u64());
+    loop {
+        let Some(step) =
+            crate::drivers::keyboard::with_keyboard_manager(|manager| manager.read_step(&mut read))
+        else {
+            return KeyEvent::pack_option(None);
+        };
+        match step {
+            ReadStep::Event(event) => return KeyEvent::pack_option(Some(event)),
+            ReadStep::FocusLost => return beskar_core::syscall::consts::READ_KEY_FOCUS_LOST,
+            ReadStep::Park => crate::process::scheduler::sleep_on(
+                beskar_core::process::SleepHandle::SLEEP_HANDLE_KEYBOARD_INTERRUPT,
+            ),
+        }
+    }
+}
+
+/// Sources cannot wake a poller up yet, so readiness is checked periodically.
+const POLL_RECHECK_INTERVAL: crate::time::Duration = crate::time::Duration::from_millis(10);
+
+/// Borrows an array of `PollItem`s after checking that the current process owns it.
+fn user_poll_items<'a>(
+    start: u64,
+    count: u64,
+) -> Option<&'a mut [beskar_core::syscall::poll::PollItem]> {
+    use beskar_core::syscall::poll::PollItem;
+
+    let start = VirtAddr::try_new(start).unwrap_or_default();
+    let len = count.checked_mul(size_of::<PollItem>() as u64)?;
+
+    if !start.is_aligned(beskar_core::arch::Alignment::Align8) || !probe(start, start + len) {
+        return None;
+    }
+
+    // Safety: The buffer's range is owned by the curent process and is aligned.
+    // Any bit pattern is a valid `PollItem`.
+    Some(unsafe { core::slice::from_raw_parts_mut(start.as_mut_ptr(), count.try_into().ok()?) })
+}
+
+/// Returns the events a source of the current process is ready for.
+fn poll_readiness(
+    source: beskar_core::syscall::poll::PollSource,
+) -> beskar_core::syscall::poll::PollEvents {
+    use beskar_core::syscall::poll::{PollEvents, PollSource};
+
+    match source {
+        PollSource::Keyboard => {
+            let pid = process::current().pid().as_u64();
+            if crate::drivers::keyboard::with_keyboard_manager(|manager| manager.has_events(pid))
+                .unwrap_or(false)
+            {
+                PollEvents::READABLE
+            } else {
+                PollEvents::NONE
+            }
+        }
+        PollSource::Display => {
+            if crate::display::has_event() {
+                PollEvents::READABLE
+            } else {
+                PollEvents::NONE
+            }
+        }
+        // TODO: Report readiness once these sources exist.
+        PollSource::Mouse | PollSource::IpcInbox => PollEvents::INVALID,
+        PollSource::File(raw) => {
+            use ::storage::{pipe::PipeEnd, vfs::Handle};
+
+            if raw < 0 {
+                return PollEvents::INVALID;
+            }
+            // Safety: The handle is used for comparison only
+            // and the given value is positive.
+            let handle = unsafe { Handle::from_raw(raw) };
+            let vfs = crate::storage::vfs();
+            match vfs.pipe_readiness(handle) {
+                Some((PipeEnd::Read, true)) => PollEvents::READABLE,
+                Some((PipeEnd::Write, true)) => PollEvents::WRITABLE,
+                Some((_, false)) => PollEvents::NONE,
+                // Files never block.
+                None if vfs.is_handle_valid(handle) => PollEvents::READABLE | PollEvents::WRITABLE,
+                None => PollEvents::INVALID,
+            }
+        }
+    }
+}
+
+/// Calls `check` until it reports ready items or the timeout elapses.
+///
+/// Returns the last number of ready items.
+fn poll_until(timeout: PollTimeout, mut check: impl FnMut() -> usize) -> usize {
+    let deadline = match timeout {
+        PollTimeout::Immediate => None,
+        PollTimeout::Infinite => Some(crate::time::Instant::MAX),
+        PollTimeout::After(duration) => Some(crate::time::now() + duration),
+    };
+
+    loop {
+        let count = check();
+        if count > 0 {
+            break count;
+        }
+
+        let now = crate::time::now();
+        match deadline {
+            Some(deadline) if now < deadline => {
+                crate::process::scheduler::sleep_for(POLL_RECHECK_INTERVAL.min(deadline - now));
+            }
+            _ => break 0,
+        }
+    }
+}
+
+#[must_use]
+fn sc_poll(args: &Arguments) -> i64 {
+    let Some(items) = user_poll_items(args.one, args.two) else {
+        return -1;
+    };
+
+    let count = poll_until(PollTimeout::from_raw(args.three), || {
+        beskar_core::syscall::poll::update_readiness(items, poll_readiness)
+    });
+    i64::try_from(count).unwrap()
+}
+
+#[must_use]
+fn sc_poll_create() -> i64 {
+    process::current()
+        .create_poll_set()
+        .map_or(-1, |id| i64::try_from(id).unwrap())
+}
+
+/// Copies the `PollItem` a poll set syscall points to.
+fn user_poll_item(start: u64) -> Option<beskar_core::syscall::poll::PollItem> {
+    user_poll_items(start, 1).map(|items| items[0])
+}
+
+#[must_use]
+fn sc_poll_add(args: &Arguments) -> SyscallExitCode {
+    let Some(item) = user_poll_item(args.two) else {
+        return SyscallExitCode::Failure;
+    };
+    let res = process::current().with_poll_set(args.one, |set| set.add(item));
+    if matches!(res, Some(Ok(()))) {
+        SyscallExitCode::Success
+    } else {
+        SyscallExitCode::Failure
+    }
+}
+
+#[must_use]
+fn sc_poll_del(args: &Arguments) -> SyscallExitCode {
+    let Some(source) = user_poll_item(args.two).and_then(|item| item.source()) else {
+        return SyscallExitCode::Failure;
+    };
+    let res = process::current().with_poll_set(args.one, |set| set.remove(source));
+    if matches!(res, Some(Ok(()))) {
+        SyscallExitCode::Success
+    } else {
+        SyscallExitCode::Failure
+    }
+}
+
+#[must_use]
+fn sc_poll_wait(args: &Arguments) -> i64 {
+    let Some(ready) = user_poll_items(args.two, args.three) else {
+        return -1;
+    };
+    let process = process::current();
+    if process.with_poll_set(args.one, |_| ()).is_none() {
+        return -1;
+    }
+
+    // The set is only locked while readiness is checked, so that it can change during the wait.
+    let count = poll_until(PollTimeout::from_raw(args.four), || {
+        process
+            .with_poll_set(args.one, |set| set.collect_ready(ready, poll_readiness))
+            .unwrap_or(0)
+    });
+    i64::try_from(count).unwrap()
+}
+
+/// Borrows a user-space string after checking that the current process owns it.
+fn user_str<'a>(start: u64, len: u64) -> Option<&'a str> {
+    let start = VirtAddr::try_new(start)?;
+    if !probe(start, start + len) {
+        return None;
+    }
+    // Safety: The buffer's range is owned by the curent process.
+    let bytes = unsafe { core::slice::from_raw_parts(start.as_ptr(), len.try_into().ok()?) };
+    core::str::from_utf8(bytes).ok()
+}
+
+#[must_use]
+fn sc_get_env(args: &Arguments) -> i64 {
+    let Some(key) = user_str(args.one, args.two) else {
+        return -1;
+    };
+
+    let buffer_start = VirtAddr::try_new(args.three).unwrap_or_default();
+    let buffer_len = args.four;
+    if !probe(buffer_start, buffer_start + buffer_len) {
+        return -1;
+    }
+    // Safety: The buffer's range is owned by the curent process.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(buffer_start.as_mut_ptr(), buffer_len.try_into().unwrap())
+    };
+
+    process::current().with_env(|env| {
+        let value = if key.is_empty() {
+            env.as_bytes()
+        } else {
+            match env.get(key) {
+                Some(value) => value.as_bytes(),
+                None => return -1,
+            }
+        };
+
+        let copied = value.len().min(buffer.len());
+        buffer[..copied].copy_from_slice(&value[..copied]);
+        i64::try_from(value.len()).unwrap()
+    })
+}
+
+#[must_use]
+fn sc_set_env(args: &Arguments) -> SyscallExitCode {
+    let Some(key) = user_str(args.one, args.two) else {
+        return SyscallExitCode::Failure;
+    };
+
+    if args.three == 0 {
+        process::current().with_env(|env| env.unset(key));
+        return SyscallExitCode::Success;
+    }
+
+    let Some(value) = user_str(args.three, args.four) else {
+        return SyscallExitCode::Failure;
+    };
+
+    match process::current().with_env(|env| env.set(key, value)) {
+        Ok(()) => SyscallExitCode::Success,
+        Err(_) => SyscallExitCode::Failure,
+    }
+}
+
+#[must_use]
+fn sc_metadata(args: &Arguments) -> SyscallExitCode {
+    use ::storage::fs::{FileType, Path};
+    use beskar_core::syscall::{FileInfo, FileKind};
+
+    let Some(path) = user_str(args.one, args.two) else {
+        return SyscallExitCode::Failure;
+    };
+
+    let info_start = VirtAddr::try_new(args.three).unwrap_or_default();
+    if !info_start.is_aligned(beskar_core::arch::Alignment::Align8)
+        || !probe(info_start, info_start + size_of::<FileInfo>() as u64)
+    {
+        return SyscallExitCode::Failure;
+    }
+
+    let Ok(metadata) = crate::storage::vfs().metadata(Path::from(path)) else {
+        return SyscallExitCode::Failure;
+    };
+    let kind = match metadata.file_type() {
+        FileType::File => FileKind::File,
+        FileType::Directory => FileKind::Directory,
+    };
+    let info = FileInfo::new(metadata.size() as u64, kind);
+
+    // Safety: The buffer's range is owned by the curent process and is aligned.
+    unsafe { info_start.as_mut_ptr::<FileInfo>().write(info) };
+
+    SyscallExitCode::Success
+}
+
+#[must_use]
+fn sc_read_dir(args: &Arguments) -> i64 {
+    let Some(path) = user_str(args.one, args.two) else {
+        return -1;
+    };
+
+    let buffer_start = VirtAddr::try_new(args.three).unwrap_or_default();
+    let buffer_len = args.four;
+    if !probe(buffer_start, buffer_start + buffer_len) {
+        return -1;
+    }
+    // Safety: The buffer's range is owned by the curent process.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(buffer_start.as_mut_ptr(), buffer_len.try_into().unwrap())
+    };
+
+    let Ok(entries) = crate::storage::vfs().read_dir(::storage::fs::Path::from(path)) else {
+        return -1;
+    };
+
+    let mut needed = 0;
+    for entry in &entries {
+        let name = entry.as_path();
+        let end = needed + name.len() + 1;
+        if let Some(dst) = buffer.get_mut(needed..end) {
+            dst[..name.len()].copy_from_slice(name.as_bytes());
+            dst[name.len()] = 0;
+        }
+        needed = end;
+    }
+    i64::try_from(needed).unwrap()
+}
+
+#[must_use]
+fn sc_process_list(args: &Arguments) -> i64 {
+    use beskar_core::syscall::process::{ProcessInfo, fill_list};
+
+    let buffer_start = VirtAddr::try_new(args.one).unwrap_or_default();
+    let Some(buffer_size) = args.two.checked_mul(size_of::<ProcessInfo>() as u64) else {
+        return -1;
+    };
+    if !buffer_start.is_aligned(beskar_core::arch::Alignment::Align8)
+        || !probe(buffer_start, buffer_start + buffer_size)
+    {
+        return -1;
+    }
+    // Safety: The buffer's range is owned by the curent process and is aligned.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(
+            buffer_start.as_mut_ptr::<ProcessInfo>(),
+            args.two.try_into().unwrap(),
+        )
+    };
+
+    let processes = process::list(args.three, buffer.len());
+    let written = fill_list(processes.iter().map(|p| p.info()), args.three, buffer);
+    i64::try_from(written).unwrap()
+}
+
+#[must_use]
+fn sc_packet_capture(args: &Arguments) -> i64 {
+    let Ok(ethertype) = u16::try_from(args.three) else {
+        return -1;
+    };
+
+    let buffer_start = VirtAddr::try_new(args.one).unwrap_or_default();
+    let buffer_len = args.two;
+    if !probe(buffer_start, buffer_start + buffer_len) {
+        return -1;
+    }
+    // Safety: The buffer's range is owned by the curent process.
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(buffer_start.as_mut_ptr(), buffer_len.try_into().unwrap())
+    };
+
+    let filter = (ethertype != 0).then_some(ethertype);
+    let written = crate::network::take_captured(filter, buffer);
+    i64::try_from(written).unwrap()
+}
+
+#[must_use]
+fn sc_framebuffer_info(args: &Arguments) -> SyscallExitCode {
+    use beskar_core::syscall::framebuffer::FbInfo;
+
+    let info_start = VirtAddr::try_new(args.one).unwrap_or_default();
+    if !info_start.is_aligned(beskar_core::arch::Alignment::Align4)
+        || !probe(info_start, info_start + size_of::<FbInfo>() as u64)
+    {
+        return SyscallExitCode::Failure;
+    }
+
+    let info = FbInfo::from(video::screen::with_screen(|screen| screen.info()));
+
+    // Safety: The buffer's range is owned by the curent process and is aligned.
+    unsafe { info_start.as_mut_ptr::<FbInfo>().write(info) };
+
+    SyscallExitCode::Success
+}
+
+#[must_use]
+fn sc_acquire_display() -> SyscallExitCode {
+    match crate::display::acquire() {
+        Ok(()) => SyscallExitCode::Success,
+        Err(_) => SyscallExitCode::Failure,
+    }
+}
+
+#[must_use]
+fn sc_release_display() -> SyscallExitCode {
+    crate::display::release(process::current().pid().as_u64());
+    SyscallExitCode::Success
+}
+
+#[must_use]
+fn sc_display_event() -> u64 {
+    crate::display::take_event().map_or(0, u64::from)
+}
@@ -1,6 +1,19 @@
-use crate::drivers::{hpet, tsc};
+use crate::{
+    arch::cpuid,
+    drivers::{hpet, tsc},
+    mem::address_space,
+};
 pub use beskar_core::time::{Duration, Instant};
+use beskar_core::{
+    arch::{
+        VirtAddr,
+        paging::{Frame, M4KiB, Mapper, MemSize},
+    },
+    time::TscClock as SharedTscClock,
+};
+use beskar_hal::paging::page_table::Flags;
 use core::sync::atomic::{AtomicBool, Ordering};
+use hyperdrive::{locks::seq::SeqLock, once::Once};
 
 static HPET_AVAILABLE: AtomicBool = AtomicBool::new(false);
 static TSC_AVAILABLE: AtomicBool = AtomicBool::new(false);
@@ -13,6 +26,73 @@ pub fn init() {
     HPET_AVAILABLE.store(hpet_res.is_ok(), Ordering::Relaxed);
     let tsc_res = crate::drivers::tsc::init();
     TSC_AVAILABLE.store(tsc_res.is_ok(), Ordering::Relaxed);
+
+    init_shared_clock();
+}
+
+/// The clock shared read-only with every process, so that they read the time without a syscall.
+static SHARED_CLOCK: Once<SharedClock> = Once::uninit();
+
+struct SharedClock {
+    frame: Frame<M4KiB>,
+    clock: &'static SeqLock<SharedTscClock>,
+}
+
+fn init_shared_clock() {
+    let kernel_space = address_space::get_kernel_address_space();
+    let Some(page) = kernel_space
+        .alloc_map::<M4KiB>(
+            usize::try_from(M4KiB::SIZE).unwrap(),
+            Flags::WRITABLE | Flags::NO_EXECUTE,
+        )
+        .map(|range| range.start())
+    else {
+        video::warn!("Failed to allocate the shared clock");
+        return;
+    };
+    let frame = kernel_space
+        .with_page_table(|page_table| page_table.translate(page))
+        .unwrap()
+        .0;
+
+    let clock = page.start_address().as_mut_ptr::<SeqLock<SharedTscClock>>();
+    // Safety: The page was just mapped, and is large enough for the clock.
+    let clock = unsafe {
+        clock.write(SeqLock::new(SharedTscClock::UNAVAILABLE));
+        &*clock
+    };
+    SHARED_CLOCK.call_once(|| SharedClock { frame, clock });
+
+    publish_clock();
+}
+
+/// Updates the shared clock with the current TSC calibration.
+///
+/// Userspace can only convert TSC values on its own if the TSC is the kernel clock,
+/// and if it runs at a constant rate.
+fn publish_clock() {
+    let Some(shared) = SHARED_CLOCK.get() else {
+        return;
+    };
+
+    let tsc_clock = if TSC_AVAILABLE.load(Ordering::Acquire)
+        && cpuid::check_feature(cpuid::CpuFeature::INVARIANT_TSC)
+    {
+        // This is the conversion of `TscClock::now`.
+        SharedTscClock::new(0, Instant::ZERO, tsc::ticks_per_ms() / 1_000)
+    } else {
+        SharedTscClock::UNAVAILABLE
+    };
+    shared.clock.write(tsc_clock);
+}
+
+/// Maps the shared clock into the address space of the current process, read-only.
+pub fn map_shared_clock() -> Option<VirtAddr> {
+    let shared = SHARED_CLOCK.get()?;
+    let page = crate::process::current()
+        .address_space()
+        .map_shared(shared.frame, Flags::USER_ACCESSIBLE | Flags::NO_EXECUTE)?;
+    Some(page.start_address())
 }
 
 /// Waits for AT LEAST the given number of milliseconds.
@@ -69,7 +149,8 @@ impl Clock for HpetClock {
 impl Clock for TscClock {
     #[inline]
     fn now(&self) -> Instant {
-        Instant::from_millis(tsc::main_counter_value() / self.ticks_per_ms())
+        // The TSC frequency is known to the MHz, so it is precise to the microsecond.
+        Instant::from_micros(tsc::main_counter_value() / (self.ticks_per_ms() / 1_000))
     }
 
     #[inline]
@@ -3,13 +3,138 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 extern crate alloc;
 
-use alloc::string::String;
+use alloc::{string::String, vec::Vec};
 use beskar_core::video::{
     Info, Pixel, PixelComponents, PixelFormat,
     writer::{CHAR_HEIGHT, CHAR_WIDTH, FramebufferWriter, LETTER_SPACING, LINE_SPACING},
 };
 use core::fmt::{self, Write};
 
+/// Shortens `text` to at most `max_width` characters by cutting its beginning,
+/// which is replaced by an ellipsis.
+///
+/// This keeps the most specific part of paths, e.g. `.../bin/bashkar`.
+#[must_use]
+pub fn truncate_left(text: &str, max_width: usize) -> String {
+    const ELLIPSIS: &str = "...";
+
+    let len = text.chars().count();
+    if len <= max_width {
+        return String::from(text);
+    }
+    if max_width <= ELLIPSIS.len() {
+        return ELLIPSIS[..max_width].into();
+    }
+
+    let kept = max_width - ELLIPSIS.len();
+    let mut truncated = String::from(ELLIPSIS);
+    truncated.extend(text.chars().skip(len - kept));
+    truncated
+}
+
+/// Horizontal alignment of a table column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// A grid of text cells, laid out in aligned columns.
+///
+/// Each column is as wide as its widest cell, and columns are separated by two spaces.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    columns: Vec<(String, Align)>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    /// Adds a column with the given header.
+    pub fn column(mut self, header: &str, align: Align) -> Self {
+        self.columns.push((String::from(header), align));
+        self
+    }
+
+    /// Adds a row.
+    ///
+    /// Missing cells are left empty and extra cells are ignored.
+    pub fn push_row<I, S>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut row: Vec<String> = cells
+            .into_iter()
+            .take(self.columns.len())
+            .map(Into::into)
+            .collect();
+        row.resize(self.columns.len(), String::new());
+        self.rows.push(row);
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    #[must_use]
+    /// Renders the header and the rows, one line each.
+    pub fn render(&self) -> String {
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, (header, _))| {
+                self.rows
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .chain(core::iter::once(header.chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let headers = self.columns.iter().map(|(header, _)| header);
+        let mut text = String::new();
+        for line in core::iter::once(headers.collect::<Vec<_>>())
+            .chain(self.rows.iter().map(|row| row.iter().collect()))
+        {
+            let mut cells = line.iter().zip(&self.columns).zip(&widths).peekable();
+            while let Some(((cell, (_, align)), &width)) = cells.next() {
+                let is_last = cells.peek().is_none();
+                let _ = match align {
+                    // Avoid trailing spaces on the last column.
+                    Align::Left if is_last => write!(text, "{cell}"),
+                    Align::Left => write!(text, "{cell:<width$}"),
+                    Align::Right => write!(text, "{cell:>width$}"),
+                };
+                if !is_last {
+                    text.push_str("  ");
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
 /// A rectangle expressed in character cells.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct CharRect {
@@ -93,6 +218,26 @@ impl Theme {
         }
     }
 
+    #[must_use]
+    #[inline]
+    /// Returns the theme with its foreground faded halfway into the background.
+    pub fn dimmed(&self) -> Self {
+        Self {
+            foreground: self.foreground.blend_linear(self.background, 128),
+            background: self.background,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the theme with its foreground brightened halfway towards white.
+    pub fn highlighted(&self) -> Self {
+        Self {
+            foreground: self.foreground.blend_linear(PixelComponents::WHITE, 128),
+            background: self.background,
+        }
+    }
+
     #[must_use]
     #[inline]
     pub const fn white_on_black() -> Self {
@@ -103,6 +248,95 @@ impl Theme {
     }
 }
 
+/// A semantic role for text, resolved to a color by a `Palette`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    Normal,
+    Error,
+    Warning,
+    Success,
+    Accent,
+    Dim,
+}
+
+/// Colors for each semantic slot, on top of a background.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub background: PixelComponents,
+    pub normal: PixelComponents,
+    pub error: PixelComponents,
+    pub warning: PixelComponents,
+    pub success: PixelComponents,
+    pub accent: PixelComponents,
+    pub dim: PixelComponents,
+}
+
+impl Palette {
+    #[must_use]
+    /// Builds a palette around a theme, using standard colors for the other slots.
+    pub fn from_theme(theme: Theme) -> Self {
+        Self {
+            background: theme.background,
+            normal: theme.foreground,
+            error: PixelComponents::RED,
+            warning: PixelComponents::YELLOW,
+            success: PixelComponents::GREEN,
+            accent: PixelComponents::CYAN,
+            dim: theme.dimmed().foreground,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// The dark variant of the Solarized color scheme.
+    pub const fn solarized_dark() -> Self {
+        Self {
+            background: PixelComponents::new(0x00, 0x2B, 0x36),
+            normal: PixelComponents::new(0x83, 0x94, 0x96),
+            error: PixelComponents::new(0xDC, 0x32, 0x2F),
+            warning: PixelComponents::new(0xB5, 0x89, 0x00),
+            success: PixelComponents::new(0x85, 0x99, 0x00),
+            accent: PixelComponents::new(0x26, 0x8B, 0xD2),
+            dim: PixelComponents::new(0x58, 0x6E, 0x75),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// A black and white palette, where only dimmed text stands out.
+    pub const fn mono() -> Self {
+        Self {
+            background: PixelComponents::BLACK,
+            normal: PixelComponents::WHITE,
+            error: PixelComponents::WHITE,
+            warning: PixelComponents::WHITE,
+            success: PixelComponents::WHITE,
+            accent: PixelComponents::WHITE,
+            dim: PixelComponents::new(0x80, 0x80, 0x80),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn get(&self, slot: Slot) -> PixelComponents {
+        match slot {
+            Slot::Normal => self.normal,
+            Slot::Error => self.error,
+            Slot::Warning => self.warning,
+            Slot::Success => self.success,
+            Slot::Accent => self.accent,
+            Slot::Dim => self.dim,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Returns the two-color base of the palette.
+    pub const fn theme(&self) -> Theme {
+        Theme::new(self.normal, self.background)
+    }
+}
+
 /// Border styling for ASCII boxes.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct BoxStyle {
@@ -163,6 +397,7 @@ pub struct AsciiCanvas<'a> {
     cell_h: u16,
     pixel_format: PixelFormat,
     theme: Theme,
+    palette: Palette,
 }
 
 /// Buffered text formatter for ASCII UI output.
@@ -225,6 +460,7 @@ impl<'a> AsciiCanvas<'a> {
             cell_h,
             pixel_format: info.pixel_format(),
             theme,
+            palette: Palette::from_theme(theme),
         }
     }
 
@@ -239,6 +475,13 @@ impl<'a> AsciiCanvas<'a> {
         self.set_color(theme.foreground);
     }
 
+    #[inline]
+    /// Sets the palette used by `write_semantic`, and its base theme.
+    pub const fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.set_theme(palette.theme());
+    }
+
     #[inline]
     pub fn clear(&mut self, color: PixelComponents) {
         let pixel = Pixel::from_format(self.pixel_format, color);
@@ -296,6 +539,30 @@ impl<'a> AsciiCanvas<'a> {
         self.writer.write_str_at(self.buffer, x, y, &trimmed);
     }
 
+    #[inline]
+    /// Writes a line using the color of the given palette slot.
+    pub fn write_semantic(&mut self, col: u16, row: u16, text: &str, slot: Slot) {
+        self.write_line_with_color(col, row, text, self.palette.get(slot));
+    }
+
+    #[inline]
+    /// Writes a line using the dimmed foreground color of the theme.
+    pub fn write_line_dimmed(&mut self, col: u16, row: u16, text: &str) {
+        self.write_line_with_color(col, row, text, self.theme.dimmed().foreground);
+    }
+
+    #[inline]
+    /// Writes a line using the highlighted foreground color of the theme.
+    pub fn write_line_highlighted(&mut self, col: u16, row: u16, text: &str) {
+        self.write_line_with_color(col, row, text, self.theme.highlighted().foreground);
+    }
+
+    fn write_line_with_color(&mut self, col: u16, row: u16, text: &str, color: PixelComponents) {
+        self.set_color(color);
+        self.write_line(col, row, text);
+        self.set_color(self.theme.foreground);
+    }
+
     #[inline]
     pub fn write_line_centered(&mut self, row: u16, text: &str) {
         if row >= self.rows {
@@ -392,6 +659,12 @@ impl<'a> AsciiCanvas<'a> {
     pub const fn theme(&self) -> Theme {
         self.theme
     }
+
+    #[must_use]
+    #[inline]
+    pub const fn palette(&self) -> Palette {
+        self.palette
+    }
 }
 
 #[cfg(test)]
@@ -399,6 +672,46 @@ mod tests {
     use super::*;
     use beskar_core::video::{Info, PixelComponents};
 
+    #[test]
+    fn test_truncate_left() {
+        assert_eq!(truncate_left("/home/user", 20), "/home/user");
+        assert_eq!(truncate_left("/home/user", 10), "/home/user");
+        assert_eq!(
+            truncate_left("/home/user/projects/beskar", 10),
+            ".../beskar"
+        );
+        assert_eq!(
+            truncate_left("/home/user/projects/beskar", 12),
+            "...ts/beskar"
+        );
+        assert_eq!(truncate_left("/très/long/chemin", 8), "...hemin");
+        assert_eq!(truncate_left("/home/user", 3), "...");
+        assert_eq!(truncate_left("/home/user", 2), "..");
+        assert_eq!(truncate_left("/home/user", 0), "");
+    }
+
+    #[test]
+    fn test_table() {
+        let mut table = Table::new()
+            .column("PID", Align::Right)
+            .column("NAME", Align::Left)
+            .column("STATE", Align::Left);
+        assert!(table.is_empty());
+
+        table.push_row(["0", "kernel", "R"]);
+        table.push_row(["12", "bashkar", "Z", "ignored"]);
+        table.push_row(["3"]);
+        assert_eq!(table.len(), 3);
+
+        assert_eq!(
+            table.render(),
+            "PID  NAME     STATE\n  \
+               0  kernel   R\n \
+              12  bashkar  Z\n  \
+               3           \n"
+        );
+    }
+
     #[test]
     fn test_char_rect_new() {
         let rect = CharRect::new(10, 20, 30, 40);
@@ -455,6 +768,21 @@ mod tests {
         assert_eq!(inverted.background, PixelComponents::WHITE);
     }
 
+    #[test]
+    fn test_theme_dimmed_highlighted() {
+        let theme = Theme::new(PixelComponents::new(200, 100, 0), PixelComponents::BLACK);
+
+        let dimmed = theme.dimmed();
+        assert_eq!(dimmed.background, theme.background);
+        assert!(dimmed.foreground.red < theme.foreground.red);
+        assert!(dimmed.foreground.green < theme.foreground.green);
+
+        let highlighted = theme.highlighted();
+        assert_eq!(highlighted.background, theme.background);
+        assert!(highlighted.foreground.red > theme.foreground.red);
+        assert!(highlighted.foreground.blue > theme.foreground.blue);
+    }
+
     #[test]
     fn test_theme_white_on_black() {
         let theme = Theme::white_on_black();
@@ -462,6 +790,52 @@ mod tests {
         assert_eq!(theme.background, PixelComponents::BLACK);
     }
 
+    #[test]
+    fn test_palette_from_theme() {
+        let theme = Theme::new(PixelComponents::new(200, 200, 200), PixelComponents::BLACK);
+        let palette = Palette::from_theme(theme);
+
+        assert_eq!(palette.theme(), theme);
+        assert_eq!(palette.get(Slot::Normal), theme.foreground);
+        assert_eq!(palette.get(Slot::Error), PixelComponents::RED);
+        assert_eq!(palette.get(Slot::Warning), PixelComponents::YELLOW);
+        assert_eq!(palette.get(Slot::Success), PixelComponents::GREEN);
+        assert_eq!(palette.get(Slot::Accent), PixelComponents::CYAN);
+        assert_eq!(palette.get(Slot::Dim), theme.dimmed().foreground);
+    }
+
+    #[test]
+    fn test_palette_builtins() {
+        let solarized = Palette::solarized_dark();
+        assert_eq!(
+            solarized.get(Slot::Error),
+            PixelComponents::new(0xDC, 0x32, 0x2F)
+        );
+        assert_eq!(solarized.get(Slot::Accent), solarized.accent);
+        assert_ne!(solarized.get(Slot::Error), solarized.get(Slot::Success));
+
+        let mono = Palette::mono();
+        assert_eq!(mono.theme(), Theme::white_on_black());
+        assert_eq!(mono.get(Slot::Error), mono.get(Slot::Normal));
+        assert_ne!(mono.get(Slot::Dim), mono.get(Slot::Normal));
+    }
+
+    #[test]
+    fn test_ascii_canvas_set_palette() {
+        let mut buffer = [Pixel::from_format(PixelFormat::Rgb, PixelComponents::BLACK); 800 * 600];
+        let mut canvas = create_test_canvas(800, 600, &mut buffer);
+        assert_eq!(
+            canvas.palette(),
+            Palette::from_theme(Theme::white_on_black())
+        );
+
+        canvas.set_palette(Palette::solarized_dark());
+        assert_eq!(canvas.palette(), Palette::solarized_dark());
+        assert_eq!(canvas.theme(), Palette::solarized_dark().theme());
+
+        canvas.write_semantic(0, 0, "error", Slot::Error);
+    }
+
     #[test]
     fn test_box_style_classic() {
         let style = BoxStyle::classic();
@@ -5,10 +5,71 @@ use alloc::{
     vec::Vec,
 };
 use core::fmt::Write as _;
+use hyperdrive::once::Once;
 
 /// A shell command result
 pub type CommandResult = Result<(), String>;
 
+pub mod coreutils;
+pub mod fs;
+pub mod net;
+mod registry;
+pub use registry::{Command, CommandRegistry, Handler};
+
+static BUILTINS: Once<CommandRegistry<Tty>> = Once::uninit();
+
+/// Returns the registry of built-in commands.
+fn builtins() -> &'static CommandRegistry<Tty> {
+    BUILTINS.call_once(|| {
+        let mut registry = CommandRegistry::new();
+        registry.register(
+            "cat",
+            "Print the content of files",
+            "cat <file>...",
+            |args, tty| coreutils::cat(args, &fs::SysVfs, tty),
+        );
+        registry.register("clear", "Clear the terminal screen", "clear", cmd_clear);
+        registry.register(
+            "echo",
+            "Echo arguments to the console",
+            "echo [-n] [text]",
+            cmd_echo,
+        );
+        registry.register("exit", "Exit the shell", "exit", cmd_exit);
+        registry.register(
+            "help",
+            "Display help about commands",
+            "help [cmd]",
+            cmd_help,
+        );
+        registry.register(
+            "layout",
+            "Show or set the keyboard layout (us, fr)",
+            "layout [l]",
+            cmd_layout,
+        );
+        registry.register(
+            "ls",
+            "List the entries of a directory",
+            "ls [-l] [dir]",
+            |args, tty| {
+                let cwd = String::from(tty.cwd());
+                coreutils::ls(args, &cwd, &fs::SysVfs, tty)
+            },
+        );
+        registry.register(
+            "pcap",
+            "Print a summary of the received network frames",
+            "pcap [-c count] [arp|ip|ipv6|0xNNNN]",
+            cmd_pcap,
+        );
+        registry.register("ps", "List the running processes", "ps", cmd_ps);
+        registry.register("rand", "Generate random bytes", "rand [n]", cmd_rand);
+        registry
+    });
+    BUILTINS.get().unwrap()
+}
+
 /// Execute a command with its arguments
 ///
 /// # Errors
@@ -16,24 +77,29 @@ pub type CommandResult = Result<(), String>;
 /// Returns `Ok(())` if the command was executed successfully.
 /// Returns `Err(String)` if the command was not recognized or failed.
 pub fn execute_command(command: &str, args: &[String], tty: &mut Tty) -> CommandResult {
-    match command {
-        "" => Ok(()),
-        "help" => {
-            cmd_help(tty);
-            Ok(())
-        }
-        "echo" => {
-            cmd_echo(args, tty);
-            Ok(())
-        }
-        "clear" => {
-            cmd_clear(tty);
-            Ok(())
-        }
-        "exit" => beskar_lib::exit(beskar_lib::ExitCode::Success),
-        "rand" => cmd_rand(args, tty),
-        _ => Err(alloc::format!("Unknown command: {command}")),
+    if command.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(builtin) = builtins().get(command) {
+        return builtin.run(args, tty);
     }
+
+    find_in_path(command).map_or_else(
+        || Err(alloc::format!("Unknown command: {command}")),
+        // TODO: Spawn the program once processes can be created from userspace.
+        |path| {
+            Err(alloc::format!(
+                "{path}: running programs is not supported yet"
+            ))
+        },
+    )
+}
+
+/// Search the directories listed in the `PATH` variable for a program
+fn find_in_path(command: &str) -> Option<String> {
+    let path_var = beskar_lib::process::env::get("PATH").unwrap_or_default();
+    beskar_lib::process::resolve_command(command, &path_var)
 }
 
 /// Parse a command line into a command and arguments
@@ -45,30 +111,96 @@ pub fn parse_command_line(line: &str) -> (String, Vec<String>) {
 }
 
 /// Display help text
-fn cmd_help(tty: &mut Tty) {
-    tty.write_str(
-        "BeskarOS Shell - Available commands:\n  \
-            clear       - Clear the terminal screen\n  \
-            echo [text] - Echo arguments to the console\n  \
-            exit        - Exit the shell\n  \
-            help        - Display this help text\n  \
-            rand [n]    - Generate random bytes\n\
-        ",
-    );
+fn cmd_help(args: &[String], tty: &mut Tty) -> CommandResult {
+    let registry = builtins();
+    match args.first() {
+        None => tty.write_str(&registry.help_listing()),
+        Some(name) => {
+            let help = registry
+                .help_for(name)
+                .ok_or_else(|| alloc::format!("No help for unknown command: {name}"))?;
+            tty.write_str(&help);
+        }
+    }
+    Ok(())
 }
 
 /// Clear the terminal screen
-fn cmd_clear(tty: &mut Tty) {
+#[allow(clippy::unnecessary_wraps)]
+fn cmd_clear(_args: &[String], tty: &mut Tty) -> CommandResult {
     tty.clear_screen();
+    Ok(())
 }
 
 /// Echo arguments to the console
-fn cmd_echo(args: &[String], tty: &mut Tty) {
-    if !args.is_empty() {
-        let output = args.join(" ");
-        tty.write_str(&output);
+#[allow(clippy::unnecessary_wraps)]
+fn cmd_echo(args: &[String], tty: &mut Tty) -> CommandResult {
+    coreutils::echo(args, tty);
+    Ok(())
+}
+
+/// Exit the shell
+fn cmd_exit(_args: &[String], _tty: &mut Tty) -> CommandResult {
+    beskar_lib::exit(beskar_lib::ExitCode::Success)
+}
+
+fn cmd_ps(_args: &[String], tty: &mut Tty) -> CommandResult {
+    let processes = beskar_lib::process::list()
+        .map_err(|e| alloc::format!("ps: cannot list processes: {e:?}"))?;
+    coreutils::ps(&processes, tty);
+    Ok(())
+}
+
+fn cmd_pcap(args: &[String], tty: &mut Tty) -> CommandResult {
+    use beskar_lib::args::{ArgParser, Opt};
+    use beskar_lib::time::{Duration, now};
+
+    const OPTIONS: &[Opt] = &[Opt::value(Some('c'), "count")];
+    const DEFAULT_COUNT: usize = 10;
+    /// The shell is busy while capturing, so the capture stops when the network is quiet.
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    let args = ArgParser::new(OPTIONS)
+        .parse(args)
+        .map_err(|e| alloc::format!("pcap: {e}"))?;
+    let count = args.value("count").map_or(Ok(DEFAULT_COUNT), |count| {
+        count
+            .parse::<usize>()
+            .map_err(|_| alloc::format!("pcap: invalid count: {count}"))
+    })?;
+    let filter = match args.positionals() {
+        [] => None,
+        [filter] => Some(
+            net::parse_filter(filter)
+                .ok_or_else(|| alloc::format!("pcap: invalid filter: {filter}"))?,
+        ),
+        [..] => return Err(String::from("pcap: too many arguments")),
+    };
+
+    let mut buffer = alloc::vec![0; 4096];
+    let mut printed = 0;
+    let mut deadline = now() + IDLE_TIMEOUT;
+    while printed < count && now() < deadline {
+        let written = beskar_lib::net::capture(&mut buffer, filter)
+            .map_err(|e| alloc::format!("pcap: cannot capture: {e:?}"))?;
+        if written == 0 {
+            let _ = beskar_lib::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        // Frames past the count are discarded
+        for (record, data) in
+            beskar_lib::net::Records::new(&buffer[..written]).take(count - printed)
+        {
+            tty.write_str(&net::summarize(&record, data));
+            tty.write_str("\n");
+            printed += 1;
+        }
+        deadline = now() + IDLE_TIMEOUT;
     }
-    tty.write_str("\n");
+
+    Ok(())
 }
 
 fn cmd_rand(args: &[String], tty: &mut Tty) -> CommandResult {
@@ -89,7 +221,7 @@ fn cmd_rand(args: &[String], tty: &mut Tty) -> CommandResult {
     }
 
     let mut buffer = alloc::vec![0u8; num_bytes];
-    beskar_lib::rand::rand_fill(&mut buffer)
+    beskar_lib::rand::fill(&mut buffer)
         .map_err(|e| alloc::format!("Random generation failed: {e:?}"))?;
 
     tty.write_str("Random Bytes: ");
@@ -103,3 +235,23 @@ fn cmd_rand(args: &[String], tty: &mut Tty) -> CommandResult {
 
     Ok(())
 }
+
+fn cmd_layout(args: &[String], tty: &mut Tty) -> CommandResult {
+    use beskar_lib::io::keyboard::{self, Layout};
+
+    match args.first().map(String::as_str) {
+        None => {
+            let name = match keyboard::layout() {
+                Layout::UsQwerty => "us",
+                Layout::Azerty => "fr",
+            };
+            tty.write_str(name);
+            tty.write_str("\n");
+        }
+        Some("us") => keyboard::set_layout(Layout::UsQwerty),
+        Some("fr") => keyboard::set_layout(Layout::Azerty),
+        Some(other) => return Err(alloc::format!("Unknown layout: {other}")),
+    }
+
+    Ok(())
+}
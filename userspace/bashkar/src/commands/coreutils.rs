@@ -0,0 +1,325 @@
+//! Basic file and text commands
+use super::{
+    CommandResult,
+    fs::{Vfs, join},
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use ascii_ui::{Align, Table};
+use beskar_lib::{
+    args::{ArgParser, Opt},
+    process::{ProcessInfo, ProcessKind, ProcessState},
+};
+use core::fmt::Write;
+
+/// Print the content of files
+///
+/// Usage: `cat <file>...`
+///
+/// # Errors
+///
+/// Returns an error if a file does not exist or is a directory.
+pub fn cat(args: &[String], vfs: &impl Vfs, out: &mut impl Write) -> CommandResult {
+    let args = ArgParser::new(&[])
+        .parse(args)
+        .map_err(|e| alloc::format!("cat: {e}"))?;
+    if args.positionals().is_empty() {
+        return Err(String::from("cat: missing file operand"));
+    }
+
+    for &path in args.positionals() {
+        let info = vfs
+            .metadata(path)
+            .ok_or_else(|| alloc::format!("cat: {path}: No such file or directory"))?;
+        if info.is_dir() {
+            return Err(alloc::format!("cat: {path}: Is a directory"));
+        }
+
+        let content = vfs
+            .read_file(path)
+            .ok_or_else(|| alloc::format!("cat: {path}: Cannot read file"))?;
+        let _ = out.write_str(&String::from_utf8_lossy(&content));
+    }
+
+    Ok(())
+}
+
+/// List the entries of a directory
+///
+/// Usage: `ls [-l] [dir]`, listing `cwd` when no directory is given.
+///
+/// # Errors
+///
+/// Returns an error if the directory does not exist or is a file.
+pub fn ls(args: &[String], cwd: &str, vfs: &impl Vfs, out: &mut impl Write) -> CommandResult {
+    const OPTIONS: &[Opt] = &[Opt::flag(Some('l'), "long")];
+
+    let args = ArgParser::new(OPTIONS)
+        .parse(args)
+        .map_err(|e| alloc::format!("ls: {e}"))?;
+    let path = match args.positionals() {
+        [] => cwd,
+        [path] => path,
+        [..] => return Err(String::from("ls: too many arguments")),
+    };
+
+    let info = vfs
+        .metadata(path)
+        .ok_or_else(|| alloc::format!("ls: {path}: No such file or directory"))?;
+    if !info.is_dir() {
+        return Err(alloc::format!("ls: {path}: Not a directory"));
+    }
+
+    let mut entries: Vec<String> = vfs
+        .read_dir(path)
+        .ok_or_else(|| alloc::format!("ls: {path}: Cannot read directory"))?;
+    entries.sort_unstable();
+
+    for name in &entries {
+        if args.flag("long") {
+            // Entries may vanish while listing, show them without details.
+            match vfs.metadata(&join(path, name)) {
+                Some(info) => {
+                    let kind = if info.is_dir() { 'd' } else { '-' };
+                    let _ = writeln!(out, "{kind} {:>10} {name}", info.size());
+                }
+                None => {
+                    let _ = writeln!(out, "? {:>10} {name}", "?");
+                }
+            }
+        } else {
+            let _ = writeln!(out, "{name}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the arguments, separated by spaces
+///
+/// Usage: `echo [-n] [text]...`, where `-n` omits the trailing newline.
+///
+/// Only a leading `-n` is treated as a flag: everything else is printed as is.
+pub fn echo(args: &[String], out: &mut impl Write) {
+    let (newline, words) = match args {
+        [first, rest @ ..] if first == "-n" => (false, rest),
+        _ => (true, args),
+    };
+
+    let _ = out.write_str(&words.join(" "));
+    if newline {
+        let _ = out.write_char('\n');
+    }
+}
+
+/// Print a table of processes
+///
+/// Zombie processes, whose threads have all exited, are shown with the `zombie` state
+/// and the CPU time they used before exiting.
+pub fn ps(processes: &[ProcessInfo], out: &mut impl Write) {
+    let mut table = Table::new()
+        .column("PID", Align::Right)
+        .column("KIND", Align::Left)
+        .column("STATE", Align::Left)
+        .column("CPU(ms)", Align::Right)
+        .column("MEM(KiB)", Align::Right)
+        .column("NAME", Align::Left);
+
+    for process in processes {
+        let kind = match process.kind() {
+            ProcessKind::Kernel => "kernel",
+            ProcessKind::Driver => "driver",
+            ProcessKind::User => "user",
+        };
+        let state = match process.state() {
+            ProcessState::Running => "running",
+            ProcessState::Zombie => "zombie",
+        };
+        table.push_row([
+            process.pid().to_string(),
+            String::from(kind),
+            String::from(state),
+            process.cpu_time_ms().to_string(),
+            process.resident_bytes().div_ceil(1024).to_string(),
+            String::from(process.name()),
+        ]);
+    }
+
+    let _ = out.write_str(&table.render());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use beskar_lib::io::{FileInfo, FileKind};
+
+    /// An in-memory file system where directories map to `None`.
+    struct MockVfs(BTreeMap<&'static str, Option<&'static str>>);
+
+    impl MockVfs {
+        fn new() -> Self {
+            Self(BTreeMap::from([
+                ("/", None),
+                ("/bin", None),
+                ("/hello.txt", Some("Hello, world!\n")),
+                ("/empty", Some("")),
+                ("/bin/bashkar", Some("\x7fELF")),
+            ]))
+        }
+    }
+
+    impl Vfs for MockVfs {
+        fn metadata(&self, path: &str) -> Option<FileInfo> {
+            Some(match self.0.get(path)? {
+                Some(content) => FileInfo::new(content.len() as u64, FileKind::File),
+                None => FileInfo::new(0, FileKind::Directory),
+            })
+        }
+
+        fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+            self.0.get(path)?.map(|content| content.as_bytes().to_vec())
+        }
+
+        fn read_dir(&self, path: &str) -> Option<Vec<String>> {
+            self.0.get(path)?.is_none().then(|| {
+                let prefix = join(path, "");
+                self.0
+                    .keys()
+                    .filter_map(|key| key.strip_prefix(prefix.as_str()))
+                    .filter(|name| !name.is_empty() && !name.contains('/'))
+                    .map(String::from)
+                    .collect()
+            })
+        }
+    }
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().copied().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_cat() {
+        let vfs = MockVfs::new();
+        let mut out = String::new();
+
+        cat(
+            &argv(&["/hello.txt", "/empty", "/hello.txt"]),
+            &vfs,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, "Hello, world!\nHello, world!\n");
+
+        let mut out = String::new();
+        assert_eq!(
+            cat(&argv(&["/missing"]), &vfs, &mut out),
+            Err(String::from("cat: /missing: No such file or directory"))
+        );
+        assert_eq!(
+            cat(&argv(&["/bin"]), &vfs, &mut out),
+            Err(String::from("cat: /bin: Is a directory"))
+        );
+        assert!(cat(&[], &vfs, &mut out).is_err());
+        assert!(cat(&argv(&["-x", "/hello.txt"]), &vfs, &mut out).is_err());
+        assert!(out.is_empty());
+
+        // `--` allows reading files whose name starts with a dash.
+        assert_eq!(
+            cat(&argv(&["--", "-x"]), &vfs, &mut out),
+            Err(String::from("cat: -x: No such file or directory"))
+        );
+    }
+
+    #[test]
+    fn test_ls() {
+        let vfs = MockVfs::new();
+
+        let mut out = String::new();
+        ls(&[], "/", &vfs, &mut out).unwrap();
+        assert_eq!(out, "bin\nempty\nhello.txt\n");
+
+        let mut out = String::new();
+        ls(&argv(&["-l", "/bin"]), "/", &vfs, &mut out).unwrap();
+        assert_eq!(out, "-          4 bashkar\n");
+
+        let mut out = String::new();
+        ls(&argv(&["--long"]), "/", &vfs, &mut out).unwrap();
+        assert_eq!(
+            out,
+            "d          0 bin\n-          0 empty\n-         14 hello.txt\n"
+        );
+
+        assert_eq!(
+            ls(&argv(&["/hello.txt"]), "/", &vfs, &mut out),
+            Err(String::from("ls: /hello.txt: Not a directory"))
+        );
+        assert_eq!(
+            ls(&argv(&["/missing"]), "/", &vfs, &mut out),
+            Err(String::from("ls: /missing: No such file or directory"))
+        );
+        assert!(ls(&argv(&["/", "/bin"]), "/", &vfs, &mut out).is_err());
+        assert!(ls(&argv(&["-a"]), "/", &vfs, &mut out).is_err());
+    }
+
+    #[test]
+    fn test_ps() {
+        let processes = [
+            ProcessInfo::new(
+                0,
+                "kernel",
+                ProcessKind::Kernel,
+                ProcessState::Running,
+                1500,
+                1 << 24,
+            ),
+            ProcessInfo::new(
+                1,
+                "Drivers",
+                ProcessKind::Driver,
+                ProcessState::Zombie,
+                20,
+                0,
+            ),
+            ProcessInfo::new(
+                2,
+                "bashkar",
+                ProcessKind::User,
+                ProcessState::Running,
+                0,
+                100,
+            ),
+        ];
+
+        let mut out = String::new();
+        ps(&processes, &mut out);
+        assert_eq!(
+            out,
+            "PID  KIND    STATE    CPU(ms)  MEM(KiB)  NAME\n  \
+               0  kernel  running     1500     16384  kernel\n  \
+               1  driver  zombie        20         0  Drivers\n  \
+               2  user    running        0         1  bashkar\n"
+        );
+    }
+
+    #[test]
+    fn test_echo() {
+        let mut out = String::new();
+        echo(&argv(&["hello", "world"]), &mut out);
+        assert_eq!(out, "hello world\n");
+
+        let mut out = String::new();
+        echo(&argv(&["-n", "hello"]), &mut out);
+        assert_eq!(out, "hello");
+
+        let mut out = String::new();
+        echo(&argv(&["hello", "-n", "--long"]), &mut out);
+        assert_eq!(out, "hello -n --long\n");
+
+        let mut out = String::new();
+        echo(&[], &mut out);
+        assert_eq!(out, "\n");
+    }
+}
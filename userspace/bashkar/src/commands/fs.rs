@@ -0,0 +1,56 @@
+//! File system access for shell commands
+use alloc::{string::String, vec::Vec};
+use beskar_lib::io::{FileInfo, Read as _};
+
+/// The file operations used by the shell commands.
+///
+/// Commands go through this trait rather than the syscalls directly,
+/// so that they can be exercised against an in-memory file system.
+pub trait Vfs {
+    /// Returns information about the file at `path`, or `None` if it does not exist.
+    fn metadata(&self, path: &str) -> Option<FileInfo>;
+    /// Reads the whole content of the file at `path`.
+    fn read_file(&self, path: &str) -> Option<Vec<u8>>;
+    /// Returns the names of the entries of the directory at `path`.
+    fn read_dir(&self, path: &str) -> Option<Vec<String>>;
+}
+
+/// The file system of the running system, accessed through syscalls.
+pub struct SysVfs;
+
+impl Vfs for SysVfs {
+    fn metadata(&self, path: &str) -> Option<FileInfo> {
+        beskar_lib::io::metadata(path).ok()
+    }
+
+    fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        const CHUNK_SIZE: usize = 512;
+
+        let mut file = beskar_lib::io::File::open(path).ok()?;
+        let mut content = Vec::new();
+        let mut chunk = [0; CHUNK_SIZE];
+        loop {
+            let n = file.read(&mut chunk).ok()?;
+            if n == 0 {
+                break;
+            }
+            content.extend_from_slice(&chunk[..n]);
+        }
+        Some(content)
+    }
+
+    fn read_dir(&self, path: &str) -> Option<Vec<String>> {
+        beskar_lib::io::read_dir(path).ok()
+    }
+}
+
+#[must_use]
+/// Joins a directory path and an entry name.
+pub fn join(dir: &str, name: &str) -> String {
+    let mut path = String::from(dir);
+    if !path.ends_with('/') {
+        path.push('/');
+    }
+    path.push_str(name);
+    path
+}
@@ -0,0 +1,194 @@
+//! Network diagnostic commands
+use alloc::{format, string::String};
+use beskar_lib::net::FrameRecord;
+use core::fmt::Write;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+
+/// Length of an Ethernet header, without IEEE 802.1Q tag
+const ETHERNET_HEADER_LEN: usize = 14;
+
+#[must_use]
+/// Parses a capture filter: a protocol name or a raw `EtherType` in hexadecimal.
+///
+/// Returns `None` if the filter is invalid.
+pub fn parse_filter(filter: &str) -> Option<u16> {
+    match filter {
+        "arp" => Some(ETHERTYPE_ARP),
+        "ip" | "ipv4" => Some(ETHERTYPE_IPV4),
+        "ipv6" => Some(ETHERTYPE_IPV6),
+        _ => filter
+            .strip_prefix("0x")
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+            .filter(|&ethertype| ethertype != 0),
+    }
+}
+
+#[must_use]
+/// Returns a one-line summary of a captured frame, like `tcpdump` does.
+///
+/// Frames dropped before this one are reported on a line of their own.
+pub fn summarize(record: &FrameRecord, data: &[u8]) -> String {
+    let mut line = String::new();
+    if record.dropped_before() != 0 {
+        let _ = writeln!(line, "-- {} frames dropped --", record.dropped_before());
+    }
+
+    let micros = record.timestamp_us();
+    let _ = write!(line, "{}.{:06} ", micros / 1_000_000, micros % 1_000_000);
+
+    if data.len() < ETHERNET_HEADER_LEN {
+        let _ = write!(line, "truncated frame, length {}", record.original_len());
+        return line;
+    }
+
+    let _ = write!(line, "{} > {} ", mac(&data[6..12]), mac(&data[0..6]));
+    let payload = &data[ETHERNET_HEADER_LEN..];
+    let protocol = match u16::from_be_bytes([data[12], data[13]]) {
+        ETHERTYPE_ARP => arp(payload),
+        ETHERTYPE_IPV4 => ipv4(payload),
+        ETHERTYPE_IPV6 => String::from("IPv6"),
+        ethertype => format!("ethertype {ethertype:#06x}"),
+    };
+    let _ = write!(line, "{protocol}, length {}", record.original_len());
+    line
+}
+
+fn mac(bytes: &[u8]) -> String {
+    let mut mac = String::new();
+    for (i, byte) in bytes.iter().enumerate() {
+        let sep = if i == 0 { "" } else { ":" };
+        let _ = write!(mac, "{sep}{byte:02x}");
+    }
+    mac
+}
+
+fn ip(bytes: &[u8]) -> String {
+    format!("{}.{}.{}.{}", bytes[0], bytes[1], bytes[2], bytes[3])
+}
+
+fn arp(packet: &[u8]) -> String {
+    // Only Ethernet/IPv4 ARP is described
+    if packet.len() < 28 {
+        return String::from("ARP, truncated");
+    }
+    match u16::from_be_bytes([packet[6], packet[7]]) {
+        1 => format!(
+            "ARP, who-has {} tell {}",
+            ip(&packet[24..28]),
+            ip(&packet[14..18])
+        ),
+        2 => format!("ARP, {} is-at {}", ip(&packet[14..18]), mac(&packet[8..14])),
+        op => format!("ARP, op {op}"),
+    }
+}
+
+fn ipv4(packet: &[u8]) -> String {
+    if packet.len() < 20 {
+        return String::from("IPv4, truncated");
+    }
+    let header_len = usize::from(packet[0] & 0x0F) * 4;
+    let (src, dst) = (ip(&packet[12..16]), ip(&packet[16..20]));
+    let l4 = packet.get(header_len..).unwrap_or_default();
+    let ports = || {
+        l4.get(0..4).map_or_else(String::new, |ports| {
+            format!(
+                ".{} > {dst}.{}",
+                u16::from_be_bytes([ports[0], ports[1]]),
+                u16::from_be_bytes([ports[2], ports[3]])
+            )
+        })
+    };
+
+    match packet[9] {
+        1 => match l4.first() {
+            Some(0) => format!("IPv4 {src} > {dst}: ICMP echo reply"),
+            Some(8) => format!("IPv4 {src} > {dst}: ICMP echo request"),
+            Some(kind) => format!("IPv4 {src} > {dst}: ICMP type {kind}"),
+            None => format!("IPv4 {src} > {dst}: ICMP"),
+        },
+        6 if l4.len() >= 4 => format!("IPv4 {src}{}: TCP", ports()),
+        17 if l4.len() >= 4 => format!("IPv4 {src}{}: UDP", ports()),
+        protocol => format!("IPv4 {src} > {dst}: protocol {protocol}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC_A: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+    const MAC_B: [u8; 6] = [0x52, 0x55, 0x0a, 0x00, 0x02, 0x02];
+
+    fn frame(ethertype: u16, payload: &[u8]) -> alloc::vec::Vec<u8> {
+        let mut frame = alloc::vec::Vec::new();
+        frame.extend_from_slice(&MAC_B);
+        frame.extend_from_slice(&MAC_A);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn record(frame: &[u8], dropped_before: u32) -> FrameRecord {
+        #[expect(clippy::cast_possible_truncation, reason = "Test frames are short")]
+        let len = frame.len() as u16;
+        FrameRecord::new(1_500_042, len, len, dropped_before)
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(parse_filter("arp"), Some(0x0806));
+        assert_eq!(parse_filter("ip"), Some(0x0800));
+        assert_eq!(parse_filter("ipv6"), Some(0x86DD));
+        assert_eq!(parse_filter("0x88cc"), Some(0x88CC));
+        assert_eq!(parse_filter("0x0"), None);
+        assert_eq!(parse_filter("0x10000"), None);
+        assert_eq!(parse_filter("tcp"), None);
+    }
+
+    #[test]
+    fn test_summarize_arp() {
+        let mut arp = [0; 28];
+        arp[6..8].copy_from_slice(&1_u16.to_be_bytes());
+        arp[8..14].copy_from_slice(&MAC_A);
+        arp[14..18].copy_from_slice(&[10, 0, 2, 15]);
+        arp[24..28].copy_from_slice(&[10, 0, 2, 2]);
+        let frame = frame(ETHERTYPE_ARP, &arp);
+
+        assert_eq!(
+            summarize(&record(&frame, 0), &frame),
+            "1.500042 52:54:00:12:34:56 > 52:55:0a:00:02:02 ARP, who-has 10.0.2.2 tell 10.0.2.15, length 42"
+        );
+    }
+
+    #[test]
+    fn test_summarize_udp() {
+        let mut packet = [0; 28];
+        packet[0] = 0x45;
+        packet[9] = 17;
+        packet[12..16].copy_from_slice(&[10, 0, 2, 15]);
+        packet[16..20].copy_from_slice(&[10, 0, 2, 3]);
+        packet[20..22].copy_from_slice(&49152_u16.to_be_bytes());
+        packet[22..24].copy_from_slice(&53_u16.to_be_bytes());
+        let frame = frame(ETHERTYPE_IPV4, &packet);
+
+        assert_eq!(
+            summarize(&record(&frame, 3), &frame),
+            "-- 3 frames dropped --\n1.500042 52:54:00:12:34:56 > 52:55:0a:00:02:02 IPv4 10.0.2.15.49152 > 10.0.2.3.53: UDP, length 42"
+        );
+    }
+
+    #[test]
+    fn test_summarize_other() {
+        let frame = frame(0x88CC, &[0; 4]);
+        assert!(summarize(&record(&frame, 0), &frame).ends_with("ethertype 0x88cc, length 18"));
+
+        let runt = [0; 6];
+        assert_eq!(
+            summarize(&record(&runt, 0), &runt),
+            "1.500042 truncated frame, length 6"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Name-based lookup of shell built-ins
+use super::CommandResult;
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write as _;
+
+/// A function implementing a built-in command.
+///
+/// It receives the command arguments (without the command name) and
+/// a mutable reference to the context the command runs in.
+pub type Handler<C> = fn(&[String], &mut C) -> CommandResult;
+
+/// A built-in command entry.
+pub struct Command<C> {
+    name: &'static str,
+    description: &'static str,
+    usage: &'static str,
+    handler: Handler<C>,
+}
+
+impl<C> Command<C> {
+    #[must_use]
+    #[inline]
+    /// The name the command is invoked with.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    #[must_use]
+    #[inline]
+    /// A one-line description of the command.
+    pub const fn description(&self) -> &'static str {
+        self.description
+    }
+
+    #[must_use]
+    #[inline]
+    /// The command usage, e.g. `rand [n]`.
+    pub const fn usage(&self) -> &'static str {
+        self.usage
+    }
+
+    #[inline]
+    /// Runs the command.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error message produced by the command.
+    pub fn run(&self, args: &[String], ctx: &mut C) -> CommandResult {
+        (self.handler)(args, ctx)
+    }
+}
+
+/// A set of built-in commands, kept sorted by name.
+pub struct CommandRegistry<C> {
+    commands: Vec<Command<C>>,
+}
+
+impl<C> Default for CommandRegistry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> CommandRegistry<C> {
+    #[must_use]
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+        }
+    }
+
+    /// Registers a command.
+    ///
+    /// A command registered under an existing name replaces the previous one.
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        usage: &'static str,
+        handler: Handler<C>,
+    ) {
+        let command = Command {
+            name,
+            description,
+            usage,
+            handler,
+        };
+        match self.commands.binary_search_by(|c| c.name.cmp(name)) {
+            Ok(idx) => self.commands[idx] = command,
+            Err(idx) => self.commands.insert(idx, command),
+        }
+    }
+
+    #[must_use]
+    /// Looks up a command by name.
+    pub fn get(&self, name: &str) -> Option<&Command<C>> {
+        self.commands
+            .binary_search_by(|c| c.name.cmp(name))
+            .ok()
+            .map(|idx| &self.commands[idx])
+    }
+
+    #[inline]
+    /// Iterates over the registered commands, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = &Command<C>> {
+        self.commands.iter()
+    }
+
+    #[must_use]
+    /// Builds the listing printed by `help`.
+    pub fn help_listing(&self) -> String {
+        let width = self.iter().map(|c| c.usage.len()).max().unwrap_or(0);
+
+        let mut text = String::from("BeskarOS Shell - Available commands:\n");
+        for command in self.iter() {
+            let _ = writeln!(text, "  {:width$} - {}", command.usage, command.description);
+        }
+        text
+    }
+
+    #[must_use]
+    /// Builds the detailed help of a single command, printed by `help <cmd>`.
+    pub fn help_for(&self, name: &str) -> Option<String> {
+        self.get(name)
+            .map(|command| alloc::format!("{}\n\nUsage: {}\n", command.description, command.usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn push_a(_args: &[String], ctx: &mut Vec<&'static str>) -> CommandResult {
+        ctx.push("a");
+        Ok(())
+    }
+
+    fn fail(_args: &[String], _ctx: &mut Vec<&'static str>) -> CommandResult {
+        Err(String::from("failed"))
+    }
+
+    fn registry() -> CommandRegistry<Vec<&'static str>> {
+        let mut registry = CommandRegistry::new();
+        registry.register("zeta", "Always fails", "zeta", fail);
+        registry.register("alpha", "Pushes a letter", "alpha [x]", push_a);
+        registry
+    }
+
+    #[test]
+    fn test_lookup() {
+        let registry = registry();
+
+        let mut ctx = Vec::new();
+        let alpha = registry.get("alpha").unwrap();
+        assert_eq!(alpha.name(), "alpha");
+        assert!(alpha.run(&[], &mut ctx).is_ok());
+        assert_eq!(ctx, ["a"]);
+
+        let zeta = registry.get("zeta").unwrap();
+        assert_eq!(zeta.run(&[], &mut ctx), Err(String::from("failed")));
+
+        assert!(registry.get("beta").is_none());
+        assert!(registry.get("").is_none());
+    }
+
+    #[test]
+    fn test_register_replaces() {
+        let mut registry = registry();
+        registry.register("zeta", "Now succeeds", "zeta", push_a);
+
+        assert_eq!(registry.iter().count(), 2);
+        assert_eq!(registry.get("zeta").unwrap().description(), "Now succeeds");
+    }
+
+    #[test]
+    fn test_help_listing() {
+        let registry = registry();
+
+        assert_eq!(
+            registry.help_listing(),
+            "BeskarOS Shell - Available commands:\n  \
+                alpha [x] - Pushes a letter\n  \
+                zeta      - Always fails\n"
+        );
+        assert_eq!(
+            registry.help_for("alpha").as_deref(),
+            Some("Pushes a letter\n\nUsage: alpha [x]\n")
+        );
+        assert!(registry.help_for("beta").is_none());
+    }
+}
@@ -25,6 +25,7 @@ fn main() {
                 });
 
                 bashkar::video::tty::with_tty(|tty| {
+                    tty.set_last_status(exec_res.is_ok());
                     if let Err(err_msg) = exec_res {
                         tty.write_str(&format!("Error: {}\n", err_msg));
                     }
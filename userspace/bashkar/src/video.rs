@@ -1,3 +1,4 @@
+pub mod prompt;
 pub mod screen;
 pub mod tty;
 pub mod ui;
@@ -0,0 +1,75 @@
+//! Shell prompt made of colored segments.
+use alloc::{string::String, vec::Vec};
+use ascii_ui::{AsciiCanvas, Slot};
+use beskar_core::time::Instant;
+use core::fmt::Write as _;
+
+const HOST: &str = "BESKAR-OS";
+const SEPARATOR: &str = " > ";
+
+/// A rendered prompt, ready to be drawn.
+#[derive(Debug, Clone, Default)]
+pub struct Prompt {
+    segments: Vec<(String, Slot)>,
+    width: u16,
+}
+
+impl Prompt {
+    #[must_use]
+    /// Builds the prompt, showing the time, the current directory and the last exit status.
+    ///
+    /// The current directory is truncated from the left so that the prompt fits
+    /// in `max_width` columns while leaving room for input.
+    pub fn build(cwd: &str, last_succeeded: bool, now: Instant, max_width: u16) -> Self {
+        let mut time = String::new();
+        let secs = now.secs();
+        write!(
+            time,
+            "{:02}:{:02}:{:02} ",
+            (secs / 3600) % 24,
+            (secs / 60) % 60,
+            secs % 60
+        )
+        .unwrap();
+
+        let status = if last_succeeded {
+            (SEPARATOR, Slot::Success)
+        } else {
+            (SEPARATOR, Slot::Error)
+        };
+
+        let fixed_width = time.len() + HOST.len() + 1 + status.0.len();
+        // Keep at least one column for the input cursor.
+        let cwd_width = usize::from(max_width).saturating_sub(fixed_width + 1);
+
+        let mut prompt = Self::default();
+        prompt.push(time, Slot::Dim);
+        prompt.push(String::from(HOST), Slot::Accent);
+        prompt.push(String::from(":"), Slot::Normal);
+        prompt.push(ascii_ui::truncate_left(cwd, cwd_width), Slot::Normal);
+        prompt.push(String::from(status.0), status.1);
+        prompt
+    }
+
+    fn push(&mut self, text: String, slot: Slot) {
+        let len = u16::try_from(text.chars().count()).unwrap_or(u16::MAX);
+        self.width = self.width.saturating_add(len);
+        self.segments.push((text, slot));
+    }
+
+    #[must_use]
+    #[inline]
+    /// Width of the prompt in columns.
+    pub const fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Draws the prompt on a single row of the canvas, starting at `col`.
+    pub fn render(&self, canvas: &mut AsciiCanvas<'_>, col: u16, row: u16) {
+        let mut col = col;
+        for (text, slot) in &self.segments {
+            canvas.write_semantic(col, row, text, *slot);
+            col = col.saturating_add(u16::try_from(text.chars().count()).unwrap_or(u16::MAX));
+        }
+    }
+}
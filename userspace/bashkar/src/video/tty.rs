@@ -1,5 +1,6 @@
-use super::{screen, ui};
+use super::{prompt::Prompt, screen, ui};
 use alloc::string::String;
+use ascii_ui::{AsciiCanvas, Theme};
 use beskar_core::video::{Info, Pixel, writer::FramebufferWriter};
 use beskar_lib::error::IoResult;
 use beskar_lib::io::keyboard::{self, KeyCode, KeyState};
@@ -9,9 +10,6 @@ use hyperdrive::locks::mcs::MUMcsLock;
 
 static TTY: MUMcsLock<Tty> = MUMcsLock::uninit();
 
-/// Shell prompt to display
-const PROMPT: &str = "BESKAR-OS > ";
-
 pub fn init() {
     let layout = ui::layout();
     TTY.init(Tty::new(*layout));
@@ -37,15 +35,21 @@ pub struct Tty {
     cursor_row: u16,
     /// Current raw input buffer
     input_buffer: String,
-    /// Current cursor position within the input buffer
+    /// Current cursor position within the input buffer, in bytes
     cursor_pos: usize,
     // Keyboard modifiers
     modifiers: keyboard::KeyModifiers,
+    /// Translates key presses using the active layout
+    translator: keyboard::Translator,
+    /// Current working directory, shown in the prompt
+    cwd: String,
+    /// Whether the last command succeeded, shown in the prompt
+    last_succeeded: bool,
+    /// Prompt of the current input line
+    prompt: Prompt,
 }
 
 impl Tty {
-    const PROMPT_LEN: usize = PROMPT.len();
-
     #[must_use]
     #[inline]
     const fn cell_to_pixel(&self, col: u16, row: u16) -> (u16, u16) {
@@ -116,28 +120,66 @@ impl Tty {
             input_buffer: String::new(),
             cursor_pos: 0,
             modifiers: keyboard::KeyModifiers::new(),
+            translator: keyboard::Translator::new(),
+            cwd: String::from("/"),
+            last_succeeded: true,
+            prompt: Prompt::default(),
         }
     }
 
+    #[must_use]
+    #[inline]
+    /// The current working directory
+    pub fn cwd(&self) -> &str {
+        &self.cwd
+    }
+
+    #[inline]
+    /// Record the outcome of the last command, for the next prompt
+    pub const fn set_last_status(&mut self, succeeded: bool) {
+        self.last_succeeded = succeeded;
+    }
+
+    /// Draw the prompt at the start of the current row, leaving the cursor after it.
+    fn draw_prompt(&mut self, screen: &mut FrameBuffer) {
+        let info = *screen.info();
+        let mut view = screen.view();
+        let mut canvas = AsciiCanvas::new(
+            info,
+            view.pixels_mut(),
+            Theme::new(ui::TEXT_COLOR, ui::BACKGROUND_COLOR),
+        );
+        canvas.set_palette(ui::palette());
+
+        let col = self.left_px / self.cell_w;
+        let row = self.top_px / self.line_h + self.cursor_row;
+        self.prompt.render(&mut canvas, col, row);
+
+        self.cursor_col = self.prompt.width().min(self.inner_cols - 1);
+    }
+
     /// Display the shell prompt
     ///
     /// # Panics
     ///
     /// Panics if flushing the framebuffer to the kernel framebuffer device fails.
     pub fn display_prompt(&mut self) {
-        super::screen::with_screen(|screen| {
-            let mut view = screen.view_from_row(self.top_px);
-            let pixels = view.pixels_mut();
+        self.prompt = Prompt::build(
+            &self.cwd,
+            self.last_succeeded,
+            beskar_lib::time::now(),
+            self.inner_cols,
+        );
 
+        super::screen::with_screen(|screen| {
             self.cursor_col = 0;
             self.line_start_row = self.cursor_row;
-            self.rendered_len = 0;
 
-            self.write_span(pixels.as_mut(), PROMPT);
-            self.rendered_len = Self::PROMPT_LEN;
+            // The prompt always fits on a single row
+            self.draw_prompt(screen);
+            self.rendered_len = usize::from(self.prompt.width());
 
-            let rows = self.rows_spanned(self.rendered_len);
-            self.flush_from_line(screen, self.line_start_row, rows)
+            self.flush_from_line(screen, self.line_start_row, 1)
                 .unwrap();
         });
     }
@@ -146,28 +188,28 @@ impl Tty {
     /// Redraw the current input line
     pub fn redraw_line(&mut self) {
         super::screen::with_screen(|screen| {
-            let mut view = screen.view_from_row(self.top_px);
-            let pixels = view.pixels_mut();
-
-            let prompt_len = Self::PROMPT_LEN;
+            let prompt_len = usize::from(self.prompt.width());
             let input_len = self.input_buffer.chars().count();
             let new_len = prompt_len + input_len;
             let max_len = self.rendered_len.max(new_len);
             let rows_to_clear = self.rows_spanned(max_len);
 
             // Clear the previously rendered line region to avoid leftover glyphs
-            self.clear_rows(pixels.as_mut(), self.line_start_row, rows_to_clear);
+            {
+                let mut view = screen.view_from_row(self.top_px);
+                self.clear_rows(view.pixels_mut(), self.line_start_row, rows_to_clear);
+            }
 
             // Redraw prompt + input from the tracked start row
             self.cursor_row = self.line_start_row;
-            self.cursor_col = 0;
-
-            // Write prompt
-            self.write_span(pixels.as_mut(), PROMPT);
+            self.draw_prompt(screen);
 
             // Write input buffer
             let input_copy = self.input_buffer.clone();
-            self.write_span(pixels.as_mut(), &input_copy);
+            {
+                let mut view = screen.view_from_row(self.top_px);
+                self.write_span(view.pixels_mut(), &input_copy);
+            }
 
             self.rendered_len = new_len;
             self.flush_from_line(screen, self.line_start_row, rows_to_clear)
@@ -242,13 +284,9 @@ impl Tty {
 
         match key {
             KeyCode::Backspace => {
-                if self.cursor_pos > 0 {
-                    self.cursor_pos -= 1;
-                    if self.cursor_pos < self.input_buffer.len() {
-                        self.input_buffer.remove(self.cursor_pos);
-                    } else {
-                        self.input_buffer.pop();
-                    }
+                if let Some(c) = self.input_buffer[..self.cursor_pos].chars().next_back() {
+                    self.cursor_pos -= c.len_utf8();
+                    self.input_buffer.remove(self.cursor_pos);
                     self.redraw_line();
                 }
                 false
@@ -270,15 +308,21 @@ impl Tty {
                 self.modifiers.set_ctrled(pressed == KeyState::Pressed);
                 false
             }
-            KeyCode::AltLeft | KeyCode::AltRight => {
+            KeyCode::AltLeft => {
                 self.modifiers.set_alted(pressed == KeyState::Pressed);
                 false
             }
+            KeyCode::AltRight => {
+                self.modifiers.set_alt_gr(pressed == KeyState::Pressed);
+                false
+            }
             k => {
-                let c = k.as_char(self.modifiers);
-                if c != '\0' {
+                if let Some(c) = self
+                    .translator
+                    .translate(&keyboard::layout(), k, self.modifiers)
+                {
                     self.input_buffer.insert(self.cursor_pos, c);
-                    self.cursor_pos += 1;
+                    self.cursor_pos += c.len_utf8();
                     self.redraw_line();
                 }
                 false
@@ -1,6 +1,6 @@
 use super::screen;
 use alloc::string::String;
-use ascii_ui::{AsciiCanvas, BoxStyle, CharRect, Theme};
+use ascii_ui::{AsciiCanvas, BoxStyle, CharRect, Palette, Theme};
 use beskar_core::video::PixelComponents;
 use hyperdrive::once::Once;
 
@@ -10,6 +10,16 @@ pub const SHADOW_GREEN: PixelComponents = PixelComponents::new(0x00, 0xAA, 0x00)
 pub const TEXT_COLOR: PixelComponents = PixelComponents::WHITE;
 pub const BACKGROUND_COLOR: PixelComponents = PixelComponents::BLACK;
 
+#[must_use]
+/// Colors used for semantic text, such as the prompt.
+pub fn palette() -> Palette {
+    Palette {
+        accent: PRIMARY_GREEN,
+        dim: SHADOW_GREEN,
+        ..Palette::from_theme(Theme::new(TEXT_COLOR, BACKGROUND_COLOR))
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct UiLayout {
     /// Inner console top in character rows (excludes border)
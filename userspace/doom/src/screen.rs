@@ -1,5 +1,4 @@
-use beskar_core::video::Info;
-use beskar_lib::io::screen::FrameBuffer;
+use beskar_lib::io::{FbInfo, framebuffer_info, screen::FrameBuffer};
 use hyperdrive::{locks::mcs::MUMcsLock, once::Once};
 
 const SCREENWIDTH: usize = 320;
@@ -7,7 +6,7 @@ const SCREENHEIGHT: usize = 200;
 const CHANNELS: usize = 4; // RGBA
 
 static SCREEN: MUMcsLock<FrameBuffer> = MUMcsLock::uninit();
-static SCREEN_INFO: Once<Info> = Once::uninit();
+static SCREEN_INFO: Once<FbInfo> = Once::uninit();
 
 #[link(name = "puredoom", kind = "static")]
 unsafe extern "C" {
@@ -20,8 +19,8 @@ unsafe extern "C" {
 ///
 /// Panics if the framebuffer cannot be opened.
 pub fn init() {
+    SCREEN_INFO.call_once(|| framebuffer_info().unwrap());
     SCREEN.init(FrameBuffer::open().unwrap());
-    SCREEN_INFO.call_once(|| SCREEN.with_locked(|screen| *screen.info()));
 }
 
 /// Returns the screen info.
@@ -29,7 +28,7 @@ pub fn init() {
 /// # Panics
 ///
 /// Panics if the screen info has not been initialized yet.
-fn screen_info() -> &'static Info {
+fn screen_info() -> &'static FbInfo {
     SCREEN_INFO.get().unwrap()
 }
 
@@ -47,15 +46,26 @@ pub fn draw() {
         return;
     };
 
-    let stride = usize::from(screen_info().stride());
-    let bpp = usize::from(screen_info().bytes_per_pixel());
-    let stride_bytes = stride * bpp;
+    let info = screen_info();
+    let stride_bytes = usize::from(info.stride()) * usize::from(info.bytes_per_pixel());
+
+    // Center the image, cropping it if the screen is too small.
+    let width = SCREENWIDTH.min(usize::from(info.width()));
+    let height = SCREENHEIGHT.min(usize::from(info.height()));
+    let left = (usize::from(info.width()) - width) / 2;
+    let top = (usize::from(info.height()) - height) / 2;
+
     with_screen(|screen| {
-        let mut buffer_mut = screen.buffer_mut();
-        for row in fb.chunks_exact(SCREENWIDTH * CHANNELS) {
-            buffer_mut[..SCREENWIDTH * CHANNELS].copy_from_slice(row);
-            buffer_mut = &mut buffer_mut[stride_bytes..];
+        let buffer = screen.buffer_mut();
+        for (y, row) in fb
+            .chunks_exact(SCREENWIDTH * CHANNELS)
+            .take(height)
+            .enumerate()
+        {
+            let start = (top + y) * stride_bytes + left * CHANNELS;
+            buffer[start..start + width * CHANNELS].copy_from_slice(&row[..width * CHANNELS]);
         }
-        let _ = screen.flush_rows(0..u16::try_from(SCREENHEIGHT).unwrap());
+        let top = u16::try_from(top).unwrap();
+        let _ = screen.flush_rows(top..top + u16::try_from(height).unwrap());
     });
 }
@@ -0,0 +1,6 @@
+#![no_std]
+#![forbid(unsafe_op_in_unsafe_fn)]
+#![warn(clippy::pedantic, clippy::nursery)]
+extern crate alloc;
+
+pub mod monitor;
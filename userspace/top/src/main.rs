@@ -0,0 +1,112 @@
+#![no_std]
+#![no_main]
+use ascii_ui::{AsciiCanvas, Theme};
+use beskar_core::{
+    time::Duration,
+    video::{Pixel, PixelComponents},
+};
+use beskar_lib::{
+    io::{
+        display::{Display, DisplayEvent},
+        keyboard::{self, KeyCode, KeyEvent, KeyState},
+        screen::FrameBuffer,
+    },
+    time::now,
+};
+use top::monitor::{Monitor, SortKey};
+
+beskar_lib::entry_point!(main);
+
+/// Time between two samples of the process list.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// Time between two checks for input.
+const INPUT_INTERVAL: Duration = Duration::from_millis(50);
+
+fn main() {
+    let mut display = Display::acquire().unwrap();
+    let mut foreground = true;
+    let mut monitor = Monitor::new(SortKey::default());
+    let mut events = [KeyEvent::new(KeyCode::Escape, KeyState::Released); 16];
+
+    let mut next_refresh = now();
+    loop {
+        let mut redraw = false;
+
+        match display.poll_event() {
+            Some(DisplayEvent::Gained) => {
+                foreground = true;
+                redraw = true;
+            }
+            Some(DisplayEvent::Lost) => foreground = false,
+            None => {}
+        }
+
+        let count = keyboard::poll_batch(&mut events);
+        for event in &events[..count] {
+            if event.pressed() != KeyState::Pressed {
+                continue;
+            }
+            match event.key() {
+                KeyCode::Q | KeyCode::Escape => {
+                    clear(display.framebuffer());
+                    return;
+                }
+                key => {
+                    if let Some(sort) = SortKey::from_key(key) {
+                        monitor.set_sort_key(sort);
+                        redraw = true;
+                    }
+                }
+            }
+        }
+
+        if now() >= next_refresh {
+            let processes = beskar_lib::process::list().unwrap_or_default();
+            monitor.refresh(&processes, now());
+            next_refresh = now() + REFRESH_INTERVAL;
+            redraw = true;
+        }
+
+        // In the background, flushes are discarded anyway.
+        if redraw && foreground {
+            draw(display.framebuffer(), &monitor);
+        }
+
+        let _ = beskar_lib::sleep(INPUT_INTERVAL);
+    }
+}
+
+/// Draws the whole screen in the internal framebuffer, then flushes it at once.
+///
+/// The screen size is read on every frame, so that the table follows resolution changes.
+fn draw(screen: &mut FrameBuffer, monitor: &Monitor) {
+    let info = *screen.info();
+    {
+        let mut view = screen.view();
+        let mut canvas = AsciiCanvas::new(info, view.pixels_mut(), theme());
+        canvas.clear_with_theme();
+
+        let lines = monitor.render(canvas.cols(), canvas.rows());
+        for (row, line) in (0..).zip(&lines) {
+            if row == 0 {
+                canvas.write_line_highlighted(0, row, line);
+            } else {
+                canvas.write_line(0, row, line);
+            }
+        }
+    }
+    let _ = screen.flush_all();
+}
+
+fn clear(screen: &mut FrameBuffer) {
+    let info = *screen.info();
+    screen
+        .view()
+        .pixels_mut()
+        .fill(Pixel::from_format(info.pixel_format(), theme().background));
+    let _ = screen.flush_all();
+}
+
+const fn theme() -> Theme {
+    Theme::new(PixelComponents::WHITE, PixelComponents::BLACK)
+}
@@ -0,0 +1,303 @@
+//! Process sampling and rendering, independent of the screen
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use ascii_ui::{Align, Table};
+use beskar_lib::{
+    io::keyboard::KeyCode,
+    process::{ProcessInfo, ProcessState},
+    time::Instant,
+};
+
+/// Column the process table is sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Highest CPU usage first
+    #[default]
+    Cpu,
+    /// Highest resident memory first
+    Memory,
+    /// Lowest PID first
+    Pid,
+    /// Alphabetical order
+    Name,
+}
+
+impl SortKey {
+    #[must_use]
+    /// Returns the sort key selected by a key press, if any.
+    pub const fn from_key(key: KeyCode) -> Option<Self> {
+        match key {
+            KeyCode::C => Some(Self::Cpu),
+            KeyCode::M => Some(Self::Memory),
+            KeyCode::P => Some(Self::Pid),
+            KeyCode::N => Some(Self::Name),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Cpu => "CPU",
+            Self::Memory => "memory",
+            Self::Pid => "PID",
+            Self::Name => "name",
+        }
+    }
+}
+
+/// A process, along with its CPU usage since the previous refresh.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    info: ProcessInfo,
+    /// CPU usage in tenths of a percent.
+    ///
+    /// A process running on several CPUs at once can exceed 100%.
+    cpu_permille: u64,
+}
+
+impl Sample {
+    #[must_use]
+    #[inline]
+    pub const fn info(&self) -> &ProcessInfo {
+        &self.info
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn cpu_permille(&self) -> u64 {
+        self.cpu_permille
+    }
+}
+
+/// Keeps the latest process list, sorted, and the CPU times needed to compute usage.
+#[derive(Debug, Default)]
+pub struct Monitor {
+    sort: SortKey,
+    samples: Vec<Sample>,
+    /// CPU time of every process at the last refresh, in milliseconds
+    cpu_times: BTreeMap<u64, u64>,
+    last_refresh: Option<Instant>,
+}
+
+impl Monitor {
+    #[must_use]
+    #[inline]
+    pub const fn new(sort: SortKey) -> Self {
+        Self {
+            sort,
+            samples: Vec::new(),
+            cpu_times: BTreeMap::new(),
+            last_refresh: None,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn sort_key(&self) -> SortKey {
+        self.sort
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Replaces the process list with a new one, taken at `now`.
+    ///
+    /// CPU usage is the CPU time used since the previous refresh, over the elapsed time.
+    /// It is zero on the first refresh.
+    pub fn refresh(&mut self, processes: &[ProcessInfo], now: Instant) {
+        let elapsed_ms = self
+            .last_refresh
+            .map(|last| (now - last).total_millis())
+            .filter(|&elapsed| elapsed > 0);
+
+        self.samples = processes
+            .iter()
+            .map(|&info| {
+                let cpu_permille = elapsed_ms.map_or(0, |elapsed| {
+                    // A process that appeared since the last refresh used all its time since.
+                    let previous = self.cpu_times.get(&info.pid()).copied().unwrap_or(0);
+                    info.cpu_time_ms()
+                        .saturating_sub(previous)
+                        .saturating_mul(1000)
+                        / elapsed
+                });
+                Sample { info, cpu_permille }
+            })
+            .collect();
+
+        self.cpu_times = processes
+            .iter()
+            .map(|info| (info.pid(), info.cpu_time_ms()))
+            .collect();
+        self.last_refresh = Some(now);
+        self.sort_samples();
+    }
+
+    /// Changes the sort order of the process list.
+    pub fn set_sort_key(&mut self, sort: SortKey) {
+        self.sort = sort;
+        self.sort_samples();
+    }
+
+    fn sort_samples(&mut self) {
+        let pid = |sample: &Sample| sample.info.pid();
+        match self.sort {
+            SortKey::Cpu => self
+                .samples
+                .sort_unstable_by_key(|s| (core::cmp::Reverse(s.cpu_permille), pid(s))),
+            SortKey::Memory => self
+                .samples
+                .sort_unstable_by_key(|s| (core::cmp::Reverse(s.info.resident_bytes()), pid(s))),
+            SortKey::Pid => self.samples.sort_unstable_by_key(pid),
+            SortKey::Name => self.samples.sort_unstable_by(|a, b| {
+                a.info.name().cmp(b.info.name()).then(pid(a).cmp(&pid(b)))
+            }),
+        }
+    }
+
+    #[must_use]
+    /// Renders the screen content as at most `rows` lines of at most `cols` characters.
+    ///
+    /// The first line is a summary, followed by an empty line and the process table.
+    /// Processes that do not fit are left out.
+    pub fn render(&self, cols: u16, rows: u16) -> Vec<String> {
+        let mut table = Table::new()
+            .column("PID", Align::Right)
+            .column("CPU%", Align::Right)
+            .column("CPU(ms)", Align::Right)
+            .column("MEM(KiB)", Align::Right)
+            .column("STATE", Align::Left)
+            .column("NAME", Align::Left);
+
+        let table_rows = usize::from(rows).saturating_sub(3);
+        for sample in self.samples.iter().take(table_rows) {
+            let info = sample.info;
+            let state = match info.state() {
+                ProcessState::Running => "running",
+                ProcessState::Zombie => "zombie",
+            };
+            table.push_row([
+                info.pid().to_string(),
+                format!("{}.{}", sample.cpu_permille / 10, sample.cpu_permille % 10),
+                info.cpu_time_ms().to_string(),
+                info.resident_bytes().div_ceil(1024).to_string(),
+                String::from(state),
+                String::from(info.name()),
+            ]);
+        }
+
+        let summary = format!(
+            "{} processes, sorted by {} - q: quit, c/m/p/n: sort",
+            self.samples.len(),
+            self.sort.label()
+        );
+
+        let rendered = table.render();
+        core::iter::once(summary.as_str())
+            .chain(core::iter::once(""))
+            .chain(rendered.lines())
+            .take(usize::from(rows))
+            .map(|line| line.chars().take(usize::from(cols)).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use beskar_lib::process::ProcessKind;
+
+    fn process(pid: u64, name: &str, cpu_time_ms: u64, resident_bytes: u64) -> ProcessInfo {
+        ProcessInfo::new(
+            pid,
+            name,
+            ProcessKind::User,
+            ProcessState::Running,
+            cpu_time_ms,
+            resident_bytes,
+        )
+    }
+
+    fn pids(monitor: &Monitor) -> Vec<u64> {
+        monitor.samples().iter().map(|s| s.info().pid()).collect()
+    }
+
+    #[test]
+    fn test_cpu_usage() {
+        let mut monitor = Monitor::new(SortKey::Cpu);
+
+        monitor.refresh(
+            &[process(0, "kernel", 100, 0), process(1, "a", 50, 0)],
+            Instant::from_millis(1000),
+        );
+        assert!(monitor.samples().iter().all(|s| s.cpu_permille() == 0));
+
+        monitor.refresh(
+            &[
+                process(0, "kernel", 150, 0),
+                process(1, "a", 550, 0),
+                process(2, "b", 20, 0),
+            ],
+            Instant::from_millis(2000),
+        );
+        assert_eq!(pids(&monitor), [1, 0, 2]);
+        let usage: Vec<u64> = monitor.samples().iter().map(Sample::cpu_permille).collect();
+        assert_eq!(usage, [500, 50, 20]);
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let mut monitor = Monitor::new(SortKey::Pid);
+        monitor.refresh(
+            &[
+                process(0, "kernel", 0, 4096),
+                process(3, "bashkar", 0, 8192),
+                process(1, "Drivers", 0, 0),
+            ],
+            Instant::from_millis(0),
+        );
+        assert_eq!(pids(&monitor), [0, 1, 3]);
+
+        monitor.set_sort_key(SortKey::Memory);
+        assert_eq!(pids(&monitor), [3, 0, 1]);
+
+        monitor.set_sort_key(SortKey::Name);
+        assert_eq!(pids(&monitor), [1, 3, 0]);
+
+        assert_eq!(SortKey::from_key(KeyCode::M), Some(SortKey::Memory));
+        assert_eq!(SortKey::from_key(KeyCode::Q), None);
+    }
+
+    #[test]
+    fn test_render_fits_screen() {
+        let mut monitor = Monitor::new(SortKey::Pid);
+        let processes: Vec<ProcessInfo> = (0..10).map(|pid| process(pid, "p", 0, 2048)).collect();
+        monitor.refresh(&processes, Instant::from_millis(0));
+
+        let lines = monitor.render(80, 6);
+        assert_eq!(
+            lines,
+            [
+                "10 processes, sorted by PID - q: quit, c/m/p/n: sort",
+                "",
+                "PID  CPU%  CPU(ms)  MEM(KiB)  STATE    NAME",
+                "  0   0.0        0         2  running  p",
+                "  1   0.0        0         2  running  p",
+                "  2   0.0        0         2  running  p",
+            ]
+        );
+
+        let lines = monitor.render(12, 40);
+        assert_eq!(lines.len(), 13);
+        assert!(lines.iter().all(|line| line.chars().count() <= 12));
+        assert!(monitor.render(80, 0).is_empty());
+    }
+}